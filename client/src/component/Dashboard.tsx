@@ -2,7 +2,7 @@ import React, { useState, useEffect, useCallback, useMemo } from 'react';
 import { Connection, PublicKey, SystemProgram, LAMPORTS_PER_SOL } from '@solana/web3.js';
 import { useWallet, useConnection } from '@solana/wallet-adapter-react';
 import { WalletMultiButton } from '@solana/wallet-adapter-react-ui';
-import { Program, AnchorProvider, web3, BN, setProvider } from '@coral-xyz/anchor';
+import { Program, AnchorProvider, web3, BN, setProvider, utils } from '@coral-xyz/anchor';
 
 // Your actual IDL and Program ID
 const PROGRAM_ID = new PublicKey("6fqRppPtwd8E51BLjgtSAi4vfM4DHN3MbtetETU6De1p");
@@ -209,6 +209,14 @@ export default function CompanyPoolClient() {
     const [spinResult, setSpinResult] = useState(null);
     const [canClaim, setCanClaim] = useState(false);
 
+    // Tickets only keep the won item's id; its name, image and description live on the pool
+    const findPoolItem = (pool, wonItem) =>
+        pool?.items.find((item) => item.itemId === wonItem?.itemId);
+    const wonPoolItem = useMemo(
+        () => findPoolItem(companyPool, userTicket?.wonItem),
+        [companyPool, userTicket]
+    );
+
     // Initialize program
     useEffect(() => {
         if (wallet.publicKey && connection) {
@@ -234,12 +242,12 @@ export default function CompanyPoolClient() {
     const derivePDAs = useCallback(async (companyName) => {
         try {
             const [poolPda] = await PublicKey.findProgramAddressSync(
-                [Buffer.from("company_pool"), Buffer.from(companyName)],
+                [Buffer.from("company_pool"), Buffer.from(utils.sha256.hash(companyName), "hex")],
                 PROGRAM_ID
             );
 
             const [vaultPda] = await PublicKey.findProgramAddressSync(
-                [Buffer.from("pool_vault"), Buffer.from(companyName)],
+                [Buffer.from("pool_vault"), poolPda.toBuffer()],
                 PROGRAM_ID
             );
 
@@ -408,12 +416,13 @@ export default function CompanyPoolClient() {
             setUserTicket(updatedTicket);
 
             if (updatedTicket.wonItem) {
-                setSpinResult(updatedTicket.wonItem.name);
+                const wonName = findPoolItem(companyPool, updatedTicket.wonItem)?.name;
+                setSpinResult(wonName);
                 setCanClaim(true);
 
                 // Simulate wheel spinning animation
                 setTimeout(() => {
-                    setStatus(`Congratulations! You won ${updatedTicket.wonItem.name}! 🎉`);
+                    setStatus(`Congratulations! You won ${wonName}! 🎉`);
                     setStep(4);
                     setLoading(false);
                 }, 4000);
@@ -960,16 +969,16 @@ export default function CompanyPoolClient() {
                                     <div className="bg-gradient-to-br from-purple-500/20 to-pink-500/20 rounded-xl p-8 border border-purple-300/30">
                                         <div className="mb-6">
                                             <img
-                                                src={userTicket.wonItem.image}
-                                                alt={userTicket.wonItem.name}
+                                                src={wonPoolItem?.image}
+                                                alt={wonPoolItem?.name}
                                                 className="w-32 h-32 mx-auto rounded-xl object-cover border-4 border-white/20 shadow-lg"
                                                 onError={(e) => {
                                                     e.target.src = 'https://via.placeholder.com/128/6366F1/FFFFFF?text=Prize';
                                                 }}
                                             />
                                         </div>
-                                        <h4 className="text-3xl font-bold text-white mb-3">{userTicket.wonItem.name}</h4>
-                                        <p className="text-gray-300 mb-6 text-lg">{userTicket.wonItem.description}</p>
+                                        <h4 className="text-3xl font-bold text-white mb-3">{wonPoolItem?.name}</h4>
+                                        <p className="text-gray-300 mb-6 text-lg">{wonPoolItem?.description}</p>
                                         <div className="bg-green-500/20 rounded-lg p-4 border border-green-500/30">
                                             <div className="text-4xl font-bold text-green-400 mb-1">
                                                 {userTicket.wonItem.price / LAMPORTS_PER_SOL} SOL
@@ -0,0 +1,102 @@
+// Example partner program: buys a company_pool ticket for the customer as
+// one step of its own checkout, through the generated CPI client. The pool
+// has to be created with allow_cpi_purchases, and the ticket carries a hash
+// of the order id so the partner can match it up off-chain. Whitelisted,
+// token-gated and mega jackpot pools need the extra accounts buy_ticket reads,
+// which this example leaves out.
+
+// Anchor 0.31 codegen still calls the deprecated AccountInfo::realloc
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use company_pool::cpi::accounts::BuyTicket;
+use company_pool::program::CompanyPool;
+
+declare_id!("59Ni1BMaLpSo33Rgeq2BaoB6UtzXp1pF1uGq4gbuwaLD");
+
+#[program]
+pub mod checkout_example {
+    use super::*;
+
+    // The customer signs the outer transaction, pays for the ticket and
+    // owns it afterwards
+    pub fn checkout_with_ticket(ctx: Context<CheckoutWithTicket>, order_id: u64) -> Result<()> {
+        let accounts = BuyTicket {
+            company_pool: ctx.accounts.company_pool.to_account_info(),
+            user_ticket: ctx.accounts.user_ticket.to_account_info(),
+            user_pool_state: ctx.accounts.user_pool_state.to_account_info(),
+            buyer: ctx.accounts.customer.to_account_info(),
+            whitelist_entry: None,
+            blocked_user: ctx.accounts.blocked_user.to_account_info(),
+            gate_token_account: None,
+            gate_metadata: None,
+            pool_vault: ctx.accounts.pool_vault.to_account_info(),
+            prize_vault: ctx.accounts.prize_vault.to_account_info(),
+            global_config: ctx.accounts.global_config.to_account_info(),
+            global_stats: ctx
+                .accounts
+                .global_stats
+                .as_ref()
+                .map(|account| account.to_account_info()),
+            beneficiary: None,
+            mega_jackpot: None,
+            mega_jackpot_vault: None,
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+        let order_hash = hash(&order_id.to_le_bytes()).to_bytes();
+        company_pool::cpi::buy_ticket(
+            CpiContext::new(
+                ctx.accounts.company_pool_program.to_account_info(),
+                accounts,
+            ),
+            Some(order_hash),
+            0,
+            false,
+        )?;
+
+        msg!("Order {} paid with a ticket", order_id);
+        Ok(())
+    }
+}
+
+// buy_ticket's accounts, checked by company_pool itself
+#[derive(Accounts)]
+pub struct CheckoutWithTicket<'info> {
+    /// CHECK: Checked by company_pool
+    #[account(mut)]
+    pub company_pool: UncheckedAccount<'info>,
+
+    /// CHECK: Created by company_pool
+    #[account(mut)]
+    pub user_ticket: UncheckedAccount<'info>,
+
+    /// CHECK: Created by company_pool on the customer's first purchase
+    #[account(mut)]
+    pub user_pool_state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub customer: Signer<'info>,
+
+    /// CHECK: Checked by company_pool
+    pub blocked_user: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by company_pool
+    #[account(mut)]
+    pub pool_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by company_pool
+    #[account(mut)]
+    pub prize_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by company_pool
+    pub global_config: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by company_pool
+    #[account(mut)]
+    pub global_stats: Option<UncheckedAccount<'info>>,
+
+    pub company_pool_program: Program<'info, CompanyPool>,
+
+    pub system_program: Program<'info, System>,
+}
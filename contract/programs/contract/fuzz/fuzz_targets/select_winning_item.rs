@@ -0,0 +1,27 @@
+#![no_main]
+
+use company_pool::probability::{select_winning_item_index, WeightedProbabilityCalculator};
+use libfuzzer_sys::fuzz_target;
+
+// Run with `cargo fuzz run select_winning_item` from programs/contract
+fuzz_target!(|input: (Vec<u32>, u64, Vec<u64>, u64)| {
+    let (probabilities, seed, values, ticket_price) = input;
+
+    // Arbitrary tables, including weights near u32::MAX, must never panic
+    if let Some(index) = select_winning_item_index(&probabilities, seed) {
+        assert!(probabilities[index] > 0);
+    }
+
+    // Normalized tables from arbitrary item values always sum to 100%
+    let values: Vec<u64> = values.into_iter().filter(|&v| v > 0).take(10).collect();
+    if values.is_empty() || ticket_price == 0 {
+        return;
+    }
+    let items = values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| (format!("Item{}", i), value, 1))
+        .collect();
+    let calc = WeightedProbabilityCalculator::new(items, ticket_price);
+    assert!(calc.validate_probabilities());
+});
@@ -0,0 +1,45 @@
+// Prints a pool's probability table straight from its account, the way a
+// backend would read it without an indexer.
+
+use company_pool::client::derive_pool_pda;
+use company_pool::CompanyPool;
+use solana_rpc_client::rpc_client::RpcClient;
+
+const DEFAULT_RPC_URL: &str = "https://api.devnet.solana.com";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let company_name = args
+        .next()
+        .ok_or("usage: print_pool <company name> [rpc url]")?;
+    let rpc_url = args.next().unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
+
+    let (address, _) = derive_pool_pda(&company_name);
+    let data = RpcClient::new(rpc_url).get_account_data(&address)?;
+    let pool = CompanyPool::try_from_account_data(&data).map_err(|err| err.to_string())?;
+
+    println!("{} ({})", pool.company_name, address);
+    println!(
+        "layout v{}, ticket {} lamports, {} sold, {} bps of spins win nothing",
+        pool.version, pool.ticket_price, pool.total_tickets_sold, pool.no_win_bps
+    );
+    println!(
+        "{:>4}  {:<24} {:>16} {:>8} {:>10} {:>9}  tier",
+        "id", "item", "price", "bps", "cumulative", "won"
+    );
+    for (item, cumulative) in pool.items.iter().zip(pool.cum_probability) {
+        println!(
+            "{:>4}  {:<24} {:>16} {:>8} {:>10} {:>9}  {:?}{}",
+            item.item_id,
+            item.name,
+            item.price,
+            item.probability,
+            cumulative,
+            item.times_won,
+            item.tier,
+            if item.available { "" } else { " (unavailable)" }
+        );
+    }
+
+    Ok(())
+}
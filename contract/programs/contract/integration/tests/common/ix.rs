@@ -0,0 +1,1468 @@
+// Instruction builders. Account lists come from the program's generated
+// `accounts` structs so they can't drift from the on-chain contexts.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anchor_spl::token::spl_token;
+use company_pool::{
+    accounts, instruction, Achievement, CloneOverrides, CompanyPool, CreationMode, DrawTable,
+    PoolConfig, PoolFlag, PoolItemInput, PoolV2Config, SeasonResetFlags, TicketShare,
+};
+
+pub fn global_config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"global_config"], &company_pool::ID).0
+}
+
+pub fn global_stats_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"global_stats"], &company_pool::ID).0
+}
+
+pub fn mega_jackpot_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"mega_jackpot"], &company_pool::ID).0
+}
+
+pub fn mega_jackpot_vault_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"mega_jackpot_vault"], &company_pool::ID).0
+}
+
+pub fn blocked_user_pda(user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"blocked", user.as_ref()], &company_pool::ID).0
+}
+
+pub fn pool_creator_pda(creator: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pool_creator", creator.as_ref()], &company_pool::ID).0
+}
+
+pub fn pool_pda(company_name: &str) -> Pubkey {
+    let name_seed = CompanyPool::name_seed(company_name);
+    Pubkey::find_program_address(&[b"company_pool", name_seed.as_ref()], &company_pool::ID).0
+}
+
+pub fn pool_v2_pda(company_name: &str) -> Pubkey {
+    let name_seed = CompanyPool::name_seed(company_name);
+    Pubkey::find_program_address(&[b"company_pool_v2", name_seed.as_ref()], &company_pool::ID).0
+}
+
+pub fn raffle_result_pda(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"raffle_result", pool.as_ref()], &company_pool::ID).0
+}
+
+pub fn vault_pda(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pool_vault", pool.as_ref()], &company_pool::ID).0
+}
+
+pub fn prize_vault_pda(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"prize_vault", pool.as_ref()], &company_pool::ID).0
+}
+
+pub fn pool_stats_pda(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pool_stats", pool.as_ref()], &company_pool::ID).0
+}
+
+pub fn winner_registry_pda(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"winner_registry", pool.as_ref()], &company_pool::ID).0
+}
+
+pub fn leaderboard_pda(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"leaderboard", pool.as_ref()], &company_pool::ID).0
+}
+
+pub fn pool_archive_pda(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pool_archive", pool.as_ref()], &company_pool::ID).0
+}
+
+pub fn season_archive_pda(pool: &Pubkey, season: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"season_archive", pool.as_ref(), &season.to_le_bytes()],
+        &company_pool::ID,
+    )
+    .0
+}
+
+pub fn ticket_pda(buyer: &Pubkey, pool: &Pubkey, tickets_sold: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"user_ticket",
+            buyer.as_ref(),
+            pool.as_ref(),
+            &tickets_sold.to_le_bytes(),
+        ],
+        &company_pool::ID,
+    )
+    .0
+}
+
+pub fn user_pool_state_pda(pool: &Pubkey, user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"user_pool_state", pool.as_ref(), user.as_ref()],
+        &company_pool::ID,
+    )
+    .0
+}
+
+pub fn shared_ticket_pda(ticket: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"shared_ticket", ticket.as_ref()], &company_pool::ID).0
+}
+
+pub fn spin_history_pda(owner: &Pubkey, pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"spin_history", owner.as_ref(), pool.as_ref()],
+        &company_pool::ID,
+    )
+    .0
+}
+
+pub fn voucher_pda(pool: &Pubkey, recipient: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"voucher", pool.as_ref(), recipient.as_ref()],
+        &company_pool::ID,
+    )
+    .0
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: company_pool::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+// `creator_entry` is the authority's pool_creator PDA, needed once creation
+// is allowlisted; `treasury` is needed while the config charges a creation fee
+pub fn initialize_company_pool(
+    authority: &Pubkey,
+    creator_entry: Option<Pubkey>,
+    treasury: Option<Pubkey>,
+    ticket_price: u64,
+    company_name: &str,
+    items: Vec<PoolItemInput>,
+    config: PoolConfig,
+) -> Instruction {
+    let pool = pool_pda(company_name);
+    build(
+        accounts::InitializeCompanyPool {
+            company_pool: pool,
+            pool_vault: vault_pda(&pool),
+            prize_vault: prize_vault_pda(&pool),
+            pool_stats: pool_stats_pda(&pool),
+            winner_registry: winner_registry_pda(&pool),
+            leaderboard: leaderboard_pda(&pool),
+            authority: *authority,
+            global_config: global_config_pda(),
+            creator_entry,
+            treasury,
+            global_stats: Some(global_stats_pda()),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeCompanyPool {
+            ticket_price,
+            company_name: company_name.to_string(),
+            company_image: "https://example.com/logo.png".to_string(),
+            items,
+            config,
+        },
+    )
+}
+
+fn buy_ticket_accounts(buyer: &Pubkey, pool: &Pubkey, tickets_sold: u64) -> accounts::BuyTicket {
+    accounts::BuyTicket {
+        company_pool: *pool,
+        user_ticket: ticket_pda(buyer, pool, tickets_sold),
+        user_pool_state: user_pool_state_pda(pool, buyer),
+        buyer: *buyer,
+        whitelist_entry: None,
+        blocked_user: blocked_user_pda(buyer),
+        gate_token_account: None,
+        gate_metadata: None,
+        pool_vault: vault_pda(pool),
+        prize_vault: prize_vault_pda(pool),
+        global_config: global_config_pda(),
+        global_stats: Some(global_stats_pda()),
+        beneficiary: None,
+        mega_jackpot: None,
+        mega_jackpot_vault: None,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn buy_ticket(
+    buyer: &Pubkey,
+    pool: &Pubkey,
+    tickets_sold: u64,
+    external_id: Option<[u8; 32]>,
+) -> Instruction {
+    build(
+        buy_ticket_accounts(buyer, pool, tickets_sold),
+        instruction::BuyTicket {
+            external_id,
+            donation_lamports: 0,
+            insured: false,
+        },
+    )
+}
+
+// buy_ticket from a pool that pays into the mega jackpot
+pub fn buy_ticket_in_mega_jackpot(buyer: &Pubkey, pool: &Pubkey, tickets_sold: u64) -> Instruction {
+    build(
+        accounts::BuyTicket {
+            mega_jackpot: Some(mega_jackpot_pda()),
+            mega_jackpot_vault: Some(mega_jackpot_vault_pda()),
+            ..buy_ticket_accounts(buyer, pool, tickets_sold)
+        },
+        instruction::BuyTicket {
+            external_id: None,
+            donation_lamports: 0,
+            insured: false,
+        },
+    )
+}
+
+// buy_ticket with the pool's spin insurance
+pub fn buy_insured_ticket(buyer: &Pubkey, pool: &Pubkey, tickets_sold: u64) -> Instruction {
+    build(
+        buy_ticket_accounts(buyer, pool, tickets_sold),
+        instruction::BuyTicket {
+            external_id: None,
+            donation_lamports: 0,
+            insured: true,
+        },
+    )
+}
+
+// buy_ticket with a donation on top, for the beneficiary account passed
+pub fn buy_ticket_with_donation(
+    buyer: &Pubkey,
+    pool: &Pubkey,
+    tickets_sold: u64,
+    donation_lamports: u64,
+    beneficiary: Option<Pubkey>,
+) -> Instruction {
+    build(
+        accounts::BuyTicket {
+            beneficiary,
+            ..buy_ticket_accounts(buyer, pool, tickets_sold)
+        },
+        instruction::BuyTicket {
+            external_id: None,
+            donation_lamports,
+            insured: false,
+        },
+    )
+}
+
+// The bundle's new ticket PDAs go in remaining_accounts, in ticket id order
+pub fn buy_bundle(buyer: &Pubkey, pool: &Pubkey, tickets_sold: u64, size: u8) -> Instruction {
+    let mut instruction = build(
+        accounts::BuyBundle {
+            company_pool: *pool,
+            user_pool_state: user_pool_state_pda(pool, buyer),
+            buyer: *buyer,
+            blocked_user: blocked_user_pda(buyer),
+            whitelist_entry: None,
+            gate_token_account: None,
+            gate_metadata: None,
+            pool_vault: vault_pda(pool),
+            prize_vault: prize_vault_pda(pool),
+            global_config: global_config_pda(),
+            global_stats: Some(global_stats_pda()),
+            mega_jackpot: None,
+            mega_jackpot_vault: None,
+            system_program: system_program::ID,
+        },
+        instruction::BuyBundle { size },
+    );
+    instruction.accounts.extend(
+        (tickets_sold..tickets_sold + size as u64)
+            .map(|ticket_id| AccountMeta::new(ticket_pda(buyer, pool, ticket_id), false)),
+    );
+    instruction
+}
+
+pub fn gift_ticket(
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    pool: &Pubkey,
+    tickets_sold: u64,
+) -> Instruction {
+    build(
+        accounts::GiftTicket {
+            company_pool: *pool,
+            user_ticket: ticket_pda(recipient, pool, tickets_sold),
+            recipient_pool_state: user_pool_state_pda(pool, recipient),
+            payer: *payer,
+            payer_blocked_user: blocked_user_pda(payer),
+            recipient_blocked_user: blocked_user_pda(recipient),
+            recipient_whitelist_entry: None,
+            recipient_gate_token_account: None,
+            recipient_gate_metadata: None,
+            pool_vault: vault_pda(pool),
+            prize_vault: prize_vault_pda(pool),
+            global_config: global_config_pda(),
+            mega_jackpot: None,
+            mega_jackpot_vault: None,
+            system_program: system_program::ID,
+        },
+        instruction::GiftTicket {
+            recipient: *recipient,
+        },
+    )
+}
+
+pub fn issue_voucher(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    recipient: &Pubkey,
+    count: u8,
+) -> Instruction {
+    build(
+        accounts::IssueVoucher {
+            company_pool: *pool,
+            voucher: voucher_pda(pool, recipient),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        instruction::IssueVoucher {
+            recipient: *recipient,
+            count,
+        },
+    )
+}
+
+pub fn redeem_voucher(redeemer: &Pubkey, pool: &Pubkey, tickets_sold: u64) -> Instruction {
+    build(
+        accounts::RedeemVoucher {
+            company_pool: *pool,
+            voucher: voucher_pda(pool, redeemer),
+            user_ticket: ticket_pda(redeemer, pool, tickets_sold),
+            redeemer: *redeemer,
+            blocked_user: blocked_user_pda(redeemer),
+            global_config: global_config_pda(),
+            system_program: system_program::ID,
+        },
+        instruction::RedeemVoucher {},
+    )
+}
+
+pub fn redeem_points_for_ticket(
+    redeemer: &Pubkey,
+    pool: &Pubkey,
+    tickets_sold: u64,
+) -> Instruction {
+    build(
+        accounts::RedeemPointsForTicket {
+            company_pool: *pool,
+            user_pool_state: user_pool_state_pda(pool, redeemer),
+            user_ticket: ticket_pda(redeemer, pool, tickets_sold),
+            redeemer: *redeemer,
+            blocked_user: blocked_user_pda(redeemer),
+            global_config: global_config_pda(),
+            system_program: system_program::ID,
+        },
+        instruction::RedeemPointsForTicket {},
+    )
+}
+
+// The ticket is created under the payer's seeds but owned by its
+// shared_ticket PDA
+pub fn buy_shared_ticket(
+    payer: &Pubkey,
+    pool: &Pubkey,
+    tickets_sold: u64,
+    shares: Vec<TicketShare>,
+) -> Instruction {
+    let ticket = ticket_pda(payer, pool, tickets_sold);
+    build(
+        accounts::BuySharedTicket {
+            company_pool: *pool,
+            user_ticket: ticket,
+            shared_ticket: shared_ticket_pda(&ticket),
+            payer_pool_state: user_pool_state_pda(pool, payer),
+            payer: *payer,
+            blocked_user: blocked_user_pda(payer),
+            pool_vault: vault_pda(pool),
+            prize_vault: prize_vault_pda(pool),
+            global_config: global_config_pda(),
+            global_stats: Some(global_stats_pda()),
+            system_program: system_program::ID,
+        },
+        instruction::BuySharedTicket { shares },
+    )
+}
+
+fn record_spin_result_accounts(
+    spinner: &Pubkey,
+    ticket_owner: &Pubkey,
+    pool: &Pubkey,
+    ticket: &Pubkey,
+) -> accounts::RecordSpinResult {
+    // A spinner other than the owner is a session key. The owner's state is
+    // always passed so the spin counts toward their achievements.
+    let delegated = spinner != ticket_owner;
+    accounts::RecordSpinResult {
+        company_pool: *pool,
+        user_ticket: *ticket,
+        spinner: *spinner,
+        user_pool_state: Some(user_pool_state_pda(pool, ticket_owner)),
+        ticket_owner: delegated.then_some(*ticket_owner),
+        pool_vault: vault_pda(pool),
+        prize_vault: Some(prize_vault_pda(pool)),
+        spin_history: spin_history_pda(ticket_owner, pool),
+        winner_registry: winner_registry_pda(pool),
+        global_config: global_config_pda(),
+        pool_stats: pool_stats_pda(pool),
+        instructions: sysvar::instructions::ID,
+        global_stats: Some(global_stats_pda()),
+        mega_jackpot: None,
+        shared_ticket: None,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn record_spin_result(
+    spinner: &Pubkey,
+    ticket_owner: &Pubkey,
+    pool: &Pubkey,
+    ticket: &Pubkey,
+) -> Instruction {
+    build(
+        record_spin_result_accounts(spinner, ticket_owner, pool, ticket),
+        instruction::RecordSpinResult {},
+    )
+}
+
+// record_spin_result in a pool that pays into the mega jackpot, which the
+// spin rolls for
+pub fn record_spin_result_in_mega_jackpot(
+    spinner: &Pubkey,
+    ticket_owner: &Pubkey,
+    pool: &Pubkey,
+    ticket: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::RecordSpinResult {
+            mega_jackpot: Some(mega_jackpot_pda()),
+            ..record_spin_result_accounts(spinner, ticket_owner, pool, ticket)
+        },
+        instruction::RecordSpinResult {},
+    )
+}
+
+// record_spin_result on a shared ticket by one of its participants. The
+// PDA owning the ticket has no pool state to count achievements in.
+pub fn record_shared_spin(participant: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    let shared_ticket = shared_ticket_pda(ticket);
+    build(
+        accounts::RecordSpinResult {
+            user_pool_state: None,
+            ticket_owner: None,
+            shared_ticket: Some(shared_ticket),
+            ..record_spin_result_accounts(participant, &shared_ticket, pool, ticket)
+        },
+        instruction::RecordSpinResult {},
+    )
+}
+
+fn claim_reward_accounts(
+    spinner: &Pubkey,
+    pool: &Pubkey,
+    ticket: &Pubkey,
+) -> accounts::ClaimReward {
+    accounts::ClaimReward {
+        company_pool: *pool,
+        user_ticket: *ticket,
+        raffle_result: None,
+        spinner: *spinner,
+        prize_vault: prize_vault_pda(pool),
+        pool_stats: pool_stats_pda(pool),
+        global_stats: Some(global_stats_pda()),
+        leaderboard: Some(leaderboard_pda(pool)),
+        system_program: system_program::ID,
+        reward_mint: None,
+        prize_escrow: None,
+        winner_token_account: None,
+        token_program: None,
+    }
+}
+
+pub fn claim_reward(spinner: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        claim_reward_accounts(spinner, pool, ticket),
+        instruction::ClaimReward {},
+    )
+}
+
+// `participants` are the wallets to pay, in the ticket's share order
+pub fn claim_shared_reward(
+    claimant: &Pubkey,
+    pool: &Pubkey,
+    ticket: &Pubkey,
+    participants: &[Pubkey],
+) -> Instruction {
+    let mut instruction = build(
+        accounts::ClaimSharedReward {
+            company_pool: *pool,
+            user_ticket: *ticket,
+            shared_ticket: shared_ticket_pda(ticket),
+            claimant: *claimant,
+            prize_vault: prize_vault_pda(pool),
+            pool_stats: pool_stats_pda(pool),
+            global_stats: Some(global_stats_pda()),
+            leaderboard: Some(leaderboard_pda(pool)),
+            system_program: system_program::ID,
+        },
+        instruction::ClaimSharedReward {},
+    );
+    instruction.accounts.extend(
+        participants
+            .iter()
+            .map(|participant| AccountMeta::new(*participant, false)),
+    );
+    instruction
+}
+
+pub fn claim_insurance(owner: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::ClaimInsurance {
+            company_pool: *pool,
+            user_ticket: *ticket,
+            owner: *owner,
+            prize_vault: prize_vault_pda(pool),
+            system_program: system_program::ID,
+        },
+        instruction::ClaimInsurance {},
+    )
+}
+
+pub fn wsol_account(owner: &Pubkey) -> Pubkey {
+    get_associated_token_address(owner, &spl_token::native_mint::ID)
+}
+
+pub fn claim_reward_wrapped(spinner: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::ClaimRewardWrapped {
+            claim: claim_reward_accounts(spinner, pool, ticket),
+            native_mint: spl_token::native_mint::ID,
+            wsol_account: wsol_account(spinner),
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimRewardWrapped {},
+    )
+}
+
+// The prize vault's account holding a token item's escrow
+pub fn prize_escrow(pool: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(&prize_vault_pda(pool), mint)
+}
+
+// claim_reward for a token prize, paid into the winner's associated account
+pub fn claim_token_prize(
+    spinner: &Pubkey,
+    pool: &Pubkey,
+    ticket: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::ClaimReward {
+            reward_mint: Some(*mint),
+            prize_escrow: Some(prize_escrow(pool, mint)),
+            winner_token_account: Some(get_associated_token_address(spinner, mint)),
+            token_program: Some(spl_token::ID),
+            ..claim_reward_accounts(spinner, pool, ticket)
+        },
+        instruction::ClaimReward {},
+    )
+}
+
+pub fn deposit_item_tokens(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    mint: &Pubkey,
+    item_id: u32,
+    amount: u64,
+) -> Instruction {
+    build(
+        accounts::DepositItemTokens {
+            company_pool: *pool,
+            prize_vault: prize_vault_pda(pool),
+            reward_mint: *mint,
+            prize_escrow: prize_escrow(pool, mint),
+            authority_token_account: get_associated_token_address(authority, mint),
+            authority: *authority,
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::DepositItemTokens { item_id, amount },
+    )
+}
+
+pub fn reclaim_item_tokens(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    mint: &Pubkey,
+    item_id: u32,
+) -> Instruction {
+    build(
+        accounts::ReclaimItemTokens {
+            company_pool: *pool,
+            prize_vault: prize_vault_pda(pool),
+            reward_mint: *mint,
+            prize_escrow: prize_escrow(pool, mint),
+            authority_token_account: get_associated_token_address(authority, mint),
+            authority: *authority,
+            token_program: spl_token::ID,
+        },
+        instruction::ReclaimItemTokens { item_id },
+    )
+}
+
+pub fn set_item_availability(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    item_id: u32,
+    available: bool,
+) -> Instruction {
+    build(
+        accounts::SetItemAvailability {
+            company_pool: *pool,
+            authority: *authority,
+        },
+        instruction::SetItemAvailability { item_id, available },
+    )
+}
+
+// Sponsor wallets go in remaining_accounts, in the pool's sponsor order
+pub fn distribute_sponsor_revenue(pool: &Pubkey, sponsors: &[Pubkey]) -> Instruction {
+    let mut instruction = build(
+        accounts::DistributeSponsorRevenue {
+            company_pool: *pool,
+            pool_vault: vault_pda(pool),
+            system_program: system_program::ID,
+        },
+        instruction::DistributeSponsorRevenue {},
+    );
+    instruction.accounts.extend(
+        sponsors
+            .iter()
+            .map(|sponsor| AccountMeta::new(*sponsor, false)),
+    );
+    instruction
+}
+
+pub fn claim_raffle_reward(winner: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::ClaimReward {
+            company_pool: *pool,
+            user_ticket: *ticket,
+            raffle_result: Some(raffle_result_pda(pool)),
+            spinner: *winner,
+            prize_vault: prize_vault_pda(pool),
+            pool_stats: pool_stats_pda(pool),
+            global_stats: Some(global_stats_pda()),
+            leaderboard: Some(leaderboard_pda(pool)),
+            system_program: system_program::ID,
+        },
+        instruction::ClaimReward {},
+    )
+}
+
+pub fn draw_raffle(payer: &Pubkey, pool: &Pubkey) -> Instruction {
+    build(
+        accounts::DrawRaffle {
+            company_pool: *pool,
+            raffle_result: raffle_result_pda(pool),
+            payer: *payer,
+            slot_hashes: sysvar::slot_hashes::ID,
+            system_program: system_program::ID,
+        },
+        instruction::DrawRaffle {},
+    )
+}
+
+pub fn claim_vested(claimant: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::ClaimVested {
+            company_pool: *pool,
+            user_ticket: *ticket,
+            claimant: *claimant,
+            prize_vault: prize_vault_pda(pool),
+            pool_stats: pool_stats_pda(pool),
+            global_stats: Some(global_stats_pda()),
+            system_program: system_program::ID,
+        },
+        instruction::ClaimVested {},
+    )
+}
+
+pub fn claim_achievement_reward(
+    claimant: &Pubkey,
+    pool: &Pubkey,
+    achievement: Achievement,
+) -> Instruction {
+    build(
+        accounts::ClaimAchievementReward {
+            company_pool: *pool,
+            user_pool_state: user_pool_state_pda(pool, claimant),
+            pool_vault: vault_pda(pool),
+            claimant: *claimant,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimAchievementReward { achievement },
+    )
+}
+
+pub fn crank_expire_reward(cranker: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::CrankExpireReward {
+            company_pool: *pool,
+            user_ticket: *ticket,
+            pool_vault: vault_pda(pool),
+            cranker: *cranker,
+            system_program: system_program::ID,
+        },
+        instruction::CrankExpireReward {},
+    )
+}
+
+pub fn verify_spin(pool: &Pubkey, ticket: &Pubkey, table: DrawTable) -> Instruction {
+    build(
+        accounts::VerifySpin {
+            company_pool: *pool,
+            user_ticket: *ticket,
+        },
+        instruction::VerifySpin { table },
+    )
+}
+
+pub fn crank_expire_pool(cranker: &Pubkey, pool: &Pubkey) -> Instruction {
+    build(
+        accounts::CrankExpirePool {
+            company_pool: *pool,
+            cranker: *cranker,
+        },
+        instruction::CrankExpirePool {},
+    )
+}
+
+pub fn close_pool(authority: &Pubkey, pool: &Pubkey) -> Instruction {
+    build(
+        accounts::ClosePool {
+            company_pool: *pool,
+            pool_vault: vault_pda(pool),
+            prize_vault: Some(prize_vault_pda(pool)),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        instruction::ClosePool {},
+    )
+}
+
+pub fn archive_pool(authority: &Pubkey, pool: &Pubkey) -> Instruction {
+    build(
+        accounts::ArchivePool {
+            company_pool: *pool,
+            pool_archive: pool_archive_pda(pool),
+            pool_vault: vault_pda(pool),
+            prize_vault: Some(prize_vault_pda(pool)),
+            leaderboard: Some(leaderboard_pda(pool)),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        instruction::ArchivePool {},
+    )
+}
+
+// `season` is the one being closed, whose archive this creates
+pub fn start_new_season(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    season: u16,
+    reset_flags: SeasonResetFlags,
+) -> Instruction {
+    build(
+        accounts::StartNewSeason {
+            company_pool: *pool,
+            season_archive: season_archive_pda(pool, season),
+            pool_stats: pool_stats_pda(pool),
+            leaderboard: Some(leaderboard_pda(pool)),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        instruction::StartNewSeason { reset_flags },
+    )
+}
+
+pub fn close_ticket(owner: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseTicket {
+            company_pool: *pool,
+            user_ticket: *ticket,
+            owner: *owner,
+        },
+        instruction::CloseTicket {},
+    )
+}
+
+pub fn clone_pool(
+    authority: &Pubkey,
+    source_pool: &Pubkey,
+    new_company_name: &str,
+    overrides: CloneOverrides,
+) -> Instruction {
+    let pool = pool_pda(new_company_name);
+    build(
+        accounts::ClonePool {
+            source_pool: *source_pool,
+            company_pool: pool,
+            pool_vault: vault_pda(&pool),
+            prize_vault: prize_vault_pda(&pool),
+            pool_stats: pool_stats_pda(&pool),
+            winner_registry: winner_registry_pda(&pool),
+            leaderboard: leaderboard_pda(&pool),
+            authority: *authority,
+            global_config: global_config_pda(),
+            creator_entry: None,
+            treasury: None,
+            global_stats: Some(global_stats_pda()),
+            system_program: system_program::ID,
+        },
+        instruction::ClonePool {
+            new_company_name: new_company_name.to_string(),
+            overrides,
+        },
+    )
+}
+
+pub fn cancel_stuck_spin(cranker: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::CancelStuckSpin {
+            company_pool: *pool,
+            user_ticket: *ticket,
+            cranker: *cranker,
+        },
+        instruction::CancelStuckSpin {},
+    )
+}
+
+pub fn void_spin(admin: &Pubkey, pool: &Pubkey, ticket: &Pubkey, owner: &Pubkey) -> Instruction {
+    build(
+        accounts::VoidSpin {
+            global_config: global_config_pda(),
+            company_pool: *pool,
+            user_ticket: *ticket,
+            ticket_owner: *owner,
+            pool_vault: vault_pda(pool),
+            prize_vault: prize_vault_pda(pool),
+            mega_jackpot: None,
+            admin: *admin,
+            system_program: system_program::ID,
+        },
+        instruction::VoidSpin {},
+    )
+}
+
+// void_spin passing the mega jackpot, for a spin that won it
+pub fn void_spin_in_mega_jackpot(
+    admin: &Pubkey,
+    pool: &Pubkey,
+    ticket: &Pubkey,
+    owner: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::VoidSpin {
+            global_config: global_config_pda(),
+            company_pool: *pool,
+            user_ticket: *ticket,
+            ticket_owner: *owner,
+            pool_vault: vault_pda(pool),
+            prize_vault: prize_vault_pda(pool),
+            mega_jackpot: Some(mega_jackpot_pda()),
+            admin: *admin,
+            system_program: system_program::ID,
+        },
+        instruction::VoidSpin {},
+    )
+}
+
+pub fn withdraw_funds_from_vault(
+    authority: &Pubkey,
+    co_authority: Option<Pubkey>,
+    pool: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    build(
+        accounts::WithdrawFundsFromVault {
+            company_pool: *pool,
+            authority: *authority,
+            co_authority,
+            pool_vault: vault_pda(pool),
+            global_config: global_config_pda(),
+            system_program: system_program::ID,
+        },
+        instruction::WithdrawFundsFromVault {
+            amount_to_withdraw: amount,
+        },
+    )
+}
+
+pub fn sweep_vault(
+    authority: &Pubkey,
+    co_authority: Option<Pubkey>,
+    pool: &Pubkey,
+    beneficiary: Option<Pubkey>,
+) -> Instruction {
+    build(
+        accounts::SweepVault {
+            company_pool: *pool,
+            authority: *authority,
+            co_authority,
+            beneficiary,
+            pool_vault: vault_pda(pool),
+            global_config: global_config_pda(),
+            system_program: system_program::ID,
+        },
+        instruction::SweepVault {},
+    )
+}
+
+pub fn deposit_to_vault(depositor: &Pubkey, pool: &Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::DepositToVault {
+            company_pool: *pool,
+            depositor: *depositor,
+            pool_vault: vault_pda(pool),
+            system_program: system_program::ID,
+        },
+        instruction::DepositToVault { amount },
+    )
+}
+
+pub fn reconcile(pool: &Pubkey) -> Instruction {
+    build(
+        accounts::Reconcile {
+            company_pool: *pool,
+            pool_vault: vault_pda(pool),
+            prize_vault: prize_vault_pda(pool),
+        },
+        instruction::Reconcile {},
+    )
+}
+
+pub fn initialize_global_stats(admin: &Pubkey) -> Instruction {
+    build(
+        accounts::InitializeGlobalStats {
+            global_config: global_config_pda(),
+            global_stats: global_stats_pda(),
+            admin: *admin,
+            system_program: system_program::ID,
+        },
+        instruction::InitializeGlobalStats {},
+    )
+}
+
+pub fn initialize_mega_jackpot(
+    admin: &Pubkey,
+    contribution_bps: u16,
+    hit_chance_bps: u16,
+) -> Instruction {
+    build(
+        accounts::InitializeMegaJackpot {
+            global_config: global_config_pda(),
+            mega_jackpot: mega_jackpot_pda(),
+            mega_jackpot_vault: mega_jackpot_vault_pda(),
+            admin: *admin,
+            system_program: system_program::ID,
+        },
+        instruction::InitializeMegaJackpot {
+            contribution_bps,
+            hit_chance_bps,
+        },
+    )
+}
+
+pub fn set_mega_jackpot_terms(
+    admin: &Pubkey,
+    contribution_bps: u16,
+    hit_chance_bps: u16,
+) -> Instruction {
+    build(
+        accounts::SetMegaJackpotTerms {
+            global_config: global_config_pda(),
+            mega_jackpot: mega_jackpot_pda(),
+            admin: *admin,
+        },
+        instruction::SetMegaJackpotTerms {
+            contribution_bps,
+            hit_chance_bps,
+        },
+    )
+}
+
+pub fn claim_mega_jackpot(claimant: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::ClaimMegaJackpot {
+            mega_jackpot: mega_jackpot_pda(),
+            mega_jackpot_vault: mega_jackpot_vault_pda(),
+            company_pool: *pool,
+            user_ticket: *ticket,
+            claimant: *claimant,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimMegaJackpot {},
+    )
+}
+
+pub fn get_global_stats() -> Instruction {
+    build(
+        accounts::GetGlobalStats {
+            global_stats: global_stats_pda(),
+        },
+        instruction::GetGlobalStats {},
+    )
+}
+
+// Leaves the optional global_stats account out, the way an older client
+// would: Anchor reads the program id in its place as None
+pub fn without_global_stats(mut instruction: Instruction) -> Instruction {
+    let global_stats = global_stats_pda();
+    for meta in &mut instruction.accounts {
+        if meta.pubkey == global_stats {
+            *meta = AccountMeta::new_readonly(company_pool::ID, false);
+        }
+    }
+    instruction
+}
+
+pub fn get_recent_winners(pool: &Pubkey, skip: u32) -> Instruction {
+    build(
+        accounts::GetRecentWinners {
+            company_pool: *pool,
+            winner_registry: winner_registry_pda(pool),
+        },
+        instruction::GetRecentWinners { skip },
+    )
+}
+
+pub fn get_leaderboard(pool: &Pubkey) -> Instruction {
+    build(
+        accounts::GetLeaderboard {
+            company_pool: *pool,
+            leaderboard: leaderboard_pda(pool),
+        },
+        instruction::GetLeaderboard {},
+    )
+}
+
+pub fn initialize_company_pool_v2(
+    authority: &Pubkey,
+    ticket_price: u64,
+    company_name: &str,
+    items: Vec<PoolItemInput>,
+    config: PoolV2Config,
+) -> Instruction {
+    let pool = pool_v2_pda(company_name);
+    build(
+        accounts::InitializeCompanyPoolV2 {
+            company_pool: pool,
+            pool_vault: vault_pda(&pool),
+            prize_vault: prize_vault_pda(&pool),
+            authority: *authority,
+            global_config: global_config_pda(),
+            creator_entry: None,
+            treasury: None,
+            global_stats: Some(global_stats_pda()),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeCompanyPoolV2 {
+            ticket_price,
+            company_name: company_name.to_string(),
+            company_image: "https://example.com/logo.png".to_string(),
+            items,
+            config,
+        },
+    )
+}
+
+pub fn buy_ticket_v2(buyer: &Pubkey, pool: &Pubkey, tickets_sold: u64) -> Instruction {
+    build(
+        accounts::BuyTicketV2 {
+            company_pool: *pool,
+            user_ticket: ticket_pda(buyer, pool, tickets_sold),
+            buyer: *buyer,
+            blocked_user: blocked_user_pda(buyer),
+            pool_vault: vault_pda(pool),
+            prize_vault: prize_vault_pda(pool),
+            global_config: global_config_pda(),
+            global_stats: Some(global_stats_pda()),
+            system_program: system_program::ID,
+        },
+        instruction::BuyTicketV2 {},
+    )
+}
+
+pub fn record_spin_result_v2(spinner: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::RecordSpinResultV2 {
+            company_pool: *pool,
+            user_ticket: *ticket,
+            spinner: *spinner,
+            pool_vault: vault_pda(pool),
+            global_config: global_config_pda(),
+            instructions: sysvar::instructions::ID,
+        },
+        instruction::RecordSpinResultV2 {},
+    )
+}
+
+pub fn claim_reward_v2(spinner: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::ClaimRewardV2 {
+            company_pool: *pool,
+            user_ticket: *ticket,
+            spinner: *spinner,
+            prize_vault: prize_vault_pda(pool),
+            global_stats: Some(global_stats_pda()),
+            system_program: system_program::ID,
+        },
+        instruction::ClaimRewardV2 {},
+    )
+}
+
+pub fn withdraw_funds_from_vault_v2(authority: &Pubkey, pool: &Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::WithdrawFundsFromVaultV2 {
+            company_pool: *pool,
+            authority: *authority,
+            pool_vault: vault_pda(pool),
+            global_config: global_config_pda(),
+            system_program: system_program::ID,
+        },
+        instruction::WithdrawFundsFromVaultV2 {
+            amount_to_withdraw: amount,
+        },
+    )
+}
+
+pub fn set_pool_creation_mode(admin: &Pubkey, mode: CreationMode) -> Instruction {
+    build(
+        accounts::SetPoolCreationMode {
+            global_config: global_config_pda(),
+            admin: *admin,
+        },
+        instruction::SetPoolCreationMode { mode },
+    )
+}
+
+pub fn update_creation_fee(admin: &Pubkey, fee_lamports: u64, treasury: &Pubkey) -> Instruction {
+    build(
+        accounts::UpdateCreationFee {
+            global_config: global_config_pda(),
+            admin: *admin,
+        },
+        instruction::UpdateCreationFee {
+            fee_lamports,
+            treasury: *treasury,
+        },
+    )
+}
+
+pub fn block_user(admin: &Pubkey, user: &Pubkey) -> Instruction {
+    build(
+        accounts::BlockUser {
+            global_config: global_config_pda(),
+            blocked_user: blocked_user_pda(user),
+            admin: *admin,
+            system_program: system_program::ID,
+        },
+        instruction::BlockUser { user: *user },
+    )
+}
+
+pub fn unblock_user(admin: &Pubkey, user: &Pubkey) -> Instruction {
+    build(
+        accounts::UnblockUser {
+            global_config: global_config_pda(),
+            blocked_user: blocked_user_pda(user),
+            admin: *admin,
+        },
+        instruction::UnblockUser { user: *user },
+    )
+}
+
+pub fn add_pool_creator(admin: &Pubkey, creator: &Pubkey) -> Instruction {
+    build(
+        accounts::AddPoolCreator {
+            global_config: global_config_pda(),
+            creator_entry: pool_creator_pda(creator),
+            admin: *admin,
+            system_program: system_program::ID,
+        },
+        instruction::AddPoolCreator { creator: *creator },
+    )
+}
+
+pub fn remove_pool_creator(admin: &Pubkey, creator: &Pubkey) -> Instruction {
+    build(
+        accounts::RemovePoolCreator {
+            global_config: global_config_pda(),
+            creator_entry: pool_creator_pda(creator),
+            admin: *admin,
+        },
+        instruction::RemovePoolCreator { creator: *creator },
+    )
+}
+
+pub fn migrate_global_config(payer: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateGlobalConfig {
+            global_config: global_config_pda(),
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateGlobalConfig {},
+    )
+}
+
+pub fn admin_set_pool_flag(
+    admin: &Pubkey,
+    pool: &Pubkey,
+    flag: PoolFlag,
+    enabled: bool,
+) -> Instruction {
+    build(
+        accounts::AdminSetPoolFlag {
+            global_config: global_config_pda(),
+            company_pool: *pool,
+            admin: *admin,
+        },
+        instruction::AdminSetPoolFlag { flag, enabled },
+    )
+}
+
+pub fn set_spin_operator(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    spin_operator: Option<Pubkey>,
+) -> Instruction {
+    build(
+        accounts::SetSpinOperator {
+            company_pool: *pool,
+            authority: *authority,
+        },
+        instruction::SetSpinOperator { spin_operator },
+    )
+}
+
+pub fn set_happy_hour(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    start_hour_utc: u8,
+    end_hour_utc: u8,
+    discount_bps: u16,
+) -> Instruction {
+    build(
+        accounts::SetHappyHour {
+            company_pool: *pool,
+            authority: *authority,
+        },
+        instruction::SetHappyHour {
+            start_hour_utc,
+            end_hour_utc,
+            discount_bps,
+        },
+    )
+}
+
+pub fn reveal_item(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    item_id: u32,
+    name: &str,
+    image: &str,
+    description: &str,
+) -> Instruction {
+    build(
+        accounts::RevealItem {
+            company_pool: *pool,
+            authority: *authority,
+        },
+        instruction::RevealItem {
+            item_id,
+            name: name.to_string(),
+            image: image.to_string(),
+            description: description.to_string(),
+        },
+    )
+}
+
+pub fn commit_server_seed(authority: &Pubkey, pool: &Pubkey, seed_hash: [u8; 32]) -> Instruction {
+    build(
+        accounts::CommitServerSeed {
+            company_pool: *pool,
+            authority: *authority,
+        },
+        instruction::CommitServerSeed { seed_hash },
+    )
+}
+
+pub fn reveal_server_seed(authority: &Pubkey, pool: &Pubkey, seed: [u8; 32]) -> Instruction {
+    build(
+        accounts::RevealServerSeed {
+            company_pool: *pool,
+            authority: *authority,
+        },
+        instruction::RevealServerSeed { seed },
+    )
+}
+
+pub fn get_probability_analysis(pool: &Pubkey, start_index: u8, count: u8) -> Instruction {
+    build(
+        accounts::GetProbabilityAnalysis {
+            company_pool: *pool,
+        },
+        instruction::GetProbabilityAnalysis { start_index, count },
+    )
+}
+
+pub fn get_spin_odds(pool: &Pubkey, item_index: u32, spins: u32) -> Instruction {
+    build(
+        accounts::GetSpinOdds {
+            company_pool: *pool,
+        },
+        instruction::GetSpinOdds { item_index, spins },
+    )
+}
+
+pub fn update_pool_item(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    item_id: u32,
+    new_image: Option<String>,
+    new_description: Option<String>,
+    new_price: Option<u64>,
+) -> Instruction {
+    build(
+        accounts::UpdatePoolItem {
+            company_pool: *pool,
+            pool_item: None,
+            authority: *authority,
+        },
+        instruction::UpdatePoolItem {
+            item_id,
+            new_image,
+            new_description,
+            new_price,
+        },
+    )
+}
+
+pub fn set_item_boost(
+    authority: &Pubkey,
+    pool: &Pubkey,
+    item_id: u32,
+    boost_bps_multiplier: u32,
+    starts_at: i64,
+    ends_at: i64,
+) -> Instruction {
+    build(
+        accounts::SetItemBoost {
+            company_pool: *pool,
+            authority: *authority,
+        },
+        instruction::SetItemBoost {
+            item_id,
+            boost_bps_multiplier,
+            starts_at,
+            ends_at,
+        },
+    )
+}
+
+pub fn migrate_pool(payer: &Pubkey, pool: &Pubkey) -> Instruction {
+    build(
+        accounts::MigratePool {
+            company_pool: *pool,
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        instruction::MigratePool {},
+    )
+}
+
+pub fn migrate_pool_v2(payer: &Pubkey, pool: &Pubkey) -> Instruction {
+    build(
+        accounts::MigratePool {
+            company_pool: *pool,
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        instruction::MigratePoolV2 {},
+    )
+}
+
+pub fn migrate_ticket(owner: &Pubkey, pool: &Pubkey, ticket: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateTicket {
+            user_ticket: *ticket,
+            company_pool: *pool,
+            owner: *owner,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateTicket {},
+    )
+}
+
+pub fn migrate_user_pool_state(payer: &Pubkey, pool: &Pubkey, user: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateUserPoolState {
+            user_pool_state: user_pool_state_pda(pool, user),
+            company_pool: *pool,
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateUserPoolState { _user: *user },
+    )
+}
+
+// Buys a ticket through checkout_example's CPI, as a partner program would
+pub fn checkout_with_ticket(customer: &Pubkey, pool: &Pubkey, tickets_sold: u64) -> Instruction {
+    Instruction {
+        program_id: checkout_example::ID,
+        accounts: checkout_example::accounts::CheckoutWithTicket {
+            company_pool: *pool,
+            user_ticket: ticket_pda(customer, pool, tickets_sold),
+            user_pool_state: user_pool_state_pda(pool, customer),
+            customer: *customer,
+            blocked_user: blocked_user_pda(customer),
+            pool_vault: vault_pda(pool),
+            prize_vault: prize_vault_pda(pool),
+            global_config: global_config_pda(),
+            global_stats: Some(global_stats_pda()),
+            company_pool_program: company_pool::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: checkout_example::instruction::CheckoutWithTicket { order_id: 7 }.data(),
+    }
+}
+
+// Wraps a record_spin_result instruction in spin_relay's CPI
+pub fn relay_spin(spin: Instruction) -> Instruction {
+    let mut instruction = Instruction {
+        program_id: spin_relay::ID,
+        accounts: spin_relay::accounts::RelaySpin {
+            company_pool_program: company_pool::ID,
+        }
+        .to_account_metas(None),
+        data: spin_relay::instruction::RelaySpin {}.data(),
+    };
+    instruction.accounts.extend(spin.accounts);
+    instruction
+}
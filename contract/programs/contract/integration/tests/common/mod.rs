@@ -0,0 +1,524 @@
+// Shared LiteSVM harness. Each test gets a fresh VM with the compiled program
+// loaded and the global config in place, then drives instructions through the
+// builders below.
+
+#![allow(dead_code)]
+
+pub mod ix;
+
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{AccountDeserialize, AccountSerialize, AnchorDeserialize, Discriminator};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use base64::Engine;
+use company_pool::{
+    CompanyPool, CompanyPoolV2, CreationMode, ErrorCode, GlobalConfig, GlobalStats, ItemKind,
+    LeaderboardEntry, PoolConfig, PoolFlag, PoolItemInput, RecentWinner, ReconciliationReport,
+    UserTicket,
+};
+use litesvm::types::{FailedTransactionMetadata, TransactionMetadata};
+use litesvm::LiteSVM;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::fmt::Debug;
+
+pub const TICKET_PRICE: u64 = LAMPORTS_PER_SOL;
+
+// default_config sends half of every sale to the prize vault
+pub const PRIZE_SHARE: u64 = TICKET_PRICE / 2;
+pub const REVENUE_SHARE: u64 = TICKET_PRICE - PRIZE_SHARE;
+
+// LiteSVM's default fee for a single-signature transaction
+pub const TX_FEE: u64 = 5000;
+
+// Compute ceilings per instruction; a change that pushes past one should be
+// a deliberate bump here, not a surprise on mainnet
+pub const INITIALIZE_POOL_CU: u64 = 150_000;
+pub const BUY_TICKET_CU: u64 = 40_000;
+pub const SPIN_CU: u64 = 80_000;
+pub const CLAIM_CU: u64 = 40_000;
+pub const WITHDRAW_CU: u64 = 25_000;
+pub const SWEEP_CU: u64 = 25_000;
+pub const ANALYSIS_CU: u64 = 60_000;
+// What passing the optional global stats account adds to a purchase
+pub const GLOBAL_STATS_OVERHEAD_CU: u64 = 5_000;
+
+pub type TxResult = Result<TransactionMetadata, FailedTransactionMetadata>;
+
+pub struct TestContext {
+    pub svm: LiteSVM,
+    pub admin: Keypair,
+}
+
+pub struct TestPool {
+    pub address: Pubkey,
+    pub vault: Pubkey,
+    pub prize_vault: Pubkey,
+    pub stats: Pubkey,
+    pub authority: Keypair,
+}
+
+impl TestContext {
+    pub fn new() -> Self {
+        let mut svm = LiteSVM::new();
+        let program = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../../target/deploy/company_pool.so"
+        );
+        svm.add_program_from_file(company_pool::ID, program)
+            .expect("run `anchor build` before the integration tests");
+
+        let admin = Keypair::new();
+        svm.airdrop(&admin.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+        let mut ctx = TestContext { svm, admin };
+        ctx.write_global_config(false);
+        let admin_key = ctx.admin.pubkey();
+        ctx.send_as_admin(ix::initialize_global_stats(&admin_key))
+            .expect("initialize_global_stats failed");
+        ctx
+    }
+
+    // initialize_global_config checks the upgrade authority, which LiteSVM
+    // doesn't model, so the account is written directly
+    pub fn write_global_config(&mut self, program_paused: bool) {
+        let (address, bump) = Pubkey::find_program_address(&[b"global_config"], &company_pool::ID);
+        let config = GlobalConfig {
+            admin: self.admin.pubkey(),
+            program_paused,
+            bump,
+            creation_mode: CreationMode::Open,
+            treasury: self.admin.pubkey(),
+            pool_creation_fee_lamports: 0,
+        };
+        self.write_account(address, &config);
+    }
+
+    pub fn write_account<T: AccountSerialize>(&mut self, address: Pubkey, value: &T) {
+        let mut data = Vec::new();
+        value.try_serialize(&mut data).unwrap();
+        let lamports = self.svm.minimum_balance_for_rent_exemption(data.len());
+        self.svm
+            .set_account(
+                address,
+                Account {
+                    lamports,
+                    data,
+                    owner: company_pool::ID,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+    }
+
+    pub fn account<T: AccountDeserialize>(&self, address: &Pubkey) -> T {
+        let account = self.svm.get_account(address).expect("account missing");
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub fn balance(&self, address: &Pubkey) -> u64 {
+        self.svm.get_balance(address).unwrap_or(0)
+    }
+
+    pub fn warp(&mut self, seconds: i64) {
+        let mut clock: Clock = self.svm.get_sysvar();
+        clock.unix_timestamp += seconds;
+        self.svm.set_sysvar(&clock);
+    }
+
+    pub fn funded_user(&mut self, sol: u64) -> Keypair {
+        let user = Keypair::new();
+        self.svm
+            .airdrop(&user.pubkey(), sol * LAMPORTS_PER_SOL)
+            .unwrap();
+        user
+    }
+
+    // Sends one instruction paid for by the first signer
+    pub fn send(&mut self, instruction: Instruction, signers: &[&Keypair]) -> TxResult {
+        self.send_all(&[instruction], signers)
+    }
+
+    // Sends the instructions as one transaction paid for by the first signer.
+    // The blockhash is expired afterwards so an identical retry isn't rejected
+    // as a duplicate, and the next transaction lands in a later slot.
+    pub fn send_all(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> TxResult {
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signers[0].pubkey()),
+            signers,
+            self.svm.latest_blockhash(),
+        );
+        let result = self.svm.send_transaction(tx);
+        self.svm.expire_blockhash();
+        let slot = self.svm.get_sysvar::<Clock>().slot;
+        self.svm.warp_to_slot(slot + 1);
+        result
+    }
+
+    pub fn create_pool_with(
+        &mut self,
+        company_name: &str,
+        ticket_price: u64,
+        items: Vec<PoolItemInput>,
+        config: PoolConfig,
+    ) -> Result<(TestPool, TransactionMetadata), FailedTransactionMetadata> {
+        let authority = self.funded_user(10);
+        self.create_pool_as(authority, company_name, ticket_price, items, config)
+    }
+
+    // Creates the pool with a given authority, passing its pool_creator entry
+    // when it has one and the treasury when the config charges a fee
+    pub fn create_pool_as(
+        &mut self,
+        authority: Keypair,
+        company_name: &str,
+        ticket_price: u64,
+        items: Vec<PoolItemInput>,
+        config: PoolConfig,
+    ) -> Result<(TestPool, TransactionMetadata), FailedTransactionMetadata> {
+        let creator_entry = ix::pool_creator_pda(&authority.pubkey());
+        let instruction = ix::initialize_company_pool(
+            &authority.pubkey(),
+            self.svm.get_account(&creator_entry).map(|_| creator_entry),
+            self.global_config()
+                .filter(|config| config.pool_creation_fee_lamports > 0)
+                .map(|config| config.treasury),
+            ticket_price,
+            company_name,
+            items,
+            config,
+        );
+        let meta = self.send(instruction, &[&authority])?;
+
+        let address = ix::pool_pda(company_name);
+        let pool = TestPool {
+            address,
+            vault: ix::vault_pda(&address),
+            prize_vault: ix::prize_vault_pda(&address),
+            stats: ix::pool_stats_pda(&address),
+            authority,
+        };
+        Ok((pool, meta))
+    }
+
+    // None once the config account has been removed
+    pub fn global_config(&self) -> Option<GlobalConfig> {
+        let account = self.svm.get_account(&ix::global_config_pda())?;
+        Some(GlobalConfig::read_any_version(&account.data).unwrap())
+    }
+
+    pub fn pool(&self, pool: &TestPool) -> CompanyPool {
+        self.account(&pool.address)
+    }
+
+    // Zero-copy pools aren't Borsh accounts; read the struct after the discriminator
+    pub fn pool_v2(&self, address: &Pubkey) -> CompanyPoolV2 {
+        let account = self.svm.get_account(address).expect("account missing");
+        bytemuck::pod_read_unaligned(&account.data[8..8 + std::mem::size_of::<CompanyPoolV2>()])
+    }
+
+    pub fn ticket(&self, ticket: &Pubkey) -> UserTicket {
+        self.account(ticket)
+    }
+
+    // Flips fields that no instruction can reach yet, e.g. deactivating a pool
+    pub fn edit_pool(&mut self, pool: &TestPool, edit: impl FnOnce(&mut CompanyPool)) {
+        let mut state = self.pool(pool);
+        edit(&mut state);
+        let lamports = self.balance(&pool.address);
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        let mut account = self.svm.get_account(&pool.address).unwrap();
+        data.resize(account.data.len(), 0);
+        account.data = data;
+        account.lamports = lamports;
+        self.svm.set_account(pool.address, account).unwrap();
+    }
+
+    // LiteSVM loads the token programs but not the native mint account
+    // that wrapped SOL accounts refer to
+    pub fn add_native_mint(&mut self) {
+        self.set_mint(spl_token::native_mint::ID);
+    }
+
+    // A fresh mint for token prizes. Balances are written directly with
+    // add_token_account, so it has no mint authority.
+    pub fn add_mint(&mut self) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        self.set_mint(mint);
+        mint
+    }
+
+    fn set_mint(&mut self, address: Pubkey) {
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint {
+            decimals: 9,
+            is_initialized: true,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        self.set_token_program_account(address, data);
+    }
+
+    // Writes `owner`'s associated account for `mint` holding `amount`
+    pub fn add_token_account(&mut self, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Pubkey {
+        let address = get_associated_token_address(owner, mint);
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        self.set_token_program_account(address, data);
+        address
+    }
+
+    fn set_token_program_account(&mut self, address: Pubkey, data: Vec<u8>) {
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm.set_account(address, account).unwrap();
+    }
+
+    // Like edit_pool, e.g. to leave a ticket with a draw pending
+    pub fn edit_ticket(&mut self, ticket: &Pubkey, edit: impl FnOnce(&mut UserTicket)) {
+        let mut state = self.ticket(ticket);
+        edit(&mut state);
+        let mut account = self.svm.get_account(ticket).unwrap();
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        data.resize(account.data.len(), 0);
+        account.data = data;
+        self.svm.set_account(*ticket, account).unwrap();
+    }
+
+    pub fn try_buy(
+        &mut self,
+        pool: &TestPool,
+        user: &Keypair,
+    ) -> Result<Pubkey, FailedTransactionMetadata> {
+        let sold = self.pool(pool).total_tickets_sold;
+        let ticket = ix::ticket_pda(&user.pubkey(), &pool.address, sold);
+        self.send(
+            ix::buy_ticket(&user.pubkey(), &pool.address, sold, None),
+            &[user],
+        )?;
+        Ok(ticket)
+    }
+
+    // Loads the example partner program that buys tickets through CPI
+    pub fn load_checkout_example(&mut self) {
+        let program = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../../target/deploy/checkout_example.so"
+        );
+        self.svm
+            .add_program_from_file(checkout_example::ID, program)
+            .expect("run `anchor build` before the integration tests");
+    }
+
+    // Loads the CPI wrapper used to check that spins must be top-level
+    pub fn load_spin_relay(&mut self) {
+        let program = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../../target/deploy/spin_relay.so"
+        );
+        self.svm
+            .add_program_from_file(spin_relay::ID, program)
+            .expect("run `anchor build` before the integration tests");
+    }
+
+    pub fn try_spin(&mut self, pool: &TestPool, user: &Keypair, ticket: &Pubkey) -> TxResult {
+        let owner = self.ticket(ticket).owner;
+        let instruction = ix::record_spin_result(&user.pubkey(), &owner, &pool.address, ticket);
+        self.send(instruction, &[user])
+    }
+
+    pub fn try_claim(&mut self, pool: &TestPool, user: &Keypair, ticket: &Pubkey) -> TxResult {
+        self.send(
+            ix::claim_reward(&user.pubkey(), &pool.address, ticket),
+            &[user],
+        )
+    }
+
+    // Sends an instruction built for the global config's admin
+    pub fn send_as_admin(&mut self, instruction: Instruction) -> TxResult {
+        let admin = self.admin.insecure_clone();
+        self.send(instruction, &[&admin])
+    }
+
+    pub fn try_set_pool_flag(
+        &mut self,
+        pool: &TestPool,
+        signer: &Keypair,
+        flag: PoolFlag,
+        enabled: bool,
+    ) -> TxResult {
+        self.send(
+            ix::admin_set_pool_flag(&signer.pubkey(), &pool.address, flag, enabled),
+            &[signer],
+        )
+    }
+
+    // Sets or clears a flag as the global config's admin
+    pub fn set_pool_flag(&mut self, pool: &TestPool, flag: PoolFlag, enabled: bool) {
+        let admin = self.admin.pubkey();
+        self.send_as_admin(ix::admin_set_pool_flag(
+            &admin,
+            &pool.address,
+            flag,
+            enabled,
+        ))
+        .unwrap();
+    }
+
+    pub fn try_withdraw(&mut self, pool: &TestPool, signer: &Keypair, amount: u64) -> TxResult {
+        self.send(
+            ix::withdraw_funds_from_vault(&signer.pubkey(), None, &pool.address, amount),
+            &[signer],
+        )
+    }
+
+    pub fn reconcile(&mut self, pool: &TestPool) -> ReconciliationReport {
+        let payer = self.funded_user(1);
+        let meta = self
+            .send(ix::reconcile(&pool.address), &[&payer])
+            .expect("reconcile failed");
+        ReconciliationReport::deserialize(&mut meta.return_data.data.as_slice()).unwrap()
+    }
+
+    pub fn global_stats(&mut self) -> GlobalStats {
+        let payer = self.funded_user(1);
+        let meta = self
+            .send(ix::get_global_stats(), &[&payer])
+            .expect("get_global_stats failed");
+        GlobalStats::deserialize(&mut meta.return_data.data.as_slice()).unwrap()
+    }
+
+    pub fn recent_winners(&mut self, pool: &TestPool) -> Vec<RecentWinner> {
+        let payer = self.funded_user(1);
+        let meta = self
+            .send(ix::get_recent_winners(&pool.address, 0), &[&payer])
+            .expect("get_recent_winners failed");
+        Vec::<WinnerEntry>::deserialize(&mut meta.return_data.data.as_slice()).unwrap()
+    }
+
+    pub fn leaderboard(&mut self, pool: &TestPool) -> Vec<LeaderboardEntry> {
+        let payer = self.funded_user(1);
+        let meta = self
+            .send(ix::get_leaderboard(&pool.address), &[&payer])
+            .expect("get_leaderboard failed");
+        Vec::<LeaderboardEntry>::deserialize(&mut meta.return_data.data.as_slice()).unwrap()
+    }
+
+    // Sends the sweep's beneficiary account along when the pool has one
+    pub fn try_sweep(&mut self, pool: &TestPool, signer: &Keypair) -> TxResult {
+        let beneficiary = self.pool(pool).beneficiary();
+        self.send(
+            ix::sweep_vault(&signer.pubkey(), None, &pool.address, beneficiary),
+            &[signer],
+        )
+    }
+}
+
+// Pool with a fresh name and the default config, priced at TICKET_PRICE
+pub fn create_pool(ctx: &mut TestContext, items: Vec<PoolItemInput>) -> TestPool {
+    let name = format!("Pool {}", &Keypair::new().pubkey().to_string()[..8]);
+    ctx.create_pool_with(&name, TICKET_PRICE, items, default_config())
+        .expect("pool init failed")
+        .0
+}
+
+pub fn buy(ctx: &mut TestContext, pool: &TestPool, user: &Keypair) -> Pubkey {
+    ctx.try_buy(pool, user).expect("buy failed")
+}
+
+pub fn default_config() -> PoolConfig {
+    PoolConfig {
+        max_ev_bps: 9000,
+        prize_allocation_bps: 5000,
+        ..PoolConfig::default()
+    }
+}
+
+pub fn item(name: &str, price: u64) -> PoolItemInput {
+    PoolItemInput {
+        image: "https://example.com/item.png".to_string(),
+        price,
+        name: name.to_string(),
+        description: format!("{} prize", name),
+        item_kind: ItemKind::SolPayout,
+        jackpot: false,
+        manual_weight: 0,
+        tier: None,
+        sponsor: None,
+        sponsor_share_bps: 0,
+        reveal_hash: None,
+        reward_mint: None,
+        reward_amount: 0,
+    }
+}
+
+// `expected` is a program ErrorCode, or an anchor_lang one for framework checks
+pub fn assert_error<T, E: Into<u32> + Debug>(
+    result: Result<T, FailedTransactionMetadata>,
+    expected: E,
+) {
+    assert_error_at(result, 0, expected);
+}
+
+// Like assert_error, for the instruction at `index` of a multi-instruction
+// transaction
+pub fn assert_error_at<T, E: Into<u32> + Debug>(
+    result: Result<T, FailedTransactionMetadata>,
+    index: u8,
+    expected: E,
+) {
+    let name = format!("{:?}", expected);
+    let code: u32 = expected.into();
+    match result {
+        Ok(_) => panic!("expected {}, transaction succeeded", name),
+        Err(failed) => assert_eq!(
+            failed.err,
+            TransactionError::InstructionError(index, InstructionError::Custom(code)),
+            "expected {}; logs: {:#?}",
+            name,
+            failed.meta.logs
+        ),
+    }
+}
+
+// Events of type T the transaction emitted, in order. emit! logs each one as
+// base64 after "Program data: ", discriminator first.
+pub fn events<T: AnchorDeserialize + Discriminator>(meta: &TransactionMetadata) -> Vec<T> {
+    meta.logs
+        .iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+        .filter(|data| data.starts_with(T::DISCRIMINATOR))
+        .map(|data| T::try_from_slice(&data[T::DISCRIMINATOR.len()..]).unwrap())
+        .collect()
+}
+
+pub fn assert_cu_below(meta: &TransactionMetadata, ceiling: u64) {
+    assert!(
+        meta.compute_units_consumed <= ceiling,
+        "used {} CU, ceiling is {}",
+        meta.compute_units_consumed,
+        ceiling
+    );
+}
@@ -0,0 +1,629 @@
+// Negative paths for the core lifecycle instructions. Errors owned by the
+// voucher, gamble, trade-in and NFT flows belong with those flows' builders.
+
+mod common;
+
+use common::*;
+use company_pool::{ErrorCode, PoolConfig, PoolFlag, PoolItemInput};
+use solana_sdk::clock::Clock;
+use solana_sdk::hash::hash;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const PRIZE: u64 = LAMPORTS_PER_SOL / 2;
+
+fn assert_init_error(
+    company_name: &str,
+    ticket_price: u64,
+    items: Vec<PoolItemInput>,
+    config: PoolConfig,
+    expected: ErrorCode,
+) {
+    let mut ctx = TestContext::new();
+    assert_error(
+        ctx.create_pool_with(company_name, ticket_price, items, config),
+        expected,
+    );
+}
+
+// Pool with one bought ticket, ready for spin and claim checks
+fn pool_with_ticket(config: PoolConfig) -> (TestContext, TestPool, Keypair, Pubkey) {
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with("Errors", TICKET_PRICE, vec![item("Prize", PRIZE)], config)
+        .unwrap();
+    let player = ctx.funded_user(5);
+    let ticket = buy(&mut ctx, &pool, &player);
+    (ctx, pool, player, ticket)
+}
+
+#[test]
+fn initialize_rejects_invalid_input() {
+    let prize = || vec![item("Prize", PRIZE)];
+
+    assert_init_error(
+        "Pool",
+        0,
+        prize(),
+        default_config(),
+        ErrorCode::InvalidTicketPrice,
+    );
+    assert_init_error(
+        "Pool",
+        TICKET_PRICE,
+        vec![],
+        default_config(),
+        ErrorCode::NoItemsProvided,
+    );
+    assert_init_error(
+        &"N".repeat(51),
+        TICKET_PRICE,
+        prize(),
+        default_config(),
+        ErrorCode::CompanyNameTooLong,
+    );
+    assert_init_error(
+        "Pool",
+        TICKET_PRICE,
+        (0..11)
+            .map(|i| item(&format!("Item {}", i), PRIZE))
+            .collect(),
+        default_config(),
+        ErrorCode::TooManyItems,
+    );
+    assert_init_error(
+        "Pool",
+        TICKET_PRICE,
+        vec![item("Free", 0)],
+        default_config(),
+        ErrorCode::InvalidItemPrice,
+    );
+    assert_init_error(
+        "Pool",
+        TICKET_PRICE,
+        vec![item(&"I".repeat(51), PRIZE)],
+        default_config(),
+        ErrorCode::ItemNameTooLong,
+    );
+    assert_init_error(
+        "Pool",
+        TICKET_PRICE,
+        vec![item("Prize", PRIZE), item("Prize", PRIZE)],
+        default_config(),
+        ErrorCode::DuplicateItemName,
+    );
+    assert_init_error(
+        "Pool",
+        TICKET_PRICE,
+        prize(),
+        PoolConfig {
+            max_tickets_per_user: Some(0),
+            ..default_config()
+        },
+        ErrorCode::InvalidPoolConfig,
+    );
+    assert_init_error(
+        "Pool",
+        TICKET_PRICE,
+        vec![item("Car", 2 * TICKET_PRICE)],
+        default_config(),
+        ErrorCode::ExpectedValueTooHigh,
+    );
+}
+
+#[test]
+fn paused_program_blocks_init_buy_spin_and_withdraw() {
+    let (mut ctx, pool, player, ticket) = pool_with_ticket(default_config());
+    ctx.write_global_config(true);
+
+    assert_error(
+        ctx.create_pool_with(
+            "Paused",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            default_config(),
+        ),
+        ErrorCode::ProgramPaused,
+    );
+
+    assert_error(ctx.try_buy(&pool, &player), ErrorCode::ProgramPaused);
+    assert_error(
+        ctx.try_spin(&pool, &player, &ticket),
+        ErrorCode::ProgramPaused,
+    );
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, 1),
+        ErrorCode::ProgramPaused,
+    );
+}
+
+#[test]
+fn inactive_pool_rejects_buy_spin_claim_and_withdraw() {
+    let (mut ctx, pool, player, ticket) = pool_with_ticket(default_config());
+    ctx.edit_pool(&pool, |state| state.active = false);
+
+    assert_error(ctx.try_buy(&pool, &player), ErrorCode::PoolInactive);
+    assert_error(
+        ctx.try_spin(&pool, &player, &ticket),
+        ErrorCode::PoolInactive,
+    );
+    assert_error(
+        ctx.try_claim(&pool, &player, &ticket),
+        ErrorCode::PoolInactive,
+    );
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, 1),
+        ErrorCode::PoolInactive,
+    );
+}
+
+#[test]
+fn buy_enforces_pool_limits() {
+    let (mut ctx, pool, player, _) = pool_with_ticket(PoolConfig {
+        max_tickets_per_user: Some(1),
+        ..default_config()
+    });
+    assert_error(ctx.try_buy(&pool, &player), ErrorCode::PurchaseLimitReached);
+
+    let (mut ctx, pool, player, _) = pool_with_ticket(PoolConfig {
+        purchase_cooldown_seconds: Some(3600),
+        ..default_config()
+    });
+    assert_error(
+        ctx.try_buy(&pool, &player),
+        ErrorCode::PurchaseCooldownActive,
+    );
+
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Whitelisted",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                whitelist_enabled: true,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let outsider = ctx.funded_user(5);
+    assert_error(ctx.try_buy(&pool, &outsider), ErrorCode::NotWhitelisted);
+}
+
+#[test]
+fn spin_rejects_wrong_signer_and_reused_ticket() {
+    let (mut ctx, pool, player, ticket) = pool_with_ticket(default_config());
+    let stranger = ctx.funded_user(1);
+
+    assert_error(
+        ctx.try_spin(&pool, &stranger, &ticket),
+        ErrorCode::NotTicketOwner,
+    );
+
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    assert_error(
+        ctx.try_spin(&pool, &player, &ticket),
+        ErrorCode::TicketAlreadyUsed,
+    );
+}
+
+#[test]
+fn spin_operator_spins_for_owners_but_never_claims() {
+    let operator = Keypair::new();
+    let (mut ctx, pool, player, ticket) = pool_with_ticket(PoolConfig {
+        spin_operator: Some(operator.pubkey()),
+        ..default_config()
+    });
+    ctx.svm
+        .airdrop(&operator.pubkey(), LAMPORTS_PER_SOL)
+        .unwrap();
+    let impostor = ctx.funded_user(1);
+    let second = buy(&mut ctx, &pool, &player);
+
+    assert_error(
+        ctx.try_spin(&pool, &impostor, &ticket),
+        ErrorCode::NotTicketOwner,
+    );
+    ctx.try_spin(&pool, &operator, &ticket).unwrap();
+    assert_eq!(ctx.ticket(&ticket).owner, player.pubkey());
+    assert_error(
+        ctx.try_claim(&pool, &operator, &ticket),
+        ErrorCode::NotTicketOwner,
+    );
+
+    // Owners keep spinning their own tickets next to the operator
+    ctx.try_spin(&pool, &player, &second).unwrap();
+
+    // Only the pool authority rotates the operator
+    assert_error(
+        ctx.send(
+            ix::set_spin_operator(&impostor.pubkey(), &pool.address, Some(impostor.pubkey())),
+            &[&impostor],
+        ),
+        ErrorCode::Unauthorized,
+    );
+    ctx.send(
+        ix::set_spin_operator(&pool.authority.pubkey(), &pool.address, None),
+        &[&pool.authority],
+    )
+    .unwrap();
+    let third = buy(&mut ctx, &pool, &player);
+    assert_error(
+        ctx.try_spin(&pool, &operator, &third),
+        ErrorCode::NotTicketOwner,
+    );
+}
+
+#[test]
+fn spin_rejects_cpi_wrapped_calls() {
+    let (mut ctx, pool, player, ticket) = pool_with_ticket(default_config());
+    ctx.load_spin_relay();
+    let spin = ix::record_spin_result(&player.pubkey(), &player.pubkey(), &pool.address, &ticket);
+
+    assert_error(
+        ctx.send(ix::relay_spin(spin.clone()), &[&player]),
+        ErrorCode::CpiNotAllowed,
+    );
+    assert!(!ctx.ticket(&ticket).used);
+
+    ctx.send(spin, &[&player]).unwrap();
+    assert!(ctx.ticket(&ticket).used);
+}
+
+#[test]
+fn cpi_purchases_need_the_pools_opt_in() {
+    let mut ctx = TestContext::new();
+    ctx.load_checkout_example();
+    let customer = ctx.funded_user(5);
+    let checkout = |ctx: &mut TestContext, pool: &TestPool| {
+        let sold = ctx.pool(pool).total_tickets_sold;
+        let ticket = ix::ticket_pda(&customer.pubkey(), &pool.address, sold);
+        ctx.send(
+            ix::checkout_with_ticket(&customer.pubkey(), &pool.address, sold),
+            &[&customer],
+        )
+        .map(|_| ticket)
+    };
+
+    let (closed, _) = ctx
+        .create_pool_with(
+            "Direct Only",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            default_config(),
+        )
+        .unwrap();
+    assert_error(checkout(&mut ctx, &closed), ErrorCode::CpiPurchasesDisabled);
+    assert_eq!(ctx.pool(&closed).total_tickets_sold, 0);
+
+    let (open, _) = ctx
+        .create_pool_with(
+            "Partner Checkout",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                allow_cpi_purchases: true,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let ticket = checkout(&mut ctx, &open).unwrap();
+    assert_eq!(ctx.ticket(&ticket).owner, customer.pubkey());
+    assert_eq!(ctx.pool(&open).total_tickets_sold, 1);
+    // checkout_with_ticket tags the ticket with a hash of its order id
+    assert_eq!(
+        ctx.ticket(&ticket).external_id,
+        Some(hash(&7u64.to_le_bytes()).to_bytes())
+    );
+
+    // The ticket is the customer's to spin and claim directly
+    ctx.try_spin(&open, &customer, &ticket).unwrap();
+    ctx.try_claim(&open, &customer, &ticket).unwrap();
+}
+
+#[test]
+fn claim_rejects_unspun_foreign_and_repeated_claims() {
+    let (mut ctx, pool, player, ticket) = pool_with_ticket(default_config());
+    let stranger = ctx.funded_user(1);
+
+    assert_error(
+        ctx.try_claim(&pool, &player, &ticket),
+        ErrorCode::TicketNotUsed,
+    );
+
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    assert_error(
+        ctx.try_claim(&pool, &stranger, &ticket),
+        ErrorCode::NotTicketOwner,
+    );
+
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+    assert_error(
+        ctx.try_claim(&pool, &player, &ticket),
+        ErrorCode::RewardAlreadyClaimed,
+    );
+}
+
+#[test]
+fn spin_and_claim_reject_bundling_with_the_previous_step() {
+    let (mut ctx, pool, player, ticket) = pool_with_ticket(default_config());
+
+    // Buying and spinning in one transaction would let it revert bad draws
+    let sold = ctx.pool(&pool).total_tickets_sold;
+    let bundled_ticket = ix::ticket_pda(&player.pubkey(), &pool.address, sold);
+    let buy_and_spin = [
+        ix::buy_ticket(&player.pubkey(), &pool.address, sold, None),
+        ix::record_spin_result(
+            &player.pubkey(),
+            &player.pubkey(),
+            &pool.address,
+            &bundled_ticket,
+        ),
+    ];
+    assert_error_at(
+        ctx.send_all(&buy_and_spin, &[&player]),
+        1,
+        ErrorCode::SpinTooSoon,
+    );
+    assert!(ctx.svm.get_account(&bundled_ticket).is_none());
+
+    // Likewise spinning and claiming
+    let spin_and_claim = [
+        ix::record_spin_result(&player.pubkey(), &player.pubkey(), &pool.address, &ticket),
+        ix::claim_reward(&player.pubkey(), &pool.address, &ticket),
+    ];
+    assert_error_at(
+        ctx.send_all(&spin_and_claim, &[&player]),
+        1,
+        ErrorCode::ClaimTooSoon,
+    );
+    assert!(!ctx.ticket(&ticket).used);
+
+    // Separate transactions land in later slots and go through
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+    assert!(ctx.ticket(&ticket).reward_claimed);
+}
+
+#[test]
+fn withdraw_rejects_bad_amounts_and_signers() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, 1),
+        ErrorCode::NoFundsAvailable,
+    );
+
+    let player = ctx.funded_user(5);
+    buy(&mut ctx, &pool, &player);
+
+    let stranger = ctx.funded_user(1);
+    assert_error(
+        ctx.try_withdraw(&pool, &stranger, 1),
+        ErrorCode::UnauthorizedWithdrawal,
+    );
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, 0),
+        ErrorCode::InvalidAmount,
+    );
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, TICKET_PRICE + 1),
+        ErrorCode::InsufficientFunds,
+    );
+}
+
+#[test]
+fn frozen_pool_blocks_sales_and_withdrawals_but_pays_out() {
+    let (mut ctx, pool, player, ticket) = pool_with_ticket(default_config());
+    ctx.set_pool_flag(&pool, PoolFlag::Frozen, true);
+    assert!(ctx.pool(&pool).has_flag(PoolFlag::Frozen));
+
+    assert_error(ctx.try_buy(&pool, &player), ErrorCode::PoolFrozen);
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, REVENUE_SHARE),
+        ErrorCode::PoolFrozen,
+    );
+
+    // Existing ticket holders still spin and claim
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+    assert!(ctx.ticket(&ticket).reward_claimed);
+
+    // Clearing the flag reopens the pool
+    ctx.set_pool_flag(&pool, PoolFlag::Frozen, false);
+    buy(&mut ctx, &pool, &player);
+    ctx.try_withdraw(&pool, &pool.authority, REVENUE_SHARE)
+        .unwrap();
+}
+
+#[test]
+fn flagged_and_verified_pools_keep_operating() {
+    let (mut ctx, pool, player, ticket) = pool_with_ticket(default_config());
+    ctx.set_pool_flag(&pool, PoolFlag::Flagged, true);
+    ctx.set_pool_flag(&pool, PoolFlag::Verified, true);
+    let state = ctx.pool(&pool);
+    assert_eq!(
+        state.pool_flags,
+        PoolFlag::Flagged.bit() | PoolFlag::Verified.bit()
+    );
+    assert!(!state.has_flag(PoolFlag::Frozen));
+
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+    buy(&mut ctx, &pool, &player);
+    ctx.try_withdraw(&pool, &pool.authority, REVENUE_SHARE)
+        .unwrap();
+}
+
+#[test]
+fn only_the_config_admin_sets_pool_flags() {
+    let (mut ctx, pool, player, _) = pool_with_ticket(default_config());
+    let authority = pool.authority.insecure_clone();
+
+    for signer in [&player, &authority] {
+        assert_error(
+            ctx.try_set_pool_flag(&pool, signer, PoolFlag::Verified, true),
+            ErrorCode::Unauthorized,
+        );
+    }
+    assert_eq!(ctx.pool(&pool).pool_flags, 0);
+}
+
+#[test]
+fn item_boosts_are_validated() {
+    let mut ctx = TestContext::new();
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Boosted",
+            TICKET_PRICE,
+            vec![
+                item("Sticker", PRIZE / 10),
+                item("Hoodie", PRIZE / 2),
+                item("MacBook", PRIZE),
+            ],
+            PoolConfig {
+                ends_at: Some(now + 86400),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let authority = pool.authority.insecure_clone();
+    let stranger = ctx.funded_user(1);
+    let boost = |ctx: &mut TestContext, signer: &Keypair, item_id, multiplier, ends_at| {
+        ctx.send(
+            ix::set_item_boost(
+                &signer.pubkey(),
+                &pool.address,
+                item_id,
+                multiplier,
+                now,
+                ends_at,
+            ),
+            &[signer],
+        )
+    };
+
+    assert_error(
+        boost(&mut ctx, &stranger, 2, 20000, now + 3600),
+        ErrorCode::Unauthorized,
+    );
+    assert_error(
+        boost(&mut ctx, &authority, 9, 20000, now + 3600),
+        ErrorCode::InvalidItemIndex,
+    );
+    // A boost has to raise the odds, and no further than certainty
+    for multiplier in [10000, 10000 * 10000] {
+        assert_error(
+            boost(&mut ctx, &authority, 2, multiplier, now + 3600),
+            ErrorCode::InvalidBoost,
+        );
+    }
+    // The window can't be over already or outlast the pool
+    for ends_at in [now, now + 86400 + 1] {
+        assert_error(
+            boost(&mut ctx, &authority, 2, 20000, ends_at),
+            ErrorCode::InvalidBoostWindow,
+        );
+    }
+
+    boost(&mut ctx, &authority, 2, 20000, now + 86400).unwrap();
+    boost(&mut ctx, &authority, 1, 15000, now + 3600).unwrap();
+    assert_error(
+        boost(&mut ctx, &authority, 0, 20000, now + 3600),
+        ErrorCode::TooManyBoosts,
+    );
+    // Re-boosting an item replaces its own boost, and an expired one frees
+    // its slot
+    boost(&mut ctx, &authority, 2, 30000, now + 86400).unwrap();
+    ctx.warp(3600);
+    boost(&mut ctx, &authority, 0, 20000, now + 7200).unwrap();
+    let boosts = ctx.pool(&pool).boosts;
+    assert_eq!(
+        boosts.map(|boost| (boost.item_id, boost.multiplier_bps)),
+        [(2, 30000), (0, 20000)]
+    );
+}
+
+#[test]
+fn item_price_floor_holds_at_init_and_on_update() {
+    // Floored at the ticket price; the negative-EV opt-out keeps the EV cap
+    // from rejecting the pricier items first
+    let config = PoolConfig {
+        min_item_price: TICKET_PRICE,
+        allow_negative_ev: true,
+        ..default_config()
+    };
+    assert_init_error(
+        "Pool",
+        TICKET_PRICE,
+        vec![item("Cheap", TICKET_PRICE - 1), item("Fair", TICKET_PRICE)],
+        config.clone(),
+        ErrorCode::ItemPriceTooLow,
+    );
+
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Floored",
+            TICKET_PRICE,
+            vec![item("Fair", TICKET_PRICE), item("Rich", 2 * TICKET_PRICE)],
+            config,
+        )
+        .unwrap();
+    let authority = pool.authority.pubkey();
+    let reprice = |ctx: &mut TestContext, price: u64| {
+        ctx.send(
+            ix::update_pool_item(&authority, &pool.address, 1, None, None, Some(price)),
+            &[&pool.authority],
+        )
+    };
+    assert_error(
+        reprice(&mut ctx, TICKET_PRICE - 1),
+        ErrorCode::ItemPriceTooLow,
+    );
+    reprice(&mut ctx, TICKET_PRICE + 1).unwrap();
+    reprice(&mut ctx, TICKET_PRICE).unwrap();
+    assert_eq!(ctx.pool(&pool).items[1].price, TICKET_PRICE);
+
+    // Without a floor, items below the ticket price are fine
+    create_pool(&mut ctx, vec![item("Sticker", TICKET_PRICE / 100)]);
+}
+
+#[test]
+fn items_that_round_to_zero_odds_are_rejected() {
+    // A million-fold value spread leaves the flagship under half a basis point
+    let flagship = 1_000_000 * PRIZE / 1000;
+    assert_init_error(
+        "Pool",
+        TICKET_PRICE,
+        vec![item("Sticker", PRIZE / 1000), item("Flagship", flagship)],
+        default_config(),
+        ErrorCode::ZeroProbabilityItem,
+    );
+
+    // A tenfold spread is fine, until a price edit widens it again
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Spread",
+            TICKET_PRICE,
+            vec![item("Sticker", PRIZE / 10), item("Flagship", PRIZE)],
+            default_config(),
+        )
+        .unwrap();
+    let authority = pool.authority.pubkey();
+    let result = ctx.send(
+        ix::update_pool_item(&authority, &pool.address, 0, None, None, Some(1)),
+        &[&pool.authority],
+    );
+    // The log names the item the error code can't
+    let logs = &result.as_ref().unwrap_err().meta.logs;
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Item 1 rounds to a 0 bp probability")));
+    assert_error(result, ErrorCode::ZeroProbabilityItem);
+    let state = ctx.pool(&pool);
+    assert!(state.items.iter().all(|item| item.probability > 0));
+}
@@ -0,0 +1,4144 @@
+mod common;
+
+use anchor_lang::{AnchorDeserialize, AnchorSerialize, Discriminator, Space};
+use anchor_spl::token::TokenAccount;
+use common::*;
+use company_pool::probability::{
+    probability_in_spins, spin_chance, WeightedProbabilityCalculator, ODDS_SCALE,
+};
+use company_pool::{
+    migration, AccountMigratedEvent, Achievement, AchievementRewardClaimedEvent,
+    AchievementUnlockedEvent, BundleDefinition, CloneOverrides, CompanyPool, CompanyPoolV2,
+    CreationMode, DrawTable, ErrorCode, FundsWithdrawnEvent, GlobalConfig, InsuranceClaimedEvent,
+    ItemBoostSetEvent, ItemKind, ItemRevealedEvent, ItemTier, Leaderboard, LegacyUserTicket,
+    LegacyWonItem, MegaJackpot, MegaJackpotClaimedEvent, MegaJackpotWonEvent, PendingReveal,
+    PoolAnalysisEvent, PoolArchive, PoolArchivedEvent, PoolClonedEvent, PoolClosedEvent,
+    PoolConfig, PoolInitializedEvent, PoolItemInput, PoolMode, PoolStats, PoolV2Config,
+    ProbabilityAnalysisEvent, RaffleResult, RewardClaimedEvent, SeasonArchive, SeasonResetFlags,
+    SeasonStartedEvent, ServerSeedCommitment, ServerSeedCommittedEvent, ServerSeedRevealedEvent,
+    SharedRewardPaidEvent, SharedTicket, SharedTicketPurchasedEvent, SpinCancelledEvent,
+    SpinInsurance, SpinOdds, SpinOddsEvent, SpinResultEvent, SpinVerifiedEvent, SpinVoidedEvent,
+    StreakMilestone, StreakUpdatedEvent, TicketClosedEvent, TicketPurchasedEvent, TicketShare,
+    TicketState, UserPoolState, UserTicket, VaultSweptEvent, WeightingMode,
+};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::hash::Hash;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::slot_hashes::SlotHashes;
+
+const PRIZE: u64 = LAMPORTS_PER_SOL / 2;
+
+#[test]
+fn full_lifecycle_moves_lamports_and_state() {
+    let mut ctx = TestContext::new();
+    let rent_reserve = ctx.svm.minimum_balance_for_rent_exemption(0);
+
+    // Init: a single item, so every spin lands on it
+    let (pool, meta) = ctx
+        .create_pool_with(
+            "Lifecycle",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            default_config(),
+        )
+        .unwrap();
+    assert_cu_below(&meta, INITIALIZE_POOL_CU);
+
+    let state = ctx.pool(&pool);
+    assert!(state.active);
+    assert_eq!(state.authority, pool.authority.pubkey());
+    assert_eq!(state.items[0].probability, 10000);
+    assert_eq!(state.vault_rent_reserve, rent_reserve);
+    assert_eq!(ctx.balance(&pool.vault), rent_reserve);
+    assert_eq!(ctx.balance(&pool.prize_vault), rent_reserve);
+
+    // Buy
+    let player = ctx.funded_user(5);
+    let ticket_key = buy(&mut ctx, &pool, &player);
+
+    let state = ctx.pool(&pool);
+    assert_eq!(state.total_tickets_sold, 1);
+    assert_eq!(state.total_funds, REVENUE_SHARE);
+    assert_eq!(ctx.balance(&pool.vault), rent_reserve + REVENUE_SHARE);
+    assert_eq!(ctx.balance(&pool.prize_vault), rent_reserve + PRIZE_SHARE);
+
+    let ticket = ctx.ticket(&ticket_key);
+    assert_eq!(ticket.owner, player.pubkey());
+    assert_eq!(ticket.price_paid, TICKET_PRICE);
+    assert!(!ticket.used);
+
+    let user_state: UserPoolState =
+        ctx.account(&ix::user_pool_state_pda(&pool.address, &player.pubkey()));
+    assert_eq!(user_state.tickets_bought, 1);
+
+    // Spin
+    let meta = ctx.try_spin(&pool, &player, &ticket_key).unwrap();
+    assert_cu_below(&meta, SPIN_CU);
+
+    let ticket = ctx.ticket(&ticket_key);
+    assert!(ticket.used);
+    assert_eq!(ticket.won_item.as_ref().unwrap().price, PRIZE);
+    assert_eq!(ctx.pool(&pool).outstanding_liabilities, PRIZE);
+
+    let stats: PoolStats = ctx.account(&pool.stats);
+    assert_eq!(stats.total_spins, 1);
+
+    let winners = ctx.recent_winners(&pool);
+    assert_eq!(winners.len(), 1);
+    assert_eq!(winners[0].winner, player.pubkey());
+    assert_eq!(winners[0].value, PRIZE);
+
+    // Claim
+    let player_before = ctx.balance(&player.pubkey());
+    let meta = ctx.try_claim(&pool, &player, &ticket_key).unwrap();
+    assert_cu_below(&meta, CLAIM_CU);
+
+    // The player pays the fee, so allow for it
+    let received = ctx.balance(&player.pubkey()) + TX_FEE - player_before;
+    assert_eq!(received, PRIZE);
+    assert_eq!(
+        ctx.balance(&pool.prize_vault),
+        rent_reserve + PRIZE_SHARE - PRIZE
+    );
+    assert_eq!(ctx.balance(&pool.vault), rent_reserve + REVENUE_SHARE);
+
+    let state = ctx.pool(&pool);
+    assert!(ctx.ticket(&ticket_key).reward_claimed);
+    assert_eq!(state.outstanding_liabilities, 0);
+    assert_eq!(state.total_funds, REVENUE_SHARE);
+
+    // Withdraw everything above the rent reserve
+    let authority_before = ctx.balance(&pool.authority.pubkey());
+    let meta = ctx
+        .try_withdraw(&pool, &pool.authority, REVENUE_SHARE)
+        .unwrap();
+    assert_cu_below(&meta, WITHDRAW_CU);
+
+    assert_eq!(
+        ctx.balance(&pool.authority.pubkey()) + TX_FEE - authority_before,
+        REVENUE_SHARE
+    );
+    assert_eq!(ctx.balance(&pool.vault), rent_reserve);
+    assert_eq!(ctx.pool(&pool).total_funds, 0);
+
+    // Every lamport that moved went through the ledger
+    let state = ctx.pool(&pool);
+    assert_eq!(state.total_ticket_revenue, TICKET_PRICE);
+    assert_eq!(state.total_rewards_paid, PRIZE);
+    assert_eq!(state.total_withdrawn, REVENUE_SHARE);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+#[test]
+fn buy_stays_under_compute_ceiling() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+
+    let sold = ctx.pool(&pool).total_tickets_sold;
+    let meta = ctx
+        .send(
+            ix::buy_ticket(&player.pubkey(), &pool.address, sold, None),
+            &[&player],
+        )
+        .unwrap();
+    assert_cu_below(&meta, BUY_TICKET_CU);
+}
+
+#[test]
+fn external_ids_follow_the_ticket_into_its_events() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+    let order = [0x42; 32];
+
+    let ticket = ix::ticket_pda(&player.pubkey(), &pool.address, 0);
+    let bought = ctx
+        .send(
+            ix::buy_ticket(&player.pubkey(), &pool.address, 0, Some(order)),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(ctx.ticket(&ticket).external_id, Some(order));
+    let purchases: Vec<TicketPurchasedEvent> = events(&bought);
+    assert_eq!(purchases.len(), 1);
+    assert_eq!(purchases[0].external_id, Some(order));
+
+    let spun = ctx.try_spin(&pool, &player, &ticket).unwrap();
+    let spins: Vec<SpinResultEvent> = events(&spun);
+    assert_eq!(spins[0].external_id, Some(order));
+    let claimed = ctx.try_claim(&pool, &player, &ticket).unwrap();
+    let claims: Vec<RewardClaimedEvent> = events(&claimed);
+    assert_eq!(claims[0].external_id, Some(order));
+
+    // Left out, it stays empty
+    let plain = buy(&mut ctx, &pool, &player);
+    assert_eq!(ctx.ticket(&plain).external_id, None);
+}
+
+// Expiry cranks are permissionless: anyone can expire a stale reward for a
+// bounty, once, and retire a pool whose sales and claim window are over
+#[test]
+fn cranks_expire_stale_rewards_and_retire_ended_pools() {
+    let mut ctx = TestContext::new();
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Cranked",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                claim_deadline_seconds: Some(60),
+                ends_at: Some(now + 100),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    let cranker = ctx.funded_user(1);
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+
+    let crank_reward = |ctx: &mut TestContext| {
+        ctx.send(
+            ix::crank_expire_reward(&cranker.pubkey(), &pool.address, &ticket),
+            &[&cranker],
+        )
+    };
+    assert_error(crank_reward(&mut ctx), ErrorCode::RewardNotExpired);
+
+    ctx.warp(61);
+    let before = ctx.balance(&cranker.pubkey());
+    crank_reward(&mut ctx).unwrap();
+    let bounty = CompanyPool::CRANK_BOUNTY_LAMPORTS;
+    assert_eq!(ctx.balance(&cranker.pubkey()) + TX_FEE - before, bounty);
+    assert!(ctx.ticket(&ticket).reward_expired);
+    let state = ctx.pool(&pool);
+    assert_eq!(state.outstanding_liabilities, 0);
+    assert_eq!(state.total_funds, REVENUE_SHARE - bounty);
+
+    // The same ticket pays no second bounty
+    assert_error(crank_reward(&mut ctx), ErrorCode::RewardAlreadyReclaimed);
+    assert_eq!(ctx.pool(&pool).total_funds, REVENUE_SHARE - bounty);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+
+    // Sales stop at the end time, the pool retires after the claim window
+    let crank_pool = |ctx: &mut TestContext| {
+        ctx.send(
+            ix::crank_expire_pool(&cranker.pubkey(), &pool.address),
+            &[&cranker],
+        )
+    };
+    assert_error(crank_pool(&mut ctx), ErrorCode::PoolNotExpired);
+    ctx.warp(40);
+    assert_error(ctx.try_buy(&pool, &player), ErrorCode::PoolEnded);
+    assert_error(crank_pool(&mut ctx), ErrorCode::PoolNotExpired);
+
+    ctx.warp(60);
+    assert_error(crank_pool(&mut ctx), ErrorCode::PoolNotSettled);
+    ctx.try_withdraw(&pool, &pool.authority, REVENUE_SHARE - bounty)
+        .unwrap();
+    crank_pool(&mut ctx).unwrap();
+    assert!(!ctx.pool(&pool).active);
+}
+
+// Closing a retired pool hands back what its vaults were funded with at
+// creation, whatever the rent sysvar says by then, plus any prize budget no
+// win used. The ledger counts the same reserve, so it balances right up to
+// the close.
+#[test]
+fn closing_a_retired_pool_returns_the_recorded_rent_reserve() {
+    let mut ctx = TestContext::new();
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Closed",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                ends_at: Some(now + 100),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let rent_reserve = ctx.pool(&pool).vault_rent_reserve;
+    assert_eq!(rent_reserve, ctx.svm.minimum_balance_for_rent_exemption(0));
+    let player = ctx.funded_user(5);
+    buy(&mut ctx, &pool, &player);
+
+    // Rent gets cheaper after creation; the vaults still hold the old reserve
+    let mut rent: Rent = ctx.svm.get_sysvar();
+    rent.lamports_per_byte_year /= 2;
+    ctx.svm.set_sysvar(&rent);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+
+    let close = |ctx: &mut TestContext| {
+        ctx.send(
+            ix::close_pool(&pool.authority.pubkey(), &pool.address),
+            &[&pool.authority],
+        )
+    };
+    assert_error(close(&mut ctx), ErrorCode::PoolStillActive);
+
+    ctx.warp(101);
+    ctx.try_withdraw(&pool, &pool.authority, REVENUE_SHARE)
+        .unwrap();
+    assert_eq!(ctx.balance(&pool.vault), rent_reserve);
+    ctx.send(
+        ix::crank_expire_pool(&player.pubkey(), &pool.address),
+        &[&player],
+    )
+    .unwrap();
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+
+    let pool_rent = ctx.balance(&pool.address);
+    let before = ctx.balance(&pool.authority.pubkey());
+    let meta = close(&mut ctx).unwrap();
+    let closed: Vec<PoolClosedEvent> = events(&meta);
+    assert_eq!(closed[0].rent_returned, 2 * rent_reserve);
+    assert_eq!(closed[0].funds_returned, PRIZE_SHARE);
+    assert_eq!(
+        ctx.balance(&pool.authority.pubkey()) + TX_FEE - before,
+        2 * rent_reserve + PRIZE_SHARE + pool_rent
+    );
+    assert_eq!(ctx.balance(&pool.vault), 0);
+    assert_eq!(ctx.balance(&pool.prize_vault), 0);
+    assert_eq!(ctx.balance(&pool.address), 0);
+}
+
+// Archiving keeps a retired pool's figures in a small account and hands
+// back the rent of the pool and its vaults. The pool's settled tickets stay
+// closable by their owners.
+#[test]
+fn archiving_a_pool_keeps_its_summary_and_returns_the_rent() {
+    let mut ctx = TestContext::new();
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let config = PoolConfig {
+        ends_at: Some(now + 100),
+        ..default_config()
+    };
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Archived",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            config.clone(),
+        )
+        .unwrap();
+    let rent_reserve = ctx.pool(&pool).vault_rent_reserve;
+    let player = ctx.funded_user(5);
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    let close_ticket = |ctx: &mut TestContext| {
+        ctx.send(
+            ix::close_ticket(&player.pubkey(), &pool.address, &ticket),
+            &[&player],
+        )
+    };
+    assert_error(close_ticket(&mut ctx), ErrorCode::TicketNotSettled);
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+
+    // The jackpot pot is still owed to a winner once this pool retires
+    let (jackpot_pool, _) = ctx
+        .create_pool_with(
+            "Unsettled",
+            TICKET_PRICE,
+            vec![PoolItemInput {
+                jackpot: true,
+                ..item("Jackpot", PRIZE)
+            }],
+            PoolConfig {
+                jackpot_contribution_bps: 1000,
+                ..config
+            },
+        )
+        .unwrap();
+    buy(&mut ctx, &jackpot_pool, &player);
+
+    ctx.warp(101);
+    for retired in [&pool, &jackpot_pool] {
+        ctx.try_withdraw(retired, &retired.authority, REVENUE_SHARE)
+            .unwrap();
+        ctx.send(
+            ix::crank_expire_pool(&player.pubkey(), &retired.address),
+            &[&player],
+        )
+        .unwrap();
+    }
+    assert_error(
+        ctx.send(
+            ix::archive_pool(&jackpot_pool.authority.pubkey(), &jackpot_pool.address),
+            &[&jackpot_pool.authority],
+        ),
+        ErrorCode::PoolNotSettled,
+    );
+
+    let authority = pool.authority.pubkey();
+    let pool_rent = ctx.balance(&pool.address);
+    let before = ctx.balance(&authority);
+    let meta = ctx
+        .send(
+            ix::archive_pool(&authority, &pool.address),
+            &[&pool.authority],
+        )
+        .unwrap();
+    let archive_address = ix::pool_archive_pda(&pool.address);
+    let archive_rent = ctx.balance(&archive_address);
+    assert!(archive_rent < pool_rent / 4);
+    let archived: Vec<PoolArchivedEvent> = events(&meta);
+    assert_eq!(archived[0].rent_returned, 2 * rent_reserve);
+    assert_eq!(archived[0].funds_returned, 0);
+    assert_eq!(
+        ctx.balance(&authority) + TX_FEE - before,
+        2 * rent_reserve + pool_rent - archive_rent
+    );
+    assert_eq!(ctx.balance(&pool.address), 0);
+    assert_eq!(ctx.balance(&pool.vault), 0);
+    assert_eq!(ctx.balance(&pool.prize_vault), 0);
+
+    let archive: PoolArchive = ctx.account(&archive_address);
+    assert_eq!(archive.company_pool, pool.address);
+    assert_eq!(archive.name_hash, CompanyPool::name_seed("Archived"));
+    assert_eq!(archive.total_tickets_sold, 1);
+    assert_eq!(archive.total_rewards_paid, PRIZE);
+    assert_eq!(archive.top_winners[0].winner, player.pubkey());
+    assert_eq!(archive.top_winners[0].total_value_won, PRIZE);
+
+    // The claimed ticket outlives its pool until its owner closes it
+    let ticket_rent = ctx.balance(&ticket);
+    let player_before = ctx.balance(&player.pubkey());
+    close_ticket(&mut ctx).unwrap();
+    assert_eq!(ctx.balance(&ticket), 0);
+    assert_eq!(
+        ctx.balance(&player.pubkey()) + TX_FEE - player_before,
+        ticket_rent
+    );
+}
+
+// Every pool event carries the pool's next sequence number, so an indexer
+// can tell when it missed one
+#[test]
+fn pool_events_are_numbered_without_gaps() {
+    let mut ctx = TestContext::new();
+    let (pool, created) = ctx
+        .create_pool_with(
+            "Sequenced",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            default_config(),
+        )
+        .unwrap();
+    assert_eq!(events::<PoolInitializedEvent>(&created)[0].event_seq, 1);
+    let other = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+
+    let ticket = ix::ticket_pda(&player.pubkey(), &pool.address, 0);
+    let bought = ctx
+        .send(
+            ix::buy_ticket(&player.pubkey(), &pool.address, 0, None),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(events::<TicketPurchasedEvent>(&bought)[0].event_seq, 2);
+
+    // Another pool's activity in between numbers its own events
+    let other_ticket = buy(&mut ctx, &other, &player);
+    ctx.try_spin(&other, &player, &other_ticket).unwrap();
+
+    let spun = ctx.try_spin(&pool, &player, &ticket).unwrap();
+    assert_eq!(events::<SpinResultEvent>(&spun)[0].event_seq, 3);
+    // The player's first spin in the pool also unlocks an achievement
+    assert_eq!(events::<AchievementUnlockedEvent>(&spun)[0].event_seq, 4);
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let table = ctx.pool(&pool).draw_table(None, now);
+    let claimed = ctx.try_claim(&pool, &player, &ticket).unwrap();
+    assert_eq!(events::<RewardClaimedEvent>(&claimed)[0].event_seq, 5);
+    let withdrawn = ctx
+        .try_withdraw(&pool, &pool.authority, REVENUE_SHARE)
+        .unwrap();
+    assert_eq!(events::<FundsWithdrawnEvent>(&withdrawn)[0].event_seq, 6);
+
+    // Instructions that only pass the pool along number their events too
+    let verified = ctx
+        .send(ix::verify_spin(&pool.address, &ticket, table), &[&player])
+        .unwrap();
+    assert_eq!(events::<SpinVerifiedEvent>(&verified)[0].event_seq, 7);
+    downgrade_ticket_to_version_one(&mut ctx, ticket);
+    let migrated = ctx
+        .send(
+            ix::migrate_ticket(&player.pubkey(), &pool.address, &ticket),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(events::<AccountMigratedEvent>(&migrated)[0].event_seq, 8);
+    let closed = ctx
+        .send(
+            ix::close_ticket(&player.pubkey(), &pool.address, &ticket),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(events::<TicketClosedEvent>(&closed)[0].event_seq, 9);
+    let state = ix::user_pool_state_pda(&pool.address, &player.pubkey());
+    downgrade_user_pool_state_to_version_one(&mut ctx, state);
+    let migrated = ctx
+        .send(
+            ix::migrate_user_pool_state(&player.pubkey(), &pool.address, &player.pubkey()),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(events::<AccountMigratedEvent>(&migrated)[0].event_seq, 10);
+
+    // A pool from before the counter starts it with its migration
+    downgrade_pool_to_version_one(&mut ctx, other.address);
+    let migrated = ctx
+        .send(
+            ix::migrate_pool(&player.pubkey(), &other.address),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(events::<AccountMigratedEvent>(&migrated)[0].event_seq, 1);
+
+    assert_eq!(ctx.pool(&pool).event_seq, 10);
+    assert_eq!(ctx.pool(&other).event_seq, 1);
+
+    // A mega jackpot claim takes the winning ticket's pool's next number
+    let admin = ctx.admin.pubkey();
+    ctx.send_as_admin(ix::initialize_mega_jackpot(&admin, 500, 10000))
+        .unwrap();
+    let (mega, _) = ctx
+        .create_pool_with(
+            "Sequenced Mega",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                mega_jackpot: true,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    ctx.send(
+        ix::buy_ticket_in_mega_jackpot(&player.pubkey(), &mega.address, 0),
+        &[&player],
+    )
+    .unwrap();
+    let mega_ticket = ix::ticket_pda(&player.pubkey(), &mega.address, 0);
+    ctx.send(
+        ix::record_spin_result_in_mega_jackpot(
+            &player.pubkey(),
+            &player.pubkey(),
+            &mega.address,
+            &mega_ticket,
+        ),
+        &[&player],
+    )
+    .unwrap();
+    let before = ctx.pool(&mega).event_seq;
+    let claimed = ctx
+        .send(
+            ix::claim_mega_jackpot(&player.pubkey(), &mega.address, &mega_ticket),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(
+        events::<MegaJackpotClaimedEvent>(&claimed)[0].event_seq,
+        before + 1
+    );
+    assert_eq!(ctx.pool(&mega).event_seq, before + 1);
+}
+
+// Version 1 zero-copy pools are 8 bytes short of the counter and can't load
+// until migrate_pool_v2 grows them
+#[test]
+fn zero_copy_pools_migrate_to_numbered_events() {
+    let mut ctx = TestContext::new();
+    let authority = ctx.funded_user(10);
+    let config = PoolV2Config {
+        prize_allocation_bps: 5000,
+        max_ev_bps: 9000,
+        ..PoolV2Config::default()
+    };
+    ctx.send(
+        ix::initialize_company_pool_v2(
+            &authority.pubkey(),
+            TICKET_PRICE,
+            "Old fast pool",
+            vec![item("Prize", PRIZE)],
+            config,
+        ),
+        &[&authority],
+    )
+    .unwrap();
+    let pool = ix::pool_v2_pda("Old fast pool");
+    let player = ctx.funded_user(5);
+    ctx.send(ix::buy_ticket_v2(&player.pubkey(), &pool, 0), &[&player])
+        .unwrap();
+
+    let mut account = ctx.svm.get_account(&pool).unwrap();
+    let new_len = account.data.len();
+    account.data.truncate(new_len - migration::EVENT_SEQ_SPACE);
+    account.data[8 + std::mem::offset_of!(CompanyPoolV2, version)] = 1;
+    account.lamports = ctx
+        .svm
+        .minimum_balance_for_rent_exemption(account.data.len());
+    ctx.svm.set_account(pool, account).unwrap();
+    assert!(ctx
+        .send(ix::buy_ticket_v2(&player.pubkey(), &pool, 1), &[&player])
+        .is_err());
+
+    let payer = ctx.funded_user(1);
+    ctx.send(ix::migrate_pool_v2(&payer.pubkey(), &pool), &[&payer])
+        .unwrap();
+    let migrated = ctx.pool_v2(&pool);
+    assert_eq!(migrated.version, CompanyPoolV2::VERSION);
+    // The migration itself is the first numbered event
+    assert_eq!(migrated.event_seq, 1);
+    assert_eq!(migrated.total_tickets_sold, 1);
+    assert_error(
+        ctx.send(ix::migrate_pool_v2(&payer.pubkey(), &pool), &[&payer]),
+        ErrorCode::AccountAlreadyMigrated,
+    );
+
+    let bought = ctx
+        .send(ix::buy_ticket_v2(&player.pubkey(), &pool, 1), &[&player])
+        .unwrap();
+    assert_eq!(events::<TicketPurchasedEvent>(&bought)[0].event_seq, 2);
+}
+
+// A pool at its inline limits is roughly 5KB, more than an SBF stack frame
+// holds; every hot path has to keep it boxed
+#[test]
+fn full_size_pools_run_on_the_heap() {
+    let mut ctx = TestContext::new();
+    let items: Vec<PoolItemInput> = (0..CompanyPool::MAX_INLINE_ITEMS)
+        .map(|i| PoolItemInput {
+            image: "i".repeat(200),
+            name: format!("{:-<50}", i),
+            description: "d".repeat(200),
+            ..item("", PRIZE)
+        })
+        .collect();
+    let (pool, _) = ctx
+        .create_pool_with("Full pool", TICKET_PRICE, items, default_config())
+        .expect("pool init failed");
+
+    let player = ctx.funded_user(5);
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).expect("spin failed");
+    ctx.try_claim(&pool, &player, &ticket)
+        .expect("claim failed");
+    ctx.try_withdraw(&pool, &pool.authority, REVENUE_SHARE)
+        .expect("withdraw failed");
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+// The odds come from the item values alone, so init never copies the names.
+// The stored table matches the named calculator's, down to the last point.
+#[test]
+fn initialize_weighs_full_size_items_without_their_names() {
+    let mut ctx = TestContext::new();
+    let prices: Vec<u64> = [1u64, 2, 3, 4, 5, 6, 8, 10, 15, 20]
+        .iter()
+        .map(|tenths| tenths * TICKET_PRICE / 10)
+        .collect();
+    let items: Vec<PoolItemInput> = prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| PoolItemInput {
+            image: "i".repeat(200),
+            name: format!("{:-<50}", i),
+            description: "d".repeat(200),
+            ..item("", price)
+        })
+        .collect();
+    let named: Vec<(String, u64, u32)> = items
+        .iter()
+        .map(|item| (item.name.clone(), item.price, 1))
+        .collect();
+
+    let (pool, meta) = ctx
+        .create_pool_with("Long names", TICKET_PRICE, items, default_config())
+        .expect("pool init failed");
+    assert_cu_below(&meta, INITIALIZE_POOL_CU);
+
+    let stored: Vec<u32> = ctx
+        .pool(&pool)
+        .items
+        .iter()
+        .map(|item| item.probability)
+        .collect();
+    let reference: Vec<u32> = WeightedProbabilityCalculator::new(named, TICKET_PRICE)
+        .items
+        .iter()
+        .map(|item| item.probability)
+        .collect();
+    assert_eq!(stored, reference);
+    assert_eq!(
+        stored,
+        vec![5174, 1829, 996, 647, 463, 352, 229, 163, 89, 58]
+    );
+}
+
+// The creation event carries the whole odds table, so a frontend can show
+// the odds without fetching the pool
+#[test]
+fn initialized_event_carries_the_odds_table() {
+    let mut ctx = TestContext::new();
+    let items = vec![
+        item("Sticker", TICKET_PRICE / 10),
+        item("Mug", TICKET_PRICE / 4),
+        item("Hoodie", PRIZE),
+    ];
+    let weighting = WeightingMode::TicketRatio {
+        exponent_milli: 2000,
+    };
+    let config = PoolConfig {
+        weighting,
+        ..default_config()
+    };
+    let (pool, created) = ctx
+        .create_pool_with("Odds table", TICKET_PRICE, items.clone(), config)
+        .unwrap();
+
+    let event = &events::<PoolInitializedEvent>(&created)[0];
+    let stored: Vec<u32> = ctx
+        .pool(&pool)
+        .items
+        .iter()
+        .map(|item| item.probability)
+        .collect();
+    assert_eq!(event.probabilities, stored);
+    assert_eq!(
+        event.probabilities.iter().sum::<u32>(),
+        event.total_probability_check
+    );
+    assert_eq!(
+        event.total_value,
+        TICKET_PRICE / 10 + TICKET_PRICE / 4 + PRIZE
+    );
+    assert_eq!(event.weighting, weighting);
+
+    // Zero-copy pools report theirs the same way
+    let authority = ctx.funded_user(10);
+    let config = PoolV2Config {
+        prize_allocation_bps: 5000,
+        max_ev_bps: 9000,
+        weighting,
+        ..PoolV2Config::default()
+    };
+    let created = ctx
+        .send(
+            ix::initialize_company_pool_v2(
+                &authority.pubkey(),
+                TICKET_PRICE,
+                "Odds table v2",
+                items,
+                config,
+            ),
+            &[&authority],
+        )
+        .unwrap();
+    let event_v2 = &events::<PoolInitializedEvent>(&created)[0];
+    let fast = ctx.pool_v2(&ix::pool_v2_pda("Odds table v2"));
+    let stored_v2: Vec<u32> = fast.items[..fast.item_count as usize]
+        .iter()
+        .map(|item| item.probability)
+        .collect();
+    assert_eq!(event_v2.probabilities, stored_v2);
+    assert_eq!(event_v2.probabilities, event.probabilities);
+    assert_eq!(event_v2.total_value, event.total_value);
+    assert_eq!(event_v2.weighting, weighting);
+}
+
+// Zero-copy pools skip the Borsh round trip of the item table, so buying and
+// spinning cost less than in the same pool stored the v1 way. Both pools get
+// a warm-up ticket first so neither side pays for one-off account creation.
+#[test]
+fn zero_copy_pools_buy_and_spin_for_fewer_units() {
+    let mut ctx = TestContext::new();
+    let items: Vec<PoolItemInput> = (0..CompanyPool::MAX_INLINE_ITEMS)
+        .map(|i| item(&format!("Prize {}", i), PRIZE))
+        .collect();
+    let borsh = create_pool(&mut ctx, items.clone());
+
+    let authority = ctx.funded_user(10);
+    let config = PoolV2Config {
+        prize_allocation_bps: 5000,
+        max_ev_bps: 9000,
+        ..PoolV2Config::default()
+    };
+    ctx.send(
+        ix::initialize_company_pool_v2(
+            &authority.pubkey(),
+            TICKET_PRICE,
+            "Fast pool",
+            items,
+            config,
+        ),
+        &[&authority],
+    )
+    .expect("v2 pool init failed");
+    let fast = ix::pool_v2_pda("Fast pool");
+
+    let player = ctx.funded_user(10);
+    let warm_up = buy(&mut ctx, &borsh, &player);
+    ctx.try_spin(&borsh, &player, &warm_up)
+        .expect("spin failed");
+
+    let borsh_ticket = ix::ticket_pda(&player.pubkey(), &borsh.address, 1);
+    let borsh_buy = ctx
+        .send(
+            ix::buy_ticket(&player.pubkey(), &borsh.address, 1, None),
+            &[&player],
+        )
+        .unwrap();
+    let borsh_spin = ctx.try_spin(&borsh, &player, &borsh_ticket).unwrap();
+
+    let mut fast_tickets = Vec::new();
+    let mut fast_runs = Vec::new();
+    for sold in 0..2 {
+        let ticket = ix::ticket_pda(&player.pubkey(), &fast, sold);
+        let bought = ctx
+            .send(ix::buy_ticket_v2(&player.pubkey(), &fast, sold), &[&player])
+            .unwrap();
+        let spun = ctx
+            .send(
+                ix::record_spin_result_v2(&player.pubkey(), &fast, &ticket),
+                &[&player],
+            )
+            .unwrap();
+        fast_tickets.push(ticket);
+        fast_runs.push((bought, spun));
+    }
+    let (fast_buy, fast_spin) = &fast_runs[1];
+
+    assert!(
+        fast_buy.compute_units_consumed < borsh_buy.compute_units_consumed,
+        "buy: {} CU zero-copy, {} CU Borsh",
+        fast_buy.compute_units_consumed,
+        borsh_buy.compute_units_consumed
+    );
+    assert!(
+        fast_spin.compute_units_consumed < borsh_spin.compute_units_consumed,
+        "spin: {} CU zero-copy, {} CU Borsh",
+        fast_spin.compute_units_consumed,
+        borsh_spin.compute_units_consumed
+    );
+
+    // The rest of the zero-copy lifecycle still moves lamports correctly
+    let state = ctx.pool_v2(&fast);
+    assert_eq!(state.total_tickets_sold, 2);
+    assert_eq!(state.total_funds, 2 * REVENUE_SHARE);
+    assert_eq!(state.outstanding_liabilities, 2 * PRIZE);
+
+    let before = ctx.balance(&player.pubkey());
+    ctx.send(
+        ix::claim_reward_v2(&player.pubkey(), &fast, &fast_tickets[0]),
+        &[&player],
+    )
+    .expect("claim failed");
+    assert_eq!(ctx.balance(&player.pubkey()) + TX_FEE - before, PRIZE);
+    assert!(ctx.ticket(&fast_tickets[0]).reward_claimed);
+
+    ctx.send(
+        ix::withdraw_funds_from_vault_v2(&authority.pubkey(), &fast, 2 * REVENUE_SHARE),
+        &[&authority],
+    )
+    .expect("withdraw failed");
+    let state = ctx.pool_v2(&fast);
+    assert_eq!(state.total_funds, 0);
+    assert_eq!(state.outstanding_liabilities, PRIZE);
+    assert_eq!(state.total_rewards_paid, PRIZE);
+    assert_eq!(ctx.balance(&ix::vault_pda(&fast)), state.vault_rent_reserve);
+}
+
+// Spins pick from the pool's cumulative table with a binary search, so a full
+// pool stays within the same ceiling as a single-item one
+#[test]
+fn full_pool_spins_stay_under_compute_ceiling() {
+    let mut ctx = TestContext::new();
+    let items = (0..CompanyPool::MAX_INLINE_ITEMS)
+        .map(|i| item(&format!("Prize {}", i), PRIZE))
+        .collect();
+    let pool = create_pool(&mut ctx, items);
+    let player = ctx.funded_user(5);
+
+    let ticket = buy(&mut ctx, &pool, &player);
+    let meta = ctx.try_spin(&pool, &player, &ticket).unwrap();
+    assert_cu_below(&meta, SPIN_CU);
+}
+
+// Analysis reads the stored odds instead of recomputing them, so a full pool
+// fits one call; clients can still page through the items
+#[test]
+fn full_pool_analysis_stays_under_compute_ceiling() {
+    let mut ctx = TestContext::new();
+    let items = (0..CompanyPool::MAX_INLINE_ITEMS)
+        .map(|i| item(&format!("{:-<50}", i), PRIZE))
+        .collect();
+    let pool = create_pool(&mut ctx, items);
+    let player = ctx.funded_user(1);
+    let analyze =
+        |start_index, count| ix::get_probability_analysis(&pool.address, start_index, count);
+
+    let meta = ctx.send(analyze(0, u8::MAX), &[&player]).unwrap();
+    assert_cu_below(&meta, ANALYSIS_CU);
+    let summary = &events::<PoolAnalysisEvent>(&meta)[0];
+    assert_eq!(summary.item_count, CompanyPool::MAX_INLINE_ITEMS as u32);
+    assert_eq!(summary.expected_value, PRIZE);
+    assert_eq!(summary.ev_bps, 5000);
+    assert_eq!(summary.house_edge_bps, 5000);
+    let items = events::<ProbabilityAnalysisEvent>(&meta);
+    assert_eq!(items.len(), CompanyPool::MAX_INLINE_ITEMS);
+    for analysis in &items {
+        assert_eq!(analysis.probability_basis_points, 1000);
+        assert_eq!(analysis.expected_cost, 10 * TICKET_PRICE);
+        assert_eq!(analysis.profit, PRIZE as i128 - 10 * TICKET_PRICE as i128);
+    }
+
+    // Pages of four: only the first carries the summary, the last is short
+    let mut indexes = Vec::new();
+    for start_index in [0, 4, 8] {
+        let meta = ctx.send(analyze(start_index, 4), &[&player]).unwrap();
+        assert_eq!(
+            events::<PoolAnalysisEvent>(&meta).len(),
+            (start_index == 0) as usize
+        );
+        indexes.extend(
+            events::<ProbabilityAnalysisEvent>(&meta)
+                .iter()
+                .map(|analysis| analysis.item_index),
+        );
+    }
+    assert_eq!(indexes, (0..10).collect::<Vec<u32>>());
+
+    assert_error(
+        ctx.send(analyze(10, 4), &[&player]),
+        ErrorCode::InvalidItemIndex,
+    );
+}
+
+#[test]
+fn withdrawals_cannot_reach_the_prize_vault() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+
+    // Only the revenue share is withdrawable; the owed prize sits apart
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, TICKET_PRICE),
+        company_pool::ErrorCode::InsufficientFunds,
+    );
+    ctx.try_withdraw(&pool, &pool.authority, REVENUE_SHARE)
+        .unwrap();
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+}
+
+#[test]
+fn sweep_empties_the_revenue_vault_only() {
+    let mut ctx = TestContext::new();
+    let rent_reserve = ctx.svm.minimum_balance_for_rent_exemption(0);
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    buy(&mut ctx, &pool, &player);
+
+    let meta = ctx.try_sweep(&pool, &pool.authority).unwrap();
+    assert_cu_below(&meta, SWEEP_CU);
+    assert_eq!(
+        events::<VaultSweptEvent>(&meta)[0].recipient,
+        pool.authority.pubkey()
+    );
+    assert_eq!(ctx.balance(&pool.vault), rent_reserve);
+    assert_eq!(
+        ctx.balance(&pool.prize_vault),
+        rent_reserve + 2 * PRIZE_SHARE
+    );
+
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+    assert_eq!(
+        ctx.balance(&pool.prize_vault),
+        rent_reserve + 2 * PRIZE_SHARE - PRIZE
+    );
+    assert_error(
+        ctx.try_sweep(&pool, &pool.authority),
+        company_pool::ErrorCode::NoFundsAvailable,
+    );
+}
+
+// Charity pools commit their revenue to a beneficiary at init: only a sweep
+// moves it, and only to the beneficiary
+#[test]
+fn beneficiary_pools_pay_out_to_the_beneficiary_only() {
+    let mut ctx = TestContext::new();
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let charity = ctx.funded_user(1).pubkey();
+    let (pool, created) = ctx
+        .create_pool_with(
+            "Charity Drive",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                beneficiary: Some(charity),
+                ends_at: Some(now + 100),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        events::<PoolInitializedEvent>(&created)[0].beneficiary,
+        Some(charity)
+    );
+    let player = ctx.funded_user(5);
+    buy(&mut ctx, &pool, &player);
+    buy(&mut ctx, &pool, &player);
+
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, REVENUE_SHARE),
+        ErrorCode::BeneficiaryPool,
+    );
+    let authority = pool.authority.pubkey();
+    for recipient in [None, Some(authority)] {
+        assert_error(
+            ctx.send(
+                ix::sweep_vault(&authority, None, &pool.address, recipient),
+                &[&pool.authority],
+            ),
+            ErrorCode::InvalidBeneficiary,
+        );
+    }
+
+    // Closing out: sweep the revenue, then retire the settled pool
+    let (charity_before, authority_before) = (ctx.balance(&charity), ctx.balance(&authority));
+    let meta = ctx.try_sweep(&pool, &pool.authority).unwrap();
+    assert_eq!(events::<VaultSweptEvent>(&meta)[0].recipient, charity);
+    assert_eq!(ctx.balance(&charity) - charity_before, 2 * REVENUE_SHARE);
+    assert_eq!(authority_before - ctx.balance(&authority), TX_FEE);
+    assert_eq!(ctx.pool(&pool).total_funds, 0);
+
+    ctx.warp(101);
+    ctx.send(
+        ix::crank_expire_pool(&player.pubkey(), &pool.address),
+        &[&player],
+    )
+    .unwrap();
+    assert!(!ctx.pool(&pool).active);
+}
+
+// The beneficiary account is only accepted on pools that have one
+#[test]
+fn beneficiary_accounts_are_rejected_on_ordinary_pools() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+    buy(&mut ctx, &pool, &player);
+
+    let authority = pool.authority.pubkey();
+    assert_error(
+        ctx.send(
+            ix::sweep_vault(&authority, None, &pool.address, Some(player.pubkey())),
+            &[&pool.authority],
+        ),
+        ErrorCode::InvalidBeneficiary,
+    );
+    ctx.try_withdraw(&pool, &pool.authority, REVENUE_SHARE)
+        .unwrap();
+}
+
+// Buyers can add a donation to their ticket. It goes straight to the pool's
+// beneficiary and never reaches the vaults or the pool's funds.
+#[test]
+fn donations_go_to_the_beneficiary_and_stay_off_the_books() {
+    let mut ctx = TestContext::new();
+    let charity = ctx.funded_user(1).pubkey();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Round Up",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                beneficiary: Some(charity),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    let vault = ix::vault_pda(&pool.address);
+    let donation = LAMPORTS_PER_SOL / 100;
+
+    for round in 1..=2 {
+        let sold = ctx.pool(&pool).total_tickets_sold;
+        let (charity_before, vault_before) = (ctx.balance(&charity), ctx.balance(&vault));
+        let bought = ctx
+            .send(
+                ix::buy_ticket_with_donation(
+                    &player.pubkey(),
+                    &pool.address,
+                    sold,
+                    donation,
+                    Some(charity),
+                ),
+                &[&player],
+            )
+            .unwrap();
+        assert_eq!(
+            events::<TicketPurchasedEvent>(&bought)[0].donation,
+            donation
+        );
+        assert_eq!(ctx.balance(&charity) - charity_before, donation);
+        assert_eq!(ctx.balance(&vault) - vault_before, REVENUE_SHARE);
+        let state = ctx.pool(&pool);
+        assert_eq!(state.total_donations, round * donation);
+        assert_eq!(state.total_funds, round * REVENUE_SHARE);
+        assert_eq!(state.outstanding_liabilities, 0);
+    }
+
+    // Only to the beneficiary itself
+    let sold = ctx.pool(&pool).total_tickets_sold;
+    for recipient in [None, Some(player.pubkey())] {
+        assert_error(
+            ctx.send(
+                ix::buy_ticket_with_donation(
+                    &player.pubkey(),
+                    &pool.address,
+                    sold,
+                    donation,
+                    recipient,
+                ),
+                &[&player],
+            ),
+            ErrorCode::InvalidBeneficiary,
+        );
+    }
+}
+
+// Without a beneficiary there is no one to donate to, while plain purchases
+// go through as always
+#[test]
+fn pools_without_a_beneficiary_reject_donations() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+
+    assert_error(
+        ctx.send(
+            ix::buy_ticket_with_donation(&player.pubkey(), &pool.address, 0, 1, None),
+            &[&player],
+        ),
+        ErrorCode::DonationsNotAccepted,
+    );
+    buy(&mut ctx, &pool, &player);
+    let state = ctx.pool(&pool);
+    assert_eq!(state.total_tickets_sold, 1);
+    assert_eq!(state.total_donations, 0);
+}
+
+// Participating pools pay the mega jackpot's share of each sale out of their
+// revenue into the shared vault; pools that didn't opt in pay nothing
+#[test]
+fn mega_jackpot_pools_a_share_of_every_participating_sale() {
+    let mut ctx = TestContext::new();
+    let rent_reserve = ctx.svm.minimum_balance_for_rent_exemption(0);
+    let admin = ctx.admin.pubkey();
+    ctx.send_as_admin(ix::initialize_mega_jackpot(&admin, 500, 0))
+        .unwrap();
+    let mega_vault = ix::mega_jackpot_vault_pda();
+    let share = TICKET_PRICE / 20;
+
+    let mega_config = PoolConfig {
+        mega_jackpot: true,
+        ..default_config()
+    };
+    let (first, _) = ctx
+        .create_pool_with(
+            "Mega One",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            mega_config.clone(),
+        )
+        .unwrap();
+    let (second, _) = ctx
+        .create_pool_with(
+            "Mega Two",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            mega_config,
+        )
+        .unwrap();
+    let plain = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+
+    for pool in [&first, &second] {
+        assert_error(
+            ctx.send(
+                ix::buy_ticket(&player.pubkey(), &pool.address, 0, None),
+                &[&player],
+            ),
+            ErrorCode::MegaJackpotRequired,
+        );
+        ctx.send(
+            ix::buy_ticket_in_mega_jackpot(&player.pubkey(), &pool.address, 0),
+            &[&player],
+        )
+        .unwrap();
+        let state = ctx.pool(pool);
+        assert_eq!(state.total_mega_jackpot_contributions, share);
+        assert_eq!(state.total_funds, REVENUE_SHARE - share);
+        assert_eq!(
+            ctx.balance(&pool.vault),
+            rent_reserve + REVENUE_SHARE - share
+        );
+        assert_eq!(ctx.balance(&pool.prize_vault), rent_reserve + PRIZE_SHARE);
+        assert_eq!(ctx.reconcile(pool).delta, 0);
+    }
+    buy(&mut ctx, &plain, &player);
+    assert_eq!(ctx.pool(&plain).total_funds, REVENUE_SHARE);
+
+    let mega_jackpot: MegaJackpot = ctx.account(&ix::mega_jackpot_pda());
+    assert_eq!(mega_jackpot.pot, 2 * share);
+    assert_eq!(mega_jackpot.total_contributed, 2 * share);
+    assert_eq!(ctx.balance(&mega_vault), rent_reserve + 2 * share);
+
+    // Terms are capped so a pool always keeps most of its revenue share
+    assert_error(
+        ctx.send_as_admin(ix::set_mega_jackpot_terms(&admin, 1001, 0)),
+        ErrorCode::InvalidMegaJackpotTerms,
+    );
+    assert_error(
+        ctx.create_pool_with(
+            "Mega Greedy",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                mega_jackpot: true,
+                prize_allocation_bps: 9500,
+                ..default_config()
+            },
+        ),
+        ErrorCode::InvalidPoolConfig,
+    );
+}
+
+// A hit hands the winning ticket the whole pot, claimed from the shared vault
+// while the next round starts from zero
+#[test]
+fn mega_jackpot_hit_pays_the_pot_to_the_winning_ticket() {
+    let mut ctx = TestContext::new();
+    let rent_reserve = ctx.svm.minimum_balance_for_rent_exemption(0);
+    let admin = ctx.admin.pubkey();
+    ctx.send_as_admin(ix::initialize_mega_jackpot(&admin, 500, 10000))
+        .unwrap();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Mega Hit",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                mega_jackpot: true,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    let share = TICKET_PRICE / 20;
+    let mut tickets = Vec::new();
+    for sold in 0..2 {
+        ctx.send(
+            ix::buy_ticket_in_mega_jackpot(&player.pubkey(), &pool.address, sold),
+            &[&player],
+        )
+        .unwrap();
+        tickets.push(ix::ticket_pda(&player.pubkey(), &pool.address, sold));
+    }
+
+    // Every spin rolls for it, so the accounts can't be left out
+    assert_error(
+        ctx.try_spin(&pool, &player, &tickets[0]),
+        ErrorCode::MegaJackpotRequired,
+    );
+    let spun = ctx
+        .send(
+            ix::record_spin_result_in_mega_jackpot(
+                &player.pubkey(),
+                &player.pubkey(),
+                &pool.address,
+                &tickets[0],
+            ),
+            &[&player],
+        )
+        .unwrap();
+    let won = events::<MegaJackpotWonEvent>(&spun);
+    assert_eq!(won.len(), 1);
+    assert_eq!(
+        (won[0].company_pool, won[0].amount),
+        (pool.address, 2 * share)
+    );
+    assert_eq!(ctx.ticket(&tickets[0]).mega_jackpot_won, 2 * share);
+    let mega_jackpot: MegaJackpot = ctx.account(&ix::mega_jackpot_pda());
+    assert_eq!((mega_jackpot.pot, mega_jackpot.owed), (0, 2 * share));
+
+    // With the pot empty the next spin has nothing to win. Claiming in the
+    // spin's own transaction is refused whatever it drew, so a miss can't
+    // be reverted and the spin tried again.
+    let spin_and_claim = [
+        ix::record_spin_result_in_mega_jackpot(
+            &player.pubkey(),
+            &player.pubkey(),
+            &pool.address,
+            &tickets[1],
+        ),
+        ix::claim_mega_jackpot(&player.pubkey(), &pool.address, &tickets[1]),
+    ];
+    assert_error_at(
+        ctx.send_all(&spin_and_claim, &[&player]),
+        1,
+        ErrorCode::ClaimTooSoon,
+    );
+    let spun = ctx.send(spin_and_claim[0].clone(), &[&player]).unwrap();
+    assert!(events::<MegaJackpotWonEvent>(&spun).is_empty());
+
+    let stranger = ctx.funded_user(1);
+    assert_error(
+        ctx.send(
+            ix::claim_mega_jackpot(&stranger.pubkey(), &pool.address, &tickets[0]),
+            &[&stranger],
+        ),
+        ErrorCode::NotTicketOwner,
+    );
+    let player_before = ctx.balance(&player.pubkey());
+    let claimed = ctx
+        .send(
+            ix::claim_mega_jackpot(&player.pubkey(), &pool.address, &tickets[0]),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(
+        events::<MegaJackpotClaimedEvent>(&claimed)[0].amount,
+        2 * share
+    );
+    assert_eq!(
+        ctx.balance(&player.pubkey()) + TX_FEE - player_before,
+        2 * share
+    );
+    assert_eq!(ctx.balance(&ix::mega_jackpot_vault_pda()), rent_reserve);
+    assert_eq!(ctx.ticket(&tickets[0]).mega_jackpot_won, 0);
+    assert_error(
+        ctx.send(
+            ix::claim_mega_jackpot(&player.pubkey(), &pool.address, &tickets[0]),
+            &[&player],
+        ),
+        ErrorCode::NoMegaJackpotWon,
+    );
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+// A voided spin gives back its mega jackpot win along with its prize: the
+// amount goes back into the pot and the ticket can no longer claim it
+#[test]
+fn voiding_a_spin_returns_its_mega_jackpot_win_to_the_pot() {
+    let mut ctx = TestContext::new();
+    let admin = ctx.admin.pubkey();
+    ctx.send_as_admin(ix::initialize_mega_jackpot(&admin, 500, 10000))
+        .unwrap();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Mega Void",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                mega_jackpot: true,
+                dispute_window_seconds: 600,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    ctx.send(
+        ix::buy_ticket_in_mega_jackpot(&player.pubkey(), &pool.address, 0),
+        &[&player],
+    )
+    .unwrap();
+    let ticket = ix::ticket_pda(&player.pubkey(), &pool.address, 0);
+    let share = TICKET_PRICE / 20;
+    ctx.send(
+        ix::record_spin_result_in_mega_jackpot(
+            &player.pubkey(),
+            &player.pubkey(),
+            &pool.address,
+            &ticket,
+        ),
+        &[&player],
+    )
+    .unwrap();
+    assert_eq!(ctx.ticket(&ticket).mega_jackpot_won, share);
+    assert_error(
+        ctx.send(
+            ix::claim_mega_jackpot(&player.pubkey(), &pool.address, &ticket),
+            &[&player],
+        ),
+        ErrorCode::DisputeWindowOpen,
+    );
+
+    // The win can't be voided without the jackpot to return it to
+    assert_error(
+        ctx.send_as_admin(ix::void_spin(
+            &admin,
+            &pool.address,
+            &ticket,
+            &player.pubkey(),
+        )),
+        ErrorCode::MegaJackpotRequired,
+    );
+    ctx.send_as_admin(ix::void_spin_in_mega_jackpot(
+        &admin,
+        &pool.address,
+        &ticket,
+        &player.pubkey(),
+    ))
+    .unwrap();
+    assert_eq!(ctx.ticket(&ticket).mega_jackpot_won, 0);
+    let mega_jackpot: MegaJackpot = ctx.account(&ix::mega_jackpot_pda());
+    assert_eq!((mega_jackpot.pot, mega_jackpot.owed), (share, 0));
+    assert_eq!((mega_jackpot.total_won, mega_jackpot.wins), (0, 0));
+
+    ctx.warp(600);
+    assert_error(
+        ctx.send(
+            ix::claim_mega_jackpot(&player.pubkey(), &pool.address, &ticket),
+            &[&player],
+        ),
+        ErrorCode::NoMegaJackpotWon,
+    );
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+// An insured spin that lands under the pool's floor draws the insurance
+// payout on top of its prize, once, while uninsured tickets get nothing
+#[test]
+fn insured_spin_below_the_floor_pays_out() {
+    let mut ctx = TestContext::new();
+    let premium = TICKET_PRICE / 20;
+    let payout = TICKET_PRICE / 10;
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Insured",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                insurance: Some(SpinInsurance {
+                    price: premium,
+                    floor_value: 2 * PRIZE,
+                    payout,
+                    replaces_prize: false,
+                }),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+
+    let prize_vault_before = ctx.balance(&pool.prize_vault);
+    let bought = ctx
+        .send(
+            ix::buy_insured_ticket(&player.pubkey(), &pool.address, 0),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(
+        events::<TicketPurchasedEvent>(&bought)[0].insurance_premium,
+        premium
+    );
+    assert_eq!(
+        ctx.balance(&pool.prize_vault) - prize_vault_before,
+        PRIZE_SHARE + premium
+    );
+    let insured = ix::ticket_pda(&player.pubkey(), &pool.address, 0);
+    assert!(ctx.ticket(&insured).insured);
+    let uninsured = buy(&mut ctx, &pool, &player);
+    assert!(!ctx.ticket(&uninsured).insured);
+
+    let spun = ctx.try_spin(&pool, &player, &insured).unwrap();
+    assert!(events::<SpinResultEvent>(&spun)[0].insurance_triggered);
+
+    let player_before = ctx.balance(&player.pubkey());
+    let claimed = ctx
+        .send(
+            ix::claim_insurance(&player.pubkey(), &pool.address, &insured),
+            &[&player],
+        )
+        .unwrap();
+    let event = &events::<InsuranceClaimedEvent>(&claimed)[0];
+    assert_eq!((event.item_value, event.payout), (PRIZE, payout));
+    assert!(!event.prize_forfeited);
+    assert_eq!(
+        ctx.balance(&player.pubkey()) + TX_FEE - player_before,
+        payout
+    );
+    let state = ctx.pool(&pool);
+    assert_eq!(state.total_insurance_premiums, premium);
+    assert_eq!(state.total_insurance_paid, payout);
+    assert_error(
+        ctx.send(
+            ix::claim_insurance(&player.pubkey(), &pool.address, &insured),
+            &[&player],
+        ),
+        ErrorCode::InsuranceAlreadyClaimed,
+    );
+
+    // The prize itself is still there to claim
+    ctx.try_claim(&pool, &player, &insured).unwrap();
+    assert_error(
+        ctx.send(
+            ix::claim_insurance(&player.pubkey(), &pool.address, &uninsured),
+            &[&player],
+        ),
+        ErrorCode::TicketNotInsured,
+    );
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+// A win at the floor or above isn't covered, and pools that don't offer
+// insurance won't sell it
+#[test]
+fn insured_spin_at_the_floor_cannot_claim_insurance() {
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Floored",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                insurance: Some(SpinInsurance {
+                    price: TICKET_PRICE / 20,
+                    floor_value: PRIZE,
+                    payout: TICKET_PRICE / 10,
+                    replaces_prize: true,
+                }),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    ctx.send(
+        ix::buy_insured_ticket(&player.pubkey(), &pool.address, 0),
+        &[&player],
+    )
+    .unwrap();
+    let ticket = ix::ticket_pda(&player.pubkey(), &pool.address, 0);
+
+    let spun = ctx.try_spin(&pool, &player, &ticket).unwrap();
+    assert!(!events::<SpinResultEvent>(&spun)[0].insurance_triggered);
+    assert_error(
+        ctx.send(
+            ix::claim_insurance(&player.pubkey(), &pool.address, &ticket),
+            &[&player],
+        ),
+        ErrorCode::InsuranceNotTriggered,
+    );
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+
+    let plain = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    assert_error(
+        ctx.send(
+            ix::buy_insured_ticket(&player.pubkey(), &plain.address, 0),
+            &[&player],
+        ),
+        ErrorCode::InsuranceNotOffered,
+    );
+}
+
+// An auto-paid prize is already gone when the spin's insurance is claimed, so
+// those pools can only insure on top of the prize
+#[test]
+fn auto_payout_pools_only_insure_on_top_of_the_prize() {
+    let mut ctx = TestContext::new();
+    let insurance = SpinInsurance {
+        price: TICKET_PRICE / 20,
+        floor_value: PRIZE + 1,
+        payout: TICKET_PRICE / 10,
+        replaces_prize: true,
+    };
+    let config = PoolConfig {
+        auto_payout: true,
+        insurance: Some(insurance),
+        ..default_config()
+    };
+    assert_error(
+        ctx.create_pool_with(
+            "Replaced",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            config.clone(),
+        ),
+        ErrorCode::InvalidPoolConfig,
+    );
+
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Topped Up",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                insurance: Some(SpinInsurance {
+                    replaces_prize: false,
+                    ..insurance
+                }),
+                ..config
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    ctx.send(
+        ix::buy_insured_ticket(&player.pubkey(), &pool.address, 0),
+        &[&player],
+    )
+    .unwrap();
+    let ticket = ix::ticket_pda(&player.pubkey(), &pool.address, 0);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    assert!(ctx.ticket(&ticket).reward_claimed);
+
+    let claimed = ctx
+        .send(
+            ix::claim_insurance(&player.pubkey(), &pool.address, &ticket),
+            &[&player],
+        )
+        .unwrap();
+    let event = &events::<InsuranceClaimedEvent>(&claimed)[0];
+    assert_eq!(event.payout, TICKET_PRICE / 10);
+    assert!(!event.prize_forfeited);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+fn three_way_shares(participants: &[&Keypair; 3]) -> Vec<TicketShare> {
+    participants
+        .iter()
+        .zip([3334, 3333, 3333])
+        .map(|(participant, share_bps)| TicketShare {
+            owner: participant.pubkey(),
+            share_bps,
+        })
+        .collect()
+}
+
+// A shared ticket can be spun by any participant, not just the payer, and
+// its reward is split by share with the rounding dust on the first one
+#[test]
+fn shared_ticket_reward_splits_between_participants_to_the_lamport() {
+    let mut ctx = TestContext::new();
+    // Two lamports over a round amount, so the split leaves dust
+    let reward = PRIZE + 2;
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Shared",
+            TICKET_PRICE,
+            vec![item("Prize", reward)],
+            default_config(),
+        )
+        .unwrap();
+    // A second sale keeps the prize vault above the odd reward
+    let filler = ctx.funded_user(5);
+    buy(&mut ctx, &pool, &filler);
+
+    let payer = ctx.funded_user(5);
+    let (alice, bob, carol) = (ctx.funded_user(1), ctx.funded_user(1), ctx.funded_user(1));
+    let shares = three_way_shares(&[&alice, &bob, &carol]);
+    let payer_before = ctx.balance(&payer.pubkey());
+    let bought = ctx
+        .send(
+            ix::buy_shared_ticket(&payer.pubkey(), &pool.address, 1, shares.clone()),
+            &[&payer],
+        )
+        .unwrap();
+    let ticket = ix::ticket_pda(&payer.pubkey(), &pool.address, 1);
+    let shared_ticket = ix::shared_ticket_pda(&ticket);
+    let event = &events::<SharedTicketPurchasedEvent>(&bought)[0];
+    assert_eq!(event.shared_ticket, shared_ticket);
+    assert_eq!(event.shares, shares);
+    assert_eq!(ctx.ticket(&ticket).owner, shared_ticket);
+    assert_eq!(ctx.account::<SharedTicket>(&shared_ticket).shares, shares);
+    assert!(payer_before - ctx.balance(&payer.pubkey()) > TICKET_PRICE);
+
+    // Only participants spin; the payer isn't one
+    for outsider in [&payer, &filler] {
+        assert_error(
+            ctx.send(
+                ix::record_shared_spin(&outsider.pubkey(), &pool.address, &ticket),
+                &[outsider],
+            ),
+            ErrorCode::NotTicketOwner,
+        );
+    }
+    ctx.send(
+        ix::record_shared_spin(&bob.pubkey(), &pool.address, &ticket),
+        &[&bob],
+    )
+    .unwrap();
+
+    let participants = [alice.pubkey(), bob.pubkey(), carol.pubkey()];
+    let before = participants.map(|participant| ctx.balance(&participant));
+    let claimed = ctx
+        .send(
+            ix::claim_shared_reward(&carol.pubkey(), &pool.address, &ticket, &participants),
+            &[&carol],
+        )
+        .unwrap();
+    let after = participants.map(|participant| ctx.balance(&participant));
+    // 500_000_002 lamports split 33.34/33.33/33.33%, two lamports of dust first
+    assert_eq!(after[0] - before[0], 166_700_002);
+    assert_eq!(after[1] - before[1], 166_650_000);
+    assert_eq!(after[2] + TX_FEE - before[2], 166_650_000);
+    let paid: Vec<u64> = events::<SharedRewardPaidEvent>(&claimed)
+        .iter()
+        .map(|event| event.amount)
+        .collect();
+    assert_eq!(paid, vec![166_700_002, 166_650_000, 166_650_000]);
+    assert_eq!(paid.iter().sum::<u64>(), reward);
+
+    // Each participant's part counts toward their leaderboard total
+    let ranks: Vec<Option<u8>> = events::<SharedRewardPaidEvent>(&claimed)
+        .iter()
+        .map(|event| event.leaderboard_rank)
+        .collect();
+    assert_eq!(ranks, vec![Some(1), Some(2), Some(3)]);
+    let board: Vec<(Pubkey, u64)> = ctx
+        .leaderboard(&pool)
+        .iter()
+        .map(|entry| (entry.winner, entry.total_value_won))
+        .collect();
+    assert_eq!(
+        board,
+        participants
+            .iter()
+            .copied()
+            .zip(paid.iter().copied())
+            .collect::<Vec<_>>()
+    );
+
+    assert_eq!(ctx.ticket(&ticket).state(), TicketState::Claimed);
+    assert_error(
+        ctx.send(
+            ix::claim_shared_reward(&alice.pubkey(), &pool.address, &ticket, &participants),
+            &[&alice],
+        ),
+        ErrorCode::RewardAlreadyClaimed,
+    );
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+// Every participant must be paid in the one claim, so leaving one out or
+// passing them out of order fails
+#[test]
+fn shared_reward_claim_needs_every_participant_account() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let payer = ctx.funded_user(5);
+    let (alice, bob, carol) = (ctx.funded_user(1), ctx.funded_user(1), ctx.funded_user(1));
+    ctx.send(
+        ix::buy_shared_ticket(
+            &payer.pubkey(),
+            &pool.address,
+            0,
+            three_way_shares(&[&alice, &bob, &carol]),
+        ),
+        &[&payer],
+    )
+    .unwrap();
+    let ticket = ix::ticket_pda(&payer.pubkey(), &pool.address, 0);
+    ctx.send(
+        ix::record_shared_spin(&alice.pubkey(), &pool.address, &ticket),
+        &[&alice],
+    )
+    .unwrap();
+
+    let (a, b, c) = (alice.pubkey(), bob.pubkey(), carol.pubkey());
+    for participants in [vec![a, b], vec![a, c, b], vec![a, b, payer.pubkey()]] {
+        assert_error(
+            ctx.send(
+                ix::claim_shared_reward(&a, &pool.address, &ticket, &participants),
+                &[&alice],
+            ),
+            ErrorCode::ParticipantAccountMismatch,
+        );
+    }
+    assert_eq!(ctx.ticket(&ticket).state(), TicketState::Spun);
+
+    ctx.send(
+        ix::claim_shared_reward(&a, &pool.address, &ticket, &[a, b, c]),
+        &[&alice],
+    )
+    .unwrap();
+    assert_eq!(ctx.ticket(&ticket).state(), TicketState::Claimed);
+}
+
+// The payer of a shared ticket is checked and credited as the buyer of any
+// other ticket: barred and blocked payers are refused, and a purchase pays
+// the happy hour price, extends their streak and counts in the global stats
+#[test]
+fn shared_tickets_go_through_the_payers_purchase_checks() {
+    let mut ctx = TestContext::new();
+    let admin = ctx.admin.pubkey();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Shared Checks",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                authority_play_disabled: true,
+                loyalty_enabled: true,
+                loyalty_points_per_ticket: 1,
+                points_per_free_ticket: 100,
+                streak_milestones: vec![StreakMilestone {
+                    days: 2,
+                    bonus_points: 10,
+                }],
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let authority = pool.authority.insecure_clone();
+    let payer = ctx.funded_user(5);
+    let (alice, bob, carol) = (ctx.funded_user(1), ctx.funded_user(1), ctx.funded_user(1));
+    let shares = three_way_shares(&[&alice, &bob, &carol]);
+
+    assert_error(
+        ctx.send(
+            ix::buy_shared_ticket(&authority.pubkey(), &pool.address, 0, shares.clone()),
+            &[&authority],
+        ),
+        ErrorCode::AuthorityCannotPlay,
+    );
+
+    ctx.send(
+        ix::set_happy_hour(&authority.pubkey(), &pool.address, 22, 2, 2000),
+        &[&authority],
+    )
+    .unwrap();
+    warp_to_utc_hour(&mut ctx, 23);
+    let bought = ctx
+        .send(
+            ix::buy_shared_ticket(&payer.pubkey(), &pool.address, 0, shares.clone()),
+            &[&payer],
+        )
+        .unwrap();
+    let discounted = TICKET_PRICE - TICKET_PRICE / 5;
+    let ticket = ix::ticket_pda(&payer.pubkey(), &pool.address, 0);
+    assert_eq!(ctx.ticket(&ticket).price_paid, discounted);
+    assert_eq!(
+        events::<SharedTicketPurchasedEvent>(&bought)[0].ticket_price,
+        discounted
+    );
+    let streak = &events::<StreakUpdatedEvent>(&bought)[0];
+    assert_eq!((streak.user, streak.current_streak), (payer.pubkey(), 1));
+    assert_eq!(ctx.global_stats().total_tickets_sold, 1);
+
+    ctx.send_as_admin(ix::block_user(&admin, &payer.pubkey()))
+        .unwrap();
+    assert_error(
+        ctx.send(
+            ix::buy_shared_ticket(&payer.pubkey(), &pool.address, 1, shares),
+            &[&payer],
+        ),
+        ErrorCode::UserBlocked,
+    );
+}
+
+#[test]
+fn daily_withdrawal_cap_resets_after_a_day() {
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Daily Cap",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            company_pool::PoolConfig {
+                max_daily_withdrawal: Some(LAMPORTS_PER_SOL / 2),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    buy(&mut ctx, &pool, &player);
+    buy(&mut ctx, &pool, &player);
+
+    let third = LAMPORTS_PER_SOL / 3;
+    ctx.try_withdraw(&pool, &pool.authority, third).unwrap();
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, third),
+        company_pool::ErrorCode::DailyWithdrawalLimitExceeded,
+    );
+
+    ctx.warp(24 * 60 * 60);
+    ctx.try_withdraw(&pool, &pool.authority, third).unwrap();
+}
+
+#[test]
+fn deposits_are_withdrawable_and_reconciled() {
+    let mut ctx = TestContext::new();
+    let rent_reserve = ctx.svm.minimum_balance_for_rent_exemption(0);
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let sponsor = ctx.funded_user(2);
+    let amount = LAMPORTS_PER_SOL / 4;
+
+    ctx.send(
+        ix::deposit_to_vault(&sponsor.pubkey(), &pool.address, amount),
+        &[&sponsor],
+    )
+    .unwrap();
+    let state = ctx.pool(&pool);
+    assert_eq!(state.total_funds, amount);
+    assert_eq!(state.total_authority_deposits, amount);
+    assert_eq!(ctx.balance(&pool.vault), rent_reserve + amount);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+
+    ctx.try_withdraw(&pool, &pool.authority, amount).unwrap();
+    assert_eq!(ctx.balance(&pool.vault), rent_reserve);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+#[test]
+fn auto_payout_pays_on_spin() {
+    let mut ctx = TestContext::new();
+    let rent_reserve = ctx.svm.minimum_balance_for_rent_exemption(0);
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Auto Payout",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            company_pool::PoolConfig {
+                auto_payout: true,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    let ticket = buy(&mut ctx, &pool, &player);
+
+    let player_before = ctx.balance(&player.pubkey());
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+
+    // The spin pays for its own spin history account, so compare the vault
+    assert_eq!(
+        ctx.balance(&pool.prize_vault),
+        rent_reserve + PRIZE_SHARE - PRIZE
+    );
+    assert!(ctx.balance(&player.pubkey()) + TX_FEE > player_before);
+    assert!(ctx.ticket(&ticket).reward_claimed);
+    assert_eq!(ctx.pool(&pool).outstanding_liabilities, 0);
+    assert_error(
+        ctx.try_claim(&pool, &player, &ticket),
+        company_pool::ErrorCode::AutoPayoutPool,
+    );
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+#[test]
+fn large_rewards_vest_after_an_upfront_tranche() {
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Vesting",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                vesting_threshold_lamports: Some(PRIZE / 2),
+                vesting_duration_seconds: 1000,
+                vesting_upfront_bps: 2500,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    let claim_vested = |ctx: &mut TestContext| {
+        ctx.send(
+            ix::claim_vested(&player.pubkey(), &pool.address, &ticket),
+            &[&player],
+        )
+    };
+    assert_error(claim_vested(&mut ctx), ErrorCode::NoVestedReward);
+
+    // A quarter is paid at claim, the rest stays owed
+    let upfront = PRIZE / 4;
+    let before = ctx.balance(&player.pubkey());
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+    assert_eq!(ctx.balance(&player.pubkey()) + TX_FEE - before, upfront);
+    let state = ctx.ticket(&ticket);
+    assert!(state.reward_claimed);
+    assert_eq!(state.claimed_so_far, upfront);
+    assert_eq!(state.vested_total(), PRIZE);
+    assert_eq!(ctx.pool(&pool).outstanding_liabilities, PRIZE - upfront);
+    assert_error(
+        ctx.try_claim(&pool, &player, &ticket),
+        ErrorCode::RewardAlreadyClaimed,
+    );
+
+    // Half the duration unlocks half the remainder
+    ctx.warp(500);
+    let half = (PRIZE - upfront) / 2;
+    let before = ctx.balance(&player.pubkey());
+    claim_vested(&mut ctx).unwrap();
+    assert_eq!(ctx.balance(&player.pubkey()) + TX_FEE - before, half);
+    assert_eq!(
+        ctx.pool(&pool).outstanding_liabilities,
+        PRIZE - upfront - half
+    );
+    assert_error(claim_vested(&mut ctx), ErrorCode::NoVestedReward);
+
+    // The end of the duration unlocks the rest, and nothing after that
+    ctx.warp(600);
+    let before = ctx.balance(&player.pubkey());
+    claim_vested(&mut ctx).unwrap();
+    assert_eq!(
+        ctx.balance(&player.pubkey()) + TX_FEE - before,
+        PRIZE - upfront - half
+    );
+    assert_eq!(ctx.ticket(&ticket).claimed_so_far, PRIZE);
+    assert_eq!(ctx.pool(&pool).outstanding_liabilities, 0);
+    assert_eq!(ctx.pool(&pool).total_rewards_paid, PRIZE);
+    assert_error(claim_vested(&mut ctx), ErrorCode::NoVestedReward);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+
+    // Nobody else can release the owner's schedule
+    let stranger = ctx.funded_user(1);
+    assert_error(
+        ctx.send(
+            ix::claim_vested(&stranger.pubkey(), &pool.address, &ticket),
+            &[&stranger],
+        ),
+        ErrorCode::NotTicketOwner,
+    );
+}
+
+#[test]
+fn raffles_draw_one_ticket_per_item_after_the_end() {
+    let mut ctx = TestContext::new();
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Raffle",
+            TICKET_PRICE,
+            vec![item("First", PRIZE), item("Second", PRIZE / 2)],
+            PoolConfig {
+                pool_mode: PoolMode::Raffle,
+                ends_at: Some(now + 100),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let players: Vec<_> = (0..3).map(|_| ctx.funded_user(5)).collect();
+    let tickets: Vec<_> = players
+        .iter()
+        .map(|player| buy(&mut ctx, &pool, player))
+        .collect();
+    assert_error(
+        ctx.try_spin(&pool, &players[0], &tickets[0]),
+        ErrorCode::RaffleModePool,
+    );
+
+    let drawer = ctx.funded_user(1);
+    let draw = |ctx: &mut TestContext| {
+        ctx.send(ix::draw_raffle(&drawer.pubkey(), &pool.address), &[&drawer])
+    };
+    assert_error(draw(&mut ctx), ErrorCode::RaffleStillOpen);
+
+    // Give the draw a fresh slot hash to seed from
+    ctx.warp(101);
+    let slot = ctx.svm.get_sysvar::<Clock>().slot;
+    ctx.svm
+        .set_sysvar(&SlotHashes::new(&[(slot, Hash::new_unique())]));
+    draw(&mut ctx).unwrap();
+    assert!(draw(&mut ctx).is_err());
+
+    let result: RaffleResult = ctx.account(&ix::raffle_result_pda(&pool.address));
+    assert_eq!(result.tickets_in_draw, 3);
+    assert_eq!(result.winners.len(), 2);
+    assert_ne!(result.winners[0].ticket_id, result.winners[1].ticket_id);
+    assert_eq!(result.winners[0].won_item.price, PRIZE);
+    assert_eq!(result.winners[1].won_item.price, PRIZE / 2);
+    assert_eq!(ctx.pool(&pool).outstanding_liabilities, PRIZE + PRIZE / 2);
+
+    let claim = |ctx: &mut TestContext, ticket_id: u64| {
+        let index = ticket_id as usize;
+        ctx.send(
+            ix::claim_raffle_reward(&players[index].pubkey(), &pool.address, &tickets[index]),
+            &[&players[index]],
+        )
+    };
+    let loser = (0..3)
+        .find(|&ticket_id| result.prize_for(ticket_id).is_none())
+        .unwrap();
+    assert_error(claim(&mut ctx, loser), ErrorCode::NotRaffleWinner);
+
+    for winner in &result.winners {
+        let index = winner.ticket_id as usize;
+        let before = ctx.balance(&players[index].pubkey());
+        claim(&mut ctx, winner.ticket_id).unwrap();
+        assert_eq!(
+            ctx.balance(&players[index].pubkey()) + TX_FEE - before,
+            winner.won_item.price
+        );
+        assert!(ctx.ticket(&tickets[index]).reward_claimed);
+        assert_error(
+            claim(&mut ctx, winner.ticket_id),
+            ErrorCode::RewardAlreadyClaimed,
+        );
+    }
+    assert_eq!(ctx.pool(&pool).outstanding_liabilities, 0);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+#[test]
+fn sponsors_are_paid_their_share_of_sales_once() {
+    let mut ctx = TestContext::new();
+    let first = Pubkey::new_unique();
+    let second = Pubkey::new_unique();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Sponsored",
+            TICKET_PRICE,
+            vec![
+                PoolItemInput {
+                    sponsor: Some(first),
+                    sponsor_share_bps: 1000,
+                    ..item("First", PRIZE)
+                },
+                item("Unsponsored", PRIZE / 2),
+                PoolItemInput {
+                    sponsor: Some(second),
+                    sponsor_share_bps: 500,
+                    ..item("Second", PRIZE / 4)
+                },
+            ],
+            default_config(),
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    for _ in 0..3 {
+        buy(&mut ctx, &pool, &player);
+    }
+
+    let payer = ctx.funded_user(1);
+    assert_error(
+        ctx.send(
+            ix::distribute_sponsor_revenue(&pool.address, &[second, first]),
+            &[&payer],
+        ),
+        ErrorCode::SponsorAccountMismatch,
+    );
+
+    // The sponsors' shares can't be withdrawn from under them
+    let sales = 3 * TICKET_PRICE;
+    let first_share = sales / 10;
+    let second_share = sales / 20;
+    assert_error(
+        ctx.try_withdraw(&pool, &pool.authority, 3 * REVENUE_SHARE),
+        ErrorCode::InsufficientVaultFunds,
+    );
+
+    ctx.send(
+        ix::distribute_sponsor_revenue(&pool.address, &[first, second]),
+        &[&payer],
+    )
+    .unwrap();
+    assert_eq!(ctx.balance(&first), first_share);
+    assert_eq!(ctx.balance(&second), second_share);
+    let state = ctx.pool(&pool);
+    assert_eq!(state.sponsors[0].paid, first_share);
+    assert_eq!(state.sponsors[1].paid, second_share);
+    assert_eq!(
+        state.total_funds,
+        3 * REVENUE_SHARE - first_share - second_share
+    );
+
+    // Nothing new was sold, so nothing more is owed
+    ctx.send(
+        ix::distribute_sponsor_revenue(&pool.address, &[first, second]),
+        &[&payer],
+    )
+    .unwrap();
+    assert_eq!(ctx.balance(&first), first_share);
+    assert_eq!(ctx.balance(&second), second_share);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+
+    ctx.try_withdraw(&pool, &pool.authority, state.total_funds)
+        .unwrap();
+}
+
+#[test]
+fn streaks_reward_consecutive_days_of_play() {
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Streaks",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                loyalty_enabled: true,
+                loyalty_points_per_ticket: 1,
+                points_per_free_ticket: 100,
+                streak_milestones: vec![
+                    StreakMilestone {
+                        days: 2,
+                        bonus_points: 10,
+                    },
+                    StreakMilestone {
+                        days: 3,
+                        bonus_points: 30,
+                    },
+                ],
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(10);
+    let buy_today = |ctx: &mut TestContext| {
+        let sold = ctx.pool(&pool).total_tickets_sold;
+        let meta = ctx
+            .send(
+                ix::buy_ticket(&player.pubkey(), &pool.address, sold, None),
+                &[&player],
+            )
+            .unwrap();
+        events::<StreakUpdatedEvent>(&meta)
+    };
+    let user_state = |ctx: &TestContext| -> UserPoolState {
+        ctx.account(&ix::user_pool_state_pda(&pool.address, &player.pubkey()))
+    };
+
+    // Start on the last second of a UTC day
+    let day = UserPoolState::SECONDS_PER_DAY;
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    ctx.warp(day - 1 - now.rem_euclid(day));
+    let first_day = UserPoolState::utc_day(ctx.svm.get_sysvar::<Clock>().unix_timestamp);
+    let updates = buy_today(&mut ctx);
+    assert_eq!(updates[0].current_streak, 1);
+    assert!(!updates[0].milestone_hit);
+
+    // Midnight exactly is the next day
+    ctx.warp(1);
+    let updates = buy_today(&mut ctx);
+    assert_eq!(updates[0].current_streak, 2);
+    assert!(updates[0].milestone_hit);
+    assert_eq!(updates[0].bonus_points, 10);
+    assert_eq!(updates[0].loyalty_points, 2 + 10);
+
+    // A second purchase the same day doesn't move the streak
+    assert!(buy_today(&mut ctx).is_empty());
+    assert_eq!(user_state(&ctx).current_streak, 2);
+
+    ctx.warp(day);
+    let updates = buy_today(&mut ctx);
+    assert_eq!(updates[0].current_streak, 3);
+    assert_eq!(updates[0].bonus_points, 30);
+    assert_eq!(updates[0].loyalty_points, 4 + 10 + 30);
+
+    // Skipping a day starts the streak over
+    ctx.warp(2 * day);
+    let updates = buy_today(&mut ctx);
+    assert_eq!(updates[0].current_streak, 1);
+    assert!(!updates[0].milestone_hit);
+    let state = user_state(&ctx);
+    assert_eq!(state.current_streak, 1);
+    assert_eq!(state.last_played_day, first_day + 4);
+    assert_eq!(state.loyalty_points, 5 + 10 + 30);
+}
+
+fn loyalty_pool(ctx: &mut TestContext, company_name: &str) -> TestPool {
+    ctx.create_pool_with(
+        company_name,
+        TICKET_PRICE,
+        vec![item("Prize", PRIZE)],
+        PoolConfig {
+            loyalty_enabled: true,
+            loyalty_points_per_ticket: 5,
+            points_per_free_ticket: 100,
+            ..default_config()
+        },
+    )
+    .unwrap()
+    .0
+}
+
+// Points reset by a season are only cleared once each player is next seen,
+// however many seasons went by in between
+#[test]
+fn season_reset_clears_points_on_the_next_purchase() {
+    let mut ctx = TestContext::new();
+    let pool = loyalty_pool(&mut ctx, "Season Reset");
+    let (alice, bob) = (ctx.funded_user(10), ctx.funded_user(10));
+    for player in [&alice, &bob, &alice, &bob] {
+        buy(&mut ctx, &pool, player);
+    }
+    let user_state = |ctx: &TestContext, player: &Keypair| -> UserPoolState {
+        ctx.account(&ix::user_pool_state_pda(&pool.address, &player.pubkey()))
+    };
+    assert_eq!(user_state(&ctx, &alice).loyalty_points, 10);
+
+    assert_error(
+        ctx.send(
+            ix::start_new_season(
+                &alice.pubkey(),
+                &pool.address,
+                0,
+                SeasonResetFlags::default(),
+            ),
+            &[&alice],
+        ),
+        ErrorCode::Unauthorized,
+    );
+    let started = ctx
+        .send(
+            ix::start_new_season(
+                &pool.authority.pubkey(),
+                &pool.address,
+                0,
+                SeasonResetFlags {
+                    loyalty_points: true,
+                    ..SeasonResetFlags::default()
+                },
+            ),
+            &[&pool.authority],
+        )
+        .unwrap();
+    let event = &events::<SeasonStartedEvent>(&started)[0];
+    assert_eq!((event.season, event.previous_tickets_sold), (1, 4));
+    assert_eq!(ctx.pool(&pool).season(), 1);
+
+    // Nobody's state changes until they come back
+    assert_eq!(user_state(&ctx, &alice).loyalty_points, 10);
+    assert_eq!(user_state(&ctx, &alice).last_season, 0);
+    buy(&mut ctx, &pool, &alice);
+    let state = user_state(&ctx, &alice);
+    assert_eq!((state.loyalty_points, state.last_season), (5, 1));
+    assert_eq!(user_state(&ctx, &bob).loyalty_points, 10);
+
+    // A later season that resets nothing still leaves Bob owing the reset
+    ctx.send(
+        ix::start_new_season(
+            &pool.authority.pubkey(),
+            &pool.address,
+            1,
+            SeasonResetFlags::default(),
+        ),
+        &[&pool.authority],
+    )
+    .unwrap();
+    buy(&mut ctx, &pool, &alice);
+    buy(&mut ctx, &pool, &bob);
+    assert_eq!(user_state(&ctx, &alice).loyalty_points, 10);
+    let state = user_state(&ctx, &bob);
+    assert_eq!((state.loyalty_points, state.last_season), (5, 2));
+
+    let archive: SeasonArchive = ctx.account(&ix::season_archive_pda(&pool.address, 1));
+    assert_eq!(archive.tickets_sold, 1);
+    assert_eq!(archive.reset_flags, SeasonResetFlags::default());
+}
+
+// Without reset flags a season carries every player's progress over, and
+// the pool's stats and leaderboard reset only when asked, after the
+// archive has kept them
+#[test]
+fn season_without_resets_carries_progress_and_archives_the_season() {
+    let mut ctx = TestContext::new();
+    let pool = loyalty_pool(&mut ctx, "Season Carry");
+    let player = ctx.funded_user(10);
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+
+    ctx.send(
+        ix::start_new_season(
+            &pool.authority.pubkey(),
+            &pool.address,
+            0,
+            SeasonResetFlags {
+                stats: true,
+                leaderboard: true,
+                ..SeasonResetFlags::default()
+            },
+        ),
+        &[&pool.authority],
+    )
+    .unwrap();
+
+    let archive: SeasonArchive = ctx.account(&ix::season_archive_pda(&pool.address, 0));
+    assert_eq!(archive.season, 0);
+    assert_eq!(archive.tickets_sold, 1);
+    assert_eq!(archive.ticket_revenue, TICKET_PRICE);
+    assert_eq!(archive.total_spins, 1);
+    assert_eq!(archive.total_rewards_paid, PRIZE);
+    assert_eq!(archive.top_winners[0].winner, player.pubkey());
+    assert_eq!(archive.top_winners[0].total_value_won, PRIZE);
+    let stats: PoolStats = ctx.account(&pool.stats);
+    assert_eq!((stats.total_spins, stats.total_rewards_paid), (0, 0));
+    assert!(ctx.leaderboard(&pool).is_empty());
+
+    // Tickets keep counting up, and the points carry into the new season
+    buy(&mut ctx, &pool, &player);
+    let state: UserPoolState =
+        ctx.account(&ix::user_pool_state_pda(&pool.address, &player.pubkey()));
+    assert_eq!((state.loyalty_points, state.last_season), (10, 1));
+    assert_eq!(ctx.pool(&pool).total_tickets_sold, 2);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+}
+
+#[test]
+fn achievements_unlock_and_pay_their_bonus_once() {
+    const BONUS: u64 = LAMPORTS_PER_SOL / 100;
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Achievements",
+            TICKET_PRICE,
+            vec![PoolItemInput {
+                tier: Some(ItemTier::Legendary),
+                ..item("Crown", PRIZE)
+            }],
+            PoolConfig {
+                achievement_bonus_lamports: BONUS,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(12);
+    let claim = |ctx: &mut TestContext, achievement: Achievement| {
+        ctx.send(
+            ix::claim_achievement_reward(&player.pubkey(), &pool.address, achievement),
+            &[&player],
+        )
+    };
+
+    let ticket = buy(&mut ctx, &pool, &player);
+    assert_error(
+        claim(&mut ctx, Achievement::FirstSpin),
+        ErrorCode::AchievementLocked,
+    );
+
+    // The first spin is also the first legendary win
+    let meta = ctx.try_spin(&pool, &player, &ticket).unwrap();
+    let unlocked: Vec<Achievement> = events::<AchievementUnlockedEvent>(&meta)
+        .iter()
+        .map(|event| event.achievement)
+        .collect();
+    assert_eq!(
+        unlocked,
+        vec![Achievement::FirstSpin, Achievement::FirstLegendary]
+    );
+
+    for spin in 2..=10 {
+        let ticket = buy(&mut ctx, &pool, &player);
+        let meta = ctx.try_spin(&pool, &player, &ticket).unwrap();
+        let unlocked = events::<AchievementUnlockedEvent>(&meta);
+        if spin < 10 {
+            assert!(unlocked.is_empty());
+        } else {
+            assert_eq!(unlocked[0].achievement, Achievement::TenSpins);
+            assert_eq!(unlocked[0].achievements, 0b0111);
+        }
+    }
+    let state: UserPoolState =
+        ctx.account(&ix::user_pool_state_pda(&pool.address, &player.pubkey()));
+    assert_eq!(state.achievements, 0b0111);
+    assert_eq!(
+        (state.spins, state.legendary_wins, state.jackpot_wins),
+        (10, 10, 0)
+    );
+
+    // The bonus comes out of the authority's revenue, once per achievement
+    let funds_before = ctx.pool(&pool).total_funds;
+    let balance_before = ctx.balance(&player.pubkey());
+    let meta = claim(&mut ctx, Achievement::TenSpins).unwrap();
+    assert_eq!(
+        events::<AchievementRewardClaimedEvent>(&meta)[0].amount,
+        BONUS
+    );
+    assert_eq!(
+        ctx.balance(&player.pubkey()),
+        balance_before + BONUS - TX_FEE
+    );
+    assert_eq!(ctx.pool(&pool).total_funds, funds_before - BONUS);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+
+    assert_error(
+        claim(&mut ctx, Achievement::TenSpins),
+        ErrorCode::AchievementAlreadyRewarded,
+    );
+    assert_error(
+        claim(&mut ctx, Achievement::JackpotWinner),
+        ErrorCode::AchievementLocked,
+    );
+    claim(&mut ctx, Achievement::FirstSpin).unwrap();
+    let state: UserPoolState =
+        ctx.account(&ix::user_pool_state_pda(&pool.address, &player.pubkey()));
+    assert_eq!(
+        state.achievements_rewarded,
+        Achievement::FirstSpin.bit() | Achievement::TenSpins.bit()
+    );
+}
+
+#[test]
+fn item_boosts_apply_only_during_their_window() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(
+        &mut ctx,
+        vec![item("Sticker", PRIZE / 10), item("MacBook", PRIZE)],
+    );
+    let player = ctx.funded_user(5);
+    let authority = pool.authority.insecure_clone();
+    let spin = |ctx: &mut TestContext| {
+        let ticket = buy(ctx, &pool, &player);
+        let meta = ctx.try_spin(&pool, &player, &ticket).unwrap();
+        events::<SpinResultEvent>(&meta)[0].boost_active
+    };
+
+    // A weekend of double odds on the MacBook, starting in an hour
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let (starts_at, ends_at) = (now + 3600, now + 3600 + 2 * 86400);
+    let meta = ctx
+        .send(
+            ix::set_item_boost(
+                &authority.pubkey(),
+                &pool.address,
+                1,
+                20000,
+                starts_at,
+                ends_at,
+            ),
+            &[&authority],
+        )
+        .unwrap();
+    let set = &events::<ItemBoostSetEvent>(&meta)[0];
+    assert_eq!((set.item_id, set.multiplier_bps), (1, 20000));
+    let state = ctx.pool(&pool);
+    assert_eq!(state.boosts[0].ends_at, ends_at);
+    let configured = state.items[1].probability;
+    assert_eq!(
+        state.boosted_probability(&state.items[1], starts_at),
+        2 * configured
+    );
+    assert!(!spin(&mut ctx));
+
+    ctx.warp(3600);
+    assert!(spin(&mut ctx));
+
+    // The odds revert on their own once the window closes
+    ctx.warp(2 * 86400);
+    assert!(!spin(&mut ctx));
+    let state = ctx.pool(&pool);
+    assert_eq!(state.items[1].probability, configured);
+    assert_eq!(
+        state.boosted_probability(&state.items[1], ends_at),
+        configured
+    );
+}
+
+#[test]
+fn mystery_items_claim_only_after_reveal() {
+    let mut ctx = TestContext::new();
+    let (name, image, description) = (
+        "MacBook Pro",
+        "https://example.com/macbook.png",
+        "16 inch, space black",
+    );
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Mystery",
+            TICKET_PRICE,
+            vec![PoolItemInput {
+                reveal_hash: Some(PendingReveal::hash_of(name, image, description)),
+                ..item("Mystery Box", PRIZE)
+            }],
+            default_config(),
+        )
+        .unwrap();
+    let authority = pool.authority.insecure_clone();
+    let player = ctx.funded_user(5);
+    let reveal = |ctx: &mut TestContext, name: &str| {
+        ctx.send(
+            ix::reveal_item(
+                &authority.pubkey(),
+                &pool.address,
+                0,
+                name,
+                image,
+                description,
+            ),
+            &[&authority],
+        )
+    };
+
+    // Only the placeholder is on-chain, so analysis can't give the prize away
+    let meta = ctx
+        .send(
+            ix::get_probability_analysis(&pool.address, 0, 1),
+            &[&player],
+        )
+        .unwrap();
+    assert_eq!(
+        events::<ProbabilityAnalysisEvent>(&meta)[0].item_name,
+        "Mystery Box"
+    );
+
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    assert_error(
+        ctx.try_claim(&pool, &player, &ticket),
+        ErrorCode::ItemNotRevealed,
+    );
+
+    assert_error(reveal(&mut ctx, "MacBook Air"), ErrorCode::RevealMismatch);
+    let meta = reveal(&mut ctx, name).unwrap();
+    assert_eq!(events::<ItemRevealedEvent>(&meta)[0].name, name);
+    let revealed = ctx.pool(&pool).items.remove(0);
+    assert_eq!(
+        (revealed.name.as_str(), revealed.image.as_str()),
+        (name, image)
+    );
+    assert_error(reveal(&mut ctx, name), ErrorCode::NoPendingReveal);
+
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+}
+
+#[test]
+fn serials_count_each_item_s_wins_separately() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(
+        &mut ctx,
+        vec![item("Hoodie", PRIZE / 4), item("Cap", PRIZE / 4)],
+    );
+    let player = ctx.funded_user(20);
+
+    // Equal prices give even odds, so both items come up within a few spins
+    let mut serials: [Vec<u32>; 2] = Default::default();
+    for _ in 0..16 {
+        let ticket = buy(&mut ctx, &pool, &player);
+        let spun = ctx.try_spin(&pool, &player, &ticket).unwrap();
+        let spin = &events::<SpinResultEvent>(&spun)[0];
+        let Some(index) = spin.item_index else {
+            assert_eq!(spin.serial, None);
+            continue;
+        };
+        let serial = spin.serial.unwrap();
+        assert_eq!(ctx.ticket(&ticket).serial(), Some(serial));
+        let claimed = ctx.try_claim(&pool, &player, &ticket).unwrap();
+        assert_eq!(
+            events::<RewardClaimedEvent>(&claimed)[0].serial,
+            Some(serial)
+        );
+        serials[index as usize].push(serial);
+    }
+
+    // Each item counts up from 1 on its own, whatever the other one drew
+    let items = ctx.pool(&pool).items;
+    for (serials, item) in serials.iter().zip(&items) {
+        let expected: Vec<u32> = (1..=item.times_won).collect();
+        assert_eq!(serials, &expected);
+    }
+    assert!(serials.iter().all(|won| !won.is_empty()), "{:?}", serials);
+}
+
+#[test]
+fn wrapped_claims_pay_into_the_winner_s_wsol_account() {
+    let mut ctx = TestContext::new();
+    ctx.add_native_mint();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+    let wsol = ix::wsol_account(&player.pubkey());
+    let claim_wrapped = |ctx: &mut TestContext, ticket: &Pubkey| {
+        ctx.send(
+            ix::claim_reward_wrapped(&player.pubkey(), &pool.address, ticket),
+            &[&player],
+        )
+        .unwrap()
+    };
+
+    // The first wrapped claim creates the account at the winner's expense
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    let before = ctx.balance(&player.pubkey());
+    let claimed = claim_wrapped(&mut ctx, &ticket);
+    let event = &events::<RewardClaimedEvent>(&claimed)[0];
+    assert!(event.wrapped);
+    assert_eq!(event.reward_amount, PRIZE);
+    let account_rent = ctx
+        .svm
+        .minimum_balance_for_rent_exemption(TokenAccount::LEN);
+    assert_eq!(
+        ctx.balance(&player.pubkey()),
+        before - account_rent - TX_FEE
+    );
+    let token_account: TokenAccount = ctx.account(&wsol);
+    assert_eq!(token_account.owner, player.pubkey());
+    assert_eq!(token_account.amount, PRIZE);
+    assert_eq!(ctx.ticket(&ticket).state(), TicketState::Claimed);
+
+    // Later claims add to what the account already holds
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    let before = ctx.balance(&player.pubkey());
+    claim_wrapped(&mut ctx, &ticket);
+    assert_eq!(ctx.balance(&player.pubkey()), before - TX_FEE);
+    assert_eq!(ctx.account::<TokenAccount>(&wsol).amount, 2 * PRIZE);
+    assert_error(
+        ctx.send(
+            ix::claim_reward_wrapped(&player.pubkey(), &pool.address, &ticket),
+            &[&player],
+        ),
+        ErrorCode::RewardAlreadyClaimed,
+    );
+
+    // Native claims still say so
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    let claimed = ctx.try_claim(&pool, &player, &ticket).unwrap();
+    assert!(!events::<RewardClaimedEvent>(&claimed)[0].wrapped);
+}
+
+#[test]
+fn token_prizes_pay_from_their_escrow_and_reclaim_once_retired() {
+    let mut ctx = TestContext::new();
+    let mint = ctx.add_mint();
+    let pool = create_pool(
+        &mut ctx,
+        vec![
+            item("Cash", PRIZE),
+            PoolItemInput {
+                item_kind: ItemKind::Token,
+                reward_mint: Some(mint),
+                reward_amount: 1_000,
+                ..item("Tokens", PRIZE)
+            },
+        ],
+    );
+    let authority = pool.authority.pubkey();
+    let escrow = ix::prize_escrow(&pool.address, &mint);
+    let authority_tokens = ctx.add_token_account(&authority, &mint, 2_500);
+    let set_available = |ctx: &mut TestContext, item_id: u32, available: bool| {
+        ctx.send(
+            ix::set_item_availability(&authority, &pool.address, item_id, available),
+            &[&pool.authority],
+        )
+    };
+
+    // Token items stay out of the draw until their escrow can pay a win
+    assert!(!ctx.pool(&pool).items[1].available);
+    assert_error(
+        set_available(&mut ctx, 1, true),
+        ErrorCode::TokenPrizeDepleted,
+    );
+    for amount in [1_000, 1_500] {
+        ctx.send(
+            ix::deposit_item_tokens(&authority, &pool.address, &mint, 1, amount),
+            &[&pool.authority],
+        )
+        .unwrap();
+    }
+    assert_eq!(ctx.account::<TokenAccount>(&escrow).amount, 2_500);
+    assert_eq!(ctx.account::<TokenAccount>(&authority_tokens).amount, 0);
+    assert_eq!(ctx.pool(&pool).token_prize(1).unwrap().escrowed, 2_500);
+
+    // Leave the token item alone in the draw so the spin lands on it
+    set_available(&mut ctx, 1, true).unwrap();
+    set_available(&mut ctx, 0, false).unwrap();
+    let player = ctx.funded_user(5);
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    let won_item = ctx.ticket(&ticket).won_item.unwrap();
+    assert_eq!(won_item.item_id, 1);
+    assert_eq!(won_item.vault_liability(), 0);
+    let prize = *ctx.pool(&pool).token_prize(1).unwrap();
+    assert_eq!((prize.escrowed, prize.owed), (1_500, 1_000));
+
+    // Token wins claim into the winner's token account, never in SOL
+    assert_error(
+        ctx.try_claim(&pool, &player, &ticket),
+        ErrorCode::TokenPrizeAccountsMissing,
+    );
+    let player_tokens = ctx.add_token_account(&player.pubkey(), &mint, 0);
+    let vault_before = ctx.balance(&pool.prize_vault);
+    ctx.send(
+        ix::claim_token_prize(&player.pubkey(), &pool.address, &ticket, &mint),
+        &[&player],
+    )
+    .unwrap();
+    assert_eq!(ctx.account::<TokenAccount>(&player_tokens).amount, 1_000);
+    assert_eq!(ctx.account::<TokenAccount>(&escrow).amount, 1_500);
+    assert_eq!(ctx.balance(&pool.prize_vault), vault_before);
+    assert_eq!(ctx.ticket(&ticket).state(), TicketState::Claimed);
+    assert_eq!(ctx.pool(&pool).token_prize(1).unwrap().owed, 0);
+
+    // The escrow stays locked while the item can still be won
+    let reclaim = |ctx: &mut TestContext| {
+        ctx.send(
+            ix::reclaim_item_tokens(&authority, &pool.address, &mint, 1),
+            &[&pool.authority],
+        )
+    };
+    assert_error(reclaim(&mut ctx), ErrorCode::TokenPrizeActive);
+
+    // Retired, whatever is left goes back to the authority
+    set_available(&mut ctx, 0, true).unwrap();
+    set_available(&mut ctx, 1, false).unwrap();
+    reclaim(&mut ctx).unwrap();
+    assert_eq!(ctx.account::<TokenAccount>(&authority_tokens).amount, 1_500);
+    assert_eq!(ctx.account::<TokenAccount>(&escrow).amount, 0);
+    assert_error(reclaim(&mut ctx), ErrorCode::NothingToReclaim);
+    assert_error(
+        set_available(&mut ctx, 1, true),
+        ErrorCode::TokenPrizeDepleted,
+    );
+}
+
+#[test]
+fn spins_replay_from_their_recorded_draw_table() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(
+        &mut ctx,
+        vec![
+            item("Common", PRIZE / 4),
+            item("Middle", PRIZE / 2),
+            item("Rare", PRIZE),
+        ],
+    );
+    let player = ctx.funded_user(5);
+    let ticket = buy(&mut ctx, &pool, &player);
+    assert_error(
+        ctx.send(
+            ix::verify_spin(&pool.address, &ticket, DrawTable::default()),
+            &[&player],
+        ),
+        ErrorCode::SpinNotReplayable,
+    );
+
+    // The table is rebuilt from the pool as it stood at the spin
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let table = ctx.pool(&pool).draw_table(None, now);
+    let record = ctx.ticket(&ticket).draw_record.unwrap();
+    assert_eq!(record.table_hash, table.hash());
+    let drawn = ctx
+        .ticket(&ticket)
+        .won_item
+        .map(|won_item| won_item.item_id);
+    assert_eq!(record.drawn_index, drawn);
+
+    let verified = ctx
+        .send(
+            ix::verify_spin(&pool.address, &ticket, table.clone()),
+            &[&player],
+        )
+        .unwrap();
+    let event = &events::<SpinVerifiedEvent>(&verified)[0];
+    assert_eq!(event.company_pool, pool.address);
+    assert_eq!(event.random_seed, record.random_seed);
+    assert_eq!(event.drawn_index, record.drawn_index);
+
+    // A table with any weight moved no longer matches what was drawn from
+    let mut tampered = table;
+    tampered.weights.swap(0, 2);
+    assert_error(
+        ctx.send(
+            ix::verify_spin(&pool.address, &ticket, tampered),
+            &[&player],
+        ),
+        ErrorCode::SpinVerificationFailed,
+    );
+}
+
+#[test]
+fn server_seeded_spins_draw_in_committed_batches() {
+    let mut ctx = TestContext::new();
+    let first_seed = [11; 32];
+    let first_hash = ServerSeedCommitment::hash_of(&first_seed);
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Seeded",
+            TICKET_PRICE,
+            vec![item("Common", PRIZE / 4), item("Rare", PRIZE)],
+            PoolConfig {
+                server_seed_hash: Some(first_hash),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let authority = &pool.authority;
+    let commit = |seed_hash| ix::commit_server_seed(&authority.pubkey(), &pool.address, seed_hash);
+    let reveal = |seed| ix::reveal_server_seed(&authority.pubkey(), &pool.address, seed);
+    let player = ctx.funded_user(5);
+
+    // The commitment made at init can't be swapped while spins draw under it
+    let second_seed = [22; 32];
+    let second_hash = ServerSeedCommitment::hash_of(&second_seed);
+    assert_error(
+        ctx.send(commit(second_hash), &[authority]),
+        ErrorCode::ServerSeedAlreadyCommitted,
+    );
+
+    // Every spin of the batch reports the commitment and still replays
+    for _ in 0..2 {
+        let ticket = buy(&mut ctx, &pool, &player);
+        let spun = ctx.try_spin(&pool, &player, &ticket).unwrap();
+        let event = &events::<SpinResultEvent>(&spun)[0];
+        assert_eq!(event.server_seed_hash, Some(first_hash));
+        assert_eq!(
+            ctx.ticket(&ticket).draw_record.unwrap().random_seed,
+            event.random_seed
+        );
+        let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+        let table = ctx.pool(&pool).draw_table(None, now);
+        ctx.send(ix::verify_spin(&pool.address, &ticket, table), &[&player])
+            .unwrap();
+    }
+
+    // Only the committed seed closes the batch
+    assert_error(
+        ctx.send(reveal(second_seed), &[authority]),
+        ErrorCode::ServerSeedMismatch,
+    );
+    let revealed = ctx.send(reveal(first_seed), &[authority]).unwrap();
+    let event = &events::<ServerSeedRevealedEvent>(&revealed)[0];
+    assert_eq!(event.seed, first_seed);
+    assert_eq!(ServerSeedCommitment::hash_of(&event.seed), event.seed_hash);
+    assert_eq!(event.seed_hash, first_hash);
+    assert_eq!(event.spins, 2);
+    assert_eq!(ctx.pool(&pool).server_seed.previous_seed, first_seed);
+
+    // Spins wait for the next commitment, then draw under it until it lapses
+    let ticket = buy(&mut ctx, &pool, &player);
+    assert_error(
+        ctx.try_spin(&pool, &player, &ticket),
+        ErrorCode::ServerSeedCommitmentMissing,
+    );
+    let committed = ctx.send(commit(second_hash), &[authority]).unwrap();
+    let event = &events::<ServerSeedCommittedEvent>(&committed)[0];
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    assert_eq!(
+        event.expires_at,
+        now + ServerSeedCommitment::LIFETIME_SECONDS
+    );
+    let spun = ctx.try_spin(&pool, &player, &ticket).unwrap();
+    assert_eq!(
+        events::<SpinResultEvent>(&spun)[0].server_seed_hash,
+        Some(second_hash)
+    );
+
+    ctx.warp(ServerSeedCommitment::LIFETIME_SECONDS);
+    let ticket = buy(&mut ctx, &pool, &player);
+    assert_error(
+        ctx.try_spin(&pool, &player, &ticket),
+        ErrorCode::ServerSeedCommitmentMissing,
+    );
+
+    // Other pools have no commitments to make
+    let plain = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    assert_error(
+        ctx.send(
+            ix::commit_server_seed(&plain.authority.pubkey(), &plain.address, first_hash),
+            &[&plain.authority],
+        ),
+        ErrorCode::ServerSeedsDisabled,
+    );
+}
+
+#[test]
+fn dispute_window_holds_wins_while_the_admin_may_void_them() {
+    let mut ctx = TestContext::new();
+    let config = PoolConfig {
+        dispute_window_seconds: 600,
+        ..default_config()
+    };
+    assert_error(
+        ctx.create_pool_with(
+            "Auto",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                auto_payout: true,
+                ..config.clone()
+            },
+        ),
+        ErrorCode::InvalidPoolConfig,
+    );
+    let (pool, _) = ctx
+        .create_pool_with("Disputed", TICKET_PRICE, vec![item("Prize", PRIZE)], config)
+        .unwrap();
+    let player = ctx.funded_user(5);
+    let admin = ctx.admin.pubkey();
+    let void = |signer: &Pubkey, ticket: &Pubkey| {
+        ix::void_spin(signer, &pool.address, ticket, &player.pubkey())
+    };
+
+    // Wins can't be claimed while they could still be voided
+    let kept = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &kept).unwrap();
+    assert_error(
+        ctx.try_claim(&pool, &player, &kept),
+        ErrorCode::DisputeWindowOpen,
+    );
+
+    // Voiding returns the ticket price and releases the prize
+    let voided = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &voided).unwrap();
+    assert_error(
+        ctx.send(void(&pool.authority.pubkey(), &voided), &[&pool.authority]),
+        ErrorCode::Unauthorized,
+    );
+    let player_before = ctx.balance(&player.pubkey());
+    let sent = ctx.send_as_admin(void(&admin, &voided)).unwrap();
+    let event = &events::<SpinVoidedEvent>(&sent)[0];
+    assert_eq!(event.owner, player.pubkey());
+    assert_eq!(event.refund, TICKET_PRICE);
+    assert_eq!(event.voided_item.as_ref().unwrap().price, PRIZE);
+    assert_eq!(ctx.balance(&player.pubkey()) - player_before, TICKET_PRICE);
+    assert!(ctx.ticket(&voided).won_item.is_none());
+    assert_eq!(ctx.ticket(&voided).state(), TicketState::Claimed);
+    assert_eq!(ctx.pool(&pool).outstanding_liabilities, PRIZE);
+    assert_eq!(ctx.reconcile(&pool).delta, 0);
+
+    // Once the window closes the win settles as usual and can't be voided
+    ctx.warp(600);
+    assert_error(
+        ctx.send_as_admin(void(&admin, &kept)),
+        ErrorCode::DisputeWindowClosed,
+    );
+    ctx.try_claim(&pool, &player, &kept).unwrap();
+    assert_error(
+        ctx.try_claim(&pool, &player, &voided),
+        ErrorCode::RewardAlreadyClaimed,
+    );
+}
+
+// By default anyone may play, the pool's own authority included. A pool can
+// bar its authority and operators instead, whoever pays for the ticket.
+#[test]
+fn pools_can_bar_their_authority_from_playing() {
+    let mut ctx = TestContext::new();
+    let (open_pool, meta) = ctx
+        .create_pool_with(
+            "Open House",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            default_config(),
+        )
+        .unwrap();
+    assert!(events::<PoolInitializedEvent>(&meta)[0].authority_can_play);
+    let authority = open_pool.authority.insecure_clone();
+    buy(&mut ctx, &open_pool, &authority);
+
+    let (pool, meta) = ctx
+        .create_pool_with(
+            "Players Only",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                authority_play_disabled: true,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    assert!(!events::<PoolInitializedEvent>(&meta)[0].authority_can_play);
+    assert_error(
+        ctx.try_buy(&pool, &pool.authority),
+        ErrorCode::AuthorityCannotPlay,
+    );
+
+    let player = ctx.funded_user(5);
+    let gift = |recipient: &Pubkey, sold: u64| {
+        ix::gift_ticket(&player.pubkey(), recipient, &pool.address, sold)
+    };
+    assert_error(
+        ctx.send(gift(&pool.authority.pubkey(), 0), &[&player]),
+        ErrorCode::AuthorityCannotPlay,
+    );
+    let friend = Keypair::new().pubkey();
+    ctx.send(gift(&friend, 0), &[&player]).unwrap();
+    buy(&mut ctx, &pool, &player);
+    assert_eq!(ctx.pool(&pool).total_tickets_sold, 2);
+}
+
+// Clients simulate get_spin_odds and read the odds from the return data, to
+// show them before a purchase
+#[test]
+fn spin_odds_come_back_as_return_data() {
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Odds",
+            TICKET_PRICE,
+            vec![item("Laptop", PRIZE), item("Sticker", PRIZE / 10)],
+            PoolConfig {
+                no_win_bps: 2000,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let probability = ctx.pool(&pool).items[0].probability;
+    let player = ctx.funded_user(1);
+
+    let meta = ctx
+        .send(ix::get_spin_odds(&pool.address, 0, 10), &[&player])
+        .unwrap();
+    let odds = SpinOdds::deserialize(&mut meta.return_data.data.as_slice()).unwrap();
+    let chance = spin_chance(probability, 2000);
+    assert_eq!(odds.chance, chance);
+    assert_eq!(odds.probability_in_spins, probability_in_spins(chance, 10));
+    assert_eq!(
+        events::<SpinOddsEvent>(&meta)[0].probability_in_spins,
+        odds.probability_in_spins
+    );
+
+    let p = probability as f64 / 10000.0 * 0.8;
+    let reference = 1.0 - (1.0 - p).powi(10);
+    let scale = ODDS_SCALE as f64;
+    assert!((odds.probability_in_spins as f64 / scale - reference).abs() < 1e-8);
+    assert!((odds.expected_spins.unwrap() as f64 / scale - 1.0 / p).abs() < 1e-6);
+
+    assert_error(
+        ctx.send(ix::get_spin_odds(&pool.address, 2, 10), &[&player]),
+        ErrorCode::InvalidItemIndex,
+    );
+}
+
+// A clone copies the source's items and settings into a pool and vaults of
+// its own, starting with no sales. Only the source's authority can clone it.
+#[test]
+fn cloned_pools_copy_the_source_and_start_from_zero() {
+    let mut ctx = TestContext::new();
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let (source, _) = ctx
+        .create_pool_with(
+            "Season One",
+            TICKET_PRICE,
+            vec![item("Laptop", PRIZE), item("Sticker", PRIZE / 10)],
+            PoolConfig {
+                max_tickets_per_user: Some(3),
+                trade_in_bps: 2500,
+                ends_at: Some(now + 1000),
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    buy(&mut ctx, &source, &player);
+    let authority = source.authority.insecure_clone();
+    let clone = |name: &str, overrides: CloneOverrides| {
+        ix::clone_pool(&authority.pubkey(), &source.address, name, overrides)
+    };
+
+    // The first season has ended, so its end time can't be copied as is
+    ctx.warp(2000);
+    assert_error(
+        ctx.send(
+            clone("Season Two", CloneOverrides::default()),
+            &[&authority],
+        ),
+        ErrorCode::InvalidPoolConfig,
+    );
+    let ends_at = now + 10_000;
+    let meta = ctx
+        .send(
+            clone(
+                "Season Two",
+                CloneOverrides {
+                    ends_at: Some(ends_at),
+                    ..CloneOverrides::default()
+                },
+            ),
+            &[&authority],
+        )
+        .unwrap();
+    let address = ix::pool_pda("Season Two");
+    let event = &events::<PoolClonedEvent>(&meta)[0];
+    assert_eq!(event.source_pool, source.address);
+    assert_eq!(event.company_pool, address);
+
+    let original = ctx.pool(&source);
+    let copy: CompanyPool = ctx.account(&address);
+    assert_eq!(copy.company_name, "Season Two");
+    assert_eq!(copy.authority, authority.pubkey());
+    assert_eq!(copy.ticket_price, TICKET_PRICE);
+    assert_eq!(copy.items.len(), original.items.len());
+    for (copied, item) in copy.items.iter().zip(&original.items) {
+        assert_eq!(copied.name, item.name);
+        assert_eq!(copied.price, item.price);
+        assert_eq!(copied.probability, item.probability);
+        assert_eq!(copied.tier, item.tier);
+    }
+    assert_eq!(copy.max_tickets_per_user, Some(3));
+    assert_eq!(copy.trade_in_bps, 2500);
+    assert_eq!(copy.ends_at, Some(ends_at));
+    assert_eq!(copy.total_tickets_sold, 0);
+    assert_eq!(copy.total_funds, 0);
+    assert_eq!(
+        ctx.balance(&ix::vault_pda(&address)),
+        ctx.svm.minimum_balance_for_rent_exemption(0)
+    );
+    assert_eq!(ctx.pool(&source).total_tickets_sold, 1);
+
+    // A dearer ticket applies; a cheaper one would pay out more per lamport
+    let overrides = |ticket_price: u64| CloneOverrides {
+        ticket_price: Some(ticket_price),
+        ends_at: Some(ends_at),
+    };
+    assert_error(
+        ctx.send(clone("Bargain", overrides(TICKET_PRICE / 2)), &[&authority]),
+        ErrorCode::ExpectedValueTooHigh,
+    );
+    ctx.send(
+        clone("Season Three", overrides(2 * TICKET_PRICE)),
+        &[&authority],
+    )
+    .unwrap();
+    let dearer: CompanyPool = ctx.account(&ix::pool_pda("Season Three"));
+    assert_eq!(dearer.ticket_price, 2 * TICKET_PRICE);
+    assert_eq!(
+        dearer
+            .items
+            .iter()
+            .map(|item| item.probability)
+            .sum::<u32>(),
+        10000
+    );
+
+    let stranger = ctx.funded_user(5);
+    assert_error(
+        ctx.send(
+            ix::clone_pool(
+                &stranger.pubkey(),
+                &source.address,
+                "Knockoff",
+                overrides(TICKET_PRICE),
+            ),
+            &[&stranger],
+        ),
+        ErrorCode::Unauthorized,
+    );
+}
+
+// Tickets from every buyer count against the pool's per-slot limit, a bundle
+// with its full size, until the next slot. Each transaction lands in a slot of
+// its own, so the purchases that share one are sent together.
+#[test]
+fn slot_limits_hold_purchases_back_until_the_next_slot() {
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Launch Day",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                max_tickets_per_slot: Some(3),
+                bundles: vec![BundleDefinition {
+                    size: 2,
+                    price: 2 * TICKET_PRICE,
+                    guaranteed_tier: ItemTier::Common,
+                }],
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let bot = ctx.funded_user(20);
+    let player = ctx.funded_user(5);
+    let buy_ix =
+        |user: &Keypair, ticket_id| ix::buy_ticket(&user.pubkey(), &pool.address, ticket_id, None);
+
+    // Two tickets and a bundle of two are one too many; the bundle fails and
+    // takes the whole transaction with it
+    assert_error_at(
+        ctx.send_all(
+            &[
+                buy_ix(&bot, 0),
+                buy_ix(&bot, 1),
+                ix::buy_bundle(&bot.pubkey(), &pool.address, 2, 2),
+            ],
+            &[&bot],
+        ),
+        2,
+        ErrorCode::SlotPurchaseLimitReached,
+    );
+    assert_eq!(ctx.pool(&pool).total_tickets_sold, 0);
+    assert!(ctx
+        .svm
+        .get_account(&ix::ticket_pda(&bot.pubkey(), &pool.address, 0))
+        .is_none());
+
+    ctx.send_all(
+        &[
+            buy_ix(&bot, 0),
+            ix::buy_bundle(&bot.pubkey(), &pool.address, 1, 2),
+        ],
+        &[&bot],
+    )
+    .unwrap();
+    let filled = ctx.pool(&pool);
+    assert_eq!(filled.total_tickets_sold, 3);
+    assert_eq!(filled.purchases_in_slot, 3);
+
+    // A fresh slot starts from zero, and another buyer's ticket counts too
+    assert_error_at(
+        ctx.send_all(
+            &[
+                buy_ix(&bot, 3),
+                buy_ix(&bot, 4),
+                buy_ix(&bot, 5),
+                buy_ix(&player, 6),
+            ],
+            &[&bot, &player],
+        ),
+        3,
+        ErrorCode::SlotPurchaseLimitReached,
+    );
+    ctx.send_all(
+        &[buy_ix(&bot, 3), buy_ix(&bot, 4), buy_ix(&player, 5)],
+        &[&bot, &player],
+    )
+    .unwrap();
+    let refilled = ctx.pool(&pool);
+    assert_eq!(refilled.total_tickets_sold, 6);
+    assert!(refilled.last_purchase_slot > filled.last_purchase_slot);
+}
+
+// Moves the clock forward to the next time the UTC hour is `hour`
+fn warp_to_utc_hour(ctx: &mut TestContext, hour: i64) {
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    ctx.warp((hour * 3600 - now.rem_euclid(86400)).rem_euclid(86400));
+}
+
+// The happy hour takes its discount off tickets bought inside its UTC window,
+// one that ends before it starts wraps past midnight; the odds stay those of
+// the full price
+#[test]
+fn happy_hour_discounts_tickets_inside_its_window() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let authority = pool.authority.insecure_clone();
+    let player = ctx.funded_user(10);
+    let odds = ctx.pool(&pool).items[0].probability;
+
+    assert_error(
+        ctx.send(
+            ix::set_happy_hour(&authority.pubkey(), &pool.address, 22, 2, 10000),
+            &[&authority],
+        ),
+        ErrorCode::InvalidHappyHour,
+    );
+    ctx.send(
+        ix::set_happy_hour(&authority.pubkey(), &pool.address, 22, 2, 2000),
+        &[&authority],
+    )
+    .unwrap();
+    let discounted = TICKET_PRICE - TICKET_PRICE / 5;
+
+    for (hour, price) in [
+        (21, TICKET_PRICE),
+        (23, discounted),
+        (1, discounted),
+        (2, TICKET_PRICE),
+    ] {
+        warp_to_utc_hour(&mut ctx, hour);
+        let before = ctx.pool(&pool);
+        let sold = before.total_tickets_sold;
+        let bought = ctx
+            .send(
+                ix::buy_ticket(&player.pubkey(), &pool.address, sold, None),
+                &[&player],
+            )
+            .unwrap();
+        let ticket = ix::ticket_pda(&player.pubkey(), &pool.address, sold);
+        assert_eq!(ctx.ticket(&ticket).price_paid, price);
+        assert_eq!(
+            events::<TicketPurchasedEvent>(&bought)[0].discount,
+            TICKET_PRICE - price
+        );
+
+        // Only what was paid reaches the pool's books
+        let after = ctx.pool(&pool);
+        assert_eq!(after.total_funds - before.total_funds, price / 2);
+        assert_eq!(
+            after.total_ticket_revenue - before.total_ticket_revenue,
+            price
+        );
+        assert_eq!(after.items[0].probability, odds);
+    }
+}
+
+#[test]
+fn stuck_spins_return_to_unspun_and_spin_again() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+    let stranger = ctx.funded_user(1);
+    let ticket = buy(&mut ctx, &pool, &player);
+
+    // No instruction leaves a draw pending yet, so request one by hand
+    let requested_at = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    ctx.edit_ticket(&ticket, |state| {
+        state.request_draw(requested_at, 0).unwrap()
+    });
+    assert_eq!(ctx.ticket(&ticket).state(), TicketState::SpinPending);
+
+    // A pending ticket can't be spun again or claimed
+    assert_error(
+        ctx.try_spin(&pool, &player, &ticket),
+        ErrorCode::TicketAlreadyUsed,
+    );
+    assert_error(
+        ctx.try_claim(&pool, &player, &ticket),
+        ErrorCode::TicketNotUsed,
+    );
+
+    // Only the authority may cancel before the timeout
+    let cancel =
+        |cranker: &Keypair| ix::cancel_stuck_spin(&cranker.pubkey(), &pool.address, &ticket);
+    assert_error(
+        ctx.send(cancel(&stranger), &[&stranger]),
+        ErrorCode::SpinNotStuck,
+    );
+    ctx.warp(UserTicket::SPIN_PENDING_TIMEOUT_SECONDS);
+    let cancelled = ctx.send(cancel(&stranger), &[&stranger]).unwrap();
+    let event = &events::<SpinCancelledEvent>(&cancelled)[0];
+    assert_eq!((event.ticket_id, event.owner), (0, player.pubkey()));
+    assert_eq!(event.requested_at, requested_at);
+    assert_eq!(event.cranker, stranger.pubkey());
+    let state = ctx.ticket(&ticket);
+    assert_eq!(state.state(), TicketState::Unspun);
+    assert_eq!((state.spun_at, state.spun_slot), (0, 0));
+    assert_error(
+        ctx.send(cancel(&stranger), &[&stranger]),
+        ErrorCode::SpinNotPending,
+    );
+
+    // The recovered ticket spins and claims as if nothing had happened
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    assert_eq!(ctx.ticket(&ticket).state(), TicketState::Spun);
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+    assert_eq!(ctx.ticket(&ticket).state(), TicketState::Claimed);
+
+    // The authority doesn't have to wait out the timeout
+    let second = buy(&mut ctx, &pool, &player);
+    ctx.edit_ticket(&second, |state| {
+        state.request_draw(requested_at, 0).unwrap()
+    });
+    ctx.send(
+        ix::cancel_stuck_spin(&pool.authority.pubkey(), &pool.address, &second),
+        &[&pool.authority],
+    )
+    .unwrap();
+    assert_eq!(ctx.ticket(&second).state(), TicketState::Unspun);
+}
+
+#[test]
+fn leaderboard_ranks_the_biggest_winners() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let win = |ctx: &mut TestContext, player: &Keypair| {
+        let ticket = buy(ctx, &pool, player);
+        ctx.try_spin(&pool, player, &ticket).unwrap();
+        let claimed = ctx.try_claim(&pool, player, &ticket).unwrap();
+        events::<RewardClaimedEvent>(&claimed)[0].leaderboard_rank
+    };
+
+    // Equal totals keep the order they were reached in
+    let players: Vec<Keypair> = (0..Leaderboard::SIZE).map(|_| ctx.funded_user(5)).collect();
+    for (i, player) in players.iter().enumerate() {
+        assert_eq!(win(&mut ctx, player), Some(i as u8 + 1));
+    }
+
+    // Tying the bottom entry isn't enough to get on a full board
+    let latecomer = ctx.funded_user(5);
+    assert_eq!(win(&mut ctx, &latecomer), None);
+
+    // A repeat winner's total grows in place
+    assert_eq!(win(&mut ctx, &players[9]), Some(1));
+    let board = ctx.leaderboard(&pool);
+    assert_eq!(board.len(), Leaderboard::SIZE);
+    assert_eq!(board[0].winner, players[9].pubkey());
+    assert_eq!(board[0].total_value_won, 2 * PRIZE);
+    assert_eq!(
+        board
+            .iter()
+            .filter(|entry| entry.winner == players[9].pubkey())
+            .count(),
+        1
+    );
+
+    // A bigger win pushes the bottom entry off
+    ctx.edit_pool(&pool, |state| state.items[0].price = 3 * PRIZE);
+    ctx.svm.airdrop(&pool.prize_vault, 2 * PRIZE).unwrap();
+    assert_eq!(win(&mut ctx, &latecomer), Some(1));
+    let board = ctx.leaderboard(&pool);
+    let ranked: Vec<Pubkey> = board.iter().map(|entry| entry.winner).collect();
+    let mut expected = vec![latecomer.pubkey(), players[9].pubkey()];
+    expected.extend(players[..8].iter().map(|player| player.pubkey()));
+    assert_eq!(ranked, expected);
+    assert_eq!(board[0].total_value_won, 3 * PRIZE);
+}
+
+#[test]
+fn legacy_tickets_migrate_and_refund_rent() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+    let ticket_key = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket_key).unwrap();
+    let ticket = ctx.ticket(&ticket_key);
+
+    // Rewrite the spun ticket as an old client would have stored it
+    let legacy = LegacyUserTicket {
+        owner: ticket.owner,
+        company_pool: ticket.company_pool,
+        purchased_at: ticket.purchased_at,
+        used: true,
+        ticket_id: ticket.ticket_id,
+        won_item: Some(LegacyWonItem {
+            name: "Prize".to_string(),
+            price: PRIZE,
+            image: "https://example.com/prize.png".to_string(),
+            description: "A prize".to_string(),
+            item_index: 0,
+            item_id: 0,
+            item_kind: ItemKind::SolPayout,
+            tier: ItemTier::Common,
+        }),
+        reward_claimed: false,
+        gambled: false,
+        traded_in: false,
+        spun_at: ticket.spun_at,
+        reward_expired: false,
+        reward_nft_mint: None,
+        fulfillment_pending: false,
+        shipping_info_hash: None,
+        price_paid: ticket.price_paid,
+        consolation_amount: 0,
+        rerolled: false,
+        forced_min_tier: None,
+    };
+    let legacy_len = 8 + LegacyUserTicket::INIT_SPACE;
+    let mut data = UserTicket::DISCRIMINATOR.to_vec();
+    legacy.serialize(&mut data).unwrap();
+    data.resize(legacy_len, 0);
+    let legacy_rent = ctx.svm.minimum_balance_for_rent_exemption(legacy_len);
+    ctx.svm
+        .set_account(
+            ticket_key,
+            Account {
+                lamports: legacy_rent,
+                data,
+                owner: company_pool::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        )
+        .unwrap();
+
+    let balance_before = ctx.balance(&player.pubkey());
+    ctx.send(
+        ix::migrate_ticket(&player.pubkey(), &pool.address, &ticket_key),
+        &[&player],
+    )
+    .unwrap();
+
+    let current_rent = ctx
+        .svm
+        .minimum_balance_for_rent_exemption(8 + UserTicket::INIT_SPACE);
+    assert_eq!(
+        ctx.balance(&player.pubkey()),
+        balance_before + legacy_rent - current_rent - TX_FEE
+    );
+    let migrated = ctx.ticket(&ticket_key);
+    let won_item = migrated.won_item.as_ref().unwrap();
+    assert_eq!((won_item.item_id, won_item.price), (0, PRIZE));
+    assert_eq!(migrated.ticket_id, ticket.ticket_id);
+
+    // The migrated ticket claims like any other
+    ctx.try_claim(&pool, &player, &ticket_key).unwrap();
+
+    assert_error(
+        ctx.send(
+            ix::migrate_ticket(&player.pubkey(), &pool.address, &ticket_key),
+            &[&player],
+        ),
+        ErrorCode::AccountAlreadyMigrated,
+    );
+}
+
+// Version 1 pools and tickets are the current layout minus the leading
+// version byte, so dropping it recreates one. Both also shrink back to
+// their version 2 size, without any of the fields later versions appended
+// (see the table in migration.rs).
+fn downgrade_pool_to_version_one(ctx: &mut TestContext, address: Pubkey) {
+    let mut account = ctx.svm.get_account(&address).unwrap();
+    let table_start = 8 + CompanyPool::INIT_SPACE
+        - migration::CUMULATIVE_TABLE_SPACE
+        - migration::POOL_FLAGS_SPACE
+        - migration::CPI_PURCHASES_SPACE
+        - migration::ENDS_AT_SPACE
+        - migration::EVENT_SEQ_SPACE
+        - migration::SPIN_OPERATOR_SPACE
+        - migration::VESTING_SPACE
+        - migration::POOL_MODE_SPACE
+        - migration::SPONSORS_SPACE
+        - migration::STREAKS_SPACE
+        - migration::ACHIEVEMENT_BONUS_SPACE
+        - migration::BOOSTS_SPACE
+        - migration::REVEALS_SPACE
+        - migration::BENEFICIARY_SPACE
+        - migration::MIN_ITEM_PRICE_SPACE
+        - migration::TOKEN_PRIZES_SPACE
+        - migration::SERVER_SEEDS_SPACE
+        - migration::DISPUTE_WINDOW_SPACE
+        - migration::AUTHORITY_PLAY_SPACE
+        - migration::HAPPY_HOUR_SPACE
+        - migration::SLOT_LIMIT_SPACE
+        - migration::DONATIONS_SPACE
+        - migration::MEGA_JACKPOT_SPACE
+        - migration::INSURANCE_SPACE
+        - migration::SEASONS_SPACE
+        - migration::SHARED_TICKETS_SPACE;
+    account.data.truncate(table_start);
+    account.data.remove(8);
+    account.lamports = ctx
+        .svm
+        .minimum_balance_for_rent_exemption(account.data.len());
+    ctx.svm.set_account(address, account).unwrap();
+}
+
+fn downgrade_ticket_to_version_one(ctx: &mut TestContext, address: Pubkey) {
+    let mut account = ctx.svm.get_account(&address).unwrap();
+    let slots_start = 8 + UserTicket::INIT_SPACE
+        - migration::TICKET_SLOTS_SPACE
+        - migration::TICKET_EXTERNAL_ID_SPACE
+        - migration::TICKET_VESTING_SPACE
+        - migration::TICKET_SERIAL_SPACE
+        - migration::TICKET_SPIN_PENDING_SPACE
+        - migration::TICKET_DRAW_RECORD_SPACE
+        - migration::TICKET_MEGA_JACKPOT_SPACE
+        - migration::TICKET_INSURANCE_SPACE;
+    account.data.truncate(slots_start);
+    account.data.remove(8);
+    account.lamports = ctx
+        .svm
+        .minimum_balance_for_rent_exemption(account.data.len());
+    ctx.svm.set_account(address, account).unwrap();
+}
+
+// Version 1 user states have no version byte, and this one has the size it
+// had before the streak
+fn downgrade_user_pool_state_to_version_one(ctx: &mut TestContext, address: Pubkey) {
+    let mut account = ctx.svm.get_account(&address).unwrap();
+    let groups = &migration::USER_STATE_APPENDED_SPACE;
+    account.data.remove(8);
+    account
+        .data
+        .truncate(8 + UserPoolState::INIT_SPACE - 1 - groups[2] - groups[3] - groups[4]);
+    account.lamports = ctx
+        .svm
+        .minimum_balance_for_rent_exemption(account.data.len());
+    ctx.svm.set_account(address, account).unwrap();
+}
+
+#[test]
+fn version_one_accounts_migrate_and_keep_working() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+    let ticket_key = buy(&mut ctx, &pool, &player);
+
+    downgrade_pool_to_version_one(&mut ctx, pool.address);
+    downgrade_ticket_to_version_one(&mut ctx, ticket_key);
+
+    // Anyone can migrate the pool, the ticket needs its owner
+    let payer = ctx.funded_user(1);
+    ctx.send(ix::migrate_pool(&payer.pubkey(), &pool.address), &[&payer])
+        .unwrap();
+    ctx.send(
+        ix::migrate_ticket(&player.pubkey(), &pool.address, &ticket_key),
+        &[&player],
+    )
+    .unwrap();
+
+    let state = ctx.pool(&pool);
+    assert_eq!(state.version, CompanyPool::VERSION);
+    assert_eq!(state.total_tickets_sold, 1);
+    assert_eq!(ctx.ticket(&ticket_key).version, UserTicket::VERSION);
+    assert_error(
+        ctx.send(ix::migrate_pool(&payer.pubkey(), &pool.address), &[&payer]),
+        ErrorCode::AccountAlreadyMigrated,
+    );
+
+    // The migrated ticket spins and claims, and the pool keeps selling
+    ctx.try_spin(&pool, &player, &ticket_key).unwrap();
+    ctx.try_claim(&pool, &player, &ticket_key).unwrap();
+    assert!(ctx.ticket(&ticket_key).reward_claimed);
+
+    let second_ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &second_ticket).unwrap();
+    ctx.try_claim(&pool, &player, &second_ticket).unwrap();
+    assert_eq!(ctx.pool(&pool).total_tickets_sold, 2);
+}
+
+// A pool state from before its version byte, at the size it had before the
+// streak, must be migrated before the player can buy again, and keeps its
+// purchases and points
+#[test]
+fn user_pool_states_without_a_version_migrate_and_keep_their_progress() {
+    let mut ctx = TestContext::new();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Old Player",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                loyalty_enabled: true,
+                loyalty_points_per_ticket: 3,
+                points_per_free_ticket: 100,
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(5);
+    buy(&mut ctx, &pool, &player);
+
+    let address = ix::user_pool_state_pda(&pool.address, &player.pubkey());
+    downgrade_user_pool_state_to_version_one(&mut ctx, address);
+    assert!(ctx.try_buy(&pool, &player).is_err());
+
+    let payer = ctx.funded_user(1);
+    let migrated = ctx
+        .send(
+            ix::migrate_user_pool_state(&payer.pubkey(), &pool.address, &player.pubkey()),
+            &[&payer],
+        )
+        .unwrap();
+    let event = &events::<AccountMigratedEvent>(&migrated)[0];
+    assert_eq!(
+        (event.from_version, event.to_version),
+        (1, UserPoolState::VERSION)
+    );
+    let state: UserPoolState = ctx.account(&address);
+    assert_eq!(state.version, UserPoolState::VERSION);
+    assert_eq!((state.tickets_bought, state.loyalty_points), (1, 3));
+    assert_eq!(state.current_streak, 0);
+    assert_error(
+        ctx.send(
+            ix::migrate_user_pool_state(&payer.pubkey(), &pool.address, &player.pubkey()),
+            &[&payer],
+        ),
+        ErrorCode::AccountAlreadyMigrated,
+    );
+
+    buy(&mut ctx, &pool, &player);
+    let state: UserPoolState = ctx.account(&address);
+    assert_eq!((state.tickets_bought, state.loyalty_points), (2, 6));
+}
+
+#[test]
+fn allowlisted_creation_admits_only_added_creators() {
+    let mut ctx = TestContext::new();
+    let admin = ctx.admin.pubkey();
+    let company = ctx.funded_user(10);
+    let create = |ctx: &mut TestContext, name: &str| {
+        ctx.create_pool_as(
+            company.insecure_clone(),
+            name,
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            default_config(),
+        )
+    };
+
+    ctx.send_as_admin(ix::set_pool_creation_mode(
+        &admin,
+        CreationMode::Allowlisted,
+    ))
+    .unwrap();
+    assert_error(
+        create(&mut ctx, "Curated"),
+        ErrorCode::CreatorNotAllowlisted,
+    );
+    assert_error(
+        ctx.send(
+            ix::initialize_company_pool_v2(
+                &company.pubkey(),
+                TICKET_PRICE,
+                "Curated v2",
+                vec![item("Prize", PRIZE)],
+                PoolV2Config::default(),
+            ),
+            &[&company],
+        ),
+        ErrorCode::CreatorNotAllowlisted,
+    );
+
+    ctx.send_as_admin(ix::add_pool_creator(&admin, &company.pubkey()))
+        .unwrap();
+    let (pool, _) = create(&mut ctx, "Curated").unwrap();
+
+    // Removing the creator stops new pools, not the ones it already runs
+    ctx.send_as_admin(ix::remove_pool_creator(&admin, &company.pubkey()))
+        .unwrap();
+    assert_error(
+        create(&mut ctx, "Curated 2"),
+        ErrorCode::CreatorNotAllowlisted,
+    );
+    let player = ctx.funded_user(5);
+    let ticket = buy(&mut ctx, &pool, &player);
+    ctx.try_spin(&pool, &player, &ticket).unwrap();
+    ctx.try_claim(&pool, &player, &ticket).unwrap();
+
+    // Open mode lets anyone create pools again
+    ctx.send_as_admin(ix::set_pool_creation_mode(&admin, CreationMode::Open))
+        .unwrap();
+    create(&mut ctx, "Curated 2").unwrap();
+}
+
+#[test]
+fn only_the_config_admin_manages_pool_creators() {
+    let mut ctx = TestContext::new();
+    let intruder = ctx.funded_user(1);
+
+    for instruction in [
+        ix::set_pool_creation_mode(&intruder.pubkey(), CreationMode::Allowlisted),
+        ix::add_pool_creator(&intruder.pubkey(), &intruder.pubkey()),
+    ] {
+        assert_error(ctx.send(instruction, &[&intruder]), ErrorCode::Unauthorized);
+    }
+    let config: GlobalConfig = ctx.account(&ix::global_config_pda());
+    assert!(config.creation_mode == CreationMode::Open);
+}
+
+#[test]
+fn blocked_users_cannot_buy_but_keep_their_tickets() {
+    let mut ctx = TestContext::new();
+    let admin = ctx.admin.pubkey();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let authority = ctx.funded_user(10);
+    ctx.send(
+        ix::initialize_company_pool_v2(
+            &authority.pubkey(),
+            TICKET_PRICE,
+            "Blocked fast pool",
+            vec![item("Prize", PRIZE)],
+            PoolV2Config {
+                prize_allocation_bps: 5000,
+                max_ev_bps: 9000,
+                ..PoolV2Config::default()
+            },
+        ),
+        &[&authority],
+    )
+    .unwrap();
+    let fast = ix::pool_v2_pda("Blocked fast pool");
+    let player = ctx.funded_user(5);
+    let held = buy(&mut ctx, &pool, &player);
+
+    ctx.send_as_admin(ix::block_user(&admin, &player.pubkey()))
+        .unwrap();
+    assert_error(ctx.try_buy(&pool, &player), ErrorCode::UserBlocked);
+    assert_error(
+        ctx.send(ix::buy_ticket_v2(&player.pubkey(), &fast, 0), &[&player]),
+        ErrorCode::UserBlocked,
+    );
+
+    // Blocking stops purchases only; the ticket bought before still pays out
+    ctx.try_spin(&pool, &player, &held).unwrap();
+    ctx.try_claim(&pool, &player, &held).unwrap();
+    assert!(ctx.ticket(&held).reward_claimed);
+
+    ctx.send_as_admin(ix::unblock_user(&admin, &player.pubkey()))
+        .unwrap();
+    assert!(ctx
+        .svm
+        .get_account(&ix::blocked_user_pda(&player.pubkey()))
+        .map_or(true, |account| account.lamports == 0));
+    buy(&mut ctx, &pool, &player);
+    ctx.send(ix::buy_ticket_v2(&player.pubkey(), &fast, 0), &[&player])
+        .unwrap();
+
+    // Only the config admin manages the list
+    let intruder = ctx.funded_user(1);
+    assert_error(
+        ctx.send(
+            ix::block_user(&intruder.pubkey(), &player.pubkey()),
+            &[&intruder],
+        ),
+        ErrorCode::Unauthorized,
+    );
+}
+
+// Bundles, vouchers, loyalty points and gifts are all ways into a pool, so
+// each one turns a blocked wallet away; a gift fails from either end
+#[test]
+fn blocked_users_cannot_get_tickets_any_other_way() {
+    let mut ctx = TestContext::new();
+    let admin = ctx.admin.pubkey();
+    let (pool, _) = ctx
+        .create_pool_with(
+            "Blocked Side Doors",
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            PoolConfig {
+                loyalty_enabled: true,
+                loyalty_points_per_ticket: 5,
+                points_per_free_ticket: 5,
+                bundles: vec![BundleDefinition {
+                    size: 2,
+                    price: 2 * TICKET_PRICE,
+                    guaranteed_tier: ItemTier::Common,
+                }],
+                ..default_config()
+            },
+        )
+        .unwrap();
+    let player = ctx.funded_user(10);
+    let friend = ctx.funded_user(10);
+    buy(&mut ctx, &pool, &player);
+    ctx.send(
+        ix::issue_voucher(&pool.authority.pubkey(), &pool.address, &player.pubkey(), 1),
+        &[&pool.authority],
+    )
+    .unwrap();
+
+    ctx.send_as_admin(ix::block_user(&admin, &player.pubkey()))
+        .unwrap();
+    let attempts = [
+        ix::buy_bundle(&player.pubkey(), &pool.address, 1, 2),
+        ix::redeem_voucher(&player.pubkey(), &pool.address, 1),
+        ix::redeem_points_for_ticket(&player.pubkey(), &pool.address, 1),
+        ix::gift_ticket(&player.pubkey(), &friend.pubkey(), &pool.address, 1),
+    ];
+    for attempt in attempts {
+        assert_error(ctx.send(attempt, &[&player]), ErrorCode::UserBlocked);
+    }
+    assert_error(
+        ctx.send(
+            ix::gift_ticket(&friend.pubkey(), &player.pubkey(), &pool.address, 1),
+            &[&friend],
+        ),
+        ErrorCode::UserBlocked,
+    );
+
+    // The voucher and the points are still there once the block is lifted
+    ctx.send_as_admin(ix::unblock_user(&admin, &player.pubkey()))
+        .unwrap();
+    ctx.send(
+        ix::redeem_voucher(&player.pubkey(), &pool.address, 1),
+        &[&player],
+    )
+    .unwrap();
+    ctx.send(
+        ix::redeem_points_for_ticket(&player.pubkey(), &pool.address, 2),
+        &[&player],
+    )
+    .unwrap();
+    assert_eq!(ctx.pool(&pool).total_tickets_sold, 3);
+}
+
+#[test]
+fn global_configs_from_before_the_creation_mode_migrate_as_open_and_free() {
+    let mut ctx = TestContext::new();
+    let address = ix::global_config_pda();
+    let mut account = ctx.svm.get_account(&address).unwrap();
+    account.data.truncate(
+        8 + GlobalConfig::INIT_SPACE
+            - migration::CREATION_FEE_SPACE
+            - migration::CREATION_MODE_SPACE,
+    );
+    account.lamports = ctx
+        .svm
+        .minimum_balance_for_rent_exemption(account.data.len());
+    ctx.svm.set_account(address, account).unwrap();
+
+    let payer = ctx.funded_user(1);
+    ctx.send(ix::migrate_global_config(&payer.pubkey()), &[&payer])
+        .unwrap();
+    let config: GlobalConfig = ctx.account(&address);
+    assert_eq!(config.admin, ctx.admin.pubkey());
+    assert!(config.creation_mode == CreationMode::Open);
+    assert_eq!(config.treasury, ctx.admin.pubkey());
+    assert_eq!(config.pool_creation_fee_lamports, 0);
+    assert_error(
+        ctx.send(ix::migrate_global_config(&payer.pubkey()), &[&payer]),
+        ErrorCode::AccountAlreadyMigrated,
+    );
+
+    // Pools can be created against the migrated config
+    create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+}
+
+#[test]
+fn pool_creation_pays_the_configured_fee_to_the_treasury() {
+    const FEE: u64 = LAMPORTS_PER_SOL / 2;
+    let mut ctx = TestContext::new();
+    let admin = ctx.admin.pubkey();
+    let treasury = Pubkey::new_unique();
+    ctx.send_as_admin(ix::update_creation_fee(&admin, FEE, &treasury))
+        .unwrap();
+
+    let company = ctx.funded_user(10);
+    ctx.create_pool_as(
+        company,
+        "Fee",
+        TICKET_PRICE,
+        vec![item("Prize", PRIZE)],
+        default_config(),
+    )
+    .unwrap();
+    assert_eq!(ctx.balance(&treasury), FEE);
+
+    // The fee can't be dodged by leaving out or swapping the treasury
+    let company = ctx.funded_user(10);
+    for wrong_treasury in [None, Some(company.pubkey())] {
+        assert_error(
+            ctx.send(
+                ix::initialize_company_pool(
+                    &company.pubkey(),
+                    None,
+                    wrong_treasury,
+                    TICKET_PRICE,
+                    "Fee 2",
+                    vec![item("Prize", PRIZE)],
+                    default_config(),
+                ),
+                &[&company],
+            ),
+            ErrorCode::InvalidTreasury,
+        );
+    }
+
+    // A zero fee stops the charge and the treasury is no longer needed
+    ctx.send_as_admin(ix::update_creation_fee(&admin, 0, &treasury))
+        .unwrap();
+    ctx.send(
+        ix::initialize_company_pool(
+            &company.pubkey(),
+            None,
+            None,
+            TICKET_PRICE,
+            "Fee 2",
+            vec![item("Prize", PRIZE)],
+            default_config(),
+        ),
+        &[&company],
+    )
+    .unwrap();
+    assert_eq!(ctx.balance(&treasury), FEE);
+}
+
+#[test]
+fn only_the_config_admin_sets_the_creation_fee() {
+    let mut ctx = TestContext::new();
+    let intruder = ctx.funded_user(1);
+    assert_error(
+        ctx.send(
+            ix::update_creation_fee(&intruder.pubkey(), 1, &intruder.pubkey()),
+            &[&intruder],
+        ),
+        ErrorCode::Unauthorized,
+    );
+    let config = ctx.global_config().unwrap();
+    assert_eq!(config.pool_creation_fee_lamports, 0);
+    assert_eq!(config.treasury, ctx.admin.pubkey());
+}
+
+#[test]
+fn pools_cannot_be_created_before_the_global_config() {
+    let mut ctx = TestContext::new();
+    ctx.svm
+        .set_account(ix::global_config_pda(), Account::default())
+        .unwrap();
+
+    let company = ctx.funded_user(10);
+    assert_error(
+        ctx.send(
+            ix::initialize_company_pool(
+                &company.pubkey(),
+                None,
+                None,
+                TICKET_PRICE,
+                "Too Early",
+                vec![item("Prize", PRIZE)],
+                default_config(),
+            ),
+            &[&company],
+        ),
+        anchor_lang::error::ErrorCode::AccountNotInitialized,
+    );
+}
+
+#[test]
+fn global_stats_add_up_across_pools() {
+    const FEE: u64 = LAMPORTS_PER_SOL / 10;
+    let mut ctx = TestContext::new();
+    let admin = ctx.admin.pubkey();
+    ctx.send_as_admin(ix::update_creation_fee(&admin, FEE, &Pubkey::new_unique()))
+        .unwrap();
+
+    let pools = ["Stats A", "Stats B"].map(|name| {
+        ctx.create_pool_with(
+            name,
+            TICKET_PRICE,
+            vec![item("Prize", PRIZE)],
+            default_config(),
+        )
+        .unwrap()
+        .0
+    });
+    let player = ctx.funded_user(10);
+    for pool in &pools {
+        let ticket = buy(&mut ctx, pool, &player);
+        ctx.try_spin(pool, &player, &ticket).unwrap();
+        ctx.try_claim(pool, &player, &ticket).unwrap();
+    }
+    buy(&mut ctx, &pools[0], &player);
+
+    let stats = ctx.global_stats();
+    assert_eq!(stats.total_pools_created, 2);
+    assert_eq!(stats.total_tickets_sold, 3);
+    assert_eq!(stats.total_rewards_paid, 2 * PRIZE);
+    assert_eq!(stats.total_platform_fees, 2 * FEE);
+}
+
+#[test]
+fn clients_without_global_stats_skip_the_update() {
+    let mut ctx = TestContext::new();
+    let pool = create_pool(&mut ctx, vec![item("Prize", PRIZE)]);
+    let player = ctx.funded_user(5);
+
+    let buy_ticket = |ctx: &mut TestContext, with_stats: bool| {
+        let sold = ctx.pool(&pool).total_tickets_sold;
+        let instruction = ix::buy_ticket(&player.pubkey(), &pool.address, sold, None);
+        let instruction = if with_stats {
+            instruction
+        } else {
+            ix::without_global_stats(instruction)
+        };
+        ctx.send(instruction, &[&player]).unwrap()
+    };
+    // The first purchase also creates the buyer's pool state, so measure after it
+    buy_ticket(&mut ctx, true);
+    let without = buy_ticket(&mut ctx, false);
+    assert_eq!(ctx.global_stats().total_tickets_sold, 1);
+    let with = buy_ticket(&mut ctx, true);
+    assert_eq!(ctx.global_stats().total_tickets_sold, 2);
+
+    // The PDA check and the extra account dominate the cost
+    assert_cu_below(&with, BUY_TICKET_CU);
+    assert!(with.compute_units_consumed > without.compute_units_consumed);
+    let overhead = with.compute_units_consumed - without.compute_units_consumed;
+    assert!(
+        overhead <= GLOBAL_STATS_OVERHEAD_CU,
+        "global stats cost {} CU, ceiling is {}",
+        overhead,
+        GLOBAL_STATS_OVERHEAD_CU
+    );
+}
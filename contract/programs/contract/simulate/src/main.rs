@@ -0,0 +1,77 @@
+// Answers "if this many tickets sell, what do I make and how much could I
+// lose?" for a pool before it exists. Amounts are in lamports.
+
+use company_pool::simulation::{simulate_pool_with_trials, DEFAULT_TRIALS};
+use company_pool::units::Lamports;
+
+const USAGE: &str = "usage: simulate <ticket price> <tickets> <name=value>... \
+                     [--vault <lamports>] [--seed <n>] [--trials <n>]";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let ticket_price: u64 = args.next().ok_or(USAGE)?.parse()?;
+    let num_tickets: u64 = args.next().ok_or(USAGE)?.parse()?;
+
+    let mut items = Vec::new();
+    let mut starting_vault = 0u64;
+    let mut seed = 0u64;
+    let mut trials = DEFAULT_TRIALS;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--vault" => starting_vault = args.next().ok_or(USAGE)?.parse()?,
+            "--seed" => seed = args.next().ok_or(USAGE)?.parse()?,
+            "--trials" => trials = args.next().ok_or(USAGE)?.parse()?,
+            item => {
+                let (name, value) = item.split_once('=').ok_or(USAGE)?;
+                items.push((name.to_string(), value.parse::<u64>()?));
+            }
+        }
+    }
+    if items.is_empty() || ticket_price == 0 {
+        return Err(USAGE.into());
+    }
+
+    let report = simulate_pool_with_trials(&items, ticket_price, num_tickets, seed, trials);
+
+    println!(
+        "{} tickets at {}, {} trials, seed {}",
+        num_tickets,
+        Lamports(ticket_price).to_sol_display(),
+        trials,
+        seed
+    );
+    println!(
+        "{:<24} {:>16} {:>8} {:>10} {:>14}",
+        "item", "value", "bps", "sim bps", "wins per sale"
+    );
+    for (index, item) in report.items.iter().enumerate() {
+        println!(
+            "{:<24} {:>16} {:>8} {:>10.4} {:>14.2}",
+            item.name,
+            item.value,
+            item.probability,
+            report.win_frequency(index) * 10000.0,
+            item.wins as f64 / trials.max(1) as f64
+        );
+    }
+
+    println!();
+    println!("revenue          {:>20}", report.revenue());
+    println!("mean payout      {:>20.0}", report.mean_payout());
+    println!("payout std dev   {:>20.0}", report.payout_std_dev());
+    println!("expected profit  {:>20.0}", report.expected_profit());
+    for percent in [5, 25, 50, 75, 95] {
+        println!(
+            "profit p{:<2}       {:>20}",
+            percent,
+            report.profit_percentile(percent)
+        );
+    }
+    println!(
+        "insolvency       {:>19.2}%  starting from a {} vault",
+        report.insolvency_probability(starting_vault) * 100.0,
+        Lamports(starting_vault).to_sol_display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,313 @@
+// Instruction builders and account readers for Rust clients (indexers, bots,
+// admin tooling), so they don't have to hand-roll instruction data. Off-chain
+// only, behind the `client` feature.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+
+use crate::{
+    accounts, instruction, CompanyPool, GlobalConfig, PoolConfig, PoolItemInput, PoolStats,
+    UserTicket,
+};
+
+pub fn derive_global_config_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"global_config"], &crate::ID)
+}
+
+pub fn derive_global_stats_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"global_stats"], &crate::ID)
+}
+
+pub fn derive_pool_pda(company_name: &str) -> (Pubkey, u8) {
+    let name_seed = CompanyPool::name_seed(company_name);
+    Pubkey::find_program_address(&[b"company_pool", name_seed.as_ref()], &crate::ID)
+}
+
+// Pools from before pool version 1 seed their vault with the company name
+// instead; CompanyPool::vault_seed covers both
+pub fn derive_vault_pda(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool_vault", pool.as_ref()], &crate::ID)
+}
+
+pub fn derive_prize_vault_pda(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"prize_vault", pool.as_ref()], &crate::ID)
+}
+
+pub fn derive_pool_stats_pda(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool_stats", pool.as_ref()], &crate::ID)
+}
+
+pub fn derive_winner_registry_pda(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"winner_registry", pool.as_ref()], &crate::ID)
+}
+
+pub fn derive_leaderboard_pda(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"leaderboard", pool.as_ref()], &crate::ID)
+}
+
+// `ticket_id` is the pool's total_tickets_sold when the ticket is bought
+pub fn derive_ticket_pda(buyer: &Pubkey, pool: &Pubkey, ticket_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"user_ticket",
+            buyer.as_ref(),
+            pool.as_ref(),
+            &ticket_id.to_le_bytes(),
+        ],
+        &crate::ID,
+    )
+}
+
+pub fn derive_user_pool_state_pda(pool: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"user_pool_state", pool.as_ref(), user.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn derive_blocked_user_pda(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"blocked", user.as_ref()], &crate::ID)
+}
+
+pub fn derive_pool_creator_pda(creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool_creator", creator.as_ref()], &crate::ID)
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+pub struct InitializePoolParams {
+    pub authority: Pubkey,
+    pub ticket_price: u64,
+    pub company_name: String,
+    pub company_image: String,
+    pub items: Vec<PoolItemInput>,
+    pub config: PoolConfig,
+    // Needed once pool creation is allowlisted
+    pub creator_entry: Option<Pubkey>,
+    // Needed while the global config charges a creation fee
+    pub treasury: Option<Pubkey>,
+    pub update_global_stats: bool,
+}
+
+pub fn build_initialize_pool_ix(params: InitializePoolParams) -> Instruction {
+    let pool = derive_pool_pda(&params.company_name).0;
+    build(
+        accounts::InitializeCompanyPool {
+            company_pool: pool,
+            pool_vault: derive_vault_pda(&pool).0,
+            prize_vault: derive_prize_vault_pda(&pool).0,
+            pool_stats: derive_pool_stats_pda(&pool).0,
+            winner_registry: derive_winner_registry_pda(&pool).0,
+            leaderboard: derive_leaderboard_pda(&pool).0,
+            authority: params.authority,
+            global_config: derive_global_config_pda().0,
+            creator_entry: params.creator_entry,
+            treasury: params.treasury,
+            global_stats: params
+                .update_global_stats
+                .then(|| derive_global_stats_pda().0),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeCompanyPool {
+            ticket_price: params.ticket_price,
+            company_name: params.company_name,
+            company_image: params.company_image,
+            items: params.items,
+            config: params.config,
+        },
+    )
+}
+
+// Plain purchase from a pool with no whitelist, token gate or mega jackpot.
+// `external_id` is public: pass a hash of the order reference, not the
+// reference itself.
+pub fn build_buy_ticket_ix(
+    buyer: &Pubkey,
+    pool: &Pubkey,
+    ticket_id: u64,
+    external_id: Option<[u8; 32]>,
+) -> Instruction {
+    build(
+        accounts::BuyTicket {
+            company_pool: *pool,
+            user_ticket: derive_ticket_pda(buyer, pool, ticket_id).0,
+            user_pool_state: derive_user_pool_state_pda(pool, buyer).0,
+            buyer: *buyer,
+            whitelist_entry: None,
+            blocked_user: derive_blocked_user_pda(buyer).0,
+            gate_token_account: None,
+            gate_metadata: None,
+            pool_vault: derive_vault_pda(pool).0,
+            prize_vault: derive_prize_vault_pda(pool).0,
+            global_config: derive_global_config_pda().0,
+            global_stats: None,
+            beneficiary: None,
+            mega_jackpot: None,
+            mega_jackpot_vault: None,
+            system_program: system_program::ID,
+        },
+        instruction::BuyTicket {
+            external_id,
+            donation_lamports: 0,
+            insured: false,
+        },
+    )
+}
+
+// Account readers taking the raw account data, discriminator included.
+// Older layouts are read the way the program reads them.
+
+impl CompanyPool {
+    pub fn try_from_account_data(data: &[u8]) -> Result<CompanyPool> {
+        CompanyPool::read_any_version(data)
+    }
+}
+
+impl UserTicket {
+    pub fn try_from_account_data(data: &[u8]) -> Result<UserTicket> {
+        UserTicket::read_any_version(data)
+    }
+}
+
+impl GlobalConfig {
+    pub fn try_from_account_data(data: &[u8]) -> Result<GlobalConfig> {
+        GlobalConfig::read_any_version(data)
+    }
+}
+
+impl PoolStats {
+    pub fn try_from_account_data(mut data: &[u8]) -> Result<PoolStats> {
+        PoolStats::try_deserialize(&mut data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Discriminator;
+
+    fn params() -> InitializePoolParams {
+        InitializePoolParams {
+            authority: Pubkey::new_unique(),
+            ticket_price: 1_000_000,
+            company_name: "Client Pool".to_string(),
+            company_image: "https://example.com/logo.png".to_string(),
+            items: vec![PoolItemInput {
+                image: String::new(),
+                price: 500_000,
+                name: "Prize".to_string(),
+                description: String::new(),
+                item_kind: crate::ItemKind::SolPayout,
+                jackpot: false,
+                manual_weight: 0,
+                tier: None,
+                sponsor: None,
+                sponsor_share_bps: 0,
+                reveal_hash: None,
+                reward_mint: None,
+                reward_amount: 0,
+            }],
+            config: PoolConfig {
+                max_ev_bps: 9000,
+                prize_allocation_bps: 5000,
+                ..PoolConfig::default()
+            },
+            creator_entry: None,
+            treasury: Some(Pubkey::new_unique()),
+            update_global_stats: true,
+        }
+    }
+
+    // Decodes the way the program's dispatcher does: discriminator first,
+    // then the Borsh-encoded arguments
+    fn decode<T: Discriminator + AnchorDeserialize>(data: &[u8]) -> T {
+        assert!(data.starts_with(T::DISCRIMINATOR));
+        T::try_from_slice(&data[T::DISCRIMINATOR.len()..]).unwrap()
+    }
+
+    #[test]
+    fn initialize_pool_round_trips_through_the_dispatch_encoding() {
+        let params = params();
+        let authority = params.authority;
+        let treasury = params.treasury;
+        let ix = build_initialize_pool_ix(params);
+        assert_eq!(ix.program_id, crate::ID);
+
+        let args: instruction::InitializeCompanyPool = decode(&ix.data);
+        assert_eq!(args.ticket_price, 1_000_000);
+        assert_eq!(args.company_name, "Client Pool");
+        assert_eq!(args.items.len(), 1);
+        assert_eq!(args.items[0].price, 500_000);
+        assert_eq!(args.config.max_ev_bps, 9000);
+
+        let pool = derive_pool_pda("Client Pool").0;
+        let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(keys[0], pool);
+        assert_eq!(keys[1], derive_vault_pda(&pool).0);
+        assert!(keys.contains(&authority));
+        assert!(keys.contains(&treasury.unwrap()));
+        assert!(keys.contains(&derive_global_stats_pda().0));
+        // The missing creator entry goes in as the program id, Anchor's None
+        assert!(keys.contains(&crate::ID));
+        let signers: Vec<Pubkey> = ix
+            .accounts
+            .iter()
+            .filter(|meta| meta.is_signer)
+            .map(|meta| meta.pubkey)
+            .collect();
+        assert_eq!(signers, vec![authority]);
+    }
+
+    #[test]
+    fn buy_ticket_targets_the_next_ticket_pda() {
+        let buyer = Pubkey::new_unique();
+        let pool = derive_pool_pda("Client Pool").0;
+        let ix = build_buy_ticket_ix(&buyer, &pool, 41, Some([9; 32]));
+
+        let args: instruction::BuyTicket = decode(&ix.data);
+        assert_eq!(args.external_id, Some([9; 32]));
+        assert_eq!(
+            ix.accounts[1].pubkey,
+            derive_ticket_pda(&buyer, &pool, 41).0
+        );
+        assert!(ix.accounts[1].is_writable);
+        assert_ne!(
+            derive_ticket_pda(&buyer, &pool, 41).0,
+            derive_ticket_pda(&buyer, &pool, 42).0
+        );
+    }
+
+    #[test]
+    fn account_readers_check_the_discriminator() {
+        let stats = PoolStats {
+            company_pool: Pubkey::new_unique(),
+            total_spins: 3,
+            wins_per_item: [1; 10],
+            total_rewards_paid: 7,
+            total_value_won: 9,
+            biggest_win_value: 5,
+            biggest_winner: Pubkey::new_unique(),
+            bump: 254,
+            total_trade_in_margin: 0,
+            wins_per_tier: [0; crate::ItemTier::COUNT],
+            no_win_spins: 1,
+        };
+        let mut data = Vec::new();
+        stats.try_serialize(&mut data).unwrap();
+        let read = PoolStats::try_from_account_data(&data).unwrap();
+        assert_eq!(read.company_pool, stats.company_pool);
+        assert_eq!(read.total_spins, 3);
+
+        data[0] ^= 1;
+        assert!(PoolStats::try_from_account_data(&data).is_err());
+        assert!(CompanyPool::try_from_account_data(&data).is_err());
+    }
+}
@@ -1,8 +1,42 @@
+// Anchor 0.31 codegen still calls the deprecated AccountInfo::realloc
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as sysvar_instructions, get_instruction_relative,
+};
+use anchor_lang::solana_program::sysvar::slot_hashes as sysvar_slot_hashes;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::metadata::{
+    create_master_edition_v3, create_metadata_accounts_v3, mpl_token_metadata::types::DataV2,
+    update_metadata_accounts_v2, CreateMasterEditionV3, CreateMetadataAccountsV3, Metadata,
+    UpdateMetadataAccountsV2,
+};
+use anchor_spl::token::{
+    mint_to, spl_token, sync_native, Mint, MintTo, SyncNative, Token, TokenAccount,
+};
 
 // Import the probability module
-mod probability;
+pub mod probability;
 use probability::*;
+// Part of PoolConfig, so programs calling in through CPI need it too
+pub use probability::WeightingMode;
+
+pub mod metadata;
+use metadata::*;
+
+pub mod migration;
+
+pub mod units;
+use units::Lamports;
+
+#[cfg(any(test, feature = "client"))]
+pub mod client;
+
+#[cfg(not(target_os = "solana"))]
+pub mod simulation;
 
 declare_id!("3z5DJ8k16cB8oAtbS45ye4PdtFQZBrFjNKhqks2AAxxr");
 
@@ -16,25 +50,200 @@ pub mod company_pool {
         company_name: String,
         company_image: String,
         items: Vec<PoolItemInput>,
+        config: PoolConfig,
     ) -> Result<()> {
         let company_pool = &mut ctx.accounts.company_pool;
         let clock = Clock::get()?;
 
+        ctx.accounts.global_config.check_pool_creator(
+            ctx.accounts.creator_entry.as_deref(),
+            &ctx.accounts.authority.key(),
+        )?;
+
         // Validate inputs
         require!(ticket_price > 0, ErrorCode::InvalidTicketPrice);
-        require!(!items.is_empty(), ErrorCode::NoItemsProvided);
+        require!(
+            !items.is_empty() || config.item_accounts,
+            ErrorCode::NoItemsProvided
+        );
+        require!(
+            items.is_empty() || !config.item_accounts,
+            ErrorCode::InvalidPoolConfig
+        );
         require!(company_name.len() <= 50, ErrorCode::CompanyNameTooLong);
         require!(company_image.len() <= 200, ErrorCode::CompanyImageTooLong);
-        require!(items.len() <= 10, ErrorCode::TooManyItems);
+        require!(
+            items.len() <= CompanyPool::MAX_INLINE_ITEMS,
+            ErrorCode::TooManyItems
+        );
+        require!(
+            config.max_tickets_per_user != Some(0),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.max_tickets_per_slot != Some(0),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.max_daily_withdrawal != Some(0),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.co_authority != Some(ctx.accounts.authority.key()),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.gate_mint.is_none() || config.gate_collection.is_none(),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.gate_mint.is_none() || config.gate_min_amount > 0,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.gamble_win_bps.unwrap_or(0) <= 10000,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(config.trade_in_bps <= 10000, ErrorCode::InvalidPoolConfig);
+
+        // At most one jackpot item, paid in SOL, and only it can receive contributions
+        let jackpot_items = items.iter().filter(|item| item.jackpot).count();
+        require!(jackpot_items <= 1, ErrorCode::InvalidPoolConfig);
+        require!(
+            items
+                .iter()
+                .all(|item| !item.jackpot || item.item_kind == ItemKind::SolPayout),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.jackpot_contribution_bps <= 10000
+                && (config.jackpot_contribution_bps == 0 || jackpot_items == 1),
+            ErrorCode::InvalidPoolConfig
+        );
+        // Token prizes are claimed from their escrow, never paid at the spin
+        let token_items = items
+            .iter()
+            .filter(|item| item.item_kind == ItemKind::Token)
+            .count();
+        require!(
+            token_items <= CompanyPool::MAX_TOKEN_PRIZES
+                && (token_items == 0 || !config.auto_payout),
+            ErrorCode::InvalidTokenPrize
+        );
+        // The jackpot pot is held in the prize vault, so it comes out of the prize share
+        require!(
+            config.prize_allocation_bps <= 10000
+                && config.jackpot_contribution_bps <= config.prize_allocation_bps,
+            ErrorCode::InvalidPoolConfig
+        );
+        // The mega jackpot's share comes out of the revenue share instead,
+        // which has to fit the largest one allowed
+        require!(
+            !config.mega_jackpot
+                || config.prize_allocation_bps <= 10000 - MegaJackpot::MAX_CONTRIBUTION_BPS,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config
+                .insurance
+                .is_none_or(|insurance| insurance.is_valid(config.auto_payout)),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            !config.loyalty_enabled
+                || (config.loyalty_points_per_ticket > 0 && config.points_per_free_ticket > 0),
+            ErrorCode::InvalidPoolConfig
+        );
+        // Streak bonuses are paid in loyalty points, in order of streak length
+        require!(
+            (config.streak_milestones.is_empty() || config.loyalty_enabled)
+                && config.streak_milestones.len() <= CompanyPool::MAX_STREAK_MILESTONES
+                && config
+                    .streak_milestones
+                    .iter()
+                    .enumerate()
+                    .all(|(i, milestone)| {
+                        milestone.days > 1
+                            && milestone.bonus_points > 0
+                            && (i == 0 || config.streak_milestones[i - 1].days < milestone.days)
+                    }),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(config.weighting.is_valid(), ErrorCode::InvalidPoolConfig);
+        require!(
+            config.no_win_bps < 10000
+                && (config.consolation_lamports == 0 || config.no_win_bps > 0),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.min_probability_bps.unwrap_or(0) <= config.max_probability_bps.unwrap_or(10000)
+                && config.max_probability_bps.unwrap_or(0) <= 10000,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config
+                .ends_at
+                .is_none_or(|ends_at| ends_at > clock.unix_timestamp),
+            ErrorCode::InvalidPoolConfig
+        );
+        // Sponsors are paid out of the revenue vault's share of every sale
+        let sponsored_items = items.iter().filter(|item| item.sponsor.is_some()).count();
+        let sponsor_share_bps: u32 = items.iter().map(|item| item.sponsor_share_bps as u32).sum();
+        require!(
+            items
+                .iter()
+                .all(|item| item.sponsor.is_some() == (item.sponsor_share_bps > 0))
+                && sponsored_items <= CompanyPool::MAX_SPONSORS
+                && sponsor_share_bps <= CompanyPool::MAX_SPONSOR_SHARE_BPS as u32
+                && sponsor_share_bps + config.prize_allocation_bps as u32 <= 10000,
+            ErrorCode::InvalidSponsorShares
+        );
+        // Mystery items are held back from claims until revealed, which
+        // auto-payout pools can't do since they pay on the spin
+        let mystery_items = items
+            .iter()
+            .filter(|item| item.reveal_hash.is_some())
+            .count();
+        require!(
+            mystery_items <= CompanyPool::MAX_PENDING_REVEALS
+                && (mystery_items == 0 || !config.auto_payout),
+            ErrorCode::InvalidPoolConfig
+        );
+        // Raffles draw once sales end and pay through claims, never on a spin
+        require!(
+            config.pool_mode == PoolMode::Spin || (config.ends_at.is_some() && !config.auto_payout),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.vesting_threshold_lamports.is_none()
+                || (config.vesting_duration_seconds > 0 && config.vesting_upfront_bps <= 10000),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            config.server_seed_hash != Some([0; 32]),
+            ErrorCode::InvalidServerSeedHash
+        );
+        // Auto-paid rewards leave during the spin, before they could be voided,
+        // and a reward must stay claimable past its dispute window
+        require!(
+            config.dispute_window_seconds == 0
+                || (!config.auto_payout
+                    && config
+                        .claim_deadline_seconds
+                        .is_none_or(|deadline| deadline > config.dispute_window_seconds)),
+            ErrorCode::InvalidPoolConfig
+        );
 
         // Validate all items before processing
-        for item in &items {
-            require!(item.price > 0, ErrorCode::InvalidItemPrice);
-            require!(item.name.len() <= 50, ErrorCode::ItemNameTooLong);
-            require!(item.image.len() <= 200, ErrorCode::ItemImageTooLong);
+        for (i, item) in items.iter().enumerate() {
+            item.validate()?;
+            require!(
+                item.price >= config.min_item_price,
+                ErrorCode::ItemPriceTooLow
+            );
             require!(
-                item.description.len() <= 200,
-                ErrorCode::ItemDescriptionTooLong
+                items[..i].iter().all(|other| other.name != item.name),
+                ErrorCode::DuplicateItemName
             );
         }
 
@@ -46,22 +255,183 @@ pub mod company_pool {
         company_pool.total_funds = 0;
         company_pool.active = true;
         company_pool.created_at = clock.unix_timestamp;
+        company_pool.version = CompanyPool::VERSION;
+        company_pool.pool_version = if config.item_accounts {
+            CompanyPool::POOL_VERSION_ITEM_ACCOUNTS
+        } else {
+            CompanyPool::POOL_VERSION
+        };
+        company_pool.vault_bump = ctx.bumps.pool_vault;
+        company_pool.pool_bump = ctx.bumps.company_pool;
+        company_pool.max_tickets_per_user = config.max_tickets_per_user;
+        company_pool.purchase_cooldown_seconds = config.purchase_cooldown_seconds;
+        company_pool.whitelist_enabled = config.whitelist_enabled;
+        company_pool.gate_mint = config.gate_mint;
+        company_pool.gate_min_amount = config.gate_min_amount;
+        company_pool.gate_collection = config.gate_collection;
+        company_pool.gamble_win_bps = config.gamble_win_bps;
+        company_pool.gamble_payout_cap = config.gamble_payout_cap;
+        company_pool.trade_in_bps = config.trade_in_bps;
+        company_pool.claim_deadline_seconds = config.claim_deadline_seconds;
+        company_pool.outstanding_liabilities = 0;
+        company_pool.jackpot_contribution_bps = config.jackpot_contribution_bps;
+        company_pool.jackpot_accumulated = 0;
+        company_pool.loyalty_enabled = config.loyalty_enabled;
+        company_pool.loyalty_points_per_ticket = config.loyalty_points_per_ticket;
+        company_pool.points_per_free_ticket = config.points_per_free_ticket;
+        company_pool.weighting = config.weighting;
+        company_pool.min_probability_bps = config.min_probability_bps;
+        company_pool.max_probability_bps = config.max_probability_bps;
+        company_pool.no_win_bps = config.no_win_bps;
+        company_pool.consolation_lamports = config.consolation_lamports;
+        company_pool.withdrawal_timelock_seconds = config.withdrawal_timelock_seconds;
+        company_pool.pending_withdrawal = None;
+        company_pool.max_daily_withdrawal = config.max_daily_withdrawal;
+        company_pool.withdrawn_today = 0;
+        company_pool.withdrawal_day_start = 0;
+        company_pool.co_authority = config.co_authority;
+        company_pool.prize_allocation_bps = config.prize_allocation_bps;
+        company_pool.prize_vault_bump = ctx.bumps.prize_vault;
+        company_pool.total_ticket_revenue = 0;
+        company_pool.total_rewards_paid = 0;
+        company_pool.total_withdrawn = 0;
+        company_pool.total_authority_deposits = 0;
+        company_pool.auto_payout = config.auto_payout;
+        company_pool.reroll_price = config.reroll_price;
+        company_pool.bundles = config.bundles.clone();
+        company_pool.pool_flags = 0;
+        company_pool.allow_cpi_purchases = config.allow_cpi_purchases;
+        company_pool.ends_at = config.ends_at;
+        company_pool.event_seq = 0;
+        company_pool.spin_operator = config.spin_operator;
+        company_pool.vesting_threshold_lamports = config.vesting_threshold_lamports;
+        company_pool.vesting_duration_seconds = config.vesting_duration_seconds;
+        company_pool.vesting_upfront_bps = config.vesting_upfront_bps;
+        company_pool.pool_mode = config.pool_mode;
+        company_pool.sponsors = [ItemSponsor::default(); CompanyPool::MAX_SPONSORS];
+        company_pool.achievement_bonus_lamports = config.achievement_bonus_lamports;
+        company_pool.boosts = [ItemBoost::default(); CompanyPool::MAX_BOOSTS];
+        company_pool.pending_reveals = [PendingReveal::default(); CompanyPool::MAX_PENDING_REVEALS];
+        company_pool.beneficiary = config.beneficiary;
+        company_pool.min_item_price = config.min_item_price;
+        company_pool.token_prizes = [ItemTokenPrize::default(); CompanyPool::MAX_TOKEN_PRIZES];
+        company_pool.server_seeded = config.server_seed_hash.is_some();
+        company_pool.server_seed = ServerSeedCommitment {
+            hash: config.server_seed_hash.unwrap_or_default(),
+            committed_at: clock.unix_timestamp,
+            ..ServerSeedCommitment::default()
+        };
+        company_pool.dispute_window_seconds = config.dispute_window_seconds;
+        company_pool.authority_play_disabled = config.authority_play_disabled;
+        company_pool.happy_hour = HappyHour::default();
+        company_pool.max_tickets_per_slot = config.max_tickets_per_slot;
+        company_pool.last_purchase_slot = 0;
+        company_pool.purchases_in_slot = 0;
+        company_pool.total_donations = 0;
+        company_pool.mega_jackpot = config.mega_jackpot;
+        company_pool.total_mega_jackpot_contributions = 0;
+        company_pool.insurance = config.insurance;
+        company_pool.total_insurance_premiums = 0;
+        company_pool.total_insurance_paid = 0;
+        company_pool.season = 0;
+        company_pool.season_started_at = 0;
+        company_pool.season_start_tickets_sold = 0;
+        company_pool.season_start_revenue = 0;
+        company_pool.points_reset_season = 0;
+        company_pool.streaks_reset_season = 0;
+        company_pool.achievements_reset_season = 0;
+        company_pool.shared_tickets_sold = 0;
+        company_pool.streak_milestones =
+            [StreakMilestone::default(); CompanyPool::MAX_STREAK_MILESTONES];
+        for (slot, milestone) in company_pool
+            .streak_milestones
+            .iter_mut()
+            .zip(&config.streak_milestones)
+        {
+            *slot = *milestone;
+        }
 
         let mut total_value = 0u64;
         let mut pool_items = Vec::new();
 
-        // Prepare items for probability calculation
-        let items_for_probability: Vec<(String, u64)> = items
+        let manual_weights: Vec<u32> = items.iter().map(|item| item.manual_weight).collect();
+
+        // Calculate probabilities using the pool's weighting mode. Item-account
+        // pools start empty and build their table as items are added.
+        let probabilities = if items.is_empty() {
+            Vec::new()
+        } else {
+            calculate_item_probabilities_with_weighting(
+                items.iter().map(|item| item.price),
+                ticket_price,
+                config.weighting,
+                &manual_weights,
+                ProbabilityBounds::new(config.min_probability_bps, config.max_probability_bps),
+            )?
+        };
+
+        // Every item must be winnable; a zero entry would be filtered out of spins
+        check_all_winnable(probabilities.iter().copied().enumerate())?;
+
+        // Authority-supplied tiers win over the quartile defaults, but the pool
+        // always needs a Common item
+        let auto_tiers = ItemTier::assign_by_probability(&probabilities);
+        let tiers: Vec<ItemTier> = items
             .iter()
-            .map(|item| (item.name.clone(), item.price))
+            .zip(auto_tiers)
+            .map(|(item, auto_tier)| item.tier.unwrap_or(auto_tier))
             .collect();
+        require!(
+            tiers.is_empty() || tiers.contains(&ItemTier::Common),
+            ErrorCode::CommonTierRequired
+        );
+
+        // Bundles need distinct sizes and an item that can honour their guarantee
+        require!(
+            config.bundles.len() <= CompanyPool::MAX_BUNDLES
+                && config.bundles.iter().enumerate().all(|(i, bundle)| {
+                    (2..=BundleDefinition::MAX_SIZE).contains(&bundle.size)
+                        && bundle.price > 0
+                        && tiers.iter().any(|&tier| tier >= bundle.guaranteed_tier)
+                        && config.bundles[..i]
+                            .iter()
+                            .all(|other| other.size != bundle.size)
+                }),
+            ErrorCode::InvalidPoolConfig
+        );
 
-        // Calculate probabilities using the advanced weighted algorithm
-        let probabilities = calculate_item_probabilities(&items_for_probability, ticket_price)
-            .map_err(|_| ErrorCode::InvalidProbabilityCalculation)?;
+        // Mystery items take the reveal slots in the order they are listed
+        let mystery_hashes = items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| item.reveal_hash.map(|hash| (i as u32, hash)));
+        for (slot, (item_id, reveal_hash)) in
+            company_pool.pending_reveals.iter_mut().zip(mystery_hashes)
+        {
+            *slot = PendingReveal {
+                item_id,
+                reveal_hash,
+            };
+        }
+        for (i, item) in items.iter().enumerate() {
+            if let Some(mint) = item.reward_mint {
+                company_pool.add_token_prize(i as u32, mint, item.reward_amount)?;
+            }
+        }
 
         // Create pool items with calculated probabilities
+        let mut sponsor_slots = company_pool.sponsors.iter_mut();
         for (i, item) in items.into_iter().enumerate() {
+            if let Some(sponsor) = item.sponsor {
+                *sponsor_slots
+                    .next()
+                    .ok_or(ErrorCode::InvalidSponsorShares)? = ItemSponsor {
+                    sponsor,
+                    item_id: i as u32,
+                    share_bps: item.sponsor_share_bps,
+                    paid: 0,
+                };
+            }
             pool_items.push(PoolItem {
                 image: item.image,
                 price: item.price,
@@ -69,6 +439,12 @@ pub mod company_pool {
                 description: item.description,
                 probability: probabilities[i],
                 available: true,
+                times_won: 0,
+                item_kind: item.item_kind,
+                jackpot: item.jackpot,
+                manual_weight: item.manual_weight,
+                tier: tiers[i],
+                item_id: i as u32,
             });
 
             total_value = total_value
@@ -78,37 +454,112 @@ pub mod company_pool {
 
         company_pool.items = pool_items;
         company_pool.total_value = total_value;
+        company_pool.rebuild_cumulative_table();
+
+        // Token items sit out the draw until their escrow is funded and the
+        // authority makes them available
+        if token_items > 0 {
+            for item in company_pool
+                .items
+                .iter_mut()
+                .filter(|item| item.item_kind == ItemKind::Token)
+            {
+                item.available = false;
+            }
+            company_pool.recalculate_probabilities()?;
+        }
 
         // Log probability information for debugging
         msg!("=== ITEM PROBABILITIES ===");
-        for (_i, item) in company_pool.items.iter().enumerate() {
+        for item in company_pool.items.iter() {
             msg!(
-                "{}: {}% (Value: {} SOL)",
+                "{}: {}% (Value: {})",
                 item.name,
                 (item.probability as f64) / 100.0,
-                item.price
+                Lamports(item.price).to_sol_display()
             );
         }
 
         // Verify probabilities sum correctly
         let total_probability: u32 = company_pool.items.iter().map(|item| item.probability).sum();
         require!(
-            total_probability == 10000,
+            company_pool.items.is_empty() || total_probability == 10000,
             ErrorCode::ProbabilitySumMismatch
         );
 
-        // Create the vault PDA
-        let rent = Rent::get()?;
-        let minimum_balance = rent.minimum_balance(0);
+        // Guard against pools that pay out more per spin than intended.
+        // Loss-leader pools have to opt out explicitly.
+        let expected_value = company_pool.expected_value()?;
+        if !config.allow_negative_ev {
+            require!(
+                (expected_value as u128) * 10000
+                    <= (ticket_price as u128) * (config.max_ev_bps as u128),
+                ErrorCode::ExpectedValueTooHigh
+            );
+        }
 
-        if ctx.accounts.pool_vault.lamports() < minimum_balance {
+        let pool_stats = &mut ctx.accounts.pool_stats;
+        pool_stats.company_pool = company_pool.key();
+        pool_stats.bump = ctx.bumps.pool_stats;
+
+        let mut winner_registry = ctx.accounts.winner_registry.load_init()?;
+        winner_registry.company_pool = company_pool.key();
+        winner_registry.bump = ctx.bumps.winner_registry;
+        drop(winner_registry);
+
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        leaderboard.company_pool = company_pool.key();
+        leaderboard.bump = ctx.bumps.leaderboard;
+
+        // Protocol fee for opening a pool, when the config charges one
+        let creation_fee = ctx.accounts.global_config.pool_creation_fee_lamports;
+        if creation_fee > 0 {
+            let treasury = ctx
+                .accounts
+                .treasury
+                .as_ref()
+                .ok_or(ErrorCode::InvalidTreasury)?;
             let cpi_accounts = anchor_lang::system_program::Transfer {
                 from: ctx.accounts.authority.to_account_info(),
-                to: ctx.accounts.pool_vault.to_account_info(),
+                to: treasury.to_account_info(),
             };
             let cpi_context =
                 CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
-            anchor_lang::system_program::transfer(cpi_context, minimum_balance)?;
+            anchor_lang::system_program::transfer(cpi_context, creation_fee)?;
+        }
+
+        // Fund both vault PDAs up to the rent-exempt minimum. The vaults are
+        // SystemAccounts, so Anchor has already checked they are system-owned.
+        let rent = Rent::get()?;
+        let minimum_balance = rent.minimum_balance(0);
+        let mut vault_rent_funded = 0u64;
+
+        for vault in [&ctx.accounts.pool_vault, &ctx.accounts.prize_vault] {
+            let shortfall = minimum_balance.saturating_sub(vault.lamports());
+            if shortfall > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: vault.to_account_info(),
+                };
+                let cpi_context =
+                    CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_context, shortfall)?;
+                vault_rent_funded = vault_rent_funded
+                    .checked_add(shortfall)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            }
+
+            require!(
+                vault.lamports() >= minimum_balance,
+                ErrorCode::InsufficientVaultFunds
+            );
+        }
+
+        // Record the reserve so it is never mistaken for withdrawable funds
+        company_pool.vault_rent_reserve = minimum_balance;
+
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_pool_created(creation_fee)?;
         }
 
         emit!(PoolInitializedEvent {
@@ -117,59 +568,343 @@ pub mod company_pool {
             item_count: company_pool.items.len() as u32,
             authority: ctx.accounts.authority.key(),
             total_probability_check: total_probability,
+            vault_rent_funded,
+            expected_value,
+            creation_fee,
+            beneficiary: company_pool.beneficiary,
+            authority_can_play: company_pool.authority_can_play(),
+            probabilities: company_pool
+                .items
+                .iter()
+                .map(|item| item.probability)
+                .collect(),
+            total_value,
+            weighting: company_pool.weighting,
+            event_seq: company_pool.next_event_seq(),
         });
 
         Ok(())
     }
 
-    pub fn buy_ticket(ctx: Context<BuyTicket>) -> Result<()> {
+    // `external_id` is an opaque reference to the buyer's off-chain order,
+    // e.g. a hash of the order id, kept on the ticket and in its events.
+    // Never pass anything personal: it is public. `donation_lamports` is paid
+    // on top of the ticket to the pool's beneficiary; 0 donates nothing.
+    pub fn buy_ticket(
+        ctx: Context<BuyTicket>,
+        external_id: Option<[u8; 32]>,
+        donation_lamports: u64,
+        insured: bool,
+    ) -> Result<()> {
         let company_pool = &mut ctx.accounts.company_pool;
         let clock = Clock::get()?;
 
         // Validate pool state
         require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_open(clock.unix_timestamp)?;
+        company_pool.check_not_frozen()?;
+        company_pool.check_purchase_caller()?;
+        company_pool.check_player(&ctx.accounts.buyer.key())?;
+        BlockedUser::check_not_blocked(&ctx.accounts.blocked_user)?;
         require!(!company_pool.items.is_empty(), ErrorCode::NoItemsProvided);
+        company_pool.check_whitelist(
+            &company_pool.key(),
+            ctx.accounts.whitelist_entry.as_deref(),
+            &ctx.accounts.buyer.key(),
+        )?;
+        let gate_passed = company_pool.check_token_gate(
+            ctx.accounts.gate_token_account.as_deref(),
+            ctx.accounts.gate_metadata.as_ref(),
+            &ctx.accounts.buyer.key(),
+        )?;
+        company_pool.record_slot_purchases(1, clock.slot)?;
 
-        let ticket_price = company_pool.ticket_price;
+        // Per-user limits are tracked against the ticket owner
+        let user_pool_state = &mut ctx.accounts.user_pool_state;
+        user_pool_state.bump = ctx.bumps.user_pool_state;
+        user_pool_state.version = UserPoolState::VERSION;
+        user_pool_state.record_purchase(company_pool, 1, clock.unix_timestamp)?;
+        user_pool_state.credit_loyalty_points(company_pool, 1)?;
+        let streak = user_pool_state.record_play_day(company_pool, clock.unix_timestamp)?;
 
-        // Transfer SOL from buyer to pool vault
-        let cpi_accounts = anchor_lang::system_program::Transfer {
-            from: ctx.accounts.buyer.to_account_info(),
-            to: ctx.accounts.pool_vault.to_account_info(),
+        let ticket_price = company_pool.ticket_price_at(clock.unix_timestamp)?;
+        let discount = company_pool
+            .ticket_price
+            .checked_sub(ticket_price)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // Split the payment between the revenue and prize vaults, less the
+        // mega jackpot's share of the revenue
+        let (revenue_share, prize_share) = company_pool.split_ticket_payment(ticket_price)?;
+        let mega_jackpot_share = pay_mega_jackpot_share(
+            company_pool,
+            ctx.accounts.mega_jackpot.as_deref_mut(),
+            ctx.accounts.mega_jackpot_vault.as_ref(),
+            ctx.accounts.buyer.to_account_info(),
+            &ctx.accounts.system_program,
+            ticket_price,
+        )?;
+        let revenue_share = revenue_share
+            .checked_sub(mega_jackpot_share)
+            .ok_or(ErrorCode::InsufficientFunds)?;
+        for (vault, amount) in [
+            (ctx.accounts.pool_vault.to_account_info(), revenue_share),
+            (ctx.accounts.prize_vault.to_account_info(), prize_share),
+        ] {
+            if amount > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.buyer.to_account_info(),
+                    to: vault,
+                };
+                let cpi_context =
+                    CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_context, amount)?;
+            }
+        }
+
+        // Donations go straight to the beneficiary and stay out of the
+        // pool's funds and liabilities
+        if donation_lamports > 0 {
+            // Older layouts don't count donations; migrate_pool upgrades them
+            require!(
+                company_pool.version >= CompanyPool::VERSION_DONATIONS,
+                ErrorCode::UnsupportedAccountVersion
+            );
+            require!(
+                company_pool.beneficiary().is_some(),
+                ErrorCode::DonationsNotAccepted
+            );
+            let beneficiary = ctx
+                .accounts
+                .beneficiary
+                .as_ref()
+                .ok_or(ErrorCode::InvalidBeneficiary)?;
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: beneficiary.to_account_info(),
+            };
+            let cpi_context =
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            anchor_lang::system_program::transfer(cpi_context, donation_lamports)?;
+            company_pool.total_donations = company_pool
+                .total_donations
+                .checked_add(donation_lamports)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        // The premium goes to the prize vault, which pays the claims
+        let insurance_premium = if insured {
+            let insurance = company_pool
+                .insurance()
+                .ok_or(ErrorCode::InsuranceNotOffered)?;
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: ctx.accounts.prize_vault.to_account_info(),
+            };
+            let cpi_context =
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            anchor_lang::system_program::transfer(cpi_context, insurance.price)?;
+            insurance.price
+        } else {
+            0
         };
-        let cpi_context =
-            CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
-        anchor_lang::system_program::transfer(cpi_context, ticket_price)?;
 
         // Initialize the ticket account
         let user_ticket = &mut ctx.accounts.user_ticket;
-        user_ticket.owner = ctx.accounts.buyer.key();
-        user_ticket.company_pool = company_pool.key();
-        user_ticket.purchased_at = clock.unix_timestamp;
-        user_ticket.used = false;
-        user_ticket.ticket_id = company_pool.total_tickets_sold;
-        user_ticket.won_item = None; // Initialize as no item won yet
-        user_ticket.reward_claimed = false; // Initialize as not claimed
+        user_ticket.issue(ctx.accounts.buyer.key(), company_pool, ticket_price, &clock)?;
+        user_ticket.external_id = external_id;
+        user_ticket.insured = insured;
 
         // Update the company pool state
-        company_pool.total_tickets_sold = company_pool
-            .total_tickets_sold
-            .checked_add(1)
-            .ok_or(ErrorCode::MathOverflow)?;
-
-        company_pool.total_funds = company_pool
-            .total_funds
-            .checked_add(ticket_price)
-            .ok_or(ErrorCode::MathOverflow)?;
+        company_pool.record_ticket_sale(ticket_price)?;
+        company_pool.record_mega_jackpot_contribution(mega_jackpot_share)?;
+        company_pool.record_insurance_premium(insurance_premium)?;
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_tickets_sold(1)?;
+        }
 
         // Emit event
         emit!(TicketPurchasedEvent {
             buyer: ctx.accounts.buyer.key(),
             ticket_price,
+            source: TicketSource::Purchase,
             total_tickets_sold: company_pool.total_tickets_sold,
             ticket_id: user_ticket.ticket_id,
+            gate_passed,
+            jackpot_accumulated: company_pool.jackpot_accumulated,
+            prize_share,
+            discount,
+            donation: donation_lamports,
+            insurance_premium,
+            pool_flags: company_pool.flags(),
+            external_id,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+        if let Some(streak) = streak {
+            emit!(StreakUpdatedEvent {
+                user: ctx.accounts.buyer.key(),
+                current_streak: streak.current_streak,
+                milestone_hit: streak.bonus_points > 0,
+                bonus_points: streak.bonus_points,
+                loyalty_points: user_pool_state.loyalty_points,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Sells one of the pool's bundles. The new ticket PDAs go in
+    // remaining_accounts in ticket id order; the last one carries the
+    // bundle's tier guarantee.
+    pub fn buy_bundle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyBundle<'info>>,
+        size: u8,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let company_pool = &ctx.accounts.company_pool;
+
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_open(clock.unix_timestamp)?;
+        company_pool.check_not_frozen()?;
+        company_pool.check_purchase_caller()?;
+        company_pool.check_player(&ctx.accounts.buyer.key())?;
+        BlockedUser::check_not_blocked(&ctx.accounts.blocked_user)?;
+        require!(!company_pool.items.is_empty(), ErrorCode::NoItemsProvided);
+        let bundle = *company_pool
+            .bundles
+            .iter()
+            .find(|bundle| bundle.size == size)
+            .ok_or(ErrorCode::BundleNotFound)?;
+        require!(
+            ctx.remaining_accounts.len() == size as usize,
+            ErrorCode::BundleTicketMismatch
+        );
+        company_pool.check_whitelist(
+            &company_pool.key(),
+            ctx.accounts.whitelist_entry.as_deref(),
+            &ctx.accounts.buyer.key(),
+        )?;
+        company_pool.check_token_gate(
+            ctx.accounts.gate_token_account.as_deref(),
+            ctx.accounts.gate_metadata.as_ref(),
+            &ctx.accounts.buyer.key(),
+        )?;
+
+        // Every ticket in the bundle counts toward the buyer's limits
+        let user_pool_state = &mut ctx.accounts.user_pool_state;
+        user_pool_state.bump = ctx.bumps.user_pool_state;
+        user_pool_state.version = UserPoolState::VERSION;
+        user_pool_state.record_purchase(company_pool, size as u32, clock.unix_timestamp)?;
+        user_pool_state.credit_loyalty_points(company_pool, size as u32)?;
+        let streak = user_pool_state.record_play_day(company_pool, clock.unix_timestamp)?;
+
+        let (revenue_share, prize_share) = company_pool.split_ticket_payment(bundle.price)?;
+        let mega_jackpot_share = pay_mega_jackpot_share(
+            company_pool,
+            ctx.accounts.mega_jackpot.as_deref_mut(),
+            ctx.accounts.mega_jackpot_vault.as_ref(),
+            ctx.accounts.buyer.to_account_info(),
+            &ctx.accounts.system_program,
+            bundle.price,
+        )?;
+        let revenue_share = revenue_share
+            .checked_sub(mega_jackpot_share)
+            .ok_or(ErrorCode::InsufficientFunds)?;
+        // The whole bundle counts against the pool's per-slot limit
+        ctx.accounts
+            .company_pool
+            .record_slot_purchases(size as u32, clock.slot)?;
+        for (vault, amount) in [
+            (ctx.accounts.pool_vault.to_account_info(), revenue_share),
+            (ctx.accounts.prize_vault.to_account_info(), prize_share),
+        ] {
+            if amount > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.buyer.to_account_info(),
+                    to: vault,
+                };
+                let cpi_context =
+                    CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_context, amount)?;
+            }
+        }
+
+        let buyer = ctx.accounts.buyer.key();
+        let company_pool_key = ctx.accounts.company_pool.key();
+        let first_ticket_id = ctx.accounts.company_pool.total_tickets_sold;
+
+        for (position, ticket_info) in (0..size).zip(ctx.remaining_accounts.iter()) {
+            let ticket_id = ctx.accounts.company_pool.total_tickets_sold.to_le_bytes();
+            let (ticket_pda, ticket_bump) = Pubkey::find_program_address(
+                &[
+                    b"user_ticket",
+                    buyer.as_ref(),
+                    company_pool_key.as_ref(),
+                    &ticket_id,
+                ],
+                ctx.program_id,
+            );
+            require_keys_eq!(
+                ticket_info.key(),
+                ticket_pda,
+                ErrorCode::BundleTicketMismatch
+            );
+            ctx.accounts.create_ticket_account(
+                ticket_info,
+                &[
+                    b"user_ticket",
+                    buyer.as_ref(),
+                    company_pool_key.as_ref(),
+                    &ticket_id,
+                    &[ticket_bump],
+                ],
+            )?;
+
+            let mut user_ticket = Account::<UserTicket>::try_from_unchecked(ticket_info)?;
+            user_ticket.issue(
+                buyer,
+                &mut ctx.accounts.company_pool,
+                bundle.ticket_price_paid(position),
+                &clock,
+            )?;
+            if position == size - 1 {
+                user_ticket.forced_min_tier = Some(bundle.guaranteed_tier);
+            }
+            user_ticket.exit(ctx.program_id)?;
+        }
+
+        let company_pool = &mut ctx.accounts.company_pool;
+        company_pool.record_ticket_sale(bundle.price)?;
+        company_pool.record_mega_jackpot_contribution(mega_jackpot_share)?;
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_tickets_sold(size as u64)?;
+        }
+
+        emit!(BundlePurchasedEvent {
+            buyer,
+            size,
+            price: bundle.price,
+            first_ticket_id,
+            guaranteed_ticket_id: company_pool.total_tickets_sold - 1,
+            guaranteed_tier: bundle.guaranteed_tier,
+            prize_share,
+            event_seq: company_pool.next_event_seq(),
             timestamp: clock.unix_timestamp,
         });
+        if let Some(streak) = streak {
+            emit!(StreakUpdatedEvent {
+                user: buyer,
+                current_streak: streak.current_streak,
+                milestone_hit: streak.bonus_points > 0,
+                bonus_points: streak.bonus_points,
+                loyalty_points: ctx.accounts.user_pool_state.loyalty_points,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+        }
 
         Ok(())
     }
@@ -183,13 +918,29 @@ pub mod company_pool {
 
         // Validate pool state
         require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_not_frozen()?;
         require!(company_pool.total_funds > 0, ErrorCode::NoFundsAvailable);
+        require!(
+            company_pool.withdrawal_timelock_seconds == 0,
+            ErrorCode::WithdrawalTimelocked
+        );
+        // The revenue is promised to the beneficiary, see sweep_vault
+        require!(
+            company_pool.beneficiary().is_none(),
+            ErrorCode::BeneficiaryPool
+        );
 
         // Validate authority
         require!(
             company_pool.authority == ctx.accounts.authority.key(),
             ErrorCode::UnauthorizedWithdrawal
         );
+        company_pool.check_co_authority(
+            ctx.accounts
+                .co_authority
+                .as_ref()
+                .map(|signer| signer.key()),
+        )?;
 
         // Validate the amount to withdraw
         require!(amount_to_withdraw > 0, ErrorCode::InvalidAmount);
@@ -200,17 +951,24 @@ pub mod company_pool {
 
         // Get vault balance to ensure we have enough funds
         let vault_balance = ctx.accounts.pool_vault.lamports();
-        let rent_exempt_minimum = Rent::get()?.minimum_balance(0);
-        let withdrawable_balance = vault_balance.saturating_sub(rent_exempt_minimum);
+        let rent_reserve = company_pool.rent_reserve()?;
+        // Unclaimed rewards and the jackpot pot stay in the vault
+        let withdrawable_balance =
+            vault_balance.saturating_sub(company_pool.reserved_balance(rent_reserve));
 
         require!(
             amount_to_withdraw <= withdrawable_balance,
             ErrorCode::InsufficientVaultFunds
         );
+        company_pool.record_withdrawal(amount_to_withdraw, clock.unix_timestamp)?;
 
         // Create seeds for PDA signing
-        let company_name_bytes = company_pool.company_name.as_bytes();
-        let seeds = &[b"pool_vault", company_name_bytes, &[ctx.bumps.pool_vault]];
+        let vault_seed = company_pool.vault_seed(&company_pool.key());
+        let seeds = &[
+            b"pool_vault",
+            vault_seed.as_slice(),
+            &[company_pool.vault_bump],
+        ];
         let signer_seeds = &[&seeds[..]];
 
         // Transfer funds from vault to authority
@@ -236,12 +994,86 @@ pub mod company_pool {
             authority: ctx.accounts.authority.key(),
             amount_withdrawn: amount_to_withdraw,
             remaining_funds: company_pool.total_funds,
+            event_seq: company_pool.next_event_seq(),
             timestamp: clock.unix_timestamp,
         });
 
         Ok(())
     }
 
+    // Pays every sponsor its share of the ticket revenue booked since the
+    // last distribution, out of the revenue vault. The sponsor wallets go in
+    // remaining_accounts in the pool's sponsor order. Anyone may call it:
+    // the amounts only depend on sales.
+    pub fn distribute_sponsor_revenue<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DistributeSponsorRevenue<'info>>,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        let sponsor_count = company_pool.sponsor_slots().len();
+        require!(sponsor_count > 0, ErrorCode::NoSponsors);
+        require!(
+            ctx.remaining_accounts.len() == sponsor_count,
+            ErrorCode::SponsorAccountMismatch
+        );
+
+        let vault_seed = company_pool.vault_seed(&company_pool.key());
+        let seeds = &[
+            b"pool_vault",
+            vault_seed.as_slice(),
+            &[company_pool.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        for (slot, sponsor_info) in (0..sponsor_count).zip(ctx.remaining_accounts.iter()) {
+            let sponsor = company_pool.sponsors[slot];
+            require_keys_eq!(
+                sponsor_info.key(),
+                sponsor.sponsor,
+                ErrorCode::SponsorAccountMismatch
+            );
+            let amount = sponsor.owed(company_pool.total_ticket_revenue);
+            if amount == 0 {
+                continue;
+            }
+
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.pool_vault.to_account_info(),
+                to: sponsor_info.clone(),
+            };
+            let cpi_context = CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+            // Paid out of the revenue the authority could otherwise withdraw
+            company_pool.total_funds = company_pool
+                .total_funds
+                .checked_sub(amount)
+                .ok_or(ErrorCode::InsufficientFunds)?;
+            company_pool.total_withdrawn = company_pool
+                .total_withdrawn
+                .checked_add(amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            let paid = company_pool.sponsors[slot].paid + amount;
+            company_pool.sponsors[slot].paid = paid;
+
+            emit!(SponsorRevenueDistributedEvent {
+                sponsor: sponsor.sponsor,
+                item_id: sponsor.item_id,
+                amount,
+                total_paid: paid,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
     pub fn record_spin_result(ctx: Context<RecordSpinResult>) -> Result<()> {
         let company_pool = &mut ctx.accounts.company_pool;
         let user_ticket = &mut ctx.accounts.user_ticket;
@@ -249,72 +1081,188 @@ pub mod company_pool {
 
         // Validate pool state
         require!(company_pool.active, ErrorCode::PoolInactive);
+        require!(
+            company_pool.pool_mode() == PoolMode::Spin,
+            ErrorCode::RaffleModePool
+        );
         require!(!company_pool.items.is_empty(), ErrorCode::NoItemsProvided);
 
-        // CRITICAL: Validate ticket ownership and usage
+        // CRITICAL: Validate ticket ownership and usage. The owner's session
+        // key or the pool's spin operator may spin for them, as may any
+        // participant in a shared ticket, but rewards always stay with the
+        // owner.
+        let spinner = ctx.accounts.spinner.key();
+        let winner = user_ticket.owner;
         require!(
-            user_ticket.owner == ctx.accounts.spinner.key(),
+            winner == spinner
+                || company_pool.spin_operator() == Some(spinner)
+                || ctx
+                    .accounts
+                    .user_pool_state
+                    .as_ref()
+                    .is_some_and(|state| state.is_spin_delegate(&spinner, clock.unix_timestamp))
+                || ctx
+                    .accounts
+                    .shared_ticket
+                    .as_ref()
+                    .is_some_and(|shared| shared.is_participant(&spinner)),
             ErrorCode::NotTicketOwner
         );
+
+        // Spins must be top-level instructions. A program wrapping the spin in
+        // a CPI could inspect the result and revert the ones it doesn't like.
+        let current_instruction = get_instruction_relative(0, &ctx.accounts.instructions)?;
+        require_keys_eq!(
+            current_instruction.program_id,
+            crate::ID,
+            ErrorCode::CpiNotAllowed
+        );
         require!(
             user_ticket.company_pool == company_pool.key(),
             ErrorCode::InvalidTicketPool
         );
-        require!(!user_ticket.used, ErrorCode::TicketAlreadyUsed);
+        require!(
+            user_ticket.state() == TicketState::Unspun,
+            ErrorCode::TicketAlreadyUsed
+        );
+        user_ticket.check_spin_slot(clock.slot)?;
 
-        // Mark ticket as used
-        user_ticket.used = true;
+        // Validation and the draw come first and change nothing, so a spin
+        // that fails leaves every account exactly as it found it.
+        // Generate enhanced pseudo-random seed using multiple on-chain sources
+        let entropy = generate_random_seed(
+            clock.unix_timestamp,
+            clock.slot,
+            &ctx.accounts.spinner.key(),
+            company_pool.total_tickets_sold,
+            ctx.accounts.pool_vault.lamports(),
+            user_ticket.ticket_id,
+        );
+        let (random_seed, server_seed_hash) =
+            company_pool.mix_server_seed(entropy, clock.unix_timestamp)?;
+        let draw_table = company_pool.draw_table(user_ticket.forced_min_tier, clock.unix_timestamp);
+        let drawn = company_pool.draw_at(
+            random_seed,
+            user_ticket.forced_min_tier,
+            clock.unix_timestamp,
+        )?;
+        let boost_active = company_pool.boost_active(clock.unix_timestamp);
 
-        // Get available items with their pre-calculated probabilities
-        let available_items: Vec<(usize, &PoolItem)> = company_pool
-            .items
-            .iter()
-            .enumerate()
-            .filter(|(_, item)| item.available && item.probability > 0)
-            .collect();
+        user_ticket.record_draw(clock.unix_timestamp, clock.slot)?;
+        user_ticket.draw_record = Some(DrawRecord::new(random_seed, &draw_table, drawn));
 
-        require!(!available_items.is_empty(), ErrorCode::NoAvailableItems);
+        // Spins in participating pools also roll for the mega jackpot, win
+        // or lose on the item draw
+        if company_pool.joins_mega_jackpot() {
+            let mega_jackpot = ctx
+                .accounts
+                .mega_jackpot
+                .as_mut()
+                .ok_or(ErrorCode::MegaJackpotRequired)?;
+            if let Some(amount) = mega_jackpot.roll(random_seed)? {
+                user_ticket.mega_jackpot_won = amount;
+                emit!(MegaJackpotWonEvent {
+                    winner,
+                    company_pool: company_pool.key(),
+                    ticket_id: user_ticket.ticket_id,
+                    amount,
+                    event_seq: company_pool.next_event_seq(),
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+        }
 
-        // Generate enhanced pseudo-random seed using multiple on-chain sources
-        let random_seed = clock.unix_timestamp as u64
-            ^ ctx.accounts.spinner.key().to_bytes()[0..8]
-                .iter()
-                .enumerate()
-                .fold(0u64, |acc, (i, &byte)| acc ^ ((byte as u64) << (i * 8)))
-            ^ company_pool.total_tickets_sold
-            ^ ctx.accounts.pool_vault.lamports()
-            ^ (clock.slot as u64)
-            ^ user_ticket.ticket_id;
+        // Append every result to the owner's spin history for this pool
+        let spin_history = &mut ctx.accounts.spin_history;
+        if spin_history.owner == Pubkey::default() {
+            spin_history.owner = user_ticket.owner;
+            spin_history.company_pool = company_pool.key();
+            spin_history.bump = ctx.bumps.spin_history;
+        }
 
-        // Extract probabilities for available items
-        let probabilities: Vec<u32> = available_items
-            .iter()
-            .map(|(_, item)| item.probability)
-            .collect();
+        // A share of spins wins nothing
+        let Some(actual_index) = drawn else {
+            let consolation = company_pool.consolation_lamports;
+            user_ticket.consolation_amount = consolation;
 
-        // Select winning item using weighted probability algorithm
-        let winning_index = select_winning_item_index(&probabilities, random_seed)
-            .ok_or(ErrorCode::ProbabilitySelectionFailed)?;
+            spin_history.push(SpinHistoryEntry {
+                ticket_id: user_ticket.ticket_id,
+                item_index: SpinHistory::NO_WIN,
+                value: consolation,
+                timestamp: clock.unix_timestamp,
+            });
+            ctx.accounts.pool_stats.record_no_win()?;
 
-        let (actual_index, winning_item) = available_items[winning_index];
+            emit!(SpinResultEvent {
+                spinner: ctx.accounts.spinner.key(),
+                outcome: if consolation > 0 {
+                    SpinOutcome::Consolation
+                } else {
+                    SpinOutcome::Nothing
+                },
+                won_item: None,
+                item_index: None,
+                item_value: consolation,
+                win_probability: company_pool.no_win_bps as u32,
+                random_seed,
+                ticket_id: user_ticket.ticket_id,
+                pool_flags: company_pool.flags(),
+                external_id: user_ticket.external_id,
+                boost_active,
+                serial: None,
+                server_seed_hash,
+                insurance_triggered: user_ticket.is_insurance_triggered(company_pool),
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+
+            ctx.accounts.record_achievements(None, clock.unix_timestamp);
+            return ctx.accounts.settle_reward(clock.unix_timestamp);
+        };
 
         // Store the won item in the ticket for later claiming
-        user_ticket.won_item = Some(WonItem {
-            name: winning_item.name.clone(),
-            price: winning_item.price,
-            image: winning_item.image.clone(),
-            description: winning_item.description.clone(),
+        let winning_item = company_pool.items[actual_index].clone();
+        let (won_item, jackpot_payout) = company_pool.award_item(actual_index)?;
+        let reward_value = won_item.price;
+        user_ticket.won_item = Some(won_item);
+        // The item's win counter now includes this one
+        user_ticket.serial = company_pool.items[actual_index].times_won;
+
+        spin_history.push(SpinHistoryEntry {
+            ticket_id: user_ticket.ticket_id,
             item_index: actual_index as u32,
+            value: winning_item.price,
+            timestamp: clock.unix_timestamp,
         });
 
-        // Clone the winning item for the event
-        let won_item = winning_item.clone();
+        // Only item wins go on the pool's public winner list
+        ctx.accounts
+            .winner_registry
+            .load_mut()?
+            .push(WinnerEntry::new(
+                winner,
+                user_ticket.ticket_id,
+                actual_index as u32,
+                reward_value,
+                clock.unix_timestamp,
+            ));
+
+        // Update the pool's running statistics
+        ctx.accounts.pool_stats.record_spin(
+            actual_index,
+            winning_item.tier,
+            winning_item.price,
+            winner,
+        )?;
 
         // Log detailed winning information
         msg!("🎉 SPIN RESULT 🎉");
-        msg!("Winner: {}", ctx.accounts.spinner.key());
+        msg!("Winner: {}", winner);
         msg!("Won Item: {}", winning_item.name);
-        msg!("Item Value: {} SOL", winning_item.price);
+        msg!(
+            "Item Value: {}",
+            Lamports(winning_item.price).to_sol_display()
+        );
         msg!(
             "Win Probability: {}%",
             (winning_item.probability as f64) / 100.0
@@ -325,465 +1273,15003 @@ pub mod company_pool {
         // Emit success event
         emit!(SpinResultEvent {
             spinner: ctx.accounts.spinner.key(),
-            won_item: Some(won_item),
+            outcome: SpinOutcome::Item,
+            won_item: Some(winning_item.clone()),
             item_index: Some(actual_index as u32),
-            item_value: winning_item.price,
+            item_value: reward_value,
             win_probability: winning_item.probability,
             random_seed,
             ticket_id: user_ticket.ticket_id,
+            pool_flags: company_pool.flags(),
+            external_id: user_ticket.external_id,
+            boost_active,
+            serial: user_ticket.serial(),
+            server_seed_hash,
+            insurance_triggered: user_ticket.is_insurance_triggered(company_pool),
+            event_seq: company_pool.next_event_seq(),
             timestamp: clock.unix_timestamp,
         });
 
-        Ok(())
+        if jackpot_payout > 0 {
+            emit!(JackpotWonEvent {
+                winner,
+                ticket_id: user_ticket.ticket_id,
+                jackpot_amount: jackpot_payout,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        ctx.accounts
+            .record_achievements(Some(&winning_item), clock.unix_timestamp);
+        ctx.accounts.settle_reward(clock.unix_timestamp)
     }
 
     pub fn claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
-        let company_pool = &mut ctx.accounts.company_pool;
-        let user_ticket = &mut ctx.accounts.user_ticket;
-        let clock = Clock::get()?;
-
-        // Validate pool state
-        require!(company_pool.active, ErrorCode::PoolInactive);
+        let recipient = ctx.accounts.spinner.to_account_info();
+        ctx.accounts.claim(recipient, false)
+    }
 
-        // Validate ticket ownership and state
-        require!(
-            user_ticket.owner == ctx.accounts.spinner.key(),
-            ErrorCode::NotTicketOwner
-        );
-        require!(
-            user_ticket.company_pool == company_pool.key(),
-            ErrorCode::InvalidTicketPool
-        );
-        require!(user_ticket.used, ErrorCode::TicketNotUsed);
-        require!(!user_ticket.reward_claimed, ErrorCode::RewardAlreadyClaimed);
+    // claim_reward paying into the winner's wrapped SOL account instead, for
+    // treasuries and integrations that hold wSOL. The account is created on
+    // the first such claim, at the winner's expense.
+    pub fn claim_reward_wrapped(ctx: Context<ClaimRewardWrapped>) -> Result<()> {
+        let recipient = ctx.accounts.wsol_account.to_account_info();
+        ctx.accounts.claim.claim(recipient, true)?;
 
-        // Check if user won an item and clone it to avoid borrowing issues
-        let won_item = user_ticket.won_item.as_ref()
-            .ok_or(ErrorCode::NoRewardToClaim)?
-            .clone();
+        // Lamports sent to a native token account only count toward its
+        // token balance once synced
+        sync_native(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.wsol_account.to_account_info(),
+            },
+        ))
+    }
 
-        let reward_amount = won_item.price;
+    // Releases whatever part of a vesting reward has unlocked since the last
+    // claim. The schedule runs linearly from the initial claim to the pool's
+    // vesting duration, so calling it more often only splits the payouts.
+    pub fn claim_vested(ctx: Context<ClaimVested>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
 
-        // Validate vault has sufficient funds
-        let vault_balance = ctx.accounts.pool_vault.lamports();
-        let rent_exempt_minimum = Rent::get()?.minimum_balance(0);
-        let available_balance = vault_balance.saturating_sub(rent_exempt_minimum);
+        let amount = user_ticket.release_vested(company_pool, clock.unix_timestamp)?;
 
+        let vault_balance = ctx.accounts.prize_vault.lamports();
+        let rent_reserve = company_pool.rent_reserve()?;
         require!(
-            reward_amount <= available_balance,
+            amount <= vault_balance.saturating_sub(rent_reserve),
             ErrorCode::InsufficientVaultFunds
         );
 
-        // Create seeds for PDA signing
-        let company_name_bytes = company_pool.company_name.as_bytes();
-        let seeds = &[b"pool_vault", company_name_bytes, &[ctx.bumps.pool_vault]];
+        let company_pool_key = company_pool.key();
+        let seeds = &[
+            b"prize_vault",
+            company_pool_key.as_ref(),
+            &[company_pool.prize_vault_bump],
+        ];
         let signer_seeds = &[&seeds[..]];
 
-        // Transfer reward from vault to winner
         let cpi_accounts = anchor_lang::system_program::Transfer {
-            from: ctx.accounts.pool_vault.to_account_info(),
-            to: ctx.accounts.spinner.to_account_info(),
+            from: ctx.accounts.prize_vault.to_account_info(),
+            to: ctx.accounts.claimant.to_account_info(),
         };
         let cpi_context = CpiContext::new_with_signer(
             ctx.accounts.system_program.to_account_info(),
             cpi_accounts,
             signer_seeds,
         );
-        anchor_lang::system_program::transfer(cpi_context, reward_amount)?;
-
-        // Mark reward as claimed
-        user_ticket.reward_claimed = true;
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
 
-        // Update pool's total funds (tracking purposes)
-        company_pool.total_funds = company_pool
-            .total_funds
-            .saturating_sub(reward_amount);
+        let pool_stats = &mut ctx.accounts.pool_stats;
+        pool_stats.total_rewards_paid = pool_stats
+            .total_rewards_paid
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
 
-        // Log reward claim
-        msg!("🎁 REWARD CLAIMED 🎁");
-        msg!("Winner: {}", ctx.accounts.spinner.key());
-        msg!("Item: {}", won_item.name);
-        msg!("Reward Amount: {} lamports", reward_amount);
-        msg!("Ticket ID: {}", user_ticket.ticket_id);
+        company_pool.release_liability(amount);
+        company_pool.record_reward_paid(amount)?;
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_reward_paid(amount)?;
+        }
 
-        // Emit reward claimed event
-        emit!(RewardClaimedEvent {
-            winner: ctx.accounts.spinner.key(),
+        emit!(VestedRewardClaimedEvent {
+            winner: ctx.accounts.claimant.key(),
             ticket_id: user_ticket.ticket_id,
-            won_item: won_item.clone(),
-            reward_amount,
+            amount,
+            claimed_so_far: user_ticket.claimed_so_far,
+            vested_total: user_ticket.vested_total(),
+            event_seq: company_pool.next_event_seq(),
             timestamp: clock.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn get_probability_analysis(ctx: Context<GetProbabilityAnalysis>) -> Result<()> {
-        let company_pool = &ctx.accounts.company_pool;
+    // Pays out a mega jackpot win from the network-wide vault. Separate from
+    // claim_reward, which only touches the ticket's own pool.
+    pub fn claim_mega_jackpot(ctx: Context<ClaimMegaJackpot>) -> Result<()> {
+        let mega_jackpot = &mut ctx.accounts.mega_jackpot;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
 
-        // Create probability calculator for analysis
-        let items_for_analysis: Vec<(String, u64)> = company_pool
-            .items
-            .iter()
-            .map(|item| (item.name.clone(), item.price))
-            .collect();
+        // Settled like a reward, so a spin can't be retried by claiming in
+        // its own transaction and reverting on a miss
+        user_ticket.check_draw_settled(clock.slot)?;
+        ctx.accounts
+            .company_pool
+            .check_undisputed(user_ticket.spun_at, clock.unix_timestamp)?;
+        let amount = user_ticket.mega_jackpot_won;
+        require!(amount > 0, ErrorCode::NoMegaJackpotWon);
+        mega_jackpot.check_payable(amount, ctx.accounts.mega_jackpot_vault.lamports())?;
 
-        let calculator =
-            WeightedProbabilityCalculator::new(items_for_analysis, company_pool.ticket_price);
-
-        // Emit analysis event for each item
-        for item in &company_pool.items {
-            if let Some(analysis) = calculator.get_profitability_analysis(&item.name) {
-                emit!(ProbabilityAnalysisEvent {
-                    item_name: item.name.clone(),
-                    item_value: item.price,
-                    probability_basis_points: item.probability,
-                    expected_spins: analysis.expected_spins,
-                    expected_cost: analysis.expected_cost,
-                    profit: analysis.profit,
-                    profit_ratio: analysis.profit_ratio,
-                });
-            }
-        }
+        let seeds = &[b"mega_jackpot_vault".as_ref(), &[mega_jackpot.vault_bump]];
+        let signer_seeds = &[&seeds[..]];
 
-        Ok(())
-    }
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.mega_jackpot_vault.to_account_info(),
+            to: ctx.accounts.claimant.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        user_ticket.mega_jackpot_won = 0;
+        mega_jackpot.owed = mega_jackpot
+            .owed
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(MegaJackpotClaimedEvent {
+            winner: ctx.accounts.claimant.key(),
+            user_ticket: user_ticket.key(),
+            amount,
+            event_seq: ctx.accounts.company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
 
-    pub fn get_user_tickets(_ctx: Context<GetUserTickets>) -> Result<()> {
-        // This function can be used to query user tickets
-        // Implementation depends on your specific needs
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct ClaimReward<'info> {
-    #[account(
-        mut,
-        constraint = company_pool.active @ ErrorCode::PoolInactive
-    )]
-    pub company_pool: Account<'info, CompanyPool>,
+    // Pays an insured ticket's cover once its spin came in under the pool's
+    // floor. Only what the prize vault holds beyond the pool's other
+    // obligations can go to insurance.
+    pub fn claim_insurance(ctx: Context<ClaimInsurance>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
 
-    #[account(
-        mut,
-        constraint = user_ticket.owner == spinner.key() @ ErrorCode::NotTicketOwner,
-        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool,
-        constraint = user_ticket.used @ ErrorCode::TicketNotUsed,
-        constraint = !user_ticket.reward_claimed @ ErrorCode::RewardAlreadyClaimed
-    )]
-    pub user_ticket: Account<'info, UserTicket>,
+        company_pool.check_undisputed(user_ticket.spun_at, clock.unix_timestamp)?;
+        let item_value = user_ticket.won_item.as_ref().map_or(0, |item| item.price);
+        let (insurance, prize_forfeited) = user_ticket.take_insurance(company_pool, clock.slot)?;
 
-    #[account(mut)]
-    pub spinner: Signer<'info>,
+        let reserved = company_pool
+            .rent_reserve()?
+            .saturating_add(company_pool.outstanding_liabilities)
+            .saturating_add(company_pool.jackpot_accumulated);
+        require!(
+            insurance.payout <= ctx.accounts.prize_vault.lamports().saturating_sub(reserved),
+            ErrorCode::InsufficientVaultFunds
+        );
 
-    /// CHECK: This is the pool vault PDA that holds the funds
-    #[account(
-        mut,
-        seeds = [b"pool_vault", company_pool.company_name.as_bytes()],
-        bump,
-    )]
-    pub pool_vault: AccountInfo<'info>,
+        let company_pool_key = company_pool.key();
+        let seeds = &[
+            b"prize_vault",
+            company_pool_key.as_ref(),
+            &[company_pool.prize_vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
 
-    pub system_program: Program<'info, System>,
-}
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.prize_vault.to_account_info(),
+            to: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, insurance.payout)?;
+        company_pool.record_insurance_paid(insurance.payout)?;
 
-// Account Structures
-#[derive(Accounts)]
-pub struct GetProbabilityAnalysis<'info> {
-    pub company_pool: Account<'info, CompanyPool>,
-}
+        emit!(InsuranceClaimedEvent {
+            owner: ctx.accounts.owner.key(),
+            company_pool: company_pool_key,
+            ticket_id: user_ticket.ticket_id,
+            item_value,
+            payout: insurance.payout,
+            prize_forfeited,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
 
-#[derive(Accounts)]
-pub struct GetUserTickets<'info> {
-    pub user: Signer<'info>,
-}
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct RecordSpinResult<'info> {
-    #[account(
-        mut,
-        constraint = company_pool.active @ ErrorCode::PoolInactive
-    )]
-    pub company_pool: Account<'info, CompanyPool>,
+    // Pays out a shared ticket's reward, split between its participants by
+    // share. Any participant may claim; every participant wallet goes in
+    // remaining_accounts in share order and gets a separate transfer.
+    pub fn claim_shared_reward<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimSharedReward<'info>>,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let shared_ticket = &ctx.accounts.shared_ticket;
+        let clock = Clock::get()?;
 
-    #[account(
-        mut,
-        constraint = user_ticket.owner == spinner.key() @ ErrorCode::NotTicketOwner,
-        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool,
-        constraint = !user_ticket.used @ ErrorCode::TicketAlreadyUsed
-    )]
-    pub user_ticket: Account<'info, UserTicket>,
+        require!(
+            shared_ticket.is_participant(&ctx.accounts.claimant.key()),
+            ErrorCode::NotTicketOwner
+        );
+        require!(
+            ctx.remaining_accounts.len() == shared_ticket.shares.len(),
+            ErrorCode::ParticipantAccountMismatch
+        );
 
-    #[account(mut)]
-    pub spinner: Signer<'info>,
+        user_ticket.check_spun()?;
+        user_ticket.check_draw_settled(clock.slot)?;
+        company_pool.check_undisputed(user_ticket.spun_at, clock.unix_timestamp)?;
+        require!(
+            !company_pool.is_reward_expired(user_ticket.spun_at, clock.unix_timestamp),
+            ErrorCode::RewardExpired
+        );
 
-    /// CHECK: This is the pool vault PDA that holds the funds
-    #[account(
-        mut,
-        seeds = [b"pool_vault", company_pool.company_name.as_bytes()],
-        bump,
-    )]
-    pub pool_vault: AccountInfo<'info>,
+        // A losing spin can only claim its consolation, if the pool pays one
+        let won_item = user_ticket.won_item.clone();
+        require!(
+            won_item.is_some() || user_ticket.consolation_amount > 0,
+            ErrorCode::NoRewardToClaim
+        );
+        // The reward is split in lamports, which add_pool_item keeps true of
+        // every item a shared ticket can draw
+        if let Some(item) = won_item.as_ref() {
+            require!(
+                item.item_kind != ItemKind::Physical,
+                ErrorCode::PhysicalItemNotPayable
+            );
+            require!(
+                item.item_kind != ItemKind::Token,
+                ErrorCode::SharedTicketsNotSupported
+            );
+        }
+        require!(
+            won_item
+                .as_ref()
+                .is_none_or(|item| !company_pool.reveal_pending(item.item_id)),
+            ErrorCode::ItemNotRevealed
+        );
 
-    pub system_program: Program<'info, System>,
-}
+        let reward_amount = won_item
+            .as_ref()
+            .map_or(user_ticket.consolation_amount, |item| item.price);
+        // Vesting terms are fixed at init and pools with them don't sell
+        // shared tickets, whose owner couldn't sign for claim_vested anyway
+        require!(
+            company_pool.upfront_amount(reward_amount) == reward_amount,
+            ErrorCode::SharedTicketsNotSupported
+        );
+        let rent_reserve = company_pool.rent_reserve()?;
+        let available_balance = ctx
+            .accounts
+            .prize_vault
+            .lamports()
+            .saturating_sub(rent_reserve);
+        require!(
+            reward_amount <= available_balance,
+            ErrorCode::InsufficientVaultFunds
+        );
 
-#[derive(Accounts)]
-pub struct WithdrawFundsFromVault<'info> {
-    #[account(
-        mut,
-        has_one = authority @ ErrorCode::UnauthorizedWithdrawal
-    )]
-    pub company_pool: Account<'info, CompanyPool>,
+        let company_pool_key = company_pool.key();
+        let seeds = &[
+            b"prize_vault",
+            company_pool_key.as_ref(),
+            &[company_pool.prize_vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+        let parts = shared_ticket.split(reward_amount);
+        for ((share, amount), participant_info) in shared_ticket
+            .shares
+            .iter()
+            .zip(parts)
+            .zip(ctx.remaining_accounts.iter())
+        {
+            require_keys_eq!(
+                participant_info.key(),
+                share.owner,
+                ErrorCode::ParticipantAccountMismatch
+            );
+            if amount > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.prize_vault.to_account_info(),
+                    to: participant_info.clone(),
+                };
+                let cpi_context = CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    cpi_accounts,
+                    signer_seeds,
+                );
+                anchor_lang::system_program::transfer(cpi_context, amount)?;
+            }
 
-    /// CHECK: This is the pool vault PDA that holds the funds
-    #[account(
-        mut,
-        seeds = [b"pool_vault", company_pool.company_name.as_bytes()],
-        bump,
-    )]
-    pub pool_vault: AccountInfo<'info>,
+            // Each participant's part of an item win counts toward their
+            // total on the leaderboard, as a claim_reward win would
+            let leaderboard_rank = match (&won_item, ctx.accounts.leaderboard.as_mut()) {
+                (Some(_), Some(leaderboard)) => leaderboard.record_win(share.owner, amount),
+                _ => None,
+            };
 
-    pub system_program: Program<'info, System>,
-}
+            emit!(SharedRewardPaidEvent {
+                participant: share.owner,
+                shared_ticket: shared_ticket.key(),
+                ticket_id: user_ticket.ticket_id,
+                share_bps: share.share_bps,
+                amount,
+                reward_amount,
+                leaderboard_rank,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+        }
 
-#[derive(Accounts)]
-pub struct BuyTicket<'info> {
-    #[account(mut)]
-    pub company_pool: Account<'info, CompanyPool>,
+        user_ticket.mark_claimed()?;
 
-    #[account(
-        init,
-        payer = buyer,
-        space = UserTicket::SPACE,
-        seeds = [
-        b"user_ticket",
-        buyer.key().as_ref(),
-        company_pool.key().as_ref(),
-        &company_pool.total_tickets_sold.to_le_bytes()
-        ],
-        bump
-    )]
-    pub user_ticket: Account<'info, UserTicket>,
+        let pool_stats = &mut ctx.accounts.pool_stats;
+        pool_stats.total_rewards_paid = pool_stats
+            .total_rewards_paid
+            .checked_add(reward_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        company_pool.release_liability(reward_amount);
+        company_pool.record_reward_paid(reward_amount)?;
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_reward_paid(reward_amount)?;
+        }
 
-    #[account(mut)]
-    pub buyer: Signer<'info>,
+        Ok(())
+    }
 
-    /// CHECK: This is the pool vault PDA that holds the funds
-    #[account(
-        mut,
-        seeds = [b"pool_vault", company_pool.company_name.as_bytes()],
-        bump,
-    )]
-    pub pool_vault: AccountInfo<'info>,
+    // Pays the pool's achievement bonus for an achievement the caller has
+    // unlocked, once per achievement, out of the authority's revenue
+    pub fn claim_achievement_reward(
+        ctx: Context<ClaimAchievementReward>,
+        achievement: Achievement,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
 
-    pub system_program: Program<'info, System>,
-}
+        require!(
+            company_pool.achievement_bonus() > 0,
+            ErrorCode::AchievementRewardsDisabled
+        );
+        let user_pool_state = &mut ctx.accounts.user_pool_state;
+        user_pool_state.enter_season(company_pool);
+        user_pool_state.mark_achievement_rewarded(achievement)?;
+        let rent_reserve = company_pool.rent_reserve()?;
+        let amount = company_pool
+            .take_achievement_bonus(ctx.accounts.pool_vault.lamports(), rent_reserve)?;
 
-#[derive(Accounts)]
-#[instruction(ticket_price: u64, company_name: String)]
-pub struct InitializeCompanyPool<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = CompanyPool::SPACE,
-        seeds = [b"company_pool", company_name.as_bytes()],
-        bump
-    )]
-    pub company_pool: Account<'info, CompanyPool>,
+        let vault_seed = company_pool.vault_seed(&company_pool.key());
+        let seeds = &[
+            b"pool_vault",
+            vault_seed.as_slice(),
+            &[company_pool.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.pool_vault.to_account_info(),
+            to: ctx.accounts.claimant.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
 
-    /// CHECK: This is a SOL-only PDA vault that will be created manually.
-    #[account(
-        mut,
-        seeds = [b"pool_vault", company_name.as_bytes()],
-        bump
-    )]
-    pub pool_vault: AccountInfo<'info>,
+        emit!(AchievementRewardClaimedEvent {
+            user: ctx.accounts.claimant.key(),
+            achievement,
+            amount,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        Ok(())
+    }
 
-// Data Structures
-#[account]
-pub struct CompanyPool {
-    pub authority: Pubkey,
-    pub company_name: String,
-    pub company_image: String,
-    pub ticket_price: u64,
-    pub items: Vec<PoolItem>,
-    pub total_value: u64,
-    pub total_tickets_sold: u64,
+    // What items start_index..start_index + count cost a player on average,
+    // read from the stored odds in integer math so a full pool stays well
+    // within the compute and log limits. Larger pools page through their
+    // items over several calls; the first page also carries the pool's
+    // expected value and house edge.
+    pub fn get_probability_analysis(
+        ctx: Context<GetProbabilityAnalysis>,
+        start_index: u8,
+        count: u8,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let start = start_index as usize;
+        require!(
+            count > 0 && start < company_pool.items.len(),
+            ErrorCode::InvalidItemIndex
+        );
+        let end = (start + count as usize).min(company_pool.items.len());
+
+        if start == 0 {
+            let expected_value = company_pool.expected_value()?;
+            let ev_bps = (expected_value as u128 * 10000 / company_pool.ticket_price as u128)
+                .min(i64::MAX as u128) as i64;
+            emit!(PoolAnalysisEvent {
+                company_pool: company_pool.key(),
+                ticket_price: company_pool.ticket_price,
+                item_count: company_pool.items.len() as u32,
+                expected_value,
+                ev_bps,
+                house_edge_bps: 10000 - ev_bps,
+                event_seq: company_pool.next_event_seq(),
+            });
+        }
+
+        // Items that can't be won have nothing to analyze
+        for index in start..end {
+            let Some(analysis) = company_pool.item_profitability(index) else {
+                continue;
+            };
+            let item = &company_pool.items[index];
+            emit!(ProbabilityAnalysisEvent {
+                item_index: index as u32,
+                item_name: item.name.clone(),
+                item_value: item.price,
+                probability_basis_points: item.probability,
+                expected_spins: analysis.expected_spins,
+                expected_cost: analysis.expected_cost,
+                profit: analysis.profit,
+                profit_bps: analysis.profit_bps,
+                event_seq: company_pool.next_event_seq(),
+            });
+        }
+
+        Ok(())
+    }
+
+    // One item's odds for clients to show before a purchase: the chance of
+    // winning it at least once in `spins` spins and the spins it takes on
+    // average, both scaled by ODDS_SCALE. Meant to be simulated, reading the
+    // return data.
+    pub fn get_spin_odds(
+        ctx: Context<GetSpinOdds>,
+        item_index: u32,
+        spins: u32,
+    ) -> Result<SpinOdds> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        require!(
+            company_pool.pool_mode() == PoolMode::Spin,
+            ErrorCode::RaffleModePool
+        );
+        let item = company_pool
+            .items
+            .get(item_index as usize)
+            .ok_or(ErrorCode::InvalidItemIndex)?;
+
+        // Retired items hold 0 of the draw, so they come out as unwinnable
+        let chance = spin_chance(item.probability, company_pool.no_win_bps);
+        let odds = SpinOdds {
+            item_index,
+            spins,
+            chance,
+            probability_in_spins: probability_in_spins(chance, spins),
+            expected_spins: expected_spins(chance),
+        };
+
+        emit!(SpinOddsEvent {
+            company_pool: company_pool.key(),
+            item_index,
+            spins,
+            chance,
+            probability_in_spins: odds.probability_in_spins,
+            expected_spins: odds.expected_spins,
+            event_seq: company_pool.next_event_seq(),
+        });
+
+        Ok(odds)
+    }
+
+    pub fn set_item_availability(
+        ctx: Context<SetItemAvailability>,
+        item_id: u32,
+        available: bool,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Validate authority
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::Unauthorized
+        );
+
+        let index = company_pool
+            .item_position(item_id)
+            .ok_or(ErrorCode::InvalidItemIndex)?;
+        require!(
+            !available
+                || company_pool
+                    .token_prize(item_id)
+                    .is_none_or(|prize| !prize.is_depleted()),
+            ErrorCode::TokenPrizeDepleted
+        );
+
+        company_pool.items[index].available = available;
+
+        // Rebuild the probability table over the remaining available items so
+        // the stored values still sum to 10000
+        company_pool.recalculate_probabilities()?;
+
+        msg!(
+            "Item {} ({}) is now {}",
+            item_id,
+            company_pool.items[index].name,
+            if available { "available" } else { "retired" }
+        );
+
+        emit!(ItemAvailabilityChangedEvent {
+            item_id,
+            available,
+            probabilities: company_pool
+                .items
+                .iter()
+                .map(|item| item.probability)
+                .collect(),
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Multiplies an item's odds between starts_at and ends_at, e.g. 20000 for
+    // double odds over a weekend. The rest of the wheel shrinks to make room
+    // while the boost runs; the stored probabilities never change, so the
+    // odds revert on their own once it ends. A multiplier of 0 removes the
+    // item's boost.
+    pub fn set_item_boost(
+        ctx: Context<SetItemBoost>,
+        item_id: u32,
+        boost_bps_multiplier: u32,
+        starts_at: i64,
+        ends_at: i64,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Older layouts don't store boosts; migrate_pool upgrades them
+        require!(
+            company_pool.version >= CompanyPool::VERSION_BOOSTS,
+            ErrorCode::UnsupportedAccountVersion
+        );
+        let index = company_pool
+            .item_position(item_id)
+            .ok_or(ErrorCode::InvalidItemIndex)?;
+
+        let boost = if boost_bps_multiplier == 0 {
+            ItemBoost::default()
+        } else {
+            let boosted =
+                company_pool.items[index].probability as u64 * boost_bps_multiplier as u64 / 10000;
+            require!(
+                boost_bps_multiplier > 10000 && boosted <= 10000,
+                ErrorCode::InvalidBoost
+            );
+            require!(
+                starts_at < ends_at
+                    && ends_at > clock.unix_timestamp
+                    && company_pool
+                        .end_time()
+                        .is_none_or(|pool_ends_at| ends_at <= pool_ends_at),
+                ErrorCode::InvalidBoostWindow
+            );
+            ItemBoost {
+                item_id,
+                multiplier_bps: boost_bps_multiplier,
+                starts_at,
+                ends_at,
+            }
+        };
+        let slot = company_pool
+            .boost_slot(item_id, clock.unix_timestamp)
+            .ok_or(ErrorCode::TooManyBoosts)?;
+        company_pool.boosts[slot] = boost;
+
+        emit!(ItemBoostSetEvent {
+            item_id,
+            multiplier_bps: boost_bps_multiplier,
+            starts_at,
+            ends_at,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Publishes a mystery item's real name, image and description. They must
+    // hash to the reveal_hash committed at init, so the prize can't be swapped
+    // once tickets are sold. Claims of the item open from here on.
+    pub fn reveal_item(
+        ctx: Context<RevealItem>,
+        item_id: u32,
+        name: String,
+        image: String,
+        description: String,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        let slot = company_pool
+            .pending_reveals()
+            .iter()
+            .position(|pending| pending.item_id == item_id)
+            .ok_or(ErrorCode::NoPendingReveal)?;
+        require!(
+            company_pool.pending_reveals[slot].reveal_hash
+                == PendingReveal::hash_of(&name, &image, &description),
+            ErrorCode::RevealMismatch
+        );
+        require!(name.len() <= 50, ErrorCode::ItemNameTooLong);
+        require!(image.len() <= 200, ErrorCode::ItemImageTooLong);
+        require!(description.len() <= 200, ErrorCode::ItemDescriptionTooLong);
+        require!(
+            company_pool
+                .items
+                .iter()
+                .all(|other| other.item_id == item_id || other.name != name),
+            ErrorCode::DuplicateItemName
+        );
+
+        let index = company_pool
+            .item_position(item_id)
+            .ok_or(ErrorCode::InvalidItemIndex)?;
+        let item = &mut company_pool.items[index];
+        item.name = name.clone();
+        item.image = image.clone();
+        item.description = description.clone();
+        company_pool.clear_pending_reveal(slot);
+
+        emit!(ItemRevealedEvent {
+            item_id,
+            name,
+            image,
+            description,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_pool_item(
+        ctx: Context<UpdatePoolItem>,
+        item_id: u32,
+        new_image: Option<String>,
+        new_description: Option<String>,
+        new_price: Option<u64>,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Validate authority
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::Unauthorized
+        );
+
+        let index = company_pool
+            .item_position(item_id)
+            .ok_or(ErrorCode::InvalidItemIndex)?;
+
+        // Validate inputs with the same limits as initialization
+        if let Some(image) = &new_image {
+            require!(image.len() <= 200, ErrorCode::ItemImageTooLong);
+        }
+        if let Some(description) = &new_description {
+            require!(description.len() <= 200, ErrorCode::ItemDescriptionTooLong);
+        }
+        if let Some(price) = new_price {
+            require!(price > 0, ErrorCode::InvalidItemPrice);
+            company_pool.check_item_price(price)?;
+        }
+
+        let old_item = company_pool.items[index].clone();
+
+        // Item-account pools keep the text on the item's account
+        let pool_key = company_pool.key();
+        let (image, description) = if company_pool.uses_item_accounts() {
+            let pool_item: &mut PoolItemAccount = ctx
+                .accounts
+                .pool_item
+                .as_mut()
+                .filter(|pool_item| pool_item.belongs_to(&pool_key, item_id))
+                .ok_or(ErrorCode::ItemAccountMismatch)?;
+            (&mut pool_item.image, &mut pool_item.description)
+        } else {
+            let item = &mut company_pool.items[index];
+            (&mut item.image, &mut item.description)
+        };
+        let old_image = image.clone();
+        let old_description = description.clone();
+        if let Some(new_image) = new_image {
+            *image = new_image;
+        }
+        if let Some(new_description) = new_description {
+            *description = new_description;
+        }
+        let (new_image, new_description) = (image.clone(), description.clone());
+
+        // Only a price change affects the weights, metadata edits leave the
+        // probability table untouched
+        if let Some(price) = new_price {
+            if price != old_item.price {
+                company_pool.items[index].price = price;
+                company_pool.total_value = company_pool
+                    .total_value
+                    .checked_sub(old_item.price)
+                    .ok_or(ErrorCode::MathOverflow)?
+                    .checked_add(price)
+                    .ok_or(ErrorCode::MathOverflow)?;
+
+                company_pool.recalculate_probabilities()?;
+            }
+        }
+
+        let new_item = &company_pool.items[index];
+
+        emit!(ItemUpdatedEvent {
+            item_id,
+            old_image,
+            new_image,
+            old_description,
+            new_description,
+            old_price: old_item.price,
+            new_price: new_item.price,
+            probabilities: company_pool
+                .items
+                .iter()
+                .map(|item| item.probability)
+                .collect(),
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Item-account pools grow one item at a time. The pool's entry keeps what
+    // spins need, the image and description go on the item's own account.
+    pub fn add_pool_item(ctx: Context<AddPoolItem>, item: PoolItemInput) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(
+            company_pool.uses_item_accounts(),
+            ErrorCode::ItemAccountsDisabled
+        );
+        require!(
+            company_pool.items.len() < CompanyPool::MAX_ACCOUNT_ITEMS,
+            ErrorCode::TooManyItems
+        );
+        item.validate()?;
+        company_pool.check_item_price(item.price)?;
+        // Sponsors share revenue from the first sale, so they join at init
+        require!(item.sponsor.is_none(), ErrorCode::InvalidSponsorShares);
+        require!(item.reveal_hash.is_none(), ErrorCode::InvalidPoolConfig);
+        require!(
+            item.reward_mint.is_none() || !company_pool.auto_payout,
+            ErrorCode::InvalidTokenPrize
+        );
+        company_pool.check_shared_tickets_can_draw(item.item_kind)?;
+        require!(
+            company_pool
+                .items
+                .iter()
+                .all(|other| other.name != item.name),
+            ErrorCode::DuplicateItemName
+        );
+
+        // Same jackpot rules as init: at most one, paid in SOL
+        require!(
+            !item.jackpot
+                || (item.item_kind == ItemKind::SolPayout
+                    && company_pool.items.iter().all(|other| !other.jackpot)),
+            ErrorCode::InvalidPoolConfig
+        );
+
+        let item_id = company_pool.next_item_id();
+        // Like at init, a token item waits for its escrow before joining the draw
+        if let Some(mint) = item.reward_mint {
+            company_pool.add_token_prize(item_id, mint, item.reward_amount)?;
+        }
+        company_pool.items.push(PoolItem {
+            image: String::new(),
+            price: item.price,
+            name: item.name.clone(),
+            description: String::new(),
+            probability: 0,
+            available: item.reward_mint.is_none(),
+            times_won: 0,
+            item_kind: item.item_kind,
+            jackpot: item.jackpot,
+            manual_weight: item.manual_weight,
+            tier: ItemTier::default(),
+            item_id,
+        });
+        company_pool.total_value = company_pool
+            .total_value
+            .checked_add(item.price)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        company_pool.recalculate_probabilities()?;
+
+        // Earlier items keep their tiers, the new one defaults to its quartile
+        let probabilities: Vec<u32> = company_pool
+            .items
+            .iter()
+            .map(|item| item.probability)
+            .collect();
+        let tier = item
+            .tier
+            .unwrap_or(ItemTier::assign_by_probability(&probabilities)[item_id as usize]);
+        company_pool.items[item_id as usize].tier = tier;
+        require!(
+            company_pool
+                .items
+                .iter()
+                .any(|item| item.tier == ItemTier::Common),
+            ErrorCode::CommonTierRequired
+        );
+
+        let pool_item = &mut ctx.accounts.pool_item;
+        pool_item.company_pool = company_pool.key();
+        pool_item.item_id = item_id;
+        pool_item.bump = ctx.bumps.pool_item;
+        pool_item.name = item.name.clone();
+        pool_item.image = item.image;
+        pool_item.description = item.description;
+
+        emit!(PoolItemAddedEvent {
+            item_id,
+            name: item.name,
+            price: item.price,
+            tier,
+            probabilities,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Funds a token item's escrow from the authority's own token account. The
+    // item can only be made available once the escrow covers a win.
+    pub fn deposit_item_tokens(
+        ctx: Context<DepositItemTokens>,
+        item_id: u32,
+        amount: u64,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        let mint = company_pool
+            .token_prize(item_id)
+            .ok_or(ErrorCode::InvalidTokenPrize)?
+            .mint;
+        require_keys_eq!(
+            ctx.accounts.reward_mint.key(),
+            mint,
+            ErrorCode::InvalidTokenPrize
+        );
+
+        anchor_spl::token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.authority_token_account.to_account_info(),
+                    to: ctx.accounts.prize_escrow.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        let escrowed = company_pool.fund_token_prize(item_id, amount)?;
+
+        emit!(ItemTokensDepositedEvent {
+            item_id,
+            mint,
+            amount,
+            escrowed,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Returns a token item's unwon escrow to the authority. Only once the item
+    // has left the draw, and never the part winners have yet to claim.
+    pub fn reclaim_item_tokens(ctx: Context<ReclaimItemTokens>, item_id: u32) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        let index = company_pool
+            .item_position(item_id)
+            .ok_or(ErrorCode::InvalidItemIndex)?;
+        let prize = *company_pool
+            .token_prize(item_id)
+            .ok_or(ErrorCode::InvalidTokenPrize)?;
+        require_keys_eq!(
+            ctx.accounts.reward_mint.key(),
+            prize.mint,
+            ErrorCode::InvalidTokenPrize
+        );
+        require!(
+            !company_pool.items[index].available || prize.is_depleted(),
+            ErrorCode::TokenPrizeActive
+        );
+        require!(prize.escrowed > 0, ErrorCode::NothingToReclaim);
+
+        let amount = company_pool.drain_token_prize(item_id)?;
+        let company_pool_key = company_pool.key();
+        let seeds = &[
+            b"prize_vault",
+            company_pool_key.as_ref(),
+            &[company_pool.prize_vault_bump],
+        ];
+        anchor_spl::token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.prize_escrow.to_account_info(),
+                    to: ctx.accounts.authority_token_account.to_account_info(),
+                    authority: ctx.accounts.prize_vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+        )?;
+
+        emit!(ItemTokensReclaimedEvent {
+            item_id,
+            mint: prize.mint,
+            amount,
+            still_owed: prize.owed,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Publishes the hash of the server seed the next spins of a server-seeded
+    // pool draw under. The last one must be revealed first, so no seed can be
+    // dropped once spins have drawn under it.
+    pub fn commit_server_seed(ctx: Context<CommitServerSeed>, seed_hash: [u8; 32]) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(company_pool.server_seeded(), ErrorCode::ServerSeedsDisabled);
+        require!(
+            !company_pool.server_seed.is_committed(),
+            ErrorCode::ServerSeedAlreadyCommitted
+        );
+        require!(seed_hash != [0; 32], ErrorCode::InvalidServerSeedHash);
+        company_pool.server_seed.hash = seed_hash;
+        company_pool.server_seed.committed_at = clock.unix_timestamp;
+
+        emit!(ServerSeedCommittedEvent {
+            seed_hash,
+            expires_at: company_pool.server_seed.expires_at(),
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Ends a batch by publishing the committed seed. Anyone can then check it
+    // against the hash every spin of the batch reported, and the seed is mixed
+    // into the spins of the next batch. Spins wait for the next commitment.
+    pub fn reveal_server_seed(ctx: Context<RevealServerSeed>, seed: [u8; 32]) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(
+            company_pool.server_seeded() && company_pool.server_seed.is_committed(),
+            ErrorCode::ServerSeedCommitmentMissing
+        );
+        let revealed = company_pool.server_seed;
+        require!(
+            ServerSeedCommitment::hash_of(&seed) == revealed.hash,
+            ErrorCode::ServerSeedMismatch
+        );
+        company_pool.server_seed = ServerSeedCommitment {
+            previous_seed: seed,
+            ..ServerSeedCommitment::default()
+        };
+
+        emit!(ServerSeedRevealedEvent {
+            seed,
+            seed_hash: revealed.hash,
+            spins: revealed.spins,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Changing the price shifts every item's price-to-value ratio, so the
+    // probability table is rebuilt. Tickets already sold keep their price_paid.
+    pub fn update_ticket_price(ctx: Context<UpdateTicketPrice>, new_price: u64) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Validate authority
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(new_price > 0, ErrorCode::InvalidTicketPrice);
+
+        let old_price = company_pool.ticket_price;
+        company_pool.ticket_price = new_price;
+        company_pool.recalculate_probabilities()?;
+
+        emit!(TicketPriceUpdatedEvent {
+            old_price,
+            new_price,
+            probabilities: company_pool
+                .items
+                .iter()
+                .map(|item| item.probability)
+                .collect(),
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Legacy pools predate the stored bumps. Anyone can backfill them once so
+    // the downstream constraints can validate against the stored vault bump.
+    pub fn backfill_pool_bumps(ctx: Context<BackfillPoolBumps>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+
+        require!(company_pool.vault_bump == 0, ErrorCode::BumpsAlreadyStored);
+
+        // Legacy pools were seeded by the raw company name
+        let (pool_address, pool_bump) = Pubkey::find_program_address(
+            &[b"company_pool", company_pool.company_name.as_bytes()],
+            ctx.program_id,
+        );
+        require_keys_eq!(
+            pool_address,
+            company_pool.key(),
+            ErrorCode::InvalidPoolAccount
+        );
+
+        company_pool.vault_bump = ctx.bumps.pool_vault;
+        company_pool.pool_bump = pool_bump;
+
+        msg!(
+            "Backfilled bumps for {}: pool {}, vault {}",
+            company_pool.company_name,
+            pool_bump,
+            company_pool.vault_bump
+        );
+
+        Ok(())
+    }
+
+    // One-time setup of the program-wide config. Only the program's upgrade
+    // authority can create it and it becomes the config admin.
+    pub fn initialize_global_config(ctx: Context<InitializeGlobalConfig>) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+
+        global_config.admin = ctx.accounts.admin.key();
+        global_config.program_paused = false;
+        global_config.bump = ctx.bumps.global_config;
+        global_config.creation_mode = CreationMode::Open;
+        global_config.treasury = ctx.accounts.admin.key();
+        global_config.pool_creation_fee_lamports = 0;
+
+        msg!("Global config initialized, admin: {}", global_config.admin);
+
+        Ok(())
+    }
+
+    pub fn set_program_paused(ctx: Context<SetProgramPaused>, paused: bool) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+        let clock = Clock::get()?;
+
+        global_config.program_paused = paused;
+
+        msg!("Program paused: {}", paused);
+
+        emit!(ProgramPausedEvent {
+            admin: ctx.accounts.admin.key(),
+            paused,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Switches pool creation between open to anyone and limited to the
+    // creators added with add_pool_creator
+    pub fn set_pool_creation_mode(
+        ctx: Context<SetPoolCreationMode>,
+        mode: CreationMode,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        ctx.accounts.global_config.creation_mode = mode;
+
+        emit!(PoolCreationModeSetEvent {
+            admin: ctx.accounts.admin.key(),
+            mode,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Sets the flat fee pool authorities pay the treasury, 0 to stop charging
+    pub fn update_creation_fee(
+        ctx: Context<UpdateCreationFee>,
+        fee_lamports: u64,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+        let clock = Clock::get()?;
+
+        let old_fee = global_config.pool_creation_fee_lamports;
+        global_config.pool_creation_fee_lamports = fee_lamports;
+        global_config.treasury = treasury;
+
+        emit!(CreationFeeUpdatedEvent {
+            admin: ctx.accounts.admin.key(),
+            old_fee,
+            new_fee: fee_lamports,
+            treasury,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_pool_creator(ctx: Context<AddPoolCreator>, creator: Pubkey) -> Result<()> {
+        let creator_entry = &mut ctx.accounts.creator_entry;
+        let clock = Clock::get()?;
+
+        creator_entry.creator = creator;
+        creator_entry.added_at = clock.unix_timestamp;
+        creator_entry.bump = ctx.bumps.creator_entry;
+
+        emit!(PoolCreatorUpdatedEvent {
+            creator,
+            allowed: true,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Closing the entry only blocks future pools; the creator's existing
+    // pools keep running
+    pub fn remove_pool_creator(_ctx: Context<RemovePoolCreator>, creator: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+
+        emit!(PoolCreatorUpdatedEvent {
+            creator,
+            allowed: false,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Stops a wallet from buying tickets. Tickets it already holds can still
+    // be spun and claimed.
+    pub fn block_user(ctx: Context<BlockUser>, user: Pubkey) -> Result<()> {
+        let blocked_user = &mut ctx.accounts.blocked_user;
+        let clock = Clock::get()?;
+
+        blocked_user.user = user;
+        blocked_user.blocked_at = clock.unix_timestamp;
+        blocked_user.bump = ctx.bumps.blocked_user;
+
+        emit!(UserBlockUpdatedEvent {
+            user,
+            blocked: true,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn unblock_user(_ctx: Context<UnblockUser>, user: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+
+        emit!(UserBlockUpdatedEvent {
+            user,
+            blocked: false,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Moves a global config created before the creation mode existed to the
+    // current layout. Every instruction that reads the config needs this
+    // done first. The payer covers the extra rent.
+    pub fn migrate_global_config(ctx: Context<MigrateGlobalConfig>) -> Result<()> {
+        let config_info = ctx.accounts.global_config.to_account_info();
+        require!(
+            config_info.data_len() < 8 + GlobalConfig::INIT_SPACE,
+            ErrorCode::AccountAlreadyMigrated
+        );
+        let global_config = GlobalConfig::read_any_version(&config_info.try_borrow_data()?)?;
+
+        migration::rewrite_account(
+            &config_info,
+            &global_config,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        msg!(
+            "Global config migrated, creation mode: {:?}, creation fee: {}",
+            global_config.creation_mode,
+            global_config.pool_creation_fee_lamports
+        );
+
+        Ok(())
+    }
+
+    // Protocol-wide counters start from zero here; activity before this, or
+    // from clients that leave the account out, isn't counted
+    pub fn initialize_global_stats(ctx: Context<InitializeGlobalStats>) -> Result<()> {
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.bump = ctx.bumps.global_stats;
+
+        msg!("Global stats initialized");
+
+        Ok(())
+    }
+
+    // Opens the network-wide mega jackpot. Pools created with
+    // PoolConfig::mega_jackpot pay into it from then on.
+    pub fn initialize_mega_jackpot(
+        ctx: Context<InitializeMegaJackpot>,
+        contribution_bps: u16,
+        hit_chance_bps: u16,
+    ) -> Result<()> {
+        MegaJackpot::check_terms(contribution_bps, hit_chance_bps)?;
+        let clock = Clock::get()?;
+
+        let minimum_balance = Rent::get()?.minimum_balance(0);
+        let vault = &ctx.accounts.mega_jackpot_vault;
+        let shortfall = minimum_balance.saturating_sub(vault.lamports());
+        if shortfall > 0 {
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.admin.to_account_info(),
+                to: vault.to_account_info(),
+            };
+            let cpi_context =
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            anchor_lang::system_program::transfer(cpi_context, shortfall)?;
+        }
+
+        let mega_jackpot = &mut ctx.accounts.mega_jackpot;
+        mega_jackpot.contribution_bps = contribution_bps;
+        mega_jackpot.hit_chance_bps = hit_chance_bps;
+        mega_jackpot.vault_rent_reserve = minimum_balance;
+        mega_jackpot.bump = ctx.bumps.mega_jackpot;
+        mega_jackpot.vault_bump = ctx.bumps.mega_jackpot_vault;
+
+        emit!(MegaJackpotTermsSetEvent {
+            admin: ctx.accounts.admin.key(),
+            contribution_bps,
+            hit_chance_bps,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // New terms apply from the next sale and spin; the pot carries over
+    pub fn set_mega_jackpot_terms(
+        ctx: Context<SetMegaJackpotTerms>,
+        contribution_bps: u16,
+        hit_chance_bps: u16,
+    ) -> Result<()> {
+        MegaJackpot::check_terms(contribution_bps, hit_chance_bps)?;
+        let clock = Clock::get()?;
+
+        let mega_jackpot = &mut ctx.accounts.mega_jackpot;
+        mega_jackpot.contribution_bps = contribution_bps;
+        mega_jackpot.hit_chance_bps = hit_chance_bps;
+
+        emit!(MegaJackpotTermsSetEvent {
+            admin: ctx.accounts.admin.key(),
+            contribution_bps,
+            hit_chance_bps,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Sets or clears one of the program admin's flags on a pool, typically to
+    // freeze a pool impersonating a known brand
+    pub fn admin_set_pool_flag(
+        ctx: Context<AdminSetPoolFlag>,
+        flag: PoolFlag,
+        enabled: bool,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Older layouts don't store flags; migrate_pool upgrades them
+        require!(
+            company_pool.version >= CompanyPool::VERSION_POOL_FLAGS,
+            ErrorCode::UnsupportedAccountVersion
+        );
+        if enabled {
+            company_pool.pool_flags |= flag.bit();
+        } else {
+            company_pool.pool_flags &= !flag.bit();
+        }
+
+        emit!(PoolFlagSetEvent {
+            company_pool: company_pool.key(),
+            admin: ctx.accounts.admin.key(),
+            flag,
+            enabled,
+            pool_flags: company_pool.pool_flags,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Returns the spin history oldest-first via return data, meant to be
+    // read by simulating the instruction
+    pub fn get_spin_history(ctx: Context<GetSpinHistory>) -> Result<Vec<SpinHistoryEntry>> {
+        Ok(ctx.accounts.spin_history.ordered_entries())
+    }
+
+    // Newest wins first, skipping the `skip` most recent ones. Return data
+    // caps out at 1024 bytes, so the list comes back a page at a time.
+    pub fn get_recent_winners(
+        ctx: Context<GetRecentWinners>,
+        skip: u32,
+    ) -> Result<Vec<RecentWinner>> {
+        Ok(ctx.accounts.winner_registry.load()?.recent(skip as usize))
+    }
+
+    // Pools created before the registry existed need one before they can spin
+    pub fn initialize_winner_registry(ctx: Context<InitializeWinnerRegistry>) -> Result<()> {
+        let mut winner_registry = ctx.accounts.winner_registry.load_init()?;
+        winner_registry.company_pool = ctx.accounts.company_pool.key();
+        winner_registry.bump = ctx.bumps.winner_registry;
+        Ok(())
+    }
+
+    // Biggest winners first, meant to be read by simulating the instruction
+    pub fn get_leaderboard(ctx: Context<GetLeaderboard>) -> Result<Vec<LeaderboardEntry>> {
+        Ok(ctx.accounts.leaderboard.ranked().to_vec())
+    }
+
+    // Pools created before the leaderboard existed claim without one until
+    // someone creates it
+    pub fn initialize_leaderboard(ctx: Context<InitializeLeaderboard>) -> Result<()> {
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        leaderboard.company_pool = ctx.accounts.company_pool.key();
+        leaderboard.bump = ctx.bumps.leaderboard;
+        Ok(())
+    }
+
+    pub fn get_pool_stats(ctx: Context<GetPoolStats>) -> Result<PoolStats> {
+        Ok(ctx.accounts.pool_stats.as_ref().clone().into_inner())
+    }
+
+    pub fn get_global_stats(ctx: Context<GetGlobalStats>) -> Result<GlobalStats> {
+        Ok(ctx.accounts.global_stats.clone().into_inner())
+    }
+
+    // Compares each item's configured probability with how often it has
+    // actually been won, so anyone can audit the pseudo-randomness
+    pub fn get_distribution_report(
+        ctx: Context<GetDistributionReport>,
+    ) -> Result<Vec<DistributionReportEntry>> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let report = company_pool.distribution_report()?;
+
+        for entry in &report {
+            emit!(DistributionReportEvent {
+                item_index: entry.item_index,
+                configured_bps: entry.configured_bps,
+                times_won: entry.times_won,
+                realized_bps: entry.realized_bps,
+                total_spins: entry.total_spins,
+                event_seq: company_pool.next_event_seq(),
+            });
+        }
+
+        Ok(report)
+    }
+
+    // Compares the ledger counters with what the vaults actually hold. A
+    // non-zero delta means lamports moved outside the program, e.g. a direct
+    // transfer into a vault. Pools that predate the counters carry their
+    // earlier balance as a standing delta.
+    pub fn reconcile(ctx: Context<Reconcile>) -> Result<ReconciliationReport> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+        let rent_reserve = company_pool.rent_reserve()?;
+
+        let actual_balance = ctx
+            .accounts
+            .pool_vault
+            .lamports()
+            .checked_add(ctx.accounts.prize_vault.lamports())
+            .ok_or(ErrorCode::MathOverflow)?;
+        let report = company_pool.reconcile(actual_balance, rent_reserve)?;
+
+        emit!(VaultReconciledEvent {
+            company_pool: company_pool.key(),
+            expected_balance: report.expected_balance,
+            actual_balance: report.actual_balance,
+            delta: report.delta,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(report)
+    }
+
+    pub fn issue_voucher(ctx: Context<IssueVoucher>, recipient: Pubkey, count: u8) -> Result<()> {
+        let voucher = &mut ctx.accounts.voucher;
+        let clock = Clock::get()?;
+
+        require!(count > 0, ErrorCode::InvalidAmount);
+
+        // First issue creates the voucher, later issues top it up
+        if voucher.recipient == Pubkey::default() {
+            voucher.company_pool = ctx.accounts.company_pool.key();
+            voucher.recipient = recipient;
+            voucher.bump = ctx.bumps.voucher;
+        }
+
+        voucher.spins_remaining = voucher
+            .spins_remaining
+            .checked_add(count as u32)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(VoucherIssuedEvent {
+            company_pool: voucher.company_pool,
+            recipient,
+            count,
+            spins_remaining: voucher.spins_remaining,
+            event_seq: ctx.accounts.company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Creates a ticket exactly like buy_ticket but without payment, so
+    // total_funds is untouched while ticket ids stay sequential
+    pub fn redeem_voucher(ctx: Context<RedeemVoucher>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let voucher = &mut ctx.accounts.voucher;
+        let clock = Clock::get()?;
+
+        // Validate pool state
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_player(&ctx.accounts.redeemer.key())?;
+        BlockedUser::check_not_blocked(&ctx.accounts.blocked_user)?;
+        require!(!company_pool.items.is_empty(), ErrorCode::NoItemsProvided);
+        require!(
+            voucher.spins_remaining > 0,
+            ErrorCode::NoVoucherSpinsRemaining
+        );
+
+        voucher.spins_remaining -= 1;
+
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        user_ticket.issue(ctx.accounts.redeemer.key(), company_pool, 0, &clock)?;
+
+        emit!(VoucherRedeemedEvent {
+            redeemer: ctx.accounts.redeemer.key(),
+            ticket_id: user_ticket.ticket_id,
+            spins_remaining: voucher.spins_remaining,
+            total_tickets_sold: company_pool.total_tickets_sold,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Trades loyalty points for a free ticket; the pool receives no funds for it
+    pub fn redeem_points_for_ticket(ctx: Context<RedeemPointsForTicket>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Validate pool state
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_open(clock.unix_timestamp)?;
+        BlockedUser::check_not_blocked(&ctx.accounts.blocked_user)?;
+        require!(!company_pool.items.is_empty(), ErrorCode::NoItemsProvided);
+
+        ctx.accounts
+            .user_pool_state
+            .spend_loyalty_points(company_pool)?;
+
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        user_ticket.issue(ctx.accounts.redeemer.key(), company_pool, 0, &clock)?;
+
+        emit!(TicketPurchasedEvent {
+            buyer: ctx.accounts.redeemer.key(),
+            ticket_price: 0,
+            source: TicketSource::LoyaltyPoints,
+            total_tickets_sold: company_pool.total_tickets_sold,
+            ticket_id: user_ticket.ticket_id,
+            gate_passed: false,
+            jackpot_accumulated: company_pool.jackpot_accumulated,
+            prize_share: 0,
+            discount: 0,
+            donation: 0,
+            insurance_premium: 0,
+            pool_flags: company_pool.flags(),
+            external_id: None,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Revokes whatever is left on a voucher and returns its rent to the authority
+    pub fn revoke_voucher(ctx: Context<RevokeVoucher>) -> Result<()> {
+        let clock = Clock::get()?;
+
+        emit!(VoucherRevokedEvent {
+            company_pool: ctx.accounts.company_pool.key(),
+            recipient: ctx.accounts.voucher.recipient,
+            spins_revoked: ctx.accounts.voucher.spins_remaining,
+            event_seq: ctx.accounts.company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Buys a ticket on behalf of someone else; the recipient owns the ticket
+    // and it counts against the recipient's purchase limits and earns them points
+    pub fn gift_ticket(ctx: Context<GiftTicket>, recipient: Pubkey) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Validate pool state
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_open(clock.unix_timestamp)?;
+        company_pool.check_not_frozen()?;
+        company_pool.check_purchase_caller()?;
+        company_pool.check_player(&recipient)?;
+        // A blocked wallet can neither pay for a gift nor receive one
+        BlockedUser::check_not_blocked(&ctx.accounts.payer_blocked_user)?;
+        BlockedUser::check_not_blocked(&ctx.accounts.recipient_blocked_user)?;
+        require!(!company_pool.items.is_empty(), ErrorCode::NoItemsProvided);
+        company_pool.check_whitelist(
+            &company_pool.key(),
+            ctx.accounts.recipient_whitelist_entry.as_deref(),
+            &recipient,
+        )?;
+        let gate_passed = company_pool.check_token_gate(
+            ctx.accounts.recipient_gate_token_account.as_deref(),
+            ctx.accounts.recipient_gate_metadata.as_ref(),
+            &recipient,
+        )?;
+        company_pool.record_slot_purchases(1, clock.slot)?;
+
+        let recipient_state = &mut ctx.accounts.recipient_pool_state;
+        recipient_state.bump = ctx.bumps.recipient_pool_state;
+        recipient_state.version = UserPoolState::VERSION;
+        recipient_state.record_purchase(company_pool, 1, clock.unix_timestamp)?;
+        recipient_state.credit_loyalty_points(company_pool, 1)?;
+
+        let ticket_price = company_pool.ticket_price;
+
+        // Split the payment between the revenue and prize vaults, less the
+        // mega jackpot's share of the revenue
+        let (revenue_share, prize_share) = company_pool.split_ticket_payment(ticket_price)?;
+        let mega_jackpot_share = pay_mega_jackpot_share(
+            company_pool,
+            ctx.accounts.mega_jackpot.as_deref_mut(),
+            ctx.accounts.mega_jackpot_vault.as_ref(),
+            ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program,
+            ticket_price,
+        )?;
+        let revenue_share = revenue_share
+            .checked_sub(mega_jackpot_share)
+            .ok_or(ErrorCode::InsufficientFunds)?;
+        for (vault, amount) in [
+            (ctx.accounts.pool_vault.to_account_info(), revenue_share),
+            (ctx.accounts.prize_vault.to_account_info(), prize_share),
+        ] {
+            if amount > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: vault,
+                };
+                let cpi_context =
+                    CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_context, amount)?;
+            }
+        }
+
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        user_ticket.issue(recipient, company_pool, ticket_price, &clock)?;
+
+        company_pool.record_ticket_sale(ticket_price)?;
+        company_pool.record_mega_jackpot_contribution(mega_jackpot_share)?;
+
+        emit!(TicketGiftedEvent {
+            payer: ctx.accounts.payer.key(),
+            recipient,
+            ticket_price,
+            ticket_id: user_ticket.ticket_id,
+            gate_passed,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Buys a ticket owned jointly by up to four participants. The payer funds
+    // it and the purchase counts against their limits, but the ticket belongs
+    // to a SharedTicket PDA recording each participant's share.
+    pub fn buy_shared_ticket(
+        ctx: Context<BuySharedTicket>,
+        shares: Vec<TicketShare>,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Validate pool state
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_open(clock.unix_timestamp)?;
+        company_pool.check_not_frozen()?;
+        company_pool.check_purchase_caller()?;
+        company_pool.check_player(&ctx.accounts.payer.key())?;
+        BlockedUser::check_not_blocked(&ctx.accounts.blocked_user)?;
+        require!(!company_pool.items.is_empty(), ErrorCode::NoItemsProvided);
+        company_pool.check_sells_shared_tickets()?;
+        // Older layouts can't count shared tickets; migrate_pool upgrades them
+        require!(
+            company_pool.version >= CompanyPool::VERSION_SHARED_TICKETS,
+            ErrorCode::UnsupportedAccountVersion
+        );
+        SharedTicket::check_shares(&shares)?;
+        for share in &shares {
+            company_pool.check_player(&share.owner)?;
+        }
+        company_pool.record_slot_purchases(1, clock.slot)?;
+
+        // The payer's purchase, so it counts toward their limits and streak
+        let payer_state = &mut ctx.accounts.payer_pool_state;
+        payer_state.bump = ctx.bumps.payer_pool_state;
+        payer_state.version = UserPoolState::VERSION;
+        payer_state.record_purchase(company_pool, 1, clock.unix_timestamp)?;
+        payer_state.credit_loyalty_points(company_pool, 1)?;
+        let streak = payer_state.record_play_day(company_pool, clock.unix_timestamp)?;
+
+        let ticket_price = company_pool.ticket_price_at(clock.unix_timestamp)?;
+
+        // Split the payment between the revenue and prize vaults
+        let (revenue_share, prize_share) = company_pool.split_ticket_payment(ticket_price)?;
+        for (vault, amount) in [
+            (ctx.accounts.pool_vault.to_account_info(), revenue_share),
+            (ctx.accounts.prize_vault.to_account_info(), prize_share),
+        ] {
+            if amount > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: vault,
+                };
+                let cpi_context =
+                    CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_context, amount)?;
+            }
+        }
+
+        let shared_ticket = &mut ctx.accounts.shared_ticket;
+        shared_ticket.company_pool = company_pool.key();
+        shared_ticket.user_ticket = ctx.accounts.user_ticket.key();
+        shared_ticket.payer = ctx.accounts.payer.key();
+        shared_ticket.shares = shares.clone();
+        shared_ticket.bump = ctx.bumps.shared_ticket;
+
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        user_ticket.issue(shared_ticket.key(), company_pool, ticket_price, &clock)?;
+
+        company_pool.record_ticket_sale(ticket_price)?;
+        company_pool.shared_tickets_sold = company_pool
+            .shared_tickets_sold
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_tickets_sold(1)?;
+        }
+
+        emit!(SharedTicketPurchasedEvent {
+            payer: ctx.accounts.payer.key(),
+            shared_ticket: shared_ticket.key(),
+            shares,
+            ticket_price,
+            ticket_id: user_ticket.ticket_id,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+        if let Some(streak) = streak {
+            emit!(StreakUpdatedEvent {
+                user: ctx.accounts.payer.key(),
+                current_streak: streak.current_streak,
+                milestone_hit: streak.bonus_points > 0,
+                bonus_points: streak.bonus_points,
+                loyalty_points: payer_state.loyalty_points,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn add_to_whitelist(ctx: Context<AddToWhitelist>, user: Pubkey) -> Result<()> {
+        let whitelist_entry = &mut ctx.accounts.whitelist_entry;
+        let clock = Clock::get()?;
+
+        whitelist_entry.company_pool = ctx.accounts.company_pool.key();
+        whitelist_entry.user = user;
+        whitelist_entry.added_at = clock.unix_timestamp;
+        whitelist_entry.bump = ctx.bumps.whitelist_entry;
+
+        emit!(WhitelistUpdatedEvent {
+            company_pool: whitelist_entry.company_pool,
+            user,
+            whitelisted: true,
+            event_seq: ctx.accounts.company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Closing the entry only blocks future purchases; tickets already bought stay valid
+    pub fn remove_from_whitelist(ctx: Context<RemoveFromWhitelist>, user: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+
+        emit!(WhitelistUpdatedEvent {
+            company_pool: ctx.accounts.company_pool.key(),
+            user,
+            whitelisted: false,
+            event_seq: ctx.accounts.company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Double-or-nothing on an unclaimed reward, allowed once per ticket
+    pub fn gamble_reward(ctx: Context<GambleReward>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
+
+        user_ticket.check_spun()?;
+        require!(!user_ticket.gambled, ErrorCode::AlreadyGambled);
+        user_ticket.check_draw_settled(clock.slot)?;
+        company_pool.check_undisputed(user_ticket.spun_at, clock.unix_timestamp)?;
+        require!(
+            !company_pool.is_reward_expired(user_ticket.spun_at, clock.unix_timestamp),
+            ErrorCode::RewardExpired
+        );
+
+        let won_item = user_ticket
+            .won_item
+            .as_ref()
+            .ok_or(ErrorCode::NoRewardToClaim)?;
+        require!(
+            won_item.item_kind == ItemKind::SolPayout,
+            ErrorCode::PhysicalItemNotPayable
+        );
+        let old_value = won_item.price;
+
+        let random_seed = generate_random_seed(
+            clock.unix_timestamp,
+            clock.slot,
+            &ctx.accounts.spinner.key(),
+            company_pool.total_tickets_sold,
+            ctx.accounts.pool_vault.lamports(),
+            user_ticket.ticket_id,
+        );
+        let win_bps = company_pool
+            .gamble_win_bps
+            .unwrap_or(CompanyPool::DEFAULT_GAMBLE_WIN_BPS);
+        let won = random_seed % 10000 < win_bps as u64;
+
+        user_ticket.gambled = true;
+        user_ticket.spun_slot = clock.slot;
+
+        let new_value = if won {
+            // The cap never lowers a reward below what was already won
+            let doubled = old_value
+                .checked_mul(2)
+                .ok_or(ErrorCode::MathOverflow)?
+                .min(company_pool.gamble_cap())
+                .max(old_value);
+            if let Some(won_item) = user_ticket.won_item.as_mut() {
+                won_item.price = doubled;
+            }
+            company_pool.add_liability(doubled - old_value)?;
+            doubled
+        } else {
+            user_ticket.won_item = None;
+            company_pool.release_liability(old_value);
+            0
+        };
+
+        emit!(RewardGambledEvent {
+            spinner: ctx.accounts.spinner.key(),
+            ticket_id: user_ticket.ticket_id,
+            won,
+            old_value,
+            new_value,
+            random_seed,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Second chance: pay the pool's reroll price to discard a spin result and
+    // draw again. Allowed once per ticket, before the reward is settled.
+    pub fn reroll_spin(ctx: Context<RerollSpin>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
+
+        company_pool.check_not_frozen()?;
+        let reroll_price = company_pool.reroll_price;
+        require!(reroll_price > 0, ErrorCode::RerollDisabled);
+        require!(
+            !company_pool.is_reward_expired(user_ticket.spun_at, clock.unix_timestamp),
+            ErrorCode::RewardExpired
+        );
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.spinner.to_account_info(),
+            to: ctx.accounts.pool_vault.to_account_info(),
+        };
+        let cpi_context =
+            CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        anchor_lang::system_program::transfer(cpi_context, reroll_price)?;
+        company_pool.record_reroll_fee(reroll_price)?;
+
+        // Discard the old result and whatever the vault owed for it
+        let mega_jackpot = ctx.accounts.mega_jackpot.as_deref_mut();
+        let old_item = user_ticket.discard_result(company_pool, mega_jackpot.map(|j| &mut **j))?;
+        user_ticket.rerolled = true;
+        user_ticket.spun_slot = clock.slot;
+
+        // The fee is already in the vault, so the seed differs from the
+        // original spin's even within the same slot
+        let entropy = generate_random_seed(
+            clock.unix_timestamp,
+            clock.slot,
+            &ctx.accounts.spinner.key(),
+            company_pool.total_tickets_sold,
+            ctx.accounts.pool_vault.lamports(),
+            user_ticket.ticket_id,
+        );
+        let (random_seed, server_seed_hash) =
+            company_pool.mix_server_seed(entropy, clock.unix_timestamp)?;
+
+        // The re-draw replaces the spin's record and counts as a spin of its
+        // own in the pool's statistics
+        let draw_table = company_pool.draw_table(user_ticket.forced_min_tier, clock.unix_timestamp);
+        let drawn = company_pool.draw_at(
+            random_seed,
+            user_ticket.forced_min_tier,
+            clock.unix_timestamp,
+        )?;
+        user_ticket.draw_record = Some(DrawRecord::new(random_seed, &draw_table, drawn));
+        let pool_stats = &mut ctx.accounts.pool_stats;
+        match drawn {
+            Some(index) => {
+                let (won_item, jackpot_payout) = company_pool.award_item(index)?;
+                let item = &company_pool.items[index];
+                pool_stats.record_spin(index, item.tier, item.price, ctx.accounts.spinner.key())?;
+                user_ticket.won_item = Some(won_item);
+                user_ticket.serial = item.times_won;
+
+                if jackpot_payout > 0 {
+                    emit!(JackpotWonEvent {
+                        winner: ctx.accounts.spinner.key(),
+                        ticket_id: user_ticket.ticket_id,
+                        jackpot_amount: jackpot_payout,
+                        event_seq: company_pool.next_event_seq(),
+                        timestamp: clock.unix_timestamp,
+                    });
+                }
+            }
+            None => {
+                pool_stats.record_no_win()?;
+                user_ticket.consolation_amount = company_pool.consolation_lamports;
+                user_ticket.serial = 0;
+            }
+        }
+        company_pool.add_liability(user_ticket.vault_liability())?;
+
+        emit!(SpinRerolledEvent {
+            spinner: ctx.accounts.spinner.key(),
+            ticket_id: user_ticket.ticket_id,
+            old_item,
+            new_item: user_ticket.won_item.clone(),
+            reroll_price,
+            random_seed,
+            server_seed_hash,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Alternative to claim_reward: take a discounted SOL payout for the won item
+    pub fn trade_in_reward(ctx: Context<TradeInReward>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
+
+        require!(company_pool.trade_in_bps > 0, ErrorCode::TradeInDisabled);
+        user_ticket.check_draw_settled(clock.slot)?;
+        company_pool.check_undisputed(user_ticket.spun_at, clock.unix_timestamp)?;
+        require!(
+            !company_pool.is_reward_expired(user_ticket.spun_at, clock.unix_timestamp),
+            ErrorCode::RewardExpired
+        );
+
+        let won_item = user_ticket
+            .won_item
+            .as_ref()
+            .ok_or(ErrorCode::NoRewardToClaim)?;
+        require!(
+            won_item.item_kind == ItemKind::SolPayout,
+            ErrorCode::PhysicalItemNotPayable
+        );
+        let item_value = won_item.price;
+        let payout = company_pool.trade_in_payout(item_value)?;
+        let margin = item_value - payout;
+
+        // Validate the prize vault has sufficient funds
+        let vault_balance = ctx.accounts.prize_vault.lamports();
+        let rent_reserve = company_pool.rent_reserve()?;
+        let available_balance = vault_balance.saturating_sub(rent_reserve);
+
+        require!(
+            payout <= available_balance,
+            ErrorCode::InsufficientVaultFunds
+        );
+
+        // Create seeds for PDA signing
+        let company_pool_key = company_pool.key();
+        let seeds = &[
+            b"prize_vault",
+            company_pool_key.as_ref(),
+            &[company_pool.prize_vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        // Transfer the trade-in payout from prize vault to winner
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.prize_vault.to_account_info(),
+            to: ctx.accounts.spinner.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, payout)?;
+
+        // Trading in settles the reward, so it can't be claimed afterwards
+        user_ticket.mark_claimed()?;
+        user_ticket.traded_in = true;
+
+        let pool_stats = &mut ctx.accounts.pool_stats;
+        pool_stats.total_rewards_paid = pool_stats
+            .total_rewards_paid
+            .checked_add(payout)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool_stats.total_trade_in_margin = pool_stats
+            .total_trade_in_margin
+            .checked_add(margin)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // The whole item value is settled, the margin stays in the prize vault
+        company_pool.release_liability(item_value);
+        company_pool.record_reward_paid(payout)?;
+
+        emit!(RewardTradedInEvent {
+            winner: ctx.accounts.spinner.key(),
+            ticket_id: user_ticket.ticket_id,
+            item_value,
+            payout,
+            margin,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Releases the liability of a reward left unclaimed past the pool's claim
+    // deadline, optionally closing the ticket and refunding its rent to the owner
+    pub fn reclaim_expired_reward(
+        ctx: Context<ReclaimExpiredReward>,
+        close_ticket: bool,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
+
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::Unauthorized
+        );
+        let item_value = user_ticket.expire_reward(company_pool, clock.unix_timestamp)?;
+
+        emit!(RewardExpiredEvent {
+            company_pool: company_pool.key(),
+            owner: user_ticket.owner,
+            ticket_id: user_ticket.ticket_id,
+            item_value,
+            ticket_closed: close_ticket,
+            cranker: None,
+            bounty: 0,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        if close_ticket {
+            ctx.accounts
+                .user_ticket
+                .close(ctx.accounts.ticket_owner.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    // Permissionless version of reclaim_expired_reward, so expiring rewards
+    // doesn't need the authority's cron job. The caller gets a small bounty
+    // from the pool's revenue; the ticket can only expire once, so it can
+    // only be paid once.
+    pub fn crank_expire_reward(ctx: Context<CrankExpireReward>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
+
+        let item_value = user_ticket.expire_reward(company_pool, clock.unix_timestamp)?;
+        let rent_reserve = company_pool.rent_reserve()?;
+        let bounty =
+            company_pool.take_crank_bounty(ctx.accounts.pool_vault.lamports(), rent_reserve)?;
+
+        if bounty > 0 {
+            let vault_seed = company_pool.vault_seed(&company_pool.key());
+            let seeds = &[
+                b"pool_vault",
+                vault_seed.as_slice(),
+                &[company_pool.vault_bump],
+            ];
+            let signer_seeds = &[&seeds[..]];
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.pool_vault.to_account_info(),
+                to: ctx.accounts.cranker.to_account_info(),
+            };
+            let cpi_context = CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            anchor_lang::system_program::transfer(cpi_context, bounty)?;
+        }
+
+        emit!(RewardExpiredEvent {
+            company_pool: company_pool.key(),
+            owner: user_ticket.owner,
+            ticket_id: user_ticket.ticket_id,
+            item_value,
+            ticket_closed: false,
+            cranker: Some(ctx.accounts.cranker.key()),
+            bounty,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Retires a pool whose sales have ended. Anyone can call it once the claim
+    // window after the end has passed too, every reward is claimed or expired
+    // and the authority has withdrawn the revenue, so nothing is left that
+    // deactivating would lock in.
+    pub fn crank_expire_pool(ctx: Context<CrankExpirePool>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        require!(
+            company_pool.can_retire(clock.unix_timestamp),
+            ErrorCode::PoolNotExpired
+        );
+        require!(
+            company_pool.outstanding_liabilities == 0 && company_pool.total_funds == 0,
+            ErrorCode::PoolNotSettled
+        );
+
+        company_pool.active = false;
+
+        emit!(PoolExpiredEvent {
+            company_pool: company_pool.key(),
+            cranker: ctx.accounts.cranker.key(),
+            ends_at: company_pool.ends_at.unwrap_or_default(),
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Closes a retired pool once it is settled, returning both vaults and the
+    // pool account itself to the authority. The vaults' rent reserve comes
+    // back as recorded at creation; anything above it is prize budget no win
+    // used and goes back with it.
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(!company_pool.active, ErrorCode::PoolStillActive);
+        require!(company_pool.is_settled(), ErrorCode::PoolNotSettled);
+        let (rent_returned, funds_returned) = drain_pool_vaults(
+            company_pool,
+            &ctx.accounts.pool_vault,
+            ctx.accounts.prize_vault.as_ref(),
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+        )?;
+
+        emit!(PoolClosedEvent {
+            company_pool: company_pool.key(),
+            authority: ctx.accounts.authority.key(),
+            rent_returned,
+            funds_returned,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // close_pool for a finished pool whose figures should outlive it: they
+    // move into a PoolArchive a fraction of the pool's size before the pool
+    // and its vaults are closed. Its stats, registry and leaderboard
+    // accounts are left as they are.
+    pub fn archive_pool(ctx: Context<ArchivePool>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(!company_pool.active, ErrorCode::PoolStillActive);
+        require!(company_pool.is_settled(), ErrorCode::PoolNotSettled);
+        let (rent_returned, funds_returned) = drain_pool_vaults(
+            company_pool,
+            &ctx.accounts.pool_vault,
+            ctx.accounts.prize_vault.as_ref(),
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+        )?;
+
+        let pool_archive = &mut ctx.accounts.pool_archive;
+        pool_archive.company_pool = company_pool.key();
+        pool_archive.authority = company_pool.authority;
+        pool_archive.name_hash = CompanyPool::name_seed(&company_pool.company_name);
+        pool_archive.total_tickets_sold = company_pool.total_tickets_sold;
+        pool_archive.total_ticket_revenue = company_pool.total_ticket_revenue;
+        pool_archive.total_rewards_paid = company_pool.total_rewards_paid;
+        if let Some(leaderboard) = ctx.accounts.leaderboard.as_ref() {
+            pool_archive.top_winners = leaderboard.entries;
+        }
+        pool_archive.created_at = company_pool.created_at;
+        pool_archive.closed_at = clock.unix_timestamp;
+        pool_archive.bump = ctx.bumps.pool_archive;
+
+        emit!(PoolArchivedEvent {
+            company_pool: company_pool.key(),
+            pool_archive: pool_archive.key(),
+            authority: ctx.accounts.authority.key(),
+            rent_returned,
+            funds_returned,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Closes the pool's current season and opens the next, e.g. for season 2
+    // of a running drop. The closing season's figures go into a
+    // SeasonArchive, and `reset_flags` picks what the new season starts
+    // without. Tickets, funds and the ticket counter carry on regardless.
+    pub fn start_new_season(
+        ctx: Context<StartNewSeason>,
+        reset_flags: SeasonResetFlags,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Older layouts don't store the season; migrate_pool upgrades them
+        require!(
+            company_pool.version >= CompanyPool::VERSION_SEASONS,
+            ErrorCode::UnsupportedAccountVersion
+        );
+        require!(
+            !reset_flags.leaderboard || ctx.accounts.leaderboard.is_some(),
+            ErrorCode::LeaderboardRequired
+        );
+        let ended_season = company_pool.season();
+        let season = ended_season.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+        let pool_stats = &mut ctx.accounts.pool_stats;
+        let season_archive = &mut ctx.accounts.season_archive;
+        season_archive.company_pool = company_pool.key();
+        season_archive.season = ended_season;
+        season_archive.started_at = company_pool.season_started_at();
+        season_archive.ended_at = clock.unix_timestamp;
+        season_archive.tickets_sold = company_pool
+            .total_tickets_sold
+            .checked_sub(company_pool.season_start_tickets_sold)
+            .ok_or(ErrorCode::MathOverflow)?;
+        season_archive.ticket_revenue = company_pool
+            .total_ticket_revenue
+            .checked_sub(company_pool.season_start_revenue)
+            .ok_or(ErrorCode::MathOverflow)?;
+        season_archive.total_spins = pool_stats.total_spins;
+        season_archive.no_win_spins = pool_stats.no_win_spins;
+        season_archive.total_value_won = pool_stats.total_value_won;
+        season_archive.total_rewards_paid = pool_stats.total_rewards_paid;
+        season_archive.biggest_win_value = pool_stats.biggest_win_value;
+        season_archive.biggest_winner = pool_stats.biggest_winner;
+        if let Some(leaderboard) = ctx.accounts.leaderboard.as_mut() {
+            season_archive.top_winners = leaderboard.entries;
+            if reset_flags.leaderboard {
+                leaderboard.entries = [LeaderboardEntry::default(); Leaderboard::SIZE];
+            }
+        }
+        season_archive.reset_flags = reset_flags;
+        season_archive.bump = ctx.bumps.season_archive;
+
+        if reset_flags.stats {
+            pool_stats.reset();
+        }
+        company_pool.start_season(season, reset_flags, clock.unix_timestamp);
+
+        emit!(SeasonStartedEvent {
+            company_pool: company_pool.key(),
+            season,
+            season_archive: season_archive.key(),
+            reset_flags,
+            previous_tickets_sold: season_archive.tickets_sold,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Returns a settled ticket's rent to its owner. The pool only numbers the
+    // event, so tickets outlive a closed or archived pool only until their
+    // owner closes them.
+    pub fn close_ticket(ctx: Context<CloseTicket>) -> Result<()> {
+        let user_ticket = &ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
+
+        require!(user_ticket.is_settled(), ErrorCode::TicketNotSettled);
+
+        emit!(TicketClosedEvent {
+            owner: user_ticket.owner,
+            company_pool: user_ticket.company_pool,
+            ticket_id: user_ticket.ticket_id,
+            rent_returned: user_ticket.to_account_info().lamports(),
+            event_seq: next_event_seq_of(&ctx.accounts.company_pool)?,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Opens a pool under a new name with the items and settings of one the
+    // caller already runs, e.g. for the next season of a drop. The clone gets
+    // its own vaults and starts with no tickets, funds or history. Whitelist
+    // entries, boosts and admin flags stay with the source, and a
+    // server-seeded clone needs its first commit_server_seed before spinning.
+    pub fn clone_pool(
+        ctx: Context<ClonePool>,
+        new_company_name: String,
+        overrides: CloneOverrides,
+    ) -> Result<()> {
+        let source = &ctx.accounts.source_pool;
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        ctx.accounts.global_config.check_pool_creator(
+            ctx.accounts.creator_entry.as_deref(),
+            &ctx.accounts.authority.key(),
+        )?;
+        // Older layouts hold stale bytes in the settings copied below, and
+        // item-account pools keep their items elsewhere
+        require!(
+            source.version == CompanyPool::VERSION && !source.uses_item_accounts(),
+            ErrorCode::PoolNotClonable
+        );
+        require!(new_company_name.len() <= 50, ErrorCode::CompanyNameTooLong);
+
+        company_pool.version = CompanyPool::VERSION;
+        company_pool.pool_version = CompanyPool::POOL_VERSION;
+        company_pool.authority = ctx.accounts.authority.key();
+        company_pool.company_name = new_company_name;
+        company_pool.company_image = source.company_image.clone();
+        company_pool.active = true;
+        company_pool.created_at = clock.unix_timestamp;
+        company_pool.vault_bump = ctx.bumps.pool_vault;
+        company_pool.pool_bump = ctx.bumps.company_pool;
+        company_pool.prize_vault_bump = ctx.bumps.prize_vault;
+        company_pool.copy_settings_from(source)?;
+        // A source created before auto_payout excluded prize-replacing cover
+        require!(
+            company_pool
+                .insurance()
+                .is_none_or(|insurance| insurance.is_valid(company_pool.auto_payout)),
+            ErrorCode::InvalidPoolConfig
+        );
+        company_pool.recalculate_probabilities()?;
+
+        // The EV cap isn't stored, so a cheaper ticket may not make the clone
+        // pay out more per lamport than the copied table does
+        if let Some(ticket_price) = overrides.ticket_price {
+            require!(ticket_price > 0, ErrorCode::InvalidTicketPrice);
+            let copied_price = company_pool.ticket_price;
+            let copied_expected_value = company_pool.expected_value()?;
+            company_pool.ticket_price = ticket_price;
+            company_pool.recalculate_probabilities()?;
+            require!(
+                (company_pool.expected_value()? as u128) * (copied_price as u128)
+                    <= (copied_expected_value as u128) * (ticket_price as u128),
+                ErrorCode::ExpectedValueTooHigh
+            );
+        }
+        // A finished season's end time has to be replaced
+        if overrides.ends_at.is_some() {
+            company_pool.ends_at = overrides.ends_at;
+        }
+        require!(
+            company_pool
+                .ends_at
+                .is_none_or(|ends_at| ends_at > clock.unix_timestamp),
+            ErrorCode::InvalidPoolConfig
+        );
+        let expected_value = company_pool.expected_value()?;
+
+        let pool_stats = &mut ctx.accounts.pool_stats;
+        pool_stats.company_pool = company_pool.key();
+        pool_stats.bump = ctx.bumps.pool_stats;
+
+        let mut winner_registry = ctx.accounts.winner_registry.load_init()?;
+        winner_registry.company_pool = company_pool.key();
+        winner_registry.bump = ctx.bumps.winner_registry;
+        drop(winner_registry);
+
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        leaderboard.company_pool = company_pool.key();
+        leaderboard.bump = ctx.bumps.leaderboard;
+
+        // Creation fee and vault rent as in initialize_company_pool
+        let creation_fee = ctx.accounts.global_config.pool_creation_fee_lamports;
+        if creation_fee > 0 {
+            let treasury = ctx
+                .accounts
+                .treasury
+                .as_ref()
+                .ok_or(ErrorCode::InvalidTreasury)?;
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: treasury.to_account_info(),
+            };
+            let cpi_context =
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            anchor_lang::system_program::transfer(cpi_context, creation_fee)?;
+        }
+
+        let minimum_balance = Rent::get()?.minimum_balance(0);
+        let mut vault_rent_funded = 0u64;
+        for vault in [&ctx.accounts.pool_vault, &ctx.accounts.prize_vault] {
+            let shortfall = minimum_balance.saturating_sub(vault.lamports());
+            if shortfall > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: vault.to_account_info(),
+                };
+                let cpi_context =
+                    CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_context, shortfall)?;
+                vault_rent_funded = vault_rent_funded
+                    .checked_add(shortfall)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            }
+        }
+        company_pool.vault_rent_reserve = minimum_balance;
+
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_pool_created(creation_fee)?;
+        }
+
+        emit!(PoolClonedEvent {
+            source_pool: source.key(),
+            company_pool: company_pool.key(),
+            company_name: company_pool.company_name.clone(),
+            authority: ctx.accounts.authority.key(),
+            ticket_price: company_pool.ticket_price,
+            item_count: company_pool.items.len() as u32,
+            expected_value,
+            vault_rent_funded,
+            creation_fee,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Returns a ticket whose requested draw never landed to Unspun, so its
+    // owner can spin it again. The pool authority may do so at any time,
+    // anyone else once the draw has been pending for
+    // UserTicket::SPIN_PENDING_TIMEOUT_SECONDS.
+    pub fn cancel_stuck_spin(ctx: Context<CancelStuckSpin>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let cranker = ctx.accounts.cranker.key();
+        let clock = Clock::get()?;
+
+        require!(
+            user_ticket.state() == TicketState::SpinPending,
+            ErrorCode::SpinNotPending
+        );
+        require!(
+            cranker == company_pool.authority || user_ticket.is_spin_stuck(clock.unix_timestamp),
+            ErrorCode::SpinNotStuck
+        );
+
+        let requested_at = user_ticket.spun_at;
+        user_ticket.cancel_pending_draw()?;
+
+        emit!(SpinCancelledEvent {
+            company_pool: company_pool.key(),
+            owner: user_ticket.owner,
+            ticket_id: user_ticket.ticket_id,
+            requested_at,
+            cranker,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Takes back a spin found to be unfair, for instance one that exploited
+    // the randomness. Only the program admin may, and only during the pool's
+    // dispute window after the spin. A won item goes back in the draw and the
+    // owner is refunded what the ticket cost, from the vaults it was paid
+    // into. The ticket is then settled with nothing left to claim.
+    pub fn void_spin(ctx: Context<VoidSpin>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
+
+        user_ticket.check_spun()?;
+        require!(
+            company_pool.is_disputable(user_ticket.spun_at, clock.unix_timestamp),
+            ErrorCode::DisputeWindowClosed
+        );
+
+        let mega_jackpot = ctx.accounts.mega_jackpot.as_deref_mut();
+        let voided_item =
+            user_ticket.discard_result(company_pool, mega_jackpot.map(|j| &mut **j))?;
+        user_ticket.mark_claimed()?;
+
+        let rent_reserve = company_pool.rent_reserve()?;
+        let (revenue_refund, prize_refund) = company_pool.take_refund(
+            user_ticket.price_paid,
+            ctx.accounts.pool_vault.lamports(),
+            rent_reserve,
+        )?;
+        require!(
+            prize_refund
+                <= ctx
+                    .accounts
+                    .prize_vault
+                    .lamports()
+                    .saturating_sub(rent_reserve),
+            ErrorCode::InsufficientVaultFunds
+        );
+
+        let company_pool_key = company_pool.key();
+        let vault_seed = company_pool.vault_seed(&company_pool_key);
+        let vault_seeds: &[&[u8]] = &[
+            b"pool_vault",
+            vault_seed.as_slice(),
+            &[company_pool.vault_bump],
+        ];
+        let prize_vault_seeds: &[&[u8]] = &[
+            b"prize_vault",
+            company_pool_key.as_ref(),
+            &[company_pool.prize_vault_bump],
+        ];
+        for (vault, seeds, amount) in [
+            (&ctx.accounts.pool_vault, vault_seeds, revenue_refund),
+            (&ctx.accounts.prize_vault, prize_vault_seeds, prize_refund),
+        ] {
+            if amount > 0 {
+                let signer_seeds = &[seeds];
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: vault.to_account_info(),
+                    to: ctx.accounts.ticket_owner.to_account_info(),
+                };
+                let cpi_context = CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    cpi_accounts,
+                    signer_seeds,
+                );
+                anchor_lang::system_program::transfer(cpi_context, amount)?;
+            }
+        }
+
+        emit!(SpinVoidedEvent {
+            company_pool: company_pool_key,
+            owner: user_ticket.owner,
+            ticket_id: user_ticket.ticket_id,
+            voided_item,
+            refund: user_ticket.price_paid,
+            admin: ctx.accounts.admin.key(),
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Replays a ticket's latest draw from the table the caller says it drew
+    // from. Anyone can ask; it only reads the ticket and numbers the event in
+    // its pool. Works for tickets of either pool layout, since the record is
+    // kept on the ticket.
+    pub fn verify_spin(ctx: Context<VerifySpin>, table: DrawTable) -> Result<()> {
+        let user_ticket = &ctx.accounts.user_ticket;
+        let record = user_ticket
+            .draw_record()
+            .ok_or(ErrorCode::SpinNotReplayable)?;
+        record.verify(&table)?;
+
+        emit!(SpinVerifiedEvent {
+            company_pool: user_ticket.company_pool,
+            ticket_id: user_ticket.ticket_id,
+            random_seed: record.random_seed,
+            table_hash: record.table_hash,
+            drawn_index: record.drawn_index,
+            event_seq: next_event_seq_of(&ctx.accounts.company_pool)?,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Draws a raffle pool's winners once its sales have ended: one ticket per
+    // available item, every ticket equally likely and none winning twice.
+    // Anyone may call it, since who calls can't change the outcome. The seed
+    // is the latest slot hash, which nobody knew while tickets were selling.
+    pub fn draw_raffle(ctx: Context<DrawRaffle>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        require!(
+            company_pool.pool_mode() == PoolMode::Raffle,
+            ErrorCode::NotRafflePool
+        );
+        require!(
+            company_pool.has_ended(clock.unix_timestamp),
+            ErrorCode::RaffleStillOpen
+        );
+
+        let random_seed = {
+            let slot_hashes = ctx.accounts.slot_hashes.try_borrow_data()?;
+            let latest_hash = slot_hashes
+                .get(16..48)
+                .ok_or(ErrorCode::SlotHashesUnavailable)?;
+            hashv(&[
+                latest_hash,
+                company_pool.key().as_ref(),
+                &company_pool.total_tickets_sold.to_le_bytes(),
+            ])
+            .to_bytes()
+        };
+
+        let prize_positions: Vec<usize> = (0..company_pool.items.len())
+            .filter(|&position| company_pool.items[position].available)
+            .collect();
+        let ticket_ids = draw_raffle_ticket_ids(
+            &random_seed,
+            company_pool.total_tickets_sold,
+            prize_positions.len(),
+        );
+
+        // Winners are owed their prizes from the draw on
+        let mut winners = Vec::with_capacity(ticket_ids.len());
+        for (position, &ticket_id) in prize_positions.into_iter().zip(&ticket_ids) {
+            let (won_item, _) = company_pool.award_item(position)?;
+            company_pool.add_liability(won_item.vault_liability())?;
+            winners.push(RaffleWinner {
+                ticket_id,
+                won_item,
+            });
+        }
+
+        let raffle_result = &mut ctx.accounts.raffle_result;
+        raffle_result.company_pool = company_pool.key();
+        raffle_result.random_seed = random_seed;
+        raffle_result.tickets_in_draw = company_pool.total_tickets_sold;
+        raffle_result.drawn_at = clock.unix_timestamp;
+        raffle_result.drawn_slot = clock.slot;
+        raffle_result.winners = winners;
+        raffle_result.bump = ctx.bumps.raffle_result;
+
+        emit!(RaffleDrawnEvent {
+            company_pool: company_pool.key(),
+            drawn_by: ctx.accounts.payer.key(),
+            winning_ticket_ids: ticket_ids,
+            tickets_in_draw: company_pool.total_tickets_sold,
+            random_seed,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Mints a 1/1 NFT of the won item to the winner as an on-chain proof of the
+    // prize. This is independent of claiming and can happen once per ticket.
+    pub fn mint_reward_nft(ctx: Context<MintRewardNft>) -> Result<()> {
+        let company_pool = &ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        let won_item = ctx
+            .accounts
+            .user_ticket
+            .won_item
+            .clone()
+            .ok_or(ErrorCode::NoRewardToClaim)?;
+
+        // Item-account pools keep the image on the won item's account
+        let item = company_pool
+            .item_position(won_item.item_id)
+            .map(|position| &company_pool.items[position])
+            .ok_or(ErrorCode::InvalidItemIndex)?;
+        let uri = if company_pool.uses_item_accounts() {
+            ctx.accounts
+                .pool_item
+                .as_ref()
+                .filter(|pool_item| pool_item.belongs_to(&company_pool.key(), won_item.item_id))
+                .ok_or(ErrorCode::ItemAccountMismatch)?
+                .image
+                .clone()
+        } else {
+            item.image.clone()
+        };
+
+        // The vault PDA signs as mint and initial update authority
+        let vault_seed = company_pool.vault_seed(&company_pool.key());
+        let seeds = &[
+            b"pool_vault",
+            vault_seed.as_slice(),
+            &[company_pool.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    to: ctx.accounts.winner_token_account.to_account_info(),
+                    authority: ctx.accounts.pool_vault.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+
+        create_metadata_accounts_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                CreateMetadataAccountsV3 {
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    mint_authority: ctx.accounts.pool_vault.to_account_info(),
+                    payer: ctx.accounts.winner.to_account_info(),
+                    update_authority: ctx.accounts.pool_vault.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            DataV2 {
+                name: nft_name(&item.name),
+                symbol: String::new(),
+                uri,
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+            true,
+            true,
+            None,
+        )?;
+
+        // Max supply 0 makes this a true 1/1 and moves mint authority to the edition
+        create_master_edition_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                CreateMasterEditionV3 {
+                    edition: ctx.accounts.master_edition.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    update_authority: ctx.accounts.pool_vault.to_account_info(),
+                    mint_authority: ctx.accounts.pool_vault.to_account_info(),
+                    payer: ctx.accounts.winner.to_account_info(),
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            Some(0),
+        )?;
+
+        // Hand the metadata over to the pool authority
+        update_metadata_accounts_v2(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                UpdateMetadataAccountsV2 {
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    update_authority: ctx.accounts.pool_vault.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            Some(company_pool.authority),
+            None,
+            None,
+            None,
+        )?;
+
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        user_ticket.reward_nft_mint = Some(ctx.accounts.reward_mint.key());
+
+        emit!(RewardNftMintedEvent {
+            winner: ctx.accounts.winner.key(),
+            ticket_id: user_ticket.ticket_id,
+            mint: ctx.accounts.reward_mint.key(),
+            item_name: item.name.clone(),
+            event_seq: ctx.accounts.company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Lets the winner of a physical prize attach a hash of their shipping details
+    pub fn attach_shipping_info(
+        ctx: Context<AttachShippingInfo>,
+        shipping_info_hash: [u8; 32],
+    ) -> Result<()> {
+        let user_ticket = &mut ctx.accounts.user_ticket;
+
+        require!(
+            user_ticket.fulfillment_pending,
+            ErrorCode::FulfillmentNotPending
+        );
+
+        user_ticket.shipping_info_hash = Some(shipping_info_hash);
+
+        Ok(())
+    }
+
+    // Authority confirms a physical prize has been shipped, finalizing the claim
+    pub fn mark_fulfilled(ctx: Context<MarkFulfilled>) -> Result<()> {
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            user_ticket.fulfillment_pending,
+            ErrorCode::FulfillmentNotPending
+        );
+        let shipping_info_hash = user_ticket
+            .shipping_info_hash
+            .ok_or(ErrorCode::ShippingInfoMissing)?;
+
+        user_ticket.fulfillment_pending = false;
+        user_ticket.mark_claimed()?;
+
+        emit!(RewardFulfilledEvent {
+            winner: user_ticket.owner,
+            ticket_id: user_ticket.ticket_id,
+            shipping_info_hash,
+            event_seq: ctx.accounts.company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Takes everything the vault can spare. Pools with a beneficiary send it
+    // there rather than to the authority, and since the destination is fixed
+    // at init the withdrawal timelock doesn't hold them up.
+    pub fn sweep_vault(ctx: Context<SweepVault>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_not_frozen()?;
+        require!(
+            company_pool.withdrawal_timelock_seconds == 0 || company_pool.beneficiary().is_some(),
+            ErrorCode::WithdrawalTimelocked
+        );
+        let recipient = match company_pool.beneficiary() {
+            Some(_) => ctx
+                .accounts
+                .beneficiary
+                .as_ref()
+                .ok_or(ErrorCode::InvalidBeneficiary)?
+                .to_account_info(),
+            None => ctx.accounts.authority.to_account_info(),
+        };
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::UnauthorizedWithdrawal
+        );
+        company_pool.check_co_authority(
+            ctx.accounts
+                .co_authority
+                .as_ref()
+                .map(|signer| signer.key()),
+        )?;
+
+        // Everything above the reserved balance, up to whatever is left of
+        // today's withdrawal allowance
+        let gross_balance = ctx.accounts.pool_vault.lamports();
+        let reserved = company_pool.reserved_balance(company_pool.rent_reserve()?);
+        let swept = gross_balance
+            .saturating_sub(reserved)
+            .min(company_pool.daily_withdrawal_remaining(clock.unix_timestamp));
+        require!(swept > 0, ErrorCode::NoFundsAvailable);
+        company_pool.record_withdrawal(swept, clock.unix_timestamp)?;
+
+        let vault_seed = company_pool.vault_seed(&company_pool.key());
+        let seeds = &[
+            b"pool_vault",
+            vault_seed.as_slice(),
+            &[company_pool.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.pool_vault.to_account_info(),
+            to: recipient.clone(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, swept)?;
+
+        // Direct deposits can push the sweep past the tracked sales
+        company_pool.total_funds = company_pool.total_funds.saturating_sub(swept);
+
+        emit!(VaultSweptEvent {
+            authority: ctx.accounts.authority.key(),
+            recipient: recipient.key(),
+            gross_balance,
+            reserved,
+            swept,
+            remaining_funds: company_pool.total_funds,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn request_withdrawal(ctx: Context<RequestWithdrawal>, amount: u64) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_not_frozen()?;
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::UnauthorizedWithdrawal
+        );
+        require!(
+            company_pool.beneficiary().is_none(),
+            ErrorCode::BeneficiaryPool
+        );
+        require!(
+            company_pool.pending_withdrawal.is_none(),
+            ErrorCode::WithdrawalAlreadyPending
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            amount <= company_pool.total_funds,
+            ErrorCode::InsufficientFunds
+        );
+
+        let withdrawable_balance = ctx
+            .accounts
+            .pool_vault
+            .lamports()
+            .saturating_sub(company_pool.reserved_balance(company_pool.rent_reserve()?));
+        require!(
+            amount <= withdrawable_balance,
+            ErrorCode::InsufficientVaultFunds
+        );
+
+        let withdrawable_after = clock
+            .unix_timestamp
+            .checked_add(company_pool.withdrawal_timelock_seconds as i64)
+            .ok_or(ErrorCode::MathOverflow)?;
+        company_pool.pending_withdrawal = Some(PendingWithdrawal {
+            amount,
+            withdrawable_after,
+        });
+
+        emit!(WithdrawalRequestedEvent {
+            authority: ctx.accounts.authority.key(),
+            amount,
+            withdrawable_after,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(company_pool.active, ErrorCode::PoolInactive);
+        company_pool.check_not_frozen()?;
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::UnauthorizedWithdrawal
+        );
+        company_pool.check_co_authority(
+            ctx.accounts
+                .co_authority
+                .as_ref()
+                .map(|signer| signer.key()),
+        )?;
+
+        let pending = company_pool
+            .pending_withdrawal
+            .ok_or(ErrorCode::NoPendingWithdrawal)?;
+        require!(
+            clock.unix_timestamp >= pending.withdrawable_after,
+            ErrorCode::WithdrawalNotReady
+        );
+
+        // The request reserved its own amount, so everything else still has to fit
+        let rent_reserve = company_pool.rent_reserve()?;
+        require!(
+            ctx.accounts.pool_vault.lamports() >= company_pool.reserved_balance(rent_reserve),
+            ErrorCode::InsufficientVaultFunds
+        );
+        company_pool.record_withdrawal(pending.amount, clock.unix_timestamp)?;
+
+        let vault_seed = company_pool.vault_seed(&company_pool.key());
+        let seeds = &[
+            b"pool_vault",
+            vault_seed.as_slice(),
+            &[company_pool.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.pool_vault.to_account_info(),
+            to: ctx.accounts.authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, pending.amount)?;
+
+        // Claims since the request may already have drawn total_funds down
+        company_pool.total_funds = company_pool.total_funds.saturating_sub(pending.amount);
+        company_pool.pending_withdrawal = None;
+
+        emit!(WithdrawalExecutedEvent {
+            authority: ctx.accounts.authority.key(),
+            amount: pending.amount,
+            remaining_funds: company_pool.total_funds,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::UnauthorizedWithdrawal
+        );
+
+        let pending = company_pool
+            .pending_withdrawal
+            .take()
+            .ok_or(ErrorCode::NoPendingWithdrawal)?;
+
+        emit!(WithdrawalCancelledEvent {
+            authority: ctx.accounts.authority.key(),
+            amount: pending.amount,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_co_authority(
+        ctx: Context<SetCoAuthority>,
+        co_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::Unauthorized
+        );
+        // Rotating or removing an existing co-authority needs its signature too
+        company_pool.check_co_authority(
+            ctx.accounts
+                .co_authority
+                .as_ref()
+                .map(|signer| signer.key()),
+        )?;
+        require!(
+            co_authority != Some(company_pool.authority),
+            ErrorCode::InvalidPoolConfig
+        );
+
+        let old_co_authority = company_pool.co_authority;
+        company_pool.co_authority = co_authority;
+
+        emit!(CoAuthorityUpdatedEvent {
+            authority: ctx.accounts.authority.key(),
+            old_co_authority,
+            new_co_authority: co_authority,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Custodial pools let the operator's backend spin for users who never
+    // sign themselves. Claims still need the owner, so the operator never
+    // gets hold of rewards. None turns it off.
+    pub fn set_spin_operator(
+        ctx: Context<SetSpinOperator>,
+        spin_operator: Option<Pubkey>,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        let old_operator = company_pool.spin_operator();
+        company_pool.spin_operator = spin_operator;
+
+        emit!(SpinOperatorUpdatedEvent {
+            company_pool: company_pool.key(),
+            old_operator,
+            new_operator: spin_operator,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Discounts buy_ticket by `discount_bps` every day from start_hour_utc up
+    // to end_hour_utc, e.g. 22 to 2 for the four hours around midnight. The
+    // discount comes out of both vault shares; probabilities keep the full
+    // price. A discount of 0 ends the happy hour.
+    pub fn set_happy_hour(
+        ctx: Context<SetHappyHour>,
+        start_hour_utc: u8,
+        end_hour_utc: u8,
+        discount_bps: u16,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        // Older layouts don't store the window; migrate_pool upgrades them
+        require!(
+            company_pool.version >= CompanyPool::VERSION_HAPPY_HOUR,
+            ErrorCode::UnsupportedAccountVersion
+        );
+        let happy_hour = HappyHour {
+            start_hour_utc,
+            end_hour_utc,
+            discount_bps,
+        };
+        require!(
+            discount_bps == 0 || happy_hour.is_valid(),
+            ErrorCode::InvalidHappyHour
+        );
+        company_pool.happy_hour = if discount_bps == 0 {
+            HappyHour::default()
+        } else {
+            happy_hour
+        };
+
+        emit!(HappyHourSetEvent {
+            company_pool: company_pool.key(),
+            start_hour_utc,
+            end_hour_utc,
+            discount_bps,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Pools created before the vault split pay prizes out of the revenue vault.
+    // This opens their prize vault and moves what is owed to winners across.
+    pub fn migrate_prize_vault(
+        ctx: Context<MigratePrizeVault>,
+        prize_allocation_bps: u16,
+    ) -> Result<()> {
+        let company_pool = &mut ctx.accounts.company_pool;
+        let clock = Clock::get()?;
+
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            !company_pool.has_prize_vault(),
+            ErrorCode::PrizeVaultAlreadyInitialized
+        );
+        require!(
+            prize_allocation_bps <= 10000
+                && company_pool.jackpot_contribution_bps <= prize_allocation_bps,
+            ErrorCode::InvalidPoolConfig
+        );
+
+        // Unclaimed rewards and the jackpot pot follow the prizes
+        let rent_reserve = company_pool.rent_reserve()?;
+        let owed = company_pool
+            .outstanding_liabilities
+            .checked_add(company_pool.jackpot_accumulated)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            owed <= ctx
+                .accounts
+                .pool_vault
+                .lamports()
+                .saturating_sub(rent_reserve),
+            ErrorCode::InsufficientVaultFunds
+        );
+
+        let rent_funded = rent_reserve.saturating_sub(ctx.accounts.prize_vault.lamports());
+        if rent_funded > 0 {
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: ctx.accounts.prize_vault.to_account_info(),
+            };
+            let cpi_context =
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            anchor_lang::system_program::transfer(cpi_context, rent_funded)?;
+        }
+
+        if owed > 0 {
+            let vault_seed = company_pool.vault_seed(&company_pool.key());
+            let seeds = &[
+                b"pool_vault",
+                vault_seed.as_slice(),
+                &[company_pool.vault_bump],
+            ];
+            let signer_seeds = &[&seeds[..]];
+
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.pool_vault.to_account_info(),
+                to: ctx.accounts.prize_vault.to_account_info(),
+            };
+            let cpi_context = CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            anchor_lang::system_program::transfer(cpi_context, owed)?;
+        }
+
+        company_pool.prize_allocation_bps = prize_allocation_bps;
+        company_pool.prize_vault_bump = ctx.bumps.prize_vault;
+
+        emit!(PrizeVaultMigratedEvent {
+            company_pool: company_pool.key(),
+            moved: owed,
+            prize_allocation_bps,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Funds the revenue vault through the program so the deposit is tracked.
+    // Anyone can deposit, e.g. as a sponsorship; the lamports become
+    // withdrawable by the authority like ticket revenue.
+    pub fn deposit_to_vault(ctx: Context<DepositToVault>, amount: u64) -> Result<()> {
+        let clock = Clock::get()?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.depositor.to_account_info(),
+            to: ctx.accounts.pool_vault.to_account_info(),
+        };
+        let cpi_context =
+            CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        let company_pool = &mut ctx.accounts.company_pool;
+        company_pool.total_funds = company_pool
+            .total_funds
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        company_pool.total_authority_deposits = company_pool
+            .total_authority_deposits
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(DepositEvent {
+            depositor: ctx.accounts.depositor.key(),
+            amount,
+            total_funds: company_pool.total_funds,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Tops up the prize vault from outside ticket sales
+    pub fn fund_prize_vault(ctx: Context<FundPrizeVault>, amount: u64) -> Result<()> {
+        let clock = Clock::get()?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.funder.to_account_info(),
+            to: ctx.accounts.prize_vault.to_account_info(),
+        };
+        let cpi_context =
+            CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        let company_pool = &mut ctx.accounts.company_pool;
+        company_pool.total_authority_deposits = company_pool
+            .total_authority_deposits
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(PrizeVaultFundedEvent {
+            funder: ctx.accounts.funder.key(),
+            amount,
+            prize_vault_balance: ctx.accounts.prize_vault.lamports(),
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Lets a temporary session key spin the caller's tickets in this pool
+    // until expires_at. Claims and trade-ins still need the owner.
+    pub fn delegate_spinner(
+        ctx: Context<DelegateSpinner>,
+        delegate: Pubkey,
+        expires_at: i64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            expires_at > clock.unix_timestamp && delegate != ctx.accounts.owner.key(),
+            ErrorCode::InvalidSpinDelegate
+        );
+
+        let user_pool_state = &mut ctx.accounts.user_pool_state;
+        user_pool_state.bump = ctx.bumps.user_pool_state;
+        user_pool_state.version = UserPoolState::VERSION;
+        user_pool_state.spin_delegate = Some(delegate);
+        user_pool_state.spin_delegate_expires_at = expires_at;
+
+        emit!(SpinDelegateUpdatedEvent {
+            owner: ctx.accounts.owner.key(),
+            company_pool: ctx.accounts.company_pool.key(),
+            delegate: Some(delegate),
+            expires_at,
+            event_seq: ctx.accounts.company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn revoke_delegate(ctx: Context<RevokeDelegate>) -> Result<()> {
+        let user_pool_state = &mut ctx.accounts.user_pool_state;
+        user_pool_state.spin_delegate = None;
+        user_pool_state.spin_delegate_expires_at = 0;
+
+        emit!(SpinDelegateUpdatedEvent {
+            owner: ctx.accounts.owner.key(),
+            company_pool: ctx.accounts.company_pool.key(),
+            delegate: None,
+            expires_at: 0,
+            event_seq: ctx.accounts.company_pool.next_event_seq(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Moves a pool written before the leading version byte to the current
+    // layout. Anyone can send it; the caller pays for the extra byte.
+    pub fn migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+        let pool_info = ctx.accounts.company_pool.to_account_info();
+        let mut company_pool = CompanyPool::read_any_version(&pool_info.try_borrow_data()?)?;
+        require!(
+            company_pool.version < CompanyPool::VERSION,
+            ErrorCode::AccountAlreadyMigrated
+        );
+
+        let from_version = company_pool.version;
+        company_pool.version = CompanyPool::VERSION;
+        let event_seq = company_pool.next_event_seq();
+        let rent_refunded = migration::rewrite_account(
+            &pool_info,
+            &company_pool,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigratedEvent {
+            account: pool_info.key(),
+            from_version,
+            to_version: CompanyPool::VERSION,
+            rent_refunded,
+            event_seq,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Grows a version 1 zero-copy pool to the current layout. Anyone can
+    // send it; the caller pays for the extra bytes.
+    pub fn migrate_pool_v2(ctx: Context<MigratePool>) -> Result<()> {
+        let pool_info = ctx.accounts.company_pool.to_account_info();
+        let (from_version, rent_refunded) = CompanyPoolV2::grow_to_current_layout(
+            &pool_info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigratedEvent {
+            account: pool_info.key(),
+            from_version,
+            to_version: CompanyPoolV2::VERSION,
+            rent_refunded,
+            event_seq: next_event_seq_of(&pool_info)?,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+    // Moves a ticket from an older layout to the current one. Its owner
+    // covers any rent difference, which for version 0 tickets is a refund.
+    pub fn migrate_ticket(ctx: Context<MigrateTicket>) -> Result<()> {
+        let ticket_info = ctx.accounts.user_ticket.to_account_info();
+        let mut ticket = UserTicket::read_any_version(&ticket_info.try_borrow_data()?)?;
+        require!(
+            ticket.version < UserTicket::VERSION,
+            ErrorCode::AccountAlreadyMigrated
+        );
+        require_keys_eq!(
+            ticket.owner,
+            ctx.accounts.owner.key(),
+            ErrorCode::NotTicketOwner
+        );
+        require_keys_eq!(
+            ticket.company_pool,
+            ctx.accounts.company_pool.key(),
+            ErrorCode::InvalidTicketPool
+        );
+
+        let from_version = ticket.version;
+        ticket.version = UserTicket::VERSION;
+        let rent_refunded = migration::rewrite_account(
+            &ticket_info,
+            &ticket,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigratedEvent {
+            account: ticket_info.key(),
+            from_version,
+            to_version: UserTicket::VERSION,
+            rent_refunded,
+            event_seq: next_event_seq_of(&ctx.accounts.company_pool)?,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Moves a user's pool state written before its version byte to the
+    // current layout. Every instruction that reads the state needs this done
+    // first. Anyone can send it; the caller pays for the extra byte. `user`
+    // is the state's owner, to tie it to its pool.
+    pub fn migrate_user_pool_state(
+        ctx: Context<MigrateUserPoolState>,
+        _user: Pubkey,
+    ) -> Result<()> {
+        let state_info = ctx.accounts.user_pool_state.to_account_info();
+        let mut user_pool_state = UserPoolState::read_any_version(&state_info.try_borrow_data()?)?;
+        require!(
+            user_pool_state.version < UserPoolState::VERSION,
+            ErrorCode::AccountAlreadyMigrated
+        );
+
+        let from_version = user_pool_state.version;
+        user_pool_state.version = UserPoolState::VERSION;
+        let rent_refunded = migration::rewrite_account(
+            &state_info,
+            &user_pool_state,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigratedEvent {
+            account: state_info.key(),
+            from_version,
+            to_version: UserPoolState::VERSION,
+            rent_refunded,
+            event_seq: next_event_seq_of(&ctx.accounts.company_pool)?,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Creates a zero-copy pool. Its items are fixed here: SOL prizes without
+    // a jackpot, and no whitelist, gating, limits or bundles.
+    pub fn initialize_company_pool_v2(
+        ctx: Context<InitializeCompanyPoolV2>,
+        ticket_price: u64,
+        company_name: String,
+        company_image: String,
+        items: Vec<PoolItemInput>,
+        config: PoolV2Config,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        ctx.accounts.global_config.check_pool_creator(
+            ctx.accounts.creator_entry.as_deref(),
+            &ctx.accounts.authority.key(),
+        )?;
+
+        // Validate inputs
+        require!(ticket_price > 0, ErrorCode::InvalidTicketPrice);
+        require!(!items.is_empty(), ErrorCode::NoItemsProvided);
+        require!(
+            items.len() <= CompanyPoolV2::MAX_ITEMS,
+            ErrorCode::TooManyItems
+        );
+        require!(company_name.len() <= 50, ErrorCode::CompanyNameTooLong);
+        require!(company_image.len() <= 200, ErrorCode::CompanyImageTooLong);
+        require!(
+            config.prize_allocation_bps <= 10000
+                && config.no_win_bps < 10000
+                && config.weighting.is_valid(),
+            ErrorCode::InvalidPoolConfig
+        );
+        for (i, item) in items.iter().enumerate() {
+            item.validate()?;
+            require!(
+                items[..i].iter().all(|other| other.name != item.name),
+                ErrorCode::DuplicateItemName
+            );
+            require!(
+                item.item_kind == ItemKind::SolPayout
+                    && !item.jackpot
+                    && item.sponsor.is_none()
+                    && item.reveal_hash.is_none(),
+                ErrorCode::InvalidPoolConfig
+            );
+        }
+
+        let manual_weights: Vec<u32> = items.iter().map(|item| item.manual_weight).collect();
+        let probabilities = calculate_item_probabilities_with_weighting(
+            items.iter().map(|item| item.price),
+            ticket_price,
+            config.weighting,
+            &manual_weights,
+            ProbabilityBounds::default(),
+        )?;
+        check_all_winnable(probabilities.iter().copied().enumerate())?;
+
+        let mut company_pool = ctx.accounts.company_pool.load_init()?;
+        company_pool.authority = ctx.accounts.authority.key();
+        company_pool.ticket_price = ticket_price;
+        company_pool.created_at = clock.unix_timestamp;
+        company_pool.prize_allocation_bps = config.prize_allocation_bps;
+        company_pool.no_win_bps = config.no_win_bps;
+        company_pool.version = CompanyPoolV2::VERSION;
+        company_pool.active = 1;
+        company_pool.allow_cpi_purchases = config.allow_cpi_purchases as u8;
+        company_pool.pool_bump = ctx.bumps.company_pool;
+        company_pool.vault_bump = ctx.bumps.pool_vault;
+        company_pool.prize_vault_bump = ctx.bumps.prize_vault;
+        company_pool.company_name = fixed_text(&company_name);
+        company_pool.company_image = fixed_text(&company_image);
+        for (slot, (item, &probability)) in company_pool
+            .items
+            .iter_mut()
+            .zip(items.iter().zip(&probabilities))
+        {
+            slot.price = item.price;
+            slot.probability = probability;
+            slot.name = fixed_text(&item.name);
+            slot.image = fixed_text(&item.image);
+        }
+        company_pool.item_count = items.len() as u32;
+
+        // Same expected value guard as initialize_company_pool
+        let expected_value = company_pool.expected_value()?;
+        if !config.allow_negative_ev {
+            require!(
+                (expected_value as u128) * 10000
+                    <= (ticket_price as u128) * (config.max_ev_bps as u128),
+                ErrorCode::ExpectedValueTooHigh
+            );
+        }
+
+        // Protocol fee for opening a pool, as in initialize_company_pool
+        let creation_fee = ctx.accounts.global_config.pool_creation_fee_lamports;
+        if creation_fee > 0 {
+            let treasury = ctx
+                .accounts
+                .treasury
+                .as_ref()
+                .ok_or(ErrorCode::InvalidTreasury)?;
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: treasury.to_account_info(),
+            };
+            let cpi_context =
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            anchor_lang::system_program::transfer(cpi_context, creation_fee)?;
+        }
+
+        // Fund both vault PDAs up to the rent-exempt minimum
+        let minimum_balance = Rent::get()?.minimum_balance(0);
+        let mut vault_rent_funded = 0u64;
+        for vault in [&ctx.accounts.pool_vault, &ctx.accounts.prize_vault] {
+            let shortfall = minimum_balance.saturating_sub(vault.lamports());
+            if shortfall > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: vault.to_account_info(),
+                };
+                let cpi_context =
+                    CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_context, shortfall)?;
+                vault_rent_funded = vault_rent_funded
+                    .checked_add(shortfall)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            }
+        }
+        company_pool.vault_rent_reserve = minimum_balance;
+
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_pool_created(creation_fee)?;
+        }
+
+        let total_value = items
+            .iter()
+            .try_fold(0u64, |total, item| total.checked_add(item.price))
+            .ok_or(ErrorCode::MathOverflow)?;
+        emit!(PoolInitializedEvent {
+            company_name,
+            ticket_price,
+            item_count: items.len() as u32,
+            authority: ctx.accounts.authority.key(),
+            total_probability_check: probabilities.iter().sum(),
+            vault_rent_funded,
+            expected_value,
+            creation_fee,
+            beneficiary: None,
+            authority_can_play: true,
+            probabilities,
+            total_value,
+            weighting: config.weighting,
+            event_seq: company_pool.next_event_seq(),
+        });
+
+        Ok(())
+    }
+
+    // buy_ticket for zero-copy pools. Only the ticket and revenue counters
+    // are written; the item table is never read. Blocked users are refused
+    // as in buy_ticket, but the whitelist, token gate and per-slot and
+    // per-user limits are not checked: V2 pools are created without them
+    // and their layout has nowhere to keep them.
+    pub fn buy_ticket_v2(ctx: Context<BuyTicketV2>) -> Result<()> {
+        let clock = Clock::get()?;
+        let company_pool_key = ctx.accounts.company_pool.key();
+        let mut company_pool = ctx.accounts.company_pool.load_mut()?;
+        require!(company_pool.is_active(), ErrorCode::PoolInactive);
+        company_pool.check_not_frozen()?;
+        company_pool.check_purchase_caller()?;
+        BlockedUser::check_not_blocked(&ctx.accounts.blocked_user)?;
+
+        let ticket_price = company_pool.ticket_price;
+        let prize_share = CompanyPool::bps_share(ticket_price, company_pool.prize_allocation_bps)?;
+        let revenue_share = ticket_price - prize_share;
+        for (vault, amount) in [
+            (ctx.accounts.pool_vault.to_account_info(), revenue_share),
+            (ctx.accounts.prize_vault.to_account_info(), prize_share),
+        ] {
+            if amount > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.buyer.to_account_info(),
+                    to: vault,
+                };
+                let cpi_context =
+                    CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_context, amount)?;
+            }
+        }
+
+        let ticket_id = company_pool.total_tickets_sold;
+        ctx.accounts.user_ticket.fill(
+            ctx.accounts.buyer.key(),
+            company_pool_key,
+            ticket_id,
+            ticket_price,
+            &clock,
+        );
+
+        company_pool.total_tickets_sold =
+            ticket_id.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_tickets_sold(1)?;
+        }
+        company_pool.total_funds = company_pool
+            .total_funds
+            .checked_add(revenue_share)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(TicketPurchasedEvent {
+            buyer: ctx.accounts.buyer.key(),
+            ticket_price,
+            source: TicketSource::Purchase,
+            total_tickets_sold: company_pool.total_tickets_sold,
+            ticket_id,
+            gate_passed: false,
+            jackpot_accumulated: 0,
+            prize_share,
+            discount: 0,
+            donation: 0,
+            insurance_premium: 0,
+            pool_flags: company_pool.pool_flags,
+            external_id: None,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // record_spin_result for zero-copy pools. Only the ticket owner spins,
+    // and wins are booked for claim_reward_v2.
+    pub fn record_spin_result_v2(ctx: Context<RecordSpinResultV2>) -> Result<()> {
+        let clock = Clock::get()?;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        let spinner = ctx.accounts.spinner.key();
+        require!(user_ticket.owner == spinner, ErrorCode::NotTicketOwner);
+
+        // Spins must be top-level instructions, as in record_spin_result
+        let current_instruction = get_instruction_relative(0, &ctx.accounts.instructions)?;
+        require_keys_eq!(
+            current_instruction.program_id,
+            crate::ID,
+            ErrorCode::CpiNotAllowed
+        );
+
+        let mut company_pool = ctx.accounts.company_pool.load_mut()?;
+        require!(company_pool.is_active(), ErrorCode::PoolInactive);
+
+        user_ticket.check_spin_slot(clock.slot)?;
+
+        let random_seed = generate_random_seed(
+            clock.unix_timestamp,
+            clock.slot,
+            &spinner,
+            company_pool.total_tickets_sold,
+            ctx.accounts.pool_vault.lamports(),
+            user_ticket.ticket_id,
+        );
+
+        let drawn = company_pool.draw(random_seed)?;
+        user_ticket.record_draw(clock.unix_timestamp, clock.slot)?;
+        user_ticket.draw_record = Some(DrawRecord::new(
+            random_seed,
+            &company_pool.draw_table(),
+            drawn,
+        ));
+
+        let Some(index) = drawn else {
+            emit!(SpinResultV2Event {
+                spinner,
+                outcome: SpinOutcome::Nothing,
+                item_id: None,
+                item_value: 0,
+                win_probability: company_pool.no_win_bps as u32,
+                random_seed,
+                ticket_id: user_ticket.ticket_id,
+                pool_flags: company_pool.pool_flags,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+            return Ok(());
+        };
+
+        let slot = &mut company_pool.items[index];
+        slot.times_won = slot
+            .times_won
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let (price, probability) = (slot.price, slot.probability);
+        user_ticket.serial = slot.times_won;
+
+        user_ticket.won_item = Some(WonItem {
+            item_id: index as u32,
+            price,
+            item_kind: ItemKind::SolPayout,
+        });
+        company_pool.outstanding_liabilities = company_pool
+            .outstanding_liabilities
+            .checked_add(price)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(SpinResultV2Event {
+            spinner,
+            outcome: SpinOutcome::Item,
+            item_id: Some(index as u32),
+            item_value: price,
+            win_probability: probability,
+            random_seed,
+            ticket_id: user_ticket.ticket_id,
+            pool_flags: company_pool.pool_flags,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Pays a zero-copy pool's win from its prize vault. Like claim_reward,
+    // this ignores the global pause.
+    pub fn claim_reward_v2(ctx: Context<ClaimRewardV2>) -> Result<()> {
+        let clock = Clock::get()?;
+        let user_ticket = &mut ctx.accounts.user_ticket;
+        require!(
+            user_ticket.owner == ctx.accounts.spinner.key(),
+            ErrorCode::NotTicketOwner
+        );
+        user_ticket.check_spun()?;
+        user_ticket.check_draw_settled(clock.slot)?;
+        let won_item = user_ticket
+            .won_item
+            .clone()
+            .ok_or(ErrorCode::NoRewardToClaim)?;
+        let reward_amount = won_item.price;
+
+        let rent_reserve = ctx.accounts.company_pool.load()?.vault_rent_reserve;
+        require!(
+            reward_amount
+                <= ctx
+                    .accounts
+                    .prize_vault
+                    .lamports()
+                    .saturating_sub(rent_reserve),
+            ErrorCode::InsufficientVaultFunds
+        );
+
+        let company_pool_key = ctx.accounts.company_pool.key();
+        let mut company_pool = ctx.accounts.company_pool.load_mut()?;
+        let seeds = &[
+            b"prize_vault",
+            company_pool_key.as_ref(),
+            &[company_pool.prize_vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.prize_vault.to_account_info(),
+            to: ctx.accounts.spinner.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, reward_amount)?;
+
+        user_ticket.mark_claimed()?;
+        company_pool.outstanding_liabilities = company_pool
+            .outstanding_liabilities
+            .saturating_sub(reward_amount);
+        company_pool.total_rewards_paid = company_pool
+            .total_rewards_paid
+            .checked_add(reward_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if let Some(global_stats) = ctx.accounts.global_stats.as_mut() {
+            global_stats.record_reward_paid(reward_amount)?;
+        }
+
+        let item_name = company_pool
+            .items()
+            .get(won_item.item_id as usize)
+            .map(|slot| text_from_fixed(&slot.name))
+            .unwrap_or_default();
+        emit!(RewardClaimedEvent {
+            winner: ctx.accounts.spinner.key(),
+            ticket_id: user_ticket.ticket_id,
+            won_item,
+            item_name,
+            reward_amount,
+            external_id: user_ticket.external_id,
+            leaderboard_rank: None,
+            serial: user_ticket.serial(),
+            wrapped: false,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Withdraws ticket revenue from a zero-copy pool's vault. Prizes are
+    // held in the prize vault, so only the rent reserve stays behind.
+    pub fn withdraw_funds_from_vault_v2(
+        ctx: Context<WithdrawFundsFromVaultV2>,
+        amount_to_withdraw: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let company_pool_key = ctx.accounts.company_pool.key();
+        let mut company_pool = ctx.accounts.company_pool.load_mut()?;
+        require!(
+            company_pool.authority == ctx.accounts.authority.key(),
+            ErrorCode::UnauthorizedWithdrawal
+        );
+        company_pool.check_not_frozen()?;
+        require!(amount_to_withdraw > 0, ErrorCode::InvalidAmount);
+        require!(
+            amount_to_withdraw <= company_pool.total_funds,
+            ErrorCode::InsufficientFunds
+        );
+        require!(
+            amount_to_withdraw
+                <= ctx
+                    .accounts
+                    .pool_vault
+                    .lamports()
+                    .saturating_sub(company_pool.vault_rent_reserve),
+            ErrorCode::InsufficientVaultFunds
+        );
+
+        let seeds = &[
+            b"pool_vault",
+            company_pool_key.as_ref(),
+            &[company_pool.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.pool_vault.to_account_info(),
+            to: ctx.accounts.authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount_to_withdraw)?;
+
+        company_pool.total_funds -= amount_to_withdraw;
+        company_pool.total_withdrawn = company_pool
+            .total_withdrawn
+            .checked_add(amount_to_withdraw)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(FundsWithdrawnEvent {
+            authority: ctx.accounts.authority.key(),
+            amount_withdrawn: amount_to_withdraw,
+            remaining_funds: company_pool.total_funds,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // admin_set_pool_flag for zero-copy pools
+    pub fn admin_set_pool_flag_v2(
+        ctx: Context<AdminSetPoolFlagV2>,
+        flag: PoolFlag,
+        enabled: bool,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let mut company_pool = ctx.accounts.company_pool.load_mut()?;
+        if enabled {
+            company_pool.pool_flags |= flag.bit();
+        } else {
+            company_pool.pool_flags &= !flag.bit();
+        }
+
+        emit!(PoolFlagSetEvent {
+            company_pool: ctx.accounts.company_pool.key(),
+            admin: ctx.accounts.admin.key(),
+            flag,
+            enabled,
+            pool_flags: company_pool.pool_flags,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_user_tickets(_ctx: Context<GetUserTickets>) -> Result<()> {
+        // This function can be used to query user tickets
+        // Implementation depends on your specific needs
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct ClaimReward<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.active @ ErrorCode::PoolInactive,
+        constraint = !company_pool.auto_payout @ ErrorCode::AutoPayoutPool,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.owner == spinner.key() @ ErrorCode::NotTicketOwner,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool,
+        constraint = user_ticket.is_drawn() || company_pool.pool_mode() == PoolMode::Raffle @ ErrorCode::TicketNotUsed,
+        constraint = !user_ticket.reward_claimed @ ErrorCode::RewardAlreadyClaimed
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    // Required by raffle pools to claim a prize the draw assigned
+    #[account(
+        seeds = [b"raffle_result", company_pool.key().as_ref()],
+        bump = raffle_result.bump
+    )]
+    pub raffle_result: Option<Box<Account<'info, RaffleResult>>>,
+
+    #[account(mut)]
+    pub spinner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump = pool_stats.bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    // Pools from before the leaderboard claim without one
+    #[account(
+        mut,
+        seeds = [b"leaderboard", company_pool.key().as_ref()],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Option<Box<Account<'info, Leaderboard>>>,
+
+    pub system_program: Program<'info, System>,
+
+    // Token prizes only: the item's mint, the prize vault's escrow for it and
+    // the winner's account for the same mint
+    pub reward_mint: Option<Box<Account<'info, Mint>>>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = prize_vault,
+    )]
+    pub prize_escrow: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = spinner,
+    )]
+    pub winner_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+impl<'info> ClaimReward<'info> {
+    // Shared by claim_reward and claim_reward_wrapped, which differ only in
+    // where the lamports go
+    fn claim(&mut self, recipient: AccountInfo<'info>, wrapped: bool) -> Result<()> {
+        let company_pool = &mut self.company_pool;
+        let user_ticket = &mut self.user_ticket;
+        let clock = Clock::get()?;
+
+        // Validate pool state. Claims deliberately ignore the global pause so
+        // winners are never locked out of rewards they already hold.
+        require!(company_pool.active, ErrorCode::PoolInactive);
+
+        // Validate ticket ownership and state
+        require!(
+            user_ticket.owner == self.spinner.key(),
+            ErrorCode::NotTicketOwner
+        );
+        require!(
+            user_ticket.company_pool == company_pool.key(),
+            ErrorCode::InvalidTicketPool
+        );
+
+        // Raffle tickets are never spun; the draw decided what each one won
+        if company_pool.pool_mode() == PoolMode::Raffle
+            && user_ticket.state() == TicketState::Unspun
+        {
+            let raffle_result = self
+                .raffle_result
+                .as_ref()
+                .ok_or(ErrorCode::RaffleNotDrawn)?;
+            let won_item = raffle_result
+                .prize_for(user_ticket.ticket_id)
+                .ok_or(ErrorCode::NotRaffleWinner)?;
+            user_ticket.record_draw(raffle_result.drawn_at, raffle_result.drawn_slot)?;
+            user_ticket.won_item = Some(won_item.clone());
+        }
+        user_ticket.check_spun()?;
+        user_ticket.check_draw_settled(clock.slot)?;
+        company_pool.check_undisputed(user_ticket.spun_at, clock.unix_timestamp)?;
+        require!(
+            !company_pool.is_reward_expired(user_ticket.spun_at, clock.unix_timestamp),
+            ErrorCode::RewardExpired
+        );
+
+        // A losing spin can only claim its consolation, if the pool pays one
+        let won_item = user_ticket.won_item.clone();
+        require!(
+            won_item.is_some() || user_ticket.consolation_amount > 0,
+            ErrorCode::NoRewardToClaim
+        );
+        // A mystery prize waits until the authority reveals what it is
+        require!(
+            won_item
+                .as_ref()
+                .is_none_or(|item| !company_pool.reveal_pending(item.item_id)),
+            ErrorCode::ItemNotRevealed
+        );
+
+        // Physical prizes are shipped off-chain instead of paid from the vault
+        if let Some(won_item) = won_item
+            .clone()
+            .filter(|item| item.item_kind == ItemKind::Physical)
+        {
+            require!(
+                !user_ticket.fulfillment_pending,
+                ErrorCode::FulfillmentPending
+            );
+            user_ticket.fulfillment_pending = true;
+
+            emit!(FulfillmentRequestedEvent {
+                winner: self.spinner.key(),
+                ticket_id: user_ticket.ticket_id,
+                won_item,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+
+            return Ok(());
+        }
+
+        if let Some(won_item) = won_item
+            .as_ref()
+            .filter(|item| item.item_kind == ItemKind::Token)
+        {
+            let (
+                Some(reward_mint),
+                Some(prize_escrow),
+                Some(winner_token_account),
+                Some(token_program),
+            ) = (
+                self.reward_mint.as_ref(),
+                self.prize_escrow.as_ref(),
+                self.winner_token_account.as_ref(),
+                self.token_program.as_ref(),
+            )
+            else {
+                return err!(ErrorCode::TokenPrizeAccountsMissing);
+            };
+            let prize = company_pool.pay_token_prize(won_item.item_id)?;
+            require_keys_eq!(reward_mint.key(), prize.mint, ErrorCode::InvalidTokenPrize);
+
+            let company_pool_key = company_pool.key();
+            let seeds = &[
+                b"prize_vault",
+                company_pool_key.as_ref(),
+                &[company_pool.prize_vault_bump],
+            ];
+            anchor_spl::token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    anchor_spl::token::Transfer {
+                        from: prize_escrow.to_account_info(),
+                        to: winner_token_account.to_account_info(),
+                        authority: self.prize_vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                prize.amount,
+            )?;
+            user_ticket.mark_claimed()?;
+
+            emit!(TokenPrizeClaimedEvent {
+                winner: self.spinner.key(),
+                ticket_id: user_ticket.ticket_id,
+                item_id: won_item.item_id,
+                mint: prize.mint,
+                amount: prize.amount,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+
+            return Ok(());
+        }
+
+        let reward_amount = won_item
+            .as_ref()
+            .map_or(user_ticket.consolation_amount, |item| item.price);
+
+        // Rewards above the vesting threshold pay their first tranche now
+        // and the rest through claim_vested
+        let payout = company_pool.upfront_amount(reward_amount);
+
+        // Validate the prize vault has sufficient funds
+        let vault_balance = self.prize_vault.lamports();
+        let rent_reserve = company_pool.rent_reserve()?;
+        let available_balance = vault_balance.saturating_sub(rent_reserve);
+
+        require!(
+            payout <= available_balance,
+            ErrorCode::InsufficientVaultFunds
+        );
+
+        // Create seeds for PDA signing
+        let company_pool_key = company_pool.key();
+        let seeds = &[
+            b"prize_vault",
+            company_pool_key.as_ref(),
+            &[company_pool.prize_vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        // Transfer reward from prize vault to the winner or their wSOL account
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: self.prize_vault.to_account_info(),
+            to: recipient,
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, payout)?;
+
+        // Mark reward as claimed
+        user_ticket.mark_claimed()?;
+
+        let pool_stats = &mut self.pool_stats;
+        pool_stats.total_rewards_paid = pool_stats
+            .total_rewards_paid
+            .checked_add(payout)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // The unvested remainder stays booked as a liability
+        company_pool.release_liability(payout);
+        company_pool.record_reward_paid(payout)?;
+        if let Some(global_stats) = self.global_stats.as_mut() {
+            global_stats.record_reward_paid(payout)?;
+        }
+
+        if payout < reward_amount {
+            user_ticket.start_vesting(payout, clock.unix_timestamp);
+            emit!(RewardVestingStartedEvent {
+                winner: self.spinner.key(),
+                ticket_id: user_ticket.ticket_id,
+                vested_total: reward_amount,
+                upfront_amount: payout,
+                vesting_end: company_pool.vesting_end(clock.unix_timestamp),
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        let Some(won_item) = won_item else {
+            emit!(ConsolationClaimedEvent {
+                spinner: self.spinner.key(),
+                ticket_id: user_ticket.ticket_id,
+                amount: reward_amount,
+                wrapped,
+                event_seq: company_pool.next_event_seq(),
+                timestamp: clock.unix_timestamp,
+            });
+            return Ok(());
+        };
+
+        let item_name = company_pool.item_name(won_item.item_id);
+
+        // Item wins count toward the claimant's total on the leaderboard
+        let winner = self.spinner.key();
+        let leaderboard_rank = self
+            .leaderboard
+            .as_mut()
+            .and_then(|leaderboard| leaderboard.record_win(winner, reward_amount));
+
+        // Log reward claim
+        msg!("🎁 REWARD CLAIMED 🎁");
+        msg!("Winner: {}", self.spinner.key());
+        msg!("Item: {}", item_name);
+        msg!("Reward Amount: {} lamports", reward_amount);
+        msg!("Ticket ID: {}", user_ticket.ticket_id);
+
+        // Emit reward claimed event
+        emit!(RewardClaimedEvent {
+            winner: self.spinner.key(),
+            ticket_id: user_ticket.ticket_id,
+            won_item: won_item.clone(),
+            item_name,
+            reward_amount,
+            external_id: user_ticket.external_id,
+            leaderboard_rank,
+            serial: user_ticket.serial(),
+            wrapped,
+            event_seq: company_pool.next_event_seq(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewardWrapped<'info> {
+    pub claim: ClaimReward<'info>,
+
+    #[account(address = spl_token::native_mint::ID @ ErrorCode::InvalidWrappedMint)]
+    pub native_mint: Box<Account<'info, Mint>>,
+
+    // The winner's wSOL account, created on their first wrapped claim
+    #[account(
+        init_if_needed,
+        payer = claim.spinner,
+        associated_token::mint = native_mint,
+        associated_token::authority = claim.spinner,
+    )]
+    pub wsol_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    // Creates the wSOL account; the claim's own system_program pays out
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.active @ ErrorCode::PoolInactive,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.owner == claimant.key() @ ErrorCode::NotTicketOwner,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump = pool_stats.bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetItemAvailability<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetItemBoost<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealItem<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePoolItem<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    // Required by item-account pools, which keep the text here
+    #[account(mut)]
+    pub pool_item: Option<Account<'info, PoolItemAccount>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddPoolItem<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PoolItemAccount::INIT_SPACE,
+        seeds = [
+            b"item",
+            company_pool.key().as_ref(),
+            company_pool.next_item_id().to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub pool_item: Account<'info, PoolItemAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositItemTokens<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    pub reward_mint: Box<Account<'info, Mint>>,
+
+    // The prize vault's account for the mint, created on the first deposit
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = prize_vault,
+    )]
+    pub prize_escrow: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = authority,
+    )]
+    pub authority_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimItemTokens<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    pub reward_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = prize_vault,
+    )]
+    pub prize_escrow: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = authority,
+    )]
+    pub authority_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CommitServerSeed<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealServerSeed<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTicketPrice<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BackfillPoolBumps<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeGlobalConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + GlobalConfig::INIT_SPACE,
+        seeds = [b"global_config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized
+    )]
+    pub program: Program<'info, crate::program::CompanyPool>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetProgramPaused<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimMegaJackpot<'info> {
+    #[account(mut, seeds = [b"mega_jackpot"], bump = mega_jackpot.bump)]
+    pub mega_jackpot: Account<'info, MegaJackpot>,
+
+    #[account(
+        mut,
+        seeds = [b"mega_jackpot_vault"],
+        bump = mega_jackpot.vault_bump,
+    )]
+    pub mega_jackpot_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.owner == claimant.key() @ ErrorCode::NotTicketOwner,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimInsurance<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.owner == owner.key() @ ErrorCode::NotTicketOwner,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimSharedReward<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.active @ ErrorCode::PoolInactive,
+        constraint = !company_pool.auto_payout @ ErrorCode::AutoPayoutPool,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.owner == shared_ticket.key() @ ErrorCode::NotTicketOwner,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool,
+        constraint = user_ticket.is_drawn() @ ErrorCode::TicketNotUsed,
+        constraint = !user_ticket.reward_claimed @ ErrorCode::RewardAlreadyClaimed
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(
+        seeds = [b"shared_ticket", user_ticket.key().as_ref()],
+        bump = shared_ticket.bump
+    )]
+    pub shared_ticket: Account<'info, SharedTicket>,
+
+    // Any of the participants
+    pub claimant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump = pool_stats.bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    // Pools from before the leaderboard claim without one
+    #[account(
+        mut,
+        seeds = [b"leaderboard", company_pool.key().as_ref()],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Option<Box<Account<'info, Leaderboard>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPoolCreationMode<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeGlobalStats<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + GlobalStats::INIT_SPACE,
+        seeds = [b"global_stats"],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeMegaJackpot<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + MegaJackpot::INIT_SPACE,
+        seeds = [b"mega_jackpot"],
+        bump
+    )]
+    pub mega_jackpot: Account<'info, MegaJackpot>,
+
+    #[account(mut, seeds = [b"mega_jackpot_vault"], bump)]
+    pub mega_jackpot_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMegaJackpotTerms<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut, seeds = [b"mega_jackpot"], bump = mega_jackpot.bump)]
+    pub mega_jackpot: Account<'info, MegaJackpot>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateCreationFee<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct BlockUser<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + BlockedUser::INIT_SPACE,
+        seeds = [b"blocked", user.as_ref()],
+        bump
+    )]
+    pub blocked_user: Account<'info, BlockedUser>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct UnblockUser<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"blocked", user.as_ref()],
+        bump = blocked_user.bump
+    )]
+    pub blocked_user: Account<'info, BlockedUser>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct AddPoolCreator<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PoolCreatorEntry::INIT_SPACE,
+        seeds = [b"pool_creator", creator.as_ref()],
+        bump
+    )]
+    pub creator_entry: Account<'info, PoolCreatorEntry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct RemovePoolCreator<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"pool_creator", creator.as_ref()],
+        bump = creator_entry.bump
+    )]
+    pub creator_entry: Account<'info, PoolCreatorEntry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateGlobalConfig<'info> {
+    /// CHECK: May be in an older layout, so it can't load as a GlobalConfig.
+    /// Discriminator and size are checked when it is read.
+    #[account(mut, seeds = [b"global_config"], bump, owner = crate::ID)]
+    pub global_config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdminSetPoolFlag<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetSpinHistory<'info> {
+    pub spin_history: Box<Account<'info, SpinHistory>>,
+}
+
+#[derive(Accounts)]
+pub struct GetRecentWinners<'info> {
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        seeds = [b"winner_registry", company_pool.key().as_ref()],
+        bump = winner_registry.load()?.bump,
+    )]
+    pub winner_registry: AccountLoader<'info, WinnerRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeWinnerRegistry<'info> {
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<WinnerRegistry>(),
+        seeds = [b"winner_registry", company_pool.key().as_ref()],
+        bump
+    )]
+    pub winner_registry: AccountLoader<'info, WinnerRegistry>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetLeaderboard<'info> {
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        seeds = [b"leaderboard", company_pool.key().as_ref()],
+        bump = leaderboard.bump,
+    )]
+    pub leaderboard: Box<Account<'info, Leaderboard>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLeaderboard<'info> {
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Leaderboard::INIT_SPACE,
+        seeds = [b"leaderboard", company_pool.key().as_ref()],
+        bump
+    )]
+    pub leaderboard: Box<Account<'info, Leaderboard>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetPoolStats<'info> {
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump = pool_stats.bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+}
+
+#[derive(Accounts)]
+pub struct GetGlobalStats<'info> {
+    #[account(seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Account<'info, GlobalStats>,
+}
+
+#[derive(Accounts)]
+pub struct GetDistributionReport<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Reconcile<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    // Empty for pools that haven't migrated to a prize vault yet
+    #[account(
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump
+    )]
+    pub prize_vault: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct IssueVoucher<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Voucher::INIT_SPACE,
+        seeds = [b"voucher", company_pool.key().as_ref(), recipient.as_ref()],
+        bump
+    )]
+    pub voucher: Account<'info, Voucher>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemVoucher<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"voucher", company_pool.key().as_ref(), redeemer.key().as_ref()],
+        bump = voucher.bump
+    )]
+    pub voucher: Account<'info, Voucher>,
+
+    #[account(
+        init,
+        payer = redeemer,
+        space = 8 + UserTicket::INIT_SPACE,
+        seeds = [
+        b"user_ticket",
+        redeemer.key().as_ref(),
+        company_pool.key().as_ref(),
+        &company_pool.total_tickets_sold.to_le_bytes()
+        ],
+        bump
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub redeemer: Signer<'info>,
+
+    /// CHECK: The redeemer's BlockedUser PDA, which usually doesn't exist, so
+    /// only its owner and data are read, in check_not_blocked
+    #[account(seeds = [b"blocked", redeemer.key().as_ref()], bump)]
+    pub blocked_user: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemPointsForTicket<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), redeemer.key().as_ref()],
+        bump = user_pool_state.bump
+    )]
+    pub user_pool_state: Account<'info, UserPoolState>,
+
+    #[account(
+        init,
+        payer = redeemer,
+        space = 8 + UserTicket::INIT_SPACE,
+        seeds = [
+        b"user_ticket",
+        redeemer.key().as_ref(),
+        company_pool.key().as_ref(),
+        &company_pool.total_tickets_sold.to_le_bytes()
+        ],
+        bump
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub redeemer: Signer<'info>,
+
+    /// CHECK: The redeemer's BlockedUser PDA, which usually doesn't exist, so
+    /// only its owner and data are read, in check_not_blocked
+    #[account(seeds = [b"blocked", redeemer.key().as_ref()], bump)]
+    pub blocked_user: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeVoucher<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"voucher", company_pool.key().as_ref(), voucher.recipient.as_ref()],
+        bump = voucher.bump
+    )]
+    pub voucher: Account<'info, Voucher>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct GiftTicket<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + UserTicket::INIT_SPACE,
+        seeds = [
+        b"user_ticket",
+        recipient.as_ref(),
+        company_pool.key().as_ref(),
+        &company_pool.total_tickets_sold.to_le_bytes()
+        ],
+        bump
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserPoolState::INIT_SPACE,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), recipient.as_ref()],
+        bump
+    )]
+    pub recipient_pool_state: Account<'info, UserPoolState>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: The payer's BlockedUser PDA, which usually doesn't exist, so
+    /// only its owner and data are read, in check_not_blocked
+    #[account(seeds = [b"blocked", payer.key().as_ref()], bump)]
+    pub payer_blocked_user: UncheckedAccount<'info>,
+
+    /// CHECK: The recipient's BlockedUser PDA, which usually doesn't exist, so
+    /// only its owner and data are read, in check_not_blocked
+    #[account(seeds = [b"blocked", recipient.as_ref()], bump)]
+    pub recipient_blocked_user: UncheckedAccount<'info>,
+
+    // Only read when the pool has whitelisting enabled
+    pub recipient_whitelist_entry: Option<Account<'info, WhitelistEntry>>,
+
+    // Only read when the pool is token-gated
+    pub recipient_gate_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex metadata for the gate token, owner and contents are verified in check_token_gate
+    pub recipient_gate_metadata: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    // The network-wide jackpot, only needed when the pool takes part in it
+    #[account(mut, seeds = [b"mega_jackpot"], bump = mega_jackpot.bump)]
+    pub mega_jackpot: Option<Box<Account<'info, MegaJackpot>>>,
+
+    #[account(mut, seeds = [b"mega_jackpot_vault"], bump)]
+    pub mega_jackpot_vault: Option<SystemAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuySharedTicket<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + UserTicket::INIT_SPACE,
+        seeds = [
+        b"user_ticket",
+        payer.key().as_ref(),
+        company_pool.key().as_ref(),
+        &company_pool.total_tickets_sold.to_le_bytes()
+        ],
+        bump
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + SharedTicket::INIT_SPACE,
+        seeds = [b"shared_ticket", user_ticket.key().as_ref()],
+        bump
+    )]
+    pub shared_ticket: Account<'info, SharedTicket>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserPoolState::INIT_SPACE,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    pub payer_pool_state: Account<'info, UserPoolState>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: The payer's BlockedUser PDA, which usually doesn't exist, so
+    /// only its owner and data are read, in check_not_blocked
+    #[account(seeds = [b"blocked", payer.key().as_ref()], bump)]
+    pub blocked_user: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct AddToWhitelist<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + WhitelistEntry::INIT_SPACE,
+        seeds = [b"whitelist", company_pool.key().as_ref(), user.as_ref()],
+        bump
+    )]
+    pub whitelist_entry: Account<'info, WhitelistEntry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct RemoveFromWhitelist<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"whitelist", company_pool.key().as_ref(), user.as_ref()],
+        bump = whitelist_entry.bump
+    )]
+    pub whitelist_entry: Account<'info, WhitelistEntry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GambleReward<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.owner == spinner.key() @ ErrorCode::NotTicketOwner,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    pub spinner: Signer<'info>,
+
+    #[account(
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+}
+
+#[derive(Accounts)]
+pub struct RerollSpin<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.active @ ErrorCode::PoolInactive,
+        constraint = !company_pool.auto_payout @ ErrorCode::AutoPayoutPool
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.owner == spinner.key() @ ErrorCode::NotTicketOwner,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool,
+        constraint = user_ticket.is_drawn() @ ErrorCode::TicketNotUsed,
+        constraint = !user_ticket.rerolled @ ErrorCode::AlreadyRerolled,
+        constraint = !user_ticket.reward_claimed @ ErrorCode::RewardAlreadyClaimed,
+        constraint = !user_ticket.reward_expired @ ErrorCode::RewardAlreadyReclaimed,
+        constraint = !user_ticket.gambled @ ErrorCode::AlreadyGambled,
+        constraint = !user_ticket.fulfillment_pending @ ErrorCode::FulfillmentPending,
+        constraint = user_ticket.reward_nft_mint.is_none() @ ErrorCode::RewardNftAlreadyMinted
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub spinner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump = pool_stats.bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    // Takes back an unclaimed mega jackpot win along with the result
+    #[account(mut, seeds = [b"mega_jackpot"], bump = mega_jackpot.bump)]
+    pub mega_jackpot: Option<Box<Account<'info, MegaJackpot>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TradeInReward<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.active @ ErrorCode::PoolInactive,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.owner == spinner.key() @ ErrorCode::NotTicketOwner,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool,
+        constraint = user_ticket.is_drawn() @ ErrorCode::TicketNotUsed,
+        constraint = !user_ticket.reward_claimed @ ErrorCode::RewardAlreadyClaimed
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub spinner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump = pool_stats.bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimExpiredReward<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    // Receives the ticket rent when it is closed
+    #[account(mut, address = user_ticket.owner @ ErrorCode::NotTicketOwner)]
+    pub ticket_owner: SystemAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CrankExpireReward<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    // Pays the bounty
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAchievementReward<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), claimant.key().as_ref()],
+        bump = user_pool_state.bump
+    )]
+    pub user_pool_state: Account<'info, UserPoolState>,
+
+    // Pays the bonus
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CrankExpirePool<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(
+        mut,
+        close = authority,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    // Pools from before the prize vault split have none
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: Option<SystemAccount<'info>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ArchivePool<'info> {
+    #[account(
+        mut,
+        close = authority,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PoolArchive::INIT_SPACE,
+        seeds = [b"pool_archive", company_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_archive: Box<Account<'info, PoolArchive>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    // Pools from before the prize vault split have none
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: Option<SystemAccount<'info>>,
+
+    // Pools from before the leaderboard archive no winners
+    #[account(
+        seeds = [b"leaderboard", company_pool.key().as_ref()],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Option<Box<Account<'info, Leaderboard>>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartNewSeason<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    // Archives the season being closed
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SeasonArchive::INIT_SPACE,
+        seeds = [
+        b"season_archive",
+        company_pool.key().as_ref(),
+        &company_pool.season().to_le_bytes()
+        ],
+        bump
+    )]
+    pub season_archive: Box<Account<'info, SeasonArchive>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump = pool_stats.bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+
+    // Pools from before the leaderboard archive no winners and can't reset it
+    #[account(
+        mut,
+        seeds = [b"leaderboard", company_pool.key().as_ref()],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Option<Box<Account<'info, Leaderboard>>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTicket<'info> {
+    /// CHECK: The ticket's pool, in either layout or already closed. Only
+    /// written by next_event_seq_of.
+    #[account(mut, address = user_ticket.company_pool @ ErrorCode::InvalidTicketPool)]
+    pub company_pool: UncheckedAccount<'info>,
+
+    #[account(mut, close = owner, has_one = owner @ ErrorCode::Unauthorized)]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelStuckSpin<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    // The pool authority, or anyone once the spin has timed out
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VoidSpin<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    // Receives the refund
+    #[account(mut, address = user_ticket.owner @ ErrorCode::NotTicketOwner)]
+    pub ticket_owner: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    // Takes back an unclaimed mega jackpot win along with the spin
+    #[account(mut, seeds = [b"mega_jackpot"], bump = mega_jackpot.bump)]
+    pub mega_jackpot: Option<Box<Account<'info, MegaJackpot>>>,
+
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifySpin<'info> {
+    /// CHECK: The ticket's pool, in either layout. Only written by
+    /// next_event_seq_of.
+    #[account(mut, address = user_ticket.company_pool @ ErrorCode::InvalidTicketPool)]
+    pub company_pool: UncheckedAccount<'info>,
+
+    pub user_ticket: Account<'info, UserTicket>,
+}
+
+#[derive(Accounts)]
+pub struct DrawRaffle<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + RaffleResult::INIT_SPACE,
+        seeds = [b"raffle_result", company_pool.key().as_ref()],
+        bump
+    )]
+    pub raffle_result: Box<Account<'info, RaffleResult>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: The slot hashes sysvar, whose latest hash seeds the draw
+    #[account(address = sysvar_slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeSponsorRevenue<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintRewardNft<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.owner == winner.key() @ ErrorCode::NotTicketOwner,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool,
+        constraint = user_ticket.is_drawn() @ ErrorCode::TicketNotUsed,
+        constraint = user_ticket.reward_nft_mint.is_none() @ ErrorCode::RewardNftAlreadyMinted
+    )]
+    pub user_ticket: Box<Account<'info, UserTicket>>,
+
+    // Required by item-account pools, which keep the image here
+    pub pool_item: Option<Box<Account<'info, PoolItemAccount>>>,
+
+    #[account(mut)]
+    pub winner: Signer<'info>,
+
+    #[account(
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    // One mint per ticket, so the seeds alone rule out a second NFT
+    #[account(
+        init,
+        payer = winner,
+        seeds = [b"reward_mint", user_ticket.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = pool_vault,
+        mint::freeze_authority = pool_vault,
+    )]
+    pub reward_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = winner,
+        associated_token::mint = reward_mint,
+        associated_token::authority = winner,
+    )]
+    pub winner_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Created by the Token Metadata program, address checked by seeds
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), reward_mint.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Created by the Token Metadata program, address checked by seeds
+    #[account(
+        mut,
+        seeds = [
+        b"metadata",
+        token_metadata_program.key().as_ref(),
+        reward_mint.key().as_ref(),
+        b"edition"
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub master_edition: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AttachShippingInfo<'info> {
+    #[account(
+        mut,
+        constraint = user_ticket.owner == winner.key() @ ErrorCode::NotTicketOwner
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    pub winner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MarkFulfilled<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepVault<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::UnauthorizedWithdrawal
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // Required when the pool has a co-authority
+    pub co_authority: Option<Signer<'info>>,
+
+    // Receives the sweep when the pool has a beneficiary
+    #[account(
+        mut,
+        address = company_pool.beneficiary().unwrap_or_default() @ ErrorCode::InvalidBeneficiary
+    )]
+    pub beneficiary: Option<SystemAccount<'info>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestWithdrawal<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::UnauthorizedWithdrawal
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawal<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::UnauthorizedWithdrawal
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // Required when the pool has a co-authority
+    pub co_authority: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Cancelling only ever shrinks what can leave the vault, so it ignores the pause
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::UnauthorizedWithdrawal
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCoAuthority<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+
+    // The current co-authority, if the pool has one
+    pub co_authority: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetSpinOperator<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetHappyHour<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePrizeVault<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToVault<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundPrizeVault<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DelegateSpinner<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    // Gift and voucher recipients may not have one yet
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + UserPoolState::INIT_SPACE,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub user_pool_state: Account<'info, UserPoolState>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeDelegate<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), owner.key().as_ref()],
+        bump = user_pool_state.bump
+    )]
+    pub user_pool_state: Account<'info, UserPoolState>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    /// CHECK: May be in an older layout, so it can't load as a CompanyPool.
+    /// Discriminator and size are checked when it is read.
+    #[account(mut, owner = crate::ID)]
+    pub company_pool: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateTicket<'info> {
+    /// CHECK: May be in an older layout, so it can't load as a UserTicket.
+    /// Discriminator, size, owner and pool are checked by the instruction.
+    #[account(mut, owner = crate::ID)]
+    pub user_ticket: UncheckedAccount<'info>,
+
+    /// CHECK: The ticket's pool, in either layout. Checked against the
+    /// ticket once it's read, and only written by next_event_seq_of.
+    #[account(mut)]
+    pub company_pool: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct MigrateUserPoolState<'info> {
+    /// CHECK: May be in an older layout, so it can't load as a UserPoolState.
+    /// Discriminator and size are checked when it is read.
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), user.as_ref()],
+        bump
+    )]
+    pub user_pool_state: UncheckedAccount<'info>,
+
+    /// CHECK: The state's pool, in either layout. Only written by
+    /// next_event_seq_of.
+    #[account(mut)]
+    pub company_pool: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(ticket_price: u64, company_name: String)]
+pub struct InitializeCompanyPoolV2<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<CompanyPoolV2>(),
+        seeds = [b"company_pool_v2", CompanyPool::name_seed(&company_name).as_ref()],
+        bump
+    )]
+    pub company_pool: AccountLoader<'info, CompanyPoolV2>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    // Only read when pool creation is allowlisted
+    pub creator_entry: Option<Account<'info, PoolCreatorEntry>>,
+
+    // Only needed while the config charges a creation fee
+    #[account(mut, address = global_config.treasury @ ErrorCode::InvalidTreasury)]
+    pub treasury: Option<SystemAccount<'info>>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyTicketV2<'info> {
+    #[account(mut)]
+    pub company_pool: AccountLoader<'info, CompanyPoolV2>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + UserTicket::INIT_SPACE,
+        seeds = [
+        b"user_ticket",
+        buyer.key().as_ref(),
+        company_pool.key().as_ref(),
+        &company_pool.load()?.total_tickets_sold.to_le_bytes()
+        ],
+        bump
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: The buyer's BlockedUser PDA, which usually doesn't exist, so
+    /// only its owner and data are read, in check_not_blocked
+    #[account(seeds = [b"blocked", buyer.key().as_ref()], bump)]
+    pub blocked_user: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.key().as_ref()],
+        bump = company_pool.load()?.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.load()?.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordSpinResultV2<'info> {
+    #[account(mut)]
+    pub company_pool: AccountLoader<'info, CompanyPoolV2>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool,
+        constraint = !user_ticket.used @ ErrorCode::TicketAlreadyUsed
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(
+        owner = anchor_lang::system_program::ID @ ErrorCode::InvalidSpinnerAccount
+    )]
+    pub spinner: Signer<'info>,
+
+    // Read for the random seed only
+    #[account(
+        seeds = [b"pool_vault", company_pool.key().as_ref()],
+        bump = company_pool.load()?.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    /// CHECK: The instructions sysvar, read to reject spins made through CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewardV2<'info> {
+    #[account(mut)]
+    pub company_pool: AccountLoader<'info, CompanyPoolV2>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(mut)]
+    pub spinner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.load()?.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFundsFromVaultV2<'info> {
+    #[account(mut)]
+    pub company_pool: AccountLoader<'info, CompanyPoolV2>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.key().as_ref()],
+        bump = company_pool.load()?.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdminSetPoolFlagV2<'info> {
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub company_pool: AccountLoader<'info, CompanyPoolV2>,
+
+    pub admin: Signer<'info>,
+}
+
+// Account Structures
+#[derive(Accounts)]
+pub struct GetProbabilityAnalysis<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+}
+
+#[derive(Accounts)]
+pub struct GetSpinOdds<'info> {
+    #[account(mut)]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+}
+
+#[derive(Accounts)]
+pub struct GetUserTickets<'info> {
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RecordSpinResult<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.active @ ErrorCode::PoolInactive
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        constraint = user_ticket.company_pool == company_pool.key() @ ErrorCode::InvalidTicketPool,
+        constraint = !user_ticket.used @ ErrorCode::TicketAlreadyUsed
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    // The ticket owner, their unexpired spin delegate or the pool's spin
+    // operator. Must be a plain wallet, not an account some other program
+    // controls.
+    #[account(
+        mut,
+        owner = anchor_lang::system_program::ID @ ErrorCode::InvalidSpinnerAccount
+    )]
+    pub spinner: Signer<'info>,
+
+    // The owner's state, needed when a delegate spins to check the
+    // delegation. The spin only counts toward achievements when it is passed.
+    #[account(
+        mut,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), user_ticket.owner.as_ref()],
+        bump = user_pool_state.bump
+    )]
+    pub user_pool_state: Option<Account<'info, UserPoolState>>,
+
+    // Receives auto-payouts when a delegate or the operator spins
+    #[account(mut, address = user_ticket.owner @ ErrorCode::NotTicketOwner)]
+    pub ticket_owner: Option<SystemAccount<'info>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    // Only needed when the pool pays rewards out during the spin
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: Option<SystemAccount<'info>>,
+
+    #[account(
+        init_if_needed,
+        payer = spinner,
+        space = 8 + SpinHistory::INIT_SPACE,
+        seeds = [b"spin_history", user_ticket.owner.as_ref(), company_pool.key().as_ref()],
+        bump
+    )]
+    pub spin_history: Box<Account<'info, SpinHistory>>,
+
+    #[account(
+        mut,
+        seeds = [b"winner_registry", company_pool.key().as_ref()],
+        bump = winner_registry.load()?.bump,
+    )]
+    pub winner_registry: AccountLoader<'info, WinnerRegistry>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump = pool_stats.bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+
+    /// CHECK: The instructions sysvar, read to reject spins made through CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    // The network-wide jackpot, which spins in participating pools roll for
+    #[account(mut, seeds = [b"mega_jackpot"], bump = mega_jackpot.bump)]
+    pub mega_jackpot: Option<Box<Account<'info, MegaJackpot>>>,
+
+    // Lets a participant spin a shared ticket
+    #[account(
+        seeds = [b"shared_ticket", user_ticket.key().as_ref()],
+        bump = shared_ticket.bump
+    )]
+    pub shared_ticket: Option<Box<Account<'info, SharedTicket>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RecordSpinResult<'info> {
+    // Counts the spin toward the owner's achievements, announcing each one
+    // it unlocks
+    fn record_achievements(&mut self, won_item: Option<&PoolItem>, now: i64) {
+        let Some(user_pool_state) = self.user_pool_state.as_mut() else {
+            return;
+        };
+        user_pool_state.enter_season(&self.company_pool);
+        for achievement in user_pool_state.record_spin(won_item) {
+            emit!(AchievementUnlockedEvent {
+                user: self.user_ticket.owner,
+                achievement,
+                achievements: user_pool_state.achievements,
+                event_seq: self.company_pool.next_event_seq(),
+                timestamp: now,
+            });
+        }
+    }
+
+    // Manual pools book what the vault now owes until it is claimed.
+    // Auto-payout pools settle it on the spot, so there is nothing to claim;
+    // an underfunded prize vault fails the whole spin and the ticket stays unspun.
+    fn settle_reward(&mut self, now: i64) -> Result<()> {
+        let amount = self.user_ticket.vault_liability();
+        if !self.company_pool.auto_payout {
+            return self.company_pool.add_liability(amount);
+        }
+
+        // Physical prizes still ship off-chain, so fulfillment starts right away
+        if let Some(won_item) = self
+            .user_ticket
+            .won_item
+            .clone()
+            .filter(|item| item.item_kind == ItemKind::Physical)
+        {
+            self.user_ticket.fulfillment_pending = true;
+            emit!(FulfillmentRequestedEvent {
+                winner: self.user_ticket.owner,
+                ticket_id: self.user_ticket.ticket_id,
+                won_item,
+                event_seq: self.company_pool.next_event_seq(),
+                timestamp: now,
+            });
+            return Ok(());
+        }
+        if amount == 0 {
+            return Ok(());
+        }
+
+        // Large rewards vest here too: the first tranche is paid with the
+        // spin and the rest stays owed until claim_vested releases it
+        let payout = self.company_pool.upfront_amount(amount);
+
+        let prize_vault = self
+            .prize_vault
+            .as_ref()
+            .ok_or(ErrorCode::PrizeVaultRequired)?;
+        let rent_reserve = self.company_pool.rent_reserve()?;
+        require!(
+            payout <= prize_vault.lamports().saturating_sub(rent_reserve),
+            ErrorCode::InsufficientVaultFunds
+        );
+
+        let company_pool_key = self.company_pool.key();
+        let seeds = &[
+            b"prize_vault",
+            company_pool_key.as_ref(),
+            &[self.company_pool.prize_vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        // A delegate's spin still pays the owner
+        let recipient = if self.spinner.key() == self.user_ticket.owner {
+            self.spinner.to_account_info()
+        } else {
+            self.ticket_owner
+                .as_ref()
+                .ok_or(ErrorCode::TicketOwnerRequired)?
+                .to_account_info()
+        };
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: prize_vault.to_account_info(),
+            to: recipient,
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, payout)?;
+
+        self.user_ticket.mark_claimed()?;
+        self.pool_stats.total_rewards_paid = self
+            .pool_stats
+            .total_rewards_paid
+            .checked_add(payout)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.company_pool.record_reward_paid(payout)?;
+        if let Some(global_stats) = self.global_stats.as_mut() {
+            global_stats.record_reward_paid(payout)?;
+        }
+
+        emit!(RewardAutoPaidEvent {
+            winner: self.user_ticket.owner,
+            ticket_id: self.user_ticket.ticket_id,
+            amount: payout,
+            event_seq: self.company_pool.next_event_seq(),
+            timestamp: now,
+        });
+
+        if payout < amount {
+            self.company_pool
+                .add_liability(amount.saturating_sub(payout))?;
+            self.user_ticket.start_vesting(payout, now);
+            emit!(RewardVestingStartedEvent {
+                winner: self.user_ticket.owner,
+                ticket_id: self.user_ticket.ticket_id,
+                vested_total: amount,
+                upfront_amount: payout,
+                vesting_end: self.company_pool.vesting_end(now),
+                event_seq: self.company_pool.next_event_seq(),
+                timestamp: now,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFundsFromVault<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::UnauthorizedWithdrawal
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // Required when the pool has a co-authority
+    pub co_authority: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyTicket<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + UserTicket::INIT_SPACE,
+        seeds = [
+        b"user_ticket",
+        buyer.key().as_ref(),
+        company_pool.key().as_ref(),
+        &company_pool.total_tickets_sold.to_le_bytes()
+        ],
+        bump
+    )]
+    pub user_ticket: Account<'info, UserTicket>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + UserPoolState::INIT_SPACE,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub user_pool_state: Account<'info, UserPoolState>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    // Only read when the pool has whitelisting enabled
+    pub whitelist_entry: Option<Account<'info, WhitelistEntry>>,
+
+    /// CHECK: The buyer's BlockedUser PDA, which usually doesn't exist, so
+    /// only its owner and data are read, in check_not_blocked
+    #[account(seeds = [b"blocked", buyer.key().as_ref()], bump)]
+    pub blocked_user: UncheckedAccount<'info>,
+
+    // Only read when the pool is token-gated
+    pub gate_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex metadata for the gate token, owner and contents are verified in check_token_gate
+    pub gate_metadata: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    // Receives the donation, only needed when there is one
+    #[account(
+        mut,
+        address = company_pool.beneficiary().unwrap_or_default() @ ErrorCode::InvalidBeneficiary
+    )]
+    pub beneficiary: Option<SystemAccount<'info>>,
+
+    // The network-wide jackpot, only needed when the pool takes part in it
+    #[account(mut, seeds = [b"mega_jackpot"], bump = mega_jackpot.bump)]
+    pub mega_jackpot: Option<Box<Account<'info, MegaJackpot>>>,
+
+    #[account(mut, seeds = [b"mega_jackpot_vault"], bump)]
+    pub mega_jackpot_vault: Option<SystemAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyBundle<'info> {
+    #[account(
+        mut,
+        constraint = company_pool.has_prize_vault() @ ErrorCode::PrizeVaultNotInitialized
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + UserPoolState::INIT_SPACE,
+        seeds = [b"user_pool_state", company_pool.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub user_pool_state: Account<'info, UserPoolState>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: The buyer's BlockedUser PDA, which usually doesn't exist, so
+    /// only its owner and data are read, in check_not_blocked
+    #[account(seeds = [b"blocked", buyer.key().as_ref()], bump)]
+    pub blocked_user: UncheckedAccount<'info>,
+
+    // Only read when the pool has whitelisting enabled
+    pub whitelist_entry: Option<Account<'info, WhitelistEntry>>,
+
+    // Only read when the pool is token-gated
+    pub gate_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex metadata for the gate token, owner and contents are verified in check_token_gate
+    pub gate_metadata: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.vault_seed(&company_pool.key()).as_slice()],
+        bump = company_pool.vault_bump,
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump = company_pool.prize_vault_bump,
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    // The network-wide jackpot, only needed when the pool takes part in it
+    #[account(mut, seeds = [b"mega_jackpot"], bump = mega_jackpot.bump)]
+    pub mega_jackpot: Option<Box<Account<'info, MegaJackpot>>>,
+
+    #[account(mut, seeds = [b"mega_jackpot_vault"], bump)]
+    pub mega_jackpot_vault: Option<SystemAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> BuyBundle<'info> {
+    // Creates a ticket PDA the way `init` does for buy_ticket, topping the
+    // address up instead of failing if someone already sent it lamports
+    fn create_ticket_account(
+        &self,
+        ticket_info: &AccountInfo<'info>,
+        signer_seeds: &[&[u8]],
+    ) -> Result<()> {
+        use anchor_lang::system_program::{
+            allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+        };
+
+        let space = 8 + UserTicket::INIT_SPACE;
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(space);
+        let system_program = self.system_program.to_account_info();
+        let signer = &[signer_seeds];
+
+        let current_lamports = ticket_info.lamports();
+        if current_lamports == 0 {
+            let cpi_accounts = CreateAccount {
+                from: self.buyer.to_account_info(),
+                to: ticket_info.clone(),
+            };
+            return create_account(
+                CpiContext::new_with_signer(system_program, cpi_accounts, signer),
+                rent_exempt_minimum,
+                space as u64,
+                &crate::ID,
+            );
+        }
+
+        let shortfall = rent_exempt_minimum.saturating_sub(current_lamports);
+        if shortfall > 0 {
+            let cpi_accounts = Transfer {
+                from: self.buyer.to_account_info(),
+                to: ticket_info.clone(),
+            };
+            transfer(
+                CpiContext::new(system_program.clone(), cpi_accounts),
+                shortfall,
+            )?;
+        }
+        let cpi_accounts = Allocate {
+            account_to_allocate: ticket_info.clone(),
+        };
+        allocate(
+            CpiContext::new_with_signer(system_program.clone(), cpi_accounts, signer),
+            space as u64,
+        )?;
+        let cpi_accounts = Assign {
+            account_to_assign: ticket_info.clone(),
+        };
+        assign(
+            CpiContext::new_with_signer(system_program, cpi_accounts, signer),
+            &crate::ID,
+        )
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(ticket_price: u64, company_name: String)]
+pub struct InitializeCompanyPool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + CompanyPool::INIT_SPACE,
+        seeds = [b"company_pool", CompanyPool::name_seed(&company_name).as_ref()],
+        bump
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PoolStats::INIT_SPACE,
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<WinnerRegistry>(),
+        seeds = [b"winner_registry", company_pool.key().as_ref()],
+        bump
+    )]
+    pub winner_registry: AccountLoader<'info, WinnerRegistry>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Leaderboard::INIT_SPACE,
+        seeds = [b"leaderboard", company_pool.key().as_ref()],
+        bump
+    )]
+    pub leaderboard: Box<Account<'info, Leaderboard>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    // Only read when pool creation is allowlisted
+    pub creator_entry: Option<Account<'info, PoolCreatorEntry>>,
+
+    // Only needed while the config charges a creation fee
+    #[account(mut, address = global_config.treasury @ ErrorCode::InvalidTreasury)]
+    pub treasury: Option<SystemAccount<'info>>,
+
+    // Protocol-wide counters, skipped when the client leaves them out
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// The accounts of InitializeCompanyPool for the clone, plus the pool it copies
+#[derive(Accounts)]
+#[instruction(new_company_name: String)]
+pub struct ClonePool<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub source_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + CompanyPool::INIT_SPACE,
+        seeds = [b"company_pool", CompanyPool::name_seed(&new_company_name).as_ref()],
+        bump
+    )]
+    pub company_pool: Box<Account<'info, CompanyPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", company_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"prize_vault", company_pool.key().as_ref()],
+        bump
+    )]
+    pub prize_vault: SystemAccount<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PoolStats::INIT_SPACE,
+        seeds = [b"pool_stats", company_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_stats: Box<Account<'info, PoolStats>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<WinnerRegistry>(),
+        seeds = [b"winner_registry", company_pool.key().as_ref()],
+        bump
+    )]
+    pub winner_registry: AccountLoader<'info, WinnerRegistry>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Leaderboard::INIT_SPACE,
+        seeds = [b"leaderboard", company_pool.key().as_ref()],
+        bump
+    )]
+    pub leaderboard: Box<Account<'info, Leaderboard>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = !global_config.program_paused @ ErrorCode::ProgramPaused
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub creator_entry: Option<Account<'info, PoolCreatorEntry>>,
+
+    #[account(mut, address = global_config.treasury @ ErrorCode::InvalidTreasury)]
+    pub treasury: Option<SystemAccount<'info>>,
+
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Option<Box<Account<'info, GlobalStats>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Data Structures
+#[account]
+#[derive(InitSpace)]
+pub struct CompanyPool {
+    pub version: u8, // Account layout, see CompanyPool::VERSION
+    pub authority: Pubkey,
+    #[max_len(50)]
+    pub company_name: String,
+    #[max_len(200)]
+    pub company_image: String,
+    pub ticket_price: u64,
+    // Inline pools hold up to ten full items. Item-account pools keep each
+    // image and description on a PoolItemAccount instead, and the slimmer
+    // entries fit MAX_ACCOUNT_ITEMS in the same space.
+    #[max_len(10)]
+    pub items: Vec<PoolItem>,
+    pub total_value: u64,
+    pub total_tickets_sold: u64,
+    pub total_funds: u64,
+    pub active: bool,
+    pub created_at: i64,
+    // Appended fields read back as 0 on legacy pools (zeroed account tail)
+    pub pool_version: u8,
+    pub vault_bump: u8,
+    pub pool_bump: u8,
+    pub vault_rent_reserve: u64,
+    pub max_tickets_per_user: Option<u32>,
+    pub purchase_cooldown_seconds: Option<u32>,
+    pub whitelist_enabled: bool,
+    pub gate_mint: Option<Pubkey>,
+    pub gate_min_amount: u64,
+    pub gate_collection: Option<Pubkey>,
+    pub gamble_win_bps: Option<u16>,
+    pub gamble_payout_cap: Option<u64>,
+    pub trade_in_bps: u16,
+    pub claim_deadline_seconds: Option<u32>,
+    pub outstanding_liabilities: u64, // Won but unclaimed reward value owed from the vault
+    pub jackpot_contribution_bps: u16,
+    pub jackpot_accumulated: u64, // Earmarked in the vault for the next jackpot win
+    pub loyalty_enabled: bool,
+    pub loyalty_points_per_ticket: u32,
+    pub points_per_free_ticket: u32,
+    pub weighting: WeightingMode, // Reused by every probability recalculation
+    pub min_probability_bps: Option<u16>,
+    pub max_probability_bps: Option<u16>,
+    pub no_win_bps: u16,
+    pub consolation_lamports: u64,
+    pub withdrawal_timelock_seconds: u32,
+    pub pending_withdrawal: Option<PendingWithdrawal>, // Reserved in the vault until executed or cancelled
+    pub max_daily_withdrawal: Option<u64>,
+    pub withdrawn_today: u64,
+    pub withdrawal_day_start: i64,
+    pub co_authority: Option<Pubkey>, // Co-signs withdrawals and its own rotation
+    pub prize_allocation_bps: u16,
+    pub prize_vault_bump: u8, // 0 until the pool has a prize vault
+    // Ledger counters, only ever increased, so vault flows can be reconciled
+    pub total_ticket_revenue: u64, // Full price of every paid ticket and reroll, both vault shares
+    pub total_rewards_paid: u64,
+    pub total_withdrawn: u64,
+    pub total_authority_deposits: u64, // Lamports paid in outside ticket sales
+    pub auto_payout: bool,
+    pub reroll_price: u64,
+    #[max_len(2)]
+    pub bundles: Vec<BundleDefinition>,
+    // Running probability totals aligned with `items`, rebuilt whenever the
+    // table changes. Unavailable items add no width. A fixed array, so a stale
+    // account tail can't be misread as its length during migration.
+    pub cum_probability: [u32; CompanyPool::MAX_ACCOUNT_ITEMS],
+    pub pool_flags: u8,            // PoolFlag bits, only set by the program admin
+    pub allow_cpi_purchases: bool, // Other programs may buy tickets through CPI
+    pub ends_at: Option<i64>,      // Ticket sales stop after this time
+    pub event_seq: u64,            // Number of the pool's latest event
+    pub spin_operator: Option<Pubkey>, // May spin any ticket, see set_spin_operator
+    pub vesting_threshold_lamports: Option<u64>, // Rewards above this vest
+    pub vesting_duration_seconds: u32,
+    pub vesting_upfront_bps: u16, // Share of a vesting reward paid at claim
+    pub pool_mode: PoolMode,
+    pub sponsors: [ItemSponsor; CompanyPool::MAX_SPONSORS], // Filled from the front
+    // Loyalty bonuses for consecutive days of play, ascending and filled from
+    // the front
+    pub streak_milestones: [StreakMilestone; CompanyPool::MAX_STREAK_MILESTONES],
+    pub achievement_bonus_lamports: u64, // Paid once per unlocked achievement
+    // Promotional odds multipliers, see set_item_boost. Expired slots are
+    // reused, so they aren't filled from the front.
+    pub boosts: [ItemBoost; CompanyPool::MAX_BOOSTS],
+    // Mystery items not yet revealed, filled from the front
+    pub pending_reveals: [PendingReveal; CompanyPool::MAX_PENDING_REVEALS],
+    pub beneficiary: Option<Pubkey>, // Receives swept revenue, fixed at init
+    pub min_item_price: u64,         // Floor on item prices, 0 for none
+    // Items paid in SPL tokens, filled from the front
+    pub token_prizes: [ItemTokenPrize; CompanyPool::MAX_TOKEN_PRIZES],
+    pub server_seeded: bool, // Spins need a live server seed commitment, fixed at init
+    pub server_seed: ServerSeedCommitment,
+    pub dispute_window_seconds: u32, // Wins can be voided and not settled this long after the spin
+    pub authority_play_disabled: bool, // The pool's own keys can't get tickets, fixed at init
+    pub happy_hour: HappyHour,       // Daily ticket discount, see set_happy_hour
+    pub max_tickets_per_slot: Option<u16>, // Across all buyers, see record_slot_purchases
+    pub last_purchase_slot: u64,
+    pub purchases_in_slot: u16, // Tickets sold in last_purchase_slot
+    pub total_donations: u64,   // Passed on to the beneficiary by buy_ticket
+    pub mega_jackpot: bool,     // Takes part in the MegaJackpot, fixed at init
+    pub total_mega_jackpot_contributions: u64, // Revenue paid into the mega jackpot
+    pub insurance: Option<SpinInsurance>,
+    pub total_insurance_premiums: u64, // Paid into the prize vault
+    pub total_insurance_paid: u64,     // Paid out of it by claim_insurance
+    // Counted up by start_new_season from 0, the pool's opening season
+    pub season: u16,
+    pub season_started_at: i64,
+    pub season_start_tickets_sold: u64, // total_tickets_sold when the season began
+    pub season_start_revenue: u64,      // total_ticket_revenue then
+    // Latest season that cleared each kind of player progress, see
+    // UserPoolState::enter_season
+    pub points_reset_season: u16,
+    pub streaks_reset_season: u16,
+    pub achievements_reset_season: u16,
+    pub shared_tickets_sold: u64, // Keeps every item paid in SOL once nonzero
+}
+
+impl CompanyPool {
+    pub const DEFAULT_GAMBLE_WIN_BPS: u16 = 5000;
+
+    pub const WITHDRAWAL_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+
+    pub const MAX_BUNDLES: usize = 2;
+
+    // Account layout. Each version appends to the one before it, and older
+    // pools load through read_any_version, in migration.rs, until
+    // migrate_pool rewrites them:
+    //
+    //   version  adds
+    //   1        the original layout, without a version byte
+    //   2        the leading version byte
+    //   3        cumulative probability table
+    //   4        pool flags
+    //   5        CPI purchase setting
+    //   6        end time
+    //   7        event counter
+    //   8        spin operator
+    //   9        vesting terms
+    //   10       pool mode
+    //   11       sponsors
+    //   12       streak milestones
+    //   13       achievement bonus
+    //   14       item boosts
+    //   15       pending reveals
+    //   16       beneficiary
+    //   17       item price floor
+    //   18       token prizes
+    //   19       server seeds
+    //   20       dispute window
+    //   21       authority play setting
+    //   22       happy hour
+    //   23       per-slot purchase limit
+    //   24       donation total
+    //   25       mega jackpot setting and contributions
+    //   26       spin insurance and its totals
+    //   27       season fields
+    //   28       shared ticket count
+    pub const VERSION: u8 = 28;
+
+    pub const VERSION_CUMULATIVE_TABLE: u8 = 3;
+
+    pub const VERSION_POOL_FLAGS: u8 = 4;
+
+    pub const VERSION_CPI_PURCHASES: u8 = 5;
+
+    pub const VERSION_ENDS_AT: u8 = 6;
+
+    pub const VERSION_EVENT_SEQ: u8 = 7;
+
+    pub const VERSION_SPIN_OPERATOR: u8 = 8;
+
+    pub const VERSION_VESTING: u8 = 9;
+
+    pub const VERSION_POOL_MODE: u8 = 10;
+
+    pub const VERSION_SPONSORS: u8 = 11;
+
+    pub const VERSION_STREAKS: u8 = 12;
+
+    pub const VERSION_ACHIEVEMENT_BONUS: u8 = 13;
+
+    pub const VERSION_BOOSTS: u8 = 14;
+
+    pub const MAX_BOOSTS: usize = 2;
+
+    pub const VERSION_REVEALS: u8 = 15;
+
+    pub const MAX_PENDING_REVEALS: usize = 2;
+
+    pub const VERSION_BENEFICIARY: u8 = 16;
+
+    pub const VERSION_MIN_ITEM_PRICE: u8 = 17;
+
+    pub const VERSION_TOKEN_PRIZES: u8 = 18;
+
+    pub const MAX_TOKEN_PRIZES: usize = 2;
+
+    pub const VERSION_SERVER_SEEDS: u8 = 19;
+
+    pub const VERSION_DISPUTE_WINDOW: u8 = 20;
+
+    pub const VERSION_AUTHORITY_PLAY: u8 = 21;
+
+    pub const VERSION_HAPPY_HOUR: u8 = 22;
+
+    pub const VERSION_SLOT_LIMIT: u8 = 23;
+
+    pub const VERSION_DONATIONS: u8 = 24;
+
+    pub const VERSION_MEGA_JACKPOT: u8 = 25;
+
+    pub const VERSION_INSURANCE: u8 = 26;
+
+    pub const VERSION_SEASONS: u8 = 27;
+
+    pub const VERSION_SHARED_TICKETS: u8 = 28;
+
+    pub const MAX_SPONSORS: usize = 4;
+
+    // Sponsors together take at most half of every sale
+    pub const MAX_SPONSOR_SHARE_BPS: u16 = 5000;
+
+    pub const MAX_STREAK_MILESTONES: usize = 4;
+
+    // Paid to whoever cranks an expired reward, 0.0001 SOL
+    pub const CRANK_BOUNTY_LAMPORTS: u64 = 100_000;
+
+    // Pool version 1 seeds the vault with the pool key instead of the company name
+    pub const POOL_VERSION: u8 = 1;
+
+    // Pool version 2 also stores its items' text on PoolItemAccounts
+    pub const POOL_VERSION_ITEM_ACCOUNTS: u8 = 2;
+
+    pub const MAX_INLINE_ITEMS: usize = 10;
+
+    pub const MAX_ACCOUNT_ITEMS: usize = 50;
+
+    // Pool PDA seed: hashing the name keeps it within the 32-byte seed limit and
+    // avoids collisions between names that only differ past byte 32
+    pub fn name_seed(company_name: &str) -> [u8; 32] {
+        hash(company_name.as_bytes()).to_bytes()
+    }
+
+    // Whitelisted pools only sell to users holding this pool's entry
+    pub fn check_whitelist(
+        &self,
+        pool_key: &Pubkey,
+        entry: Option<&WhitelistEntry>,
+        user: &Pubkey,
+    ) -> Result<()> {
+        if !self.whitelist_enabled {
+            return Ok(());
+        }
+
+        let entry = entry.ok_or(ErrorCode::NotWhitelisted)?;
+        require!(
+            entry.user == *user && entry.company_pool == *pool_key,
+            ErrorCode::NotWhitelisted
+        );
+
+        Ok(())
+    }
+
+    pub fn add_liability(&mut self, amount: u64) -> Result<()> {
+        self.outstanding_liabilities = self
+            .outstanding_liabilities
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Pools created before the split still pay prizes from the revenue vault
+    pub fn has_prize_vault(&self) -> bool {
+        self.prize_vault_bump != 0
+    }
+
+    // What each vault was funded with for rent at creation. Pools from before
+    // the reserve was recorded read 0 here and fall back to the current rent.
+    pub fn rent_reserve(&self) -> Result<u64> {
+        if self.vault_rent_reserve > 0 {
+            return Ok(self.vault_rent_reserve);
+        }
+        Ok(Rent::get()?.minimum_balance(0))
+    }
+
+    // Revenue vault lamports that must stay put: rent, any withdrawal
+    // waiting out its timelock and revenue owed to sponsors, plus unclaimed
+    // rewards and the jackpot pot while the pool still keeps them in the
+    // same vault
+    pub fn reserved_balance(&self, rent_exempt_minimum: u64) -> u64 {
+        let reserved = rent_exempt_minimum
+            .saturating_add(self.pending_withdrawal.map_or(0, |pending| pending.amount))
+            .saturating_add(self.sponsor_revenue_owed());
+        if self.has_prize_vault() {
+            return reserved;
+        }
+        reserved
+            .saturating_add(self.outstanding_liabilities)
+            .saturating_add(self.jackpot_accumulated)
+    }
+
+    // Saturating because rewards won before liabilities were tracked were never added
+    pub fn release_liability(&mut self, amount: u64) {
+        self.outstanding_liabilities = self.outstanding_liabilities.saturating_sub(amount);
+    }
+
+    pub fn record_reward_paid(&mut self, amount: u64) -> Result<()> {
+        self.total_rewards_paid = self
+            .total_rewards_paid
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Lamports the vaults should hold by the ledger: rent for each vault plus
+    // every tracked inflow minus every tracked outflow. Signed because pools
+    // that predate the counters can have paid out more than they recorded.
+    pub fn ledger_balance(&self, rent_exempt_minimum: u64) -> i128 {
+        let vaults = if self.has_prize_vault() { 2 } else { 1 };
+        rent_exempt_minimum as i128 * vaults
+            + self.total_ticket_revenue as i128
+            + self.total_authority_deposits as i128
+            - self.total_rewards_paid as i128
+            - self.total_withdrawn as i128
+            - self.total_mega_jackpot_contributions as i128
+            + self.total_insurance_premiums as i128
+            - self.total_insurance_paid as i128
+    }
+
+    pub fn reconcile(
+        &self,
+        actual_balance: u64,
+        rent_exempt_minimum: u64,
+    ) -> Result<ReconciliationReport> {
+        let expected = self.ledger_balance(rent_exempt_minimum);
+        let delta = actual_balance as i128 - expected;
+        Ok(ReconciliationReport {
+            expected_balance: i64::try_from(expected).map_err(|_| ErrorCode::MathOverflow)?,
+            actual_balance,
+            delta: i64::try_from(delta).map_err(|_| ErrorCode::MathOverflow)?,
+        })
+    }
+
+    // Nothing in the vaults belongs to anyone but the authority: no reward,
+    // jackpot, sponsor share or token prize owed, no revenue left on the
+    // books and no withdrawal waiting out its timelock
+    pub fn is_settled(&self) -> bool {
+        self.outstanding_liabilities == 0
+            && self.total_funds == 0
+            && self.jackpot_accumulated == 0
+            && self.pending_withdrawal.is_none()
+            && self.sponsor_revenue_owed() == 0
+            && self
+                .token_prizes()
+                .iter()
+                .all(|prize| prize.escrowed == 0 && prize.owed == 0)
+    }
+
+    // Takes the items and settings of `source` for clone_pool, leaving this
+    // pool's keys, bumps, counters and balances alone. Token items wait for
+    // their own escrow, and sponsors start with nothing paid. Probabilities
+    // are the source's until the caller recalculates them.
+    pub fn copy_settings_from(&mut self, source: &CompanyPool) -> Result<()> {
+        self.ticket_price = source.ticket_price;
+        self.items = source
+            .items
+            .iter()
+            .map(|item| PoolItem {
+                available: item.available && item.item_kind != ItemKind::Token,
+                times_won: 0,
+                ..item.clone()
+            })
+            .collect();
+        self.total_value = source.total_value;
+        self.max_tickets_per_user = source.max_tickets_per_user;
+        self.purchase_cooldown_seconds = source.purchase_cooldown_seconds;
+        self.whitelist_enabled = source.whitelist_enabled;
+        self.gate_mint = source.gate_mint;
+        self.gate_min_amount = source.gate_min_amount;
+        self.gate_collection = source.gate_collection;
+        self.gamble_win_bps = source.gamble_win_bps;
+        self.gamble_payout_cap = source.gamble_payout_cap;
+        self.trade_in_bps = source.trade_in_bps;
+        self.claim_deadline_seconds = source.claim_deadline_seconds;
+        self.jackpot_contribution_bps = source.jackpot_contribution_bps;
+        self.loyalty_enabled = source.loyalty_enabled;
+        self.loyalty_points_per_ticket = source.loyalty_points_per_ticket;
+        self.points_per_free_ticket = source.points_per_free_ticket;
+        self.weighting = source.weighting;
+        self.min_probability_bps = source.min_probability_bps;
+        self.max_probability_bps = source.max_probability_bps;
+        self.no_win_bps = source.no_win_bps;
+        self.consolation_lamports = source.consolation_lamports;
+        self.withdrawal_timelock_seconds = source.withdrawal_timelock_seconds;
+        self.max_daily_withdrawal = source.max_daily_withdrawal;
+        self.co_authority = source.co_authority;
+        self.prize_allocation_bps = source.prize_allocation_bps;
+        self.auto_payout = source.auto_payout;
+        self.reroll_price = source.reroll_price;
+        self.bundles = source.bundles.clone();
+        self.allow_cpi_purchases = source.allow_cpi_purchases;
+        self.ends_at = source.ends_at;
+        self.spin_operator = source.spin_operator;
+        self.vesting_threshold_lamports = source.vesting_threshold_lamports;
+        self.vesting_duration_seconds = source.vesting_duration_seconds;
+        self.vesting_upfront_bps = source.vesting_upfront_bps;
+        self.pool_mode = source.pool_mode;
+        self.streak_milestones = source.streak_milestones;
+        self.achievement_bonus_lamports = source.achievement_bonus_lamports;
+        self.beneficiary = source.beneficiary;
+        self.min_item_price = source.min_item_price;
+        self.server_seeded = source.server_seeded;
+        self.dispute_window_seconds = source.dispute_window_seconds;
+        self.authority_play_disabled = source.authority_play_disabled;
+        self.happy_hour = source.happy_hour;
+        self.max_tickets_per_slot = source.max_tickets_per_slot;
+        self.mega_jackpot = source.mega_jackpot;
+        self.insurance = source.insurance();
+
+        self.sponsors = [ItemSponsor::default(); CompanyPool::MAX_SPONSORS];
+        for (slot, sponsor) in self.sponsors.iter_mut().zip(source.sponsor_slots()) {
+            *slot = ItemSponsor {
+                paid: 0,
+                ..*sponsor
+            };
+        }
+        self.pending_reveals = [PendingReveal::default(); CompanyPool::MAX_PENDING_REVEALS];
+        for (slot, pending) in self
+            .pending_reveals
+            .iter_mut()
+            .zip(source.pending_reveals())
+        {
+            *slot = *pending;
+        }
+        self.token_prizes = [ItemTokenPrize::default(); CompanyPool::MAX_TOKEN_PRIZES];
+        for prize in source.token_prizes() {
+            self.add_token_prize(prize.item_id, prize.mint, prize.amount)?;
+        }
+        Ok(())
+    }
+
+    // Pools with a co-authority need it as a second signer on withdrawals
+    pub fn check_co_authority(&self, co_signer: Option<Pubkey>) -> Result<()> {
+        if let Some(co_authority) = self.co_authority {
+            require!(
+                co_signer == Some(co_authority),
+                ErrorCode::CoAuthoritySignatureRequired
+            );
+        }
+        Ok(())
+    }
+
+    // Allowance left in the current 24h withdrawal window
+    pub fn daily_withdrawal_remaining(&self, now: i64) -> u64 {
+        let Some(max_daily) = self.max_daily_withdrawal else {
+            return u64::MAX;
+        };
+        if now
+            >= self
+                .withdrawal_day_start
+                .saturating_add(Self::WITHDRAWAL_WINDOW_SECONDS)
+        {
+            max_daily
+        } else {
+            max_daily.saturating_sub(self.withdrawn_today)
+        }
+    }
+
+    // Counts `amount` against the daily cap, opening a new window once the
+    // previous one has run its 24h, and adds it to the withdrawal ledger
+    pub fn record_withdrawal(&mut self, amount: u64, now: i64) -> Result<()> {
+        require!(
+            amount <= self.daily_withdrawal_remaining(now),
+            ErrorCode::DailyWithdrawalLimitExceeded
+        );
+
+        if now
+            >= self
+                .withdrawal_day_start
+                .saturating_add(Self::WITHDRAWAL_WINDOW_SECONDS)
+        {
+            self.withdrawal_day_start = now;
+            self.withdrawn_today = 0;
+        }
+        self.withdrawn_today = self.withdrawn_today.saturating_add(amount);
+        self.total_withdrawn = self
+            .total_withdrawn
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn is_reward_expired(&self, spun_at: i64, now: i64) -> bool {
+        match self.claim_deadline_seconds {
+            Some(deadline) => now > spun_at.saturating_add(deadline as i64),
+            None => false,
+        }
+    }
+
+    // Older layouts hold stale bytes where the dispute window would be
+    pub fn dispute_window_seconds(&self) -> u32 {
+        if self.version >= Self::VERSION_DISPUTE_WINDOW {
+            self.dispute_window_seconds
+        } else {
+            0
+        }
+    }
+
+    // Whether void_spin may still take back a spin made at `spun_at`
+    pub fn is_disputable(&self, spun_at: i64, now: i64) -> bool {
+        now < spun_at.saturating_add(self.dispute_window_seconds() as i64)
+    }
+
+    // Rewards are only settled once their spin can no longer be voided
+    pub fn check_undisputed(&self, spun_at: i64, now: i64) -> Result<()> {
+        require!(
+            !self.is_disputable(spun_at, now),
+            ErrorCode::DisputeWindowOpen
+        );
+        Ok(())
+    }
+
+    // (revenue, prize) shares of a voided ticket's refund, split as its
+    // payment was. The revenue share comes out of the authority's revenue,
+    // all or nothing like the achievement bonus. The prize share leaves the
+    // prize vault like a reward, in place of the one voided.
+    pub fn take_refund(
+        &mut self,
+        price_paid: u64,
+        vault_balance: u64,
+        rent_exempt_minimum: u64,
+    ) -> Result<(u64, u64)> {
+        let (revenue_share, prize_share) = self.split_ticket_payment(price_paid)?;
+        let spare = vault_balance.saturating_sub(self.reserved_balance(rent_exempt_minimum));
+        require!(
+            revenue_share <= self.total_funds && revenue_share <= spare,
+            ErrorCode::InsufficientVaultFunds
+        );
+        self.total_funds -= revenue_share;
+        self.total_withdrawn = self
+            .total_withdrawn
+            .checked_add(revenue_share)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.record_reward_paid(prize_share)?;
+        Ok((revenue_share, prize_share))
+    }
+
+    // `bps` basis points of `value`, rounded down
+    fn bps_share(value: u64, bps: u16) -> Result<u64> {
+        let share = (value as u128)
+            .checked_mul(bps as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            / 10000;
+        u64::try_from(share).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    // SOL paid for trading in an item worth `value`, rounded down
+    pub fn trade_in_payout(&self, value: u64) -> Result<u64> {
+        Self::bps_share(value, self.trade_in_bps)
+    }
+
+    // (revenue, prize) shares of a ticket payment. The prize share rounds
+    // down, so the two always add up to the full price.
+    pub fn split_ticket_payment(&self, ticket_price: u64) -> Result<(u64, u64)> {
+        let prize_share = Self::bps_share(ticket_price, self.prize_allocation_bps)?;
+        Ok((ticket_price - prize_share, prize_share))
+    }
+
+    // Books a paid ticket's revenue and sets aside the jackpot's share of it
+    pub fn record_ticket_sale(&mut self, ticket_price: u64) -> Result<()> {
+        let (revenue_share, _) = self.split_ticket_payment(ticket_price)?;
+        self.total_funds = self
+            .total_funds
+            .checked_add(revenue_share)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_ticket_revenue = self
+            .total_ticket_revenue
+            .checked_add(ticket_price)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let contribution = Self::bps_share(ticket_price, self.jackpot_contribution_bps)?;
+        self.jackpot_accumulated = self
+            .jackpot_accumulated
+            .checked_add(contribution)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn uses_item_accounts(&self) -> bool {
+        self.pool_version >= Self::POOL_VERSION_ITEM_ACCOUNTS
+    }
+
+    // Ids are never reused, so the next one is the number of items ever added
+    pub fn next_item_id(&self) -> u32 {
+        self.items.len() as u32
+    }
+
+    // Where an item currently sits in `items`. Positions are only valid for
+    // the current item list, ids for the pool's lifetime.
+    pub fn item_position(&self, item_id: u32) -> Option<usize> {
+        self.items.iter().position(|item| item.item_id == item_id)
+    }
+
+    // Display name of a won item, empty if the id is unknown
+    pub fn item_name(&self, item_id: u32) -> String {
+        self.item_position(item_id)
+            .map(|position| self.items[position].name.clone())
+            .unwrap_or_default()
+    }
+
+    // One turn of the wheel: None for a losing spin, otherwise the index of the
+    // won item. The no-win roll reads the seed's upper half so it doesn't skew
+    // which item the lower half selects. A minimum tier limits the draw to
+    // items of that tier or better and always wins one of them.
+    pub fn draw(&self, random_seed: u64, min_tier: Option<ItemTier>) -> Result<Option<usize>> {
+        if let Some(min_tier) = min_tier {
+            return self.draw_guaranteed(random_seed, min_tier).map(Some);
+        }
+
+        // Only migrate_pool builds the table for an older pool, so it has to
+        // be migrated before it can spin
+        require!(
+            self.version >= Self::VERSION_CUMULATIVE_TABLE,
+            ErrorCode::UnsupportedAccountVersion
+        );
+        let table = &self.cum_probability[..self.items.len()];
+        let total = table.last().copied().unwrap_or(0);
+        require!(total > 0, ErrorCode::NoAvailableItems);
+
+        if random_seed.rotate_right(32) % 10000 < self.no_win_bps as u64 {
+            return Ok(None);
+        }
+
+        // The first item whose running total passes the roll. Zero-width
+        // items share their predecessor's total, so they're never picked.
+        let roll = random_seed % total as u64;
+        Ok(Some(
+            table.partition_point(|&cumulative| cumulative as u64 <= roll),
+        ))
+    }
+
+    // draw with the boosts running at `now` applied. Boosted weights can't
+    // use the stored table, so the draw scans the items instead.
+    pub fn draw_at(
+        &self,
+        random_seed: u64,
+        min_tier: Option<ItemTier>,
+        now: i64,
+    ) -> Result<Option<usize>> {
+        if !self.boost_active(now) {
+            return self.draw(random_seed, min_tier);
+        }
+        if min_tier.is_none() && random_seed.rotate_right(32) % 10000 < self.no_win_bps as u64 {
+            return Ok(None);
+        }
+
+        let candidates: Vec<(usize, u32)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.available && item.probability > 0)
+            .filter(|(_, item)| min_tier.is_none_or(|min_tier| item.tier >= min_tier))
+            .map(|(index, item)| (index, self.boosted_probability(item, now)))
+            .collect();
+        require!(!candidates.is_empty(), ErrorCode::NoAvailableItems);
+
+        let weights: Vec<u32> = candidates.iter().map(|(_, weight)| *weight).collect();
+        let winning_index = select_winning_item_index(&weights, random_seed)
+            .ok_or(ErrorCode::ProbabilitySelectionFailed)?;
+
+        Ok(Some(candidates[winning_index].0))
+    }
+
+    // The weights draw_at picks from at `now`, laid out per item so one
+    // replay covers the stored table, boosts and tier guarantees alike
+    pub fn draw_table(&self, min_tier: Option<ItemTier>, now: i64) -> DrawTable {
+        let boost_active = self.boost_active(now);
+        let weights = self
+            .items
+            .iter()
+            .map(|item| {
+                let eligible = item.available
+                    && item.probability > 0
+                    && min_tier.is_none_or(|min_tier| item.tier >= min_tier);
+                match (eligible, boost_active) {
+                    (false, _) => 0,
+                    (true, true) => self.boosted_probability(item, now),
+                    (true, false) => item.probability,
+                }
+            })
+            .collect();
+        DrawTable {
+            // A guaranteed draw always wins
+            no_win_bps: if min_tier.is_none() {
+                self.no_win_bps
+            } else {
+                0
+            },
+            weights,
+        }
+    }
+
+    // Bundle guarantees draw over a filtered list instead, since the table
+    // covers every tier
+    fn draw_guaranteed(&self, random_seed: u64, min_tier: ItemTier) -> Result<usize> {
+        let available_items: Vec<(usize, u32)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.available && item.probability > 0)
+            .filter(|(_, item)| item.tier >= min_tier)
+            .map(|(index, item)| (index, item.probability))
+            .collect();
+        require!(!available_items.is_empty(), ErrorCode::NoAvailableItems);
+
+        let probabilities: Vec<u32> = available_items
+            .iter()
+            .map(|(_, probability)| *probability)
+            .collect();
+        let winning_index = select_winning_item_index(&probabilities, random_seed)
+            .ok_or(ErrorCode::ProbabilitySelectionFailed)?;
+
+        Ok(available_items[winning_index].0)
+    }
+
+    // An unmigrated account holds its stale tail where the flags would be,
+    // so older layouts have none
+    pub fn flags(&self) -> u8 {
+        if self.version >= Self::VERSION_POOL_FLAGS {
+            self.pool_flags
+        } else {
+            0
+        }
+    }
+
+    pub fn has_flag(&self, flag: PoolFlag) -> bool {
+        self.flags() & flag.bit() != 0
+    }
+
+    // Frozen pools can't take new money in or let the operator take it out
+    pub fn check_not_frozen(&self) -> Result<()> {
+        require!(!self.has_flag(PoolFlag::Frozen), ErrorCode::PoolFrozen);
+        Ok(())
+    }
+
+    // Older layouts hold a stale byte where the setting would be
+    pub fn allows_cpi_purchases(&self) -> bool {
+        self.version >= Self::VERSION_CPI_PURCHASES && self.allow_cpi_purchases
+    }
+
+    // Tickets are sold to top-level callers unless the authority lets other
+    // programs buy through CPI, e.g. a partner's checkout
+    pub fn check_purchase_caller(&self) -> Result<()> {
+        check_purchase_caller(self.allows_cpi_purchases())
+    }
+
+    // Older layouts hold a stale byte where the setting would be
+    pub fn authority_can_play(&self) -> bool {
+        self.version < Self::VERSION_AUTHORITY_PLAY || !self.authority_play_disabled
+    }
+
+    // `player` is who will own the ticket, not who pays for it
+    pub fn check_player(&self, player: &Pubkey) -> Result<()> {
+        if self.authority_can_play() {
+            return Ok(());
+        }
+        let operators = [
+            Some(self.authority),
+            self.co_authority,
+            self.spin_operator(),
+        ];
+        require!(
+            !operators.contains(&Some(*player)),
+            ErrorCode::AuthorityCannotPlay
+        );
+        Ok(())
+    }
+
+    // Older layouts hold stale bytes where the window would be
+    pub fn happy_hour(&self) -> HappyHour {
+        if self.version >= Self::VERSION_HAPPY_HOUR {
+            self.happy_hour
+        } else {
+            HappyHour::default()
+        }
+    }
+
+    // Older layouts have no limit
+    pub fn max_tickets_per_slot(&self) -> Option<u16> {
+        if self.version >= Self::VERSION_SLOT_LIMIT {
+            self.max_tickets_per_slot
+        } else {
+            None
+        }
+    }
+
+    pub fn joins_mega_jackpot(&self) -> bool {
+        self.version >= Self::VERSION_MEGA_JACKPOT && self.mega_jackpot
+    }
+
+    // Older layouts hold stale bytes where the terms would be
+    pub fn insurance(&self) -> Option<SpinInsurance> {
+        if self.version >= Self::VERSION_INSURANCE {
+            self.insurance
+        } else {
+            None
+        }
+    }
+
+    pub fn record_insurance_premium(&mut self, amount: u64) -> Result<()> {
+        self.total_insurance_premiums = self
+            .total_insurance_premiums
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_insurance_paid(&mut self, amount: u64) -> Result<()> {
+        self.total_insurance_paid = self
+            .total_insurance_paid
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Older layouts hold stale bytes where the count would be, and can't
+    // sell shared tickets
+    pub fn shared_tickets_sold(&self) -> u64 {
+        if self.version >= Self::VERSION_SHARED_TICKETS {
+            self.shared_tickets_sold
+        } else {
+            0
+        }
+    }
+
+    // Older layouts hold stale bytes where the season would be, and never
+    // started one
+    pub fn season(&self) -> u16 {
+        if self.version >= Self::VERSION_SEASONS {
+            self.season
+        } else {
+            0
+        }
+    }
+
+    // The opening season runs from the pool's creation
+    pub fn season_started_at(&self) -> i64 {
+        if self.season() == 0 {
+            self.created_at
+        } else {
+            self.season_started_at
+        }
+    }
+
+    // Moves the pool on to `season`, noting which player progress it clears
+    // so each UserPoolState can catch up when next used
+    pub fn start_season(&mut self, season: u16, reset_flags: SeasonResetFlags, now: i64) {
+        self.season = season;
+        self.season_started_at = now;
+        self.season_start_tickets_sold = self.total_tickets_sold;
+        self.season_start_revenue = self.total_ticket_revenue;
+        if reset_flags.loyalty_points {
+            self.points_reset_season = season;
+        }
+        if reset_flags.streaks {
+            self.streaks_reset_season = season;
+        }
+        if reset_flags.achievements {
+            self.achievements_reset_season = season;
+        }
+    }
+
+    // A shared ticket's reward is split in lamports in a single claim, which
+    // only works for spins paying SOL in full when claimed. Its owner is a
+    // PDA that can't sign for the mega jackpot either, and the participants
+    // aren't checked against a whitelist or token gate.
+    pub fn check_sells_shared_tickets(&self) -> Result<()> {
+        require!(
+            self.pool_mode() == PoolMode::Spin
+                && !self.auto_payout
+                && !self.whitelist_enabled
+                && self.gate_mint.is_none()
+                && self.gate_collection.is_none()
+                && self.vesting_threshold().is_none()
+                && !self.joins_mega_jackpot()
+                && self
+                    .items
+                    .iter()
+                    .all(|item| item.item_kind == ItemKind::SolPayout),
+            ErrorCode::SharedTicketsNotSupported
+        );
+        Ok(())
+    }
+
+    // Shared tickets only claim SOL, so once one is sold every item added
+    // later has to pay in SOL too, or an unspun shared ticket could draw it
+    pub fn check_shared_tickets_can_draw(&self, item_kind: ItemKind) -> Result<()> {
+        require!(
+            item_kind == ItemKind::SolPayout || self.shared_tickets_sold() == 0,
+            ErrorCode::SharedTicketsNotSupported
+        );
+        Ok(())
+    }
+
+    // Books the mega jackpot's share of a sale. It went straight to the
+    // jackpot vault, so the revenue record_ticket_sale booked is that much less.
+    pub fn record_mega_jackpot_contribution(&mut self, amount: u64) -> Result<()> {
+        self.total_funds = self
+            .total_funds
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientFunds)?;
+        self.total_mega_jackpot_contributions = self
+            .total_mega_jackpot_contributions
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Counts `count` new tickets against max_tickets_per_slot, starting over
+    // once the slot moves on. Pools without a limit keep no count.
+    pub fn record_slot_purchases(&mut self, count: u32, slot: u64) -> Result<()> {
+        let Some(max_tickets) = self.max_tickets_per_slot() else {
+            return Ok(());
+        };
+        let earlier = if slot == self.last_purchase_slot {
+            self.purchases_in_slot as u32
+        } else {
+            0
+        };
+        let purchases = earlier.checked_add(count).ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            purchases <= max_tickets as u32,
+            ErrorCode::SlotPurchaseLimitReached
+        );
+
+        self.last_purchase_slot = slot;
+        self.purchases_in_slot = purchases as u16;
+        Ok(())
+    }
+
+    // What buy_ticket charges at `now`: the ticket price, less the happy
+    // hour discount while its window is open. The probabilities stay those
+    // of the full price.
+    pub fn ticket_price_at(&self, now: i64) -> Result<u64> {
+        let happy_hour = self.happy_hour();
+        if !happy_hour.is_open(now) {
+            return Ok(self.ticket_price);
+        }
+        let discount = Self::bps_share(self.ticket_price, happy_hour.discount_bps)?;
+        Ok(self
+            .ticket_price
+            .checked_sub(discount)
+            .ok_or(ErrorCode::MathOverflow)?)
+    }
+
+    // Numbers the pool's events 1, 2, 3..., so an indexer that sees a gap
+    // knows it missed logs and can backfill from the pool's signatures. Pools
+    // in a layout without the counter number their events 0 until migrated.
+    pub fn next_event_seq(&mut self) -> u64 {
+        if self.version < Self::VERSION_EVENT_SEQ {
+            return 0;
+        }
+        self.event_seq = self.event_seq.saturating_add(1);
+        self.event_seq
+    }
+
+    // The filled sponsor slots; older layouts hold stale bytes there instead
+    pub fn sponsor_slots(&self) -> &[ItemSponsor] {
+        if self.version < Self::VERSION_SPONSORS {
+            return &[];
+        }
+        let filled = self
+            .sponsors
+            .iter()
+            .take_while(|slot| slot.share_bps > 0)
+            .count();
+        &self.sponsors[..filled]
+    }
+
+    // Ticket revenue owed to sponsors and not yet distributed
+    pub fn sponsor_revenue_owed(&self) -> u64 {
+        self.sponsor_slots().iter().fold(0u64, |owed, slot| {
+            owed.saturating_add(slot.owed(self.total_ticket_revenue))
+        })
+    }
+
+    // The configured streak milestones; older layouts hold stale bytes there
+    // instead
+    pub fn streak_milestones(&self) -> &[StreakMilestone] {
+        if self.version < Self::VERSION_STREAKS {
+            return &[];
+        }
+        let filled = self
+            .streak_milestones
+            .iter()
+            .take_while(|milestone| milestone.days > 0)
+            .count();
+        &self.streak_milestones[..filled]
+    }
+
+    // Loyalty points for reaching a streak of exactly `days`, 0 between
+    // milestones
+    pub fn streak_bonus(&self, days: u32) -> u32 {
+        self.streak_milestones()
+            .iter()
+            .find(|milestone| milestone.days as u32 == days)
+            .map_or(0, |milestone| milestone.bonus_points)
+    }
+
+    // Older layouts hold stale bytes where the bonus would be
+    pub fn achievement_bonus(&self) -> u64 {
+        if self.version >= Self::VERSION_ACHIEVEMENT_BONUS {
+            self.achievement_bonus_lamports
+        } else {
+            0
+        }
+    }
+
+    // Older layouts hold stale bytes where the reveals would be
+    pub fn pending_reveals(&self) -> &[PendingReveal] {
+        if self.version < Self::VERSION_REVEALS {
+            return &[];
+        }
+        let filled = self
+            .pending_reveals
+            .iter()
+            .take_while(|pending| pending.reveal_hash != [0; 32])
+            .count();
+        &self.pending_reveals[..filled]
+    }
+
+    pub fn reveal_pending(&self, item_id: u32) -> bool {
+        self.pending_reveals()
+            .iter()
+            .any(|pending| pending.item_id == item_id)
+    }
+
+    // Drops a revealed item's slot, moving the ones after it up
+    pub fn clear_pending_reveal(&mut self, slot: usize) {
+        self.pending_reveals.copy_within(slot + 1.., slot);
+        self.pending_reveals[Self::MAX_PENDING_REVEALS - 1] = PendingReveal::default();
+    }
+
+    // Older layouts hold stale bytes where the boosts would be
+    pub fn boost_slots(&self) -> &[ItemBoost] {
+        if self.version >= Self::VERSION_BOOSTS {
+            &self.boosts
+        } else {
+            &[]
+        }
+    }
+
+    pub fn boost_active(&self, now: i64) -> bool {
+        self.boost_slots().iter().any(|boost| boost.is_active(now))
+    }
+
+    // An item's probability with any boost running at `now` applied, as a
+    // weight relative to the others' basis points
+    pub fn boosted_probability(&self, item: &PoolItem, now: i64) -> u32 {
+        self.boost_slots()
+            .iter()
+            .find(|boost| boost.item_id == item.item_id && boost.is_active(now))
+            .map_or(item.probability, |boost| {
+                (item.probability as u64 * boost.multiplier_bps as u64 / 10000).min(u32::MAX as u64)
+                    as u32
+            })
+    }
+
+    // Where a boost for the item goes: its existing slot, or else one that
+    // is empty or has run out
+    pub fn boost_slot(&self, item_id: u32, now: i64) -> Option<usize> {
+        let slots = self.boost_slots();
+        slots
+            .iter()
+            .position(|boost| boost.multiplier_bps > 0 && boost.item_id == item_id)
+            .or_else(|| {
+                slots
+                    .iter()
+                    .position(|boost| boost.multiplier_bps == 0 || boost.ends_at <= now)
+            })
+    }
+
+    // Older layouts hold a stale byte where the mode would be, and were all
+    // spin pools
+    pub fn pool_mode(&self) -> PoolMode {
+        if self.version >= Self::VERSION_POOL_MODE {
+            self.pool_mode
+        } else {
+            PoolMode::Spin
+        }
+    }
+
+    // Older layouts hold stale bytes where the vesting terms would be
+    pub fn vesting_threshold(&self) -> Option<u64> {
+        if self.version >= Self::VERSION_VESTING {
+            self.vesting_threshold_lamports
+        } else {
+            None
+        }
+    }
+
+    // Part of a reward paid when it is claimed: all of it, unless it is
+    // above the vesting threshold
+    pub fn upfront_amount(&self, reward: u64) -> u64 {
+        match self.vesting_threshold() {
+            Some(threshold) if reward > threshold => {
+                (reward as u128 * self.vesting_upfront_bps as u128 / 10000) as u64
+            }
+            _ => reward,
+        }
+    }
+
+    // How much of a vesting reward has unlocked by `now`: the upfront
+    // tranche, plus the rest linearly over the vesting duration
+    pub fn vested_amount(&self, vested_total: u64, vesting_start: i64, now: i64) -> u64 {
+        let upfront = self.upfront_amount(vested_total);
+        let duration = self.vesting_duration_seconds as i64;
+        if duration == 0 {
+            return vested_total;
+        }
+        let elapsed = now.saturating_sub(vesting_start).clamp(0, duration);
+        let unlocked = (vested_total - upfront) as u128 * elapsed as u128 / duration as u128;
+        upfront + unlocked as u64
+    }
+
+    pub fn vesting_end(&self, vesting_start: i64) -> i64 {
+        vesting_start.saturating_add(self.vesting_duration_seconds as i64)
+    }
+
+    // Older layouts hold stale bytes where the floor would be
+    pub fn min_item_price(&self) -> u64 {
+        if self.version >= Self::VERSION_MIN_ITEM_PRICE {
+            self.min_item_price
+        } else {
+            0
+        }
+    }
+
+    pub fn check_item_price(&self, price: u64) -> Result<()> {
+        require!(price >= self.min_item_price(), ErrorCode::ItemPriceTooLow);
+        Ok(())
+    }
+
+    // Older layouts hold stale bytes where the server seed setting would be
+    pub fn server_seeded(&self) -> bool {
+        self.version >= Self::VERSION_SERVER_SEEDS && self.server_seeded
+    }
+
+    // The seed a spin at `now` draws with, and the commitment it drew under.
+    // Other pools draw with the on-chain entropy alone.
+    pub fn mix_server_seed(&mut self, entropy: u64, now: i64) -> Result<(u64, Option<[u8; 32]>)> {
+        if !self.server_seeded() {
+            return Ok((entropy, None));
+        }
+        require!(
+            self.server_seed.is_live(now),
+            ErrorCode::ServerSeedCommitmentMissing
+        );
+        self.server_seed.spins = self.server_seed.spins.saturating_add(1);
+        Ok((self.server_seed.mix(entropy), Some(self.server_seed.hash)))
+    }
+
+    // Older layouts hold stale bytes where the token prizes would be
+    pub fn token_prizes(&self) -> &[ItemTokenPrize] {
+        if self.version < Self::VERSION_TOKEN_PRIZES {
+            return &[];
+        }
+        let filled = self
+            .token_prizes
+            .iter()
+            .take_while(|prize| prize.amount > 0)
+            .count();
+        &self.token_prizes[..filled]
+    }
+
+    pub fn token_prize(&self, item_id: u32) -> Option<&ItemTokenPrize> {
+        self.token_prizes()
+            .iter()
+            .find(|prize| prize.item_id == item_id)
+    }
+
+    fn token_prize_mut(&mut self, item_id: u32) -> Result<&mut ItemTokenPrize> {
+        let filled = self.token_prizes().len();
+        self.token_prizes[..filled]
+            .iter_mut()
+            .find(|prize| prize.item_id == item_id)
+            .ok_or_else(|| ErrorCode::InvalidTokenPrize.into())
+    }
+
+    pub fn add_token_prize(&mut self, item_id: u32, mint: Pubkey, amount: u64) -> Result<()> {
+        let filled = self.token_prizes().len();
+        let slot = self
+            .token_prizes
+            .get_mut(filled)
+            .ok_or(ErrorCode::InvalidTokenPrize)?;
+        *slot = ItemTokenPrize {
+            item_id,
+            mint,
+            amount,
+            escrowed: 0,
+            owed: 0,
+        };
+        Ok(())
+    }
+
+    pub fn fund_token_prize(&mut self, item_id: u32, amount: u64) -> Result<u64> {
+        let prize = self.token_prize_mut(item_id)?;
+        prize.escrowed = prize
+            .escrowed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(prize.escrowed)
+    }
+
+    // Sets one win's worth of the escrow aside for its winner
+    fn reserve_token_prize(&mut self, item_id: u32) -> Result<()> {
+        let prize = self.token_prize_mut(item_id)?;
+        require!(!prize.is_depleted(), ErrorCode::TokenPrizeDepleted);
+        prize.escrowed -= prize.amount;
+        prize.owed = prize
+            .owed
+            .checked_add(prize.amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // A discarded or expired token win goes back into the escrow
+    pub fn return_token_prize(&mut self, item_id: u32) -> Result<()> {
+        let prize = self.token_prize_mut(item_id)?;
+        prize.owed = prize
+            .owed
+            .checked_sub(prize.amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        prize.escrowed = prize
+            .escrowed
+            .checked_add(prize.amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // A token win paid out; returns the prize for its mint and amount
+    pub fn pay_token_prize(&mut self, item_id: u32) -> Result<ItemTokenPrize> {
+        let prize = self.token_prize_mut(item_id)?;
+        prize.owed = prize
+            .owed
+            .checked_sub(prize.amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(*prize)
+    }
+
+    // Empties the escrow of everything no winner is owed, returning how much
+    pub fn drain_token_prize(&mut self, item_id: u32) -> Result<u64> {
+        let prize = self.token_prize_mut(item_id)?;
+        Ok(std::mem::take(&mut prize.escrowed))
+    }
+
+    // A token item that can't cover another win leaves the draw, unless it
+    // is the last item left in it
+    fn retire_if_depleted(&mut self, index: usize) -> Result<()> {
+        let depleted = self
+            .token_prize(self.items[index].item_id)
+            .is_some_and(ItemTokenPrize::is_depleted);
+        let others_available = self
+            .items
+            .iter()
+            .enumerate()
+            .any(|(position, item)| position != index && item.available);
+        if depleted && others_available {
+            self.items[index].available = false;
+            self.recalculate_probabilities()?;
+        }
+        Ok(())
+    }
+
+    // Older layouts hold stale bytes where the beneficiary would be
+    pub fn beneficiary(&self) -> Option<Pubkey> {
+        if self.version >= Self::VERSION_BENEFICIARY {
+            self.beneficiary
+        } else {
+            None
+        }
+    }
+
+    // Older layouts hold stale bytes where the operator would be
+    pub fn spin_operator(&self) -> Option<Pubkey> {
+        if self.version >= Self::VERSION_SPIN_OPERATOR {
+            self.spin_operator
+        } else {
+            None
+        }
+    }
+
+    // Older layouts hold stale bytes where the end time would be
+    pub fn end_time(&self) -> Option<i64> {
+        if self.version >= Self::VERSION_ENDS_AT {
+            self.ends_at
+        } else {
+            None
+        }
+    }
+
+    pub fn has_ended(&self, now: i64) -> bool {
+        self.end_time().is_some_and(|ends_at| now > ends_at)
+    }
+
+    pub fn check_open(&self, now: i64) -> Result<()> {
+        require!(!self.has_ended(now), ErrorCode::PoolEnded);
+        Ok(())
+    }
+
+    // Tickets bought before the end still get the claim window to spin and
+    // claim before crank_expire_pool may retire the pool
+    pub fn can_retire(&self, now: i64) -> bool {
+        let grace = self.claim_deadline_seconds.unwrap_or(0) as i64;
+        self.end_time()
+            .is_some_and(|ends_at| now > ends_at.saturating_add(grace))
+    }
+
+    // Moves the crank bounty out of the authority's revenue, as much of it as
+    // the revenue and the vault's unreserved balance cover
+    pub fn take_crank_bounty(
+        &mut self,
+        vault_balance: u64,
+        rent_exempt_minimum: u64,
+    ) -> Result<u64> {
+        let spare = vault_balance.saturating_sub(self.reserved_balance(rent_exempt_minimum));
+        let bounty = Self::CRANK_BOUNTY_LAMPORTS.min(self.total_funds).min(spare);
+        self.total_funds -= bounty;
+        self.total_withdrawn = self
+            .total_withdrawn
+            .checked_add(bounty)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(bounty)
+    }
+
+    // Moves the achievement bonus out of the authority's revenue. Unlike the
+    // crank bounty it is all or nothing, so an unpaid bonus can be claimed
+    // again once the revenue covers it.
+    pub fn take_achievement_bonus(
+        &mut self,
+        vault_balance: u64,
+        rent_exempt_minimum: u64,
+    ) -> Result<u64> {
+        let bonus = self.achievement_bonus();
+        let spare = vault_balance.saturating_sub(self.reserved_balance(rent_exempt_minimum));
+        require!(
+            bonus <= self.total_funds && bonus <= spare,
+            ErrorCode::InsufficientVaultFunds
+        );
+        self.total_funds -= bonus;
+        self.total_withdrawn = self
+            .total_withdrawn
+            .checked_add(bonus)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(bonus)
+    }
+
+    pub fn rebuild_cumulative_table(&mut self) {
+        self.cum_probability = [0; Self::MAX_ACCOUNT_ITEMS];
+        let mut total = 0u32;
+        for (cumulative, item) in self.cum_probability.iter_mut().zip(&self.items) {
+            if item.available {
+                total = total.saturating_add(item.probability);
+            }
+            *cumulative = total;
+        }
+    }
+
+    // Turns a drawn item into a ticket reward and counts the win on the item.
+    // The jackpot item takes the whole accumulated pot with it, returned
+    // alongside so the caller can report it.
+    pub fn award_item(&mut self, index: usize) -> Result<(WonItem, u64)> {
+        let item = &mut self.items[index];
+        item.times_won = item
+            .times_won
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let jackpot_payout = if item.jackpot {
+            std::mem::take(&mut self.jackpot_accumulated)
+        } else {
+            0
+        };
+        let won_item = WonItem {
+            item_id: item.item_id,
+            price: item
+                .price
+                .checked_add(jackpot_payout)
+                .ok_or(ErrorCode::MathOverflow)?,
+            item_kind: item.item_kind,
+        };
+
+        if won_item.item_kind == ItemKind::Token {
+            self.reserve_token_prize(won_item.item_id)?;
+            self.retire_if_depleted(index)?;
+        }
+
+        Ok((won_item, jackpot_payout))
+    }
+
+    // Puts a discarded jackpot win's pot back for the next winner. The item
+    // keeps its win count, since the draw itself still happened.
+    pub fn return_award(&mut self, won_item: &WonItem) -> Result<()> {
+        if won_item.item_kind == ItemKind::Token {
+            return self.return_token_prize(won_item.item_id);
+        }
+        let Some(item) = self
+            .item_position(won_item.item_id)
+            .map(|position| &self.items[position])
+            .filter(|item| item.jackpot)
+        else {
+            return Ok(());
+        };
+
+        self.jackpot_accumulated = self
+            .jackpot_accumulated
+            .checked_add(won_item.price.saturating_sub(item.price))
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Reroll fees go to the revenue vault in full
+    pub fn record_reroll_fee(&mut self, fee: u64) -> Result<()> {
+        self.total_funds = self
+            .total_funds
+            .checked_add(fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_ticket_revenue = self
+            .total_ticket_revenue
+            .checked_add(fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Largest value a gambled reward can be doubled up to
+    pub fn gamble_cap(&self) -> u64 {
+        self.gamble_payout_cap
+            .unwrap_or_else(|| self.items.iter().map(|item| item.price).max().unwrap_or(0))
+    }
+
+    // Token-gated pools require the user to hold the gate token; returns
+    // whether a gate was checked so purchases can report it
+    pub fn check_token_gate(
+        &self,
+        token_account: Option<&TokenAccount>,
+        metadata: Option<&UncheckedAccount>,
+        user: &Pubkey,
+    ) -> Result<bool> {
+        if self.gate_mint.is_none() && self.gate_collection.is_none() {
+            return Ok(false);
+        }
+
+        let token_account = token_account.ok_or(ErrorCode::TokenGateNotMet)?;
+        require!(token_account.owner == *user, ErrorCode::TokenGateNotMet);
+
+        if let Some(gate_mint) = self.gate_mint {
+            require!(
+                token_account.mint == gate_mint && token_account.amount >= self.gate_min_amount,
+                ErrorCode::TokenGateNotMet
+            );
+        }
+
+        if let Some(gate_collection) = self.gate_collection {
+            let metadata = metadata.ok_or(ErrorCode::TokenGateNotMet)?;
+            require!(
+                *metadata.owner == TOKEN_METADATA_PROGRAM_ID,
+                ErrorCode::TokenGateNotMet
+            );
+
+            let data = metadata.try_borrow_data()?;
+            let (mint, collection) =
+                read_verified_collection(&data).ok_or(ErrorCode::TokenGateNotMet)?;
+            require!(
+                mint == token_account.mint
+                    && collection == gate_collection
+                    && token_account.amount > 0,
+                ErrorCode::TokenGateNotMet
+            );
+        }
+
+        Ok(true)
+    }
+
+    // Second vault seed: the pool key for current pools, the company name for legacy ones
+    pub fn vault_seed(&self, pool_key: &Pubkey) -> Vec<u8> {
+        if self.pool_version >= 1 {
+            pool_key.to_bytes().to_vec()
+        } else {
+            self.company_name.as_bytes().to_vec()
+        }
+    }
+
+    pub fn distribution_report(&self) -> Result<Vec<DistributionReportEntry>> {
+        let total_spins: u64 = self.items.iter().map(|item| item.times_won as u64).sum();
+
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                // No spins yet reads as 0 realized frequency
+                let realized_bps = (item.times_won as u64)
+                    .checked_mul(10000)
+                    .ok_or(ErrorCode::MathOverflow)?
+                    .checked_div(total_spins)
+                    .unwrap_or(0);
+
+                Ok(DistributionReportEntry {
+                    item_index: index as u32,
+                    configured_bps: item.probability,
+                    times_won: item.times_won,
+                    realized_bps: realized_bps as u32,
+                    total_spins,
+                })
+            })
+            .collect()
+    }
+
+    // Average payout per spin: sum(price * probability) / 10000 over winning
+    // spins plus the consolation over losing ones, rounded down
+    pub fn expected_value(&self) -> Result<u64> {
+        let weighted_total = self.items.iter().try_fold(0u128, |total, item| {
+            (item.price as u128)
+                .checked_mul(item.probability as u128)
+                .and_then(|weighted| total.checked_add(weighted))
+        });
+        let win_bps = 10000 - self.no_win_bps as u128;
+        let expected_value = weighted_total
+            .and_then(|total| total.checked_mul(win_bps))
+            .and_then(|total| {
+                total.checked_add(
+                    (self.consolation_lamports as u128) * (self.no_win_bps as u128) * 10000,
+                )
+            })
+            .ok_or(ErrorCode::MathOverflow)?
+            / 100_000_000;
+        u64::try_from(expected_value).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    // What winning the item at `index` takes on average, from the stored
+    // table and counting the spins that win nothing. None for an item that
+    // can't be won.
+    pub fn item_profitability(&self, index: usize) -> Option<ItemProfitability> {
+        let item = self.items.get(index)?;
+        let chance = spin_chance(item.probability, self.no_win_bps);
+        let expected_spins = expected_spins(chance)?;
+        let expected_cost = self.ticket_price as u128 * ODDS_SCALE as u128 / chance as u128;
+        let expected_cost = u64::try_from(expected_cost).unwrap_or(u64::MAX);
+
+        let profit = item.price as i128 - expected_cost as i128;
+        let profit_bps = (profit * 10000)
+            .checked_div(expected_cost as i128)
+            .unwrap_or(0)
+            .clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        Some(ItemProfitability {
+            expected_spins,
+            expected_cost,
+            profit,
+            profit_bps,
+        })
+    }
+
+    pub fn probability_bounds(&self) -> ProbabilityBounds {
+        ProbabilityBounds::new(self.min_probability_bps, self.max_probability_bps)
+    }
+
+    // Recompute probabilities over the available items only. Retired items are
+    // pinned to 0 so the table keeps summing to 10000, while an available
+    // item that rounds to 0 fails the whole change: a price edit, a re-listed
+    // item or a new ticket price can't quietly make a prize unwinnable.
+    pub fn recalculate_probabilities(&mut self) -> Result<()> {
+        let available_items = self.items.iter().filter(|item| item.available);
+        let manual_weights: Vec<u32> = available_items
+            .clone()
+            .map(|item| item.manual_weight)
+            .collect();
+
+        require!(!manual_weights.is_empty(), ErrorCode::NoAvailableItems);
+
+        let probabilities = calculate_item_probabilities_with_weighting(
+            available_items.map(|item| item.price),
+            self.ticket_price,
+            self.weighting,
+            &manual_weights,
+            self.probability_bounds(),
+        )?;
+
+        let mut probabilities = probabilities.into_iter();
+        for item in self.items.iter_mut() {
+            item.probability = if item.available {
+                probabilities.next().unwrap_or(0)
+            } else {
+                0
+            };
+        }
+        check_all_winnable(
+            self.items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.available)
+                .map(|(index, item)| (index, item.probability)),
+        )?;
+        self.rebuild_cumulative_table();
+
+        Ok(())
+    }
+}
+
+// Zero-copy pool for high-volume drops. Purchases and spins touch a handful
+// of fields in place instead of Borsh round-tripping every item, at the cost
+// of a fixed item table: SOL prizes only, set once at creation.
+#[account(zero_copy)]
+pub struct CompanyPoolV2 {
+    pub authority: Pubkey,
+    pub ticket_price: u64,
+    pub total_tickets_sold: u64,
+    pub total_funds: u64, // Withdrawable revenue in the pool vault
+    pub outstanding_liabilities: u64,
+    pub total_rewards_paid: u64,
+    pub total_withdrawn: u64,
+    pub vault_rent_reserve: u64,
+    pub created_at: i64,
+    pub item_count: u32,
+    pub prize_allocation_bps: u16,
+    pub no_win_bps: u16,
+    pub version: u8,
+    pub active: u8,
+    pub pool_bump: u8,
+    pub vault_bump: u8,
+    pub prize_vault_bump: u8,
+    pub pool_flags: u8, // PoolFlag bits, as on CompanyPool
+    pub allow_cpi_purchases: u8,
+    pub _padding: [u8; 1],
+    pub company_name: [u8; 64], // Zero-padded UTF-8, see fixed_text
+    pub company_image: [u8; 200],
+    pub items: [ItemSlot; CompanyPoolV2::MAX_ITEMS],
+    pub event_seq: u64, // Number of the pool's latest event, as on CompanyPool
+}
+
+impl CompanyPoolV2 {
+    // Account layout. Version 1 is the same data without the event counter,
+    // 8 bytes shorter, and has to go through migrate_pool_v2 to load.
+    pub const VERSION: u8 = 2;
+
+    pub const MAX_ITEMS: usize = 10;
+
+    pub fn is_active(&self) -> bool {
+        self.active != 0
+    }
+
+    // The filled slots; item ids are slot positions, since slots never move
+    pub fn has_flag(&self, flag: PoolFlag) -> bool {
+        self.pool_flags & flag.bit() != 0
+    }
+
+    pub fn check_not_frozen(&self) -> Result<()> {
+        require!(!self.has_flag(PoolFlag::Frozen), ErrorCode::PoolFrozen);
+        Ok(())
+    }
+
+    pub fn check_purchase_caller(&self) -> Result<()> {
+        check_purchase_caller(self.allow_cpi_purchases != 0)
+    }
+
+    pub fn next_event_seq(&mut self) -> u64 {
+        self.event_seq = self.event_seq.saturating_add(1);
+        self.event_seq
+    }
+
+    pub fn items(&self) -> &[ItemSlot] {
+        &self.items[..self.item_count as usize]
+    }
+
+    // Same draw as CompanyPool::draw without tiers: None for a losing spin,
+    // otherwise the won slot
+    pub fn draw(&self, random_seed: u64) -> Result<Option<usize>> {
+        if random_seed.rotate_right(32) % 10000 < self.no_win_bps as u64 {
+            return Ok(None);
+        }
+
+        let probabilities: Vec<u32> = self.items().iter().map(|item| item.probability).collect();
+        let winning_index = select_winning_item_index(&probabilities, random_seed)
+            .ok_or(ErrorCode::ProbabilitySelectionFailed)?;
+        Ok(Some(winning_index))
+    }
+
+    pub fn draw_table(&self) -> DrawTable {
+        DrawTable {
+            no_win_bps: self.no_win_bps,
+            weights: self.items().iter().map(|item| item.probability).collect(),
+        }
+    }
+
+    pub fn expected_value(&self) -> Result<u64> {
+        let weighted_total = self.items().iter().try_fold(0u128, |total, item| {
+            (item.price as u128)
+                .checked_mul(item.probability as u128)
+                .and_then(|weighted| total.checked_add(weighted))
+        });
+        let expected_value = weighted_total
+            .and_then(|total| total.checked_mul(10000 - self.no_win_bps as u128))
+            .ok_or(ErrorCode::MathOverflow)?
+            / 100_000_000;
+        u64::try_from(expected_value).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
+
+#[zero_copy]
+pub struct ItemSlot {
+    pub price: u64,
+    pub probability: u32,
+    pub times_won: u32,
+    pub name: [u8; 64],
+    pub image: [u8; 200],
+}
+
+// Purchases from inside another program's instruction need the pool's opt-in
+pub fn check_purchase_caller(allow_cpi_purchases: bool) -> Result<()> {
+    require!(
+        allow_cpi_purchases || get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT,
+        ErrorCode::CpiPurchasesDisabled
+    );
+    Ok(())
+}
+
+// Sends a participating pool's mega jackpot share of a sale from the payer
+// to the jackpot vault. Returns the share, which the caller takes off the
+// revenue transfer and books with record_mega_jackpot_contribution.
+pub fn pay_mega_jackpot_share<'info>(
+    company_pool: &CompanyPool,
+    mega_jackpot: Option<&mut Account<'info, MegaJackpot>>,
+    mega_jackpot_vault: Option<&SystemAccount<'info>>,
+    payer: AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    sale_price: u64,
+) -> Result<u64> {
+    if !company_pool.joins_mega_jackpot() {
+        return Ok(0);
+    }
+    let (Some(mega_jackpot), Some(vault)) = (mega_jackpot, mega_jackpot_vault) else {
+        return err!(ErrorCode::MegaJackpotRequired);
+    };
+
+    let share = mega_jackpot.contribution(sale_price);
+    if share > 0 {
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: payer,
+            to: vault.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+        anchor_lang::system_program::transfer(cpi_context, share)?;
+        mega_jackpot.record_contribution(share)?;
+    }
+    Ok(share)
+}
+
+// Empties a retired pool's vaults into its authority, for close_pool and
+// archive_pool. Returns the rent reserve and the funds paid out.
+pub fn drain_pool_vaults<'info>(
+    company_pool: &Account<'info, CompanyPool>,
+    pool_vault: &SystemAccount<'info>,
+    prize_vault: Option<&SystemAccount<'info>>,
+    authority: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<(u64, u64)> {
+    let prize_vault = if company_pool.has_prize_vault() {
+        Some(prize_vault.ok_or(ErrorCode::PrizeVaultRequired)?)
+    } else {
+        None
+    };
+
+    let company_pool_key = company_pool.key();
+    let vault_seed = company_pool.vault_seed(&company_pool_key);
+    let vault_seeds: &[&[u8]] = &[
+        b"pool_vault",
+        vault_seed.as_slice(),
+        &[company_pool.vault_bump],
+    ];
+    let prize_vault_seeds: &[&[u8]] = &[
+        b"prize_vault",
+        company_pool_key.as_ref(),
+        &[company_pool.prize_vault_bump],
+    ];
+    let rent_reserve = company_pool.rent_reserve()?;
+    let mut rent_returned = 0u64;
+    let mut funds_returned = 0u64;
+    for (vault, seeds) in [
+        (Some(pool_vault), vault_seeds),
+        (prize_vault, prize_vault_seeds),
+    ] {
+        let Some(vault) = vault else {
+            continue;
+        };
+        let amount = vault.lamports();
+        if amount == 0 {
+            continue;
+        }
+        let signer_seeds = &[seeds];
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: vault.to_account_info(),
+            to: authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        let rent = amount.min(rent_reserve);
+        rent_returned = rent_returned
+            .checked_add(rent)
+            .ok_or(ErrorCode::MathOverflow)?;
+        funds_returned = funds_returned
+            .checked_add(amount - rent)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    Ok((rent_returned, funds_returned))
+}
+
+// next_event_seq for instructions that don't load the pool themselves, e.g.
+// for a ticket's pool, which may be in either layout. A closed pool, or one
+// in a layout without the counter, numbers the event 0.
+pub fn next_event_seq_of(pool: &AccountInfo) -> Result<u64> {
+    if pool.owner != &crate::ID {
+        return Ok(0);
+    }
+    let mut data = pool.try_borrow_mut_data()?;
+    if data.starts_with(CompanyPoolV2::DISCRIMINATOR) {
+        if data.len() != 8 + std::mem::size_of::<CompanyPoolV2>() {
+            return Ok(0);
+        }
+        return Ok(bytemuck::from_bytes_mut::<CompanyPoolV2>(&mut data[8..]).next_event_seq());
+    }
+    if data.len() != 8 + CompanyPool::INIT_SPACE {
+        return Ok(0);
+    }
+    let mut company_pool = CompanyPool::try_deserialize(&mut &data[..])?;
+    let event_seq = company_pool.next_event_seq();
+    let mut writer: &mut [u8] = &mut data;
+    company_pool.try_serialize(&mut writer)?;
+    Ok(event_seq)
+}
+
+// Copies `text` into a zero-padded field. Callers validate the length first.
+pub fn fixed_text<const N: usize>(text: &str) -> [u8; N] {
+    let mut field = [0u8; N];
+    let len = text.len().min(N);
+    field[..len].copy_from_slice(&text.as_bytes()[..len]);
+    field
+}
+
+// Reads a fixed_text field back
+pub fn text_from_fixed(field: &[u8]) -> String {
+    let len = field
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |last| last + 1);
+    String::from_utf8_lossy(&field[..len]).into_owned()
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserTicket {
+    pub version: u8, // Account layout, see UserTicket::VERSION
+    pub owner: Pubkey,
+    pub company_pool: Pubkey,
+    pub purchased_at: i64,
+    pub used: bool,
+    pub ticket_id: u64,
+    pub won_item: Option<WonItem>, // Store the item they won
+    pub reward_claimed: bool, // Track if reward has been claimed
+    pub gambled: bool,
+    pub traded_in: bool,
+    pub spun_at: i64,
+    pub reward_expired: bool, // Set when the authority reclaims an expired reward
+    pub reward_nft_mint: Option<Pubkey>,
+    pub fulfillment_pending: bool, // Physical prize claimed, awaiting shipment
+    pub shipping_info_hash: Option<[u8; 32]>, // Hash of off-chain shipping details
+    pub price_paid: u64,           // Ticket price at purchase, 0 for free tickets
+    pub consolation_amount: u64,   // Owed for a losing spin when the pool pays consolations
+    pub rerolled: bool,
+    pub forced_min_tier: Option<ItemTier>, // Bundle guarantee: only draws this tier or better
+    pub purchased_slot: u64,
+    pub spun_slot: u64, // Slot of the latest draw: the spin, a reroll or a gamble
+    pub external_id: Option<[u8; 32]>, // Opaque order reference set by buy_ticket
+    pub claimed_so_far: u64, // Paid out of a vesting reward, upfront tranche included
+    pub vesting_start: i64, // When a vesting reward was claimed, 0 if it never vested
+    pub serial: u32,    // Which win of the item this was, from 1; 0 when none
+    pub spin_pending: bool, // Draw requested but not landed yet, see TicketState
+    pub draw_record: Option<DrawRecord>, // Inputs of the latest draw, for verify_spin
+    pub mega_jackpot_won: u64, // Owed from the mega jackpot until claim_mega_jackpot
+    pub insured: bool,  // Bought with the pool's SpinInsurance
+    pub insurance_claimed: bool,
+}
+
+// What a spin or reroll drew with, enough for verify_spin to replay it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct DrawRecord {
+    pub random_seed: u64,
+    pub table_hash: [u8; 32], // DrawTable::hash of the weights drawn from
+    pub drawn_index: Option<u32>, // Position of the won item, None for a losing spin
+}
+
+impl DrawRecord {
+    pub fn new(random_seed: u64, table: &DrawTable, drawn: Option<usize>) -> Self {
+        DrawRecord {
+            random_seed,
+            table_hash: table.hash(),
+            drawn_index: drawn.map(|index| index as u32),
+        }
+    }
+
+    // The supplied table has to be the one drawn from, and replaying it has
+    // to land where the draw did
+    pub fn verify(&self, table: &DrawTable) -> Result<()> {
+        require!(
+            table.hash() == self.table_hash
+                && table.replay(self.random_seed).map(|index| index as u32) == self.drawn_index,
+            ErrorCode::SpinVerificationFailed
+        );
+        Ok(())
+    }
+}
+
+// Where a ticket is in its life, read off its flags by UserTicket::state.
+// UserTicket::advance is the only place it moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TicketState {
+    Unspun,
+    // Used up by a draw that lands in a later instruction. The pseudo-random
+    // spin draws in the same instruction and goes straight to Spun.
+    SpinPending,
+    Spun,
+    // Settled: claimed, traded in, shipped, paid out during the spin or voided
+    Claimed,
+}
+
+impl UserTicket {
+    // Account layout. Each version appends to the one before it:
+    //
+    //   version  adds
+    //   0        the original layout, copying the won item's text
+    //   1        the slimmed-down won item, still without a version byte
+    //   2        the leading version byte
+    //   3        purchase and spin slots
+    //   4        external id
+    //   5        vesting progress
+    //   6        serial number
+    //   7        pending-spin flag
+    //   8        draw record
+    //   9        mega jackpot win
+    //   10       insurance flags
+    pub const VERSION: u8 = 10;
+    pub const VERSION_SLOTS: u8 = 3;
+    pub const VERSION_EXTERNAL_ID: u8 = 4;
+    pub const VERSION_VESTING: u8 = 5;
+    pub const VERSION_SERIAL: u8 = 6;
+    pub const VERSION_SPIN_PENDING: u8 = 7;
+    pub const VERSION_DRAW_RECORD: u8 = 8;
+    pub const VERSION_MEGA_JACKPOT: u8 = 9;
+    pub const VERSION_INSURANCE: u8 = 10;
+
+    // How long a requested draw may stay outstanding before anyone can
+    // cancel it with cancel_stuck_spin
+    pub const SPIN_PENDING_TIMEOUT_SECONDS: i64 = 60 * 60;
+
+    pub fn state(&self) -> TicketState {
+        if self.reward_claimed {
+            TicketState::Claimed
+        } else if self.spin_pending {
+            TicketState::SpinPending
+        } else if self.used {
+            TicketState::Spun
+        } else {
+            TicketState::Unspun
+        }
+    }
+
+    // Every state change goes through here, so an out-of-order move fails
+    // the same way whichever instruction attempts it
+    fn advance(&mut self, to: TicketState) -> Result<()> {
+        let from = self.state();
+        let allowed = matches!(
+            (from, to),
+            (
+                TicketState::Unspun,
+                TicketState::SpinPending | TicketState::Spun
+            ) | (
+                TicketState::SpinPending,
+                TicketState::Spun | TicketState::Unspun
+            ) | (TicketState::Spun, TicketState::Claimed)
+        );
+        if !allowed {
+            let error = match to {
+                TicketState::Unspun => ErrorCode::SpinNotPending,
+                TicketState::SpinPending | TicketState::Spun => ErrorCode::TicketAlreadyUsed,
+                TicketState::Claimed if from == TicketState::Claimed => {
+                    ErrorCode::RewardAlreadyClaimed
+                }
+                TicketState::Claimed => ErrorCode::TicketNotUsed,
+            };
+            return Err(error.into());
+        }
+
+        self.used = to != TicketState::Unspun;
+        self.spin_pending = to == TicketState::SpinPending;
+        self.reward_claimed = to == TicketState::Claimed;
+        Ok(())
+    }
+
+    // Unspun to SpinPending: a draw was requested and will land later
+    pub fn request_draw(&mut self, at: i64, slot: u64) -> Result<()> {
+        self.advance(TicketState::SpinPending)?;
+        self.spun_at = at;
+        self.spun_slot = slot;
+        Ok(())
+    }
+
+    // To Spun: the draw landed. `at` and `slot` date it, which for a raffle
+    // ticket is the raffle draw rather than the claim.
+    pub fn record_draw(&mut self, at: i64, slot: u64) -> Result<()> {
+        self.advance(TicketState::Spun)?;
+        self.spun_at = at;
+        self.spun_slot = slot;
+        Ok(())
+    }
+
+    // SpinPending back to Unspun, so the ticket can be spun again
+    pub fn cancel_pending_draw(&mut self) -> Result<()> {
+        self.advance(TicketState::Unspun)?;
+        self.spun_at = 0;
+        self.spun_slot = 0;
+        Ok(())
+    }
+
+    pub fn mark_claimed(&mut self) -> Result<()> {
+        self.advance(TicketState::Claimed)
+    }
+
+    // Whether the ticket's draw has landed, whatever became of the reward
+    pub fn is_drawn(&self) -> bool {
+        matches!(self.state(), TicketState::Spun | TicketState::Claimed)
+    }
+
+    // Rewards are settled from Spun only; a pending draw has nothing to claim
+    pub fn check_spun(&self) -> Result<()> {
+        match self.state() {
+            TicketState::Spun => Ok(()),
+            TicketState::Claimed => err!(ErrorCode::RewardAlreadyClaimed),
+            TicketState::Unspun | TicketState::SpinPending => err!(ErrorCode::TicketNotUsed),
+        }
+    }
+
+    // Claimed with nothing left to vest or ship, so closing the ticket
+    // loses its owner nothing
+    pub fn is_settled(&self) -> bool {
+        self.state() == TicketState::Claimed
+            && !self.fulfillment_pending
+            && self.claimed_so_far >= self.vested_total()
+            && self.mega_jackpot_won == 0
+    }
+
+    pub fn is_spin_stuck(&self, now: i64) -> bool {
+        let deadline = self
+            .spun_at
+            .saturating_add(Self::SPIN_PENDING_TIMEOUT_SECONDS);
+        self.state() == TicketState::SpinPending && now >= deadline
+    }
+
+    // The won item's serial number, e.g. 3 for its third win. Raffle prizes
+    // and tickets that won nothing have none.
+    pub fn serial(&self) -> Option<u32> {
+        (self.serial > 0).then_some(self.serial)
+    }
+
+    // Tickets spun before the record existed, and raffle tickets, which
+    // are never spun, have nothing to replay
+    pub fn draw_record(&self) -> Option<&DrawRecord> {
+        if self.version < Self::VERSION_DRAW_RECORD {
+            return None;
+        }
+        self.draw_record.as_ref()
+    }
+
+    // Fill in a freshly created ticket and take the next ticket id from the pool
+    pub fn issue(
+        &mut self,
+        owner: Pubkey,
+        company_pool: &mut Account<CompanyPool>,
+        price_paid: u64,
+        clock: &Clock,
+    ) -> Result<()> {
+        self.fill(
+            owner,
+            company_pool.key(),
+            company_pool.total_tickets_sold,
+            price_paid,
+            clock,
+        );
+
+        company_pool.total_tickets_sold = company_pool
+            .total_tickets_sold
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    // Fill in a freshly created, unspun ticket. The caller advances the
+    // pool's ticket counter.
+    pub fn fill(
+        &mut self,
+        owner: Pubkey,
+        company_pool: Pubkey,
+        ticket_id: u64,
+        price_paid: u64,
+        clock: &Clock,
+    ) {
+        self.version = Self::VERSION;
+        self.owner = owner;
+        self.company_pool = company_pool;
+        self.purchased_at = clock.unix_timestamp;
+        self.used = false;
+        self.ticket_id = ticket_id;
+        self.won_item = None; // Initialize as no item won yet
+        self.reward_claimed = false; // Initialize as not claimed
+        self.gambled = false;
+        self.traded_in = false;
+        self.spun_at = 0;
+        self.reward_expired = false;
+        self.reward_nft_mint = None;
+        self.fulfillment_pending = false;
+        self.shipping_info_hash = None;
+        self.price_paid = price_paid;
+        self.consolation_amount = 0;
+        self.rerolled = false;
+        self.forced_min_tier = None;
+        self.purchased_slot = clock.slot;
+        self.spun_slot = 0;
+        self.external_id = None;
+        self.claimed_so_far = 0;
+        self.vesting_start = 0;
+        self.serial = 0;
+        self.spin_pending = false;
+        self.draw_record = None;
+        self.mega_jackpot_won = 0;
+        self.insured = false;
+        self.insurance_claimed = false;
+    }
+
+    // Marks a reward left unclaimed past the pool's claim deadline as expired
+    // and releases its liability, returning its value. Unclaimed consolations
+    // expire like any other reward.
+    pub fn expire_reward(&mut self, company_pool: &mut CompanyPool, now: i64) -> Result<u64> {
+        require!(self.is_drawn(), ErrorCode::TicketNotUsed);
+        require!(!self.reward_claimed, ErrorCode::RewardAlreadyClaimed);
+        require!(!self.reward_expired, ErrorCode::RewardAlreadyReclaimed);
+        // A pending shipment means the winner claimed in time
+        require!(!self.fulfillment_pending, ErrorCode::FulfillmentPending);
+        require!(
+            company_pool.is_reward_expired(self.spun_at, now),
+            ErrorCode::RewardNotExpired
+        );
+
+        let item_value = match self.won_item.as_ref() {
+            Some(won_item) => won_item.price,
+            None if self.consolation_amount > 0 => self.consolation_amount,
+            None => return err!(ErrorCode::NoRewardToClaim),
+        };
+
+        company_pool.release_liability(self.vault_liability());
+        if let Some(won_item) = self
+            .won_item
+            .as_ref()
+            .filter(|item| item.item_kind == ItemKind::Token)
+        {
+            company_pool.return_token_prize(won_item.item_id)?;
+        }
+        self.reward_expired = true;
+        Ok(item_value)
+    }
+
+    // A spin landing in the purchase's slot could share its transaction, and
+    // a transaction can revert a draw it doesn't like. Later slots can't.
+    pub fn check_spin_slot(&self, slot: u64) -> Result<()> {
+        require!(slot > self.purchased_slot, ErrorCode::SpinTooSoon);
+        Ok(())
+    }
+
+    // Like check_spin_slot: the reward is only paid out once its draw can no
+    // longer be reverted.
+    pub fn check_draw_settled(&self, slot: u64) -> Result<()> {
+        require!(slot > self.spun_slot, ErrorCode::ClaimTooSoon);
+        Ok(())
+    }
+
+    // Whether the drawn spin falls under the pool's insurance, claimed or not
+    pub fn is_insurance_triggered(&self, company_pool: &CompanyPool) -> bool {
+        self.insured
+            && self.is_drawn()
+            && company_pool
+                .insurance()
+                .is_some_and(|insurance| insurance.covers(self.won_item.as_ref()))
+    }
+
+    // Settles the ticket's insurance and returns the terms it was settled
+    // under and whether the prize was given up for it. Insurance that
+    // replaces the prize takes back whatever the spin won, so that prize has
+    // to be unclaimed; an expired one is already gone.
+    pub fn take_insurance(
+        &mut self,
+        company_pool: &mut CompanyPool,
+        slot: u64,
+    ) -> Result<(SpinInsurance, bool)> {
+        require!(self.insured, ErrorCode::TicketNotInsured);
+        require!(!self.insurance_claimed, ErrorCode::InsuranceAlreadyClaimed);
+        require!(self.is_drawn(), ErrorCode::TicketNotUsed);
+        self.check_draw_settled(slot)?;
+        let insurance = company_pool
+            .insurance()
+            .ok_or(ErrorCode::InsuranceNotOffered)?;
+        require!(
+            insurance.covers(self.won_item.as_ref()),
+            ErrorCode::InsuranceNotTriggered
+        );
+
+        let prize_forfeited = insurance.replaces_prize && !self.reward_expired;
+        if prize_forfeited {
+            require!(!self.fulfillment_pending, ErrorCode::FulfillmentPending);
+            self.check_spun()?;
+            company_pool.release_liability(self.vault_liability());
+            if let Some(won_item) = self
+                .won_item
+                .as_ref()
+                .filter(|item| item.item_kind == ItemKind::Token)
+            {
+                company_pool.return_token_prize(won_item.item_id)?;
+            }
+            self.mark_claimed()?;
+        }
+        self.insurance_claimed = true;
+        Ok((insurance, prize_forfeited))
+    }
+
+    // Lamports the vault owes this ticket: its SOL prize or its consolation
+    pub fn vault_liability(&self) -> u64 {
+        self.won_item
+            .as_ref()
+            .map_or(self.consolation_amount, WonItem::vault_liability)
+    }
+
+    // Takes back the spin's result, releasing what the vault owed for it and
+    // returning a won item to the pool. An unclaimed mega jackpot win goes
+    // back into the pot, so the jackpot is needed for one. Returns the item,
+    // None for a loss.
+    pub fn discard_result(
+        &mut self,
+        company_pool: &mut CompanyPool,
+        mega_jackpot: Option<&mut MegaJackpot>,
+    ) -> Result<Option<WonItem>> {
+        if self.mega_jackpot_won > 0 {
+            let mega_jackpot = mega_jackpot.ok_or(ErrorCode::MegaJackpotRequired)?;
+            mega_jackpot.return_win(self.mega_jackpot_won)?;
+            self.mega_jackpot_won = 0;
+        }
+        company_pool.release_liability(self.vault_liability());
+        let old_item = self.won_item.take();
+        if let Some(old_item) = old_item.as_ref() {
+            company_pool.return_award(old_item)?;
+        }
+        self.consolation_amount = 0;
+        Ok(old_item)
+    }
+
+    pub fn start_vesting(&mut self, upfront: u64, now: i64) {
+        self.claimed_so_far = upfront;
+        self.vesting_start = now;
+    }
+
+    // The full value of a vesting reward. It is the ticket's reward, so only
+    // the schedule's start and progress need storing.
+    pub fn vested_total(&self) -> u64 {
+        if self.vesting_start == 0 {
+            return 0;
+        }
+        self.vault_liability()
+    }
+
+    // Marks whatever has unlocked since the last claim as paid and returns it
+    pub fn release_vested(&mut self, company_pool: &CompanyPool, now: i64) -> Result<u64> {
+        let unlocked = company_pool.vested_amount(self.vested_total(), self.vesting_start, now);
+        let amount = unlocked.saturating_sub(self.claimed_so_far);
+        require!(amount > 0, ErrorCode::NoVestedReward);
+        self.claimed_so_far = unlocked;
+        Ok(amount)
+    }
+}
+
+// Version 0 UserTicket, stored before WonItem was slimmed down. Same
+// discriminator, told apart from later tickets by its larger size.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct LegacyUserTicket {
+    pub owner: Pubkey,
+    pub company_pool: Pubkey,
+    pub purchased_at: i64,
+    pub used: bool,
+    pub ticket_id: u64,
+    pub won_item: Option<LegacyWonItem>,
+    pub reward_claimed: bool,
+    pub gambled: bool,
+    pub traded_in: bool,
+    pub spun_at: i64,
+    pub reward_expired: bool,
+    pub reward_nft_mint: Option<Pubkey>,
+    pub fulfillment_pending: bool,
+    pub shipping_info_hash: Option<[u8; 32]>,
+    pub price_paid: u64,
+    pub consolation_amount: u64,
+    pub rerolled: bool,
+    pub forced_min_tier: Option<ItemTier>,
+}
+
+impl From<LegacyUserTicket> for UserTicket {
+    fn from(legacy: LegacyUserTicket) -> Self {
+        UserTicket {
+            version: 0,
+            owner: legacy.owner,
+            company_pool: legacy.company_pool,
+            purchased_at: legacy.purchased_at,
+            used: legacy.used,
+            ticket_id: legacy.ticket_id,
+            won_item: legacy.won_item.map(WonItem::from),
+            reward_claimed: legacy.reward_claimed,
+            gambled: legacy.gambled,
+            traded_in: legacy.traded_in,
+            spun_at: legacy.spun_at,
+            reward_expired: legacy.reward_expired,
+            reward_nft_mint: legacy.reward_nft_mint,
+            fulfillment_pending: legacy.fulfillment_pending,
+            shipping_info_hash: legacy.shipping_info_hash,
+            price_paid: legacy.price_paid,
+            consolation_amount: legacy.consolation_amount,
+            rerolled: legacy.rerolled,
+            forced_min_tier: legacy.forced_min_tier,
+            purchased_slot: 0,
+            spun_slot: 0,
+            external_id: None,
+            claimed_so_far: 0,
+            vesting_start: 0,
+            serial: 0,
+            spin_pending: false,
+            draw_record: None,
+            mega_jackpot_won: 0,
+            insured: false,
+            insurance_claimed: false,
+        }
+    }
+}
+
+// Per-user purchase tracking for limits and cooldowns
+#[account]
+#[derive(InitSpace)]
+pub struct UserPoolState {
+    pub version: u8,
+    pub tickets_bought: u32,
+    pub last_purchase_at: i64,
+    pub bump: u8,
+    pub loyalty_points: u64,
+    pub spin_delegate: Option<Pubkey>, // Session key allowed to spin this user's tickets
+    pub spin_delegate_expires_at: i64,
+    pub current_streak: u32,  // Consecutive UTC days with a paid purchase
+    pub last_played_day: i64, // UTC day number of the latest one
+    // Achievement bits unlocked, and those whose bonus has been paid
+    pub achievements: u64,
+    pub achievements_rewarded: u64,
+    // What achievements count, see Achievement::RULES
+    pub spins: u32,
+    pub legendary_wins: u32,
+    pub jackpot_wins: u32,
+    pub last_season: u16, // Pool season the progress above belongs to
+}
+
+impl UserPoolState {
+    // Account layout. Version 1 is every state written before the leading
+    // version byte, whose fields were appended without one; see
+    // UserPoolState::read_any_version.
+    pub const VERSION: u8 = 2;
+
+    pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+    // Days since the epoch, so every validator agrees on where a day ends
+    pub fn utc_day(unix_timestamp: i64) -> i64 {
+        unix_timestamp.div_euclid(Self::SECONDS_PER_DAY)
+    }
+
+    pub fn is_spin_delegate(&self, spinner: &Pubkey, now: i64) -> bool {
+        self.spin_delegate == Some(*spinner) && now < self.spin_delegate_expires_at
+    }
+
+    // Catches the state up with the pool's season, clearing the progress
+    // that any season started since the user was last seen reset. Runs
+    // before anything reads or adds to that progress.
+    pub fn enter_season(&mut self, company_pool: &CompanyPool) {
+        let season = company_pool.season();
+        if self.last_season >= season {
+            return;
+        }
+        if self.last_season < company_pool.points_reset_season {
+            self.loyalty_points = 0;
+        }
+        if self.last_season < company_pool.streaks_reset_season {
+            self.current_streak = 0;
+            self.last_played_day = 0;
+        }
+        if self.last_season < company_pool.achievements_reset_season {
+            self.achievements = 0;
+            self.achievements_rewarded = 0;
+            self.spins = 0;
+            self.legendary_wins = 0;
+            self.jackpot_wins = 0;
+        }
+        self.last_season = season;
+    }
+
+    // Checks the pool's limits for `count` new tickets, then records them
+    pub fn record_purchase(
+        &mut self,
+        company_pool: &CompanyPool,
+        count: u32,
+        now: i64,
+    ) -> Result<()> {
+        self.enter_season(company_pool);
+        let tickets_bought = self
+            .tickets_bought
+            .checked_add(count)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if let Some(max_tickets) = company_pool.max_tickets_per_user {
+            require!(
+                tickets_bought <= max_tickets,
+                ErrorCode::PurchaseLimitReached
+            );
+        }
+
+        if let Some(cooldown) = company_pool.purchase_cooldown_seconds {
+            if self.tickets_bought > 0 {
+                let next_allowed = self
+                    .last_purchase_at
+                    .checked_add(cooldown as i64)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                require!(now >= next_allowed, ErrorCode::PurchaseCooldownActive);
+            }
+        }
+
+        self.tickets_bought = tickets_bought;
+        self.last_purchase_at = now;
+
+        Ok(())
+    }
+
+    // Earns the pool's loyalty points for `count` paid tickets
+    pub fn credit_loyalty_points(&mut self, company_pool: &CompanyPool, count: u32) -> Result<()> {
+        if !company_pool.loyalty_enabled {
+            return Ok(());
+        }
+
+        let earned = (company_pool.loyalty_points_per_ticket as u64)
+            .checked_mul(count as u64)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.loyalty_points = self
+            .loyalty_points
+            .checked_add(earned)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    // Counts a paid purchase toward the streak: the day after the last one
+    // extends it, any later day starts over at 1 and the same day changes
+    // nothing. Reaching a milestone credits its bonus points. None when the
+    // day was already counted or the pool has no milestones.
+    pub fn record_play_day(
+        &mut self,
+        company_pool: &CompanyPool,
+        now: i64,
+    ) -> Result<Option<StreakUpdate>> {
+        if company_pool.streak_milestones().is_empty() {
+            return Ok(None);
+        }
+
+        let day = Self::utc_day(now);
+        if self.current_streak > 0 && day == self.last_played_day {
+            return Ok(None);
+        }
+        self.current_streak = if self.current_streak > 0 && day == self.last_played_day + 1 {
+            self.current_streak.saturating_add(1)
+        } else {
+            1
+        };
+        self.last_played_day = day;
+
+        let bonus_points = company_pool.streak_bonus(self.current_streak);
+        self.loyalty_points = self
+            .loyalty_points
+            .checked_add(bonus_points as u64)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(Some(StreakUpdate {
+            current_streak: self.current_streak,
+            bonus_points,
+        }))
+    }
+
+    pub fn has_achievement(&self, achievement: Achievement) -> bool {
+        self.achievements & achievement.bit() != 0
+    }
+
+    pub fn achievement_progress(&self, kind: AchievementKind) -> u32 {
+        match kind {
+            AchievementKind::Spins => self.spins,
+            AchievementKind::LegendaryWins => self.legendary_wins,
+            AchievementKind::JackpotWins => self.jackpot_wins,
+        }
+    }
+
+    // Counts a spin and what it won, then unlocks every achievement whose
+    // threshold is now met. Returns the newly unlocked ones.
+    pub fn record_spin(&mut self, won_item: Option<&PoolItem>) -> Vec<Achievement> {
+        self.spins = self.spins.saturating_add(1);
+        if let Some(item) = won_item {
+            if item.tier == ItemTier::Legendary {
+                self.legendary_wins = self.legendary_wins.saturating_add(1);
+            }
+            if item.jackpot {
+                self.jackpot_wins = self.jackpot_wins.saturating_add(1);
+            }
+        }
+
+        let mut unlocked = Vec::new();
+        for (achievement, kind, threshold) in Achievement::RULES {
+            if !self.has_achievement(achievement) && self.achievement_progress(kind) >= threshold {
+                self.achievements |= achievement.bit();
+                unlocked.push(achievement);
+            }
+        }
+        unlocked
+    }
+
+    // Records that an unlocked achievement's bonus is being paid
+    pub fn mark_achievement_rewarded(&mut self, achievement: Achievement) -> Result<()> {
+        require!(
+            self.has_achievement(achievement),
+            ErrorCode::AchievementLocked
+        );
+        require!(
+            self.achievements_rewarded & achievement.bit() == 0,
+            ErrorCode::AchievementAlreadyRewarded
+        );
+        self.achievements_rewarded |= achievement.bit();
+        Ok(())
+    }
+
+    // Burns the points for one free ticket
+    pub fn spend_loyalty_points(&mut self, company_pool: &CompanyPool) -> Result<()> {
+        require!(company_pool.loyalty_enabled, ErrorCode::LoyaltyDisabled);
+        self.enter_season(company_pool);
+
+        self.loyalty_points = self
+            .loyalty_points
+            .checked_sub(company_pool.points_per_free_ticket as u64)
+            .ok_or(ErrorCode::InsufficientLoyaltyPoints)?;
+
+        Ok(())
+    }
+}
+
+// Text for one item of an item-account pool, at [b"item", pool, item_id]
+#[account]
+#[derive(InitSpace)]
+pub struct PoolItemAccount {
+    pub company_pool: Pubkey,
+    pub item_id: u32,
+    pub bump: u8,
+    #[max_len(50)]
+    pub name: String,
+    #[max_len(200)]
+    pub image: String,
+    #[max_len(200)]
+    pub description: String,
+}
+
+impl PoolItemAccount {
+    pub fn belongs_to(&self, company_pool: &Pubkey, item_id: u32) -> bool {
+        self.company_pool == *company_pool && self.item_id == item_id
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct WhitelistEntry {
+    pub company_pool: Pubkey,
+    pub user: Pubkey,
+    pub added_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Voucher {
+    pub company_pool: Pubkey,
+    pub recipient: Pubkey,
+    pub spins_remaining: u32,
+    pub bump: u8,
+}
+
+// Sidecar to CompanyPool so the hot pool account doesn't grow with stats
+#[account]
+#[derive(InitSpace)]
+pub struct PoolStats {
+    pub company_pool: Pubkey,
+    pub total_spins: u64,
+    pub wins_per_item: [u64; 10], // Parallel to the first ten CompanyPool.items
+    pub total_rewards_paid: u64,
+    pub total_value_won: u64,
+    pub biggest_win_value: u64,
+    pub biggest_winner: Pubkey,
+    pub bump: u8,
+    pub total_trade_in_margin: u64, // Item value kept in the vault by trade-ins
+    pub wins_per_tier: [u64; ItemTier::COUNT], // Indexed by ItemTier
+    pub no_win_spins: u64,
+}
+
+impl PoolStats {
+    // Zeroes every counter for a new season
+    pub fn reset(&mut self) {
+        self.total_spins = 0;
+        self.wins_per_item = [0; 10];
+        self.total_rewards_paid = 0;
+        self.total_value_won = 0;
+        self.biggest_win_value = 0;
+        self.biggest_winner = Pubkey::default();
+        self.total_trade_in_margin = 0;
+        self.wins_per_tier = [0; ItemTier::COUNT];
+        self.no_win_spins = 0;
+    }
+
+    pub fn record_spin(
+        &mut self,
+        item_index: usize,
+        tier: ItemTier,
+        value: u64,
+        spinner: Pubkey,
+    ) -> Result<()> {
+        self.total_spins = self
+            .total_spins
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        // Item-account pools count wins past the tenth item on the item alone
+        if let Some(wins) = self.wins_per_item.get_mut(item_index) {
+            *wins = wins.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        }
+        self.wins_per_tier[tier as usize] = self.wins_per_tier[tier as usize]
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_value_won = self
+            .total_value_won
+            .checked_add(value)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if value > self.biggest_win_value {
+            self.biggest_win_value = value;
+            self.biggest_winner = spinner;
+        }
+
+        Ok(())
+    }
+
+    pub fn record_no_win(&mut self) -> Result<()> {
+        self.total_spins = self
+            .total_spins
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.no_win_spins = self
+            .no_win_spins
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+}
+
+// Totals across every pool, so a dashboard doesn't need an indexer
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalStats {
+    pub total_pools_created: u64,
+    pub total_tickets_sold: u64,
+    pub total_rewards_paid: u64,
+    pub total_platform_fees: u64, // Pool creation fees sent to the treasury
+    pub bump: u8,
+}
+
+impl GlobalStats {
+    pub fn record_pool_created(&mut self, creation_fee: u64) -> Result<()> {
+        self.total_pools_created = self
+            .total_pools_created
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_platform_fees = self
+            .total_platform_fees
+            .checked_add(creation_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_tickets_sold(&mut self, count: u64) -> Result<()> {
+        self.total_tickets_sold = self
+            .total_tickets_sold
+            .checked_add(count)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_reward_paid(&mut self, amount: u64) -> Result<()> {
+        self.total_rewards_paid = self
+            .total_rewards_paid
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+// Progressive jackpot shared by every pool that opts in at init. Their sales
+// fill it and any of their spins can win it all; the lamports sit in the
+// mega_jackpot_vault PDA.
+#[account]
+#[derive(InitSpace)]
+pub struct MegaJackpot {
+    pub contribution_bps: u16, // Share of every participating sale
+    pub hit_chance_bps: u16,   // Odds of a participating spin winning the pot
+    pub pot: u64,
+    pub owed: u64, // Won but not claimed yet, still in the vault
+    pub total_contributed: u64,
+    pub total_won: u64,
+    pub wins: u32,
+    pub vault_rent_reserve: u64,
+    pub bump: u8,
+    pub vault_bump: u8,
+}
+
+impl MegaJackpot {
+    // Pools keep at least 90% of their revenue share whatever the terms
+    pub const MAX_CONTRIBUTION_BPS: u16 = 1000;
+
+    pub fn check_terms(contribution_bps: u16, hit_chance_bps: u16) -> Result<()> {
+        require!(
+            contribution_bps <= Self::MAX_CONTRIBUTION_BPS && hit_chance_bps <= 10000,
+            ErrorCode::InvalidMegaJackpotTerms
+        );
+        Ok(())
+    }
+
+    // The jackpot's share of a sale, rounded down. Can't overflow with the
+    // share capped at 10%.
+    pub fn contribution(&self, sale_price: u64) -> u64 {
+        (sale_price as u128 * self.contribution_bps as u128 / 10000) as u64
+    }
+
+    pub fn record_contribution(&mut self, amount: u64) -> Result<()> {
+        self.pot = self
+            .pot
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_contributed = self
+            .total_contributed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Draws from its own hash of the spin's seed, so hitting the jackpot
+    // says nothing about which item the same seed drew
+    pub fn is_hit(&self, random_seed: u64) -> bool {
+        let roll = hashv(&[b"mega_jackpot", &random_seed.to_le_bytes()]).to_bytes();
+        u64::from_le_bytes(roll[..8].try_into().unwrap()) % 10000 < self.hit_chance_bps as u64
+    }
+
+    // On a hit the whole pot becomes the winner's and the next round starts
+    // from nothing. Returns the amount won; an empty pot can't be won.
+    pub fn roll(&mut self, random_seed: u64) -> Result<Option<u64>> {
+        if self.pot == 0 || !self.is_hit(random_seed) {
+            return Ok(None);
+        }
+        let amount = std::mem::take(&mut self.pot);
+        self.owed = self
+            .owed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_won = self
+            .total_won
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.wins = self.wins.saturating_add(1);
+        Ok(Some(amount))
+    }
+
+    // Undoes a win whose spin was discarded: the amount is back in the pot
+    // for the next hit and no longer counts as won
+    pub fn return_win(&mut self, amount: u64) -> Result<()> {
+        self.owed = self
+            .owed
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.pot = self
+            .pot
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_won = self.total_won.saturating_sub(amount);
+        self.wins = self.wins.saturating_sub(1);
+        Ok(())
+    }
+
+    // What the vault can pay out without dipping into its rent
+    pub fn check_payable(&self, amount: u64, vault_balance: u64) -> Result<()> {
+        require!(
+            amount <= self.owed && amount <= vault_balance.saturating_sub(self.vault_rent_reserve),
+            ErrorCode::InsufficientVaultFunds
+        );
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, PartialEq, Eq)]
+pub struct TicketShare {
+    pub owner: Pubkey,
+    pub share_bps: u16,
+}
+
+// The participants behind a ticket bought with buy_shared_ticket. The ticket
+// itself belongs to this PDA, so no single participant can trade or claim
+// it; any of them may spin it, and claim_shared_reward pays each one their
+// share of the reward.
+#[account]
+#[derive(InitSpace)]
+pub struct SharedTicket {
+    pub company_pool: Pubkey,
+    pub user_ticket: Pubkey,
+    pub payer: Pubkey,
+    #[max_len(4)]
+    pub shares: Vec<TicketShare>,
+    pub bump: u8,
+}
+
+impl SharedTicket {
+    pub const MAX_PARTICIPANTS: usize = 4;
+
+    // Two to four distinct participants, each with a share, adding up to
+    // the whole ticket
+    pub fn check_shares(shares: &[TicketShare]) -> Result<()> {
+        require!(
+            (2..=Self::MAX_PARTICIPANTS).contains(&shares.len()),
+            ErrorCode::InvalidTicketShares
+        );
+        let total: u32 = shares.iter().map(|share| share.share_bps as u32).sum();
+        require!(
+            total == 10000 && shares.iter().all(|share| share.share_bps > 0),
+            ErrorCode::InvalidTicketShares
+        );
+        for (position, share) in shares.iter().enumerate() {
+            require!(
+                shares[..position]
+                    .iter()
+                    .all(|earlier| earlier.owner != share.owner),
+                ErrorCode::InvalidTicketShares
+            );
+        }
+        Ok(())
+    }
+
+    pub fn is_participant(&self, key: &Pubkey) -> bool {
+        self.shares.iter().any(|share| share.owner == *key)
+    }
+
+    // Each participant's part of `amount` in share order, rounded down. The
+    // rounding dust goes to the first share so the parts add up to `amount`.
+    pub fn split(&self, amount: u64) -> Vec<u64> {
+        let mut parts: Vec<u64> = self
+            .shares
+            .iter()
+            .map(|share| (amount as u128 * share.share_bps as u128 / 10000) as u64)
+            .collect();
+        let dust = amount - parts.iter().sum::<u64>();
+        if let Some(first) = parts.first_mut() {
+            *first += dust;
+        }
+        parts
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct SpinHistory {
+    pub owner: Pubkey,
+    pub company_pool: Pubkey,
+    pub head: u8,  // Next slot to write
+    pub count: u8, // Number of filled slots, capped at capacity
+    pub entries: [SpinHistoryEntry; SpinHistory::CAPACITY],
+    pub bump: u8,
+}
+
+impl SpinHistory {
+    pub const CAPACITY: usize = 20;
+
+    // item_index recorded for spins that did not win an item
+    pub const NO_WIN: u32 = u32::MAX;
+
+    pub fn push(&mut self, entry: SpinHistoryEntry) {
+        self.entries[self.head as usize] = entry;
+        self.head = ((self.head as usize + 1) % Self::CAPACITY) as u8;
+        if (self.count as usize) < Self::CAPACITY {
+            self.count += 1;
+        }
+    }
+
+    pub fn ordered_entries(&self) -> Vec<SpinHistoryEntry> {
+        let count = self.count as usize;
+        let start = (self.head as usize + Self::CAPACITY - count) % Self::CAPACITY;
+        (0..count)
+            .map(|i| self.entries[(start + i) % Self::CAPACITY])
+            .collect()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct SpinHistoryEntry {
+    pub ticket_id: u64,
+    pub item_index: u32,
+    pub value: u64,
+    pub timestamp: i64,
+}
+
+// Recent item wins across the whole pool, oldest overwritten first. Entries
+// are fixed-size so the account never grows; clients resolve item names from
+// the pool's items by index.
+#[account(zero_copy)]
+pub struct WinnerRegistry {
+    pub company_pool: Pubkey,
+    pub head: u32,  // Next slot to write
+    pub count: u32, // Number of filled slots, capped at capacity
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    pub entries: [WinnerEntry; WinnerRegistry::CAPACITY],
+}
+
+impl WinnerRegistry {
+    pub const CAPACITY: usize = 100;
+
+    // Most entries that fit in the 1024 bytes of return data
+    pub const PAGE_SIZE: usize = 16;
+
+    pub fn push(&mut self, entry: WinnerEntry) {
+        self.entries[self.head as usize] = entry;
+        self.head = ((self.head as usize + 1) % Self::CAPACITY) as u32;
+        if (self.count as usize) < Self::CAPACITY {
+            self.count += 1;
+        }
+    }
+
+    pub fn recent(&self, skip: usize) -> Vec<RecentWinner> {
+        let count = self.count as usize;
+        (skip..count.min(skip.saturating_add(Self::PAGE_SIZE)))
+            .map(|i| {
+                RecentWinner::from(
+                    &self.entries[(self.head as usize + Self::CAPACITY - 1 - i) % Self::CAPACITY],
+                )
+            })
+            .collect()
+    }
+}
+
+// The pool's biggest winners by total item value claimed, largest first.
+// Entries are fixed-size so the account never grows; the empty ones, with
+// nothing won, sit at the end.
+#[account]
+#[derive(InitSpace)]
+pub struct Leaderboard {
+    pub company_pool: Pubkey,
+    pub bump: u8,
+    pub entries: [LeaderboardEntry; Leaderboard::SIZE],
+}
+
+impl Leaderboard {
+    pub const SIZE: usize = 10;
+
+    pub fn ranked(&self) -> &[LeaderboardEntry] {
+        let filled = self
+            .entries
+            .iter()
+            .take_while(|entry| entry.total_value_won > 0)
+            .count();
+        &self.entries[..filled]
+    }
+
+    // Adds `value` to the winner's total and moves them up past everyone
+    // they now beat. Someone new takes the last entry if they beat it, which
+    // evicts its holder once the board is full. Returns the winner's rank,
+    // 1 for the top spot, or None when they didn't make the board.
+    pub fn record_win(&mut self, winner: Pubkey, value: u64) -> Option<u8> {
+        let existing = self
+            .ranked()
+            .iter()
+            .position(|entry| entry.winner == winner);
+        let mut index = match existing {
+            Some(index) => {
+                let entry = &mut self.entries[index];
+                entry.total_value_won = entry.total_value_won.saturating_add(value);
+                index
+            }
+            None => {
+                let last = Self::SIZE - 1;
+                if self.entries[last].total_value_won >= value {
+                    return None;
+                }
+                self.entries[last] = LeaderboardEntry {
+                    winner,
+                    total_value_won: value,
+                };
+                last
+            }
+        };
+        while index > 0
+            && self.entries[index - 1].total_value_won < self.entries[index].total_value_won
+        {
+            self.entries.swap(index - 1, index);
+            index -= 1;
+        }
+        Some(index as u8 + 1)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct LeaderboardEntry {
+    pub winner: Pubkey,
+    pub total_value_won: u64,
+}
+
+// What archive_pool keeps of a pool it closes, at the closed pool's address
+// so tickets still pointing there lead to it
+#[account]
+#[derive(InitSpace)]
+pub struct PoolArchive {
+    pub company_pool: Pubkey,
+    pub authority: Pubkey,
+    pub name_hash: [u8; 32], // CompanyPool::name_seed of the pool's name
+    pub total_tickets_sold: u64,
+    pub total_ticket_revenue: u64,
+    pub total_rewards_paid: u64,
+    pub top_winners: [LeaderboardEntry; Leaderboard::SIZE], // The final leaderboard
+    pub created_at: i64,
+    pub closed_at: i64,
+    pub bump: u8,
+}
+
+// What start_new_season clears. Player progress is cleared lazily, the next
+// time each player's UserPoolState is used; the pool's stats and
+// leaderboard on the spot.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug, PartialEq, Eq,
+)]
+pub struct SeasonResetFlags {
+    pub loyalty_points: bool,
+    pub streaks: bool,
+    pub achievements: bool, // Unlocked and rewarded achievements and their counters
+    pub stats: bool,        // The PoolStats counters
+    pub leaderboard: bool,
+}
+
+// A season's figures, kept by start_new_season when it ends the season, at
+// [b"season_archive", pool, season]
+#[account]
+#[derive(InitSpace)]
+pub struct SeasonArchive {
+    pub company_pool: Pubkey,
+    pub season: u16,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub tickets_sold: u64, // During the season alone
+    pub ticket_revenue: u64,
+    // PoolStats at the end of the season, counted since the last season that
+    // reset them
+    pub total_spins: u64,
+    pub no_win_spins: u64,
+    pub total_value_won: u64,
+    pub total_rewards_paid: u64,
+    pub biggest_win_value: u64,
+    pub biggest_winner: Pubkey,
+    pub top_winners: [LeaderboardEntry; Leaderboard::SIZE],
+    pub reset_flags: SeasonResetFlags, // What the next season started without
+    pub bump: u8,
+}
+
+// A raffle pool's draw, made once by draw_raffle
+#[account]
+#[derive(InitSpace)]
+pub struct RaffleResult {
+    pub company_pool: Pubkey,
+    pub random_seed: [u8; 32],
+    pub tickets_in_draw: u64,
+    pub drawn_at: i64,
+    pub drawn_slot: u64,
+    #[max_len(50)]
+    pub winners: Vec<RaffleWinner>, // In pool item order
+    pub bump: u8,
+}
+
+impl RaffleResult {
+    pub fn prize_for(&self, ticket_id: u64) -> Option<&WonItem> {
+        self.winners
+            .iter()
+            .find(|winner| winner.ticket_id == ticket_id)
+            .map(|winner| &winner.won_item)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct RaffleWinner {
+    pub ticket_id: u64,
+    pub won_item: WonItem,
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct WinnerEntry {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub value: u64, // Includes any jackpot won with the item
+    pub timestamp: i64,
+    pub item_index: u32,
+    pub _padding: [u8; 4],
+}
+
+impl WinnerEntry {
+    pub fn new(
+        winner: Pubkey,
+        ticket_id: u64,
+        item_index: u32,
+        value: u64,
+        timestamp: i64,
+    ) -> Self {
+        WinnerEntry {
+            winner,
+            ticket_id,
+            value,
+            timestamp,
+            item_index,
+            _padding: [0; 4],
+        }
+    }
+}
+
+// WinnerEntry as returned by get_recent_winners, without the padding
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RecentWinner {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub item_index: u32,
+    pub value: u64,
+    pub timestamp: i64,
+}
+
+impl From<&WinnerEntry> for RecentWinner {
+    fn from(entry: &WinnerEntry) -> Self {
+        RecentWinner {
+            winner: entry.winner,
+            ticket_id: entry.ticket_id,
+            item_index: entry.item_index,
+            value: entry.value,
+            timestamp: entry.timestamp,
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalConfig {
+    pub admin: Pubkey,
+    pub program_paused: bool,
+    pub bump: u8,
+    pub creation_mode: CreationMode,
+    pub treasury: Pubkey,                // Receives the pool creation fee
+    pub pool_creation_fee_lamports: u64, // 0 disables the fee
+}
+
+impl GlobalConfig {
+    // Allowlisted deployments only let creators holding a PoolCreatorEntry
+    // open pools
+    pub fn check_pool_creator(
+        &self,
+        entry: Option<&PoolCreatorEntry>,
+        creator: &Pubkey,
+    ) -> Result<()> {
+        if self.creation_mode == CreationMode::Open {
+            return Ok(());
+        }
+
+        let entry = entry.ok_or(ErrorCode::CreatorNotAllowlisted)?;
+        require!(entry.creator == *creator, ErrorCode::CreatorNotAllowlisted);
+
+        Ok(())
+    }
+}
+
+// Who may create pools. Open is the default, and what configs created before
+// the mode existed read as.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Default, Debug,
+)]
+pub enum CreationMode {
+    #[default]
+    Open,
+    Allowlisted,
+}
+
+// A company the program admin approved to create pools
+#[account]
+#[derive(InitSpace)]
+pub struct PoolCreatorEntry {
+    pub creator: Pubkey,
+    pub added_at: i64,
+    pub bump: u8,
+}
+
+// A wallet the program admin stopped from buying tickets
+#[account]
+#[derive(InitSpace)]
+pub struct BlockedUser {
+    pub user: Pubkey,
+    pub blocked_at: i64,
+    pub bump: u8,
+}
+
+impl BlockedUser {
+    // `account` is the buyer's PDA, already checked by seeds. Only this
+    // program can have created an account there, and it closes it on unblock.
+    pub fn check_not_blocked(account: &AccountInfo) -> Result<()> {
+        require!(
+            account.owner != &crate::ID || account.data_is_empty(),
+            ErrorCode::UserBlocked
+        );
+        Ok(())
+    }
+}
+
+// Timelocked withdrawal waiting to be executed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct PendingWithdrawal {
+    pub amount: u64,
+    pub withdrawable_after: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct PoolItem {
+    #[max_len(200)]
+    pub image: String,
+    pub price: u64,
+    #[max_len(50)]
+    pub name: String,
+    #[max_len(200)]
+    pub description: String,
+    pub probability: u32,
+    pub available: bool,
+    pub times_won: u32,
+    pub item_kind: ItemKind,
+    pub jackpot: bool, // Pays its price plus the accumulated jackpot
+    pub manual_weight: u32,
+    pub tier: ItemTier,
+    pub item_id: u32, // Assigned when added and never reused; refer to items by this
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DistributionReportEntry {
+    pub item_index: u32,
+    pub configured_bps: u32,
+    pub times_won: u32,
+    pub realized_bps: u32,
+    pub total_spins: u64,
+}
+
+// One item's average cost to win, see CompanyPool::item_profitability
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemProfitability {
+    pub expected_spins: u64, // Scaled by ODDS_SCALE
+    pub expected_cost: u64,  // Lamports spent on tickets per win
+    pub profit: i128,        // Item value less the expected cost, in lamports
+    pub profit_bps: i64,     // Profit as a share of the expected cost
+}
+
+// Fractions scaled by ODDS_SCALE, see get_spin_odds
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SpinOdds {
+    pub item_index: u32,
+    pub spins: u32,
+    pub chance: u64,                 // Of winning the item on any one spin
+    pub probability_in_spins: u64,   // Of winning it at least once in `spins`
+    pub expected_spins: Option<u64>, // None when the item can't be won
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ReconciliationReport {
+    pub expected_balance: i64,
+    pub actual_balance: u64, // Both vaults together
+    pub delta: i64,          // Actual minus expected
+}
+
+// A third party that donated one of the pool's items, paid share_bps of all
+// ticket revenue. An empty slot has no share.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct ItemSponsor {
+    pub sponsor: Pubkey,
+    pub item_id: u32,
+    pub share_bps: u16,
+    pub paid: u64, // Lamports distributed so far
+}
+
+impl ItemSponsor {
+    // The share is of revenue since the pool opened, so whatever was paid
+    // before is simply subtracted
+    pub fn owed(&self, total_ticket_revenue: u64) -> u64 {
+        let share = total_ticket_revenue as u128 * self.share_bps as u128 / 10000;
+        (share as u64).saturating_sub(self.paid)
+    }
+}
+
+// A mystery item, listed under placeholder text until reveal_item publishes
+// the real text committed to by reveal_hash. An empty slot has no hash.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct PendingReveal {
+    pub item_id: u32,
+    pub reveal_hash: [u8; 32],
+}
+
+impl PendingReveal {
+    // Each field is length-prefixed, as Borsh encodes strings, so text can't
+    // move from one field to the next without changing the hash
+    pub fn hash_of(name: &str, image: &str, description: &str) -> [u8; 32] {
+        hashv(&[
+            &(name.len() as u32).to_le_bytes(),
+            name.as_bytes(),
+            &(image.len() as u32).to_le_bytes(),
+            image.as_bytes(),
+            &(description.len() as u32).to_le_bytes(),
+            description.as_bytes(),
+        ])
+        .to_bytes()
+    }
+}
+
+// An item paid in an SPL token rather than SOL. Its tokens sit in the prize
+// vault's associated token account for the mint, put there with
+// deposit_item_tokens, and each win pays `amount` of them. An empty slot has
+// no amount.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct ItemTokenPrize {
+    pub item_id: u32,
+    pub mint: Pubkey,
+    pub amount: u64,   // Paid per win, in the mint's base units
+    pub escrowed: u64, // Deposited and not yet won
+    pub owed: u64,     // Won and not yet claimed
+}
+
+impl ItemTokenPrize {
+    // The escrow can't cover another win
+    pub fn is_depleted(&self) -> bool {
+        self.escrowed < self.amount
+    }
+}
+
+// The operator's commitment to the seed the current batch of spins draws
+// under, see commit_server_seed. Nothing is committed while the hash is zero.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct ServerSeedCommitment {
+    pub hash: [u8; 32],
+    pub committed_at: i64,
+    pub spins: u32,              // Drawn under this commitment so far
+    pub previous_seed: [u8; 32], // Last seed revealed, zero before the first
+}
+
+impl ServerSeedCommitment {
+    // Spins stop once a commitment is this old, until the next batch
+    pub const LIFETIME_SECONDS: i64 = 24 * 60 * 60;
+
+    pub fn hash_of(seed: &[u8; 32]) -> [u8; 32] {
+        hash(seed).to_bytes()
+    }
+
+    pub fn is_committed(&self) -> bool {
+        self.hash != [0; 32]
+    }
+
+    pub fn expires_at(&self) -> i64 {
+        self.committed_at.saturating_add(Self::LIFETIME_SECONDS)
+    }
+
+    pub fn is_live(&self, now: i64) -> bool {
+        self.is_committed() && now < self.expires_at()
+    }
+
+    // The last revealed seed and the live commitment are hashed with the
+    // spin's own entropy, so the batch's seeds are bound into every draw
+    pub fn mix(&self, entropy: u64) -> u64 {
+        let mixed = hashv(&[&self.previous_seed, &self.hash, &entropy.to_le_bytes()]).to_bytes();
+        u64::from_le_bytes(mixed[..8].try_into().unwrap())
+    }
+}
+
+// A daily window of discounted tickets, in whole UTC hours. It opens at
+// start_hour_utc and closes at end_hour_utc, wrapping past midnight when the
+// end is the earlier hour. No discount means no happy hour.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug, PartialEq, Eq,
+)]
+pub struct HappyHour {
+    pub start_hour_utc: u8,
+    pub end_hour_utc: u8,
+    pub discount_bps: u16,
+}
+
+impl HappyHour {
+    pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+    pub fn is_valid(&self) -> bool {
+        self.start_hour_utc < 24
+            && self.end_hour_utc < 24
+            && self.start_hour_utc != self.end_hour_utc
+            && self.discount_bps > 0
+            && self.discount_bps < 10000
+    }
+
+    pub fn is_open(&self, now: i64) -> bool {
+        if self.discount_bps == 0 {
+            return false;
+        }
+        let hour = (now.rem_euclid(Self::SECONDS_PER_DAY) / 3600) as u8;
+        if self.start_hour_utc < self.end_hour_utc {
+            (self.start_hour_utc..self.end_hour_utc).contains(&hour)
+        } else {
+            hour >= self.start_hour_utc || hour < self.end_hour_utc
+        }
+    }
+}
+
+// Optional cover a buyer pays `price` for on top of the ticket. A spin that
+// wins nothing or an item worth less than floor_value can then claim
+// `payout` from the prize vault, in place of its prize when replaces_prize
+// is set and on top of it otherwise.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug, PartialEq, Eq,
+)]
+pub struct SpinInsurance {
+    pub price: u64,
+    pub floor_value: u64,
+    pub payout: u64,
+    pub replaces_prize: bool,
+}
+
+impl SpinInsurance {
+    // An auto-paid prize is gone before the spin's insurance could take it
+    // back, so auto_payout pools only offer cover on top of the prize
+    pub fn is_valid(&self, auto_payout: bool) -> bool {
+        self.price > 0 && self.payout > 0 && !(self.replaces_prize && auto_payout)
+    }
+
+    // No prize at all, consolation or not, is always covered
+    pub fn covers(&self, won_item: Option<&WonItem>) -> bool {
+        won_item.is_none_or(|item| item.price < self.floor_value)
+    }
+}
+
+// Multiplies an item's odds from starts_at until ends_at. An empty slot has
+// no multiplier.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct ItemBoost {
+    pub item_id: u32,
+    pub multiplier_bps: u32, // 20000 doubles the item's weight
+    pub starts_at: i64,
+    pub ends_at: i64,
+}
+
+impl ItemBoost {
+    pub fn is_active(&self, now: i64) -> bool {
+        self.multiplier_bps > 0 && self.starts_at <= now && now < self.ends_at
+    }
+}
+
+// Reaching a streak of `days` consecutive days of play earns bonus_points
+// loyalty points. An empty slot has no days.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct StreakMilestone {
+    pub days: u16,
+    pub bonus_points: u32,
+}
+
+// What a purchase did to its buyer's streak
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreakUpdate {
+    pub current_streak: u32,
+    pub bonus_points: u32, // 0 unless the streak reached a milestone
+}
+
+// A pack of `size` tickets sold for `price`. The pack's last ticket only
+// draws items of guaranteed_tier or better.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct BundleDefinition {
+    pub size: u8,
+    pub price: u64,
+    pub guaranteed_tier: ItemTier,
+}
+
+impl BundleDefinition {
+    pub const MAX_SIZE: u8 = 10;
+
+    // Each ticket's share of the bundle price, rounded down, with the
+    // remainder on the first ticket so the shares add up to the price
+    pub fn ticket_price_paid(&self, position: u8) -> u64 {
+        let share = self.price / self.size as u64;
+        if position == 0 {
+            share + self.price % self.size as u64
+        } else {
+            share
+        }
+    }
+}
+
+// How a pool hands out its items. Spin pools draw per ticket as it is spun.
+// Raffle pools draw once after ends_at, one winning ticket per item, and
+// winners claim without spinning.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Default, Debug,
+)]
+pub enum PoolMode {
+    #[default]
+    Spin,
+    Raffle,
+}
+
+// Optional pool settings passed at init
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PoolConfig {
+    pub max_tickets_per_user: Option<u32>,
+    pub purchase_cooldown_seconds: Option<u32>,
+    pub whitelist_enabled: bool,
+    // Hold at least gate_min_amount of gate_mint to buy
+    pub gate_mint: Option<Pubkey>,
+    pub gate_min_amount: u64,
+    // Or hold any NFT from a verified Metaplex collection
+    pub gate_collection: Option<Pubkey>,
+    // Double-or-nothing odds, 50/50 when unset
+    pub gamble_win_bps: Option<u16>,
+    // Cap on a doubled reward, the largest item value when unset
+    pub gamble_payout_cap: Option<u64>,
+    // Share of an item's value paid out as SOL on trade-in, 0 disables it
+    pub trade_in_bps: u16,
+    // Rewards left unclaimed this long after the spin can be reclaimed
+    pub claim_deadline_seconds: Option<u32>,
+    // Share of every ticket sale set aside for the jackpot item
+    pub jackpot_contribution_bps: u16,
+    // Points earned per paid ticket, redeemable for free tickets
+    pub loyalty_enabled: bool,
+    pub loyalty_points_per_ticket: u32,
+    pub points_per_free_ticket: u32,
+    // How item values map to probabilities, ticket ratio ^ 1.5 by default
+    pub weighting: WeightingMode,
+    // Per-item probability floor and cap in basis points
+    pub min_probability_bps: Option<u16>,
+    pub max_probability_bps: Option<u16>,
+    // Cap on the expected payout per spin as a share of the ticket price
+    pub max_ev_bps: u16,
+    // Skips the cap for deliberate loss-leader pools
+    pub allow_negative_ev: bool,
+    // Share of spins that win no item, 0 means every spin wins
+    pub no_win_bps: u16,
+    // Paid from the vault on a losing spin, 0 keeps losses empty-handed
+    pub consolation_lamports: u64,
+    // Delay between requesting and executing a withdrawal, 0 disables the timelock
+    pub withdrawal_timelock_seconds: u32,
+    // Most the authority can take out of the vault per 24h window
+    pub max_daily_withdrawal: Option<u64>,
+    // Second key that must co-sign vault withdrawals
+    pub co_authority: Option<Pubkey>,
+    // Backend key that may spin tickets for their owners, for custodial UX
+    pub spin_operator: Option<Pubkey>,
+    // Share of each ticket paid into the prize vault, the rest is revenue
+    pub prize_allocation_bps: u16,
+    // Pay rewards out during the spin instead of through claim_reward
+    pub auto_payout: bool,
+    // Fee for re-drawing a spin result once per ticket, 0 disables rerolls
+    pub reroll_price: u64,
+    // Multi-ticket packs with a guaranteed tier, at most MAX_BUNDLES
+    pub bundles: Vec<BundleDefinition>,
+    // Start with no items and add up to MAX_ACCOUNT_ITEMS with add_pool_item,
+    // each keeping its image and description on its own account
+    pub item_accounts: bool,
+    // Let other programs buy tickets through CPI. Spins stay top-level only.
+    pub allow_cpi_purchases: bool,
+    // Unix time after which tickets stop selling; crank_expire_pool can then
+    // retire the pool. Open-ended when unset.
+    pub ends_at: Option<i64>,
+    // Rewards above the threshold pay vesting_upfront_bps of their value at
+    // claim and vest the rest linearly over the duration, released through
+    // claim_vested. Paid in full when unset.
+    pub vesting_threshold_lamports: Option<u64>,
+    pub vesting_duration_seconds: u32,
+    pub vesting_upfront_bps: u16,
+    // Raffle pools need an end time and can't auto-pay
+    pub pool_mode: PoolMode,
+    // Bonus loyalty points for buying on consecutive UTC days, ascending by
+    // days, at most MAX_STREAK_MILESTONES. Needs loyalty_enabled.
+    pub streak_milestones: Vec<StreakMilestone>,
+    // Paid out of revenue once per achievement a player unlocks, through
+    // claim_achievement_reward. 0 pays nothing.
+    pub achievement_bonus_lamports: u64,
+    // Charity wallet that sweep_vault pays instead of the authority. Can't
+    // be changed later, and the authority can't withdraw from such a pool.
+    pub beneficiary: Option<Pubkey>,
+    // Lowest price an item may have, checked at init and by add_pool_item and
+    // update_pool_item. Set it to the ticket price to keep out junk items
+    // that the weighting would make near-certain; 0 allows cheap
+    // consolation items.
+    pub min_item_price: u64,
+    // Commits the first server seed and makes every later spin need a live
+    // commitment, see commit_server_seed. Can't be turned on later.
+    pub server_seed_hash: Option<[u8; 32]>,
+    // Wins wait this long after the spin before they can be claimed, traded
+    // in or gambled, while the program admin may void them with void_spin.
+    // Not for auto-payout pools. 0 settles at once.
+    pub dispute_window_seconds: u32,
+    // Keeps the authority, co-authority and spin operator from buying,
+    // redeeming or being gifted tickets in the pool, so its odds and winner
+    // stats only reflect real players. Can't be changed later.
+    pub authority_play_disabled: bool,
+    // Most tickets all buyers together can get in one slot, a bundle counting
+    // its full size, so bots can't buy out a pool the moment it opens.
+    // Unlimited when unset.
+    pub max_tickets_per_slot: Option<u16>,
+    // Pays the mega jackpot's share of every sale into the network-wide pot
+    // and lets every spin roll for it. The share comes out of revenue, so
+    // prize_allocation_bps can be at most 90%. Can't be changed later.
+    pub mega_jackpot: bool,
+    // Lets buyers insure their spin at buy_ticket, see SpinInsurance. Can't
+    // be changed later.
+    pub insurance: Option<SpinInsurance>,
+}
+
+// What clone_pool changes from the source pool; unset fields are copied
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct CloneOverrides {
+    // The probability table is recalculated for the new price
+    pub ticket_price: Option<u64>,
+    // Replaces the copied end time
+    pub ends_at: Option<i64>,
+}
+
+// Settings for a zero-copy pool; everything else keeps its PoolConfig default
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PoolV2Config {
+    pub prize_allocation_bps: u16,
+    pub no_win_bps: u16,
+    pub max_ev_bps: u16,
+    pub allow_negative_ev: bool,
+    pub weighting: WeightingMode,
+    pub allow_cpi_purchases: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PoolItemInput {
+    pub image: String,
+    pub price: u64,
+    pub name: String,
+    pub description: String,
+    pub item_kind: ItemKind,
+    pub jackpot: bool,
+    pub manual_weight: u32,     // Only used by WeightingMode::Manual
+    pub tier: Option<ItemTier>, // Assigned from the probability quartiles when unset
+    // Who donated the item and the share of ticket revenue they are paid
+    pub sponsor: Option<Pubkey>,
+    pub sponsor_share_bps: u16,
+    // Makes this a mystery item: name, image and description are placeholder
+    // text until reveal_item, and the item can't be claimed before then. See
+    // PendingReveal::hash_of.
+    pub reveal_hash: Option<[u8; 32]>,
+    // ItemKind::Token only: the token paid and how much of it per win
+    pub reward_mint: Option<Pubkey>,
+    pub reward_amount: u64,
+}
+
+impl PoolItemInput {
+    // Field limits shared by init and add_pool_item; uniqueness is checked by the caller
+    pub fn validate(&self) -> Result<()> {
+        require!(self.price > 0, ErrorCode::InvalidItemPrice);
+        require!(self.name.len() <= 50, ErrorCode::ItemNameTooLong);
+        require!(self.image.len() <= 200, ErrorCode::ItemImageTooLong);
+        require!(
+            self.description.len() <= 200,
+            ErrorCode::ItemDescriptionTooLong
+        );
+        let token_item = self.item_kind == ItemKind::Token;
+        require!(
+            self.reward_mint.is_some() == token_item && (self.reward_amount > 0) == token_item,
+            ErrorCode::InvalidTokenPrize
+        );
+        Ok(())
+    }
+}
+
+// What a spin landed on
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SpinOutcome {
+    Item,
+    Consolation,
+    Nothing,
+}
+
+// Marks the program admin puts on a pool, stored as bits in pool_flags.
+// Verified is a badge for frontends and Flagged a warning; neither changes
+// what the pool can do. Frozen stops sales and withdrawals, while existing
+// tickets can still be spun and claimed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PoolFlag {
+    Verified,
+    Flagged,
+    Frozen,
+}
+
+impl PoolFlag {
+    pub fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+// Milestones a player reaches in a pool, stored as bits in
+// UserPoolState.achievements. Each unlocks once, from its row in RULES.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Achievement {
+    FirstSpin,
+    TenSpins,
+    FirstLegendary,
+    JackpotWinner,
+}
+
+impl Achievement {
+    // What unlocks each achievement: its counter reaching the threshold.
+    // A new achievement only needs a variant and a row here.
+    pub const RULES: [(Achievement, AchievementKind, u32); 4] = [
+        (Achievement::FirstSpin, AchievementKind::Spins, 1),
+        (Achievement::TenSpins, AchievementKind::Spins, 10),
+        (
+            Achievement::FirstLegendary,
+            AchievementKind::LegendaryWins,
+            1,
+        ),
+        (Achievement::JackpotWinner, AchievementKind::JackpotWins, 1),
+    ];
+
+    pub fn bit(self) -> u64 {
+        1 << self as u8
+    }
+}
+
+// The UserPoolState counter an achievement's threshold is checked against
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AchievementKind {
+    Spins,
+    LegendaryWins,
+    JackpotWins,
+}
+
+// How a ticket was obtained; only purchases add to the pool's funds
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TicketSource {
+    Purchase,
+    LoyaltyPoints,
+}
+
+// How a won item is delivered: paid from the vault or shipped off-chain
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Default)]
+pub enum ItemKind {
+    #[default]
+    SolPayout,
+    Physical,
+    Token, // Paid in an SPL token from the item's escrow, see ItemTokenPrize
+}
+
+// Rarity shown to players; Common is the most likely quarter of the items
+#[derive(
+    AnchorSerialize,
+    AnchorDeserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    InitSpace,
+    Default,
+    Debug,
+)]
+pub enum ItemTier {
+    #[default]
+    Common,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl ItemTier {
+    pub const COUNT: usize = 4;
+
+    // Tiers by probability quartile: the likeliest quarter of the items is
+    // Common and the least likely quarter Legendary. Ties keep item order.
+    pub fn assign_by_probability(probabilities: &[u32]) -> Vec<ItemTier> {
+        let mut ranked: Vec<usize> = (0..probabilities.len()).collect();
+        ranked.sort_by(|&a, &b| probabilities[b].cmp(&probabilities[a]).then(a.cmp(&b)));
+
+        let mut tiers = vec![ItemTier::Common; probabilities.len()];
+        for (rank, &index) in ranked.iter().enumerate() {
+            tiers[index] = match rank * Self::COUNT / probabilities.len() {
+                0 => ItemTier::Common,
+                1 => ItemTier::Rare,
+                2 => ItemTier::Epic,
+                _ => ItemTier::Legendary,
+            };
+        }
+        tiers
+    }
+}
+
+// What a ticket won. Name, image, description and tier are read from the
+// pool's item by id; the kind stays so vault accounting needs no pool lookup.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct WonItem {
+    pub item_id: u32,
+    pub price: u64, // Includes any jackpot or gamble winnings
+    pub item_kind: ItemKind,
+}
+
+// WonItem as stored by tickets issued before it was slimmed down
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct LegacyWonItem {
+    #[max_len(50)]
+    pub name: String,
+    pub price: u64,
+    #[max_len(200)]
+    pub image: String,
+    #[max_len(200)]
+    pub description: String,
+    pub item_index: u32,
+    pub item_id: u32,
+    pub item_kind: ItemKind,
+    pub tier: ItemTier,
+}
+
+impl From<LegacyWonItem> for WonItem {
+    fn from(legacy: LegacyWonItem) -> Self {
+        WonItem {
+            item_id: legacy.item_id,
+            price: legacy.price,
+            item_kind: legacy.item_kind,
+        }
+    }
+}
+
+impl WonItem {
+    // Physical prizes are shipped and token prizes paid from their escrow,
+    // so neither draws on the SOL vault
+    pub fn vault_liability(&self) -> u64 {
+        match self.item_kind {
+            ItemKind::SolPayout => self.price,
+            ItemKind::Physical | ItemKind::Token => 0,
+        }
+    }
+}
+
+// Events
+#[event]
+pub struct RewardClaimedEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub won_item: WonItem,
+    pub item_name: String,
+    pub reward_amount: u64,
+    pub external_id: Option<[u8; 32]>, // Buyer's order reference, see buy_ticket
+    pub leaderboard_rank: Option<u8>,  // 1 for the top spot, None off the board
+    pub serial: Option<u32>,           // See UserTicket::serial
+    pub wrapped: bool,                 // Paid as wSOL, see claim_reward_wrapped
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolItemAddedEvent {
+    pub item_id: u32,
+    pub name: String,
+    pub price: u64,
+    pub tier: ItemTier,
+    pub probabilities: Vec<u32>,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ItemAvailabilityChangedEvent {
+    pub item_id: u32,
+    pub available: bool,
+    pub probabilities: Vec<u32>,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ItemTokensDepositedEvent {
+    pub item_id: u32,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub escrowed: u64, // Escrow balance after the deposit
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ItemTokensReclaimedEvent {
+    pub item_id: u32,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub still_owed: u64, // Left in the escrow for unclaimed wins
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ServerSeedCommittedEvent {
+    pub seed_hash: [u8; 32],
+    pub expires_at: i64, // Spins stop after this until the next commitment
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ServerSeedRevealedEvent {
+    pub seed: [u8; 32],
+    pub seed_hash: [u8; 32],
+    pub spins: u32, // Drawn under the revealed seed
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TicketPriceUpdatedEvent {
+    pub old_price: u64,
+    pub new_price: u64,
+    pub probabilities: Vec<u32>,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ItemUpdatedEvent {
+    pub item_id: u32,
+    pub old_image: String,
+    pub new_image: String,
+    pub old_description: String,
+    pub new_description: String,
+    pub old_price: u64,
+    pub new_price: u64,
+    pub probabilities: Vec<u32>,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpinResultEvent {
+    pub spinner: Pubkey,
+    pub outcome: SpinOutcome,
+    pub won_item: Option<PoolItem>,
+    pub item_index: Option<u32>,
+    pub item_value: u64,
+    pub win_probability: u32,
+    pub random_seed: u64,
+    pub ticket_id: u64,
+    pub pool_flags: u8,
+    pub external_id: Option<[u8; 32]>, // Buyer's order reference, see buy_ticket
+    pub boost_active: bool,            // Some item's odds were boosted for this spin
+    pub serial: Option<u32>,           // Which win of the item this was, from 1
+    pub server_seed_hash: Option<[u8; 32]>, // Commitment drawn under, in server-seeded pools
+    pub insurance_triggered: bool,     // The ticket was insured and can claim_insurance
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+// SpinResultEvent for zero-copy pools, which identify the item by id only
+#[event]
+pub struct SpinResultV2Event {
+    pub spinner: Pubkey,
+    pub outcome: SpinOutcome,
+    pub item_id: Option<u32>,
+    pub item_value: u64,
+    pub win_probability: u32,
+    pub random_seed: u64,
+    pub ticket_id: u64,
+    pub pool_flags: u8,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpinRerolledEvent {
+    pub spinner: Pubkey,
+    pub ticket_id: u64,
+    pub old_item: Option<WonItem>, // None for a losing spin
+    pub new_item: Option<WonItem>,
+    pub reroll_price: u64,
+    pub random_seed: u64,
+    pub server_seed_hash: Option<[u8; 32]>,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpinDelegateUpdatedEvent {
+    pub owner: Pubkey,
+    pub company_pool: Pubkey,
+    pub delegate: Option<Pubkey>, // None once revoked
+    pub expires_at: i64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProbabilityAnalysisEvent {
+    pub item_index: u32,
+    pub item_name: String,
+    pub item_value: u64,
+    pub probability_basis_points: u32,
+    pub expected_spins: u64, // ODDS_SCALE fractions, see ItemProfitability
+    pub expected_cost: u64,  // Lamports
+    pub profit: i128,        // Lamports, negative when the item costs more than it's worth
+    pub profit_bps: i64,
+    pub event_seq: u64,
+}
+
+// Emitted with the first page of get_probability_analysis
+#[event]
+pub struct PoolAnalysisEvent {
+    pub company_pool: Pubkey,
+    pub ticket_price: u64,
+    pub item_count: u32,
+    pub expected_value: u64, // Lamports paid out per spin on average
+    pub ev_bps: i64,         // Expected value as a share of the ticket price
+    pub house_edge_bps: i64, // 10000 less ev_bps, negative for loss leaders
+    pub event_seq: u64,
+}
+
+#[event]
+pub struct SpinOddsEvent {
+    pub company_pool: Pubkey,
+    pub item_index: u32,
+    pub spins: u32,
+    pub chance: u64, // ODDS_SCALE fractions, see SpinOdds
+    pub probability_in_spins: u64,
+    pub expected_spins: Option<u64>,
+    pub event_seq: u64,
+}
+
+#[event]
+pub struct PoolFlagSetEvent {
+    pub company_pool: Pubkey,
+    pub admin: Pubkey,
+    pub flag: PoolFlag,
+    pub enabled: bool,
+    pub pool_flags: u8,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolCreationModeSetEvent {
+    pub admin: Pubkey,
+    pub mode: CreationMode,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolCreatorUpdatedEvent {
+    pub creator: Pubkey,
+    pub allowed: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserBlockUpdatedEvent {
+    pub user: Pubkey,
+    pub blocked: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CreationFeeUpdatedEvent {
+    pub admin: Pubkey,
+    pub old_fee: u64,
+    pub new_fee: u64,
+    pub treasury: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProgramPausedEvent {
+    pub admin: Pubkey,
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DistributionReportEvent {
+    pub item_index: u32,
+    pub configured_bps: u32,
+    pub times_won: u32,
+    pub realized_bps: u32,
+    pub total_spins: u64,
+    pub event_seq: u64,
+}
+
+#[event]
+pub struct VoucherIssuedEvent {
+    pub company_pool: Pubkey,
+    pub recipient: Pubkey,
+    pub count: u8,
+    pub spins_remaining: u32,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoucherRedeemedEvent {
+    pub redeemer: Pubkey,
+    pub ticket_id: u64,
+    pub spins_remaining: u32,
+    pub total_tickets_sold: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoucherRevokedEvent {
+    pub company_pool: Pubkey,
+    pub recipient: Pubkey,
+    pub spins_revoked: u32,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TicketGiftedEvent {
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub ticket_price: u64,
+    pub ticket_id: u64,
+    pub gate_passed: bool,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SharedTicketPurchasedEvent {
+    pub payer: Pubkey,
+    pub shared_ticket: Pubkey, // Owns the ticket on the participants' behalf
+    pub shares: Vec<TicketShare>,
+    pub ticket_price: u64,
+    pub ticket_id: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+// One per participant paid by claim_shared_reward
+#[event]
+pub struct SharedRewardPaidEvent {
+    pub participant: Pubkey,
+    pub shared_ticket: Pubkey,
+    pub ticket_id: u64,
+    pub share_bps: u16,
+    pub amount: u64,
+    pub reward_amount: u64,           // The whole reward the ticket won
+    pub leaderboard_rank: Option<u8>, // Where the item win put the participant
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WhitelistUpdatedEvent {
+    pub company_pool: Pubkey,
+    pub user: Pubkey,
+    pub whitelisted: bool,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardGambledEvent {
+    pub spinner: Pubkey,
+    pub ticket_id: u64,
+    pub won: bool,
+    pub old_value: u64,
+    pub new_value: u64,
+    pub random_seed: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardTradedInEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub item_value: u64,
+    pub payout: u64,
+    pub margin: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolExpiredEvent {
+    pub company_pool: Pubkey,
+    pub cranker: Pubkey,
+    pub ends_at: i64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolClosedEvent {
+    pub company_pool: Pubkey,
+    pub authority: Pubkey,
+    pub rent_returned: u64,
+    pub funds_returned: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct HappyHourSetEvent {
+    pub company_pool: Pubkey,
+    pub start_hour_utc: u8,
+    pub end_hour_utc: u8,
+    pub discount_bps: u16, // 0 when the happy hour was ended
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SeasonStartedEvent {
+    pub company_pool: Pubkey,
+    pub season: u16, // The season now running
+    pub season_archive: Pubkey,
+    pub reset_flags: SeasonResetFlags,
+    pub previous_tickets_sold: u64, // Sold during the season just ended
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolArchivedEvent {
+    pub company_pool: Pubkey,
+    pub pool_archive: Pubkey,
+    pub authority: Pubkey,
+    pub rent_returned: u64,
+    pub funds_returned: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+// Numbered 0 once the ticket's pool is gone
+#[event]
+pub struct TicketClosedEvent {
+    pub owner: Pubkey,
+    pub company_pool: Pubkey,
+    pub ticket_id: u64,
+    pub rent_returned: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolClonedEvent {
+    pub source_pool: Pubkey,
+    pub company_pool: Pubkey, // The clone
+    pub company_name: String,
+    pub authority: Pubkey,
+    pub ticket_price: u64,
+    pub item_count: u32,
+    pub expected_value: u64,
+    pub vault_rent_funded: u64,
+    pub creation_fee: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpinVoidedEvent {
+    pub company_pool: Pubkey,
+    pub owner: Pubkey,
+    pub ticket_id: u64,
+    pub voided_item: Option<WonItem>, // None for a losing spin
+    pub refund: u64,                  // Ticket price paid back to the owner
+    pub admin: Pubkey,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpinCancelledEvent {
+    pub company_pool: Pubkey,
+    pub owner: Pubkey,
+    pub ticket_id: u64,
+    pub requested_at: i64, // When the cancelled draw was requested
+    pub cranker: Pubkey,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardExpiredEvent {
+    pub company_pool: Pubkey,
+    pub owner: Pubkey,
+    pub ticket_id: u64,
+    pub item_value: u64,
+    pub ticket_closed: bool,
+    pub cranker: Option<Pubkey>, // Set when expired through crank_expire_reward
+    pub bounty: u64,             // Paid to the cranker
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardNftMintedEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub mint: Pubkey,
+    pub item_name: String,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardVestingStartedEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub vested_total: u64,
+    pub upfront_amount: u64,
+    pub vesting_end: i64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VestedRewardClaimedEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub amount: u64,
+    pub claimed_so_far: u64,
+    pub vested_total: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RaffleDrawnEvent {
+    pub company_pool: Pubkey,
+    pub drawn_by: Pubkey,
+    pub winning_ticket_ids: Vec<u64>, // In pool item order
+    pub tickets_in_draw: u64,
+    pub random_seed: [u8; 32],
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ItemRevealedEvent {
+    pub item_id: u32,
+    pub name: String,
+    pub image: String,
+    pub description: String,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ItemBoostSetEvent {
+    pub item_id: u32,
+    pub multiplier_bps: u32, // 0 when the boost was removed
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AchievementUnlockedEvent {
+    pub user: Pubkey,
+    pub achievement: Achievement,
+    pub achievements: u64, // Every bit unlocked so far
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AchievementRewardClaimedEvent {
+    pub user: Pubkey,
+    pub achievement: Achievement,
+    pub amount: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StreakUpdatedEvent {
+    pub user: Pubkey,
+    pub current_streak: u32,
+    pub milestone_hit: bool,
+    pub bonus_points: u32,
+    pub loyalty_points: u64, // Balance after the bonus
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SponsorRevenueDistributedEvent {
+    pub sponsor: Pubkey,
+    pub item_id: u32,
+    pub amount: u64,
+    pub total_paid: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardAutoPaidEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub amount: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountMigratedEvent {
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub rent_refunded: i64, // Negative when the caller paid for a larger layout
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpinVerifiedEvent {
+    pub company_pool: Pubkey,
+    pub ticket_id: u64,
+    pub random_seed: u64,
+    pub table_hash: [u8; 32],
+    pub drawn_index: Option<u32>,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenPrizeClaimedEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub item_id: u32,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FulfillmentRequestedEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub won_item: WonItem,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardFulfilledEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub shipping_info_hash: [u8; 32],
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct JackpotWonEvent {
+    pub winner: Pubkey,
+    pub ticket_id: u64,
+    pub jackpot_amount: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InsuranceClaimedEvent {
+    pub owner: Pubkey,
+    pub company_pool: Pubkey,
+    pub ticket_id: u64,
+    pub item_value: u64, // What the insured spin won, 0 for nothing
+    pub payout: u64,
+    pub prize_forfeited: bool, // The pool's insurance replaced the spin's prize
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MegaJackpotWonEvent {
+    pub winner: Pubkey,
+    pub company_pool: Pubkey,
+    pub ticket_id: u64,
+    pub amount: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MegaJackpotClaimedEvent {
+    pub winner: Pubkey,
+    pub user_ticket: Pubkey,
+    pub amount: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MegaJackpotTermsSetEvent {
+    pub admin: Pubkey,
+    pub contribution_bps: u16,
+    pub hit_chance_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ConsolationClaimedEvent {
+    pub spinner: Pubkey,
+    pub ticket_id: u64,
+    pub amount: u64,
+    pub wrapped: bool, // Paid as wSOL, see claim_reward_wrapped
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolInitializedEvent {
+    pub company_name: String,
+    pub ticket_price: u64,
+    pub item_count: u32,
+    pub authority: Pubkey,
+    pub total_probability_check: u32,
+    pub vault_rent_funded: u64,
+    pub expected_value: u64,         // Average payout per spin in lamports
+    pub creation_fee: u64,           // Paid to the protocol treasury
+    pub beneficiary: Option<Pubkey>, // Where swept revenue goes, see PoolConfig
+    pub authority_can_play: bool,    // Whether the pool's own keys may hold tickets
+    pub probabilities: Vec<u32>,     // Stored odds in bps, in input item order
+    pub total_value: u64,            // Sum of the item prices
+    pub weighting: WeightingMode,    // How the odds were derived from the prices
+    pub event_seq: u64,
+}
+
+#[event]
+pub struct BundlePurchasedEvent {
+    pub buyer: Pubkey,
+    pub size: u8,
+    pub price: u64,
+    pub first_ticket_id: u64,
+    pub guaranteed_ticket_id: u64,
+    pub guaranteed_tier: ItemTier,
+    pub prize_share: u64, // Part of the price paid into the prize vault
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TicketPurchasedEvent {
+    pub buyer: Pubkey,
+    pub ticket_price: u64,
+    pub source: TicketSource,
+    pub total_tickets_sold: u64,
+    pub ticket_id: u64,
+    pub gate_passed: bool, // True when the pool is token-gated and the buyer passed
+    pub jackpot_accumulated: u64,
+    pub prize_share: u64,       // Part of the price paid into the prize vault
+    pub discount: u64,          // Taken off the pool's ticket price by its happy hour
+    pub donation: u64,          // Paid to the pool's beneficiary on top of the ticket
+    pub insurance_premium: u64, // Paid into the prize vault to insure the spin
+    pub pool_flags: u8,         // PoolFlag bits, so frontends can badge the pool
+    pub external_id: Option<[u8; 32]>, // Buyer's order reference, see buy_ticket
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FundsWithdrawnEvent {
+    pub authority: Pubkey,
+    pub amount_withdrawn: u64,
+    pub remaining_funds: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultSweptEvent {
+    pub authority: Pubkey,
+    pub recipient: Pubkey, // The beneficiary if the pool has one, else the authority
+    pub gross_balance: u64,
+    pub reserved: u64, // See CompanyPool::reserved_balance
+    pub swept: u64,
+    pub remaining_funds: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PrizeVaultMigratedEvent {
+    pub company_pool: Pubkey,
+    pub moved: u64, // Unclaimed rewards and jackpot moved out of the revenue vault
+    pub prize_allocation_bps: u16,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositEvent {
+    pub depositor: Pubkey,
+    pub amount: u64,
     pub total_funds: u64,
-    pub active: bool,
-    pub created_at: i64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PrizeVaultFundedEvent {
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub prize_vault_balance: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SpinOperatorUpdatedEvent {
+    pub company_pool: Pubkey,
+    pub old_operator: Option<Pubkey>,
+    pub new_operator: Option<Pubkey>,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CoAuthorityUpdatedEvent {
+    pub authority: Pubkey,
+    pub old_co_authority: Option<Pubkey>,
+    pub new_co_authority: Option<Pubkey>,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalRequestedEvent {
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub withdrawable_after: i64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalExecutedEvent {
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub remaining_funds: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalCancelledEvent {
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultReconciledEvent {
+    pub company_pool: Pubkey,
+    pub expected_balance: i64,
+    pub actual_balance: u64,
+    pub delta: i64,
+    pub event_seq: u64,
+    pub timestamp: i64,
+}
+
+// Error Codes
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Ticket price must be greater than 0")]
+    InvalidTicketPrice,
+    #[msg("At least one item must be provided")]
+    NoItemsProvided,
+    #[msg("Company name is too long (max 50 characters)")]
+    CompanyNameTooLong,
+    #[msg("Company image URL is too long (max 200 characters)")]
+    CompanyImageTooLong,
+    #[msg("Too many items (max 10 allowed)")]
+    TooManyItems,
+    #[msg("Item price must be greater than 0")]
+    InvalidItemPrice,
+    #[msg("Item price is below the pool's minimum item price")]
+    ItemPriceTooLow,
+    #[msg("Item name is too long (max 50 characters)")]
+    ItemNameTooLong,
+    #[msg("Item image URL is too long (max 200 characters)")]
+    ItemImageTooLong,
+    #[msg("Item description is too long (max 200 characters)")]
+    ItemDescriptionTooLong,
+    #[msg("Pool is not active")]
+    PoolInactive,
+    #[msg("No available items to spin")]
+    NoAvailableItems,
+    #[msg("Unauthorized withdrawal attempt")]
+    UnauthorizedWithdrawal,
+    #[msg("No funds available for withdrawal")]
+    NoFundsAvailable,
+    #[msg("Invalid withdrawal amount")]
+    InvalidAmount,
+    #[msg("Insufficient funds for withdrawal")]
+    InsufficientFunds,
+    #[msg("Insufficient vault funds")]
+    InsufficientVaultFunds,
+    #[msg("Math overflow occurred")]
+    MathOverflow,
+    #[msg("Invalid probability calculation")]
+    InvalidProbabilityCalculation,
+    #[msg("Probability sum mismatch")]
+    ProbabilitySumMismatch,
+    #[msg("Failed to select winning item")]
+    ProbabilitySelectionFailed,
+    #[msg("You don't own this ticket")]
+    NotTicketOwner,
+    #[msg("Ticket belongs to different pool")]
+    InvalidTicketPool,
+    #[msg("This ticket has already been used")]
+    TicketAlreadyUsed,
+    #[msg("Ticket must be used (spun) before claiming reward")]
+    TicketNotUsed,
+    #[msg("Reward has already been claimed")]
+    RewardAlreadyClaimed,
+    #[msg("No reward to claim for this ticket")]
+    NoRewardToClaim,
+    #[msg("Only the pool authority can perform this action")]
+    Unauthorized,
+    #[msg("No item with that id in this pool")]
+    InvalidItemIndex,
+    #[msg("Pool bumps are already stored")]
+    BumpsAlreadyStored,
+    #[msg("Account is not the expected pool PDA")]
+    InvalidPoolAccount,
+    #[msg("Program is paused")]
+    ProgramPaused,
+    #[msg("No voucher spins remaining")]
+    NoVoucherSpinsRemaining,
+    #[msg("Invalid pool configuration")]
+    InvalidPoolConfig,
+    #[msg("Purchase limit reached for this user")]
+    PurchaseLimitReached,
+    #[msg("Purchase cooldown is still active")]
+    PurchaseCooldownActive,
+    #[msg("User is not whitelisted for this pool")]
+    NotWhitelisted,
+    #[msg("Token gate requirement not met")]
+    TokenGateNotMet,
+    #[msg("Reward has already been gambled")]
+    AlreadyGambled,
+    #[msg("Trade-in is disabled for this pool")]
+    TradeInDisabled,
+    #[msg("Claim deadline for this reward has passed")]
+    RewardExpired,
+    #[msg("Reward has not expired yet")]
+    RewardNotExpired,
+    #[msg("Expired reward has already been reclaimed")]
+    RewardAlreadyReclaimed,
+    #[msg("Reward NFT has already been minted for this ticket")]
+    RewardNftAlreadyMinted,
+    #[msg("Physical and token items cannot be paid out in SOL")]
+    PhysicalItemNotPayable,
+    #[msg("Fulfillment is already pending for this ticket")]
+    FulfillmentPending,
+    #[msg("Ticket is not awaiting fulfillment")]
+    FulfillmentNotPending,
+    #[msg("Shipping info has not been attached")]
+    ShippingInfoMissing,
+    #[msg("Loyalty points are disabled for this pool")]
+    LoyaltyDisabled,
+    #[msg("Not enough loyalty points for a free ticket")]
+    InsufficientLoyaltyPoints,
+    #[msg("Probability bounds cannot be satisfied for these items")]
+    ProbabilityClampInfeasible,
+    #[msg("Every item needs a non-zero probability")]
+    ZeroProbabilityItem,
+    #[msg("Expected payout per spin exceeds the configured share of the ticket price")]
+    ExpectedValueTooHigh,
+    #[msg("At least one item must be in the Common tier")]
+    CommonTierRequired,
+    #[msg("Withdrawals from this pool must go through the timelock")]
+    WithdrawalTimelocked,
+    #[msg("A withdrawal is already pending")]
+    WithdrawalAlreadyPending,
+    #[msg("No withdrawal is pending")]
+    NoPendingWithdrawal,
+    #[msg("The withdrawal timelock has not expired yet")]
+    WithdrawalNotReady,
+    #[msg("Withdrawal would exceed the pool's daily limit")]
+    DailyWithdrawalLimitExceeded,
+    #[msg("The pool's co-authority must also sign")]
+    CoAuthoritySignatureRequired,
+    #[msg("This pool has no prize vault yet; migrate it first")]
+    PrizeVaultNotInitialized,
+    #[msg("This pool already has a prize vault")]
+    PrizeVaultAlreadyInitialized,
+    #[msg("Rewards in this pool are paid out automatically when the ticket is spun")]
+    AutoPayoutPool,
+    #[msg("Auto-payout pools need the prize vault passed to the spin")]
+    PrizeVaultRequired,
+    #[msg("Rerolls are disabled for this pool")]
+    RerollDisabled,
+    #[msg("This ticket has already been rerolled")]
+    AlreadyRerolled,
+    #[msg("Delegated spins in auto-payout pools must pass the ticket owner")]
+    TicketOwnerRequired,
+    #[msg("Delegation must expire in the future and name another key")]
+    InvalidSpinDelegate,
+    #[msg("This pool has no bundle of that size")]
+    BundleNotFound,
+    #[msg("Bundle ticket accounts must be the next ticket PDAs in order")]
+    BundleTicketMismatch,
+    #[msg("Spins must be sent directly, not through another program")]
+    CpiNotAllowed,
+    #[msg("The spinner must be a system-owned wallet")]
+    InvalidSpinnerAccount,
+    #[msg("Item names must be unique within a pool")]
+    DuplicateItemName,
+    #[msg("This pool stores its items inline")]
+    ItemAccountsDisabled,
+    #[msg("Item account does not match this pool item")]
+    ItemAccountMismatch,
+    #[msg("Account is already in the current layout")]
+    AccountAlreadyMigrated,
+    #[msg("Account layout is too old to read")]
+    UnsupportedAccountVersion,
+    #[msg("Tickets can't be spun in the slot they were bought in")]
+    SpinTooSoon,
+    #[msg("Rewards can't be settled in the slot they were drawn in")]
+    ClaimTooSoon,
+    #[msg("Pool is frozen by the program admin")]
+    PoolFrozen,
+    #[msg("Pool creation is limited to allowlisted creators")]
+    CreatorNotAllowlisted,
+    #[msg("Treasury account does not match the global config")]
+    InvalidTreasury,
+    #[msg("This wallet is blocked from buying tickets")]
+    UserBlocked,
+    #[msg("This pool only sells tickets to direct callers, not other programs")]
+    CpiPurchasesDisabled,
+    #[msg("Pool has ended and no longer sells tickets")]
+    PoolEnded,
+    #[msg("Pool has not ended, or its claim window is still open")]
+    PoolNotExpired,
+    #[msg("Pool still owes rewards or holds revenue")]
+    PoolNotSettled,
+    #[msg("No vested reward is available to claim yet")]
+    NoVestedReward,
+    #[msg("Raffle pools draw their winners with draw_raffle instead of spins")]
+    RaffleModePool,
+    #[msg("Pool is not a raffle")]
+    NotRafflePool,
+    #[msg("Raffle is still selling tickets")]
+    RaffleStillOpen,
+    #[msg("Raffle winners have not been drawn yet")]
+    RaffleNotDrawn,
+    #[msg("Ticket did not win the raffle")]
+    NotRaffleWinner,
+    #[msg("Slot hashes sysvar holds no hash to draw from")]
+    SlotHashesUnavailable,
+    #[msg("Sponsor shares are invalid or exceed the pool's revenue share")]
+    InvalidSponsorShares,
+    #[msg("Pool has no sponsors")]
+    NoSponsors,
+    #[msg("Sponsor accounts must match the pool's sponsors in order")]
+    SponsorAccountMismatch,
+    #[msg("Achievement has not been unlocked")]
+    AchievementLocked,
+    #[msg("Achievement bonus already paid")]
+    AchievementAlreadyRewarded,
+    #[msg("Pool pays no achievement bonus")]
+    AchievementRewardsDisabled,
+    #[msg("Boost must raise the item's odds without taking it past 10000 bps")]
+    InvalidBoost,
+    #[msg("Boost window must be in the future and end by the pool's end")]
+    InvalidBoostWindow,
+    #[msg("Pool already has the maximum number of boosts")]
+    TooManyBoosts,
+    #[msg("Mystery item must be revealed before it can be claimed")]
+    ItemNotRevealed,
+    #[msg("Item has no pending reveal")]
+    NoPendingReveal,
+    #[msg("Revealed item does not match its reveal hash")]
+    RevealMismatch,
+    #[msg("Pools with a beneficiary can only sweep their revenue to it")]
+    BeneficiaryPool,
+    #[msg("Beneficiary account does not match the pool's beneficiary")]
+    InvalidBeneficiary,
+    #[msg("Ticket has no spin pending")]
+    SpinNotPending,
+    #[msg("Pending spin has not timed out yet")]
+    SpinNotStuck,
+    #[msg("Wrapped claims pay into the native SOL mint only")]
+    InvalidWrappedMint,
+    #[msg("Item is not paid in this token, or its token prize is misconfigured")]
+    InvalidTokenPrize,
+    #[msg("Token prize escrow cannot cover another win")]
+    TokenPrizeDepleted,
+    #[msg("Token prizes need the reward mint, escrow and winner token accounts")]
+    TokenPrizeAccountsMissing,
+    #[msg("Token prize escrow can only be reclaimed from a retired or depleted item")]
+    TokenPrizeActive,
+    #[msg("Token prize escrow is empty")]
+    NothingToReclaim,
+    #[msg("Draw table does not match the spin's recorded hash or result")]
+    SpinVerificationFailed,
+    #[msg("Ticket has no recorded draw to replay")]
+    SpinNotReplayable,
+    #[msg("Pool was not created with server seeds")]
+    ServerSeedsDisabled,
+    #[msg("No live server seed commitment")]
+    ServerSeedCommitmentMissing,
+    #[msg("Reveal the committed server seed first")]
+    ServerSeedAlreadyCommitted,
+    #[msg("Server seed does not match its commitment")]
+    ServerSeedMismatch,
+    #[msg("Server seed hash can't be zero")]
+    InvalidServerSeedHash,
+    #[msg("Spin is still in its dispute window")]
+    DisputeWindowOpen,
+    #[msg("Spin's dispute window has closed")]
+    DisputeWindowClosed,
+    #[msg("Pool must be retired before it can be closed")]
+    PoolStillActive,
+    #[msg("This pool's authority and operators can't hold tickets in it")]
+    AuthorityCannotPlay,
+    #[msg("Only migrated pools with inline items can be cloned")]
+    PoolNotClonable,
+    #[msg("Ticket still has a reward to claim, vest or ship")]
+    TicketNotSettled,
+    #[msg("Happy hour needs two different UTC hours and a discount below 100%")]
+    InvalidHappyHour,
+    #[msg("The pool sold its most tickets for this slot, try again in the next one")]
+    SlotPurchaseLimitReached,
+    #[msg("Only pools with a beneficiary accept donations")]
+    DonationsNotAccepted,
+    #[msg("The pool takes part in the mega jackpot, pass its accounts")]
+    MegaJackpotRequired,
+    #[msg("Mega jackpot contribution is capped at 10% and its hit chance at 100%")]
+    InvalidMegaJackpotTerms,
+    #[msg("This ticket has no mega jackpot win to claim")]
+    NoMegaJackpotWon,
+    #[msg("This pool doesn't offer spin insurance")]
+    InsuranceNotOffered,
+    #[msg("Ticket wasn't insured")]
+    TicketNotInsured,
+    #[msg("Insurance already claimed for this ticket")]
+    InsuranceAlreadyClaimed,
+    #[msg("The spin won at least the insurance floor")]
+    InsuranceNotTriggered,
+    #[msg("This pool does not sell shared tickets")]
+    SharedTicketsNotSupported,
+    #[msg("Shared tickets need 2 to 4 distinct participants with shares adding up to 10000 bps")]
+    InvalidTicketShares,
+    #[msg("Participant accounts must match the ticket's shares, in order")]
+    ParticipantAccountMismatch,
+    #[msg("Resetting the leaderboard needs the pool's leaderboard account")]
+    LeaderboardRequired,
 }
 
-impl CompanyPool {
-    pub const SPACE: usize = 8 + 32 + 54 + 204 + 8 + 4 + (10 * 471) + 8 + 8 + 8 + 1 + 8;
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn max_string(len: usize) -> String {
+        "x".repeat(len)
+    }
+
+    fn max_pool_item() -> PoolItem {
+        PoolItem {
+            image: max_string(200),
+            price: u64::MAX,
+            name: max_string(50),
+            description: max_string(200),
+            probability: 10000,
+            available: true,
+            times_won: u32::MAX,
+            item_kind: ItemKind::Physical,
+            jackpot: true,
+            manual_weight: u32::MAX,
+            tier: ItemTier::Legendary,
+            item_id: u32::MAX,
+        }
+    }
+
+    fn max_won_item() -> WonItem {
+        WonItem {
+            item_id: u32::MAX,
+            price: u64::MAX,
+            item_kind: ItemKind::Physical,
+        }
+    }
+
+    fn serialized_account_len<T: AnchorSerialize>(account: &T) -> usize {
+        let mut data = Vec::new();
+        account.serialize(&mut data).unwrap();
+        8 + data.len()
+    }
+
+    #[test]
+    fn spin_history_wraps_and_keeps_order() {
+        let mut history = SpinHistory {
+            owner: Pubkey::new_unique(),
+            company_pool: Pubkey::new_unique(),
+            head: 0,
+            count: 0,
+            entries: [SpinHistoryEntry::default(); SpinHistory::CAPACITY],
+            bump: 255,
+        };
+
+        for ticket_id in 0..(SpinHistory::CAPACITY as u64 + 5) {
+            history.push(SpinHistoryEntry {
+                ticket_id,
+                ..Default::default()
+            });
+        }
+
+        let ids: Vec<u64> = history
+            .ordered_entries()
+            .iter()
+            .map(|e| e.ticket_id)
+            .collect();
+        let expected: Vec<u64> = (5..(SpinHistory::CAPACITY as u64 + 5)).collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn winner_registry_wraps_newest_first() {
+        let mut registry = WinnerRegistry {
+            company_pool: Pubkey::new_unique(),
+            head: 0,
+            count: 0,
+            bump: 255,
+            _padding: [0; 7],
+            entries: [WinnerEntry::default(); WinnerRegistry::CAPACITY],
+        };
+        let winner = Pubkey::new_unique();
+
+        for ticket_id in 0..3 {
+            registry.push(WinnerEntry::new(winner, ticket_id, 1, 100, 0));
+        }
+        let ids: Vec<u64> = registry.recent(0).iter().map(|e| e.ticket_id).collect();
+        assert_eq!(ids, vec![2, 1, 0]);
+
+        for ticket_id in 3..(WinnerRegistry::CAPACITY as u64 + 10) {
+            registry.push(WinnerEntry::new(winner, ticket_id, 1, 100, 0));
+        }
+        assert_eq!(registry.count as usize, WinnerRegistry::CAPACITY);
+
+        // Pages walk back from the newest win until the oldest kept one
+        let newest = WinnerRegistry::CAPACITY as u64 + 9;
+        let first_page: Vec<u64> = registry.recent(0).iter().map(|e| e.ticket_id).collect();
+        let expected: Vec<u64> = (0..WinnerRegistry::PAGE_SIZE as u64)
+            .map(|i| newest - i)
+            .collect();
+        assert_eq!(first_page, expected);
+
+        let last_page = registry.recent(WinnerRegistry::CAPACITY - 1);
+        assert_eq!(last_page.len(), 1);
+        assert_eq!(last_page[0].ticket_id, 10);
+        assert!(registry.recent(WinnerRegistry::CAPACITY).is_empty());
+    }
+
+    #[test]
+    fn leaderboard_keeps_the_biggest_totals() {
+        let mut leaderboard = Leaderboard {
+            company_pool: Pubkey::new_unique(),
+            bump: 255,
+            entries: [LeaderboardEntry::default(); Leaderboard::SIZE],
+        };
+        let winners: Vec<Pubkey> = (0..Leaderboard::SIZE)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        for (i, winner) in winners.iter().enumerate() {
+            let rank = leaderboard.record_win(*winner, 100 * (i as u64 + 1));
+            assert_eq!(rank, Some(1));
+        }
+        assert_eq!(leaderboard.ranked().len(), Leaderboard::SIZE);
+
+        // Full, so a newcomer has to beat the bottom entry to get on
+        let newcomer = Pubkey::new_unique();
+        assert_eq!(leaderboard.record_win(newcomer, 100), None);
+        assert_eq!(leaderboard.record_win(newcomer, 150), Some(10));
+        assert!(leaderboard
+            .ranked()
+            .iter()
+            .all(|entry| entry.winner != winners[0]));
+
+        // A repeat winner moves up instead of taking a second entry
+        assert_eq!(leaderboard.record_win(newcomer, 800), Some(2));
+        assert_eq!(leaderboard.entries[1].winner, newcomer);
+        assert_eq!(leaderboard.entries[1].total_value_won, 950);
+        assert_eq!(
+            leaderboard
+                .ranked()
+                .iter()
+                .filter(|entry| entry.winner == newcomer)
+                .count(),
+            1
+        );
+        assert!(leaderboard
+            .ranked()
+            .windows(2)
+            .all(|pair| pair[0].total_value_won >= pair[1].total_value_won));
+    }
+
+    #[test]
+    fn winner_registry_page_fits_return_data() {
+        let page = vec![RecentWinner::from(&WinnerEntry::default()); WinnerRegistry::PAGE_SIZE];
+        assert!(page.try_to_vec().unwrap().len() <= 1024);
+        assert!(8 + std::mem::size_of::<WinnerRegistry>() <= 10_240);
+    }
+
+    #[test]
+    fn zero_copy_pools_draw_like_borsh_pools() {
+        let mut pool = pool_with_items(&[100, 500, 2000], 10);
+        pool.items[0].name = "Sticker".to_string();
+        let mut fast: CompanyPoolV2 = bytemuck::Zeroable::zeroed();
+        for (slot, item) in fast.items.iter_mut().zip(&pool.items) {
+            slot.price = item.price;
+            slot.probability = item.probability;
+            slot.name = fixed_text(&item.name);
+        }
+        fast.item_count = pool.items.len() as u32;
+
+        assert_eq!(fast.items().len(), 3);
+        assert_eq!(
+            fast.expected_value().unwrap(),
+            pool.expected_value().unwrap()
+        );
+        for seed in 0..500u64 {
+            let seed = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            assert_eq!(fast.draw(seed).unwrap(), pool.draw(seed, None).unwrap());
+        }
+
+        pool.no_win_bps = 4000;
+        fast.no_win_bps = 4000;
+        for seed in 0..500u64 {
+            let seed = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            assert_eq!(fast.draw(seed).unwrap(), pool.draw(seed, None).unwrap());
+        }
+
+        assert_eq!(text_from_fixed(&fast.items[0].name), "Sticker");
+        assert_eq!(
+            text_from_fixed(&fixed_text::<64>(&max_string(50))),
+            max_string(50)
+        );
+        assert_eq!(text_from_fixed(&[0u8; 64]), "");
+        assert!(8 + std::mem::size_of::<CompanyPoolV2>() <= 10_240);
+    }
+
+    fn pool_with_items(prices: &[u64], ticket_price: u64) -> CompanyPool {
+        let probabilities = calculate_item_probabilities(prices, ticket_price).unwrap();
+
+        let mut pool = CompanyPool {
+            version: CompanyPool::VERSION,
+            authority: Pubkey::new_unique(),
+            company_name: "Test".to_string(),
+            company_image: String::new(),
+            ticket_price,
+            items: prices
+                .iter()
+                .zip(probabilities)
+                .enumerate()
+                .map(|(item_id, (&price, probability))| PoolItem {
+                    image: String::new(),
+                    price,
+                    name: format!("Item{}", item_id),
+                    description: String::new(),
+                    probability,
+                    available: true,
+                    times_won: 0,
+                    item_kind: ItemKind::SolPayout,
+                    jackpot: false,
+                    manual_weight: 0,
+                    tier: ItemTier::Common,
+                    item_id: item_id as u32,
+                })
+                .collect(),
+            total_value: prices.iter().sum(),
+            total_tickets_sold: 0,
+            total_funds: 0,
+            active: true,
+            created_at: 0,
+            pool_version: CompanyPool::POOL_VERSION,
+            vault_bump: 255,
+            pool_bump: 255,
+            vault_rent_reserve: 0,
+            max_tickets_per_user: None,
+            purchase_cooldown_seconds: None,
+            whitelist_enabled: false,
+            gate_mint: None,
+            gate_min_amount: 0,
+            gate_collection: None,
+            gamble_win_bps: None,
+            gamble_payout_cap: None,
+            trade_in_bps: 0,
+            claim_deadline_seconds: None,
+            outstanding_liabilities: 0,
+            jackpot_contribution_bps: 0,
+            jackpot_accumulated: 0,
+            loyalty_enabled: false,
+            loyalty_points_per_ticket: 0,
+            points_per_free_ticket: 0,
+            weighting: WeightingMode::default(),
+            min_probability_bps: None,
+            max_probability_bps: None,
+            no_win_bps: 0,
+            consolation_lamports: 0,
+            withdrawal_timelock_seconds: 0,
+            pending_withdrawal: None,
+            max_daily_withdrawal: None,
+            withdrawn_today: 0,
+            withdrawal_day_start: 0,
+            co_authority: None,
+            prize_allocation_bps: 0,
+            prize_vault_bump: 0,
+            total_ticket_revenue: 0,
+            total_rewards_paid: 0,
+            total_withdrawn: 0,
+            total_authority_deposits: 0,
+            auto_payout: false,
+            reroll_price: 0,
+            bundles: Vec::new(),
+            cum_probability: [0; CompanyPool::MAX_ACCOUNT_ITEMS],
+            pool_flags: 0,
+            allow_cpi_purchases: false,
+            ends_at: None,
+            event_seq: 0,
+            spin_operator: None,
+            vesting_threshold_lamports: None,
+            vesting_duration_seconds: 0,
+            vesting_upfront_bps: 0,
+            pool_mode: PoolMode::Spin,
+            sponsors: [ItemSponsor::default(); CompanyPool::MAX_SPONSORS],
+            streak_milestones: [StreakMilestone::default(); CompanyPool::MAX_STREAK_MILESTONES],
+            achievement_bonus_lamports: 0,
+            boosts: [ItemBoost::default(); CompanyPool::MAX_BOOSTS],
+            pending_reveals: [PendingReveal::default(); CompanyPool::MAX_PENDING_REVEALS],
+            beneficiary: None,
+            min_item_price: 0,
+            token_prizes: [ItemTokenPrize::default(); CompanyPool::MAX_TOKEN_PRIZES],
+            server_seeded: false,
+            server_seed: ServerSeedCommitment::default(),
+            dispute_window_seconds: 0,
+            authority_play_disabled: false,
+            happy_hour: HappyHour::default(),
+            max_tickets_per_slot: None,
+            last_purchase_slot: 0,
+            purchases_in_slot: 0,
+            total_donations: 0,
+            mega_jackpot: false,
+            total_mega_jackpot_contributions: 0,
+            insurance: None,
+            total_insurance_premiums: 0,
+            total_insurance_paid: 0,
+            season: 0,
+            season_started_at: 0,
+            season_start_tickets_sold: 0,
+            season_start_revenue: 0,
+            points_reset_season: 0,
+            streaks_reset_season: 0,
+            achievements_reset_season: 0,
+            shared_tickets_sold: 0,
+        };
+        pool.rebuild_cumulative_table();
+        pool
+    }
+
+    #[test]
+    fn distribution_report_matches_deterministic_seeds() {
+        let mut pool = pool_with_items(&[100, 500, 2000, 50], 10);
+        let probabilities: Vec<u32> = pool.items.iter().map(|item| item.probability).collect();
+
+        // Seeds 0..10000 hit every basis point exactly once
+        for seed in 0..10_000u64 {
+            let index = select_winning_item_index(&probabilities, seed).unwrap();
+            pool.items[index].times_won += 1;
+        }
+
+        let report = pool.distribution_report().unwrap();
+        for entry in report {
+            assert_eq!(entry.total_spins, 10_000);
+            assert_eq!(entry.realized_bps, entry.configured_bps);
+        }
+    }
+
+    #[test]
+    fn distribution_report_handles_no_spins() {
+        let pool = pool_with_items(&[100, 200], 10);
+        let report = pool.distribution_report().unwrap();
+        assert!(report
+            .iter()
+            .all(|entry| entry.realized_bps == 0 && entry.total_spins == 0));
+    }
+
+    #[test]
+    fn user_pool_state_enforces_limit_and_cooldown() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        pool.max_tickets_per_user = Some(2);
+        pool.purchase_cooldown_seconds = Some(60);
+
+        let mut state = UserPoolState {
+            version: UserPoolState::VERSION,
+            tickets_bought: 0,
+            last_purchase_at: 0,
+            bump: 255,
+            loyalty_points: 0,
+            spin_delegate: None,
+            spin_delegate_expires_at: 0,
+            current_streak: 0,
+            last_played_day: 0,
+            achievements: 0,
+            achievements_rewarded: 0,
+            spins: 0,
+            legendary_wins: 0,
+            jackpot_wins: 0,
+            last_season: 0,
+        };
+        state.record_purchase(&pool, 1, 1_000).unwrap();
+        assert!(state.record_purchase(&pool, 1, 1_059).is_err());
+        state.record_purchase(&pool, 1, 1_060).unwrap();
+        assert!(state.record_purchase(&pool, 1, 10_000).is_err());
+        assert_eq!(state.tickets_bought, 2);
+
+        // A batch that would overshoot the cap is rejected as a whole
+        let mut batch_state = UserPoolState {
+            version: UserPoolState::VERSION,
+            tickets_bought: 0,
+            last_purchase_at: 0,
+            bump: 255,
+            loyalty_points: 0,
+            spin_delegate: None,
+            spin_delegate_expires_at: 0,
+            current_streak: 0,
+            last_played_day: 0,
+            achievements: 0,
+            achievements_rewarded: 0,
+            spins: 0,
+            legendary_wins: 0,
+            jackpot_wins: 0,
+            last_season: 0,
+        };
+        assert!(batch_state.record_purchase(&pool, 3, 0).is_err());
+        assert_eq!(batch_state.tickets_bought, 0);
+    }
+
+    #[test]
+    fn spin_delegate_expires() {
+        let delegate = Pubkey::new_unique();
+        let state = UserPoolState {
+            version: UserPoolState::VERSION,
+            tickets_bought: 0,
+            last_purchase_at: 0,
+            bump: 255,
+            loyalty_points: 0,
+            spin_delegate: Some(delegate),
+            spin_delegate_expires_at: 1_000,
+            current_streak: 0,
+            last_played_day: 0,
+            achievements: 0,
+            achievements_rewarded: 0,
+            spins: 0,
+            legendary_wins: 0,
+            jackpot_wins: 0,
+            last_season: 0,
+        };
+
+        assert!(state.is_spin_delegate(&delegate, 999));
+        assert!(!state.is_spin_delegate(&delegate, 1_000));
+        assert!(!state.is_spin_delegate(&Pubkey::new_unique(), 999));
+    }
+
+    #[test]
+    fn user_pool_states_read_back_from_every_layout() {
+        let state = UserPoolState {
+            version: UserPoolState::VERSION,
+            tickets_bought: 3,
+            last_purchase_at: 1_000,
+            bump: 254,
+            loyalty_points: 40,
+            spin_delegate: Some(Pubkey::new_unique()),
+            spin_delegate_expires_at: 2_000,
+            current_streak: 5,
+            last_played_day: 19_000,
+            achievements: 0b101,
+            achievements_rewarded: 0b1,
+            spins: 9,
+            legendary_wins: 2,
+            jackpot_wins: 1,
+            last_season: 4,
+        };
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + UserPoolState::INIT_SPACE);
+        assert_eq!(UserPoolState::read_any_version(&data).unwrap().spins, 9);
+
+        // Every version 1 state is the current layout without the version
+        // byte, less the groups appended after it was written
+        data.remove(8);
+        let mut layout_len = data.len();
+        for groups in (0..=migration::USER_STATE_APPENDED_SPACE.len()).rev() {
+            data.truncate(layout_len);
+            let old = UserPoolState::read_any_version(&data).unwrap();
+            assert_eq!(old.version, 1);
+            assert_eq!(
+                (old.tickets_bought, old.last_purchase_at, old.bump),
+                (3, 1_000, 254)
+            );
+            assert_eq!(old.loyalty_points, if groups >= 1 { 40 } else { 0 });
+            assert_eq!(old.spin_delegate.is_some(), groups >= 2);
+            assert_eq!(old.current_streak, if groups >= 3 { 5 } else { 0 });
+            assert_eq!(old.spins, if groups >= 4 { 9 } else { 0 });
+            assert_eq!(old.last_season, if groups >= 5 { 4 } else { 0 });
+            if groups > 0 {
+                layout_len -= migration::USER_STATE_APPENDED_SPACE[groups - 1];
+            }
+        }
+        assert_eq!(layout_len, 8 + 13);
+        data.truncate(layout_len - 1);
+        assert!(UserPoolState::read_any_version(&data).is_err());
+
+        // A cleared delegate leaves stale bytes in the tail, where the
+        // fields the layout lacks would be read from
+        let cleared = UserPoolState {
+            spin_delegate: None,
+            ..state
+        };
+        let mut data = Vec::new();
+        cleared.try_serialize(&mut data).unwrap();
+        data.remove(8);
+        // The fields that layout had, with the delegate's tag alone
+        data.truncate(8 + 13 + 8 + 1 + 8);
+        data.resize(8 + 13 + 8 + (1 + 32) + 8, 0xff);
+        let old = UserPoolState::read_any_version(&data).unwrap();
+        assert_eq!(old.spin_delegate, None);
+        assert_eq!((old.current_streak, old.last_played_day), (0, 0));
+        assert_eq!(
+            (old.achievements, old.jackpot_wins, old.last_season),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn whitelist_check_requires_matching_entry() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        let pool_key = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let entry = WhitelistEntry {
+            company_pool: pool_key,
+            user,
+            added_at: 0,
+            bump: 255,
+        };
+
+        // Disabled whitelist ignores the entry entirely
+        assert!(pool.check_whitelist(&pool_key, None, &user).is_ok());
+
+        pool.whitelist_enabled = true;
+        assert!(pool.check_whitelist(&pool_key, Some(&entry), &user).is_ok());
+        assert!(pool.check_whitelist(&pool_key, None, &user).is_err());
+        assert!(pool
+            .check_whitelist(&pool_key, Some(&entry), &Pubkey::new_unique())
+            .is_err());
+        assert!(pool
+            .check_whitelist(&Pubkey::new_unique(), Some(&entry), &user)
+            .is_err());
+    }
+
+    fn metadata_bytes(mint: Pubkey, collection: Option<(bool, Pubkey)>) -> Vec<u8> {
+        let mut data = vec![4u8]; // MetadataV1 key
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(mint.as_ref());
+        for field in ["Name", "SYM", "https://example.com"] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.extend_from_slice(&500u16.to_le_bytes());
+        // One creator
+        data.push(1);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&[1, 100]);
+        data.extend_from_slice(&[0, 1]); // primary sale, mutable
+        data.extend_from_slice(&[1, 255]); // edition nonce
+        data.extend_from_slice(&[1, 0]); // token standard
+        match collection {
+            Some((verified, key)) => {
+                data.extend_from_slice(&[1, verified as u8]);
+                data.extend_from_slice(key.as_ref());
+            }
+            None => data.push(0),
+        }
+        data
+    }
+
+    #[test]
+    fn reads_only_verified_collections_from_metadata() {
+        let mint = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+
+        assert_eq!(
+            read_verified_collection(&metadata_bytes(mint, Some((true, collection)))),
+            Some((mint, collection))
+        );
+        assert_eq!(
+            read_verified_collection(&metadata_bytes(mint, Some((false, collection)))),
+            None
+        );
+        assert_eq!(read_verified_collection(&metadata_bytes(mint, None)), None);
+
+        let truncated = metadata_bytes(mint, Some((true, collection)));
+        assert_eq!(
+            read_verified_collection(&truncated[..truncated.len() - 1]),
+            None
+        );
+    }
+
+    #[test]
+    fn trade_in_payout_rounds_down() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        pool.trade_in_bps = 8000;
+        assert_eq!(pool.trade_in_payout(10).unwrap(), 8);
+        assert_eq!(pool.trade_in_payout(11).unwrap(), 8);
+        assert_eq!(pool.trade_in_payout(1).unwrap(), 0);
+
+        pool.trade_in_bps = 10000;
+        assert_eq!(pool.trade_in_payout(u64::MAX).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn reward_expiry_and_liabilities() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        assert!(!pool.is_reward_expired(0, i64::MAX));
+
+        pool.claim_deadline_seconds = Some(60);
+        assert!(!pool.is_reward_expired(1_000, 1_060));
+        assert!(pool.is_reward_expired(1_000, 1_061));
+
+        pool.add_liability(150).unwrap();
+        pool.release_liability(100);
+        assert_eq!(pool.outstanding_liabilities, 50);
+        // Legacy rewards released without having been added don't underflow
+        pool.release_liability(500);
+        assert_eq!(pool.outstanding_liabilities, 0);
+    }
+
+    #[test]
+    fn ended_pools_stop_sales_and_retire_after_the_claim_window() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        assert!(pool.check_open(i64::MAX).is_ok());
+        assert!(!pool.can_retire(i64::MAX));
+
+        pool.ends_at = Some(1_000);
+        pool.claim_deadline_seconds = Some(60);
+        assert!(pool.check_open(1_000).is_ok());
+        assert!(pool.check_open(1_001).is_err());
+        assert!(!pool.can_retire(1_060));
+        assert!(pool.can_retire(1_061));
+
+        // A stale end time in an older layout is ignored
+        pool.version = CompanyPool::VERSION_CPI_PURCHASES;
+        assert!(pool.check_open(i64::MAX).is_ok());
+        assert!(!pool.can_retire(i64::MAX));
+    }
+
+    #[test]
+    fn pool_events_are_numbered_from_one() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        assert_eq!(pool.next_event_seq(), 1);
+        assert_eq!(pool.next_event_seq(), 2);
+        assert_eq!(pool.event_seq, 2);
+
+        // Pools in an older layout number nothing until migrated
+        pool.version = CompanyPool::VERSION_ENDS_AT;
+        assert_eq!(pool.next_event_seq(), 0);
+        assert_eq!(pool.event_seq, 2);
+    }
+
+    #[test]
+    fn large_rewards_vest_linearly_after_the_upfront_tranche() {
+        let mut pool = pool_with_items(&[1_000], 100);
+        pool.vesting_threshold_lamports = Some(500);
+        pool.vesting_duration_seconds = 100;
+        pool.vesting_upfront_bps = 2500;
+        assert_eq!(pool.upfront_amount(500), 500);
+        assert_eq!(pool.upfront_amount(1_000), 250);
+
+        assert_eq!(pool.vested_amount(1_000, 1_000, 1_000), 250);
+        assert_eq!(pool.vested_amount(1_000, 1_000, 1_050), 625);
+        assert_eq!(pool.vested_amount(1_000, 1_000, 1_100), 1_000);
+        assert_eq!(pool.vested_amount(1_000, 1_000, 5_000), 1_000);
+        // A clock behind the start unlocks nothing past the upfront tranche
+        assert_eq!(pool.vested_amount(1_000, 1_000, 0), 250);
+        assert_eq!(pool.vesting_end(1_000), 1_100);
+
+        // Pools in an older layout pay everything up front
+        pool.version = CompanyPool::VERSION_SPIN_OPERATOR;
+        assert_eq!(pool.upfront_amount(1_000), 1_000);
+    }
+
+    #[test]
+    fn sponsors_are_owed_their_share_of_revenue_once() {
+        let mut pool = pool_with_items(&[1_000, 2_000], 100);
+        pool.sponsors[0] = ItemSponsor {
+            sponsor: Pubkey::new_unique(),
+            item_id: 0,
+            share_bps: 1000,
+            paid: 0,
+        };
+        pool.sponsors[1] = ItemSponsor {
+            sponsor: Pubkey::new_unique(),
+            item_id: 1,
+            share_bps: 333,
+            paid: 0,
+        };
+        assert_eq!(pool.sponsor_slots().len(), 2);
+
+        pool.total_ticket_revenue = 1_001;
+        assert_eq!(pool.sponsors[0].owed(pool.total_ticket_revenue), 100);
+        assert_eq!(pool.sponsors[1].owed(pool.total_ticket_revenue), 33);
+        assert_eq!(pool.sponsor_revenue_owed(), 133);
+        let rent = 890_880;
+        assert_eq!(pool.reserved_balance(rent), rent + 133);
+
+        // Rounding is redone on the running total, so it never drifts
+        pool.sponsors[1].paid = 33;
+        pool.total_ticket_revenue = 2_002;
+        assert_eq!(pool.sponsors[1].owed(pool.total_ticket_revenue), 33);
+        pool.sponsors[1].paid = 66;
+        assert_eq!(pool.sponsors[1].owed(pool.total_ticket_revenue), 0);
+
+        pool.version = CompanyPool::VERSION_POOL_MODE;
+        assert!(pool.sponsor_slots().is_empty());
+        assert_eq!(pool.sponsor_revenue_owed(), 0);
+    }
+
+    #[test]
+    fn crank_bounty_comes_out_of_spare_revenue() {
+        let mut pool = pool_with_items(&[1_000], 1_000);
+        let rent = 890_880;
+        pool.total_funds = 1_000_000;
+        let bounty = pool.take_crank_bounty(rent + 1_000_000, rent).unwrap();
+        assert_eq!(bounty, CompanyPool::CRANK_BOUNTY_LAMPORTS);
+        assert_eq!(pool.total_funds, 1_000_000 - bounty);
+        assert_eq!(pool.total_withdrawn, bounty);
+
+        // Never more than the revenue left, nor into the vault's reserve
+        pool.total_funds = 40_000;
+        assert_eq!(pool.take_crank_bounty(u64::MAX, rent).unwrap(), 40_000);
+        pool.total_funds = 1_000_000;
+        pool.outstanding_liabilities = 1_000_000;
+        assert_eq!(pool.take_crank_bounty(rent + 1_000_500, rent).unwrap(), 500);
+    }
+
+    #[test]
+    fn nft_name_fits_metaplex_limit() {
+        assert_eq!(nft_name("PS5"), "PS5");
+        assert_eq!(nft_name(&max_string(50)).len(), MAX_NFT_NAME_LEN);
+
+        // 31 ASCII bytes followed by a 2-byte char must not be split
+        let name = format!("{}é", "a".repeat(31));
+        assert_eq!(nft_name(&name), "a".repeat(31));
+    }
+
+    #[test]
+    fn ticket_sales_feed_the_jackpot() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        pool.jackpot_contribution_bps = 1500;
+
+        for _ in 0..10 {
+            pool.record_ticket_sale(1_000).unwrap();
+        }
+        assert_eq!(pool.total_funds, 10_000);
+        assert_eq!(pool.jackpot_accumulated, 1_500);
+
+        // Contributions round down per sale
+        pool.record_ticket_sale(7).unwrap();
+        assert_eq!(pool.jackpot_accumulated, 1_501);
+    }
+
+    #[test]
+    fn discarded_jackpot_win_returns_the_pot() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        pool.items[1].jackpot = true;
+        pool.jackpot_accumulated = 1_500;
+
+        let (won_item, jackpot_payout) = pool.award_item(1).unwrap();
+        assert_eq!(won_item.price, 1_700);
+        assert_eq!(jackpot_payout, 1_500);
+        assert_eq!(pool.jackpot_accumulated, 0);
+        assert_eq!(pool.items[1].times_won, 1);
+
+        pool.return_award(&won_item).unwrap();
+        assert_eq!(pool.jackpot_accumulated, 1_500);
+
+        // Ordinary items leave the pot alone
+        let (won_item, _) = pool.award_item(0).unwrap();
+        pool.return_award(&won_item).unwrap();
+        assert_eq!(pool.jackpot_accumulated, 1_500);
+    }
+
+    #[test]
+    fn item_ids_survive_list_changes() {
+        let mut pool = pool_with_items(&[100, 200, 300], 10);
+        pool.items[2].jackpot = true;
+        pool.jackpot_accumulated = 500;
+        let (won_item, _) = pool.award_item(2).unwrap();
+
+        pool.items.remove(0);
+        assert_eq!(pool.item_position(2), Some(1));
+        assert_eq!(pool.item_position(0), None);
+
+        // The discarded win still finds its jackpot item by id
+        pool.return_award(&won_item).unwrap();
+        assert_eq!(pool.jackpot_accumulated, 500);
+
+        let calculator = WeightedProbabilityCalculator::new(
+            pool.items
+                .iter()
+                .map(|item| (item.name.clone(), item.price, 1)),
+            pool.ticket_price,
+        )
+        .with_item_ids(&[1, 2]);
+        assert!(calculator.get_probability_of_item(2) > 0.0);
+        assert_eq!(calculator.get_probability_of_item(0), 0.0);
+        assert_eq!(
+            calculator.get_profitability_analysis(2).unwrap().item_name,
+            "Item2"
+        );
+    }
+
+    #[test]
+    fn item_account_pools_draw_past_the_tenth_item() {
+        let mut pool = pool_with_items(&[100; CompanyPool::MAX_ACCOUNT_ITEMS], 10);
+        pool.pool_version = CompanyPool::POOL_VERSION_ITEM_ACCOUNTS;
+        let mut stats =
+            PoolStats::try_deserialize_unchecked(&mut [0u8; 8 + PoolStats::INIT_SPACE].as_slice())
+                .unwrap();
+
+        let index = (0..1000u64)
+            .filter_map(|seed| {
+                pool.draw(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15), None)
+                    .unwrap()
+            })
+            .find(|&index| index >= 10)
+            .expect("no draw reached past the tenth item");
+        let (won_item, _) = pool.award_item(index).unwrap();
+        stats
+            .record_spin(
+                index,
+                pool.items[index].tier,
+                won_item.price,
+                Pubkey::new_unique(),
+            )
+            .unwrap();
+
+        assert_eq!(won_item.item_id, index as u32);
+        assert_eq!(pool.items[index].times_won, 1);
+        assert_eq!(stats.total_spins, 1);
+        assert_eq!(stats.wins_per_item.iter().sum::<u64>(), 0);
+    }
+
+    #[test]
+    fn draw_tables_replay_every_kind_of_draw() {
+        let mut pool = pool_with_items(&[100, 200, 300, 400], 10);
+        pool.no_win_bps = 1500;
+        pool.items[1].tier = ItemTier::Rare;
+        pool.items[3].tier = ItemTier::Rare;
+        pool.items[2].available = false;
+        pool.recalculate_probabilities().unwrap();
+        pool.boosts[0] = ItemBoost {
+            item_id: pool.items[3].item_id,
+            multiplier_bps: 30000,
+            starts_at: 100,
+            ends_at: 200,
+        };
+
+        // The stored table, the boosted scan and the tier guarantee all land
+        // where a replay of their table does
+        for now in [50, 150] {
+            for min_tier in [None, Some(ItemTier::Rare)] {
+                let table = pool.draw_table(min_tier, now);
+                assert_eq!(table.weights[2], 0);
+                for seed in 0..2_000u64 {
+                    let seed = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                    assert_eq!(
+                        table.replay(seed),
+                        pool.draw_at(seed, min_tier, now).unwrap()
+                    );
+                }
+            }
+        }
+
+        let table = pool.draw_table(None, 150);
+        let seed = 0x5eed;
+        let record = DrawRecord::new(seed, &table, pool.draw_at(seed, None, 150).unwrap());
+        assert!(record.verify(&table).is_ok());
+        let mut tampered = table.clone();
+        tampered.weights[0] += 1;
+        assert!(record.verify(&tampered).is_err());
+        let misreported = DrawRecord {
+            drawn_index: record.drawn_index.map_or(Some(0), |index| Some(index + 1)),
+            ..record
+        };
+        assert!(misreported.verify(&table).is_err());
+    }
+
+    #[test]
+    fn server_seeded_spins_need_a_live_commitment() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        assert_eq!(pool.mix_server_seed(42, 0).unwrap(), (42, None));
+
+        pool.server_seeded = true;
+        assert!(pool.mix_server_seed(42, 0).is_err());
+
+        let seed = [7; 32];
+        pool.server_seed.hash = ServerSeedCommitment::hash_of(&seed);
+        pool.server_seed.committed_at = 100;
+        let (mixed, committed) = pool.mix_server_seed(42, 100).unwrap();
+        assert_ne!(mixed, 42);
+        assert_eq!(committed, Some(pool.server_seed.hash));
+        assert_eq!(pool.server_seed.spins, 1);
+
+        // The previous batch's seed feeds into the next one's draws
+        pool.server_seed.previous_seed = [1; 32];
+        assert_ne!(pool.mix_server_seed(42, 100).unwrap().0, mixed);
+
+        let expiry = pool.server_seed.expires_at();
+        assert!(pool.mix_server_seed(42, expiry - 1).is_ok());
+        assert!(pool.mix_server_seed(42, expiry).is_err());
+        assert_eq!(pool.server_seed.spins, 3);
+    }
+
+    #[test]
+    fn voided_tickets_are_refunded_as_they_were_paid() {
+        let mut pool = pool_with_items(&[100, 200], 1_000);
+        assert!(!pool.is_disputable(100, 100));
+        assert!(pool.check_undisputed(100, 100).is_ok());
+
+        pool.dispute_window_seconds = 60;
+        assert!(pool.is_disputable(100, 159));
+        assert!(pool.check_undisputed(100, 159).is_err());
+        assert!(!pool.is_disputable(100, 160));
+
+        pool.prize_allocation_bps = 4000;
+        pool.prize_vault_bump = 255;
+        pool.record_ticket_sale(1_000).unwrap();
+        let rent = 890_880;
+        assert!(pool.take_refund(1_000, rent + 599, rent).is_err());
+        assert_eq!(
+            pool.take_refund(1_000, rent + 600, rent).unwrap(),
+            (600, 400)
+        );
+        assert_eq!(pool.total_funds, 0);
+        assert_eq!(pool.ledger_balance(rent), 2 * rent as i128);
+
+        // Revenue already withdrawn can't be refunded
+        assert!(pool.take_refund(1_000, u64::MAX, rent).is_err());
+    }
+
+    #[test]
+    fn pools_settle_for_closing_once_the_vaults_owe_nothing() {
+        let mut pool = pool_with_items(&[100, 200], 1_000);
+        // The reserve recorded at creation wins over the rent sysvar
+        pool.vault_rent_reserve = 890_880;
+        assert_eq!(pool.rent_reserve().unwrap(), 890_880);
+        assert!(pool.is_settled());
+
+        pool.outstanding_liabilities = 1;
+        assert!(!pool.is_settled());
+        pool.outstanding_liabilities = 0;
+        pool.jackpot_accumulated = 1;
+        assert!(!pool.is_settled());
+        pool.jackpot_accumulated = 0;
+        pool.pending_withdrawal = Some(PendingWithdrawal {
+            amount: 1,
+            withdrawable_after: 0,
+        });
+        assert!(!pool.is_settled());
+        pool.pending_withdrawal = None;
+
+        pool.add_token_prize(pool.items[0].item_id, Pubkey::new_unique(), 10)
+            .unwrap();
+        assert!(pool.is_settled());
+        pool.fund_token_prize(pool.items[0].item_id, 10).unwrap();
+        assert!(!pool.is_settled());
+        pool.drain_token_prize(pool.items[0].item_id).unwrap();
+        assert!(pool.is_settled());
+    }
+
+    #[test]
+    fn pools_can_keep_their_own_keys_from_playing() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        let co_authority = Pubkey::new_unique();
+        let operator = Pubkey::new_unique();
+        pool.co_authority = Some(co_authority);
+        pool.spin_operator = Some(operator);
+        assert!(pool.authority_can_play());
+        assert!(pool.check_player(&pool.authority).is_ok());
+
+        pool.authority_play_disabled = true;
+        assert!(!pool.authority_can_play());
+        for key in [pool.authority, co_authority, operator] {
+            assert!(pool.check_player(&key).is_err());
+        }
+        assert!(pool.check_player(&Pubkey::new_unique()).is_ok());
+    }
+
+    #[test]
+    fn item_profitability_reads_the_stored_odds() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        pool.items[0].probability = 7500;
+        pool.items[1].probability = 2500;
+
+        // Won three spins in four at 10 lamports a spin
+        let cheap = pool.item_profitability(0).unwrap();
+        assert_eq!(cheap.expected_spins, 1_333_333_333);
+        assert_eq!(cheap.expected_cost, 13);
+        assert_eq!(cheap.profit, 87);
+        assert_eq!(cheap.profit_bps, 66923);
+
+        // Spins that win nothing make every item dearer
+        pool.no_win_bps = 5000;
+        let dear = pool.item_profitability(1).unwrap();
+        assert_eq!(dear.expected_spins, 8 * ODDS_SCALE);
+        assert_eq!(dear.expected_cost, 80);
+        assert_eq!(dear.profit, 120);
+        assert_eq!(dear.profit_bps, 15000);
+
+        pool.items[1].probability = 0;
+        assert_eq!(pool.item_profitability(1), None);
+        assert_eq!(pool.item_profitability(2), None);
+    }
+
+    #[test]
+    fn slot_limits_count_every_buyer_until_the_slot_moves_on() {
+        let mut pool = pool_with_items(&[100, 200], 1_000);
+        pool.record_slot_purchases(u32::MAX, 1).unwrap();
+        assert_eq!(pool.purchases_in_slot, 0);
+
+        pool.max_tickets_per_slot = Some(3);
+        pool.record_slot_purchases(2, 10).unwrap();
+        assert!(pool.record_slot_purchases(2, 10).is_err());
+        // A rejected batch leaves the count as it was
+        assert_eq!(pool.purchases_in_slot, 2);
+        pool.record_slot_purchases(1, 10).unwrap();
+        assert!(pool.record_slot_purchases(1, 10).is_err());
+
+        pool.record_slot_purchases(3, 11).unwrap();
+        assert_eq!(pool.last_purchase_slot, 11);
+        assert_eq!(pool.purchases_in_slot, 3);
+        assert!(pool.record_slot_purchases(4, 12).is_err());
+    }
+
+    #[test]
+    fn happy_hours_discount_tickets_inside_their_window() {
+        let hour = 3600;
+        let mut pool = pool_with_items(&[100, 200], 1_000);
+        pool.happy_hour = HappyHour {
+            start_hour_utc: 18,
+            end_hour_utc: 20,
+            discount_bps: 2500,
+        };
+        assert!(pool.happy_hour.is_valid());
+        assert_eq!(pool.ticket_price_at(17 * hour + 3599).unwrap(), 1_000);
+        assert_eq!(pool.ticket_price_at(18 * hour).unwrap(), 750);
+        assert_eq!(pool.ticket_price_at(19 * hour + 3599).unwrap(), 750);
+        assert_eq!(pool.ticket_price_at(20 * hour).unwrap(), 1_000);
+        // Any day, and before 1970 too
+        assert_eq!(pool.ticket_price_at(400 * 86400 + 18 * hour).unwrap(), 750);
+        assert_eq!(pool.ticket_price_at(-86400 + 18 * hour).unwrap(), 750);
+
+        // Across midnight
+        pool.happy_hour.start_hour_utc = 22;
+        pool.happy_hour.end_hour_utc = 2;
+        for (at, price) in [
+            (21, 1_000),
+            (22, 750),
+            (23, 750),
+            (24, 750),
+            (25, 750),
+            (26, 1_000),
+        ] {
+            assert_eq!(pool.ticket_price_at(at * hour).unwrap(), price);
+        }
+
+        for invalid in [
+            HappyHour {
+                discount_bps: 10000,
+                ..pool.happy_hour
+            },
+            HappyHour {
+                end_hour_utc: 22,
+                ..pool.happy_hour
+            },
+            HappyHour {
+                start_hour_utc: 24,
+                ..pool.happy_hour
+            },
+        ] {
+            assert!(!invalid.is_valid());
+        }
+        pool.version = CompanyPool::VERSION_AUTHORITY_PLAY;
+        assert_eq!(pool.ticket_price_at(23 * hour).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn cloned_pools_copy_settings_but_not_state() {
+        let mut source = pool_with_items(&[100, 200, 300], 10);
+        source.items[0].times_won = 4;
+        source.items[2].item_kind = ItemKind::Token;
+        source
+            .add_token_prize(source.items[2].item_id, Pubkey::new_unique(), 5)
+            .unwrap();
+        source
+            .fund_token_prize(source.items[2].item_id, 50)
+            .unwrap();
+        source.sponsors[0] = ItemSponsor {
+            sponsor: Pubkey::new_unique(),
+            item_id: source.items[1].item_id,
+            share_bps: 300,
+            paid: 70,
+        };
+        source.trade_in_bps = 2500;
+        source.total_tickets_sold = 12;
+        source.jackpot_accumulated = 9;
+
+        let mut clone = pool_with_items(&[1], 1);
+        clone.copy_settings_from(&source).unwrap();
+        clone.recalculate_probabilities().unwrap();
+
+        assert_eq!(clone.ticket_price, source.ticket_price);
+        assert_eq!(clone.items.len(), 3);
+        assert!(clone.items.iter().all(|item| item.times_won == 0));
+        assert_eq!(clone.items[1].name, source.items[1].name);
+        assert_eq!(clone.trade_in_bps, 2500);
+        assert_eq!(clone.total_tickets_sold, 0);
+        assert_eq!(clone.jackpot_accumulated, 0);
+
+        // The token item sits out until the clone's own escrow is funded
+        assert!(!clone.items[2].available);
+        assert_eq!(clone.items[2].probability, 0);
+        let prize = clone.token_prize(clone.items[2].item_id).unwrap();
+        assert_eq!((prize.amount, prize.escrowed), (5, 0));
+        assert_eq!(clone.sponsor_slots().len(), 1);
+        assert_eq!(clone.sponsor_slots()[0].share_bps, 300);
+        assert_eq!(clone.sponsor_slots()[0].paid, 0);
+    }
+
+    #[test]
+    fn boosts_shift_draws_only_inside_their_window() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        let item_id = pool.items[1].item_id;
+        let base = pool.items[1].probability;
+        pool.boosts[1] = ItemBoost {
+            item_id,
+            multiplier_bps: 20000,
+            starts_at: 100,
+            ends_at: 200,
+        };
+        assert_eq!(pool.boosted_probability(&pool.items[1], 150), 2 * base);
+        assert_eq!(
+            pool.boosted_probability(&pool.items[0], 150),
+            pool.items[0].probability
+        );
+
+        let wins_at = |pool: &CompanyPool, now: i64| {
+            (0..10_000u64)
+                .filter(|&seed| {
+                    pool.draw_at(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15), None, now)
+                        .unwrap()
+                        == Some(1)
+                })
+                .count() as u32
+        };
+        // The boosted item's share becomes 2p / (2p + (10000 - p))
+        let boosted_bps = 2 * base as u64 * 10000 / (10000 + base as u64);
+        for (now, expected) in [(99, base as u64), (150, boosted_bps), (200, base as u64)] {
+            let wins = wins_at(&pool, now) as u64;
+            assert!(
+                wins.abs_diff(expected) < 300,
+                "{wins} wins at {now}, expected {expected}"
+            );
+        }
+        assert!(!pool.boost_active(200));
+        // Outside the window the table draw is used unchanged
+        for seed in 0..100u64 {
+            assert_eq!(
+                pool.draw_at(seed, None, 200).unwrap(),
+                pool.draw(seed, None).unwrap()
+            );
+        }
+
+        // The item's own slot is reused, then any empty or expired one
+        assert_eq!(pool.boost_slot(item_id, 150), Some(1));
+        assert_eq!(pool.boost_slot(pool.items[0].item_id, 150), Some(0));
+        pool.boosts[0] = ItemBoost {
+            item_id: pool.items[0].item_id,
+            multiplier_bps: 15000,
+            starts_at: 0,
+            ends_at: 300,
+        };
+        assert_eq!(pool.boost_slot(99, 150), None);
+        assert_eq!(pool.boost_slot(99, 200), Some(1));
+
+        pool.version = CompanyPool::VERSION_ACHIEVEMENT_BONUS;
+        assert!(!pool.boost_active(150));
+    }
+
+    #[test]
+    fn token_wins_hold_their_escrow_until_claimed() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        pool.items[1].item_kind = ItemKind::Token;
+        pool.add_token_prize(1, Pubkey::new_unique(), 40).unwrap();
+        pool.fund_token_prize(1, 100).unwrap();
+
+        // A discarded win puts its tokens back
+        let (won_item, _) = pool.award_item(1).unwrap();
+        let prize = pool.token_prize(1).unwrap();
+        assert_eq!((prize.escrowed, prize.owed), (60, 40));
+        pool.return_award(&won_item).unwrap();
+        assert_eq!(pool.token_prize(1).unwrap().escrowed, 100);
+
+        // The win that leaves less than `amount` behind retires the item
+        pool.award_item(1).unwrap();
+        assert!(pool.items[1].available);
+        pool.award_item(1).unwrap();
+        assert!(!pool.items[1].available);
+        assert_eq!(pool.items[1].probability, 0);
+        assert_eq!(pool.items[0].probability, 10000);
+        assert!(pool.reserve_token_prize(1).is_err());
+
+        assert_eq!(pool.pay_token_prize(1).unwrap().amount, 40);
+        assert_eq!(pool.drain_token_prize(1).unwrap(), 20);
+        assert_eq!(pool.token_prize(1).unwrap().owed, 40);
+
+        // Older layouts have no token prizes
+        pool.version = CompanyPool::VERSION_MIN_ITEM_PRICE;
+        assert!(pool.token_prize(1).is_none());
+    }
+
+    #[test]
+    fn revealed_items_leave_the_pending_list() {
+        let mut pool = pool_with_items(&[100, 200, 300], 10);
+        let hash = PendingReveal::hash_of("MacBook", "mac.png", "16 inch");
+        // Text moved across a field boundary hashes differently
+        assert_ne!(
+            hash,
+            PendingReveal::hash_of("MacBoo", "kmac.png", "16 inch")
+        );
+        pool.pending_reveals[0] = PendingReveal {
+            item_id: 1,
+            reveal_hash: hash,
+        };
+        pool.pending_reveals[1] = PendingReveal {
+            item_id: 2,
+            reveal_hash: [7; 32],
+        };
+        assert!(pool.reveal_pending(1) && pool.reveal_pending(2));
+        assert!(!pool.reveal_pending(0));
+
+        pool.clear_pending_reveal(0);
+        assert!(!pool.reveal_pending(1));
+        assert_eq!(pool.pending_reveals().len(), 1);
+        assert_eq!(pool.pending_reveals[0].item_id, 2);
+        pool.clear_pending_reveal(0);
+        assert!(pool.pending_reveals().is_empty());
+
+        pool.pending_reveals[0].reveal_hash = hash;
+        pool.version = CompanyPool::VERSION_BOOSTS;
+        assert!(!pool.reveal_pending(0));
+    }
+
+    #[test]
+    fn draws_skip_unavailable_items() {
+        let mut pool = pool_with_items(&[100, 200, 300], 10);
+        pool.items[0].available = false;
+        pool.items[2].available = false;
+        pool.rebuild_cumulative_table();
+        for seed in 0..50u64 {
+            assert_eq!(
+                pool.draw(seed.wrapping_mul(0x9E37_79B9), None).unwrap(),
+                Some(1)
+            );
+        }
+
+        pool.items[1].available = false;
+        pool.rebuild_cumulative_table();
+        assert!(pool.draw(0, None).is_err());
+    }
+
+    #[test]
+    fn table_draws_match_the_linear_scan_for_every_roll() {
+        // xorshift keeps the cases reproducible without a proptest dependency
+        let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..100 {
+            let count = (next() % CompanyPool::MAX_ACCOUNT_ITEMS as u64 + 1) as usize;
+            let prices: Vec<u64> = (0..count).map(|_| next() % 1_000_000 + 1).collect();
+            let mut pool = pool_with_items(&prices, next() % 10_000 + 1);
+            for item in pool.items.iter_mut() {
+                item.available = next() % 4 != 0;
+            }
+            pool.rebuild_cumulative_table();
+
+            // What draws did before the table: filter, then walk the list
+            let available: Vec<(usize, u32)> = pool
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.available && item.probability > 0)
+                .map(|(index, item)| (index, item.probability))
+                .collect();
+            let probabilities: Vec<u32> = available.iter().map(|(_, weight)| *weight).collect();
+            let total: u64 = probabilities.iter().map(|&weight| weight as u64).sum();
+            if total == 0 {
+                assert!(pool.draw(0, None).is_err());
+                continue;
+            }
+
+            // Both picks only depend on the seed modulo the total, so covering
+            // every roll covers every seed
+            for roll in 0..total {
+                let seed = roll + total * (next() % (u64::MAX / total - 1));
+                let expected =
+                    select_winning_item_index(&probabilities, seed).map(|i| available[i].0);
+                assert_eq!(pool.draw(seed, None).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn guaranteed_draws_never_land_below_the_tier() {
+        let mut pool = pool_with_items(&[20, 50, 100, 400], 10);
+        pool.no_win_bps = 5000;
+        for (item, tier) in pool.items.iter_mut().zip([
+            ItemTier::Common,
+            ItemTier::Common,
+            ItemTier::Rare,
+            ItemTier::Epic,
+        ]) {
+            item.tier = tier;
+        }
+
+        let mut unrestricted_commons = 0;
+        let mut unrestricted_losses = 0;
+        for seed in 0..500u64 {
+            let seed = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            let index = pool.draw(seed, Some(ItemTier::Rare)).unwrap().unwrap();
+            assert!(pool.items[index].tier >= ItemTier::Rare);
+
+            match pool.draw(seed, None).unwrap() {
+                Some(index) if pool.items[index].tier == ItemTier::Common => {
+                    unrestricted_commons += 1
+                }
+                None => unrestricted_losses += 1,
+                _ => {}
+            }
+        }
+        assert!(unrestricted_commons > 0);
+        assert!(unrestricted_losses > 0);
+
+        // No qualifying item left means the guaranteed ticket can't be spun yet
+        pool.items[2].available = false;
+        pool.items[3].available = false;
+        assert!(pool.draw(0, Some(ItemTier::Rare)).is_err());
+    }
+
+    #[test]
+    fn bundle_price_is_shared_across_its_tickets() {
+        let bundle = BundleDefinition {
+            size: 3,
+            price: 1_000,
+            guaranteed_tier: ItemTier::Rare,
+        };
+        let shares: Vec<u64> = (0..bundle.size)
+            .map(|position| bundle.ticket_price_paid(position))
+            .collect();
+        assert_eq!(shares, vec![334, 333, 333]);
+        assert_eq!(shares.iter().sum::<u64>(), bundle.price);
+    }
+
+    #[test]
+    fn loyalty_points_accrue_and_redeem() {
+        let mut pool = pool_with_items(&[100], 10);
+        let mut state = UserPoolState {
+            version: UserPoolState::VERSION,
+            tickets_bought: 0,
+            last_purchase_at: 0,
+            bump: 0,
+            loyalty_points: 0,
+            spin_delegate: None,
+            spin_delegate_expires_at: 0,
+            current_streak: 0,
+            last_played_day: 0,
+            achievements: 0,
+            achievements_rewarded: 0,
+            spins: 0,
+            legendary_wins: 0,
+            jackpot_wins: 0,
+            last_season: 0,
+        };
+
+        // Nothing accrues while the scheme is off
+        state.credit_loyalty_points(&pool, 3).unwrap();
+        assert_eq!(state.loyalty_points, 0);
+        assert!(state.spend_loyalty_points(&pool).is_err());
+
+        pool.loyalty_enabled = true;
+        pool.loyalty_points_per_ticket = 10;
+        pool.points_per_free_ticket = 25;
+
+        state.credit_loyalty_points(&pool, 2).unwrap();
+        assert!(state.spend_loyalty_points(&pool).is_err());
+        assert_eq!(state.loyalty_points, 20);
+
+        state.credit_loyalty_points(&pool, 1).unwrap();
+        state.spend_loyalty_points(&pool).unwrap();
+        assert_eq!(state.loyalty_points, 5);
+    }
+
+    #[test]
+    fn streaks_count_consecutive_utc_days() {
+        let mut pool = pool_with_items(&[100], 10);
+        let mut state = UserPoolState {
+            version: UserPoolState::VERSION,
+            tickets_bought: 0,
+            last_purchase_at: 0,
+            bump: 0,
+            loyalty_points: 0,
+            spin_delegate: None,
+            spin_delegate_expires_at: 0,
+            current_streak: 0,
+            last_played_day: 0,
+            achievements: 0,
+            achievements_rewarded: 0,
+            spins: 0,
+            legendary_wins: 0,
+            jackpot_wins: 0,
+            last_season: 0,
+        };
+        let day = UserPoolState::SECONDS_PER_DAY;
+        let start = 19_000 * day;
+
+        // Nothing is tracked without milestones
+        assert_eq!(state.record_play_day(&pool, start).unwrap(), None);
+        assert_eq!(state.current_streak, 0);
+
+        pool.streak_milestones[0] = StreakMilestone {
+            days: 2,
+            bonus_points: 50,
+        };
+        pool.streak_milestones[1] = StreakMilestone {
+            days: 4,
+            bonus_points: 200,
+        };
+        assert_eq!(pool.streak_bonus(3), 0);
+
+        let update = state.record_play_day(&pool, start + day - 1).unwrap();
+        assert_eq!(
+            update,
+            Some(StreakUpdate {
+                current_streak: 1,
+                bonus_points: 0
+            })
+        );
+        // The last second of the day and the first of the next are a day apart
+        let update = state.record_play_day(&pool, start + day).unwrap();
+        assert_eq!(
+            update,
+            Some(StreakUpdate {
+                current_streak: 2,
+                bonus_points: 50
+            })
+        );
+        assert_eq!(
+            state.record_play_day(&pool, start + 2 * day - 1).unwrap(),
+            None
+        );
+        assert_eq!(state.loyalty_points, 50);
+
+        // Skipping a day starts over
+        let update = state.record_play_day(&pool, start + 3 * day).unwrap();
+        assert_eq!(update.unwrap().current_streak, 1);
+        assert_eq!(state.last_played_day, 19_003);
+
+        pool.version = CompanyPool::VERSION_SPONSORS;
+        assert!(pool.streak_milestones().is_empty());
+        assert_eq!(state.record_play_day(&pool, start + 4 * day).unwrap(), None);
+    }
+
+    #[test]
+    fn entering_a_season_clears_only_what_it_reset() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        let progressed = UserPoolState {
+            version: UserPoolState::VERSION,
+            tickets_bought: 3,
+            last_purchase_at: 0,
+            bump: 0,
+            loyalty_points: 40,
+            spin_delegate: None,
+            spin_delegate_expires_at: 0,
+            current_streak: 2,
+            last_played_day: 19_000,
+            achievements: 1,
+            achievements_rewarded: 1,
+            spins: 3,
+            legendary_wins: 0,
+            jackpot_wins: 0,
+            last_season: 0,
+        };
+
+        // Nothing to do within the same season
+        let mut state = progressed.clone();
+        state.enter_season(&pool);
+        assert_eq!(state.loyalty_points, 40);
+
+        pool.start_season(
+            1,
+            SeasonResetFlags {
+                streaks: true,
+                ..SeasonResetFlags::default()
+            },
+            1_000,
+        );
+        pool.start_season(
+            2,
+            SeasonResetFlags {
+                achievements: true,
+                ..SeasonResetFlags::default()
+            },
+            2_000,
+        );
+        state.enter_season(&pool);
+        assert_eq!(state.last_season, 2);
+        assert_eq!((state.current_streak, state.last_played_day), (0, 0));
+        assert_eq!((state.achievements, state.spins), (0, 0));
+        assert_eq!((state.loyalty_points, state.tickets_bought), (40, 3));
+
+        // Someone who already played in season 1 keeps their streak
+        let mut state = UserPoolState {
+            last_season: 1,
+            ..progressed.clone()
+        };
+        state.enter_season(&pool);
+        assert_eq!(state.current_streak, 2);
+        assert_eq!(state.achievements_rewarded, 0);
+
+        // Old layouts never start a season
+        pool.version = CompanyPool::VERSION_INSURANCE;
+        let mut state = progressed;
+        state.enter_season(&pool);
+        assert_eq!((state.achievements, state.last_season), (1, 0));
+    }
+
+    #[test]
+    fn achievements_unlock_once_from_the_rules() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        pool.items[1].tier = ItemTier::Legendary;
+        pool.items[1].jackpot = true;
+        let mut state = UserPoolState {
+            version: UserPoolState::VERSION,
+            tickets_bought: 0,
+            last_purchase_at: 0,
+            bump: 0,
+            loyalty_points: 0,
+            spin_delegate: None,
+            spin_delegate_expires_at: 0,
+            current_streak: 0,
+            last_played_day: 0,
+            achievements: 0,
+            achievements_rewarded: 0,
+            spins: 0,
+            legendary_wins: 0,
+            jackpot_wins: 0,
+            last_season: 0,
+        };
+
+        assert_eq!(state.record_spin(None), vec![Achievement::FirstSpin]);
+        assert!(state.record_spin(Some(&pool.items[0])).is_empty());
+        assert!(state
+            .mark_achievement_rewarded(Achievement::TenSpins)
+            .is_err());
+
+        assert_eq!(
+            state.record_spin(Some(&pool.items[1])),
+            vec![Achievement::FirstLegendary, Achievement::JackpotWinner]
+        );
+        for _ in 3..9 {
+            assert!(state.record_spin(None).is_empty());
+        }
+        assert_eq!(state.record_spin(None), vec![Achievement::TenSpins]);
+        assert!(state.record_spin(Some(&pool.items[1])).is_empty());
+        assert_eq!(state.achievements, 0b1111);
+        assert_eq!(
+            (state.spins, state.legendary_wins, state.jackpot_wins),
+            (11, 2, 2)
+        );
+
+        state
+            .mark_achievement_rewarded(Achievement::FirstLegendary)
+            .unwrap();
+        assert!(state
+            .mark_achievement_rewarded(Achievement::FirstLegendary)
+            .is_err());
+        assert_eq!(
+            state.achievements_rewarded,
+            Achievement::FirstLegendary.bit()
+        );
+    }
+
+    #[test]
+    fn price_change_rebuilds_probability_table() {
+        let prices = [100, 500, 2000];
+        let mut pool = pool_with_items(&prices, 10);
+
+        pool.ticket_price = 1000;
+        pool.recalculate_probabilities().unwrap();
+
+        let new_table: Vec<u32> = pool.items.iter().map(|item| item.probability).collect();
+        let expected: Vec<u32> = pool_with_items(&prices, 1000)
+            .items
+            .iter()
+            .map(|item| item.probability)
+            .collect();
+        assert_eq!(new_table, expected);
+        assert_eq!(new_table.iter().sum::<u32>(), 10000);
+    }
+
+    #[test]
+    fn weighting_modes_shape_the_distribution() {
+        let prices = [100u64, 500, 2000];
+        let table = |weighting| {
+            calculate_item_probabilities_with_weighting(
+                prices,
+                10,
+                weighting,
+                &[1, 1, 2],
+                ProbabilityBounds::default(),
+            )
+            .unwrap()
+        };
+
+        // The default mode is the original 1.5 exponent
+        assert_eq!(
+            table(WeightingMode::default()),
+            calculate_item_probabilities(&prices, 10).unwrap()
+        );
+
+        // A steeper exponent concentrates the odds on the cheapest item
+        let linear = table(WeightingMode::TicketRatio {
+            exponent_milli: 1000,
+        });
+        let squared = table(WeightingMode::TicketRatio {
+            exponent_milli: 2000,
+        });
+        assert!(squared[0] > linear[0]);
+        assert!(squared[2] < linear[2]);
+        assert_eq!(squared.iter().sum::<u32>(), 10000);
+
+        // Inverse value ignores the ticket price, so it matches exponent 1
+        assert_eq!(table(WeightingMode::InverseValue), linear);
+        assert_eq!(table(WeightingMode::Manual), vec![2500, 2500, 5000]);
+
+        assert!(!WeightingMode::TicketRatio {
+            exponent_milli: 499
+        }
+        .is_valid());
+        assert!(!WeightingMode::TicketRatio {
+            exponent_milli: 3001
+        }
+        .is_valid());
+    }
+
+    // Sum is exactly 10000 and every item is within 1 bp of its exact share
+    fn assert_fair_apportionment(calculator: &WeightedProbabilityCalculator) {
+        let total: u32 = calculator.items.iter().map(|item| item.probability).sum();
+        assert_eq!(total, 10000);
+
+        for item in &calculator.items {
+            let exact = item.weight / calculator.total_weight * 10000.0;
+            assert!(
+                (item.probability as f64 - exact).abs() < 1.0,
+                "{} bp vs exact {}",
+                item.probability,
+                exact
+            );
+        }
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(500))]
+
+        #[test]
+        fn normalization_is_fair_over_random_item_sets(
+            values in proptest::collection::vec(1..=1_000_000u64, 1..=10),
+            ticket_price in 1..=10_000u64,
+            weights in proptest::collection::vec(1..=1000u32, 10),
+        ) {
+            let items: Vec<(String, u64, u32)> = values
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| (format!("Item{}", i), value, 1))
+                .collect();
+
+            let calculator = WeightedProbabilityCalculator::new(items.clone(), ticket_price);
+            assert_fair_apportionment(&calculator);
+
+            let calculator = WeightedProbabilityCalculator::with_weighting(
+                items,
+                ticket_price,
+                WeightingMode::Manual,
+                &weights[..values.len()],
+            );
+            assert_fair_apportionment(&calculator);
+        }
+    }
+
+    #[test]
+    fn equal_items_are_apportioned_deterministically() {
+        let equal_items = |count: usize| vec![100; count];
+
+        let ten = calculate_item_probabilities(&equal_items(10), 10).unwrap();
+        assert_eq!(ten, vec![1000; 10]);
+
+        // The one leftover point goes to the first item, not the last
+        let three = calculate_item_probabilities(&equal_items(3), 10).unwrap();
+        assert_eq!(three, vec![3334, 3333, 3333]);
+        assert_eq!(
+            three,
+            calculate_item_probabilities(&equal_items(3), 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn probability_bounds_lift_unwinnable_items() {
+        let prices = [1u64, 1, 1, 1_000_000];
+        let table = |bounds| {
+            calculate_item_probabilities_with_weighting(
+                prices,
+                1,
+                WeightingMode::default(),
+                &[],
+                bounds,
+            )
+        };
+
+        // Unclamped, the flagship prize rounds down to nothing
+        assert_eq!(table(ProbabilityBounds::default()).unwrap()[3], 0);
+
+        let floored = table(ProbabilityBounds::new(Some(50), None)).unwrap();
+        assert_eq!(floored[3], 50);
+        assert_eq!(floored.iter().sum::<u32>(), 10000);
+
+        let capped = table(ProbabilityBounds::new(Some(50), Some(3000))).unwrap();
+        assert_eq!(capped, vec![3000, 3000, 3000, 1000]);
+
+        // Four items can't each stay under 20%
+        assert!(table(ProbabilityBounds::new(None, Some(2000))).is_err());
+        assert!(table(ProbabilityBounds::new(Some(2600), None)).is_err());
+    }
+
+    #[test]
+    fn expected_value_weights_prices_by_probability() {
+        let mut pool = pool_with_items(&[100, 300], 10);
+        pool.items[0].probability = 7500;
+        pool.items[1].probability = 2500;
+        assert_eq!(pool.expected_value().unwrap(), 150);
+
+        // Rounds down
+        pool.items[1].price = 301;
+        assert_eq!(pool.expected_value().unwrap(), 150);
+
+        pool.items[0].price = u64::MAX;
+        pool.items[0].probability = 10000;
+        pool.items[1].probability = 0;
+        assert_eq!(pool.expected_value().unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn expected_value_counts_losing_spins() {
+        let mut pool = pool_with_items(&[1000], 10);
+        pool.no_win_bps = 6000;
+        assert_eq!(pool.expected_value().unwrap(), 400);
+
+        pool.consolation_lamports = 50;
+        assert_eq!(pool.expected_value().unwrap(), 430);
+    }
+
+    #[test]
+    fn tiers_follow_probability_order() {
+        let probabilities = [500, 4000, 100, 2500, 1500, 900, 300, 200];
+        let tiers = ItemTier::assign_by_probability(&probabilities);
+
+        // Two items per quartile, likeliest first
+        assert_eq!(
+            tiers,
+            vec![
+                ItemTier::Epic,
+                ItemTier::Common,
+                ItemTier::Legendary,
+                ItemTier::Common,
+                ItemTier::Rare,
+                ItemTier::Rare,
+                ItemTier::Epic,
+                ItemTier::Legendary,
+            ]
+        );
+
+        // A more likely item is never in a rarer tier
+        for a in 0..probabilities.len() {
+            for b in 0..probabilities.len() {
+                if probabilities[a] > probabilities[b] {
+                    assert!((tiers[a] as u8) <= (tiers[b] as u8));
+                }
+            }
+        }
+
+        assert_eq!(
+            ItemTier::assign_by_probability(&[10000]),
+            vec![ItemTier::Common]
+        );
+    }
+
+    #[test]
+    fn reserved_balance_covers_rent_liabilities_and_jackpot() {
+        let mut pool = pool_with_items(&[1_000], 1_000);
+        assert_eq!(pool.reserved_balance(890_880), 890_880);
+
+        pool.outstanding_liabilities = 500;
+        pool.jackpot_accumulated = 200;
+        assert_eq!(pool.reserved_balance(890_880), 891_580);
+
+        pool.outstanding_liabilities = u64::MAX;
+        assert_eq!(pool.reserved_balance(890_880), u64::MAX);
+    }
+
+    #[test]
+    fn pending_withdrawal_is_reserved() {
+        let mut pool = pool_with_items(&[1_000], 1_000);
+        pool.pending_withdrawal = Some(PendingWithdrawal {
+            amount: 300,
+            withdrawable_after: 0,
+        });
+        assert_eq!(pool.reserved_balance(890_880), 891_180);
+    }
+
+    #[test]
+    fn daily_withdrawal_cap_resets_after_24_hours() {
+        let mut pool = pool_with_items(&[1_000], 1_000);
+        pool.max_daily_withdrawal = Some(1_000);
+        let day = CompanyPool::WITHDRAWAL_WINDOW_SECONDS;
+        let start = 1_700_000_000;
+
+        pool.record_withdrawal(600, start).unwrap();
+        assert_eq!(pool.daily_withdrawal_remaining(start + 60), 400);
+        assert!(pool.record_withdrawal(500, start + 60).is_err());
+        pool.record_withdrawal(400, start + day - 1).unwrap();
+
+        // The window runs from the first withdrawal, not from midnight
+        assert_eq!(pool.daily_withdrawal_remaining(start + day), 1_000);
+        pool.record_withdrawal(1_000, start + day).unwrap();
+        assert_eq!(pool.withdrawal_day_start, start + day);
+        assert!(pool.record_withdrawal(1, start + day + 1).is_err());
+    }
+
+    #[test]
+    fn co_authority_must_co_sign_when_set() {
+        let mut pool = pool_with_items(&[1_000], 1_000);
+        let co_authority = Pubkey::new_unique();
+        assert!(pool.check_co_authority(None).is_ok());
 
-#[account]
-pub struct UserTicket {
-    pub owner: Pubkey,
-    pub company_pool: Pubkey,
-    pub purchased_at: i64,
-    pub used: bool,
-    pub ticket_id: u64,
-    pub won_item: Option<WonItem>, // Store the item they won
-    pub reward_claimed: bool, // Track if reward has been claimed
-}
+        pool.co_authority = Some(co_authority);
+        assert!(pool.check_co_authority(None).is_err());
+        assert!(pool.check_co_authority(Some(Pubkey::new_unique())).is_err());
+        assert!(pool.check_co_authority(Some(co_authority)).is_ok());
+    }
 
-impl UserTicket {
-    // Updated space calculation to include new fields
-    pub const SPACE: usize = 8 + 32 + 32 + 8 + 1 + 8 + 1 + (4 + 54 + 8 + 204 + 204 + 4) + 1;
-}
+    #[test]
+    fn ticket_split_rounds_the_prize_share_down() {
+        let mut pool = pool_with_items(&[100], 10);
+        for (bps, price, prize) in [
+            (0, 1_000, 0),
+            (10000, 1_000, 1_000),
+            (3333, 1_000, 333),
+            (5000, 7, 3),
+            (1, 9_999, 0),
+            (9999, u64::MAX, 18_444_899_399_302_180_659),
+        ] {
+            pool.prize_allocation_bps = bps;
+            let (revenue, prize_share) = pool.split_ticket_payment(price).unwrap();
+            assert_eq!(prize_share, prize, "{} bps of {}", bps, price);
+            assert_eq!(revenue + prize_share, price);
+        }
+    }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct PoolItem {
-    pub image: String,
-    pub price: u64,
-    pub name: String,
-    pub description: String,
-    pub probability: u32,
-    pub available: bool,
-}
+    #[test]
+    fn ticket_sales_only_book_revenue() {
+        let mut pool = pool_with_items(&[100], 10);
+        pool.prize_allocation_bps = 4000;
+        pool.jackpot_contribution_bps = 1000;
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct PoolItemInput {
-    pub image: String,
-    pub price: u64,
-    pub name: String,
-    pub description: String,
-}
+        pool.record_ticket_sale(1_001).unwrap();
+        assert_eq!(pool.total_funds, 601);
+        assert_eq!(pool.jackpot_accumulated, 100);
+    }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct WonItem {
-    pub name: String,
-    pub price: u64,
-    pub image: String,
-    pub description: String,
-    pub item_index: u32,
-}
+    #[test]
+    fn split_pools_keep_prize_obligations_out_of_the_revenue_reserve() {
+        let mut pool = pool_with_items(&[1_000], 1_000);
+        pool.outstanding_liabilities = 500;
+        pool.jackpot_accumulated = 200;
+        assert_eq!(pool.reserved_balance(890_880), 891_580);
 
-// Events
-#[event]
-pub struct RewardClaimedEvent {
-    pub winner: Pubkey,
-    pub ticket_id: u64,
-    pub won_item: WonItem,
-    pub reward_amount: u64,
-    pub timestamp: i64,
-}
+        pool.prize_vault_bump = 254;
+        assert_eq!(pool.reserved_balance(890_880), 890_880);
+    }
 
-#[event]
-pub struct SpinResultEvent {
-    pub spinner: Pubkey,
-    pub won_item: Option<PoolItem>,
-    pub item_index: Option<u32>,
-    pub item_value: u64,
-    pub win_probability: u32,
-    pub random_seed: u64,
-    pub ticket_id: u64,
-    pub timestamp: i64,
-}
+    #[test]
+    fn ledger_reconciles_a_mixed_sequence_to_zero() {
+        let rent = 890_880;
+        let mut pool = pool_with_items(&[100], 10);
+        pool.prize_allocation_bps = 5000;
+        pool.prize_vault_bump = 254;
+        pool.trade_in_bps = 8000;
+        let mut vaults = 2 * rent;
 
-#[event]
-pub struct ProbabilityAnalysisEvent {
-    pub item_name: String,
-    pub item_value: u64,
-    pub probability_basis_points: u32,
-    pub expected_spins: f64,
-    pub expected_cost: f64,
-    pub profit: f64,
-    pub profit_ratio: f64,
-}
+        for _ in 0..3 {
+            pool.record_ticket_sale(1_001).unwrap();
+            vaults += 1_001;
+        }
+        pool.record_reward_paid(100).unwrap();
+        vaults -= 100;
+        pool.record_reward_paid(pool.trade_in_payout(100).unwrap())
+            .unwrap();
+        vaults -= 80;
+        pool.record_withdrawal(500, 0).unwrap();
+        vaults -= 500;
+        pool.total_authority_deposits += 250;
+        vaults += 250;
 
-#[event]
-pub struct PoolInitializedEvent {
-    pub company_name: String,
-    pub ticket_price: u64,
-    pub item_count: u32,
-    pub authority: Pubkey,
-    pub total_probability_check: u32,
-}
+        let report = pool.reconcile(vaults, rent).unwrap();
+        assert_eq!(report.delta, 0);
+        assert_eq!(report.expected_balance, vaults as i64);
+        assert_eq!(pool.total_ticket_revenue, 3_003);
+        assert_eq!(pool.total_rewards_paid, 180);
+        assert_eq!(pool.total_withdrawn, 500);
 
-#[event]
-pub struct TicketPurchasedEvent {
-    pub buyer: Pubkey,
-    pub ticket_price: u64,
-    pub total_tickets_sold: u64,
-    pub ticket_id: u64,
-    pub timestamp: i64,
-}
+        // A direct transfer into a vault shows up as a positive delta
+        assert_eq!(pool.reconcile(vaults + 42, rent).unwrap().delta, 42);
 
-#[event]
-pub struct FundsWithdrawnEvent {
-    pub authority: Pubkey,
-    pub amount_withdrawn: u64,
-    pub remaining_funds: u64,
-    pub timestamp: i64,
-}
+        // Legacy pools only have the revenue vault's rent
+        pool.prize_vault_bump = 0;
+        assert_eq!(pool.reconcile(vaults, rent).unwrap().delta, rent as i64);
+    }
 
-// Error Codes
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Ticket price must be greater than 0")]
-    InvalidTicketPrice,
-    #[msg("At least one item must be provided")]
-    NoItemsProvided,
-    #[msg("Company name is too long (max 50 characters)")]
-    CompanyNameTooLong,
-    #[msg("Company image URL is too long (max 200 characters)")]
-    CompanyImageTooLong,
-    #[msg("Too many items (max 10 allowed)")]
-    TooManyItems,
-    #[msg("Item price must be greater than 0")]
-    InvalidItemPrice,
-    #[msg("Item price cannot be lower than ticket price")]
-    ItemPriceTooLow,
-    #[msg("Item name is too long (max 50 characters)")]
-    ItemNameTooLong,
-    #[msg("Item image URL is too long (max 200 characters)")]
-    ItemImageTooLong,
-    #[msg("Item description is too long (max 200 characters)")]
-    ItemDescriptionTooLong,
-    #[msg("Pool is not active")]
-    PoolInactive,
-    #[msg("No available items to spin")]
-    NoAvailableItems,
-    #[msg("Unauthorized withdrawal attempt")]
-    UnauthorizedWithdrawal,
-    #[msg("No funds available for withdrawal")]
-    NoFundsAvailable,
-    #[msg("Invalid withdrawal amount")]
-    InvalidAmount,
-    #[msg("Insufficient funds for withdrawal")]
-    InsufficientFunds,
-    #[msg("Insufficient vault funds")]
-    InsufficientVaultFunds,
-    #[msg("Math overflow occurred")]
-    MathOverflow,
-    #[msg("Invalid probability calculation")]
-    InvalidProbabilityCalculation,
-    #[msg("Probability sum mismatch")]
-    ProbabilitySumMismatch,
-    #[msg("Failed to select winning item")]
-    ProbabilitySelectionFailed,
-    #[msg("You don't own this ticket")]
-    NotTicketOwner,
-    #[msg("Ticket belongs to different pool")]
-    InvalidTicketPool,
-    #[msg("This ticket has already been used")]
-    TicketAlreadyUsed,
-    #[msg("Ticket must be used (spun) before claiming reward")]
-    TicketNotUsed,
-    #[msg("Reward has already been claimed")]
-    RewardAlreadyClaimed,
-    #[msg("No reward to claim for this ticket")]
-    NoRewardToClaim,
-}
\ No newline at end of file
+    #[test]
+    fn maximal_pool_fits_allocated_space() {
+        let pool = CompanyPool {
+            version: CompanyPool::VERSION,
+            authority: Pubkey::new_unique(),
+            company_name: max_string(50),
+            company_image: max_string(200),
+            ticket_price: u64::MAX,
+            items: (0..10).map(|_| max_pool_item()).collect(),
+            total_value: u64::MAX,
+            total_tickets_sold: u64::MAX,
+            total_funds: u64::MAX,
+            active: true,
+            created_at: i64::MAX,
+            pool_version: CompanyPool::POOL_VERSION,
+            vault_bump: 255,
+            pool_bump: 255,
+            vault_rent_reserve: u64::MAX,
+            max_tickets_per_user: Some(u32::MAX),
+            purchase_cooldown_seconds: Some(u32::MAX),
+            whitelist_enabled: true,
+            gate_mint: Some(Pubkey::new_unique()),
+            gate_min_amount: u64::MAX,
+            gate_collection: Some(Pubkey::new_unique()),
+            gamble_win_bps: Some(10000),
+            gamble_payout_cap: Some(u64::MAX),
+            trade_in_bps: 10000,
+            claim_deadline_seconds: Some(u32::MAX),
+            outstanding_liabilities: u64::MAX,
+            jackpot_contribution_bps: 10000,
+            jackpot_accumulated: u64::MAX,
+            loyalty_enabled: true,
+            loyalty_points_per_ticket: u32::MAX,
+            points_per_free_ticket: u32::MAX,
+            weighting: WeightingMode::TicketRatio {
+                exponent_milli: u16::MAX,
+            },
+            min_probability_bps: Some(u16::MAX),
+            max_probability_bps: Some(u16::MAX),
+            no_win_bps: u16::MAX,
+            consolation_lamports: u64::MAX,
+            withdrawal_timelock_seconds: u32::MAX,
+            pending_withdrawal: Some(PendingWithdrawal {
+                amount: u64::MAX,
+                withdrawable_after: i64::MAX,
+            }),
+            max_daily_withdrawal: Some(u64::MAX),
+            withdrawn_today: u64::MAX,
+            withdrawal_day_start: i64::MAX,
+            co_authority: Some(Pubkey::new_unique()),
+            prize_allocation_bps: u16::MAX,
+            prize_vault_bump: 255,
+            total_ticket_revenue: u64::MAX,
+            total_rewards_paid: u64::MAX,
+            total_withdrawn: u64::MAX,
+            total_authority_deposits: u64::MAX,
+            auto_payout: true,
+            reroll_price: u64::MAX,
+            bundles: vec![
+                BundleDefinition {
+                    size: u8::MAX,
+                    price: u64::MAX,
+                    guaranteed_tier: ItemTier::Legendary,
+                };
+                CompanyPool::MAX_BUNDLES
+            ],
+            cum_probability: [u32::MAX; CompanyPool::MAX_ACCOUNT_ITEMS],
+            pool_flags: u8::MAX,
+            allow_cpi_purchases: true,
+            ends_at: Some(i64::MAX),
+            event_seq: u64::MAX,
+            spin_operator: Some(Pubkey::new_unique()),
+            vesting_threshold_lamports: Some(u64::MAX),
+            vesting_duration_seconds: u32::MAX,
+            vesting_upfront_bps: u16::MAX,
+            pool_mode: PoolMode::Raffle,
+            sponsors: [ItemSponsor {
+                sponsor: Pubkey::new_unique(),
+                item_id: u32::MAX,
+                share_bps: u16::MAX,
+                paid: u64::MAX,
+            }; CompanyPool::MAX_SPONSORS],
+            streak_milestones: [StreakMilestone {
+                days: u16::MAX,
+                bonus_points: u32::MAX,
+            }; CompanyPool::MAX_STREAK_MILESTONES],
+            achievement_bonus_lamports: u64::MAX,
+            boosts: [ItemBoost {
+                item_id: u32::MAX,
+                multiplier_bps: u32::MAX,
+                starts_at: i64::MAX,
+                ends_at: i64::MAX,
+            }; CompanyPool::MAX_BOOSTS],
+            pending_reveals: [PendingReveal {
+                item_id: u32::MAX,
+                reveal_hash: [u8::MAX; 32],
+            }; CompanyPool::MAX_PENDING_REVEALS],
+            beneficiary: Some(Pubkey::new_unique()),
+            min_item_price: u64::MAX,
+            token_prizes: [ItemTokenPrize {
+                item_id: u32::MAX,
+                mint: Pubkey::new_unique(),
+                amount: u64::MAX,
+                escrowed: u64::MAX,
+                owed: u64::MAX,
+            }; CompanyPool::MAX_TOKEN_PRIZES],
+            server_seeded: true,
+            server_seed: ServerSeedCommitment {
+                hash: [u8::MAX; 32],
+                committed_at: i64::MAX,
+                spins: u32::MAX,
+                previous_seed: [u8::MAX; 32],
+            },
+            dispute_window_seconds: u32::MAX,
+            authority_play_disabled: true,
+            happy_hour: HappyHour {
+                start_hour_utc: 23,
+                end_hour_utc: 22,
+                discount_bps: 9999,
+            },
+            max_tickets_per_slot: Some(u16::MAX),
+            last_purchase_slot: u64::MAX,
+            purchases_in_slot: u16::MAX,
+            total_donations: u64::MAX,
+            mega_jackpot: true,
+            total_mega_jackpot_contributions: u64::MAX,
+            insurance: Some(SpinInsurance {
+                price: u64::MAX,
+                floor_value: u64::MAX,
+                payout: u64::MAX,
+                replaces_prize: true,
+            }),
+            total_insurance_premiums: u64::MAX,
+            total_insurance_paid: u64::MAX,
+            season: u16::MAX,
+            season_started_at: i64::MAX,
+            season_start_tickets_sold: u64::MAX,
+            season_start_revenue: u64::MAX,
+            points_reset_season: u16::MAX,
+            streaks_reset_season: u16::MAX,
+            achievements_reset_season: u16::MAX,
+            shared_tickets_sold: u64::MAX,
+        };
+
+        assert!(serialized_account_len(&pool) <= 8 + CompanyPool::INIT_SPACE);
+
+        // Item-account entries leave their image and description empty
+        let pool = CompanyPool {
+            pool_version: CompanyPool::POOL_VERSION_ITEM_ACCOUNTS,
+            items: (0..CompanyPool::MAX_ACCOUNT_ITEMS)
+                .map(|_| PoolItem {
+                    image: String::new(),
+                    description: String::new(),
+                    ..max_pool_item()
+                })
+                .collect(),
+            ..pool
+        };
+        assert!(serialized_account_len(&pool) <= 8 + CompanyPool::INIT_SPACE);
+    }
+
+    #[test]
+    fn slim_tickets_cost_less_than_half_the_rent() {
+        let rent = Rent::default();
+        let legacy = rent.minimum_balance(8 + LegacyUserTicket::INIT_SPACE);
+        // The slim layout, before the version byte and the fields appended
+        // since
+        let slim_len = 8 + UserTicket::INIT_SPACE
+            - 1
+            - migration::TICKET_SLOTS_SPACE
+            - migration::TICKET_EXTERNAL_ID_SPACE
+            - migration::TICKET_VESTING_SPACE
+            - migration::TICKET_SERIAL_SPACE
+            - migration::TICKET_SPIN_PENDING_SPACE
+            - migration::TICKET_DRAW_RECORD_SPACE
+            - migration::TICKET_MEGA_JACKPOT_SPACE
+            - migration::TICKET_INSURANCE_SPACE;
+        let current = rent.minimum_balance(slim_len);
+        let saved_bps = (legacy - current) * 10000 / legacy;
+        assert!(saved_bps > 5000, "only saved {} bps", saved_bps);
+    }
+
+    #[test]
+    fn tickets_move_only_along_their_state_machine() {
+        let mut data = UserTicket::DISCRIMINATOR.to_vec();
+        data.resize(8 + UserTicket::INIT_SPACE, 0);
+        let mut ticket = UserTicket::try_deserialize(&mut data.as_slice()).unwrap();
+        ticket.fill(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            100,
+            &Clock::default(),
+        );
+        assert_eq!(ticket.state(), TicketState::Unspun);
+        let unspun = ticket.clone();
+        let fails_with = |result: Result<()>, code: ErrorCode| {
+            assert_eq!(result.unwrap_err(), anchor_lang::error::Error::from(code));
+        };
+
+        // Nothing to claim or cancel before a draw
+        fails_with(ticket.mark_claimed(), ErrorCode::TicketNotUsed);
+        fails_with(ticket.cancel_pending_draw(), ErrorCode::SpinNotPending);
+
+        // Unspun -> SpinPending -> Unspun, stuck once the timeout has passed
+        ticket.request_draw(1_000, 5).unwrap();
+        assert_eq!(ticket.state(), TicketState::SpinPending);
+        assert!(ticket.used);
+        fails_with(ticket.request_draw(1_001, 6), ErrorCode::TicketAlreadyUsed);
+        fails_with(ticket.mark_claimed(), ErrorCode::TicketNotUsed);
+        let timeout = UserTicket::SPIN_PENDING_TIMEOUT_SECONDS;
+        assert!(!ticket.is_spin_stuck(1_000 + timeout - 1));
+        assert!(ticket.is_spin_stuck(1_000 + timeout));
+        ticket.cancel_pending_draw().unwrap();
+        assert_eq!(ticket.state(), TicketState::Unspun);
+        assert_eq!((ticket.spun_at, ticket.spun_slot), (0, 0));
+
+        // Unspun -> SpinPending -> Spun -> Claimed
+        ticket.request_draw(2_000, 7).unwrap();
+        ticket.record_draw(2_010, 8).unwrap();
+        assert_eq!(ticket.state(), TicketState::Spun);
+        assert_eq!((ticket.spun_at, ticket.spun_slot), (2_010, 8));
+        assert!(!ticket.is_spin_stuck(i64::MAX));
+        fails_with(ticket.cancel_pending_draw(), ErrorCode::SpinNotPending);
+        fails_with(ticket.record_draw(2_020, 9), ErrorCode::TicketAlreadyUsed);
+        ticket.mark_claimed().unwrap();
+        assert_eq!(ticket.state(), TicketState::Claimed);
+        fails_with(ticket.mark_claimed(), ErrorCode::RewardAlreadyClaimed);
+        fails_with(ticket.request_draw(3_000, 10), ErrorCode::TicketAlreadyUsed);
+
+        // Unspun -> Spun directly, as the same-instruction spin does
+        let mut ticket = unspun;
+        ticket.record_draw(4_000, 11).unwrap();
+        assert_eq!(ticket.state(), TicketState::Spun);
+        assert!(ticket.used && !ticket.spin_pending);
+    }
+
+    #[test]
+    fn tickets_settle_once_nothing_is_left_to_vest_or_ship() {
+        let mut data = UserTicket::DISCRIMINATOR.to_vec();
+        data.resize(8 + UserTicket::INIT_SPACE, 0);
+        let mut ticket = UserTicket::try_deserialize(&mut data.as_slice()).unwrap();
+        ticket.record_draw(1_000, 5).unwrap();
+        ticket.consolation_amount = 1_000;
+        assert!(!ticket.is_settled());
+
+        // A vesting reward is claimed before all of it is paid
+        ticket.mark_claimed().unwrap();
+        ticket.vesting_start = 1_000;
+        ticket.claimed_so_far = 400;
+        assert!(!ticket.is_settled());
+        ticket.claimed_so_far = 1_000;
+        assert!(ticket.is_settled());
+
+        ticket.fulfillment_pending = true;
+        assert!(!ticket.is_settled());
+    }
+
+    #[test]
+    fn insurance_pays_out_below_the_floor_once() {
+        let mut pool = pool_with_items(&[100, 200], 1_000);
+        let insurance = SpinInsurance {
+            price: 50,
+            floor_value: 150,
+            payout: 300,
+            replaces_prize: false,
+        };
+        pool.insurance = Some(insurance);
+        assert!(insurance.is_valid(true));
+        assert!(!SpinInsurance {
+            replaces_prize: true,
+            ..insurance
+        }
+        .is_valid(true));
+        let mut data = UserTicket::DISCRIMINATOR.to_vec();
+        data.resize(8 + UserTicket::INIT_SPACE, 0);
+        let mut ticket = UserTicket::try_deserialize(&mut data.as_slice()).unwrap();
+        ticket.insured = true;
+        assert!(ticket.take_insurance(&mut pool, 10).is_err());
+
+        // Not before the draw settles, then only below the floor
+        ticket.record_draw(1_000, 5).unwrap();
+        assert!(ticket.take_insurance(&mut pool, 5).is_err());
+        ticket.won_item = Some(WonItem {
+            item_id: 1,
+            price: 200,
+            item_kind: ItemKind::SolPayout,
+        });
+        assert!(!ticket.is_insurance_triggered(&pool));
+        assert!(ticket.take_insurance(&mut pool, 6).is_err());
+        ticket.won_item = Some(WonItem {
+            item_id: 0,
+            price: 100,
+            item_kind: ItemKind::SolPayout,
+        });
+        assert!(ticket.is_insurance_triggered(&pool));
+
+        // On top of the prize, which stays claimable
+        pool.add_liability(100).unwrap();
+        let unclaimed = ticket.clone();
+        assert_eq!(
+            ticket.take_insurance(&mut pool, 6).unwrap(),
+            (insurance, false)
+        );
+        assert_eq!(ticket.state(), TicketState::Spun);
+        assert_eq!(pool.outstanding_liabilities, 100);
+        assert!(ticket.take_insurance(&mut pool, 6).is_err());
+
+        // In place of it, giving up what the spin won
+        pool.insurance = Some(SpinInsurance {
+            replaces_prize: true,
+            ..insurance
+        });
+        let mut ticket = unclaimed;
+        assert!(ticket.take_insurance(&mut pool, 6).unwrap().1);
+        assert_eq!(ticket.state(), TicketState::Claimed);
+        assert_eq!(pool.outstanding_liabilities, 0);
+
+        // Uninsured tickets and pools without insurance never pay
+        let mut uninsured = ticket.clone();
+        uninsured.insured = false;
+        uninsured.insurance_claimed = false;
+        assert!(!uninsured.is_insurance_triggered(&pool));
+        assert!(uninsured.take_insurance(&mut pool, 6).is_err());
+        pool.version = CompanyPool::VERSION_INSURANCE - 1;
+        assert!(!ticket.is_insurance_triggered(&pool));
+    }
+
+    #[test]
+    fn shared_reward_splits_to_the_lamport() {
+        let share = |share_bps| TicketShare {
+            owner: Pubkey::new_unique(),
+            share_bps,
+        };
+        let shared = SharedTicket {
+            company_pool: Pubkey::new_unique(),
+            user_ticket: Pubkey::new_unique(),
+            payer: Pubkey::new_unique(),
+            shares: vec![share(3334), share(3333), share(3333)],
+            bump: 255,
+        };
+        assert!(SharedTicket::check_shares(&shared.shares).is_ok());
+
+        // 1000 lamports split three ways leave 1 lamport of dust up front
+        assert_eq!(shared.split(1_000), vec![334, 333, 333]);
+        assert_eq!(shared.split(100), vec![34, 33, 33]);
+        assert_eq!(shared.split(1), vec![1, 0, 0]);
+        assert_eq!(shared.split(0), vec![0, 0, 0]);
+        let parts = shared.split(u64::MAX);
+        assert_eq!(
+            parts.iter().map(|&part| part as u128).sum::<u128>(),
+            u64::MAX as u128
+        );
+
+        assert!(SharedTicket::check_shares(&[share(10000)]).is_err());
+        assert!(SharedTicket::check_shares(&[share(5000), share(4999)]).is_err());
+        assert!(SharedTicket::check_shares(&[share(10000), share(0)]).is_err());
+        assert!(SharedTicket::check_shares(&[(); 5].map(|_| share(2000))).is_err());
+        let duplicate = share(5000);
+        assert!(SharedTicket::check_shares(&[duplicate, duplicate]).is_err());
+    }
+
+    #[test]
+    fn pools_that_sold_shared_tickets_only_add_sol_items() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        for kind in [ItemKind::SolPayout, ItemKind::Physical, ItemKind::Token] {
+            assert!(pool.check_shared_tickets_can_draw(kind).is_ok());
+        }
+
+        pool.shared_tickets_sold = 1;
+        assert!(pool
+            .check_shared_tickets_can_draw(ItemKind::SolPayout)
+            .is_ok());
+        assert!(pool
+            .check_shared_tickets_can_draw(ItemKind::Physical)
+            .is_err());
+        assert!(pool.check_shared_tickets_can_draw(ItemKind::Token).is_err());
+
+        // Older layouts never sold one, whatever their tail holds
+        pool.version = CompanyPool::VERSION_SHARED_TICKETS - 1;
+        assert!(pool.check_shared_tickets_can_draw(ItemKind::Token).is_ok());
+    }
+
+    #[test]
+    fn tickets_read_back_from_every_layout() {
+        let legacy = LegacyUserTicket {
+            owner: Pubkey::new_unique(),
+            company_pool: Pubkey::new_unique(),
+            purchased_at: 10,
+            used: true,
+            ticket_id: 7,
+            won_item: Some(LegacyWonItem {
+                name: max_string(50),
+                price: 1_500,
+                image: max_string(200),
+                description: max_string(200),
+                item_index: 2,
+                item_id: 2,
+                item_kind: ItemKind::Physical,
+                tier: ItemTier::Epic,
+            }),
+            reward_claimed: false,
+            gambled: true,
+            traded_in: false,
+            spun_at: 20,
+            reward_expired: false,
+            reward_nft_mint: None,
+            fulfillment_pending: true,
+            shipping_info_hash: Some([7; 32]),
+            price_paid: 100,
+            consolation_amount: 0,
+            rerolled: true,
+            forced_min_tier: Some(ItemTier::Rare),
+        };
+        let mut data = Vec::new();
+        legacy.serialize(&mut data).unwrap();
+        assert!(8 + data.len() <= 8 + LegacyUserTicket::INIT_SPACE);
+
+        let mut account = UserTicket::DISCRIMINATOR.to_vec();
+        account.extend_from_slice(&data);
+        account.resize(8 + LegacyUserTicket::INIT_SPACE, 0);
+        let ticket = UserTicket::read_any_version(&account).unwrap();
+        assert_eq!(ticket.version, 0);
+        let won_item = ticket.won_item.as_ref().unwrap();
+        assert_eq!((won_item.item_id, won_item.price), (2, 1_500));
+        assert!(won_item.item_kind == ItemKind::Physical);
+        assert_eq!(ticket.ticket_id, 7);
+        assert_eq!(ticket.shipping_info_hash, Some([7; 32]));
+        assert!(ticket.rerolled && ticket.fulfillment_pending);
+        assert_eq!(ticket.forced_min_tier, Some(ItemTier::Rare));
+
+        // The same ticket in the current layout, then without the insurance
+        // flags, then without the mega jackpot win, then without the draw
+        // record, then without the
+        // pending-spin flag, then without the serial number, then without the
+        // vesting progress, then without the external id, then without the
+        // slots, then with the version byte dropped too
+        let mut current = Vec::new();
+        UserTicket {
+            version: UserTicket::VERSION,
+            purchased_slot: 5,
+            external_id: Some([3; 32]),
+            claimed_so_far: 250,
+            vesting_start: 30,
+            serial: 4,
+            spin_pending: true,
+            draw_record: Some(DrawRecord {
+                random_seed: 9,
+                table_hash: [9; 32],
+                drawn_index: Some(2),
+            }),
+            mega_jackpot_won: 12,
+            insured: true,
+            insurance_claimed: true,
+            ..ticket
+        }
+        .try_serialize(&mut current)
+        .unwrap();
+        current.resize(8 + UserTicket::INIT_SPACE, 0);
+        let read = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(read.version, UserTicket::VERSION);
+        assert_eq!(read.external_id, Some([3; 32]));
+        assert_eq!((read.claimed_so_far, read.vesting_start), (250, 30));
+        assert_eq!(read.serial(), Some(4));
+        assert_eq!(read.state(), TicketState::SpinPending);
+        assert_eq!(read.draw_record().unwrap().random_seed, 9);
+        assert_eq!(read.mega_jackpot_won, 12);
+        assert!(read.insured && read.insurance_claimed);
+        current.truncate(current.len() - migration::TICKET_INSURANCE_SPACE);
+        current[8] = 9;
+        let version_nine = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(version_nine.version, 9);
+        assert_eq!(version_nine.mega_jackpot_won, 12);
+        assert!(!version_nine.insured && !version_nine.insurance_claimed);
+        current.truncate(current.len() - migration::TICKET_MEGA_JACKPOT_SPACE);
+        current[8] = 8;
+        let version_eight = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(version_eight.version, 8);
+        assert_eq!(version_eight.draw_record().unwrap().random_seed, 9);
+        assert_eq!(version_eight.mega_jackpot_won, 0);
+        current.truncate(current.len() - migration::TICKET_DRAW_RECORD_SPACE);
+        current[8] = 7;
+        let version_seven = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(version_seven.version, 7);
+        assert!(version_seven.spin_pending);
+        assert_eq!(version_seven.draw_record(), None);
+        assert_eq!(version_seven.draw_record, None);
+        current.truncate(current.len() - migration::TICKET_SPIN_PENDING_SPACE);
+        current[8] = 6;
+        let version_six = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(version_six.version, 6);
+        assert_eq!(version_six.serial(), Some(4));
+        assert!(!version_six.spin_pending);
+        current.truncate(current.len() - migration::TICKET_SERIAL_SPACE);
+        current[8] = 5;
+        let version_five = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(version_five.version, 5);
+        assert_eq!(version_five.vesting_start, 30);
+        assert_eq!(version_five.serial(), None);
+        current.truncate(current.len() - migration::TICKET_VESTING_SPACE);
+        current[8] = 4;
+        let version_four = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(version_four.version, 4);
+        assert_eq!(version_four.external_id, Some([3; 32]));
+        assert_eq!(
+            (version_four.claimed_so_far, version_four.vesting_start),
+            (0, 0)
+        );
+        current.truncate(current.len() - migration::TICKET_EXTERNAL_ID_SPACE);
+        current[8] = 3;
+        let version_three = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(version_three.version, 3);
+        assert_eq!(version_three.purchased_slot, 5);
+        assert_eq!(version_three.external_id, None);
+        current.truncate(current.len() - migration::TICKET_SLOTS_SPACE);
+        current[8] = 2;
+        // The missing reward mint leaves 32 bytes of slack at the end, which
+        // the slots read from
+        let stale_tail = current.len() - 32;
+        current[stale_tail..stale_tail + 16].fill(0xff);
+        let version_two = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(version_two.version, 2);
+        assert_eq!((version_two.purchased_slot, version_two.spun_slot), (0, 0));
+        assert_eq!(version_two.external_id, None);
+        current.remove(8);
+        let version_one = UserTicket::read_any_version(&current).unwrap();
+        assert_eq!(version_one.version, 1);
+        assert_eq!(version_one.ticket_id, 7);
+    }
+
+    #[test]
+    fn older_pool_layouts_read_back_with_a_rebuilt_table() {
+        let mut pool = pool_with_items(&[100, 200], 10);
+        // Set so every optional field after the table takes its full space
+        // in the serialized data
+        pool.ends_at = Some(1);
+        pool.spin_operator = Some(Pubkey::new_unique());
+        pool.vesting_threshold_lamports = Some(1);
+        pool.beneficiary = Some(Pubkey::new_unique());
+        pool.max_tickets_per_slot = Some(5);
+        pool.insurance = Some(SpinInsurance {
+            price: 1,
+            floor_value: 2,
+            payout: 3,
+            replaces_prize: true,
+        });
+        let mut data = Vec::new();
+        pool.try_serialize(&mut data).unwrap();
+        data.resize(8 + CompanyPool::INIT_SPACE, 0);
+        assert_eq!(
+            CompanyPool::read_any_version(&data).unwrap().version,
+            CompanyPool::VERSION
+        );
+
+        // Step down one version at a time, dropping the field each one added
+        // (see CompanyPool::VERSION); whatever the old tail held there is
+        // reset
+        data.truncate(data.len() - migration::SHARED_TICKETS_SPACE);
+        data[8] = 27;
+        let shared_tickets_at = serialized_account_len(&pool) - migration::SHARED_TICKETS_SPACE;
+        data[shared_tickets_at..].fill(0xff);
+        let version_twenty_seven = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_twenty_seven.version, 27);
+        assert_eq!(version_twenty_seven.shared_tickets_sold(), 0);
+        assert_eq!(version_twenty_seven.shared_tickets_sold, 0);
+
+        data.truncate(data.len() - migration::SEASONS_SPACE);
+        data[8] = 26;
+        let seasons_at = shared_tickets_at - migration::SEASONS_SPACE;
+        data[seasons_at..seasons_at + migration::SEASONS_SPACE].fill(0xff);
+        let version_twenty_six = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_twenty_six.version, 26);
+        assert_eq!(version_twenty_six.season(), 0);
+        assert_eq!(version_twenty_six.season, 0);
+        assert_eq!(version_twenty_six.points_reset_season, 0);
+
+        data.truncate(data.len() - migration::INSURANCE_SPACE);
+        data[8] = 25;
+        let insurance_at = seasons_at - migration::INSURANCE_SPACE;
+        data[insurance_at..insurance_at + migration::INSURANCE_SPACE].fill(0xff);
+        data[insurance_at] = 1;
+        data[insurance_at + SpinInsurance::INIT_SPACE] = 1;
+        let version_twenty_five = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_twenty_five.version, 25);
+        assert_eq!(version_twenty_five.insurance(), None);
+        assert_eq!(version_twenty_five.insurance, None);
+        assert_eq!(version_twenty_five.total_insurance_premiums, 0);
+
+        data.truncate(data.len() - migration::MEGA_JACKPOT_SPACE);
+        data[8] = 24;
+        let mega_jackpot_at = insurance_at - migration::MEGA_JACKPOT_SPACE;
+        data[mega_jackpot_at..mega_jackpot_at + migration::MEGA_JACKPOT_SPACE].fill(0xff);
+        data[mega_jackpot_at] = 1;
+        let version_twenty_four = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_twenty_four.version, 24);
+        assert!(!version_twenty_four.joins_mega_jackpot());
+        assert_eq!(version_twenty_four.total_mega_jackpot_contributions, 0);
+
+        data.truncate(data.len() - migration::DONATIONS_SPACE);
+        data[8] = 23;
+        let donations_at = mega_jackpot_at - migration::DONATIONS_SPACE;
+        data[donations_at..donations_at + migration::DONATIONS_SPACE].fill(0xff);
+        let version_twenty_three = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_twenty_three.version, 23);
+        assert_eq!(version_twenty_three.total_donations, 0);
+
+        data.truncate(data.len() - migration::SLOT_LIMIT_SPACE);
+        data[8] = 22;
+        let slot_limit_at = donations_at - migration::SLOT_LIMIT_SPACE;
+        data[slot_limit_at..slot_limit_at + migration::SLOT_LIMIT_SPACE].fill(0xff);
+        data[slot_limit_at] = 1;
+        let mut version_twenty_two = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_twenty_two.version, 22);
+        assert_eq!(version_twenty_two.max_tickets_per_slot, None);
+        assert_eq!(version_twenty_two.purchases_in_slot, 0);
+        assert!(version_twenty_two.record_slot_purchases(1_000, 7).is_ok());
+
+        data.truncate(data.len() - migration::HAPPY_HOUR_SPACE);
+        data[8] = 21;
+        let happy_hour_at = slot_limit_at - migration::HAPPY_HOUR_SPACE;
+        data[happy_hour_at..happy_hour_at + migration::HAPPY_HOUR_SPACE].fill(0xff);
+        let version_twenty_one = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_twenty_one.version, 21);
+        assert_eq!(version_twenty_one.happy_hour, HappyHour::default());
+        assert_eq!(version_twenty_one.ticket_price_at(0).unwrap(), 10);
+
+        data.truncate(data.len() - migration::AUTHORITY_PLAY_SPACE);
+        data[8] = 20;
+        let play_at = happy_hour_at - migration::AUTHORITY_PLAY_SPACE;
+        data[play_at] = 1;
+        let version_twenty = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_twenty.version, 20);
+        assert!(version_twenty.authority_can_play());
+        assert!(!version_twenty.authority_play_disabled);
+
+        data.truncate(data.len() - migration::DISPUTE_WINDOW_SPACE);
+        data[8] = 19;
+        let window_at = play_at - migration::DISPUTE_WINDOW_SPACE;
+        data[window_at..window_at + migration::DISPUTE_WINDOW_SPACE].fill(1);
+        let version_nineteen = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_nineteen.version, 19);
+        assert_eq!(version_nineteen.dispute_window_seconds(), 0);
+        assert_eq!(version_nineteen.dispute_window_seconds, 0);
+
+        data.truncate(data.len() - migration::SERVER_SEEDS_SPACE);
+        data[8] = 18;
+        let seeds_at = window_at - migration::SERVER_SEEDS_SPACE;
+        data[seeds_at..seeds_at + migration::SERVER_SEEDS_SPACE].fill(1);
+        let version_eighteen = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_eighteen.version, 18);
+        assert!(!version_eighteen.server_seeded());
+        assert!(!version_eighteen.server_seed.is_committed());
+
+        data.truncate(data.len() - migration::TOKEN_PRIZES_SPACE);
+        data[8] = 17;
+        let prizes_at = seeds_at - migration::TOKEN_PRIZES_SPACE;
+        data[prizes_at..prizes_at + migration::TOKEN_PRIZES_SPACE].fill(1);
+        let version_seventeen = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_seventeen.version, 17);
+        assert!(version_seventeen.token_prizes().is_empty());
+        assert_eq!(version_seventeen.token_prizes[0].amount, 0);
+
+        data.truncate(data.len() - migration::MIN_ITEM_PRICE_SPACE);
+        data[8] = 16;
+        let floor_at = prizes_at - migration::MIN_ITEM_PRICE_SPACE;
+        data[floor_at..floor_at + migration::MIN_ITEM_PRICE_SPACE].fill(1);
+        let version_sixteen = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_sixteen.version, 16);
+        assert_eq!(version_sixteen.min_item_price(), 0);
+        assert_eq!(version_sixteen.min_item_price, 0);
+
+        data.truncate(data.len() - migration::BENEFICIARY_SPACE);
+        data[8] = 15;
+        let beneficiary_at = floor_at - migration::BENEFICIARY_SPACE;
+        data[beneficiary_at..beneficiary_at + migration::BENEFICIARY_SPACE].fill(1);
+        let version_fifteen = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_fifteen.version, 15);
+        assert_eq!(version_fifteen.beneficiary(), None);
+        assert_eq!(version_fifteen.beneficiary, None);
+
+        data.truncate(data.len() - migration::REVEALS_SPACE);
+        data[8] = 14;
+        let reveals_at = beneficiary_at - migration::REVEALS_SPACE;
+        data[reveals_at..reveals_at + migration::REVEALS_SPACE].fill(1);
+        let version_fourteen = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_fourteen.version, 14);
+        assert!(version_fourteen.pending_reveals().is_empty());
+        assert_eq!(version_fourteen.pending_reveals[0].reveal_hash, [0; 32]);
+
+        data.truncate(data.len() - migration::BOOSTS_SPACE);
+        data[8] = 13;
+        let boosts_at = reveals_at - migration::BOOSTS_SPACE;
+        data[boosts_at..boosts_at + migration::BOOSTS_SPACE].fill(1);
+        let version_thirteen = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_thirteen.version, 13);
+        assert!(version_thirteen.boost_slots().is_empty());
+        assert_eq!(version_thirteen.boosts[0].multiplier_bps, 0);
+
+        data.truncate(data.len() - migration::ACHIEVEMENT_BONUS_SPACE);
+        data[8] = 12;
+        let bonus_at = boosts_at - migration::ACHIEVEMENT_BONUS_SPACE;
+        data[bonus_at..bonus_at + migration::ACHIEVEMENT_BONUS_SPACE].fill(1);
+        let version_twelve = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_twelve.version, 12);
+        assert_eq!(version_twelve.achievement_bonus(), 0);
+        assert_eq!(version_twelve.achievement_bonus_lamports, 0);
+
+        data.truncate(data.len() - migration::STREAKS_SPACE);
+        data[8] = 11;
+        let streaks_at = bonus_at - migration::STREAKS_SPACE;
+        data[streaks_at..streaks_at + migration::STREAKS_SPACE].fill(1);
+        let version_eleven = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_eleven.version, 11);
+        assert!(version_eleven.streak_milestones().is_empty());
+        assert_eq!(version_eleven.streak_milestones[0].days, 0);
+
+        data.truncate(data.len() - migration::SPONSORS_SPACE);
+        data[8] = 10;
+        let sponsors_at = streaks_at - migration::SPONSORS_SPACE;
+        data[sponsors_at..sponsors_at + migration::SPONSORS_SPACE].fill(1);
+        let version_ten = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_ten.version, 10);
+        assert!(version_ten.sponsor_slots().is_empty());
+        assert_eq!(version_ten.sponsors[0].share_bps, 0);
+
+        data.truncate(data.len() - migration::POOL_MODE_SPACE);
+        data[8] = 9;
+        let mode_at = sponsors_at - 1;
+        data[mode_at] = 1;
+        let version_nine = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_nine.version, 9);
+        assert_eq!(version_nine.pool_mode, PoolMode::Spin);
+
+        data.truncate(data.len() - migration::VESTING_SPACE);
+        data[8] = 8;
+        let vesting_at = mode_at - migration::VESTING_SPACE;
+        data[vesting_at..vesting_at + migration::VESTING_SPACE].fill(0xff);
+        data[vesting_at] = 1;
+        let version_eight = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_eight.version, 8);
+        assert_eq!(version_eight.vesting_threshold_lamports, None);
+        assert_eq!(version_eight.vesting_duration_seconds, 0);
+        assert_eq!(version_eight.vesting_upfront_bps, 0);
+
+        data.truncate(data.len() - migration::SPIN_OPERATOR_SPACE);
+        data[8] = 7;
+        let operator_at = vesting_at - migration::SPIN_OPERATOR_SPACE;
+        data[operator_at..operator_at + migration::SPIN_OPERATOR_SPACE].fill(0xff);
+        data[operator_at] = 1;
+        let version_seven = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_seven.version, 7);
+        assert_eq!(version_seven.spin_operator, None);
+
+        data.truncate(data.len() - migration::EVENT_SEQ_SPACE);
+        data[8] = 6;
+        let counter_at = operator_at - migration::EVENT_SEQ_SPACE;
+        data[counter_at..counter_at + 8].fill(0xff);
+        let version_six = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_six.version, 6);
+        assert_eq!(version_six.event_seq, 0);
+
+        data.truncate(data.len() - migration::ENDS_AT_SPACE);
+        data[8] = 5;
+        let end_time_at = counter_at - migration::ENDS_AT_SPACE;
+        data[end_time_at..end_time_at + migration::ENDS_AT_SPACE].fill(0xff);
+        data[end_time_at] = 1;
+        let version_five = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_five.version, 5);
+        assert_eq!(version_five.ends_at, None);
+
+        data.truncate(data.len() - migration::CPI_PURCHASES_SPACE);
+        data[8] = 4;
+        let setting_at = end_time_at - 1;
+        data[setting_at] = 1;
+        let version_four = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_four.version, 4);
+        assert!(!version_four.allow_cpi_purchases);
+
+        data.truncate(data.len() - migration::POOL_FLAGS_SPACE);
+        data[8] = 3;
+        let flags_at = setting_at - 1;
+        data[flags_at] = u8::MAX;
+        let version_three = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_three.version, 3);
+        assert_eq!(version_three.pool_flags, 0);
+
+        data.truncate(data.len() - migration::CUMULATIVE_TABLE_SPACE);
+        data[8] = 2;
+        let stale_tail = data.len() - 4;
+        data[stale_tail..].fill(0xff);
+        let version_two = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_two.version, 2);
+        assert_eq!(version_two.cum_probability, pool.cum_probability);
+
+        data.remove(8);
+        let version_one = CompanyPool::read_any_version(&data).unwrap();
+        assert_eq!(version_one.version, 1);
+        assert_eq!(version_one.authority, pool.authority);
+        assert_eq!(version_one.items.len(), 2);
+        assert_eq!(version_one.pool_version, CompanyPool::POOL_VERSION);
+        assert_eq!(version_one.cum_probability, pool.cum_probability);
+
+        // Any other size is a layout this program can't read
+        data.pop();
+        assert!(CompanyPool::read_any_version(&data).is_err());
+    }
+
+    #[test]
+    fn global_configs_read_back_from_every_layout() {
+        let config = GlobalConfig {
+            admin: Pubkey::new_unique(),
+            program_paused: true,
+            bump: 254,
+            creation_mode: CreationMode::Allowlisted,
+            treasury: Pubkey::new_unique(),
+            pool_creation_fee_lamports: 1_000,
+        };
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + GlobalConfig::INIT_SPACE);
+        let current = GlobalConfig::read_any_version(&data).unwrap();
+        assert!(current.creation_mode == CreationMode::Allowlisted);
+        assert_eq!(current.pool_creation_fee_lamports, 1_000);
+
+        data.truncate(data.len() - migration::CREATION_FEE_SPACE);
+        let before_fee = GlobalConfig::read_any_version(&data).unwrap();
+        assert!(before_fee.creation_mode == CreationMode::Allowlisted);
+        assert_eq!(before_fee.treasury, config.admin);
+        assert_eq!(before_fee.pool_creation_fee_lamports, 0);
+
+        data.truncate(data.len() - migration::CREATION_MODE_SPACE);
+        let older = GlobalConfig::read_any_version(&data).unwrap();
+        assert_eq!((older.admin, older.bump), (config.admin, 254));
+        assert!(older.program_paused);
+        assert!(older.creation_mode == CreationMode::Open);
+
+        data.pop();
+        assert!(GlobalConfig::read_any_version(&data).is_err());
+    }
+
+    #[test]
+    fn allowlisted_creation_needs_the_creators_entry() {
+        let creator = Pubkey::new_unique();
+        let entry = PoolCreatorEntry {
+            creator,
+            added_at: 0,
+            bump: 255,
+        };
+        let mut config = GlobalConfig {
+            admin: Pubkey::new_unique(),
+            program_paused: false,
+            bump: 255,
+            creation_mode: CreationMode::Open,
+            treasury: Pubkey::default(),
+            pool_creation_fee_lamports: 0,
+        };
+        assert!(config.check_pool_creator(None, &creator).is_ok());
+
+        config.creation_mode = CreationMode::Allowlisted;
+        assert!(config.check_pool_creator(Some(&entry), &creator).is_ok());
+        assert!(config.check_pool_creator(None, &creator).is_err());
+        assert!(config
+            .check_pool_creator(Some(&entry), &Pubkey::new_unique())
+            .is_err());
+    }
+
+    #[test]
+    fn maximal_ticket_fits_allocated_space() {
+        let ticket = UserTicket {
+            version: UserTicket::VERSION,
+            owner: Pubkey::new_unique(),
+            company_pool: Pubkey::new_unique(),
+            purchased_at: i64::MAX,
+            used: true,
+            ticket_id: u64::MAX,
+            won_item: Some(max_won_item()),
+            reward_claimed: true,
+            gambled: true,
+            traded_in: true,
+            spun_at: i64::MAX,
+            reward_expired: true,
+            reward_nft_mint: Some(Pubkey::new_unique()),
+            fulfillment_pending: true,
+            shipping_info_hash: Some([u8::MAX; 32]),
+            price_paid: u64::MAX,
+            consolation_amount: u64::MAX,
+            rerolled: true,
+            forced_min_tier: Some(ItemTier::Legendary),
+            purchased_slot: u64::MAX,
+            spun_slot: u64::MAX,
+            external_id: Some([u8::MAX; 32]),
+            claimed_so_far: u64::MAX,
+            vesting_start: i64::MAX,
+            serial: u32::MAX,
+            spin_pending: true,
+            draw_record: Some(DrawRecord {
+                random_seed: u64::MAX,
+                table_hash: [u8::MAX; 32],
+                drawn_index: Some(u32::MAX),
+            }),
+            mega_jackpot_won: u64::MAX,
+            insured: true,
+            insurance_claimed: true,
+        };
+
+        assert!(serialized_account_len(&ticket) <= 8 + UserTicket::INIT_SPACE);
+    }
+
+    #[test]
+    fn mega_jackpot_pays_out_the_whole_pot_on_a_hit() {
+        let mut mega_jackpot = MegaJackpot {
+            contribution_bps: 100,
+            hit_chance_bps: 1,
+            pot: 0,
+            owed: 0,
+            total_contributed: 0,
+            total_won: 0,
+            wins: 0,
+            vault_rent_reserve: 890_880,
+            bump: 255,
+            vault_bump: 254,
+        };
+        assert_eq!(mega_jackpot.contribution(1_000_000_000), 10_000_000);
+        assert_eq!(mega_jackpot.contribution(99), 0);
+        assert!(MegaJackpot::check_terms(1001, 0).is_err());
+        assert!(MegaJackpot::check_terms(0, 10001).is_err());
+
+        // An empty pot can't be won, even by a hitting seed
+        let hit = (0..100_000u64)
+            .find(|&seed| mega_jackpot.is_hit(seed))
+            .unwrap();
+        assert_eq!(mega_jackpot.roll(hit).unwrap(), None);
+
+        mega_jackpot.record_contribution(10_000_000).unwrap();
+        mega_jackpot.record_contribution(5_000_000).unwrap();
+        let miss = (0..100_000u64)
+            .find(|&seed| !mega_jackpot.is_hit(seed))
+            .unwrap();
+        assert_eq!(mega_jackpot.roll(miss).unwrap(), None);
+        assert_eq!(mega_jackpot.pot, 15_000_000);
+
+        assert_eq!(mega_jackpot.roll(hit).unwrap(), Some(15_000_000));
+        assert_eq!((mega_jackpot.pot, mega_jackpot.owed), (0, 15_000_000));
+        assert_eq!((mega_jackpot.total_won, mega_jackpot.wins), (15_000_000, 1));
+        // Only what's owed can leave the vault, and never its rent
+        assert!(mega_jackpot.check_payable(15_000_001, u64::MAX).is_err());
+        assert!(mega_jackpot.check_payable(15_000_000, 15_000_000).is_err());
+        assert!(mega_jackpot
+            .check_payable(15_000_000, 15_000_000 + 890_880)
+            .is_ok());
+
+        // A voided win goes back into the pot for the next hit
+        assert!(mega_jackpot.return_win(15_000_001).is_err());
+        mega_jackpot.return_win(15_000_000).unwrap();
+        assert_eq!((mega_jackpot.pot, mega_jackpot.owed), (15_000_000, 0));
+        assert_eq!((mega_jackpot.total_won, mega_jackpot.wins), (0, 0));
+
+        mega_jackpot.hit_chance_bps = 0;
+        mega_jackpot.record_contribution(1).unwrap();
+        assert!((0..10_000u64).all(|seed| mega_jackpot.roll(seed).unwrap().is_none()));
+    }
+}
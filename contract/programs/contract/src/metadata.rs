@@ -0,0 +1,96 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::pubkey;
+
+// Metaplex Token Metadata program
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey =
+    pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+// Metaplex limit on the on-chain NFT name
+pub const MAX_NFT_NAME_LEN: usize = 32;
+
+const METADATA_V1_KEY: u8 = 4;
+const CREATOR_LEN: usize = 34; // address + verified + share
+
+// Minimal reader over a borsh-serialized Metaplex metadata account, so we
+// don't pull in the full mpl crate just to read the collection field
+struct MetadataReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> MetadataReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.offset.checked_add(len)?;
+        let bytes = self.data.get(self.offset..end)?;
+        self.offset = end;
+        Some(bytes)
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_pubkey(&mut self) -> Option<Pubkey> {
+        self.take(32).map(|bytes| Pubkey::try_from(bytes).unwrap())
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        let len = self.read_u32()? as usize;
+        self.take(len).map(|_| ())
+    }
+
+    // Option<T> of a fixed-size T
+    fn skip_option(&mut self, len: usize) -> Option<()> {
+        if self.read_u8()? == 1 {
+            self.take(len)?;
+        }
+        Some(())
+    }
+}
+
+// Mint recorded in the metadata plus its collection, if that collection is verified
+pub fn read_verified_collection(data: &[u8]) -> Option<(Pubkey, Pubkey)> {
+    let mut reader = MetadataReader { data, offset: 0 };
+
+    if reader.read_u8()? != METADATA_V1_KEY {
+        return None;
+    }
+    reader.read_pubkey()?; // update authority
+    let mint = reader.read_pubkey()?;
+    reader.skip_string()?; // name
+    reader.skip_string()?; // symbol
+    reader.skip_string()?; // uri
+    reader.take(2)?; // seller fee basis points
+
+    if reader.read_u8()? == 1 {
+        let creators = reader.read_u32()? as usize;
+        reader.take(creators.checked_mul(CREATOR_LEN)?)?;
+    }
+
+    reader.read_u8()?; // primary sale happened
+    reader.read_u8()?; // is mutable
+    reader.skip_option(1)?; // edition nonce
+    reader.skip_option(1)?; // token standard
+
+    if reader.read_u8()? != 1 {
+        return None;
+    }
+    let verified = reader.read_u8()? == 1;
+    let collection = reader.read_pubkey()?;
+
+    verified.then_some((mint, collection))
+}
+
+// Item names can be longer than Metaplex allows; cut on a char boundary
+pub fn nft_name(item_name: &str) -> String {
+    let mut end = item_name.len().min(MAX_NFT_NAME_LEN);
+    while !item_name.is_char_boundary(end) {
+        end -= 1;
+    }
+    item_name[..end].to_string()
+}
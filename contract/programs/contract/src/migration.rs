@@ -0,0 +1,542 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use crate::{
+    CompanyPool, CompanyPoolV2, DrawRecord, ErrorCode, GlobalConfig, HappyHour, ItemBoost,
+    ItemSponsor, ItemTokenPrize, LegacyUserTicket, PendingReveal, PoolMode, ServerSeedCommitment,
+    SpinInsurance, StreakMilestone, UserPoolState, UserTicket,
+};
+
+// Version 1 pools and tickets are the version 2 layout without the leading
+// version byte. Each later version appends fields:
+//
+//   version  pools                                   tickets
+//   3        cumulative probability table            purchase and spin slots
+//   4        admin flags                             external id
+//   5        CPI purchase setting                    vesting progress
+//   6        end time                                serial number
+//   7        event counter                           pending-spin flag
+//   8        spin operator                           draw record
+//   9        vesting terms                           mega jackpot win
+//   10       pool mode                               insurance flags
+//   11       sponsors
+//   12       streak milestones
+//   13       achievement bonus
+//   14       item boosts
+//   15       pending reveals
+//   16       beneficiary
+//   17       item price floor
+//   18       token prizes
+//   19       server seeds
+//   20       dispute window
+//   21       authority play setting
+//   22       happy hour
+//   23       per-slot purchase limit
+//   24       donation total
+//   25       mega jackpot setting
+//   26       spin insurance
+//   27       seasons
+//   28       shared ticket count
+//
+// Nothing in their data says which layout they are, so they are told apart
+// by size: every account is allocated exactly its layout's space.
+
+// Space the cumulative probability table added to the pool layout
+pub const CUMULATIVE_TABLE_SPACE: usize = 4 * CompanyPool::MAX_ACCOUNT_ITEMS;
+
+// Space the purchase and spin slots added to the ticket layout
+pub const TICKET_SLOTS_SPACE: usize = 16;
+
+// Space the external order id added to the ticket layout
+pub const TICKET_EXTERNAL_ID_SPACE: usize = 1 + 32;
+
+// Space the vesting progress added to the ticket layout
+pub const TICKET_VESTING_SPACE: usize = 8 + 8;
+
+// Space the serial number added to the ticket layout
+pub const TICKET_SERIAL_SPACE: usize = 4;
+
+// Space the pending-spin flag added to the ticket layout
+pub const TICKET_SPIN_PENDING_SPACE: usize = 1;
+
+// Space the draw record added to the ticket layout
+pub const TICKET_DRAW_RECORD_SPACE: usize = 1 + DrawRecord::INIT_SPACE;
+
+// Space the mega jackpot win added to the ticket layout
+pub const TICKET_MEGA_JACKPOT_SPACE: usize = 8;
+
+// Space the insurance flags added to the ticket layout
+pub const TICKET_INSURANCE_SPACE: usize = 1 + 1;
+
+// Space the admin flags added to the pool layout
+pub const POOL_FLAGS_SPACE: usize = 1;
+
+// Space the CPI purchase setting added to the pool layout
+pub const CPI_PURCHASES_SPACE: usize = 1;
+
+// Space the end time added to the pool layout
+pub const ENDS_AT_SPACE: usize = 1 + 8;
+
+// Space the event counter added to the pool layout, and to the zero-copy one
+pub const EVENT_SEQ_SPACE: usize = 8;
+
+// Space the spin operator added to the pool layout
+pub const SPIN_OPERATOR_SPACE: usize = 1 + 32;
+
+// Space the vesting terms added to the pool layout
+pub const VESTING_SPACE: usize = (1 + 8) + 4 + 2;
+
+// Space the pool mode added to the pool layout
+pub const POOL_MODE_SPACE: usize = 1;
+
+// Space the sponsor slots added to the pool layout
+pub const SPONSORS_SPACE: usize = CompanyPool::MAX_SPONSORS * ItemSponsor::INIT_SPACE;
+
+// Space the streak milestones added to the pool layout
+pub const STREAKS_SPACE: usize = CompanyPool::MAX_STREAK_MILESTONES * StreakMilestone::INIT_SPACE;
+
+// Space the achievement bonus added to the pool layout
+pub const ACHIEVEMENT_BONUS_SPACE: usize = 8;
+
+// Space the item boosts added to the pool layout
+pub const BOOSTS_SPACE: usize = CompanyPool::MAX_BOOSTS * ItemBoost::INIT_SPACE;
+
+// Space the pending reveals added to the pool layout
+pub const REVEALS_SPACE: usize = CompanyPool::MAX_PENDING_REVEALS * PendingReveal::INIT_SPACE;
+
+// Space the beneficiary added to the pool layout
+pub const BENEFICIARY_SPACE: usize = 1 + 32;
+
+// Space the item price floor added to the pool layout
+pub const MIN_ITEM_PRICE_SPACE: usize = 8;
+
+// Space the token prize slots added to the pool layout
+pub const TOKEN_PRIZES_SPACE: usize = CompanyPool::MAX_TOKEN_PRIZES * ItemTokenPrize::INIT_SPACE;
+
+// Space the server seed setting and commitment added to the pool layout
+pub const SERVER_SEEDS_SPACE: usize = 1 + ServerSeedCommitment::INIT_SPACE;
+
+// Space the dispute window added to the pool layout
+pub const DISPUTE_WINDOW_SPACE: usize = 4;
+
+// Space the authority play setting added to the pool layout
+pub const AUTHORITY_PLAY_SPACE: usize = 1;
+
+// Space the happy hour added to the pool layout
+pub const HAPPY_HOUR_SPACE: usize = HappyHour::INIT_SPACE;
+
+// Space the per-slot purchase limit and its counters added to the pool layout
+pub const SLOT_LIMIT_SPACE: usize = (1 + 2) + 8 + 2;
+
+// Space the donation total added to the pool layout
+pub const DONATIONS_SPACE: usize = 8;
+
+// Space the mega jackpot setting and contribution total added to the pool
+// layout
+pub const MEGA_JACKPOT_SPACE: usize = 1 + 8;
+
+// Space the spin insurance terms and their totals added to the pool layout
+pub const INSURANCE_SPACE: usize = (1 + SpinInsurance::INIT_SPACE) + 8 + 8;
+
+// Space the season number, its start and the per-progress reset seasons
+// added to the pool layout
+pub const SEASONS_SPACE: usize = 2 + 8 + 8 + 8 + 2 + 2 + 2;
+
+// Space the shared ticket count added to the pool layout
+pub const SHARED_TICKETS_SPACE: usize = 8;
+
+// Space each group of fields added to the user pool state before it had a
+// version byte: the loyalty points, the spin delegate, the streak, the
+// achievement progress and the season
+pub const USER_STATE_APPENDED_SPACE: [usize; 5] = [8, (1 + 32) + 8, 4 + 8, 8 + 8 + 4 + 4 + 4, 2];
+
+// Space the creation mode added to the global config, which has no version
+// byte of its own
+pub const CREATION_MODE_SPACE: usize = 1;
+
+// Space the treasury and pool creation fee added to the global config
+pub const CREATION_FEE_SPACE: usize = 32 + 8;
+
+// Reads the current layout or an older one. `appended` lists the bytes each
+// version after 2 added, oldest first. Missing fields read as whatever the old
+// account's tail held, so the caller rebuilds them.
+fn read_with_version_byte<T: AccountDeserialize + Discriminator>(
+    data: &[u8],
+    current_len: usize,
+    appended: &[usize],
+) -> Result<T> {
+    require!(
+        data.starts_with(T::DISCRIMINATOR),
+        anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+    );
+    if data.len() == current_len {
+        return T::try_deserialize(&mut &data[..]);
+    }
+
+    // Walk back through the older layouts, ending at version 2
+    let mut layout_len = current_len;
+    let mut has_version_byte = false;
+    for added in appended.iter().rev() {
+        layout_len -= added;
+        has_version_byte |= data.len() == layout_len;
+    }
+    require!(
+        has_version_byte || data.len() + 1 == layout_len,
+        ErrorCode::UnsupportedAccountVersion
+    );
+
+    let mut upgraded = Vec::with_capacity(current_len);
+    upgraded.extend_from_slice(&data[..8]);
+    if !has_version_byte {
+        upgraded.push(1);
+    }
+    upgraded.extend_from_slice(&data[8..]);
+    upgraded.resize(current_len, 0);
+    T::try_deserialize(&mut upgraded.as_slice())
+}
+
+impl CompanyPool {
+    pub fn read_any_version(data: &[u8]) -> Result<CompanyPool> {
+        let mut pool = read_with_version_byte::<CompanyPool>(
+            data,
+            8 + CompanyPool::INIT_SPACE,
+            &[
+                CUMULATIVE_TABLE_SPACE,
+                POOL_FLAGS_SPACE,
+                CPI_PURCHASES_SPACE,
+                ENDS_AT_SPACE,
+                EVENT_SEQ_SPACE,
+                SPIN_OPERATOR_SPACE,
+                VESTING_SPACE,
+                POOL_MODE_SPACE,
+                SPONSORS_SPACE,
+                STREAKS_SPACE,
+                ACHIEVEMENT_BONUS_SPACE,
+                BOOSTS_SPACE,
+                REVEALS_SPACE,
+                BENEFICIARY_SPACE,
+                MIN_ITEM_PRICE_SPACE,
+                TOKEN_PRIZES_SPACE,
+                SERVER_SEEDS_SPACE,
+                DISPUTE_WINDOW_SPACE,
+                AUTHORITY_PLAY_SPACE,
+                HAPPY_HOUR_SPACE,
+                SLOT_LIMIT_SPACE,
+                DONATIONS_SPACE,
+                MEGA_JACKPOT_SPACE,
+                INSURANCE_SPACE,
+                SEASONS_SPACE,
+                SHARED_TICKETS_SPACE,
+            ],
+        )?;
+        if pool.version < CompanyPool::VERSION_POOL_FLAGS {
+            pool.rebuild_cumulative_table();
+            pool.pool_flags = 0;
+        }
+        if pool.version < CompanyPool::VERSION_CPI_PURCHASES {
+            pool.allow_cpi_purchases = false;
+        }
+        if pool.version < CompanyPool::VERSION_ENDS_AT {
+            pool.ends_at = None;
+        }
+        if pool.version < CompanyPool::VERSION_EVENT_SEQ {
+            pool.event_seq = 0;
+        }
+        if pool.version < CompanyPool::VERSION_SPIN_OPERATOR {
+            pool.spin_operator = None;
+        }
+        if pool.version < CompanyPool::VERSION_VESTING {
+            pool.vesting_threshold_lamports = None;
+            pool.vesting_duration_seconds = 0;
+            pool.vesting_upfront_bps = 0;
+        }
+        if pool.version < CompanyPool::VERSION_POOL_MODE {
+            pool.pool_mode = PoolMode::Spin;
+        }
+        if pool.version < CompanyPool::VERSION_SPONSORS {
+            pool.sponsors = [ItemSponsor::default(); CompanyPool::MAX_SPONSORS];
+        }
+        if pool.version < CompanyPool::VERSION_STREAKS {
+            pool.streak_milestones =
+                [StreakMilestone::default(); CompanyPool::MAX_STREAK_MILESTONES];
+        }
+        if pool.version < CompanyPool::VERSION_ACHIEVEMENT_BONUS {
+            pool.achievement_bonus_lamports = 0;
+        }
+        if pool.version < CompanyPool::VERSION_BOOSTS {
+            pool.boosts = [ItemBoost::default(); CompanyPool::MAX_BOOSTS];
+        }
+        if pool.version < CompanyPool::VERSION_REVEALS {
+            pool.pending_reveals = [PendingReveal::default(); CompanyPool::MAX_PENDING_REVEALS];
+        }
+        if pool.version < CompanyPool::VERSION_BENEFICIARY {
+            pool.beneficiary = None;
+        }
+        if pool.version < CompanyPool::VERSION_MIN_ITEM_PRICE {
+            pool.min_item_price = 0;
+        }
+        if pool.version < CompanyPool::VERSION_TOKEN_PRIZES {
+            pool.token_prizes = [ItemTokenPrize::default(); CompanyPool::MAX_TOKEN_PRIZES];
+        }
+        if pool.version < CompanyPool::VERSION_SERVER_SEEDS {
+            pool.server_seeded = false;
+            pool.server_seed = ServerSeedCommitment::default();
+        }
+        if pool.version < CompanyPool::VERSION_DISPUTE_WINDOW {
+            pool.dispute_window_seconds = 0;
+        }
+        if pool.version < CompanyPool::VERSION_AUTHORITY_PLAY {
+            pool.authority_play_disabled = false;
+        }
+        if pool.version < CompanyPool::VERSION_HAPPY_HOUR {
+            pool.happy_hour = HappyHour::default();
+        }
+        if pool.version < CompanyPool::VERSION_SLOT_LIMIT {
+            pool.max_tickets_per_slot = None;
+            pool.last_purchase_slot = 0;
+            pool.purchases_in_slot = 0;
+        }
+        if pool.version < CompanyPool::VERSION_DONATIONS {
+            pool.total_donations = 0;
+        }
+        if pool.version < CompanyPool::VERSION_MEGA_JACKPOT {
+            pool.mega_jackpot = false;
+            pool.total_mega_jackpot_contributions = 0;
+        }
+        if pool.version < CompanyPool::VERSION_INSURANCE {
+            pool.insurance = None;
+            pool.total_insurance_premiums = 0;
+            pool.total_insurance_paid = 0;
+        }
+        if pool.version < CompanyPool::VERSION_SEASONS {
+            pool.season = 0;
+            pool.season_started_at = 0;
+            pool.season_start_tickets_sold = 0;
+            pool.season_start_revenue = 0;
+            pool.points_reset_season = 0;
+            pool.streaks_reset_season = 0;
+            pool.achievements_reset_season = 0;
+        }
+        if pool.version < CompanyPool::VERSION_SHARED_TICKETS {
+            pool.shared_tickets_sold = 0;
+        }
+        Ok(pool)
+    }
+}
+
+impl GlobalConfig {
+    // A config from before the creation mode reads as Open, and one from
+    // before the creation fee charges nothing and names its admin as treasury
+    pub fn read_any_version(data: &[u8]) -> Result<GlobalConfig> {
+        let current_len = 8 + GlobalConfig::INIT_SPACE;
+        let before_fee = current_len - CREATION_FEE_SPACE;
+        require!(
+            data.len() == current_len
+                || data.len() == before_fee
+                || data.len() + CREATION_MODE_SPACE == before_fee,
+            ErrorCode::UnsupportedAccountVersion
+        );
+        let mut upgraded = data.to_vec();
+        upgraded.resize(current_len, 0);
+        let mut config = GlobalConfig::try_deserialize(&mut upgraded.as_slice())?;
+        if data.len() < current_len {
+            config.treasury = config.admin;
+            config.pool_creation_fee_lamports = 0;
+        }
+        Ok(config)
+    }
+}
+
+impl UserTicket {
+    pub fn read_any_version(data: &[u8]) -> Result<UserTicket> {
+        if data.len() == 8 + LegacyUserTicket::INIT_SPACE {
+            require!(
+                data.starts_with(UserTicket::DISCRIMINATOR),
+                anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+            );
+            return Ok(LegacyUserTicket::deserialize(&mut &data[8..])?.into());
+        }
+        let mut ticket = read_with_version_byte::<UserTicket>(
+            data,
+            8 + UserTicket::INIT_SPACE,
+            &[
+                TICKET_SLOTS_SPACE,
+                TICKET_EXTERNAL_ID_SPACE,
+                TICKET_VESTING_SPACE,
+                TICKET_SERIAL_SPACE,
+                TICKET_SPIN_PENDING_SPACE,
+                TICKET_DRAW_RECORD_SPACE,
+                TICKET_MEGA_JACKPOT_SPACE,
+                TICKET_INSURANCE_SPACE,
+            ],
+        )?;
+        if ticket.version < UserTicket::VERSION_SLOTS {
+            // Unknown slots never hold up a spin or claim
+            ticket.purchased_slot = 0;
+            ticket.spun_slot = 0;
+        }
+        if ticket.version < UserTicket::VERSION_EXTERNAL_ID {
+            ticket.external_id = None;
+        }
+        if ticket.version < UserTicket::VERSION_VESTING {
+            ticket.claimed_so_far = 0;
+            ticket.vesting_start = 0;
+        }
+        if ticket.version < UserTicket::VERSION_SERIAL {
+            ticket.serial = 0;
+        }
+        if ticket.version < UserTicket::VERSION_SPIN_PENDING {
+            ticket.spin_pending = false;
+        }
+        if ticket.version < UserTicket::VERSION_DRAW_RECORD {
+            ticket.draw_record = None;
+        }
+        if ticket.version < UserTicket::VERSION_MEGA_JACKPOT {
+            ticket.mega_jackpot_won = 0;
+        }
+        if ticket.version < UserTicket::VERSION_INSURANCE {
+            ticket.insured = false;
+            ticket.insurance_claimed = false;
+        }
+        Ok(ticket)
+    }
+}
+
+impl UserPoolState {
+    // Version 1 states have no version byte and grew by appending fields, so
+    // only the size says which of them an account has. The missing ones read
+    // as whatever the old tail held and are reset.
+    pub fn read_any_version(data: &[u8]) -> Result<UserPoolState> {
+        require!(
+            data.starts_with(UserPoolState::DISCRIMINATOR),
+            anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+        );
+        let current_len = 8 + UserPoolState::INIT_SPACE;
+        if data.len() == current_len {
+            return UserPoolState::try_deserialize(&mut &data[..]);
+        }
+
+        // Count the groups the old layout has, dropping the newest first
+        let mut layout_len = current_len - 1;
+        let mut groups = USER_STATE_APPENDED_SPACE.len();
+        while data.len() < layout_len && groups > 0 {
+            groups -= 1;
+            layout_len -= USER_STATE_APPENDED_SPACE[groups];
+        }
+        require!(
+            data.len() == layout_len,
+            ErrorCode::UnsupportedAccountVersion
+        );
+
+        let mut upgraded = Vec::with_capacity(current_len);
+        upgraded.extend_from_slice(&data[..8]);
+        upgraded.push(1);
+        upgraded.extend_from_slice(&data[8..]);
+        upgraded.resize(current_len, 0);
+        let mut state = UserPoolState::try_deserialize(&mut upgraded.as_slice())?;
+        if groups < 1 {
+            state.loyalty_points = 0;
+        }
+        if groups < 2 {
+            state.spin_delegate = None;
+            state.spin_delegate_expires_at = 0;
+        }
+        if groups < 3 {
+            state.current_streak = 0;
+            state.last_played_day = 0;
+        }
+        if groups < 4 {
+            state.achievements = 0;
+            state.achievements_rewarded = 0;
+            state.spins = 0;
+            state.legendary_wins = 0;
+            state.jackpot_wins = 0;
+        }
+        if groups < 5 {
+            state.last_season = 0;
+        }
+        Ok(state)
+    }
+}
+
+impl CompanyPoolV2 {
+    // Zero-copy pools can't be read at a shorter size, so they are grown in
+    // place and the version byte set directly; the new counter starts
+    // zeroed. Returns the old version and the rent moved as resize_account
+    // does.
+    pub fn grow_to_current_layout<'info>(
+        account: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+    ) -> Result<(u8, i64)> {
+        let current_len = 8 + std::mem::size_of::<CompanyPoolV2>();
+        {
+            let data = account.try_borrow_data()?;
+            require!(
+                data.starts_with(CompanyPoolV2::DISCRIMINATOR),
+                anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+            );
+            require!(data.len() != current_len, ErrorCode::AccountAlreadyMigrated);
+            require!(
+                data.len() + EVENT_SEQ_SPACE == current_len,
+                ErrorCode::UnsupportedAccountVersion
+            );
+        }
+        let rent_moved = resize_account(account, current_len, payer, system_program)?;
+
+        let version_at = 8 + std::mem::offset_of!(CompanyPoolV2, version);
+        let mut data = account.try_borrow_mut_data()?;
+        let from_version = data[version_at];
+        data[version_at] = CompanyPoolV2::VERSION;
+        Ok((from_version, rent_moved))
+    }
+}
+
+// Writes `value` over `account` at its layout's size, see resize_account
+pub fn rewrite_account<'info, T: AccountSerialize + Space>(
+    account: &AccountInfo<'info>,
+    value: &T,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<i64> {
+    let moved = resize_account(account, 8 + T::INIT_SPACE, payer, system_program)?;
+    let mut data = account.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    value.try_serialize(&mut writer)?;
+    Ok(moved)
+}
+
+// Resizes `account` to `new_len`. The payer tops up the rent a larger
+// account needs and gets back what a smaller one frees; the return value is
+// the lamports moved to the payer, negative when it paid.
+fn resize_account<'info>(
+    account: &AccountInfo<'info>,
+    new_len: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<i64> {
+    let rent = Rent::get()?.minimum_balance(new_len);
+    let balance = account.lamports();
+
+    if rent > balance {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent - balance,
+        )?;
+    }
+
+    account.resize(new_len)?;
+
+    if balance > rent {
+        account.sub_lamports(balance - rent)?;
+        payer.add_lamports(balance - rent)?;
+    }
+
+    Ok(balance as i64 - rent as i64)
+}
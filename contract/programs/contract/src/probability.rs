@@ -1,13 +1,118 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::units::Lamports;
+
+// Bounds on the ticket ratio exponent, in thousandths (1500 = 1.5)
+pub const DEFAULT_EXPONENT_MILLI: u16 = 1500;
+pub const MIN_EXPONENT_MILLI: u16 = 500;
+pub const MAX_EXPONENT_MILLI: u16 = 3000;
+
+// How a pool turns item values into win probabilities
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum WeightingMode {
+    // Weight is 1 / value
+    InverseValue,
+    // Weight is 1 / (value / ticket_price) ^ (exponent_milli / 1000)
+    TicketRatio { exponent_milli: u16 },
+    // Weights are set per item by the authority
+    Manual,
+}
+
+impl Default for WeightingMode {
+    fn default() -> Self {
+        WeightingMode::TicketRatio {
+            exponent_milli: DEFAULT_EXPONENT_MILLI,
+        }
+    }
+}
+
+impl WeightingMode {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            WeightingMode::TicketRatio { exponent_milli } => {
+                (MIN_EXPONENT_MILLI..=MAX_EXPONENT_MILLI).contains(exponent_milli)
+            }
+            _ => true,
+        }
+    }
+}
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct WeightedItem {
+    pub id: u32, // Position in the input unless set with with_item_ids
     pub name: String,
     pub value: u64,
-    pub weight: f64,
+    pub weight: f64,      // Per unit
+    pub quantity: u32,    // Winnable units left, 1 unless set with with_quantities
     pub probability: u32, // Stored as basis points (1 = 0.01%)
 }
 
+impl WeightedItem {
+    // A stack of units is as likely as that many single items
+    pub fn stack_weight(&self) -> f64 {
+        self.weight * self.quantity as f64
+    }
+}
+
+// Per-item probability limits in basis points
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProbabilityBounds {
+    pub min_bps: u32,
+    pub max_bps: u32,
+}
+
+impl Default for ProbabilityBounds {
+    fn default() -> Self {
+        Self {
+            min_bps: 0,
+            max_bps: 10000,
+        }
+    }
+}
+
+impl ProbabilityBounds {
+    pub fn new(min_bps: Option<u16>, max_bps: Option<u16>) -> Self {
+        Self {
+            min_bps: min_bps.map_or(0, u32::from),
+            max_bps: max_bps.map_or(10000, u32::from),
+        }
+    }
+}
+
+// Largest-remainder (Hamilton) apportionment of `total` basis points: floor
+// every exact share, then hand the leftover points to the largest fractional
+// parts. The result sums to `total` and each share is within 1 bp of exact.
+pub fn apportion(weights: &[f64], total: u32) -> Vec<u32> {
+    let total_weight: f64 = weights.iter().sum();
+    if !total_weight.is_finite() || total_weight <= 0.0 {
+        return vec![0; weights.len()];
+    }
+
+    let mut shares = Vec::with_capacity(weights.len());
+    let mut remainders: Vec<(usize, f64)> = Vec::with_capacity(weights.len());
+    let mut floored_total = 0u32;
+
+    for (i, weight) in weights.iter().enumerate() {
+        let exact = weight / total_weight * total as f64;
+        let floored = exact.floor();
+        shares.push(floored as u32);
+        floored_total = floored_total.saturating_add(floored as u32);
+        remainders.push((i, exact - floored));
+    }
+
+    // Largest fractional part first; ties go to the earlier item so the
+    // result is deterministic
+    remainders.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let leftover = total.saturating_sub(floored_total) as usize;
+    for &(i, _) in remainders.iter().take(leftover) {
+        shares[i] += 1;
+    }
+
+    shares
+}
+
 pub struct WeightedProbabilityCalculator {
     pub items: Vec<WeightedItem>,
     pub ticket_price: u64,
@@ -15,25 +120,90 @@ pub struct WeightedProbabilityCalculator {
 }
 
 impl WeightedProbabilityCalculator {
-    pub fn new(items: Vec<(String, u64)>, ticket_price: u64) -> Self {
-        let mut calculator = Self {
+    // Items are (name, value, quantity): a stack of `quantity` winnable
+    // units weighs as much as that many single items of the same value
+    pub fn new(items: impl IntoIterator<Item = (String, u64, u32)>, ticket_price: u64) -> Self {
+        let mut calculator = Self::unweighted(items, ticket_price);
+        calculator.calculate_weights_advanced();
+        calculator
+    }
+
+    // Calculator over bare item values for the on-chain paths, which only
+    // need the probabilities. The names stay empty, and an empty String
+    // doesn't allocate.
+    pub fn from_values(
+        values: impl IntoIterator<Item = u64>,
+        ticket_price: u64,
+        weighting: WeightingMode,
+        manual_weights: &[u32],
+    ) -> Self {
+        let items = values.into_iter().map(|value| (String::new(), value, 1));
+        let mut calculator = Self::unweighted(items, ticket_price);
+        calculator.apply_weighting(weighting, manual_weights);
+        calculator
+    }
+
+    fn unweighted(items: impl IntoIterator<Item = (String, u64, u32)>, ticket_price: u64) -> Self {
+        Self {
             items: items
                 .into_iter()
-                .map(|(name, value)| WeightedItem {
+                .enumerate()
+                .map(|(position, (name, value, quantity))| WeightedItem {
+                    id: position as u32,
                     name,
                     value,
                     weight: 0.0,
+                    quantity,
                     probability: 0,
                 })
                 .collect(),
             ticket_price,
             total_weight: 0.0,
-        };
+        }
+    }
 
-        calculator.calculate_weights_advanced();
+    // Tags the items with the pool's stable item ids, in input order, so
+    // lookups keep working however the pool's item list is arranged
+    pub fn with_item_ids(mut self, ids: &[u32]) -> Self {
+        for (item, &id) in self.items.iter_mut().zip(ids) {
+            item.id = id;
+        }
+        self
+    }
+
+    // Sets how many units of each item are left, in input order, and
+    // reapportions. Called again as units are won, so the table follows the
+    // stock down.
+    pub fn with_quantities(mut self, quantities: &[u32]) -> Self {
+        for (item, &quantity) in self.items.iter_mut().zip(quantities) {
+            item.quantity = quantity;
+        }
+        self.normalize_probabilities();
+        self
+    }
+
+    // Calculator using the pool's weighting mode; manual weights are only read in Manual mode
+    pub fn with_weighting(
+        items: impl IntoIterator<Item = (String, u64, u32)>,
+        ticket_price: u64,
+        weighting: WeightingMode,
+        manual_weights: &[u32],
+    ) -> Self {
+        let mut calculator = Self::unweighted(items, ticket_price);
+        calculator.apply_weighting(weighting, manual_weights);
         calculator
     }
 
+    fn apply_weighting(&mut self, weighting: WeightingMode, manual_weights: &[u32]) {
+        match weighting {
+            WeightingMode::InverseValue => self.calculate_weights_simple(),
+            WeightingMode::TicketRatio { exponent_milli } => {
+                self.calculate_weights_ticket_ratio(exponent_milli)
+            }
+            WeightingMode::Manual => self.calculate_weights_manual(manual_weights),
+        }
+    }
+
     // Simple inverse value weighting (higher value = lower probability)
     pub fn calculate_weights_simple(&mut self) {
         self.total_weight = 0.0;
@@ -49,15 +219,32 @@ impl WeightedProbabilityCalculator {
 
     // Advanced weighting based on ticket price ratio
     pub fn calculate_weights_advanced(&mut self) {
+        self.calculate_weights_ticket_ratio(DEFAULT_EXPONENT_MILLI);
+    }
+
+    // Ticket price ratio weighting with the exponent given in thousandths
+    pub fn calculate_weights_ticket_ratio(&mut self, exponent_milli: u16) {
         self.total_weight = 0.0;
+        let exponent = (exponent_milli as f64) / 1000.0;
 
         for item in &mut self.items {
             // Weight based on how many tickets needed to buy the product
             let tickets_needed = (item.value as f64) / (self.ticket_price as f64);
             
             // Higher value items have exponentially lower probability
-            // Using power of 1.5 as in original code
-            item.weight = 1.0 / tickets_needed.powf(1.5).max(f64::MIN_POSITIVE);
+            item.weight = 1.0 / tickets_needed.powf(exponent).max(f64::MIN_POSITIVE);
+            self.total_weight += item.weight;
+        }
+
+        self.normalize_probabilities();
+    }
+
+    // Authority-set weights, normalized to sum to 10000
+    pub fn calculate_weights_manual(&mut self, weights: &[u32]) {
+        self.total_weight = 0.0;
+
+        for (i, item) in self.items.iter_mut().enumerate() {
+            item.weight = weights.get(i).copied().unwrap_or(0) as f64;
             self.total_weight += item.weight;
         }
 
@@ -65,54 +252,126 @@ impl WeightedProbabilityCalculator {
     }
 
     fn normalize_probabilities(&mut self) {
-        // Calculate individual probabilities and normalize to sum to 10000
-        let mut total_probability = 0u32;
-        
-        // Store the length to avoid borrowing issues
-        let items_len = self.items.len();
-        
-        for item in &mut self.items {
-            let probability_float = item.weight / self.total_weight.max(f64::MIN_POSITIVE);
-            // Scale by 10000 for precision
-            item.probability = (probability_float * 10000.0).round() as u32;
-            total_probability = total_probability.saturating_add(item.probability);
+        let weights: Vec<f64> = self.items.iter().map(WeightedItem::stack_weight).collect();
+        self.total_weight = weights.iter().sum();
+        let probabilities = apportion(&weights, 10000);
+
+        for (item, probability) in self.items.iter_mut().zip(probabilities) {
+            item.probability = probability;
         }
+    }
 
-        // Normalize probabilities to ensure they sum exactly to 10000
-        if total_probability > 0 && total_probability != 10000 {
-            let scale_factor = 10000.0 / (total_probability as f64);
-            let mut running_total = 0u32;
-            
-            for (i, item) in self.items.iter_mut().enumerate() {
-                if i == items_len - 1 {
-                    // Last item gets the remainder to ensure exact sum of 10000
-                    item.probability = 10000 - running_total;
-                } else {
-                    item.probability = ((item.probability as f64) * scale_factor).round() as u32;
-                    running_total = running_total.saturating_add(item.probability);
-                }
+    // Clamps every item into the bounds and spreads the slack over the
+    // unclamped items in proportion to their weights. Returns false when the
+    // bounds can't be met with this many items.
+    pub fn apply_bounds(&mut self, bounds: ProbabilityBounds) -> bool {
+        let count = self.items.len() as u32;
+        if bounds.min_bps > bounds.max_bps
+            || bounds.min_bps.saturating_mul(count) > 10000
+            || bounds.max_bps.saturating_mul(count) < 10000
+        {
+            return false;
+        }
+        let within_bounds = self
+            .items
+            .iter()
+            .all(|item| (bounds.min_bps..=bounds.max_bps).contains(&item.probability));
+        if within_bounds {
+            return true;
+        }
+
+        let total_weight: f64 = self.items.iter().map(WeightedItem::stack_weight).sum();
+        if !total_weight.is_finite() || total_weight <= 0.0 {
+            return false;
+        }
+        let shares: Vec<f64> = self
+            .items
+            .iter()
+            .map(|item| item.stack_weight() / total_weight)
+            .collect();
+        let (min, max) = (bounds.min_bps as f64, bounds.max_bps as f64);
+        let clamped_total = |scale: f64| -> f64 {
+            shares.iter().map(|share| (share * scale).clamp(min, max)).sum()
+        };
+
+        // The clamped table grows with the scale, so search for the scale at
+        // which it sums to 10000
+        let mut low = 0.0;
+        let mut high = 10000.0;
+        while clamped_total(high) < 10000.0 {
+            high *= 2.0;
+            if !high.is_finite() {
+                return false;
             }
         }
+        // Enough halvings to exhaust f64 precision, kept low for compute
+        for _ in 0..64 {
+            let mid = (low + high) / 2.0;
+            if clamped_total(mid) < 10000.0 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        // Items pinned to a bound take it exactly; the rest split what's left
+        let mut pinned_total = 0u32;
+        let mut free: Vec<usize> = Vec::new();
+        for (i, share) in shares.iter().enumerate() {
+            let scaled = share * high;
+            if scaled <= min {
+                self.items[i].probability = bounds.min_bps;
+            } else if scaled >= max {
+                self.items[i].probability = bounds.max_bps;
+            } else {
+                free.push(i);
+                continue;
+            }
+            pinned_total += self.items[i].probability;
+        }
+
+        if pinned_total > 10000 || (free.is_empty() && pinned_total != 10000) {
+            return false;
+        }
+
+        let free_weights: Vec<f64> = free.iter().map(|&i| shares[i]).collect();
+        let free_probabilities = apportion(&free_weights, 10000 - pinned_total);
+        for (&i, probability) in free.iter().zip(free_probabilities) {
+            self.items[i].probability = probability;
+        }
+
+        true
     }
 
     // Get probability of a specific item (returns value between 0.0 and 1.0)
-    pub fn get_probability_of_item(&self, item_name: &str) -> f64 {
+    pub fn get_probability_of_item(&self, item_id: u32) -> f64 {
         self.items
             .iter()
-            .find(|item| item.name == item_name)
+            .find(|item| item.id == item_id)
             .map(|item| (item.probability as f64) / 10000.0)
             .unwrap_or(0.0)
     }
 
+    // Probability of one particular unit of a stacked item; the same as
+    // get_probability_of_item for a single one
+    pub fn get_unit_probability_of_item(&self, item_id: u32) -> f64 {
+        let quantity = self
+            .items
+            .iter()
+            .find(|item| item.id == item_id)
+            .map_or(1, |item| item.quantity.max(1));
+        self.get_probability_of_item(item_id) / quantity as f64
+    }
+
     // Get probability of getting a specific item at least once in k spins
-    pub fn get_probability_in_k_spins(&self, item_name: &str, spins: u32) -> f64 {
-        let single_probability = self.get_probability_of_item(item_name);
+    pub fn get_probability_in_k_spins(&self, item_id: u32, spins: u32) -> f64 {
+        let single_probability = self.get_probability_of_item(item_id);
         1.0 - (1.0 - single_probability).powi(spins as i32)
     }
 
     // Calculate expected number of spins to get a specific item
-    pub fn get_expected_spins_for_item(&self, item_name: &str) -> f64 {
-        let probability = self.get_probability_of_item(item_name);
+    pub fn get_expected_spins_for_item(&self, item_id: u32) -> f64 {
+        let probability = self.get_probability_of_item(item_id);
         if probability <= 0.0 {
             return f64::INFINITY;
         }
@@ -133,21 +392,21 @@ impl WeightedProbabilityCalculator {
         total == 10000
     }
 
-    // Get profitability analysis for an item
-    pub fn get_profitability_analysis(&self, item_name: &str) -> Option<ProfitabilityAnalysis> {
-        let item = self.items.iter().find(|item| item.name == item_name)?;
-        let expected_spins = self.get_expected_spins_for_item(item_name);
-        
-        if !expected_spins.is_finite() {
-            return None;
-        }
+    // Get profitability analysis for an item. Costs and profit are in
+    // lamports, like the ticket price and item values they come from.
+    pub fn get_profitability_analysis(&self, item_id: u32) -> Option<ProfitabilityAnalysis> {
+        let item = self.items.iter().find(|item| item.id == item_id)?;
+        let expected_spins = self.get_expected_spins_for_item(item_id);
+        let Lamports(expected_cost) = Lamports(self.ticket_price).per_win(item.probability)?;
 
-        let expected_cost = expected_spins * (self.ticket_price as f64);
-        let profit = (item.value as f64) - expected_cost;
-        let profit_ratio = profit / expected_cost.max(f64::MIN_POSITIVE);
+        let profit = item.value as i128 - expected_cost as i128;
+        let profit_ratio = profit as f64 / (expected_cost as f64).max(f64::MIN_POSITIVE);
 
         Some(ProfitabilityAnalysis {
-            item_name: item_name.to_string(),
+            item_name: item.name.clone(),
+            quantity: item.quantity,
+            stack_probability: self.get_probability_of_item(item_id),
+            unit_probability: self.get_unit_probability_of_item(item_id),
             expected_spins,
             expected_cost,
             item_value: item.value,
@@ -160,13 +419,60 @@ impl WeightedProbabilityCalculator {
 #[derive(Debug)]
 pub struct ProfitabilityAnalysis {
     pub item_name: String,
-    pub expected_spins: f64,
-    pub expected_cost: f64,
+    pub quantity: u32,
+    pub stack_probability: f64, // Of winning any unit of the item, 0.0 to 1.0
+    pub unit_probability: f64,  // Of winning one particular unit
+    pub expected_spins: f64,    // Until any unit is won
+    pub expected_cost: u64, // Lamports spent on tickets per win, on average
     pub item_value: u64,
-    pub profit: f64,
+    pub profit: i128, // Item value less the expected cost, in lamports
     pub profit_ratio: f64,
 }
 
+// Integer odds for clients to show, e.g. "10 tickets: 37% at the prize",
+// as fractions scaled by ODDS_SCALE: 1.0 is 1_000_000_000. Unlike the f64
+// getters above, every node computes exactly the same values.
+pub const ODDS_SCALE: u64 = 1_000_000_000;
+
+// Powers are taken at this finer scale, so the rounding of each product
+// stays far below ODDS_SCALE's last digit
+const FINE_SCALE: u128 = 1_000_000_000_000_000_000;
+
+// Chance that one spin wins an item holding `probability_bps` of the draw
+// when `no_win_bps` of spins draw nothing. Exact, as ODDS_SCALE is a
+// multiple of 10000 * 10000.
+pub fn spin_chance(probability_bps: u32, no_win_bps: u16) -> u64 {
+    let win_bps = 10000 - no_win_bps.min(10000) as u64;
+    probability_bps.min(10000) as u64 * win_bps * (ODDS_SCALE / 100_000_000)
+}
+
+// Chance of winning at least once in `spins` spins, 1 - (1 - chance)^spins,
+// by squaring in u128
+pub fn probability_in_spins(chance: u64, spins: u32) -> u64 {
+    let step = FINE_SCALE / ODDS_SCALE as u128;
+    let mut base = (ODDS_SCALE - chance.min(ODDS_SCALE)) as u128 * step;
+    let mut miss = FINE_SCALE;
+    let mut exponent = spins;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            miss = miss * base / FINE_SCALE;
+        }
+        base = base * base / FINE_SCALE;
+        exponent >>= 1;
+    }
+    ((FINE_SCALE - miss) / step) as u64
+}
+
+// Spins it takes on average to win, 1 / chance, rounded down; None when
+// the item can't be won
+pub fn expected_spins(chance: u64) -> Option<u64> {
+    if chance == 0 {
+        return None;
+    }
+    let spins = ODDS_SCALE as u128 * ODDS_SCALE as u128 / chance.min(ODDS_SCALE) as u128;
+    Some(spins as u64)
+}
+
 // Custom error types for this module
 #[derive(Debug)]
 pub enum ProbabilityError {
@@ -187,16 +493,22 @@ impl From<ProbabilityError> for anchor_lang::error::Error {
     }
 }
 
-// Utility functions for Solana program integration
+// Utility functions for Solana program integration. They take item values
+// only: names play no part in the odds, so none are copied on-chain.
 pub fn calculate_item_probabilities(
-    items: &[(String, u64)],
+    values: &[u64],
     ticket_price: u64,
 ) -> Result<Vec<u32>> {
-    if items.is_empty() {
+    if values.is_empty() {
         return Err(crate::ErrorCode::NoItemsProvided.into());
     }
 
-    let calculator = WeightedProbabilityCalculator::new(items.to_vec(), ticket_price);
+    let calculator = WeightedProbabilityCalculator::from_values(
+        values.iter().copied(),
+        ticket_price,
+        WeightingMode::default(),
+        &[],
+    );
     
     if !calculator.validate_probabilities() {
         return Err(crate::ErrorCode::InvalidProbabilityCalculation.into());
@@ -205,21 +517,73 @@ pub fn calculate_item_probabilities(
     Ok(calculator.items.iter().map(|item| item.probability).collect())
 }
 
+// Every listed item must be winnable, or the prize list advertises items
+// the draw never lands on. Takes (item index, probability) pairs; errors
+// can't carry data, so the first offender's index goes to the program log.
+pub fn check_all_winnable(probabilities: impl IntoIterator<Item = (usize, u32)>) -> Result<()> {
+    let zero = probabilities
+        .into_iter()
+        .find(|&(_, probability)| probability == 0);
+    if let Some((index, _)) = zero {
+        msg!(
+            "Item {} rounds to a 0 bp probability; narrow the value spread or set min_probability_bps",
+            index
+        );
+        return Err(crate::ErrorCode::ZeroProbabilityItem.into());
+    }
+    Ok(())
+}
+
+// Same as calculate_item_probabilities but with an explicit weighting mode
+// and per-item probability bounds. Takes the values by iterator so callers
+// can map straight off the pool without collecting a copy first.
+pub fn calculate_item_probabilities_with_weighting(
+    values: impl IntoIterator<Item = u64>,
+    ticket_price: u64,
+    weighting: WeightingMode,
+    manual_weights: &[u32],
+    bounds: ProbabilityBounds,
+) -> Result<Vec<u32>> {
+    let mut calculator = WeightedProbabilityCalculator::from_values(
+        values,
+        ticket_price,
+        weighting,
+        manual_weights,
+    );
+    if calculator.items.is_empty() {
+        return Err(crate::ErrorCode::NoItemsProvided.into());
+    }
+
+    if !calculator.validate_probabilities() {
+        return Err(crate::ErrorCode::InvalidProbabilityCalculation.into());
+    }
+    if !calculator.apply_bounds(bounds) {
+        return Err(crate::ErrorCode::ProbabilityClampInfeasible.into());
+    }
+
+    if !calculator.validate_probabilities() {
+        return Err(crate::ErrorCode::InvalidProbabilityCalculation.into());
+    }
+
+    Ok(calculator.items.iter().map(|item| item.probability).collect())
+}
+
 // Select winning item based on weighted probabilities
 pub fn select_winning_item_index(
     probabilities: &[u32],
     random_seed: u64,
 ) -> Option<usize> {
-    let total_weight: u32 = probabilities.iter().sum();
+    // Summed in u64 so weights near u32::MAX can't overflow
+    let total_weight: u64 = probabilities.iter().map(|&weight| weight as u64).sum();
     if total_weight == 0 {
         return None;
     }
 
-    let random_value = (random_seed % total_weight as u64) as u32;
-    let mut cumulative_weight = 0u32;
+    let random_value = random_seed % total_weight;
+    let mut cumulative_weight = 0u64;
     
     for (index, &weight) in probabilities.iter().enumerate() {
-        cumulative_weight = cumulative_weight.saturating_add(weight);
+        cumulative_weight += weight as u64;
         if random_value < cumulative_weight {
             return Some(index);
         }
@@ -228,101 +592,478 @@ pub fn select_winning_item_index(
     None
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use approx::assert_relative_eq;
-
-//     #[test]
-//     fn test_probability_calculation() {
-//         let items = vec![
-//             ("iPhone".to_string(), 10),
-//             ("iPad".to_string(), 50),
-//             ("MacBook".to_string(), 200),
-//             ("AirPods".to_string(), 1000),
-//         ];
+// Everything a spin drew from: each item's weight by position, 0 for items
+// the draw couldn't land on, and the no-win share rolled before them.
+// Replaying it against the spin's seed gives back the spin's result, so a
+// ticket only needs to keep the hash.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DrawTable {
+    pub no_win_bps: u16,
+    pub weights: Vec<u32>,
+}
+
+impl DrawTable {
+    pub fn hash(&self) -> [u8; 32] {
+        let weights: Vec<u8> = self
+            .weights
+            .iter()
+            .flat_map(|weight| weight.to_le_bytes())
+            .collect();
+        hashv(&[&self.no_win_bps.to_le_bytes(), &weights]).to_bytes()
+    }
+
+    // None for a losing spin, otherwise the won item's position. The no-win
+    // roll reads the seed's upper half, as the pool's draw does.
+    pub fn replay(&self, random_seed: u64) -> Option<usize> {
+        if random_seed.rotate_right(32) % 10000 < self.no_win_bps as u64 {
+            return None;
+        }
+        select_winning_item_index(&self.weights, random_seed)
+    }
+}
+
+// Raffle winners: `count` distinct ticket ids below `tickets`, each ticket
+// equally likely for the first pick. A repeat moves on to the next id not
+// yet drawn, so the draw never needs more than `count` hashes.
+pub fn draw_raffle_ticket_ids(seed: &[u8; 32], tickets: u64, count: usize) -> Vec<u64> {
+    let count = tickets.min(count as u64);
+    let mut winners = Vec::with_capacity(count as usize);
+    for pick in 0..count {
+        let entropy = hashv(&[seed, &pick.to_le_bytes()]).to_bytes();
+        let mut ticket_id = u64::from_le_bytes(entropy[..8].try_into().unwrap()) % tickets;
+        while winners.contains(&ticket_id) {
+            ticket_id = (ticket_id + 1) % tickets;
+        }
+        winners.push(ticket_id);
+    }
+    winners
+}
+
+// Pseudo-random seed mixed from several on-chain sources; shared by spins and gambles
+pub fn generate_random_seed(
+    timestamp: i64,
+    slot: u64,
+    user: &Pubkey,
+    tickets_sold: u64,
+    vault_lamports: u64,
+    ticket_id: u64,
+) -> u64 {
+    timestamp as u64
+        ^ user.to_bytes()[0..8]
+            .iter()
+            .enumerate()
+            .fold(0u64, |acc, (i, &byte)| acc ^ ((byte as u64) << (i * 8)))
+        ^ tickets_sold
+        ^ vault_lamports
+        ^ slot
+        ^ ticket_id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Uniform in lo..=hi, with each end drawn an eighth of the time so the
+    // extremes come up
+    fn skewed(lo: u64, hi: u64) -> impl Strategy<Value = u64> {
+        prop_oneof![1 => Just(lo), 1 => Just(hi), 6 => lo..=hi]
+    }
+
+    fn item_values(max: u64) -> impl Strategy<Value = Vec<u64>> {
+        prop::collection::vec(skewed(1, max), 1..=10)
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 0.0001, "{} != {}", a, b);
+    }
+
+    fn named(values: &[u64]) -> Vec<(String, u64, u32)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| (format!("Item{}", i), value, 1))
+            .collect()
+    }
+
+    fn probabilities_of(calc: &WeightedProbabilityCalculator) -> Vec<u32> {
+        calc.items.iter().map(|item| item.probability).collect()
+    }
+
+    #[test]
+    fn test_probability_calculation() {
+        let items = vec![
+            ("iPhone".to_string(), 10, 1),
+            ("iPad".to_string(), 50, 1),
+            ("MacBook".to_string(), 200, 1),
+            ("AirPods".to_string(), 1000, 1),
+        ];
         
-//         let calculator = WeightedProbabilityCalculator::new(items, 100);
+        let calculator = WeightedProbabilityCalculator::new(items, 100);
         
-//         // Verify probabilities sum to 10000 (100%)
-//         assert!(calculator.validate_probabilities());
+        // Verify probabilities sum to 10000 (100%)
+        assert!(calculator.validate_probabilities());
         
-//         // Higher value items should have lower probability
-//         let iphone_prob = calculator.get_probability_of_item("iPhone");
-//         let airpods_prob = calculator.get_probability_of_item("AirPods");
+        // Higher value items should have lower probability
+        let iphone_prob = calculator.get_probability_of_item(0);
+        let airpods_prob = calculator.get_probability_of_item(3);
         
-//         assert!(iphone_prob > airpods_prob);
-//     }
-
-//     #[test]
-//     fn test_single_item() {
-//         let single_item = vec![("Prize".to_string(), 100)];
-//         let calc = WeightedProbabilityCalculator::new(single_item, 10);
-//         assert_eq!(calc.items[0].probability, 10000);
-//     }
-
-//     #[test]
-//     fn test_equal_value_items() {
-//         let equal_items = vec![
-//             ("A".to_string(), 100),
-//             ("B".to_string(), 100),
-//             ("C".to_string(), 100)
-//         ];
-//         let calc = WeightedProbabilityCalculator::new(equal_items, 10);
-//         assert!((3333..=3334).contains(&calc.items[0].probability));
-//     }
-
-//     #[test]
-//     fn test_random_selection_distribution() {
-//         let items = vec![
-//             ("Common".to_string(), 100),  // ~70%
-//             ("Rare".to_string(), 500),    // ~20%
-//             ("Legendary".to_string(), 2000) // ~10%
-//         ];
+        assert!(iphone_prob > airpods_prob);
+    }
+
+    #[test]
+    fn test_single_item() {
+        let single_item = vec![("Prize".to_string(), 100, 1)];
+        let calc = WeightedProbabilityCalculator::new(single_item, 10);
+        assert_eq!(calc.items[0].probability, 10000);
+    }
+
+    #[test]
+    fn test_equal_value_items() {
+        let equal_items = vec![
+            ("A".to_string(), 100, 1),
+            ("B".to_string(), 100, 1),
+            ("C".to_string(), 100, 1)
+        ];
+        let calc = WeightedProbabilityCalculator::new(equal_items, 10);
+        assert!((3333..=3334).contains(&calc.items[0].probability));
+    }
+
+    #[test]
+    fn stacked_units_weigh_like_separate_items() {
+        // Five units of one prize and three of another, all at the same price
+        let stacked = WeightedProbabilityCalculator::new(
+            vec![("AirPods".to_string(), 100, 5), ("Watch".to_string(), 100, 3)],
+            10,
+        );
+        let separate = WeightedProbabilityCalculator::new(named(&[100; 8]), 10);
+        assert!(stacked.validate_probabilities());
+        assert_eq!(probabilities_of(&stacked), vec![6250, 3750]);
+        let five_singles: u32 = separate.items[..5]
+            .iter()
+            .map(|item| item.probability)
+            .sum();
+        assert_eq!(stacked.items[0].probability, five_singles);
+        assert_eq!(
+            stacked.get_unit_probability_of_item(0),
+            separate.get_probability_of_item(0)
+        );
+
+        // Odds shrink as units are won, down to nothing for an empty stack
+        let stacked = stacked.with_quantities(&[1, 3]);
+        assert_eq!(probabilities_of(&stacked), vec![2500, 7500]);
+        assert_eq!(
+            stacked.get_unit_probability_of_item(0),
+            stacked.get_probability_of_item(0)
+        );
+        let stacked = stacked.with_quantities(&[0, 3]);
+        assert_eq!(probabilities_of(&stacked), vec![0, 10000]);
+    }
+
+    #[test]
+    fn analysis_reports_unit_and_stack_odds() {
+        // A stack of five next to five singles of a pricier item
+        let mut items = vec![("AirPods".to_string(), 100, 5)];
+        items.extend((0..5).map(|i| (format!("Watch{}", i), 400, 1)));
+        let calc = WeightedProbabilityCalculator::new(items, 10);
+        assert!(calc.validate_probabilities());
+
+        let stack = calc.get_profitability_analysis(0).unwrap();
+        assert_eq!(stack.quantity, 5);
+        assert_close(stack.stack_probability, calc.items[0].probability as f64 / 10000.0);
+        assert_close(stack.unit_probability, stack.stack_probability / 5.0);
+        assert_close(stack.expected_spins, 1.0 / stack.stack_probability);
+
+        // The singles add up to what the same five units would as one stack
+        let watches: f64 = (1..=5)
+            .map(|id| calc.get_profitability_analysis(id).unwrap().stack_probability)
+            .sum();
+        let as_stack = WeightedProbabilityCalculator::new(
+            vec![("AirPods".to_string(), 100, 5), ("Watch".to_string(), 400, 5)],
+            10,
+        );
+        assert_close(watches, as_stack.get_probability_of_item(1));
+        let single = calc.get_profitability_analysis(1).unwrap();
+        assert_eq!(single.quantity, 1);
+        assert_eq!(single.unit_probability, single.stack_probability);
+    }
+
+    proptest! {
+        #[test]
+        fn value_only_calculator_matches_the_named_one(
+            (values, manual) in item_values(u64::MAX).prop_flat_map(|values| {
+                let len = values.len();
+                (Just(values), prop::collection::vec(skewed(0, 1000), len))
+            }),
+            ticket_price in skewed(1, u64::MAX),
+        ) {
+            let manual: Vec<u32> = manual.iter().map(|&weight| weight as u32).collect();
+            let modes = [
+                WeightingMode::default(),
+                WeightingMode::InverseValue,
+                WeightingMode::TicketRatio { exponent_milli: 2500 },
+                WeightingMode::Manual,
+            ];
+            for weighting in modes {
+                let named_calc = WeightedProbabilityCalculator::with_weighting(
+                    named(&values),
+                    ticket_price,
+                    weighting,
+                    &manual,
+                );
+                let value_calc = WeightedProbabilityCalculator::from_values(
+                    values.iter().copied(),
+                    ticket_price,
+                    weighting,
+                    &manual,
+                );
+                prop_assert_eq!(probabilities_of(&value_calc), probabilities_of(&named_calc));
+                prop_assert_eq!(
+                    value_calc.total_weight.to_bits(),
+                    named_calc.total_weight.to_bits()
+                );
+                prop_assert!(value_calc.items.iter().all(|item| item.name.is_empty()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_selection_distribution() {
+        let items = vec![
+            ("Common".to_string(), 100, 1),
+            ("Rare".to_string(), 500, 1),
+            ("Legendary".to_string(), 2000, 1)
+        ];
         
-//         let calc = WeightedProbabilityCalculator::new(items, 10);
-//         let mut results = [0, 0, 0];
+        let calc = WeightedProbabilityCalculator::new(items, 10);
+        let probabilities = probabilities_of(&calc);
+        let mut results = [0u32; 3];
         
-//         // Simulate 10,000 spins
-//         for seed in 0..10_000 {
-//             let winner = select_winning_item_index(
-//                 &calc.items.iter().map(|i| i.probability).collect::<Vec<_>>(),
-//                 seed
-//             ).unwrap();
-//             results[winner] += 1;
-//         }
+        // Consecutive seeds walk the table evenly, so 10,000 spins
+        // reproduce the configured basis points exactly
+        for seed in 0..10_000 {
+            let winner = select_winning_item_index(&probabilities, seed).unwrap();
+            results[winner] += 1;
+        }
         
-//         // Verify distribution is roughly correct
-//         assert!(results[0] > 6500 && results[0] < 7500); // Common
-//         assert!(results[1] > 1500 && results[1] < 2500); // Rare
-//         assert!(results[2] > 500 && results[2] < 1500);  // Legendary
-//     }
-
-//     #[test]
-//     fn test_probability_math() {
-//         let items = vec![
-//             ("A".to_string(), 100),
-//             ("B".to_string(), 200)
-//         ];
+        assert_eq!(results.to_vec(), probabilities);
+    }
+
+    #[test]
+    fn test_probability_math() {
+        let items = vec![
+            ("A".to_string(), 100, 1),
+            ("B".to_string(), 200, 1)
+        ];
         
-//         let calc = WeightedProbabilityCalculator::new(items, 10);
+        let calc = WeightedProbabilityCalculator::new(items, 10);
         
-//         // Test probability calculations
-//         let prob_a = calc.get_probability_of_item("A");
-//         let prob_b = calc.get_probability_of_item("B");
+        // Test probability calculations
+        let prob_a = calc.get_probability_of_item(0);
+        let prob_b = calc.get_probability_of_item(1);
         
-//         assert_relative_eq!(prob_a + prob_b, 1.0, epsilon = 0.0001);
+        assert_close(prob_a + prob_b, 1.0);
         
-//         // Test expected spins
-//         let expected_a = calc.get_expected_spins_for_item("A");
-//         assert_relative_eq!(expected_a, 1.0 / prob_a, epsilon = 0.0001);
+        // Test expected spins
+        let expected_a = calc.get_expected_spins_for_item(0);
+        assert_close(expected_a, 1.0 / prob_a);
         
-//         // Test probability in k spins
-//         let prob_in_10 = calc.get_probability_in_k_spins("A", 10);
-//         assert_relative_eq!(
-//             prob_in_10,
-//             1.0 - (1.0 - prob_a).powi(10),
-//             epsilon = 0.0001
-//         );
-//     }
-// }
\ No newline at end of file
+        // Test probability in k spins
+        let prob_in_10 = calc.get_probability_in_k_spins(0, 10);
+        assert_close(prob_in_10, 1.0 - (1.0 - prob_a).powi(10));
+    }
+
+    #[test]
+    fn fixed_point_odds_match_float_references() {
+        let scale = ODDS_SCALE as f64;
+        for probability_bps in [1, 7, 250, 3333, 5000, 9999, 10000] {
+            for no_win_bps in [0, 1500, 9999] {
+                let chance = spin_chance(probability_bps, no_win_bps);
+                let p = probability_bps as f64 / 10000.0 * (10000 - no_win_bps) as f64 / 10000.0;
+                assert!((chance as f64 / scale - p).abs() < 1e-15);
+
+                for spins in [0, 1, 2, 10, 99, 500, 1000] {
+                    let reference = 1.0 - (1.0 - p).powi(spins as i32);
+                    let odds = probability_in_spins(chance, spins) as f64 / scale;
+                    assert!(
+                        (odds - reference).abs() < 1e-8,
+                        "{} bps, {} no-win, {} spins: {} != {}",
+                        probability_bps,
+                        no_win_bps,
+                        spins,
+                        odds,
+                        reference
+                    );
+                }
+
+                let expected = expected_spins(chance).unwrap() as f64 / scale;
+                assert!((expected - 1.0 / p).abs() / (1.0 / p) < 1e-9);
+            }
+        }
+
+        // Items that can't be won never are; certain ones always are
+        assert_eq!(probability_in_spins(0, 1000), 0);
+        assert_eq!(expected_spins(0), None);
+        assert_eq!(probability_in_spins(ODDS_SCALE, 1), ODDS_SCALE);
+        assert_eq!(probability_in_spins(ODDS_SCALE, 0), 0);
+        assert_eq!(
+            probability_in_spins(spin_chance(10000, 0), u32::MAX),
+            ODDS_SCALE
+        );
+    }
+
+    #[test]
+    fn test_profitability_in_lamports() {
+        const SOL: u64 = 1_000_000_000;
+
+        // A 2 SOL item that every 0.1 SOL spin wins
+        let calc = WeightedProbabilityCalculator::new(named(&[2 * SOL]), SOL / 10);
+        let analysis = calc.get_profitability_analysis(0).unwrap();
+        assert_eq!(analysis.expected_cost, 100_000_000);
+        assert_eq!(analysis.profit, 1_900_000_000);
+        assert_close(analysis.profit_ratio, 19.0);
+
+        // Won one spin in four, next to a 0.1 SOL item that loses money
+        let calc = WeightedProbabilityCalculator::with_weighting(
+            named(&[2 * SOL, SOL / 10]),
+            SOL / 10,
+            WeightingMode::Manual,
+            &[1, 3],
+        );
+        let analysis = calc.get_profitability_analysis(0).unwrap();
+        assert_eq!(analysis.expected_spins, 4.0);
+        assert_eq!(analysis.expected_cost, 400_000_000);
+        assert_eq!(analysis.profit, 1_600_000_000);
+        let analysis = calc.get_profitability_analysis(1).unwrap();
+        assert_eq!(analysis.expected_cost, 133_333_333);
+        assert_eq!(analysis.profit, -33_333_333);
+    }
+
+    #[test]
+    fn test_selection_edge_cases() {
+        assert_eq!(select_winning_item_index(&[], 42), None);
+        assert_eq!(select_winning_item_index(&[0, 0, 0], 42), None);
+        assert_eq!(select_winning_item_index(&[10000], u64::MAX), Some(0));
+
+        // Weights that overflow a u32 sum still select correctly
+        let saturated = [u32::MAX, u32::MAX, 1];
+        assert_eq!(select_winning_item_index(&saturated, 0), Some(0));
+        assert_eq!(select_winning_item_index(&saturated, u32::MAX as u64), Some(1));
+        assert_eq!(select_winning_item_index(&saturated, 2 * u32::MAX as u64), Some(2));
+    }
+
+    #[test]
+    fn test_extreme_item_values() {
+        for values in [
+            vec![1],
+            vec![u64::MAX],
+            vec![1, u64::MAX],
+            vec![u64::MAX; 10],
+        ] {
+            for ticket_price in [1, u64::MAX] {
+                let calc = WeightedProbabilityCalculator::new(named(&values), ticket_price);
+                assert!(calc.validate_probabilities(), "{:?} at {}", values, ticket_price);
+            }
+        }
+
+        let calc = WeightedProbabilityCalculator::new(named(&[u64::MAX; 10]), 1);
+        assert_eq!(probabilities_of(&calc), vec![1000; 10]);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(512))]
+
+        #[test]
+        fn prop_normalization_sums_to_10000(
+            values in item_values(u64::MAX),
+            ticket_price in skewed(1, u64::MAX),
+        ) {
+            let calc = WeightedProbabilityCalculator::new(named(&values), ticket_price);
+            let total: u32 = probabilities_of(&calc).iter().sum();
+            prop_assert_eq!(total, 10000, "{:?} at {}", values, ticket_price);
+        }
+
+        #[test]
+        fn prop_selection_never_panics_on_wide_weights(
+            table in prop::collection::vec(prop_oneof![1 => Just(0), 1 => Just(u32::MAX), 6 => any::<u32>()], 0..=16),
+            seed in any::<u64>(),
+        ) {
+            match select_winning_item_index(&table, seed) {
+                Some(index) => prop_assert!(table[index] > 0),
+                None => prop_assert!(table.iter().all(|&p| p == 0)),
+            }
+        }
+
+        #[test]
+        fn prop_raffle_draws_distinct_tickets_in_range(
+            seed in any::<[u8; 32]>(),
+            tickets in skewed(1, 60),
+            count in 0..=50usize,
+        ) {
+            let winners = draw_raffle_ticket_ids(&seed, tickets, count);
+
+            prop_assert_eq!(winners.len() as u64, tickets.min(count as u64));
+            prop_assert!(winners.iter().all(|&ticket_id| ticket_id < tickets));
+            let mut distinct = winners.clone();
+            distinct.sort_unstable();
+            distinct.dedup();
+            prop_assert_eq!(distinct.len(), winners.len());
+            prop_assert_eq!(winners, draw_raffle_ticket_ids(&seed, tickets, count));
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(4096))]
+
+        #[test]
+        fn prop_selection_skips_zero_probability_items(
+            mut table in prop::collection::vec(prop_oneof![1 => Just(0), 2 => 1..=10000u32], 1..=10),
+            seed in any::<u64>(),
+        ) {
+            if table.iter().all(|&p| p == 0) {
+                let last = table.len() - 1;
+                table[last] = 1;
+            }
+
+            let index = select_winning_item_index(&table, seed).unwrap();
+            prop_assert!(table[index] > 0, "{:?} picked {} for seed {}", table, index, seed);
+        }
+    }
+
+    // Every run draws fresh tables and seeds, so the bound is wide enough that
+    // a correct draw essentially never strays past it
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn prop_selection_frequencies_within_five_sigma(
+            values in item_values(1_000_000),
+            ticket_price in skewed(1, 10_000),
+            seeds in prop::collection::vec(any::<u64>(), 100_000),
+        ) {
+            let probabilities =
+                probabilities_of(&WeightedProbabilityCalculator::new(named(&values), ticket_price));
+
+            let mut wins = vec![0u64; probabilities.len()];
+            for &seed in &seeds {
+                wins[select_winning_item_index(&probabilities, seed).unwrap()] += 1;
+            }
+
+            let spins = seeds.len() as f64;
+            for (&probability, &won) in probabilities.iter().zip(&wins) {
+                let p = probability as f64 / 10000.0;
+                let expected = p * spins;
+                let sigma = (spins * p * (1.0 - p)).sqrt();
+                prop_assert!(
+                    (won as f64 - expected).abs() <= 5.0 * sigma + 1.0,
+                    "{} wins vs {} expected at {} bp", won, expected, probability
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn raffle_draws_nothing_from_an_empty_pool() {
+        assert!(draw_raffle_ticket_ids(&[0; 32], 0, 3).is_empty());
+    }
+}
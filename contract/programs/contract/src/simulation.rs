@@ -0,0 +1,287 @@
+// Monte Carlo model of a pool's economics, for companies sizing one up
+// before they create it: each trial sells `num_tickets` tickets and draws
+// every one of them with select_winning_item_index, as a spin does. Only
+// built off-chain.
+
+use crate::probability::{select_winning_item_index, WeightedProbabilityCalculator};
+
+// Trials simulate_pool runs; each one is a full sale of the pool
+pub const DEFAULT_TRIALS: u32 = 2_000;
+
+#[derive(Clone, Debug)]
+pub struct SimulatedItem {
+    pub name: String,
+    pub value: u64,       // Lamports paid out per win
+    pub probability: u32, // Basis points, as the pool would store them
+    pub wins: u64,        // Across all trials
+}
+
+#[derive(Clone, Debug)]
+pub struct SimulationReport {
+    pub ticket_price: u64,
+    pub num_tickets: u64,
+    pub trials: u32,
+    pub items: Vec<SimulatedItem>,
+    // Each trial's total payout in lamports, ascending
+    pub payouts: Vec<u64>,
+    // Each trial's deepest point below the vault it started with: the most
+    // the payouts so far ran ahead of the ticket revenue so far. Ascending.
+    pub drawdowns: Vec<u64>,
+}
+
+impl SimulationReport {
+    // Ticket revenue of one trial, in lamports
+    pub fn revenue(&self) -> u64 {
+        self.ticket_price.saturating_mul(self.num_tickets)
+    }
+
+    pub fn mean_payout(&self) -> f64 {
+        if self.payouts.is_empty() {
+            return 0.0;
+        }
+        self.payouts
+            .iter()
+            .map(|&payout| payout as f64)
+            .sum::<f64>()
+            / self.payouts.len() as f64
+    }
+
+    pub fn payout_std_dev(&self) -> f64 {
+        if self.payouts.is_empty() {
+            return 0.0;
+        }
+        let mean = self.mean_payout();
+        let variance = self
+            .payouts
+            .iter()
+            .map(|&payout| (payout as f64 - mean).powi(2))
+            .sum::<f64>()
+            / self.payouts.len() as f64;
+        variance.sqrt()
+    }
+
+    // Revenue less the mean payout; negative when the pool loses money
+    pub fn expected_profit(&self) -> f64 {
+        self.revenue() as f64 - self.mean_payout()
+    }
+
+    // Payout not exceeded by `percent` of the trials (nearest rank)
+    pub fn payout_percentile(&self, percent: u8) -> u64 {
+        percentile(&self.payouts, percent)
+    }
+
+    // Profit at the same rank, counted from the worst trial up: the 5th
+    // percentile is the profit 95% of trials beat
+    pub fn profit_percentile(&self, percent: u8) -> i128 {
+        self.revenue() as i128 - self.payout_percentile(100 - percent.min(100)) as i128
+    }
+
+    // Share of trials in which a vault starting with `starting_vault`
+    // lamports can't pay a win when it comes
+    pub fn insolvency_probability(&self, starting_vault: u64) -> f64 {
+        if self.drawdowns.is_empty() {
+            return 0.0;
+        }
+        let insolvent = self
+            .drawdowns
+            .iter()
+            .filter(|&&drawdown| drawdown > starting_vault)
+            .count();
+        insolvent as f64 / self.drawdowns.len() as f64
+    }
+
+    // Share of all simulated draws won by the item at `index`
+    pub fn win_frequency(&self, index: usize) -> f64 {
+        let draws = self.num_tickets as f64 * self.trials as f64;
+        match self.items.get(index) {
+            Some(item) if draws > 0.0 => item.wins as f64 / draws,
+            _ => 0.0,
+        }
+    }
+}
+
+fn percentile(sorted: &[u64], percent: u8) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (sorted.len() * percent.min(100) as usize).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+// Runs DEFAULT_TRIALS trials of the pool the items would make at this
+// ticket price, weighted as a new pool is. The same seed gives the same
+// report.
+pub fn simulate_pool(
+    items: &[(String, u64)],
+    ticket_price: u64,
+    num_tickets: u64,
+    seed: u64,
+) -> SimulationReport {
+    simulate_pool_with_trials(items, ticket_price, num_tickets, seed, DEFAULT_TRIALS)
+}
+
+pub fn simulate_pool_with_trials(
+    items: &[(String, u64)],
+    ticket_price: u64,
+    num_tickets: u64,
+    seed: u64,
+    trials: u32,
+) -> SimulationReport {
+    let calculator = WeightedProbabilityCalculator::new(
+        items.iter().map(|(name, value)| (name.clone(), *value, 1)),
+        ticket_price,
+    );
+    let probabilities: Vec<u32> = calculator
+        .items
+        .iter()
+        .map(|item| item.probability)
+        .collect();
+
+    let mut rng = SplitMix64(seed);
+    let mut wins = vec![0u64; items.len()];
+    let mut payouts = Vec::with_capacity(trials as usize);
+    let mut drawdowns = Vec::with_capacity(trials as usize);
+    for _ in 0..trials {
+        // Revenue less payouts so far; the vault dips below its start
+        // whenever this goes negative
+        let mut net: i128 = 0;
+        let mut deepest: i128 = 0;
+        let mut payout = 0u64;
+        for _ in 0..num_tickets {
+            net += ticket_price as i128;
+            let Some(index) = select_winning_item_index(&probabilities, rng.next()) else {
+                continue;
+            };
+            wins[index] += 1;
+            let value = calculator.items[index].value;
+            payout = payout.saturating_add(value);
+            net -= value as i128;
+            deepest = deepest.min(net);
+        }
+        payouts.push(payout);
+        drawdowns.push(u64::try_from(-deepest).unwrap_or(u64::MAX));
+    }
+    payouts.sort_unstable();
+    drawdowns.sort_unstable();
+
+    SimulationReport {
+        ticket_price,
+        num_tickets,
+        trials,
+        items: calculator
+            .items
+            .iter()
+            .zip(wins)
+            .map(|(item, wins)| SimulatedItem {
+                name: item.name.clone(),
+                value: item.value,
+                probability: item.probability,
+                wins,
+            })
+            .collect(),
+        payouts,
+        drawdowns,
+    }
+}
+
+// splitmix64: fast, and reproducible from the seed on every platform
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL: u64 = 1_000_000_000;
+
+    fn items(values: &[u64]) -> Vec<(String, u64)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| (format!("Item{}", i), value))
+            .collect()
+    }
+
+    #[test]
+    fn simulated_frequencies_converge_to_the_configured_odds() {
+        let pool = items(&[SOL / 20, SOL / 5, SOL, 5 * SOL]);
+        let report = simulate_pool(&pool, SOL / 10, 1_000, 42);
+        let draws = (report.num_tickets * report.trials as u64) as f64;
+
+        for (index, item) in report.items.iter().enumerate() {
+            let p = item.probability as f64 / 10000.0;
+            // Four standard deviations of the win count, as a share
+            let tolerance = 4.0 * (p * (1.0 - p) / draws).sqrt();
+            assert!(
+                (report.win_frequency(index) - p).abs() <= tolerance,
+                "{} won {} of draws, configured {} bps",
+                item.name,
+                report.win_frequency(index),
+                item.probability
+            );
+        }
+        let total_wins: u64 = report.items.iter().map(|item| item.wins).sum();
+        assert_eq!(total_wins as f64, draws);
+
+        // The mean payout lands near the pool's expected value
+        let expected_value: f64 = report
+            .items
+            .iter()
+            .map(|item| item.value as f64 * item.probability as f64 / 10000.0)
+            .sum::<f64>()
+            * report.num_tickets as f64;
+        let standard_error = report.payout_std_dev() / (report.trials as f64).sqrt();
+        assert!((report.mean_payout() - expected_value).abs() <= 4.0 * standard_error);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_report() {
+        let pool = items(&[SOL / 10, SOL]);
+        let first = simulate_pool_with_trials(&pool, SOL / 10, 200, 7, 100);
+        let again = simulate_pool_with_trials(&pool, SOL / 10, 200, 7, 100);
+        assert_eq!(first.payouts, again.payouts);
+        assert_eq!(first.drawdowns, again.drawdowns);
+
+        let other = simulate_pool_with_trials(&pool, SOL / 10, 200, 8, 100);
+        assert_ne!(first.payouts, other.payouts);
+    }
+
+    #[test]
+    fn insolvency_needs_a_win_the_vault_cannot_cover() {
+        // Every prize costs less than a ticket, so the vault never dips
+        let cheap = simulate_pool_with_trials(&items(&[SOL / 20, SOL / 50]), SOL / 10, 50, 1, 50);
+        assert_eq!(cheap.insolvency_probability(0), 0.0);
+        assert!(cheap.expected_profit() > 0.0);
+
+        // Every ticket wins the only prize, so five 0.1 SOL tickets leave the
+        // vault 49.5 SOL down by the end
+        let one_prize = simulate_pool_with_trials(&items(&[10 * SOL]), SOL / 10, 5, 1, 20);
+        let shortfall = 50 * SOL - 5 * (SOL / 10);
+        assert_eq!(one_prize.drawdowns, vec![shortfall; 20]);
+        assert_eq!(one_prize.insolvency_probability(0), 1.0);
+        assert_eq!(one_prize.insolvency_probability(shortfall - 1), 1.0);
+        assert_eq!(one_prize.insolvency_probability(shortfall), 0.0);
+        assert_eq!(one_prize.payout_percentile(50), 50 * SOL);
+        assert_eq!(one_prize.profit_percentile(50), -(shortfall as i128));
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let sorted: Vec<u64> = (1..=10).collect();
+        assert_eq!(percentile(&sorted, 0), 1);
+        assert_eq!(percentile(&sorted, 5), 1);
+        assert_eq!(percentile(&sorted, 50), 5);
+        assert_eq!(percentile(&sorted, 95), 10);
+        assert_eq!(percentile(&sorted, 100), 10);
+        assert_eq!(percentile(&[], 50), 0);
+    }
+}
@@ -0,0 +1,64 @@
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+
+// Every amount the program stores, compares or transfers is in lamports:
+// ticket and item prices, vault balances, bonuses. Lamports marks a value as
+// such where it leaves that world, i.e. when it is shown to a person.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Lamports(pub u64);
+
+impl Lamports {
+    // Exact amount in SOL for logs, e.g. "2 SOL" or "0.000005 SOL". Integer
+    // formatting, so no lamport is lost to float rounding.
+    pub fn to_sol_display(self) -> String {
+        let whole = self.0 / LAMPORTS_PER_SOL;
+        let fraction = self.0 % LAMPORTS_PER_SOL;
+        if fraction == 0 {
+            return format!("{} SOL", whole);
+        }
+        let digits = format!("{:09}", fraction);
+        format!("{}.{} SOL", whole, digits.trim_end_matches('0'))
+    }
+
+    // What `1 / probability` spins at this price cost on average, rounded
+    // down; None for an item that can't be won
+    pub fn per_win(self, probability_bps: u32) -> Option<Lamports> {
+        if probability_bps == 0 {
+            return None;
+        }
+        let cost = self.0 as u128 * 10000 / probability_bps as u128;
+        Some(Lamports(u64::try_from(cost).unwrap_or(u64::MAX)))
+    }
+}
+
+impl From<u64> for Lamports {
+    fn from(lamports: u64) -> Self {
+        Lamports(lamports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sol_display_keeps_every_lamport() {
+        assert_eq!(Lamports(0).to_sol_display(), "0 SOL");
+        assert_eq!(Lamports(2 * LAMPORTS_PER_SOL).to_sol_display(), "2 SOL");
+        assert_eq!(Lamports(LAMPORTS_PER_SOL / 10).to_sol_display(), "0.1 SOL");
+        assert_eq!(Lamports(5000).to_sol_display(), "0.000005 SOL");
+        assert_eq!(
+            Lamports(u64::MAX).to_sol_display(),
+            "18446744073.709551615 SOL"
+        );
+    }
+
+    #[test]
+    fn per_win_scales_the_price_by_the_odds() {
+        let ticket = Lamports(LAMPORTS_PER_SOL / 10);
+        assert_eq!(ticket.per_win(10000), Some(ticket));
+        assert_eq!(ticket.per_win(2500), Some(Lamports(400_000_000)));
+        assert_eq!(ticket.per_win(7500), Some(Lamports(133_333_333)));
+        assert_eq!(ticket.per_win(0), None);
+        assert_eq!(Lamports(u64::MAX).per_win(1), Some(Lamports(u64::MAX)));
+    }
+}
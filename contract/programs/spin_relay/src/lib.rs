@@ -0,0 +1,47 @@
+// Test-only program: forwards a record_spin_result instruction to
+// company_pool through a CPI, so the integration tests can check that
+// wrapped spins are rejected. Never deployed outside local tests.
+
+// Anchor 0.31 codegen still calls the deprecated AccountInfo::realloc
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::InstructionData;
+use company_pool::program::CompanyPool;
+
+declare_id!("xoV4a47xnuXAqNNZPnJB3zyvMRx869GiJk8qtPa5Xu5");
+
+#[program]
+pub mod spin_relay {
+    use super::*;
+
+    // remaining_accounts are record_spin_result's accounts, in order
+    pub fn relay_spin<'info>(ctx: Context<'_, '_, 'info, 'info, RelaySpin<'info>>) -> Result<()> {
+        let accounts = ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect();
+        let instruction = Instruction {
+            program_id: company_pool::ID,
+            accounts,
+            data: company_pool::instruction::RecordSpinResult {}.data(),
+        };
+
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.company_pool_program.to_account_info());
+        invoke(&instruction, &account_infos)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct RelaySpin<'info> {
+    pub company_pool_program: Program<'info, CompanyPool>,
+}
@@ -0,0 +1,16 @@
+#!/usr/bin/env bash
+# Builds the programs and fails if the SBF linker reports a stack frame past
+# the 4KB limit. `anchor build` only prints those as warnings, but the
+# function faults with an access violation the first time it runs.
+set -euo pipefail
+cd "$(dirname "$0")/.."
+
+log=$(mktemp)
+trap 'rm -f "$log"' EXIT
+
+anchor build 2>&1 | tee "$log"
+if grep -q "Stack offset of" "$log"; then
+  echo "error: a function's stack frame exceeds the SBF limit:" >&2
+  grep "Stack offset of" "$log" >&2
+  exit 1
+fi
@@ -3,6 +3,17 @@ import { Program, AnchorProvider, web3, setProvider } from "@coral-xyz/anchor";
 import { CompanyPool } from "../target/types/company_pool";
 import { assert } from "chai";
 import { PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { createHash } from "crypto";
+
+// Pool PDAs are seeded by the sha256 of the company name
+const poolNameSeed = (name: string): Buffer => createHash("sha256").update(name).digest();
 
 describe("company_pool - Complete Test Suite", () => {
   // Configure the client to use the local cluster.
@@ -28,14 +39,82 @@ describe("company_pool - Complete Test Suite", () => {
     image: "https://test.com/item1.png",
     price: new anchor.BN(10),
     name: "Item1",
-    description: "Test item 1"
+    description: "Test item 1",
+    itemKind: { solPayout: {} },
+    jackpot: false,
+    manualWeight: 0,
+    tier: null,
+    sponsor: null,
+    sponsorShareBps: 0,
+    revealHash: null,
+    rewardMint: null,
+    rewardAmount: new anchor.BN(0),
   };
 
   const item2 = {
     image: "https://test.com/item2.png", 
     price: new anchor.BN(50),
     name: "Item2",
-    description: "Test item 2"
+    description: "Test item 2",
+    itemKind: { solPayout: {} },
+    jackpot: false,
+    manualWeight: 0,
+    tier: null,
+    sponsor: null,
+    sponsorShareBps: 0,
+    revealHash: null,
+    rewardMint: null,
+    rewardAmount: new anchor.BN(0),
+  };
+
+  // No purchase limits unless a test opts in
+  const defaultPoolConfig = {
+    maxTicketsPerUser: null,
+    purchaseCooldownSeconds: null,
+    whitelistEnabled: false,
+    gateMint: null,
+    gateMinAmount: new anchor.BN(0),
+    gateCollection: null,
+    gambleWinBps: null,
+    gamblePayoutCap: null,
+    tradeInBps: 0,
+    claimDeadlineSeconds: null,
+    jackpotContributionBps: 0,
+    loyaltyEnabled: false,
+    loyaltyPointsPerTicket: 0,
+    pointsPerFreeTicket: 0,
+    weighting: { ticketRatio: { exponentMilli: 1500 } },
+    minProbabilityBps: null,
+    maxProbabilityBps: null,
+    maxEvBps: 9000,
+    allowNegativeEv: false,
+    noWinBps: 0,
+    consolationLamports: new anchor.BN(0),
+    prizeAllocationBps: 5000,
+    withdrawalTimelockSeconds: 0,
+    maxDailyWithdrawal: null,
+    coAuthority: null,
+    spinOperator: null,
+    autoPayout: false,
+    rerollPrice: new anchor.BN(0),
+    bundles: [],
+    itemAccounts: false,
+    allowCpiPurchases: false,
+    endsAt: null,
+    vestingThresholdLamports: null,
+    vestingDurationSeconds: 0,
+    vestingUpfrontBps: 0,
+    poolMode: { spin: {} },
+    streakMilestones: [],
+    achievementBonusLamports: new anchor.BN(0),
+    beneficiary: null,
+    minItemPrice: new anchor.BN(0),
+    serverSeedHash: null,
+    disputeWindowSeconds: 0,
+    authorityPlayDisabled: false,
+    maxTicketsPerSlot: null,
+    megaJackpot: false,
+    insurance: null,
   };
   
   // Store ticket PDAs for spin tests
@@ -71,6 +150,24 @@ describe("company_pool - Complete Test Suite", () => {
     return buyer;
   };
 
+  // Spins can't share a slot with the purchase, nor claims with the spin, so
+  // wait for the cluster to move on before sending the next step
+  const waitForNextSlot = async () => {
+    const slot = await connection.getSlot("processed");
+    while ((await connection.getSlot("processed")) <= slot) {
+      await new Promise(resolve => setTimeout(resolve, 100));
+    }
+  };
+
+  // Helper function to derive a pool's prize vault PDA
+  const derivePrizeVaultPda = (poolPda: PublicKey): PublicKey => {
+    const [prizeVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prize_vault"), poolPda.toBuffer()],
+      program.programId
+    );
+    return prizeVaultPda;
+  };
+
   // Helper function to buy a ticket
   const buyTicketForUser = async (buyer: web3.Keypair): Promise<{ ticketPda: PublicKey, ticketId: number }> => {
     const poolData = await program.account.companyPool.fetch(companyPoolPda);
@@ -78,7 +175,7 @@ describe("company_pool - Complete Test Suite", () => {
     const ticketPda = deriveTicketPda(buyer.publicKey, ticketId);
     
     const tx = await program.methods
-      .buyTicket()
+      .buyTicket(null, new anchor.BN(0), false)
       .accounts({
         companyPool: companyPoolPda,
         userTicket: ticketPda,
@@ -95,6 +192,7 @@ describe("company_pool - Complete Test Suite", () => {
 
   // Helper function to spin a ticket
   const spinTicket = async (buyer: web3.Keypair, ticketPda: PublicKey): Promise<string> => {
+    await waitForNextSlot();
     const tx = await program.methods
       .recordSpinResult()
       .accounts({
@@ -102,6 +200,7 @@ describe("company_pool - Complete Test Suite", () => {
         userTicket: ticketPda,
         spinner: buyer.publicKey,
         poolVault: poolVaultPda,
+        prizeVault: derivePrizeVaultPda(companyPoolPda),
         systemProgram: SystemProgram.programId,
       })
       .signers([buyer])
@@ -111,6 +210,97 @@ describe("company_pool - Complete Test Suite", () => {
     return tx;
   };
 
+  // Helper function to create a standalone pool owned by the provider wallet
+  const createPool = async (
+    name: string,
+    items: any[],
+    price: anchor.BN = ticketPrice,
+    config: any = defaultPoolConfig
+  ): Promise<{ poolPda: PublicKey, vaultPda: PublicKey }> => {
+    const [poolPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("company_pool"), poolNameSeed(name)],
+      program.programId
+    );
+    const [vaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_vault"), poolPda.toBuffer()],
+      program.programId
+    );
+
+    const tx = await program.methods
+      .initializeCompanyPool(price, name, companyImage, items, config)
+      .accounts({
+        companyPool: poolPda,
+        poolVault: vaultPda,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await connection.confirmTransaction(tx);
+    return { poolPda, vaultPda };
+  };
+
+  // Helper function to buy and spin a ticket in any pool, returning the ticket PDA
+  const buyAndSpinInPool = async (
+    poolPda: PublicKey,
+    vaultPda: PublicKey,
+    buyer: web3.Keypair
+  ): Promise<PublicKey> => {
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const ticketIdBuffer = Buffer.alloc(8);
+    ticketIdBuffer.writeBigUInt64LE(BigInt(poolData.totalTicketsSold.toNumber()), 0);
+    const [ticketPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_ticket"), buyer.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+      program.programId
+    );
+
+    await program.methods
+      .buyTicket(null, new anchor.BN(0), false)
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        buyer: buyer.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    await waitForNextSlot();
+    await program.methods
+      .recordSpinResult()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: buyer.publicKey,
+        poolVault: vaultPda,
+        prizeVault: derivePrizeVaultPda(poolPda),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    return ticketPda;
+  };
+
+  // Helper function to derive a user's spin history PDA for a pool
+  const deriveSpinHistoryPda = (user: PublicKey, poolPda: PublicKey): PublicKey => {
+    const [historyPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("spin_history"), user.toBuffer(), poolPda.toBuffer()],
+      program.programId
+    );
+    return historyPda;
+  };
+
+  // Helper function to derive a pool's stats PDA
+  const derivePoolStatsPda = (poolPda: PublicKey): PublicKey => {
+    const [statsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_stats"), poolPda.toBuffer()],
+      program.programId
+    );
+    return statsPda;
+  };
+
   // ================== SETUP TESTS ==================
 
   it("Airdrops SOL to wallet", async () => {
@@ -122,14 +312,62 @@ describe("company_pool - Complete Test Suite", () => {
     await new Promise(resolve => setTimeout(resolve, 1000));
   });
 
+  it("Initializes the global config", async () => {
+    const [globalConfigPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global_config")],
+      program.programId
+    );
+    const [programDataPda] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+
+    const existing = await connection.getAccountInfo(globalConfigPda);
+    if (!existing) {
+      await program.methods
+        .initializeGlobalConfig()
+        .accounts({
+          globalConfig: globalConfigPda,
+          admin: provider.wallet.publicKey,
+          program: program.programId,
+          programData: programDataPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    const config = await program.account.globalConfig.fetch(globalConfigPda);
+    assert.equal(config.admin.toString(), provider.wallet.publicKey.toString());
+    assert.equal(config.programPaused, false);
+    assert.deepEqual(config.creationMode, { open: {} });
+    assert.equal(config.poolCreationFeeLamports.toNumber(), 0);
+    console.log("✅ Global config ready");
+  });
+
+  it("Initializes the global stats", async () => {
+    const [globalStatsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global_stats")],
+      program.programId
+    );
+    if (!(await connection.getAccountInfo(globalStatsPda))) {
+      await program.methods
+        .initializeGlobalStats()
+        .accounts({ admin: provider.wallet.publicKey })
+        .rpc();
+    }
+    const stats = await program.methods.getGlobalStats().accounts({}).view();
+    assert.isAtLeast(stats.totalPoolsCreated.toNumber(), 0);
+    console.log("✅ Global stats ready");
+  });
+
   it("Derives PDA for CompanyPool", async () => {
     [companyPoolPda, bump] = await PublicKey.findProgramAddressSync(
-      [Buffer.from("company_pool"), Buffer.from(companyName)],
+      [Buffer.from("company_pool"), poolNameSeed(companyName)],
       program.programId
     );
 
     [poolVaultPda, vaultBump] = await PublicKey.findProgramAddressSync(
-      [Buffer.from("pool_vault"), Buffer.from(companyName)],
+      [Buffer.from("pool_vault"), companyPoolPda.toBuffer()],
       program.programId
     );
 
@@ -149,7 +387,8 @@ describe("company_pool - Complete Test Suite", () => {
           ticketPrice,
           companyName,
           companyImage,
-          [item1, item2]
+          [item1, item2],
+          defaultPoolConfig
         )
         .accounts({
           companyPool: companyPoolPda,
@@ -185,12 +424,12 @@ describe("company_pool - Complete Test Suite", () => {
     const minTicketPrice = new anchor.BN(1);
     
     const [minPoolPda] = await PublicKey.findProgramAddressSync(
-      [Buffer.from("company_pool"), Buffer.from(minCompanyName)],
+      [Buffer.from("company_pool"), poolNameSeed(minCompanyName)],
       program.programId
     );
     
     const [minVaultPda] = await PublicKey.findProgramAddressSync(
-      [Buffer.from("pool_vault"), Buffer.from(minCompanyName)],
+      [Buffer.from("pool_vault"), minPoolPda.toBuffer()],
       program.programId
     );
 
@@ -199,7 +438,8 @@ describe("company_pool - Complete Test Suite", () => {
         minTicketPrice,
         minCompanyName,
         companyImage,
-        [{ ...item1, price: new anchor.BN(1) }]
+        [{ ...item1, price: new anchor.BN(1) }],
+        { ...defaultPoolConfig, allowNegativeEv: true } // Item is worth the whole ticket
       )
       .accounts({
         companyPool: minPoolPda,
@@ -223,16 +463,25 @@ describe("company_pool - Complete Test Suite", () => {
       image: `https://test.com/item${i}.png`,
       price: new anchor.BN(10 + i),
       name: `Item${i}`,
-      description: `Description for item ${i}`
+      description: `Description for item ${i}`,
+      itemKind: { solPayout: {} },
+      jackpot: false,
+      manualWeight: 0,
+      tier: null,
+      sponsor: null,
+      sponsorShareBps: 0,
+      revealHash: null,
+      rewardMint: null,
+      rewardAmount: new anchor.BN(0),
     }));
     
     const [maxItemsPoolPda] = await PublicKey.findProgramAddressSync(
-      [Buffer.from("company_pool"), Buffer.from(maxItemsName)],
+      [Buffer.from("company_pool"), poolNameSeed(maxItemsName)],
       program.programId
     );
     
     const [maxItemsVaultPda] = await PublicKey.findProgramAddressSync(
-      [Buffer.from("pool_vault"), Buffer.from(maxItemsName)],
+      [Buffer.from("pool_vault"), maxItemsPoolPda.toBuffer()],
       program.programId
     );
 
@@ -241,7 +490,8 @@ describe("company_pool - Complete Test Suite", () => {
         ticketPrice,
         maxItemsName,
         companyImage,
-        maxItems
+        maxItems,
+        defaultPoolConfig
       )
       .accounts({
         companyPool: maxItemsPoolPda,
@@ -265,7 +515,8 @@ describe("company_pool - Complete Test Suite", () => {
           ticketPrice,
           "", // Empty name
           companyImage,
-          [item1]
+          [item1],
+          defaultPoolConfig
         )
         .accounts({
           companyPool: companyPoolPda, // This will fail anyway
@@ -287,17 +538,26 @@ describe("company_pool - Complete Test Suite", () => {
         image: `https://test.com/item${i}.png`,
         price: new anchor.BN(10),
         name: `Item${i}`,
-        description: `Description ${i}`
+        description: `Description ${i}`,
+        itemKind: { solPayout: {} },
+        jackpot: false,
+        manualWeight: 0,
+        tier: null,
+        sponsor: null,
+        sponsorShareBps: 0,
+        revealHash: null,
+        rewardMint: null,
+        rewardAmount: new anchor.BN(0),
       }));
 
       const failName = "FailTest";
       const [failPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("company_pool"), Buffer.from(failName)],
+        [Buffer.from("company_pool"), poolNameSeed(failName)],
         program.programId
       );
       
       const [failVaultPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("pool_vault"), Buffer.from(failName)],
+        [Buffer.from("pool_vault"), failPda.toBuffer()],
         program.programId
       );
 
@@ -306,7 +566,8 @@ describe("company_pool - Complete Test Suite", () => {
           ticketPrice,
           failName,
           companyImage,
-          tooManyItems
+          tooManyItems,
+          defaultPoolConfig
         )
         .accounts({
           companyPool: failPda,
@@ -323,6 +584,50 @@ describe("company_pool - Complete Test Suite", () => {
     }
   });
 
+  it("Fails when two items share a name", async () => {
+    try {
+      const twins = [0, 1].map((i) => ({
+        image: `https://test.com/twin${i}.png`,
+        price: new anchor.BN(10),
+        name: "iPhone",
+        description: `Twin ${i}`,
+        itemKind: { solPayout: {} },
+        jackpot: false,
+        manualWeight: 0,
+        tier: null,
+        sponsor: null,
+        sponsorShareBps: 0,
+        revealHash: null,
+        rewardMint: null,
+        rewardAmount: new anchor.BN(0),
+      }));
+
+      const failName = "DupItemTest";
+      const [failPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("company_pool"), poolNameSeed(failName)],
+        program.programId
+      );
+      const [failVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("pool_vault"), failPda.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initializeCompanyPool(ticketPrice, failName, companyImage, twins, defaultPoolConfig)
+        .accounts({
+          companyPool: failPda,
+          poolVault: failVaultPda,
+          authority: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.fail("Should have failed with duplicate item names");
+    } catch (error) {
+      assert.ok(error.toString().includes("DuplicateItemName"));
+    }
+  });
+
   it("Prevents duplicate pool creation with same company name", async () => {
     try {
       // Try to create another pool with the same name
@@ -331,7 +636,8 @@ describe("company_pool - Complete Test Suite", () => {
           ticketPrice,
           companyName, // Same name as original
           companyImage,
-          [item1]
+          [item1],
+          defaultPoolConfig
         )
         .accounts({
           companyPool: companyPoolPda,
@@ -369,7 +675,7 @@ describe("company_pool - Complete Test Suite", () => {
       const ticketPda = deriveTicketPda(provider.wallet.publicKey, poolDataBefore.totalTicketsSold.toNumber());
 
       const tx = await program.methods
-        .buyTicket()
+        .buyTicket(null, new anchor.BN(0), false)
         .accounts({
           companyPool: companyPoolPda,
           userTicket: ticketPda,
@@ -396,7 +702,8 @@ describe("company_pool - Complete Test Suite", () => {
       assert.equal(ticketData.used, false);
 
       assert.ok(poolDataAfter.totalTicketsSold.eq(poolDataBefore.totalTicketsSold.add(new anchor.BN(1))));
-      assert.ok(poolDataAfter.totalFunds.eq(poolDataBefore.totalFunds.add(ticketPrice)));
+      const prizeShare = ticketPrice.muln(poolDataBefore.prizeAllocationBps).divn(10000);
+      assert.ok(poolDataAfter.totalFunds.eq(poolDataBefore.totalFunds.add(ticketPrice).sub(prizeShare)));
 
       console.log("✅ Ticket purchased successfully!");
 
@@ -443,7 +750,7 @@ describe("company_pool - Complete Test Suite", () => {
       const ticketPda = deriveTicketPda(poorBuyer.publicKey, ticketId);
 
       await program.methods
-        .buyTicket()
+        .buyTicket(null, new anchor.BN(0), false)
         .accounts({
           companyPool: companyPoolPda,
           userTicket: ticketPda,
@@ -492,7 +799,7 @@ describe("company_pool - Complete Test Suite", () => {
           const ticketPda = deriveTicketPda(buyer.publicKey, ticketId);
           
           const tx = await program.methods
-            .buyTicket()
+            .buyTicket(null, new anchor.BN(0), false)
             .accounts({
               companyPool: companyPoolPda,
               userTicket: ticketPda,
@@ -594,6 +901,7 @@ describe("company_pool - Complete Test Suite", () => {
       });
 
       // Record spin result
+      await waitForNextSlot();
       const tx = await program.methods
         .recordSpinResult()
         .accounts({
@@ -796,6 +1104,7 @@ describe("company_pool - Complete Test Suite", () => {
       
       console.log("🚫 Attempting to spin with used ticket");
 
+      await waitForNextSlot();
       await program.methods
         .recordSpinResult()
         .accounts({
@@ -848,6 +1157,7 @@ describe("company_pool - Complete Test Suite", () => {
 
       console.log("🚫 Attempting to spin with wrong owner");
 
+      await waitForNextSlot();
       await program.methods
         .recordSpinResult()
         .accounts({
@@ -877,7 +1187,7 @@ describe("company_pool - Complete Test Suite", () => {
       console.log("📊 Testing probability analysis...");
 
       const tx = await program.methods
-        .getProbabilityAnalysis()
+        .getProbabilityAnalysis(0, 10)
         .accounts({
           companyPool: companyPoolPda,
         })
@@ -931,19 +1241,20 @@ describe("company_pool - Complete Test Suite", () => {
       assert.notEqual(ticketDataAfterSpin.wonItem, null, "Should have won an item");
       
       const wonItem = ticketDataAfterSpin.wonItem;
-      console.log(`   🎉 Won item: ${wonItem.name} (Value: ${wonItem.price} lamports)`);
+      console.log(`   🎉 Won item #${wonItem.itemId} (Value: ${wonItem.price} lamports)`);
       
       const userBalanceBefore = await connection.getBalance(rewardTester.publicKey);
-      const vaultBalanceBefore = await connection.getBalance(poolVaultPda);
+      const prizeVaultPda = derivePrizeVaultPda(companyPoolPda);
+      const vaultBalanceBefore = await connection.getBalance(prizeVaultPda);
       
       console.log("   Step 3: Claiming reward...");
+      await waitForNextSlot();
       const claimTx = await program.methods
         .claimReward()
         .accounts({
           companyPool: companyPoolPda,
           userTicket: ticketPda,
           spinner: rewardTester.publicKey,
-          poolVault: poolVaultPda,
           systemProgram: SystemProgram.programId,
         })
         .signers([rewardTester])
@@ -954,7 +1265,7 @@ describe("company_pool - Complete Test Suite", () => {
       
       const ticketDataAfterClaim = await program.account.userTicket.fetch(ticketPda);
       const userBalanceAfter = await connection.getBalance(rewardTester.publicKey);
-      const vaultBalanceAfter = await connection.getBalance(poolVaultPda);
+      const vaultBalanceAfter = await connection.getBalance(prizeVaultPda);
       
       assert.equal(ticketDataAfterClaim.rewardClaimed, true, "Reward should be marked as claimed");
       assert.equal(ticketDataAfterClaim.used, true, "Ticket should remain marked as used");
@@ -983,13 +1294,13 @@ describe("company_pool - Complete Test Suite", () => {
       const ticketData = await program.account.userTicket.fetch(ticketPda);
       assert.equal(ticketData.used, false, "Ticket should be unused");
       
+      await waitForNextSlot();
       await program.methods
         .claimReward()
         .accounts({
           companyPool: companyPoolPda,
           userTicket: ticketPda,
           spinner: testBuyer.publicKey,
-          poolVault: poolVaultPda,
           systemProgram: SystemProgram.programId,
         })
         .signers([testBuyer])
@@ -1016,13 +1327,13 @@ describe("company_pool - Complete Test Suite", () => {
       
       await spinTicket(doubleClaimer, ticketPda);
       
+      await waitForNextSlot();
       const claimTx = await program.methods
         .claimReward()
         .accounts({
           companyPool: companyPoolPda,
           userTicket: ticketPda,
           spinner: doubleClaimer.publicKey,
-          poolVault: poolVaultPda,
           systemProgram: SystemProgram.programId,
         })
         .signers([doubleClaimer])
@@ -1033,13 +1344,13 @@ describe("company_pool - Complete Test Suite", () => {
       assert.equal(ticketData.rewardClaimed, true, "Reward should be claimed");
       
       // Try to claim again
+      await waitForNextSlot();
       await program.methods
         .claimReward()
         .accounts({
           companyPool: companyPoolPda,
           userTicket: ticketPda,
           spinner: doubleClaimer.publicKey,
-          poolVault: poolVaultPda,
           systemProgram: SystemProgram.programId,
         })
         .signers([doubleClaimer])
@@ -1070,13 +1381,13 @@ describe("company_pool - Complete Test Suite", () => {
       console.log("Ticket owner:", originalOwner.publicKey.toString());
       console.log("Wrong owner:", wrongOwner.publicKey.toString());
       
+      await waitForNextSlot();
       const claimTx = program.methods
         .claimReward()
         .accounts({
           companyPool: companyPoolPda,
           userTicket: ticketPda,
           spinner: wrongOwner.publicKey,
-          poolVault: poolVaultPda,
           systemProgram: SystemProgram.programId,
         })
         .signers([wrongOwner]);
@@ -1126,13 +1437,13 @@ describe("company_pool - Complete Test Suite", () => {
       
       for (let i = 0; i < numberOfUsers; i++) {
         try {
+          await waitForNextSlot();
           const claimTx = await program.methods
             .claimReward()
             .accounts({
               companyPool: companyPoolPda,
               userTicket: ticketPdas[i],
               spinner: users[i].publicKey,
-              poolVault: poolVaultPda,
               systemProgram: SystemProgram.programId,
             })
             .signers([users[i]])
@@ -1450,13 +1761,13 @@ describe("company_pool - Complete Test Suite", () => {
       
       // Try to claim reward and measure cost
       try {
+        await waitForNextSlot();
         const claimTx = await program.methods
           .claimReward()
           .accounts({
             companyPool: companyPoolPda,
             userTicket: ticketPda,
             spinner: costTester.publicKey,
-            poolVault: poolVaultPda,
             systemProgram: SystemProgram.programId,
           })
           .signers([costTester])
@@ -1668,12 +1979,12 @@ describe("company_pool - Complete Test Suite", () => {
       const largePriceCompanyName = "LargePrice";
       
       const [largePricePda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("company_pool"), Buffer.from(largePriceCompanyName)],
+        [Buffer.from("company_pool"), poolNameSeed(largePriceCompanyName)],
         program.programId
       );
       
       const [largePriceVaultPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("pool_vault"), Buffer.from(largePriceCompanyName)],
+        [Buffer.from("pool_vault"), largePricePda.toBuffer()],
         program.programId
       );
 
@@ -1681,7 +1992,16 @@ describe("company_pool - Complete Test Suite", () => {
         image: "https://test.com/large.png",
         price: largePrice,
         name: "LargeItem",
-        description: "Very expensive item"
+        description: "Very expensive item",
+        itemKind: { solPayout: {} },
+        jackpot: false,
+        manualWeight: 0,
+        tier: null,
+        sponsor: null,
+        sponsorShareBps: 0,
+        revealHash: null,
+        rewardMint: null,
+        rewardAmount: new anchor.BN(0),
       };
 
       const tx = await program.methods
@@ -1689,7 +2009,8 @@ describe("company_pool - Complete Test Suite", () => {
           new anchor.BN(1000 * LAMPORTS_PER_SOL), // High ticket price
           largePriceCompanyName,
           companyImage,
-          [largeItem]
+          [largeItem],
+          { ...defaultPoolConfig, allowNegativeEv: true } // Item outvalues the ticket
         )
         .accounts({
           companyPool: largePricePda,
@@ -1718,12 +2039,12 @@ describe("company_pool - Complete Test Suite", () => {
       
       const smallCompanyName = "SmallValue";
       const [smallPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("company_pool"), Buffer.from(smallCompanyName)],
+        [Buffer.from("company_pool"), poolNameSeed(smallCompanyName)],
         program.programId
       );
       
       const [smallVaultPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("pool_vault"), Buffer.from(smallCompanyName)],
+        [Buffer.from("pool_vault"), smallPda.toBuffer()],
         program.programId
       );
 
@@ -1731,7 +2052,16 @@ describe("company_pool - Complete Test Suite", () => {
         image: "https://test.com/small.png",
         price: new anchor.BN(1), // 1 lamport
         name: "SmallItem",
-        description: "Minimal value item"
+        description: "Minimal value item",
+        itemKind: { solPayout: {} },
+        jackpot: false,
+        manualWeight: 0,
+        tier: null,
+        sponsor: null,
+        sponsorShareBps: 0,
+        revealHash: null,
+        rewardMint: null,
+        rewardAmount: new anchor.BN(0),
       };
 
       const tx = await program.methods
@@ -1739,7 +2069,8 @@ describe("company_pool - Complete Test Suite", () => {
           new anchor.BN(10), // Small ticket price
           smallCompanyName,
           companyImage,
-          [smallItem]
+          [smallItem],
+          defaultPoolConfig
         )
         .accounts({
           companyPool: smallPda,
@@ -1768,12 +2099,12 @@ describe("company_pool - Complete Test Suite", () => {
       
       const specialCompanyName = "Test-Corp_123"; // Allowed special chars
       const [specialPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("company_pool"), Buffer.from(specialCompanyName)],
+        [Buffer.from("company_pool"), poolNameSeed(specialCompanyName)],
         program.programId
       );
       
       const [specialVaultPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("pool_vault"), Buffer.from(specialCompanyName)],
+        [Buffer.from("pool_vault"), specialPda.toBuffer()],
         program.programId
       );
 
@@ -1781,7 +2112,16 @@ describe("company_pool - Complete Test Suite", () => {
         image: "https://test.com/special.png",
         price: new anchor.BN(100),
         name: "Item-1_Special", // Special chars in item name
-        description: "Item with special characters: @#$%"
+        description: "Item with special characters: @#$%",
+        itemKind: { solPayout: {} },
+        jackpot: false,
+        manualWeight: 0,
+        tier: null,
+        sponsor: null,
+        sponsorShareBps: 0,
+        revealHash: null,
+        rewardMint: null,
+        rewardAmount: new anchor.BN(0),
       };
 
       const tx = await program.methods
@@ -1789,7 +2129,8 @@ describe("company_pool - Complete Test Suite", () => {
           ticketPrice,
           specialCompanyName,
           companyImage,
-          [specialItem]
+          [specialItem],
+          defaultPoolConfig
         )
         .accounts({
           companyPool: specialPda,
@@ -1819,12 +2160,12 @@ describe("company_pool - Complete Test Suite", () => {
       
       const singleItemName = "SingleItem";
       const [singlePda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("company_pool"), Buffer.from(singleItemName)],
+        [Buffer.from("company_pool"), poolNameSeed(singleItemName)],
         program.programId
       );
       
       const [singleVaultPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("pool_vault"), Buffer.from(singleItemName)],
+        [Buffer.from("pool_vault"), singlePda.toBuffer()],
         program.programId
       );
 
@@ -1832,7 +2173,16 @@ describe("company_pool - Complete Test Suite", () => {
         image: "https://test.com/single.png",
         price: new anchor.BN(100),
         name: "OnlyItem",
-        description: "The only item in this pool"
+        description: "The only item in this pool",
+        itemKind: { solPayout: {} },
+        jackpot: false,
+        manualWeight: 0,
+        tier: null,
+        sponsor: null,
+        sponsorShareBps: 0,
+        revealHash: null,
+        rewardMint: null,
+        rewardAmount: new anchor.BN(0),
       };
 
       // Initialize single item pool
@@ -1841,7 +2191,8 @@ describe("company_pool - Complete Test Suite", () => {
           ticketPrice,
           singleItemName,
           companyImage,
-          [singleItem]
+          [singleItem],
+          defaultPoolConfig
         )
         .accounts({
           companyPool: singlePda,
@@ -1873,7 +2224,7 @@ describe("company_pool - Complete Test Suite", () => {
 
       // Buy ticket
       const buyTx = await program.methods
-        .buyTicket()
+        .buyTicket(null, new anchor.BN(0), false)
         .accounts({
           companyPool: singlePda,
           userTicket: singleTicketPda,
@@ -1887,6 +2238,7 @@ describe("company_pool - Complete Test Suite", () => {
       await connection.confirmTransaction(buyTx);
       
       // Spin ticket
+      await waitForNextSlot();
       const spinTx = await program.methods
         .recordSpinResult()
         .accounts({
@@ -1905,7 +2257,7 @@ describe("company_pool - Complete Test Suite", () => {
       const ticketData = await program.account.userTicket.fetch(singleTicketPda);
       assert.ok(ticketData.used, "Ticket should be used");
       assert.notEqual(ticketData.wonItem, null, "Should have won the single item");
-      assert.equal(ticketData.wonItem.name, "OnlyItem", "Should have won the correct item");
+      assert.equal(ticketData.wonItem.itemId, 0, "Should have won the correct item");
       
       console.log("✅ Single item pool test passed");
       
@@ -1935,13 +2287,13 @@ describe("company_pool - Complete Test Suite", () => {
       }
       
       // Try to claim reward
+      await waitForNextSlot();
       const claimTx = await program.methods
         .claimReward()
         .accounts({
           companyPool: companyPoolPda,
           userTicket: ticketPda,
           spinner: replayBuyer.publicKey,
-          poolVault: poolVaultPda,
           systemProgram: SystemProgram.programId,
         })
         .signers([replayBuyer])
@@ -1951,13 +2303,13 @@ describe("company_pool - Complete Test Suite", () => {
       
       // Try to claim reward again (should fail)
       try {
+        await waitForNextSlot();
         await program.methods
           .claimReward()
           .accounts({
             companyPool: companyPoolPda,
             userTicket: ticketPda,
             spinner: replayBuyer.publicKey,
-            poolVault: poolVaultPda,
             systemProgram: SystemProgram.programId,
           })
           .signers([replayBuyer])
@@ -1988,16 +2340,25 @@ describe("company_pool - Complete Test Suite", () => {
         image: `https://test.com/perf${i}.png`,
         price: new anchor.BN(10 + i),
         name: `PerfItem${i}`,
-        description: `Performance test item ${i}`
+        description: `Performance test item ${i}`,
+        itemKind: { solPayout: {} },
+        jackpot: false,
+        manualWeight: 0,
+        tier: null,
+        sponsor: null,
+        sponsorShareBps: 0,
+        revealHash: null,
+        rewardMint: null,
+        rewardAmount: new anchor.BN(0),
       }));
       
       const [perfPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("company_pool"), Buffer.from(perfCompanyName)],
+        [Buffer.from("company_pool"), poolNameSeed(perfCompanyName)],
         program.programId
       );
       
       const [perfVaultPda] = await PublicKey.findProgramAddressSync(
-        [Buffer.from("pool_vault"), Buffer.from(perfCompanyName)],
+        [Buffer.from("pool_vault"), perfPda.toBuffer()],
         program.programId
       );
 
@@ -2008,7 +2369,8 @@ describe("company_pool - Complete Test Suite", () => {
           ticketPrice,
           perfCompanyName,
           companyImage,
-          maxItems
+          maxItems,
+          defaultPoolConfig
         )
         .accounts({
           companyPool: perfPda,
@@ -2039,7 +2401,7 @@ describe("company_pool - Complete Test Suite", () => {
       // Buy ticket
       const buyStartTime = Date.now();
       const buyTx = await program.methods
-        .buyTicket()
+        .buyTicket(null, new anchor.BN(0), false)
         .accounts({
           companyPool: perfPda,
           userTicket: perfTicketPda,
@@ -2056,6 +2418,7 @@ describe("company_pool - Complete Test Suite", () => {
       
       // Spin ticket
       const spinStartTime = Date.now();
+      await waitForNextSlot();
       const spinTx = await program.methods
         .recordSpinResult()
         .accounts({
@@ -2085,6 +2448,3345 @@ describe("company_pool - Complete Test Suite", () => {
     }
   });
 
+  // ================== ITEM MANAGEMENT TESTS ==================
+
+  it("Retires an item and excludes it from spins", async () => {
+    const { poolPda, vaultPda } = await createPool("RetireTest", [
+      { ...item1, name: "Keep1" },
+      { ...item2, name: "Retired" },
+      { ...item1, price: new anchor.BN(20), name: "Keep2" },
+    ]);
+
+    await program.methods
+      .setItemAvailability(1, false)
+      .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+      .rpc();
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.items[1].available, false);
+    assert.equal(poolData.items[1].probability, 0);
+    const total = poolData.items.reduce((sum, item) => sum + item.probability, 0);
+    assert.equal(total, 10000, "Available items should still sum to 10000");
+    // The retired item adds no width to the cumulative table spins search
+    const p = poolData.items.map((item) => item.probability);
+    assert.deepEqual(poolData.cumProbability.slice(0, 3), [p[0], p[0], p[0] + p[2]]);
+
+    const spinner = await createBuyerWithSol(15);
+    for (let i = 0; i < 10; i++) {
+      const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, spinner);
+      const ticketData = await program.account.userTicket.fetch(ticketPda);
+      assert.notEqual(ticketData.wonItem.itemId, 1, "Retired item should never be won");
+    }
+
+    console.log("✅ Retired item excluded from spins");
+  });
+
+  it("Keeps tickets that won a retired item claimable", async () => {
+    const { poolPda, vaultPda } = await createPool("RetireClaim", [
+      { ...item1, name: "Solo" },
+      { ...item2, name: "Other" },
+    ]);
+
+    // Fund the vault so the reward can be paid out
+    const winner = await createBuyerWithSol(5);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, winner);
+    const wonIndex = (await program.account.userTicket.fetch(ticketPda)).wonItem.itemId;
+
+    await program.methods
+      .setItemAvailability(wonIndex, false)
+      .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+      .rpc();
+
+    await waitForNextSlot();
+    await program.methods
+      .claimReward()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: winner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([winner])
+      .rpc();
+
+    const ticketData = await program.account.userTicket.fetch(ticketPda);
+    assert.ok(ticketData.rewardClaimed, "Reward for the retired item should be claimable");
+    console.log("✅ Retired item reward still claimable");
+  });
+
+  it("Rejects availability changes from non-authority", async () => {
+    const { poolPda } = await createPool("RetireAuth", [item1, item2]);
+    const intruder = await createBuyerWithSol(1);
+
+    try {
+      await program.methods
+        .setItemAvailability(0, false)
+        .accounts({ companyPool: poolPda, authority: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Should have failed for non-authority");
+    } catch (error) {
+      assert.ok(error.toString().includes("Unauthorized"));
+      console.log("✅ Non-authority availability change rejected");
+    }
+  });
+
+  it("Updates item metadata without touching probabilities", async () => {
+    const { poolPda } = await createPool("UpdateMeta", [item1, item2]);
+    const before = await program.account.companyPool.fetch(poolPda);
+
+    await program.methods
+      .updatePoolItem(0, "https://test.com/fixed.png", "Fixed typo", null)
+      .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+      .rpc();
+
+    const after = await program.account.companyPool.fetch(poolPda);
+    assert.equal(after.items[0].image, "https://test.com/fixed.png");
+    assert.equal(after.items[0].description, "Fixed typo");
+    assert.deepEqual(
+      after.items.map(item => item.probability),
+      before.items.map(item => item.probability),
+      "Metadata edits should not change probabilities"
+    );
+    console.log("✅ Metadata update left probabilities intact");
+  });
+
+  it("Recalculates probabilities and total value on price change", async () => {
+    const { poolPda } = await createPool("UpdatePrice", [item1, item2]);
+    const before = await program.account.companyPool.fetch(poolPda);
+
+    await program.methods
+      .updatePoolItem(1, null, null, new anchor.BN(500))
+      .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+      .rpc();
+
+    const after = await program.account.companyPool.fetch(poolPda);
+    assert.ok(after.items[1].price.eq(new anchor.BN(500)));
+    assert.ok(after.totalValue.eq(new anchor.BN(510)));
+    assert.ok(after.items[1].probability < before.items[1].probability, "Pricier item should be rarer");
+    const total = after.items.reduce((sum, item) => sum + item.probability, 0);
+    assert.equal(total, 10000);
+
+    try {
+      await program.methods
+        .updatePoolItem(1, null, null, new anchor.BN(0))
+        .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+        .rpc();
+      assert.fail("Should have rejected zero price");
+    } catch (error) {
+      assert.ok(error.toString().includes("InvalidItemPrice"));
+    }
+    console.log("✅ Price update recalculated probabilities");
+  });
+
+  it("Claims the snapshotted price after the item price changes", async () => {
+    const { poolPda, vaultPda } = await createPool("UpdateSnapshot", [{ ...item1, name: "Only" }]);
+    const winner = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, winner);
+
+    await program.methods
+      .updatePoolItem(0, null, null, new anchor.BN(999))
+      .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+      .rpc();
+
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const vaultBefore = await connection.getBalance(prizeVaultPda);
+    await waitForNextSlot();
+    await program.methods
+      .claimReward()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: winner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([winner])
+      .rpc();
+    const vaultAfter = await connection.getBalance(prizeVaultPda);
+
+    assert.equal(vaultBefore - vaultAfter, item1.price.toNumber(), "Claim should pay the old snapshot price");
+    console.log("✅ Won item snapshot price honored");
+  });
+
+  // ================== VAULT SEED TESTS ==================
+
+  it("Derives the vault from the pool key and stores its bump", async () => {
+    const data = await program.account.companyPool.fetch(companyPoolPda);
+    const [expectedVault, expectedBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_vault"), companyPoolPda.toBuffer()],
+      program.programId
+    );
+
+    assert.equal(data.version, 28, "New pools should use the current account layout");
+    assert.equal(data.poolVersion, 1, "New pools should seed their vault with the pool key");
+    assert.equal(data.vaultBump, expectedBump, "Vault bump should be stored at init");
+    assert.equal(poolVaultPda.toString(), expectedVault.toString());
+    console.log("✅ Vault derived from pool key");
+  });
+
+  it("Refuses to migrate accounts already in the current layout", async () => {
+    const payer = await createBuyerWithSol(1);
+    try {
+      await program.methods
+        .migratePool()
+        .accounts({ companyPool: companyPoolPda, payer: payer.publicKey })
+        .signers([payer])
+        .rpc();
+      assert.fail("A current pool should not migrate again");
+    } catch (error) {
+      assert.include(error.toString(), "AccountAlreadyMigrated");
+    }
+
+    const { poolPda, vaultPda } = await createPool("TicketVersionTest", [item1, item2]);
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    assert.equal((await program.account.userTicket.fetch(ticketPda)).version, 10);
+  });
+
+  it("Stores the buyer's external order id on the ticket and its event", async () => {
+    const buyer = await createBuyerWithSol(3);
+    const poolData = await program.account.companyPool.fetch(companyPoolPda);
+    const ticketPda = deriveTicketPda(buyer.publicKey, poolData.totalTicketsSold.toNumber());
+    const orderId = Array.from(Buffer.alloc(32, 0x42));
+
+    const signature = await program.methods
+      .buyTicket(orderId, new anchor.BN(0), false)
+      .accounts({
+        companyPool: companyPoolPda,
+        userTicket: ticketPda,
+        buyer: buyer.publicKey,
+        poolVault: poolVaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc({ commitment: "confirmed" });
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.deepEqual(ticket.externalId, orderId);
+
+    const txDetails = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const events = [...parser.parseLogs(txDetails.meta.logMessages)];
+    const purchase = events.find(event => event.name === "ticketPurchasedEvent");
+    assert.deepEqual(purchase.data.externalId, orderId);
+    console.log("✅ External order id kept on the ticket");
+  });
+
+  it("Numbers each pool event after the pool's last one", async () => {
+    const buyer = await createBuyerWithSol(3);
+    const seqBefore = (await program.account.companyPool.fetch(companyPoolPda)).eventSeq.toNumber();
+    const poolData = await program.account.companyPool.fetch(companyPoolPda);
+    const ticketPda = deriveTicketPda(buyer.publicKey, poolData.totalTicketsSold.toNumber());
+
+    const signature = await program.methods
+      .buyTicket(null, new anchor.BN(0), false)
+      .accounts({
+        companyPool: companyPoolPda,
+        userTicket: ticketPda,
+        buyer: buyer.publicKey,
+        poolVault: poolVaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc({ commitment: "confirmed" });
+
+    const txDetails = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const events = [...parser.parseLogs(txDetails.meta.logMessages)];
+    const purchase = events.find(event => event.name === "ticketPurchasedEvent");
+    assert.equal(purchase.data.eventSeq.toNumber(), seqBefore + 1);
+    const seqAfter = (await program.account.companyPool.fetch(companyPoolPda)).eventSeq.toNumber();
+    assert.equal(seqAfter, seqBefore + 1);
+    console.log("✅ Pool events numbered in sequence");
+  });
+
+  it("Supports multibyte company names in the vault seeds", async () => {
+    // 8 four-byte characters: 32 bytes
+    const multibyteName = "🎡🎡🎡🎡🎡🎡🎡🎡";
+    assert.equal(Buffer.from(multibyteName).length, 32);
+
+    const { poolPda, vaultPda } = await createPool(multibyteName, [item1]);
+    const buyer = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, buyer);
+
+    const ticketData = await program.account.userTicket.fetch(ticketPda);
+    assert.ok(ticketData.used, "Spin should succeed against the pool-keyed vault");
+    console.log("✅ Multibyte company name pool works end to end");
+  });
+
+  it("Initializes a pool with a 33-byte ASCII company name", async () => {
+    const longName = "A".repeat(33);
+    const { poolPda } = await createPool(longName, [item1]);
+
+    const data = await program.account.companyPool.fetch(poolPda);
+    assert.equal(data.companyName, longName);
+    console.log("✅ 33-byte name accepted via hashed seed");
+  });
+
+  it("Initializes and plays a pool with a 40-byte emoji company name", async () => {
+    const emojiName = "🎰".repeat(10);
+    assert.equal(Buffer.from(emojiName).length, 40);
+
+    const { poolPda, vaultPda } = await createPool(emojiName, [item1, item2]);
+    const buyer = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, buyer);
+
+    const ticketData = await program.account.userTicket.fetch(ticketPda);
+    assert.ok(ticketData.used, "Spin should succeed for a 40-byte name");
+    console.log("✅ 40-byte emoji name works end to end");
+  });
+
+  it("Gives names sharing a 32-byte prefix distinct pools", async () => {
+    const prefix = "P".repeat(32);
+    const { poolPda: firstPda, vaultPda: firstVault } = await createPool(prefix + "-one", [item1]);
+    const { poolPda: secondPda, vaultPda: secondVault } = await createPool(prefix + "-two", [item2]);
+
+    assert.notEqual(firstPda.toString(), secondPda.toString());
+    assert.notEqual(firstVault.toString(), secondVault.toString());
+    const second = await program.account.companyPool.fetch(secondPda);
+    assert.equal(second.companyName, prefix + "-two");
+    console.log("✅ Shared-prefix names do not collide");
+  });
+
+  it("Keeps buy_ticket and claim_reward cheap with stored bumps", async () => {
+    const { poolPda, vaultPda } = await createPool("BumpCU", [{ ...item1, name: "Only" }]);
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const [, expectedPoolBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("company_pool"), poolNameSeed("BumpCU")],
+      program.programId
+    );
+    assert.equal(poolData.poolBump, expectedPoolBump, "Pool bump should be stored at init");
+
+    const player = await createBuyerWithSol(3);
+    const ticketIdBuffer = Buffer.alloc(8);
+    const [ticketPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_ticket"), player.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+      program.programId
+    );
+
+    const unitsConsumed = async (signature: string): Promise<number> => {
+      await connection.confirmTransaction(signature, "confirmed");
+      const details = await connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return details.meta.computeUnitsConsumed;
+    };
+
+    const buyUnits = await unitsConsumed(
+      await program.methods
+        .buyTicket(null, new anchor.BN(0), false)
+        .accounts({
+          companyPool: poolPda,
+          userTicket: ticketPda,
+          buyer: player.publicKey,
+          poolVault: vaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc()
+    );
+
+    await waitForNextSlot();
+    await program.methods
+      .recordSpinResult()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: player.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([player])
+      .rpc();
+
+    await waitForNextSlot();
+    const claimUnits = await unitsConsumed(
+      await program.methods
+        .claimReward()
+        .accounts({
+          companyPool: poolPda,
+          userTicket: ticketPda,
+          spinner: player.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc()
+    );
+
+    console.log(`   buy_ticket: ${buyUnits} CU, claim_reward: ${claimUnits} CU`);
+    // A find_program_address loop for the vault alone used to cost several thousand CU
+    assert.ok(buyUnits < 25000, "buy_ticket should stay under 25k CU");
+    assert.ok(claimUnits < 15000, "claim_reward should stay under 15k CU");
+    console.log("✅ Stored bumps keep instruction costs down");
+  });
+
+  it("Rejects a spoofed non-system vault in buy_ticket and claim_reward", async () => {
+    const { poolPda, vaultPda } = await createPool("SpoofVault", [{ ...item1, name: "Only" }]);
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const vaultBalance = await connection.getBalance(vaultPda);
+    assert.ok(poolData.vaultRentReserve.toNumber() > 0, "Rent reserve should be recorded");
+    assert.ok(vaultBalance >= poolData.vaultRentReserve.toNumber(), "Vault should start rent-exempt");
+
+    const player = await createBuyerWithSol(3);
+    const ticketIdBuffer = Buffer.alloc(8);
+    const [ticketPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_ticket"), player.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+      program.programId
+    );
+
+    // The pool account itself is program-owned, so it cannot pose as the vault
+    try {
+      await program.methods
+        .buyTicket(null, new anchor.BN(0), false)
+        .accounts({
+          companyPool: poolPda,
+          userTicket: ticketPda,
+          buyer: player.publicKey,
+          poolVault: poolPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc();
+      assert.fail("buy_ticket should reject a non-system vault");
+    } catch (error) {
+      assert.ok(error.toString().includes("AccountNotSystemOwned"), `Got: ${error}`);
+    }
+
+    const spunTicket = await buyAndSpinInPool(poolPda, vaultPda, player);
+    try {
+      await waitForNextSlot();
+      await program.methods
+        .claimReward()
+        .accounts({
+          companyPool: poolPda,
+          userTicket: spunTicket,
+          spinner: player.publicKey,
+          prizeVault: poolPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc();
+      assert.fail("claim_reward should reject a non-system vault");
+    } catch (error) {
+      assert.ok(error.toString().includes("AccountNotSystemOwned"), `Got: ${error}`);
+    }
+    console.log("✅ Spoofed vault rejected");
+  });
+
+  // ================== EMERGENCY PAUSE TESTS ==================
+
+  it("Halts purchases, spins, withdrawals and pool creation while paused", async () => {
+    const { poolPda, vaultPda } = await createPool("PauseTest", [{ ...item1, name: "Only" }]);
+    const player = await createBuyerWithSol(5);
+    const spunTicket = await buyAndSpinInPool(poolPda, vaultPda, player);
+
+    await program.methods.setProgramPaused(true).accounts({ admin: provider.wallet.publicKey }).rpc();
+
+    try {
+      try {
+        await buyAndSpinInPool(poolPda, vaultPda, player);
+        assert.fail("buy_ticket should fail while paused");
+      } catch (error) {
+        assert.ok(error.toString().includes("ProgramPaused"), `Got: ${error}`);
+      }
+
+      try {
+        await createPool("PausedCreate", [item1]);
+        assert.fail("initialize_company_pool should fail while paused");
+      } catch (error) {
+        assert.ok(error.toString().includes("ProgramPaused"), `Got: ${error}`);
+      }
+
+      try {
+        await program.methods
+          .withdrawFundsFromVault(new anchor.BN(1))
+          .accounts({
+            companyPool: poolPda,
+            authority: provider.wallet.publicKey,
+            poolVault: vaultPda,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+        assert.fail("withdraw_funds_from_vault should fail while paused");
+      } catch (error) {
+        assert.ok(error.toString().includes("ProgramPaused"), `Got: ${error}`);
+      }
+
+      // Claims are intentionally left open so winners keep access to their rewards
+      await waitForNextSlot();
+      await program.methods
+        .claimReward()
+        .accounts({
+          companyPool: poolPda,
+          userTicket: spunTicket,
+          spinner: player.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc();
+      const ticketData = await program.account.userTicket.fetch(spunTicket);
+      assert.ok(ticketData.rewardClaimed, "Claims should still work while paused");
+    } finally {
+      await program.methods.setProgramPaused(false).accounts({ admin: provider.wallet.publicKey }).rpc();
+    }
+    console.log("✅ Emergency pause enforced");
+  });
+
+  it("Only lets the config admin toggle the pause", async () => {
+    const intruder = await createBuyerWithSol(1);
+    try {
+      await program.methods
+        .setProgramPaused(true)
+        .accounts({ admin: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Non-admin should not pause the program");
+    } catch (error) {
+      assert.ok(error.toString().includes("Unauthorized"), `Got: ${error}`);
+    }
+    console.log("✅ Pause restricted to admin");
+  });
+
+  it("Lets the config admin freeze and badge a pool", async () => {
+    const { poolPda, vaultPda } = await createPool("FrozenPoolTest", [item1]);
+    const player = await createBuyerWithSol(5);
+    const ticketPda = await buyTicketInPool(poolPda, vaultPda, player);
+    const setFlag = (flag: any, enabled: boolean) =>
+      program.methods
+        .adminSetPoolFlag(flag, enabled)
+        .accounts({ companyPool: poolPda, admin: provider.wallet.publicKey })
+        .rpc();
+
+    await setFlag({ verified: {} }, true);
+    await setFlag({ frozen: {} }, true);
+    const pool = await program.account.companyPool.fetch(poolPda);
+    assert.equal(pool.poolFlags, 0b101, "Verified and Frozen bits should be set");
+
+    try {
+      await buyTicketInPool(poolPda, vaultPda, player);
+      assert.fail("Frozen pools should not sell tickets");
+    } catch (error) {
+      assert.include(error.toString(), "PoolFrozen");
+    }
+    try {
+      await program.methods
+        .withdrawFundsFromVault(new anchor.BN(1))
+        .accounts({
+          companyPool: poolPda,
+          authority: provider.wallet.publicKey,
+          poolVault: vaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Frozen pools should not pay out to the operator");
+    } catch (error) {
+      assert.include(error.toString(), "PoolFrozen");
+    }
+
+    // The existing ticket still plays out
+    await waitForNextSlot();
+    await program.methods
+      .recordSpinResult()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: player.publicKey,
+        poolVault: vaultPda,
+        prizeVault: derivePrizeVaultPda(poolPda),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([player])
+      .rpc();
+    await claimTicket(poolPda, ticketPda, player);
+    assert.isTrue((await program.account.userTicket.fetch(ticketPda)).rewardClaimed);
+
+    const intruder = await createBuyerWithSol(1);
+    try {
+      await program.methods
+        .adminSetPoolFlag({ frozen: {} }, false)
+        .accounts({ companyPool: poolPda, admin: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Only the config admin should set pool flags");
+    } catch (error) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    await setFlag({ frozen: {} }, false);
+    await buyTicketInPool(poolPda, vaultPda, player);
+    console.log("✅ Frozen pool kept paying ticket holders");
+  });
+
+  it("Limits pool creation to allowlisted creators when the admin asks", async () => {
+    const company = await createBuyerWithSol(5);
+    const [creatorEntry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_creator"), company.publicKey.toBuffer()],
+      program.programId
+    );
+    const createAs = (name: string, entry: PublicKey | null) => {
+      const [poolPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("company_pool"), poolNameSeed(name)],
+        program.programId
+      );
+      return program.methods
+        .initializeCompanyPool(ticketPrice, name, companyImage, [item1], defaultPoolConfig)
+        .accounts({ companyPool: poolPda, authority: company.publicKey, creatorEntry: entry })
+        .signers([company])
+        .rpc();
+    };
+    const setMode = (mode: any) =>
+      program.methods.setPoolCreationMode(mode).accounts({ admin: provider.wallet.publicKey }).rpc();
+
+    await setMode({ allowlisted: {} });
+    try {
+      try {
+        await createAs("CuratedPoolTest", null);
+        assert.fail("Unlisted creators should be rejected");
+      } catch (error) {
+        assert.include(error.toString(), "CreatorNotAllowlisted");
+      }
+
+      await program.methods
+        .addPoolCreator(company.publicKey)
+        .accounts({ admin: provider.wallet.publicKey })
+        .rpc();
+      await createAs("CuratedPoolTest", creatorEntry);
+
+      await program.methods
+        .removePoolCreator(company.publicKey)
+        .accounts({ admin: provider.wallet.publicKey })
+        .rpc();
+      try {
+        await createAs("CuratedPoolTest2", null);
+        assert.fail("Removed creators should be rejected");
+      } catch (error) {
+        assert.include(error.toString(), "CreatorNotAllowlisted");
+      }
+    } finally {
+      await setMode({ open: {} });
+    }
+
+    // The pool created while listed keeps selling
+    const [poolPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("company_pool"), poolNameSeed("CuratedPoolTest")],
+      program.programId
+    );
+    const [vaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_vault"), poolPda.toBuffer()],
+      program.programId
+    );
+    await buyTicketInPool(poolPda, vaultPda, await createBuyerWithSol(3));
+    console.log("✅ Pool creation followed the allowlist");
+  });
+
+  it("Charges the configured pool creation fee to the treasury", async () => {
+    const fee = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+    const treasury = web3.Keypair.generate().publicKey;
+    const company = await createBuyerWithSol(5);
+    const createAs = (name: string, treasuryAccount: PublicKey | null) => {
+      const [poolPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("company_pool"), poolNameSeed(name)],
+        program.programId
+      );
+      return program.methods
+        .initializeCompanyPool(ticketPrice, name, companyImage, [item1], defaultPoolConfig)
+        .accounts({ companyPool: poolPda, authority: company.publicKey, treasury: treasuryAccount })
+        .signers([company])
+        .rpc();
+    };
+    const setFee = (lamports: anchor.BN, to: PublicKey) =>
+      program.methods
+        .updateCreationFee(lamports, to)
+        .accounts({ admin: provider.wallet.publicKey })
+        .rpc();
+
+    await setFee(fee, treasury);
+    try {
+      try {
+        await createAs("FeePoolTest", null);
+        assert.fail("The fee needs the treasury account");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidTreasury");
+      }
+      await createAs("FeePoolTest", treasury);
+      assert.equal(await provider.connection.getBalance(treasury), fee.toNumber());
+    } finally {
+      await setFee(new anchor.BN(0), provider.wallet.publicKey);
+    }
+
+    // With the fee back at zero no treasury is passed and nothing is charged
+    await createAs("FeePoolTest2", null);
+    assert.equal(await provider.connection.getBalance(treasury), fee.toNumber());
+    console.log("✅ Pool creation fee went to the treasury");
+  });
+
+  it("Blocks a wallet from buying without touching its tickets", async () => {
+    const { poolPda, vaultPda } = await createPool("BlockedUserTest", [item1]);
+    const player = await createBuyerWithSol(5);
+    const heldTicket = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const [blockedPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("blocked"), player.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .blockUser(player.publicKey)
+      .accounts({ admin: provider.wallet.publicKey })
+      .rpc();
+    try {
+      try {
+        await buyTicketInPool(poolPda, vaultPda, player);
+        assert.fail("Blocked wallets should not buy tickets");
+      } catch (error) {
+        assert.include(error.toString(), "UserBlocked");
+      }
+      await claimTicket(poolPda, heldTicket, player);
+      assert.isTrue((await program.account.userTicket.fetch(heldTicket)).rewardClaimed);
+    } finally {
+      await program.methods
+        .unblockUser(player.publicKey)
+        .accounts({ admin: provider.wallet.publicKey })
+        .rpc();
+    }
+
+    assert.isNull(await connection.getAccountInfo(blockedPda));
+    await buyTicketInPool(poolPda, vaultPda, player);
+    console.log("✅ Blocked wallet kept its ticket and could buy again once unblocked");
+  });
+
+  it("Counts pools, tickets and rewards in the global stats", async () => {
+    const [globalStats] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global_stats")],
+      program.programId
+    );
+    const readStats = () => program.methods.getGlobalStats().accounts({}).view();
+    const before = await readStats();
+
+    const name = "GlobalStatsTest";
+    const [poolPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("company_pool"), poolNameSeed(name)],
+      program.programId
+    );
+    const [vaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_vault"), poolPda.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initializeCompanyPool(ticketPrice, name, companyImage, [item1], defaultPoolConfig)
+      .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey, globalStats })
+      .rpc();
+
+    const player = await createBuyerWithSol(3);
+    const ticketIdBuffer = Buffer.alloc(8);
+    const [ticketPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_ticket"), player.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+      program.programId
+    );
+    await program.methods
+      .buyTicket(null, new anchor.BN(0), false)
+      .accounts({ companyPool: poolPda, userTicket: ticketPda, buyer: player.publicKey, globalStats })
+      .signers([player])
+      .rpc();
+    await waitForNextSlot();
+    await program.methods
+      .recordSpinResult()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: player.publicKey,
+        poolVault: vaultPda,
+        prizeVault: derivePrizeVaultPda(poolPda),
+      })
+      .signers([player])
+      .rpc();
+    await waitForNextSlot();
+    await program.methods
+      .claimReward()
+      .accounts({ companyPool: poolPda, userTicket: ticketPda, spinner: player.publicKey, globalStats })
+      .signers([player])
+      .rpc();
+
+    const after = await readStats();
+    const won = (await program.account.userTicket.fetch(ticketPda)).wonItem.price.toNumber();
+    assert.equal(after.totalPoolsCreated.toNumber() - before.totalPoolsCreated.toNumber(), 1);
+    assert.equal(after.totalTicketsSold.toNumber() - before.totalTicketsSold.toNumber(), 1);
+    assert.equal(after.totalRewardsPaid.toNumber() - before.totalRewardsPaid.toNumber(), won);
+    console.log("✅ Global stats followed the pool's activity");
+  });
+
+  // ================== SPIN HISTORY TESTS ==================
+
+  it("Records spin history in order and wraps after capacity", async () => {
+    const { poolPda, vaultPda } = await createPool("HistoryWrap", [item1, item2]);
+    const player = await createBuyerWithSol(30);
+    const capacity = 20;
+    const totalSpins = capacity + 2;
+
+    const ticketIds: number[] = [];
+    for (let i = 0; i < totalSpins; i++) {
+      const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+      ticketIds.push((await program.account.userTicket.fetch(ticketPda)).ticketId.toNumber());
+    }
+
+    const history = await program.methods
+      .getSpinHistory()
+      .accounts({ spinHistory: deriveSpinHistoryPda(player.publicKey, poolPda) })
+      .view();
+
+    assert.equal(history.length, capacity, "History should be capped at capacity");
+    assert.deepEqual(
+      history.map(entry => entry.ticketId.toNumber()),
+      ticketIds.slice(totalSpins - capacity),
+      "Entries should be the latest spins, oldest first"
+    );
+    console.log("✅ Spin history wraps and keeps order");
+  });
+
+  it("Keeps spin histories for different pools separate", async () => {
+    const first = await createPool("HistoryPoolA", [item1]);
+    const second = await createPool("HistoryPoolB", [item2]);
+    const player = await createBuyerWithSol(5);
+
+    await buyAndSpinInPool(first.poolPda, first.vaultPda, player);
+    await buyAndSpinInPool(second.poolPda, second.vaultPda, player);
+    await buyAndSpinInPool(second.poolPda, second.vaultPda, player);
+
+    const firstHistory = await program.methods
+      .getSpinHistory()
+      .accounts({ spinHistory: deriveSpinHistoryPda(player.publicKey, first.poolPda) })
+      .view();
+    const secondHistory = await program.methods
+      .getSpinHistory()
+      .accounts({ spinHistory: deriveSpinHistoryPda(player.publicKey, second.poolPda) })
+      .view();
+
+    assert.equal(firstHistory.length, 1);
+    assert.equal(secondHistory.length, 2);
+    assert.ok(firstHistory[0].value.eq(item1.price));
+    assert.ok(secondHistory.every(entry => entry.value.eq(item2.price)));
+    console.log("✅ Spin histories do not mix across pools");
+  });
+
+  // ================== POOL STATISTICS TESTS ==================
+
+  it("Aggregates pool statistics over 50 scripted spins", async () => {
+    const items = [
+      { ...item1, name: "Common" },
+      { ...item2, name: "Rare" },
+      { ...item1, price: new anchor.BN(200), name: "Epic" },
+    ];
+    const { poolPda, vaultPda } = await createPool("StatsTest", items);
+    const players = await Promise.all([createBuyerWithSol(30), createBuyerWithSol(30)]);
+
+    const wins = [0, 0, 0];
+    let valueWon = 0;
+    let biggest = 0;
+    const claimedTickets: PublicKey[] = [];
+    let rewardsPaid = 0;
+
+    for (let i = 0; i < 50; i++) {
+      const player = players[i % players.length];
+      const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+      const wonItem = (await program.account.userTicket.fetch(ticketPda)).wonItem;
+      wins[wonItem.itemId]++;
+      valueWon += wonItem.price.toNumber();
+      biggest = Math.max(biggest, wonItem.price.toNumber());
+
+      // Claim every fifth win so the paid-out counter is exercised too
+      if (i % 5 === 0) {
+        await waitForNextSlot();
+        await program.methods
+          .claimReward()
+          .accounts({
+            companyPool: poolPda,
+            userTicket: ticketPda,
+            spinner: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([player])
+          .rpc();
+        claimedTickets.push(ticketPda);
+        rewardsPaid += wonItem.price.toNumber();
+      }
+    }
+
+    const stats = await program.methods
+      .getPoolStats()
+      .accounts({ companyPool: poolPda, poolStats: derivePoolStatsPda(poolPda) })
+      .view();
+
+    assert.equal(stats.totalSpins.toNumber(), 50);
+    assert.deepEqual(stats.winsPerItem.slice(0, 3).map(count => count.toNumber()), wins);
+    assert.equal(
+      stats.winsPerItem.reduce((sum, count) => sum + count.toNumber(), 0),
+      50,
+      "Per-item wins should add up to total spins"
+    );
+    // Auto-assigned tiers follow the value order here: Common, Rare, Epic
+    assert.deepEqual(stats.winsPerTier.map(count => count.toNumber()), [...wins, 0]);
+    assert.equal(stats.totalValueWon.toNumber(), valueWon);
+    assert.equal(stats.totalRewardsPaid.toNumber(), rewardsPaid);
+    assert.equal(stats.biggestWinValue.toNumber(), biggest);
+    assert.equal(claimedTickets.length, 10);
+    console.log("✅ Pool statistics add up");
+  });
+
+  it("Reports realized win frequencies against configured probabilities", async () => {
+    const { poolPda, vaultPda } = await createPool("DistReport", [
+      { ...item1, name: "Cheap" },
+      { ...item2, name: "Pricey" },
+    ]);
+    const player = await createBuyerWithSol(250);
+    const spins = 200;
+    for (let i = 0; i < spins; i++) {
+      await buyAndSpinInPool(poolPda, vaultPda, player);
+    }
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const report = await program.methods
+      .getDistributionReport()
+      .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+      .view();
+
+    assert.equal(report.length, 2);
+    report.forEach((entry, i) => {
+      assert.equal(entry.timesWon, poolData.items[i].timesWon);
+      assert.equal(entry.configuredBps, poolData.items[i].probability);
+      assert.equal(entry.totalSpins.toNumber(), spins);
+      assert.equal(entry.realizedBps, Math.floor(entry.timesWon * 10000 / spins));
+    });
+    console.log("✅ Distribution report math verified");
+  });
+
+  // ================== VOUCHER TESTS ==================
+
+  const deriveVoucherPda = (poolPda: PublicKey, recipient: PublicKey): PublicKey => {
+    const [voucherPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("voucher"), poolPda.toBuffer(), recipient.toBuffer()],
+      program.programId
+    );
+    return voucherPda;
+  };
+
+  const redeemVoucherInPool = async (poolPda: PublicKey, redeemer: web3.Keypair): Promise<PublicKey> => {
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const ticketIdBuffer = Buffer.alloc(8);
+    ticketIdBuffer.writeBigUInt64LE(BigInt(poolData.totalTicketsSold.toNumber()), 0);
+    const [ticketPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_ticket"), redeemer.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+      program.programId
+    );
+
+    await program.methods
+      .redeemVoucher()
+      .accounts({
+        companyPool: poolPda,
+        voucher: deriveVoucherPda(poolPda, redeemer.publicKey),
+        userTicket: ticketPda,
+        redeemer: redeemer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([redeemer])
+      .rpc();
+
+    return ticketPda;
+  };
+
+  it("Redeems issued vouchers for free tickets until they run out", async () => {
+    const { poolPda } = await createPool("VoucherTest", [item1, item2]);
+    const recipient = await createBuyerWithSol(2);
+    const voucherPda = deriveVoucherPda(poolPda, recipient.publicKey);
+
+    await program.methods
+      .issueVoucher(recipient.publicKey, 3)
+      .accounts({
+        companyPool: poolPda,
+        voucher: voucherPda,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const fundsBefore = (await program.account.companyPool.fetch(poolPda)).totalFunds.toNumber();
+
+    for (let i = 0; i < 3; i++) {
+      const ticketPda = await redeemVoucherInPool(poolPda, recipient);
+      const ticket = await program.account.userTicket.fetch(ticketPda);
+      assert.equal(ticket.owner.toString(), recipient.publicKey.toString());
+      assert.equal(ticket.ticketId.toNumber(), i);
+      assert.isFalse(ticket.used);
+    }
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalTicketsSold.toNumber(), 3);
+    assert.equal(poolData.totalFunds.toNumber(), fundsBefore, "Vouchers should not add pool funds");
+    assert.equal((await program.account.voucher.fetch(voucherPda)).spinsRemaining, 0);
+
+    try {
+      await redeemVoucherInPool(poolPda, recipient);
+      assert.fail("Fourth redemption should fail");
+    } catch (error) {
+      assert.include(error.toString(), "NoVoucherSpinsRemaining");
+    }
+    console.log("✅ Voucher spins redeemed and exhausted");
+  });
+
+  it("Blocks redemption once the authority revokes a voucher", async () => {
+    const { poolPda } = await createPool("VoucherRevokeTest", [item1, item2]);
+    const recipient = await createBuyerWithSol(2);
+    const voucherPda = deriveVoucherPda(poolPda, recipient.publicKey);
+
+    await program.methods
+      .issueVoucher(recipient.publicKey, 3)
+      .accounts({
+        companyPool: poolPda,
+        voucher: voucherPda,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await redeemVoucherInPool(poolPda, recipient);
+
+    await program.methods
+      .revokeVoucher()
+      .accounts({
+        companyPool: poolPda,
+        voucher: voucherPda,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    assert.isNull(await connection.getAccountInfo(voucherPda), "Voucher account should be closed");
+
+    try {
+      await redeemVoucherInPool(poolPda, recipient);
+      assert.fail("Redemption after revoke should fail");
+    } catch (error) {
+      assert.include(error.toString(), "AccountNotInitialized");
+    }
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalTicketsSold.toNumber(), 1);
+    console.log("✅ Revoked voucher can no longer be redeemed");
+  });
+
+  // ================== PURCHASE LIMIT TESTS ==================
+
+  const buyTicketInPool = async (
+    poolPda: PublicKey,
+    vaultPda: PublicKey,
+    buyer: web3.Keypair,
+    whitelistEntry: PublicKey | null = null
+  ): Promise<PublicKey> => {
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const ticketIdBuffer = Buffer.alloc(8);
+    ticketIdBuffer.writeBigUInt64LE(BigInt(poolData.totalTicketsSold.toNumber()), 0);
+    const [ticketPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_ticket"), buyer.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+      program.programId
+    );
+
+    await program.methods
+      .buyTicket(null, new anchor.BN(0), false)
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        buyer: buyer.publicKey,
+        whitelistEntry,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    return ticketPda;
+  };
+
+  const deriveUserPoolStatePda = (poolPda: PublicKey, user: PublicKey): PublicKey => {
+    const [statePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_pool_state"), poolPda.toBuffer(), user.toBuffer()],
+      program.programId
+    );
+    return statePda;
+  };
+
+  it("Rejects purchases past the per-user ticket cap", async () => {
+    const { poolPda, vaultPda } = await createPool("LimitTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      maxTicketsPerUser: 2,
+    });
+    const buyer = await createBuyerWithSol(5);
+
+    await buyTicketInPool(poolPda, vaultPda, buyer);
+    await buyTicketInPool(poolPda, vaultPda, buyer);
+
+    try {
+      await buyTicketInPool(poolPda, vaultPda, buyer);
+      assert.fail("Third purchase should hit the cap");
+    } catch (error) {
+      assert.include(error.toString(), "PurchaseLimitReached");
+    }
+
+    const state = await program.account.userPoolState.fetch(
+      deriveUserPoolStatePda(poolPda, buyer.publicKey)
+    );
+    assert.equal(state.ticketsBought, 2);
+    console.log("✅ Per-user cap enforced");
+  });
+
+  it("Allows another purchase once the cooldown has passed", async () => {
+    const { poolPda, vaultPda } = await createPool("CooldownTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      purchaseCooldownSeconds: 2,
+    });
+    const buyer = await createBuyerWithSol(5);
+
+    await buyTicketInPool(poolPda, vaultPda, buyer);
+
+    try {
+      await buyTicketInPool(poolPda, vaultPda, buyer);
+      assert.fail("Immediate second purchase should be on cooldown");
+    } catch (error) {
+      assert.include(error.toString(), "PurchaseCooldownActive");
+    }
+
+    // The local validator clock follows wall time, so waiting warps past the cooldown
+    await new Promise(resolve => setTimeout(resolve, 3000));
+    await buyTicketInPool(poolPda, vaultPda, buyer);
+
+    const state = await program.account.userPoolState.fetch(
+      deriveUserPoolStatePda(poolPda, buyer.publicKey)
+    );
+    assert.equal(state.ticketsBought, 2);
+    console.log("✅ Cooldown enforced and released");
+  });
+
+  it("Counts gifted tickets against the recipient's limit", async () => {
+    const { poolPda, vaultPda } = await createPool("GiftLimitTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      maxTicketsPerUser: 1,
+    });
+    const payer = await createBuyerWithSol(5);
+    const recipient = web3.Keypair.generate();
+
+    // Payer uses up their own allowance first
+    await buyTicketInPool(poolPda, vaultPda, payer);
+
+    const giftTo = async (to: PublicKey): Promise<PublicKey> => {
+      const poolData = await program.account.companyPool.fetch(poolPda);
+      const ticketIdBuffer = Buffer.alloc(8);
+      ticketIdBuffer.writeBigUInt64LE(BigInt(poolData.totalTicketsSold.toNumber()), 0);
+      const [ticketPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("user_ticket"), to.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+        program.programId
+      );
+
+      await program.methods
+        .giftTicket(to)
+        .accounts({
+          companyPool: poolPda,
+          userTicket: ticketPda,
+          recipientPoolState: deriveUserPoolStatePda(poolPda, to),
+          payer: payer.publicKey,
+          poolVault: vaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([payer])
+        .rpc();
+
+      return ticketPda;
+    };
+
+    const giftedTicket = await giftTo(recipient.publicKey);
+    const ticket = await program.account.userTicket.fetch(giftedTicket);
+    assert.equal(ticket.owner.toString(), recipient.publicKey.toString());
+
+    try {
+      await giftTo(recipient.publicKey);
+      assert.fail("Second gift should hit the recipient's cap");
+    } catch (error) {
+      assert.include(error.toString(), "PurchaseLimitReached");
+    }
+
+    const payerState = await program.account.userPoolState.fetch(
+      deriveUserPoolStatePda(poolPda, payer.publicKey)
+    );
+    const recipientState = await program.account.userPoolState.fetch(
+      deriveUserPoolStatePda(poolPda, recipient.publicKey)
+    );
+    assert.equal(payerState.ticketsBought, 1);
+    assert.equal(recipientState.ticketsBought, 1);
+    console.log("✅ Gifts count against the recipient");
+  });
+
+  // ================== WHITELIST TESTS ==================
+
+  const deriveWhitelistPda = (poolPda: PublicKey, user: PublicKey): PublicKey => {
+    const [entryPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("whitelist"), poolPda.toBuffer(), user.toBuffer()],
+      program.programId
+    );
+    return entryPda;
+  };
+
+  it("Only sells tickets to whitelisted buyers", async () => {
+    const { poolPda, vaultPda } = await createPool("WhitelistTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      whitelistEnabled: true,
+    });
+    const outsider = await createBuyerWithSol(3);
+    const customer = await createBuyerWithSol(3);
+
+    try {
+      await buyTicketInPool(poolPda, vaultPda, outsider);
+      assert.fail("Buyer without a whitelist entry should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "NotWhitelisted");
+    }
+
+    const entryPda = deriveWhitelistPda(poolPda, customer.publicKey);
+    await program.methods
+      .addToWhitelist(customer.publicKey)
+      .accounts({
+        companyPool: poolPda,
+        whitelistEntry: entryPda,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // Someone else's entry doesn't unlock the pool
+    try {
+      await buyTicketInPool(poolPda, vaultPda, outsider, entryPda);
+      assert.fail("Borrowed whitelist entry should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "NotWhitelisted");
+    }
+
+    const ticketPda = await buyTicketInPool(poolPda, vaultPda, customer, entryPda);
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.equal(ticket.owner.toString(), customer.publicKey.toString());
+    console.log("✅ Whitelist gates purchases");
+  });
+
+  it("Removing a whitelist entry blocks new purchases but keeps existing tickets", async () => {
+    const { poolPda, vaultPda } = await createPool("WhitelistRemoveTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      whitelistEnabled: true,
+    });
+    const customer = await createBuyerWithSol(3);
+    const entryPda = deriveWhitelistPda(poolPda, customer.publicKey);
+
+    await program.methods
+      .addToWhitelist(customer.publicKey)
+      .accounts({
+        companyPool: poolPda,
+        whitelistEntry: entryPda,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const ticketPda = await buyTicketInPool(poolPda, vaultPda, customer, entryPda);
+
+    const authorityBalanceBefore = await connection.getBalance(provider.wallet.publicKey);
+    await program.methods
+      .removeFromWhitelist(customer.publicKey)
+      .accounts({
+        companyPool: poolPda,
+        whitelistEntry: entryPda,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    assert.isNull(await connection.getAccountInfo(entryPda), "Entry should be closed");
+    assert.isAbove(
+      await connection.getBalance(provider.wallet.publicKey),
+      authorityBalanceBefore,
+      "Entry rent should be refunded to the authority"
+    );
+
+    try {
+      await buyTicketInPool(poolPda, vaultPda, customer);
+      assert.fail("Purchase after removal should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "NotWhitelisted");
+    }
+
+    // The ticket bought while whitelisted can still be spun
+    await waitForNextSlot();
+    await program.methods
+      .recordSpinResult()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: customer.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([customer])
+      .rpc();
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isTrue(ticket.used);
+    assert.isNotNull(ticket.wonItem);
+    console.log("✅ Whitelist removal only affects future purchases");
+  });
+
+  // ================== TOKEN GATE TESTS ==================
+
+  it("Requires a sufficient gate token balance to buy", async () => {
+    const payer = (provider.wallet as anchor.Wallet).payer;
+    const gateMint = await createMint(connection, payer, provider.wallet.publicKey, null, 0);
+    const { poolPda, vaultPda } = await createPool("TokenGateTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      gateMint,
+      gateMinAmount: new anchor.BN(10),
+    });
+
+    const holder = await createBuyerWithSol(3);
+    const holderTokenAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      gateMint,
+      holder.publicKey
+    );
+    await mintTo(connection, payer, gateMint, holderTokenAccount.address, payer, 5);
+
+    const buyWithGate = async (): Promise<string> => {
+      const poolData = await program.account.companyPool.fetch(poolPda);
+      const ticketIdBuffer = Buffer.alloc(8);
+      ticketIdBuffer.writeBigUInt64LE(BigInt(poolData.totalTicketsSold.toNumber()), 0);
+      const [ticketPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("user_ticket"), holder.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+        program.programId
+      );
+
+      return program.methods
+        .buyTicket(null, new anchor.BN(0), false)
+        .accounts({
+          companyPool: poolPda,
+          userTicket: ticketPda,
+          buyer: holder.publicKey,
+          whitelistEntry: null,
+          gateTokenAccount: holderTokenAccount.address,
+          gateMetadata: null,
+          poolVault: vaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([holder])
+        .rpc({ commitment: "confirmed" });
+    };
+
+    try {
+      await buyWithGate();
+      assert.fail("Balance below the gate minimum should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "TokenGateNotMet");
+    }
+
+    await mintTo(connection, payer, gateMint, holderTokenAccount.address, payer, 5);
+    const signature = await buyWithGate();
+
+    const txDetails = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const events = [...parser.parseLogs(txDetails.meta.logMessages)];
+    const purchase = events.find(event => event.name === "ticketPurchasedEvent");
+    assert.isTrue(purchase.data.gatePassed, "Purchase event should report the passed gate");
+    console.log("✅ Token gate enforced on balance");
+  });
+
+  // ================== GAMBLE TESTS ==================
+
+  const gambleTicket = async (poolPda: PublicKey, vaultPda: PublicKey, ticketPda: PublicKey, spinner: web3.Keypair) => {
+    await waitForNextSlot();
+    await program.methods
+      .gambleReward()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: spinner.publicKey,
+        poolVault: vaultPda,
+      })
+      .signers([spinner])
+      .rpc();
+  };
+
+  const claimTicket = async (poolPda: PublicKey, ticketPda: PublicKey, spinner: web3.Keypair) => {
+    await waitForNextSlot();
+    await program.methods
+      .claimReward()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: spinner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([spinner])
+      .rpc();
+  };
+
+  it("Doubles a gambled reward on a win, capped at the largest item, and only once", async () => {
+    const { poolPda, vaultPda } = await createPool("GambleWinTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      gambleWinBps: 10000, // Always win
+    });
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const wonBefore = (await program.account.userTicket.fetch(ticketPda)).wonItem.price.toNumber();
+
+    await gambleTicket(poolPda, vaultPda, ticketPda, player);
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    const expected = Math.min(wonBefore * 2, item2.price.toNumber());
+    assert.isTrue(ticket.gambled);
+    assert.equal(ticket.wonItem.price.toNumber(), expected);
+
+    try {
+      await gambleTicket(poolPda, vaultPda, ticketPda, player);
+      assert.fail("Second gamble should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "AlreadyGambled");
+    }
+
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const vaultBefore = await connection.getBalance(prizeVaultPda);
+    await claimTicket(poolPda, ticketPda, player);
+    assert.equal(vaultBefore - (await connection.getBalance(prizeVaultPda)), expected);
+    console.log("✅ Gamble win doubles the reward");
+  });
+
+  it("Clears the reward when a gamble is lost", async () => {
+    const { poolPda, vaultPda } = await createPool("GambleLossTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      gambleWinBps: 0, // Always lose
+    });
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+
+    await gambleTicket(poolPda, vaultPda, ticketPda, player);
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isTrue(ticket.gambled);
+    assert.isNull(ticket.wonItem);
+
+    try {
+      await claimTicket(poolPda, ticketPda, player);
+      assert.fail("Claim after a lost gamble should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "NoRewardToClaim");
+    }
+    console.log("✅ Gamble loss leaves nothing to claim");
+  });
+
+  // ================== TRADE-IN TESTS ==================
+
+  const tradeInTicket = async (poolPda: PublicKey, ticketPda: PublicKey, spinner: web3.Keypair) => {
+    await waitForNextSlot();
+    await program.methods
+      .tradeInReward()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: spinner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([spinner])
+      .rpc();
+  };
+
+  it("Pays a rounded-down trade-in and blocks a later claim", async () => {
+    // Odd prices so 80% doesn't divide evenly
+    const items = [
+      { ...item1, price: new anchor.BN(11) },
+      { ...item2, price: new anchor.BN(57) },
+    ];
+    const { poolPda, vaultPda } = await createPool("TradeInTest", items, ticketPrice, {
+      ...defaultPoolConfig,
+      tradeInBps: 8000,
+    });
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const itemValue = (await program.account.userTicket.fetch(ticketPda)).wonItem.price.toNumber();
+    const expectedPayout = Math.floor((itemValue * 8000) / 10000);
+
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const vaultBefore = await connection.getBalance(prizeVaultPda);
+    await tradeInTicket(poolPda, ticketPda, player);
+    assert.equal(vaultBefore - (await connection.getBalance(prizeVaultPda)), expectedPayout);
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isTrue(ticket.tradedIn);
+    assert.isTrue(ticket.rewardClaimed);
+
+    const stats = await program.account.poolStats.fetch(derivePoolStatsPda(poolPda));
+    assert.equal(stats.totalTradeInMargin.toNumber(), itemValue - expectedPayout);
+
+    try {
+      await claimTicket(poolPda, ticketPda, player);
+      assert.fail("Claim after trade-in should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "RewardAlreadyClaimed");
+    }
+    console.log("✅ Trade-in paid at a discount");
+  });
+
+  it("Rejects trade-ins when the pool hasn't enabled them", async () => {
+    const { poolPda, vaultPda } = await createPool("TradeInDisabledTest", [item1, item2]);
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+
+    try {
+      await tradeInTicket(poolPda, ticketPda, player);
+      assert.fail("Trade-in should be disabled by default");
+    } catch (error) {
+      assert.include(error.toString(), "TradeInDisabled");
+    }
+    console.log("✅ Trade-in disabled by default");
+  });
+
+  // ================== CLAIM DEADLINE TESTS ==================
+
+  const reclaimExpired = async (poolPda: PublicKey, ticketPda: PublicKey, owner: PublicKey, closeTicket: boolean) => {
+    await program.methods
+      .reclaimExpiredReward(closeTicket)
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        ticketOwner: owner,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+  };
+
+  it("Claims inside the deadline and rejects claims after it", async () => {
+    const { poolPda, vaultPda } = await createPool("DeadlineTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      claimDeadlineSeconds: 3,
+    });
+    const player = await createBuyerWithSol(3);
+
+    const onTimeTicket = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const liabilityAfterSpin = (await program.account.companyPool.fetch(poolPda)).outstandingLiabilities.toNumber();
+    await claimTicket(poolPda, onTimeTicket, player);
+    assert.isTrue((await program.account.userTicket.fetch(onTimeTicket)).rewardClaimed);
+
+    const poolAfterClaim = await program.account.companyPool.fetch(poolPda);
+    assert.isAbove(liabilityAfterSpin, 0);
+    assert.equal(poolAfterClaim.outstandingLiabilities.toNumber(), 0, "Claim should release the liability");
+
+    const lateTicket = await buyAndSpinInPool(poolPda, vaultPda, player);
+    await new Promise(resolve => setTimeout(resolve, 5000));
+
+    try {
+      await claimTicket(poolPda, lateTicket, player);
+      assert.fail("Claim after the deadline should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "RewardExpired");
+    }
+    console.log("✅ Claim deadline enforced");
+  });
+
+  it("Reclaims an expired reward only after expiry and only once", async () => {
+    const { poolPda, vaultPda } = await createPool("ReclaimTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      claimDeadlineSeconds: 3,
+    });
+    const player = await createBuyerWithSol(3);
+    const keptTicket = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const closedTicket = await buyAndSpinInPool(poolPda, vaultPda, player);
+
+    try {
+      await reclaimExpired(poolPda, keptTicket, player.publicKey, false);
+      assert.fail("Reclaim before expiry should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "RewardNotExpired");
+    }
+
+    await new Promise(resolve => setTimeout(resolve, 5000));
+
+    await reclaimExpired(poolPda, keptTicket, player.publicKey, false);
+    assert.isTrue((await program.account.userTicket.fetch(keptTicket)).rewardExpired);
+
+    try {
+      await reclaimExpired(poolPda, keptTicket, player.publicKey, false);
+      assert.fail("Second reclaim should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "RewardAlreadyReclaimed");
+    }
+
+    // Closing refunds the ticket rent to its owner
+    const ownerBalanceBefore = await connection.getBalance(player.publicKey);
+    await reclaimExpired(poolPda, closedTicket, player.publicKey, true);
+    assert.isNull(await connection.getAccountInfo(closedTicket));
+    assert.isAbove(await connection.getBalance(player.publicKey), ownerBalanceBefore);
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.outstandingLiabilities.toNumber(), 0, "Reclaims should release all liabilities");
+    console.log("✅ Expired rewards reclaimed");
+  });
+
+  it("Lets anyone crank an expired reward for a one-time bounty", async () => {
+    const { poolPda, vaultPda } = await createPool("CrankTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      claimDeadlineSeconds: 3,
+    });
+    const player = await createBuyerWithSol(3);
+    const cranker = await createBuyerWithSol(1);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+
+    const crank = () =>
+      program.methods
+        .crankExpireReward()
+        .accounts({ companyPool: poolPda, userTicket: ticketPda, cranker: cranker.publicKey })
+        .signers([cranker])
+        .rpc();
+
+    try {
+      await crank();
+      assert.fail("Crank before expiry should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "RewardNotExpired");
+    }
+
+    await new Promise(resolve => setTimeout(resolve, 5000));
+
+    const fundsBefore = (await program.account.companyPool.fetch(poolPda)).totalFunds.toNumber();
+    await crank();
+    assert.isTrue((await program.account.userTicket.fetch(ticketPda)).rewardExpired);
+    const fundsAfter = (await program.account.companyPool.fetch(poolPda)).totalFunds.toNumber();
+    assert.equal(fundsBefore - fundsAfter, 100_000, "Bounty comes out of the pool revenue");
+
+    try {
+      await crank();
+      assert.fail("Second crank should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "RewardAlreadyReclaimed");
+    }
+    console.log("✅ Expired reward cranked for a bounty");
+  });
+
+  // ================== REWARD NFT TESTS ==================
+
+  const TOKEN_METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+  // Reads the update authority, name and uri out of a Metaplex metadata account
+  const readNftMetadata = (data: Buffer) => {
+    const updateAuthority = new PublicKey(data.subarray(1, 33));
+    let offset = 65;
+    const readString = () => {
+      const len = data.readUInt32LE(offset);
+      const value = data.subarray(offset + 4, offset + 4 + len).toString("utf8").replace(/\0/g, "");
+      offset += 4 + len;
+      return value;
+    };
+    const name = readString();
+    readString(); // symbol
+    const uri = readString();
+    return { updateAuthority, name, uri };
+  };
+
+  it("Mints a 1/1 reward NFT once per winning ticket", async () => {
+    const { poolPda, vaultPda } = await createPool("RewardNftTest", [item1, item2]);
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const wonItem = (await program.account.userTicket.fetch(ticketPda)).wonItem;
+
+    const [rewardMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reward_mint"), ticketPda.toBuffer()],
+      program.programId
+    );
+    const [metadataPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), rewardMint.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+    const [masterEditionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), rewardMint.toBuffer(), Buffer.from("edition")],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+    const winnerTokenAccount = getAssociatedTokenAddressSync(rewardMint, player.publicKey);
+
+    const mintNft = () =>
+      program.methods
+        .mintRewardNft()
+        .accounts({
+          companyPool: poolPda,
+          userTicket: ticketPda,
+          winner: player.publicKey,
+          poolVault: vaultPda,
+          rewardMint,
+          winnerTokenAccount,
+          metadata: metadataPda,
+          masterEdition: masterEditionPda,
+        })
+        .preInstructions([web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 })])
+        .signers([player])
+        .rpc();
+
+    await mintNft();
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.equal(ticket.rewardNftMint.toString(), rewardMint.toString());
+    assert.equal(Number((await getAccount(connection, winnerTokenAccount)).amount), 1);
+
+    const metadata = readNftMetadata((await connection.getAccountInfo(metadataPda)).data);
+    // Display fields come from the pool's item, not the ticket
+    const poolItem = (await program.account.companyPool.fetch(poolPda)).items.find(
+      (item) => item.itemId === wonItem.itemId
+    );
+    assert.equal(metadata.name, poolItem.name);
+    assert.equal(metadata.uri, poolItem.image);
+    assert.equal(metadata.updateAuthority.toString(), provider.wallet.publicKey.toString());
+    assert.isNotNull(await connection.getAccountInfo(masterEditionPda), "Master edition should exist");
+
+    try {
+      await mintNft();
+      assert.fail("Second mint should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "RewardNftAlreadyMinted");
+    }
+    console.log("✅ Reward NFT minted once");
+  });
+
+  // ================== PHYSICAL FULFILLMENT TESTS ==================
+
+  it("Takes a physical prize from claim through fulfillment without touching the vault", async () => {
+    const physicalItem = { ...item2, name: "PS5", itemKind: { physical: {} } };
+    const { poolPda, vaultPda } = await createPool("PhysicalTest", [physicalItem]);
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+
+    const poolAfterSpin = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolAfterSpin.outstandingLiabilities.toNumber(), 0, "Physical wins owe nothing from the vault");
+
+    // SOL payouts of a physical prize are rejected
+    try {
+      await gambleTicket(poolPda, vaultPda, ticketPda, player);
+      assert.fail("Gambling a physical prize should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "PhysicalItemNotPayable");
+    }
+
+    const vaultBefore = await connection.getBalance(vaultPda);
+    await claimTicket(poolPda, ticketPda, player);
+    assert.equal(await connection.getBalance(vaultPda), vaultBefore, "Claim should not pay lamports");
+
+    let ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isTrue(ticket.fulfillmentPending);
+    assert.isFalse(ticket.rewardClaimed);
+
+    const markFulfilled = () =>
+      program.methods
+        .markFulfilled()
+        .accounts({
+          companyPool: poolPda,
+          userTicket: ticketPda,
+          authority: provider.wallet.publicKey,
+        })
+        .rpc();
+
+    try {
+      await markFulfilled();
+      assert.fail("Fulfillment without shipping info should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "ShippingInfoMissing");
+    }
+
+    const shippingInfoHash = Array.from(createHash("sha256").update("221B Baker Street").digest());
+    await program.methods
+      .attachShippingInfo(shippingInfoHash)
+      .accounts({ userTicket: ticketPda, winner: player.publicKey })
+      .signers([player])
+      .rpc();
+
+    await markFulfilled();
+
+    ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isFalse(ticket.fulfillmentPending);
+    assert.isTrue(ticket.rewardClaimed);
+    assert.deepEqual(Array.from(ticket.shippingInfoHash), shippingInfoHash);
+    console.log("✅ Physical prize fulfilled");
+  });
+
+  // ================== JACKPOT TESTS ==================
+
+  it("Grows the jackpot from ticket sales, keeps it in the prize vault and pays it to the winner", async () => {
+    const jackpotItem = { ...item1, name: "Jackpot", jackpot: true };
+    const { poolPda, vaultPda } = await createPool("JackpotTest", [jackpotItem], ticketPrice, {
+      ...defaultPoolConfig,
+      jackpotContributionBps: 1000,
+    });
+    const player = await createBuyerWithSol(15);
+
+    for (let i = 0; i < 10; i++) {
+      await buyTicketInPool(poolPda, vaultPda, player);
+    }
+
+    let poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(
+      poolData.jackpotAccumulated.toNumber(),
+      LAMPORTS_PER_SOL,
+      "10% of ten 1 SOL tickets should be earmarked"
+    );
+
+    // Emptying the revenue vault leaves the pot untouched in the prize vault
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const prizeVaultBefore = await connection.getBalance(prizeVaultPda);
+    await program.methods
+      .withdrawFundsFromVault(poolData.totalFunds)
+      .accounts({
+        companyPool: poolPda,
+        authority: provider.wallet.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    assert.equal(await connection.getBalance(prizeVaultPda), prizeVaultBefore);
+    assert.isAtLeast(prizeVaultBefore, LAMPORTS_PER_SOL, "The prize vault should hold the pot");
+
+    // The only item is the jackpot, so the next spin wins the whole pot
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const expectedPot = 1.1 * LAMPORTS_PER_SOL;
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.equal(ticket.wonItem.price.toNumber(), item1.price.toNumber() + expectedPot);
+
+    poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.jackpotAccumulated.toNumber(), 0, "Jackpot should reset after a win");
+
+    const playerBefore = await connection.getBalance(player.publicKey);
+    await claimTicket(poolPda, ticketPda, player);
+    const playerAfter = await connection.getBalance(player.publicKey);
+    assert.isAbove(playerAfter - playerBefore, expectedPot - 10_000, "Claim should pay the item plus the pot");
+    console.log("✅ Jackpot accumulated, ring-fenced and paid");
+  });
+
+  // ================== LOYALTY POINTS TESTS ==================
+
+  const redeemPointsInPool = async (poolPda: PublicKey, redeemer: web3.Keypair): Promise<PublicKey> => {
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const ticketIdBuffer = Buffer.alloc(8);
+    ticketIdBuffer.writeBigUInt64LE(BigInt(poolData.totalTicketsSold.toNumber()), 0);
+    const [ticketPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_ticket"), redeemer.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+      program.programId
+    );
+
+    await program.methods
+      .redeemPointsForTicket()
+      .accounts({
+        companyPool: poolPda,
+        userPoolState: deriveUserPoolStatePda(poolPda, redeemer.publicKey),
+        userTicket: ticketPda,
+        redeemer: redeemer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([redeemer])
+      .rpc();
+
+    return ticketPda;
+  };
+
+  it("Accrues loyalty points on purchases and redeems them for an unpaid ticket", async () => {
+    const { poolPda, vaultPda } = await createPool("LoyaltyTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      loyaltyEnabled: true,
+      loyaltyPointsPerTicket: 10,
+      pointsPerFreeTicket: 30,
+    });
+    const player = await createBuyerWithSol(5);
+    const statePda = deriveUserPoolStatePda(poolPda, player.publicKey);
+
+    await buyTicketInPool(poolPda, vaultPda, player);
+    await buyTicketInPool(poolPda, vaultPda, player);
+    assert.equal((await program.account.userPoolState.fetch(statePda)).loyaltyPoints.toNumber(), 20);
+
+    try {
+      await redeemPointsInPool(poolPda, player);
+      assert.fail("Redeeming without enough points should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "InsufficientLoyaltyPoints");
+    }
+
+    await buyTicketInPool(poolPda, vaultPda, player);
+    const fundsBefore = (await program.account.companyPool.fetch(poolPda)).totalFunds.toNumber();
+
+    const ticketPda = await redeemPointsInPool(poolPda, player);
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.equal(ticket.owner.toString(), player.publicKey.toString());
+    assert.isFalse(ticket.used);
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalFunds.toNumber(), fundsBefore, "Free tickets must not add funds");
+    assert.equal(poolData.totalTicketsSold.toNumber(), 4);
+    assert.equal((await program.account.userPoolState.fetch(statePda)).loyaltyPoints.toNumber(), 0);
+
+    // The redemption is reported as an unpaid ticket
+    const signatures = await connection.getSignaturesForAddress(ticketPda, { limit: 1 }, "confirmed");
+    const tx = await connection.getTransaction(signatures[0].signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const events = [...parser.parseLogs(tx.meta.logMessages)];
+    const purchase = events.find(event => event.name === "ticketPurchasedEvent");
+    assert.deepEqual(purchase.data.source, { loyaltyPoints: {} });
+    assert.equal(purchase.data.ticketPrice.toNumber(), 0);
+    console.log("✅ Loyalty points accrued and redeemed");
+  });
+
+  it("Rejects point redemption when the pool has loyalty disabled", async () => {
+    const { poolPda, vaultPda } = await createPool("NoLoyaltyTest", [item1, item2]);
+    const player = await createBuyerWithSol(3);
+
+    await buyTicketInPool(poolPda, vaultPda, player);
+    const state = await program.account.userPoolState.fetch(deriveUserPoolStatePda(poolPda, player.publicKey));
+    assert.equal(state.loyaltyPoints.toNumber(), 0, "Disabled pools accrue no points");
+
+    try {
+      await redeemPointsInPool(poolPda, player);
+      assert.fail("Redemption in a pool without loyalty should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "LoyaltyDisabled");
+    }
+  });
+
+  // ================== TICKET PRICE UPDATE TESTS ==================
+
+  it("Updates the ticket price while tickets keep the price they were bought at", async () => {
+    const { poolPda, vaultPda } = await createPool("PriceUpdateTest", [item1, item2]);
+    const player = await createBuyerWithSol(5);
+    const newPrice = new anchor.BN(2 * LAMPORTS_PER_SOL);
+
+    const oldTicketPda = await buyTicketInPool(poolPda, vaultPda, player);
+
+    const updatePrice = (price: anchor.BN) =>
+      program.methods
+        .updateTicketPrice(price)
+        .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+        .rpc();
+
+    try {
+      await updatePrice(new anchor.BN(0));
+      assert.fail("A zero ticket price should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "InvalidTicketPrice");
+    }
+
+    await updatePrice(newPrice);
+    const newTicketPda = await buyTicketInPool(poolPda, vaultPda, player);
+
+    const oldTicket = await program.account.userTicket.fetch(oldTicketPda);
+    const newTicket = await program.account.userTicket.fetch(newTicketPda);
+    assert.equal(oldTicket.pricePaid.toString(), ticketPrice.toString());
+    assert.equal(newTicket.pricePaid.toString(), newPrice.toString());
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.ticketPrice.toString(), newPrice.toString());
+    // Half of each sale goes to the prize vault under the default allocation
+    assert.equal(poolData.totalFunds.toString(), ticketPrice.add(newPrice).divn(2).toString());
+    const probabilitySum = poolData.items.reduce((sum, item) => sum + item.probability, 0);
+    assert.equal(probabilitySum, 10000);
+    console.log("✅ Ticket price updated with per-ticket snapshots");
+  });
+
+  // ================== WEIGHTING MODE TESTS ==================
+
+  it("Shapes the distribution with the pool's weighting exponent", async () => {
+    const items = [item1, item2, { ...item2, name: "Item3", price: new anchor.BN(200) }];
+    const gentle = await createPool("WeightExp1000", items, ticketPrice, {
+      ...defaultPoolConfig,
+      weighting: { ticketRatio: { exponentMilli: 1000 } },
+    });
+    const steep = await createPool("WeightExp2000", items, ticketPrice, {
+      ...defaultPoolConfig,
+      weighting: { ticketRatio: { exponentMilli: 2000 } },
+    });
+
+    const gentlePool = await program.account.companyPool.fetch(gentle.poolPda);
+    const steepPool = await program.account.companyPool.fetch(steep.poolPda);
+    const gentleOdds = gentlePool.items.map((item) => item.probability);
+    const steepOdds = steepPool.items.map((item) => item.probability);
+
+    assert.deepEqual(steepPool.weighting, { ticketRatio: { exponentMilli: 2000 } });
+    assert.equal(steepOdds.reduce((sum, p) => sum + p, 0), 10000);
+    assert.isAbove(steepOdds[0], gentleOdds[0], "A steeper exponent favours the cheapest item");
+    assert.isBelow(steepOdds[2], gentleOdds[2], "A steeper exponent starves the priciest item");
+
+    try {
+      await createPool("WeightExpTooHigh", items, ticketPrice, {
+        ...defaultPoolConfig,
+        weighting: { ticketRatio: { exponentMilli: 3001 } },
+      });
+      assert.fail("An exponent above 3.0 should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "InvalidPoolConfig");
+    }
+    console.log("✅ Weighting exponent applied per pool");
+  });
+
+  // ================== PROBABILITY BOUNDS TESTS ==================
+
+  it("Lifts an otherwise unwinnable flagship item to the probability floor", async () => {
+    const cheap = (name: string) => ({ ...item1, name, price: new anchor.BN(1) });
+    const flagship = { ...item2, name: "Flagship", price: new anchor.BN(1_000_000) };
+    const items = [cheap("CheapA"), cheap("CheapB"), cheap("CheapC"), flagship];
+
+    // Without a floor the flagship would round to 0 bp and never be drawn
+    try {
+      await createPool("UnboundedFlagship", items);
+      assert.fail("A pool with a zero-probability item should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "ZeroProbabilityItem");
+    }
+
+    const { poolPda } = await createPool("FlooredFlagship", items, ticketPrice, {
+      ...defaultPoolConfig,
+      minProbabilityBps: 50,
+    });
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const odds = poolData.items.map((item) => item.probability);
+    assert.equal(odds[3], 50, "Flagship should sit exactly on the floor");
+    assert.equal(odds.reduce((sum, p) => sum + p, 0), 10000);
+
+    try {
+      await createPool("InfeasibleBounds", items, ticketPrice, {
+        ...defaultPoolConfig,
+        maxProbabilityBps: 2000,
+      });
+      assert.fail("Caps that can't cover 10000 bp should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "ProbabilityClampInfeasible");
+    }
+    console.log("✅ Probability floor keeps the flagship winnable");
+  });
+
+  // ================== EXPECTED VALUE GUARDRAIL TESTS ==================
+
+  it("Caps the expected payout per spin unless the pool opts out", async () => {
+    const price = new anchor.BN(1000);
+    const evConfig = { ...defaultPoolConfig, maxEvBps: 9000 };
+    const onlyItem = (value: number) => [{ ...item1, name: "Only", price: new anchor.BN(value) }];
+
+    // A single item always wins, so its value is the expected payout
+    const { poolPda } = await createPool("EvAtLimit", onlyItem(900), price, evConfig);
+    assert.isTrue((await program.account.companyPool.fetch(poolPda)).active);
+
+    try {
+      await createPool("EvOverLimit", onlyItem(901), price, evConfig);
+      assert.fail("EV just over 90% of the ticket price should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "ExpectedValueTooHigh");
+    }
+
+    const { poolPda: promoPda } = await createPool("EvLossLeader", onlyItem(2000), price, {
+      ...evConfig,
+      allowNegativeEv: true,
+    });
+    const promoPool = await program.account.companyPool.fetch(promoPda);
+    assert.equal(promoPool.items[0].price.toNumber(), 2000);
+    console.log("✅ Expected value guardrail enforced with explicit override");
+  });
+
+  // ================== RARITY TIER TESTS ==================
+
+  it("Auto-assigns rarity tiers in probability order and carries them into wins", async () => {
+    const tierRank = (tier: any) => ["common", "rare", "epic", "legendary"].indexOf(Object.keys(tier)[0]);
+    const items = [10, 400, 50, 3000, 150, 1000, 20, 7000].map((price, i) => ({
+      ...item1,
+      name: `Tiered${i}`,
+      price: new anchor.BN(price),
+    }));
+    const { poolPda, vaultPda } = await createPool("TierTest", items);
+    const poolData = await program.account.companyPool.fetch(poolPda);
+
+    for (const a of poolData.items) {
+      for (const b of poolData.items) {
+        if (a.probability > b.probability) {
+          assert.isAtMost(tierRank(a.tier), tierRank(b.tier), `${a.name} should not be rarer than ${b.name}`);
+        }
+      }
+    }
+    assert.deepEqual(
+      poolData.items.map((item) => tierRank(item.tier)).sort(),
+      [0, 0, 1, 1, 2, 2, 3, 3],
+      "Eight items split evenly across the quartiles"
+    );
+
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const wonItem = (await program.account.userTicket.fetch(ticketPda)).wonItem;
+    assert.isDefined(poolData.items.find((item) => item.itemId === wonItem.itemId));
+
+    try {
+      await createPool("NoCommonTier", [
+        { ...item1, tier: { rare: {} } },
+        { ...item2, tier: { legendary: {} } },
+      ]);
+      assert.fail("A pool without a Common item should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "CommonTierRequired");
+    }
+    console.log("✅ Rarity tiers assigned and recorded");
+  });
+
+  // ================== CONSOLATION TESTS ==================
+
+  it("Pays the configured consolation for a losing spin exactly once", async () => {
+    const consolation = 5000;
+    const { poolPda, vaultPda } = await createPool("ConsolationTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      noWinBps: 9999,
+      consolationLamports: new anchor.BN(consolation),
+    });
+    const player = await createBuyerWithSol(10);
+
+    // 99.99% of spins lose; retry the rare win so the test stays deterministic
+    let ticketPda: PublicKey;
+    let ticket;
+    for (let attempt = 0; attempt < 5; attempt++) {
+      ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+      ticket = await program.account.userTicket.fetch(ticketPda);
+      if (ticket.wonItem === null) break;
+    }
+    assert.isNull(ticket.wonItem, "Spin should have lost");
+    assert.equal(ticket.consolationAmount.toNumber(), consolation);
+
+    const liabilitiesBefore = (await program.account.companyPool.fetch(poolPda)).outstandingLiabilities.toNumber();
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const vaultBefore = await connection.getBalance(prizeVaultPda);
+    await claimTicket(poolPda, ticketPda, player);
+    assert.equal(vaultBefore - (await connection.getBalance(prizeVaultPda)), consolation, "Claim should pay exactly the consolation");
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.outstandingLiabilities.toNumber(), liabilitiesBefore - consolation);
+
+    try {
+      await claimTicket(poolPda, ticketPda, player);
+      assert.fail("A consolation should only be claimable once");
+    } catch (error) {
+      assert.include(error.toString(), "RewardAlreadyClaimed");
+    }
+    console.log("✅ Consolation paid once for a losing spin");
+  });
+
+  // ================== SWEEP TESTS ==================
+
+  const sweepVault = async (poolPda: PublicKey, vaultPda: PublicKey) => {
+    const tx = await program.methods
+      .sweepVault()
+      .accounts({
+        companyPool: poolPda,
+        authority: provider.wallet.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+    const txDetails = await connection.getTransaction(tx, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    return [...parser.parseLogs(txDetails.meta.logMessages)].find((e) => e.name === "vaultSweptEvent").data;
+  };
+
+  it("Sweeps everything above rent when no rewards are outstanding", async () => {
+    const { poolPda, vaultPda } = await createPool("SweepEmptyTest", [item1]);
+    const player = await createBuyerWithSol(5);
+    await buyTicketInPool(poolPda, vaultPda, player);
+    await buyTicketInPool(poolPda, vaultPda, player);
+
+    const rent = await connection.getMinimumBalanceForRentExemption(0);
+    const vaultBefore = await connection.getBalance(vaultPda);
+    const event = await sweepVault(poolPda, vaultPda);
+
+    assert.equal(event.grossBalance.toNumber(), vaultBefore);
+    assert.equal(event.reserved.toNumber(), rent);
+    assert.equal(event.swept.toNumber(), vaultBefore - rent);
+    assert.equal(await connection.getBalance(vaultPda), rent, "Only the rent reserve should remain");
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalFunds.toNumber(), 0);
+
+    // Nothing is left to sweep
+    try {
+      await sweepVault(poolPda, vaultPda);
+      assert.fail("An empty vault should not sweep");
+    } catch (error) {
+      assert.include(error.toString(), "NoFundsAvailable");
+    }
+    console.log("✅ Swept the whole withdrawable balance");
+  });
+
+  it("Keeps unclaimed rewards out of reach of a sweep", async () => {
+    const prize = { ...item1, price: new anchor.BN(0.4 * LAMPORTS_PER_SOL) };
+    const { poolPda, vaultPda } = await createPool("SweepOwedTest", [prize]);
+    const player = await createBuyerWithSol(5);
+
+    // The only item is the prize, so the spin always wins it
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    await buyTicketInPool(poolPda, vaultPda, player);
+
+    const rent = await connection.getMinimumBalanceForRentExemption(0);
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const prizeVaultBefore = await connection.getBalance(prizeVaultPda);
+    const event = await sweepVault(poolPda, vaultPda);
+    assert.equal(event.reserved.toNumber(), rent);
+    assert.equal(await connection.getBalance(vaultPda), rent);
+    assert.equal(
+      await connection.getBalance(prizeVaultPda),
+      prizeVaultBefore,
+      "The sweep should not touch the prize vault"
+    );
+
+    // The winner can still be paid after the sweep
+    await claimTicket(poolPda, ticketPda, player);
+    assert.equal(
+      prizeVaultBefore - (await connection.getBalance(prizeVaultPda)),
+      prize.price.toNumber()
+    );
+    console.log("✅ Sweep left the unclaimed reward alone");
+  });
+
+  // ================== WITHDRAWAL TIMELOCK TESTS ==================
+
+  const requestWithdrawal = (poolPda: PublicKey, vaultPda: PublicKey, amount: number) =>
+    program.methods
+      .requestWithdrawal(new anchor.BN(amount))
+      .accounts({
+        companyPool: poolPda,
+        authority: provider.wallet.publicKey,
+        poolVault: vaultPda,
+      })
+      .rpc();
+
+  const executeWithdrawal = (poolPda: PublicKey, vaultPda: PublicKey) =>
+    program.methods
+      .executeWithdrawal()
+      .accounts({
+        companyPool: poolPda,
+        authority: provider.wallet.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+  const cancelWithdrawal = (poolPda: PublicKey) =>
+    program.methods
+      .cancelWithdrawal()
+      .accounts({
+        companyPool: poolPda,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+
+  it("Executes a timelocked withdrawal only after its deadline", async () => {
+    const { poolPda, vaultPda } = await createPool("TimelockTest", [item1], ticketPrice, {
+      ...defaultPoolConfig,
+      withdrawalTimelockSeconds: 3,
+    });
+    const player = await createBuyerWithSol(3);
+    await buyTicketInPool(poolPda, vaultPda, player);
+
+    // Direct withdrawals would bypass the delay
+    try {
+      await program.methods
+        .withdrawFundsFromVault(new anchor.BN(1000))
+        .accounts({
+          companyPool: poolPda,
+          authority: provider.wallet.publicKey,
+          poolVault: vaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Direct withdrawal should be rejected under a timelock");
+    } catch (error) {
+      assert.include(error.toString(), "WithdrawalTimelocked");
+    }
+
+    const amount = 0.3 * LAMPORTS_PER_SOL;
+    await requestWithdrawal(poolPda, vaultPda, amount);
+    const pending = (await program.account.companyPool.fetch(poolPda)).pendingWithdrawal;
+    assert.equal(pending.amount.toNumber(), amount);
+
+    try {
+      await executeWithdrawal(poolPda, vaultPda);
+      assert.fail("Execution before the deadline should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "WithdrawalNotReady");
+    }
+
+    await new Promise(resolve => setTimeout(resolve, 5000));
+
+    const vaultBefore = await connection.getBalance(vaultPda);
+    await executeWithdrawal(poolPda, vaultPda);
+    assert.equal(vaultBefore - (await connection.getBalance(vaultPda)), amount);
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.isNull(poolData.pendingWithdrawal);
+    assert.equal(poolData.totalFunds.toNumber(), ticketPrice.toNumber() / 2 - amount);
+    console.log("✅ Timelocked withdrawal waited out its deadline");
+  });
+
+  it("Reserves a pending withdrawal until it is cancelled", async () => {
+    const { poolPda, vaultPda } = await createPool("TimelockCancelTest", [item1], ticketPrice, {
+      ...defaultPoolConfig,
+      withdrawalTimelockSeconds: 3600,
+    });
+    const player = await createBuyerWithSol(3);
+    await buyTicketInPool(poolPda, vaultPda, player);
+
+    const amount = 0.3 * LAMPORTS_PER_SOL;
+    await requestWithdrawal(poolPda, vaultPda, amount);
+
+    try {
+      await requestWithdrawal(poolPda, vaultPda, 1000);
+      assert.fail("Only one withdrawal can be pending");
+    } catch (error) {
+      assert.include(error.toString(), "WithdrawalAlreadyPending");
+    }
+
+    await cancelWithdrawal(poolPda);
+    assert.isNull((await program.account.companyPool.fetch(poolPda)).pendingWithdrawal);
+
+    // With the reservation released the whole revenue share can be requested again
+    await requestWithdrawal(poolPda, vaultPda, ticketPrice.toNumber() / 2);
+
+    try {
+      await cancelWithdrawal(poolPda);
+      await cancelWithdrawal(poolPda);
+      assert.fail("Cancelling twice should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "NoPendingWithdrawal");
+    }
+    console.log("✅ Cancelled withdrawal freed its reservation");
+  });
+
+  it("Rejects withdrawals past the daily limit", async () => {
+    const cap = 0.6 * LAMPORTS_PER_SOL;
+    const { poolPda, vaultPda } = await createPool("DailyCapTest", [item1], ticketPrice, {
+      ...defaultPoolConfig,
+      maxDailyWithdrawal: new anchor.BN(cap),
+    });
+    const player = await createBuyerWithSol(3);
+    await buyTicketInPool(poolPda, vaultPda, player);
+    await buyTicketInPool(poolPda, vaultPda, player);
+
+    const withdraw = (amount: number) =>
+      program.methods
+        .withdrawFundsFromVault(new anchor.BN(amount))
+        .accounts({
+          companyPool: poolPda,
+          authority: provider.wallet.publicKey,
+          poolVault: vaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    await withdraw(0.4 * LAMPORTS_PER_SOL);
+    try {
+      await withdraw(0.3 * LAMPORTS_PER_SOL);
+      assert.fail("The second withdrawal would exceed the daily cap");
+    } catch (error) {
+      assert.include(error.toString(), "DailyWithdrawalLimitExceeded");
+    }
+
+    // The rest of the allowance is still available
+    await withdraw(0.2 * LAMPORTS_PER_SOL);
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.withdrawnToday.toNumber(), cap);
+    console.log("✅ Daily withdrawal cap enforced");
+  });
+
+  // ================== CO-AUTHORITY TESTS ==================
+
+  it("Requires the co-authority to co-sign withdrawals", async () => {
+    const coAuthority = await createBuyerWithSol(1);
+    const { poolPda, vaultPda } = await createPool("CoAuthorityTest", [item1], ticketPrice, {
+      ...defaultPoolConfig,
+      coAuthority: coAuthority.publicKey,
+    });
+    const player = await createBuyerWithSol(3);
+    await buyTicketInPool(poolPda, vaultPda, player);
+
+    const withdrawal = (coAuthorityKey: PublicKey | null) =>
+      program.methods.withdrawFundsFromVault(new anchor.BN(1000)).accounts({
+        companyPool: poolPda,
+        authority: provider.wallet.publicKey,
+        coAuthority: coAuthorityKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      });
+
+    try {
+      await withdrawal(null).rpc();
+      assert.fail("Withdrawal without the co-authority should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "CoAuthoritySignatureRequired");
+    }
+
+    // Signing as the authority doesn't stand in for the authority itself
+    try {
+      await program.methods
+        .withdrawFundsFromVault(new anchor.BN(1000))
+        .accounts({
+          companyPool: poolPda,
+          authority: coAuthority.publicKey,
+          coAuthority: coAuthority.publicKey,
+          poolVault: vaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([coAuthority])
+        .rpc();
+      assert.fail("The co-authority alone should not withdraw");
+    } catch (error) {
+      assert.include(error.toString(), "UnauthorizedWithdrawal");
+    }
+
+    const vaultBefore = await connection.getBalance(vaultPda);
+    await withdrawal(coAuthority.publicKey).signers([coAuthority]).rpc();
+    assert.equal(vaultBefore - (await connection.getBalance(vaultPda)), 1000);
+    console.log("✅ Withdrawal needed both signatures");
+  });
+
+  it("Rotates the co-authority only with both signatures", async () => {
+    const coAuthority = await createBuyerWithSol(1);
+    const replacement = web3.Keypair.generate();
+    const { poolPda } = await createPool("CoAuthorityRotationTest", [item1], ticketPrice, {
+      ...defaultPoolConfig,
+      coAuthority: coAuthority.publicKey,
+    });
+
+    try {
+      await program.methods
+        .setCoAuthority(replacement.publicKey)
+        .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+        .rpc();
+      assert.fail("Rotation without the current co-authority should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "CoAuthoritySignatureRequired");
+    }
+
+    await program.methods
+      .setCoAuthority(replacement.publicKey)
+      .accounts({
+        companyPool: poolPda,
+        authority: provider.wallet.publicKey,
+        coAuthority: coAuthority.publicKey,
+      })
+      .signers([coAuthority])
+      .rpc();
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.isTrue(poolData.coAuthority.equals(replacement.publicKey));
+    console.log("✅ Co-authority rotated with both signatures");
+  });
+
+  // ================== PRIZE VAULT TESTS ==================
+
+  it("Splits each sale between the revenue and prize vaults", async () => {
+    // An odd price so the prize share has to round
+    const price = new anchor.BN(LAMPORTS_PER_SOL + 1);
+    const { poolPda, vaultPda } = await createPool("PrizeSplitTest", [item1], price, {
+      ...defaultPoolConfig,
+      prizeAllocationBps: 3333,
+    });
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const player = await createBuyerWithSol(3);
+
+    const revenueBefore = await connection.getBalance(vaultPda);
+    const prizeBefore = await connection.getBalance(prizeVaultPda);
+    await buyTicketInPool(poolPda, vaultPda, player);
+
+    const prizeShare = price.muln(3333).divn(10000).toNumber();
+    const revenueShare = price.toNumber() - prizeShare;
+    assert.equal((await connection.getBalance(prizeVaultPda)) - prizeBefore, prizeShare);
+    assert.equal((await connection.getBalance(vaultPda)) - revenueBefore, revenueShare);
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalFunds.toNumber(), revenueShare, "Only revenue is withdrawable");
+
+    // Revenue withdrawals can't reach into the prize vault
+    try {
+      await program.methods
+        .withdrawFundsFromVault(new anchor.BN(revenueShare + 1))
+        .accounts({
+          companyPool: poolPda,
+          authority: provider.wallet.publicKey,
+          poolVault: vaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Withdrawing past the revenue share should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "InsufficientFunds");
+    }
+    console.log("✅ Ticket sale split across both vaults");
+  });
+
+  it("Lets anyone top up the prize vault", async () => {
+    const { poolPda } = await createPool("PrizeFundTest", [item1]);
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const sponsor = await createBuyerWithSol(2);
+    const amount = 0.25 * LAMPORTS_PER_SOL;
+
+    const before = await connection.getBalance(prizeVaultPda);
+    await program.methods
+      .fundPrizeVault(new anchor.BN(amount))
+      .accounts({ companyPool: poolPda, funder: sponsor.publicKey })
+      .signers([sponsor])
+      .rpc();
+    assert.equal((await connection.getBalance(prizeVaultPda)) - before, amount);
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalFunds.toNumber(), 0, "Top-ups are not revenue");
+
+    try {
+      await program.methods
+        .fundPrizeVault(new anchor.BN(0))
+        .accounts({ companyPool: poolPda, funder: sponsor.publicKey })
+        .signers([sponsor])
+        .rpc();
+      assert.fail("A zero top-up should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "InvalidAmount");
+    }
+    console.log("✅ Prize vault topped up");
+  });
+
+  it("Refuses to migrate a pool that already has a prize vault", async () => {
+    const { poolPda, vaultPda } = await createPool("PrizeMigrateTest", [item1]);
+    try {
+      await program.methods
+        .migratePrizeVault(5000)
+        .accounts({
+          companyPool: poolPda,
+          authority: provider.wallet.publicKey,
+          poolVault: vaultPda,
+        })
+        .rpc();
+      assert.fail("A split pool should not migrate again");
+    } catch (error) {
+      assert.include(error.toString(), "PrizeVaultAlreadyInitialized");
+    }
+    console.log("✅ Migration only runs once");
+  });
+
+  it("Rejects a jackpot contribution larger than the prize allocation", async () => {
+    try {
+      await createPool("PrizeAllocationTest", [item1], ticketPrice, {
+        ...defaultPoolConfig,
+        prizeAllocationBps: 500,
+        jackpotContributionBps: 1000,
+      });
+      assert.fail("The jackpot has to come out of the prize share");
+    } catch (error) {
+      assert.include(error.toString(), "InvalidPoolConfig");
+    }
+    console.log("✅ Prize allocation validated");
+  });
+
+  // ================== LEDGER TESTS ==================
+
+  it("Reconciles the ledger to a zero delta across a mixed sequence", async () => {
+    const { poolPda, vaultPda } = await createPool("LedgerTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      tradeInBps: 8000,
+    });
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const player = await createBuyerWithSol(6);
+    const sponsor = await createBuyerWithSol(1);
+
+    const reconcile = () =>
+      program.methods
+        .reconcile()
+        .accounts({ companyPool: poolPda, poolVault: vaultPda })
+        .view();
+
+    const claimed = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const tradedIn = await buyAndSpinInPool(poolPda, vaultPda, player);
+    await buyTicketInPool(poolPda, vaultPda, player);
+    await claimTicket(poolPda, claimed, player);
+    await tradeInTicket(poolPda, tradedIn, player);
+    await program.methods
+      .fundPrizeVault(new anchor.BN(0.1 * LAMPORTS_PER_SOL))
+      .accounts({ companyPool: poolPda, funder: sponsor.publicKey })
+      .signers([sponsor])
+      .rpc();
+    await program.methods
+      .withdrawFundsFromVault(new anchor.BN(0.5 * LAMPORTS_PER_SOL))
+      .accounts({
+        companyPool: poolPda,
+        authority: provider.wallet.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalTicketRevenue.toNumber(), 3 * ticketPrice.toNumber());
+    assert.equal(poolData.totalAuthorityDeposits.toNumber(), 0.1 * LAMPORTS_PER_SOL);
+    assert.equal(poolData.totalWithdrawn.toNumber(), 0.5 * LAMPORTS_PER_SOL);
+    assert.isAbove(poolData.totalRewardsPaid.toNumber(), 0);
+
+    let report = await reconcile();
+    const vaultBalances =
+      (await connection.getBalance(vaultPda)) + (await connection.getBalance(prizeVaultPda));
+    assert.equal(report.actualBalance.toNumber(), vaultBalances);
+    assert.equal(report.delta.toNumber(), 0, "Every lamport should be accounted for");
+
+    // A transfer that bypasses the program shows up as drift
+    const stray = 12_345;
+    await provider.sendAndConfirm(
+      new web3.Transaction().add(
+        SystemProgram.transfer({ fromPubkey: provider.wallet.publicKey, toPubkey: vaultPda, lamports: stray })
+      )
+    );
+    report = await reconcile();
+    assert.equal(report.delta.toNumber(), stray);
+    console.log("✅ Ledger reconciled against the vaults");
+  });
+
+  // ================== DEPOSIT TESTS ==================
+
+  const depositToVault = async (poolPda: PublicKey, vaultPda: PublicKey, depositor: web3.Keypair | null, amount: number) => {
+    const builder = program.methods
+      .depositToVault(new anchor.BN(amount))
+      .accounts({
+        companyPool: poolPda,
+        depositor: depositor ? depositor.publicKey : provider.wallet.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      });
+    const tx = await (depositor ? builder.signers([depositor]) : builder).rpc({ commitment: "confirmed" });
+    const txDetails = await connection.getTransaction(tx, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    return [...parser.parseLogs(txDetails.meta.logMessages)].find((e) => e.name === "depositEvent").data;
+  };
+
+  it("Tracks authority deposits and lets them be withdrawn again", async () => {
+    const { poolPda, vaultPda } = await createPool("DepositTest", [item1]);
+    const amount = 0.3 * LAMPORTS_PER_SOL;
+
+    const vaultBefore = await connection.getBalance(vaultPda);
+    const event = await depositToVault(poolPda, vaultPda, null, amount);
+    assert.equal((await connection.getBalance(vaultPda)) - vaultBefore, amount);
+    assert.isTrue(event.depositor.equals(provider.wallet.publicKey));
+    assert.equal(event.amount.toNumber(), amount);
+
+    let poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalAuthorityDeposits.toNumber(), amount);
+    assert.equal(poolData.totalFunds.toNumber(), amount);
+
+    await program.methods
+      .withdrawFundsFromVault(new anchor.BN(amount))
+      .accounts({
+        companyPool: poolPda,
+        authority: provider.wallet.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    assert.equal(await connection.getBalance(vaultPda), vaultBefore);
+
+    poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalFunds.toNumber(), 0);
+    const report = await program.methods
+      .reconcile()
+      .accounts({ companyPool: poolPda, poolVault: vaultPda })
+      .view();
+    assert.equal(report.delta.toNumber(), 0, "Deposits should keep the ledger balanced");
+
+    try {
+      await depositToVault(poolPda, vaultPda, null, 0);
+      assert.fail("A zero deposit should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "InvalidAmount");
+    }
+    console.log("✅ Deposit tracked and withdrawn");
+  });
+
+  it("Records a sponsor's deposit under their own key", async () => {
+    const { poolPda, vaultPda } = await createPool("SponsorDepositTest", [item1]);
+    const sponsor = await createBuyerWithSol(2);
+    const amount = 0.2 * LAMPORTS_PER_SOL;
+
+    const event = await depositToVault(poolPda, vaultPda, sponsor, amount);
+    assert.isTrue(event.depositor.equals(sponsor.publicKey));
+    assert.equal(event.totalFunds.toNumber(), amount);
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.totalAuthorityDeposits.toNumber(), amount);
+    console.log("✅ Sponsor deposit recorded");
+  });
+
+  // ================== AUTO-PAYOUT TESTS ==================
+
+  it("Pays the reward during the spin in auto-payout pools", async () => {
+    const { poolPda, vaultPda } = await createPool("AutoPayoutTest", [item1], ticketPrice, {
+      ...defaultPoolConfig,
+      autoPayout: true,
+    });
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+    const player = await createBuyerWithSol(3);
+
+    const ticketPda = await buyTicketInPool(poolPda, vaultPda, player);
+    const vaultBefore = await connection.getBalance(prizeVaultPda);
+    await waitForNextSlot();
+    await program.methods
+      .recordSpinResult()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: player.publicKey,
+        poolVault: vaultPda,
+        prizeVault: prizeVaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([player])
+      .rpc();
+    assert.equal(vaultBefore - (await connection.getBalance(prizeVaultPda)), item1.price.toNumber());
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isTrue(ticket.used);
+    assert.isTrue(ticket.rewardClaimed, "The reward should be settled by the spin");
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.outstandingLiabilities.toNumber(), 0, "Nothing should be left owed");
+    assert.equal(poolData.totalRewardsPaid.toNumber(), item1.price.toNumber());
+
+    try {
+      await claimTicket(poolPda, ticketPda, player);
+      assert.fail("Auto-payout pools have nothing to claim");
+    } catch (error) {
+      assert.include(error.toString(), "AutoPayoutPool");
+    }
+    console.log("✅ Reward paid on spin");
+  });
+
+  it("Still books a claim for manual pools", async () => {
+    const { poolPda, vaultPda } = await createPool("ManualPayoutTest", [item1]);
+    const player = await createBuyerWithSol(3);
+
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    let ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isFalse(ticket.rewardClaimed);
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.outstandingLiabilities.toNumber(), item1.price.toNumber());
+
+    await claimTicket(poolPda, ticketPda, player);
+    ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isTrue(ticket.rewardClaimed);
+    console.log("✅ Manual pool kept the claim step");
+  });
+
+  it("Fails the whole spin when the prize vault can't cover an auto-payout", async () => {
+    const prize = { ...item1, price: new anchor.BN(0.5 * LAMPORTS_PER_SOL) };
+    // Nothing from sales reaches the prize vault, so it only holds its rent
+    const { poolPda, vaultPda } = await createPool("AutoPayoutDryTest", [prize], ticketPrice, {
+      ...defaultPoolConfig,
+      autoPayout: true,
+      prizeAllocationBps: 0,
+    });
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyTicketInPool(poolPda, vaultPda, player);
+
+    try {
+      await waitForNextSlot();
+      await program.methods
+        .recordSpinResult()
+        .accounts({
+          companyPool: poolPda,
+          userTicket: ticketPda,
+          spinner: player.publicKey,
+          poolVault: vaultPda,
+          prizeVault: derivePrizeVaultPda(poolPda),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc();
+      assert.fail("The spin should fail when the payout can't be made");
+    } catch (error) {
+      assert.include(error.toString(), "InsufficientVaultFunds");
+    }
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isFalse(ticket.used, "The ticket should not be burned");
+    assert.isNull(ticket.wonItem);
+    console.log("✅ Underfunded auto-payout left the ticket unspun");
+  });
+
+  it("Rejects a purchase, spin and claim bundled into one transaction", async () => {
+    const { poolPda, vaultPda } = await createPool("BundledSpinTest", [item1]);
+    const player = await createBuyerWithSol(3);
+    const spinAccounts = (ticketPda: PublicKey) => ({
+      companyPool: poolPda,
+      userTicket: ticketPda,
+      spinner: player.publicKey,
+      poolVault: vaultPda,
+      prizeVault: derivePrizeVaultPda(poolPda),
+      systemProgram: SystemProgram.programId,
+    });
+
+    // Buy and spin in one transaction
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const ticketIdBuffer = Buffer.alloc(8);
+    ticketIdBuffer.writeBigUInt64LE(BigInt(poolData.totalTicketsSold.toNumber()), 0);
+    const [bundledTicket] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_ticket"), player.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+      program.programId
+    );
+    const buyIx = await program.methods
+      .buyTicket(null, new anchor.BN(0), false)
+      .accounts({
+        companyPool: poolPda,
+        userTicket: bundledTicket,
+        buyer: player.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+    try {
+      await program.methods
+        .recordSpinResult()
+        .accounts(spinAccounts(bundledTicket))
+        .preInstructions([buyIx])
+        .signers([player])
+        .rpc();
+      assert.fail("A spin bundled with its purchase should fail");
+    } catch (error) {
+      assert.include(error.toString(), "SpinTooSoon");
+    }
+    assert.isNull(await connection.getAccountInfo(bundledTicket));
+
+    // Spin and claim in one transaction
+    const ticketPda = await buyTicketInPool(poolPda, vaultPda, player);
+    await waitForNextSlot();
+    const spinIx = await program.methods
+      .recordSpinResult()
+      .accounts(spinAccounts(ticketPda))
+      .instruction();
+    try {
+      await program.methods
+        .claimReward()
+        .accounts({
+          companyPool: poolPda,
+          userTicket: ticketPda,
+          spinner: player.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([spinIx])
+        .signers([player])
+        .rpc();
+      assert.fail("A claim bundled with its spin should fail");
+    } catch (error) {
+      assert.include(error.toString(), "ClaimTooSoon");
+    }
+    assert.isFalse((await program.account.userTicket.fetch(ticketPda)).used);
+
+    // The same steps in separate transactions go through
+    await waitForNextSlot();
+    await program.methods
+      .recordSpinResult()
+      .accounts(spinAccounts(ticketPda))
+      .signers([player])
+      .rpc();
+    await claimTicket(poolPda, ticketPda, player);
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isTrue(ticket.rewardClaimed);
+    assert.isAbove(ticket.spunSlot.toNumber(), ticket.purchasedSlot.toNumber());
+    console.log("✅ Bundled spins and claims were rejected");
+  });
+
+  // ================== WINNER REGISTRY TESTS ==================
+
+  const getRecentWinners = (poolPda: PublicKey, skip = 0) =>
+    program.methods.getRecentWinners(skip).accounts({ companyPool: poolPda }).view();
+
+  it("Lists a pool's recent winners newest first", async () => {
+    const { poolPda, vaultPda } = await createPool("WinnerRegistryTest", [item1]);
+    const first = await createBuyerWithSol(2);
+    const second = await createBuyerWithSol(2);
+
+    await buyAndSpinInPool(poolPda, vaultPda, first);
+    await buyAndSpinInPool(poolPda, vaultPda, second);
+
+    const winners = await getRecentWinners(poolPda);
+    assert.equal(winners.length, 2);
+    assert.isTrue(winners[0].winner.equals(second.publicKey), "Newest win should come first");
+    assert.isTrue(winners[1].winner.equals(first.publicKey));
+    assert.deepEqual(winners.map((w) => w.ticketId.toNumber()), [1, 0]);
+    winners.forEach((w) => {
+      assert.equal(w.itemIndex, 0);
+      assert.equal(w.value.toNumber(), item1.price.toNumber());
+    });
+
+    assert.equal((await getRecentWinners(poolPda, 1)).length, 1);
+    assert.equal((await getRecentWinners(poolPda, 2)).length, 0);
+    console.log("✅ Recent winners listed in order");
+  });
+
+  it("Leaves losing spins off the winner list", async () => {
+    const { poolPda, vaultPda } = await createPool("WinnerRegistryLossTest", [item1], ticketPrice, {
+      ...defaultPoolConfig,
+      noWinBps: 9999,
+    });
+    const player = await createBuyerWithSol(4);
+
+    let wins = 0;
+    for (let i = 0; i < 2; i++) {
+      const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+      if ((await program.account.userTicket.fetch(ticketPda)).wonItem !== null) wins++;
+    }
+
+    assert.equal((await getRecentWinners(poolPda)).length, wins, "Only item wins should be listed");
+    console.log("✅ Losses excluded from the winner list");
+  });
+
+  // ================== REROLL TESTS ==================
+
+  const rerollPrice = new anchor.BN(0.05 * LAMPORTS_PER_SOL);
+
+  const rerollSpin = (poolPda: PublicKey, vaultPda: PublicKey, ticketPda: PublicKey, spinner: web3.Keypair) =>
+    program.methods
+      .rerollSpin()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: spinner.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([spinner])
+      .rpc();
+
+  it("Rerolls a spin result once for the pool's fee", async () => {
+    const { poolPda, vaultPda } = await createPool("RerollTest", [item1, item2], ticketPrice, {
+      ...defaultPoolConfig,
+      rerollPrice,
+    });
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const poolBefore = await program.account.companyPool.fetch(poolPda);
+    const vaultBefore = await connection.getBalance(vaultPda);
+
+    await rerollSpin(poolPda, vaultPda, ticketPda, player);
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isTrue(ticket.rerolled);
+    assert.isNotNull(ticket.wonItem, "Every spin wins an item in this pool");
+    assert.equal((await connection.getBalance(vaultPda)) - vaultBefore, rerollPrice.toNumber());
+
+    // The old liability is replaced by the new item's value
+    const poolAfter = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolAfter.outstandingLiabilities.toNumber(), ticket.wonItem.price.toNumber());
+    assert.equal(
+      poolAfter.totalTicketRevenue.sub(poolBefore.totalTicketRevenue).toNumber(),
+      rerollPrice.toNumber()
+    );
+
+    try {
+      await rerollSpin(poolPda, vaultPda, ticketPda, player);
+      assert.fail("A ticket can only be rerolled once");
+    } catch (error) {
+      assert.include(error.toString(), "AlreadyRerolled");
+    }
+
+    await claimTicket(poolPda, ticketPda, player);
+    console.log("✅ Reroll drew a new result and was limited to one");
+  });
+
+  it("Rejects rerolls when the pool has no reroll price", async () => {
+    const { poolPda, vaultPda } = await createPool("RerollDisabledTest", [item1]);
+    const player = await createBuyerWithSol(3);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+
+    try {
+      await rerollSpin(poolPda, vaultPda, ticketPda, player);
+      assert.fail("Rerolls should be disabled");
+    } catch (error) {
+      assert.include(error.toString(), "RerollDisabled");
+    }
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isFalse(ticket.rerolled);
+    console.log("✅ Disabled pool rejected the reroll");
+  });
+
+  // ================== BUNDLE TESTS ==================
+
+  const bundlePrice = new anchor.BN(2.5 * LAMPORTS_PER_SOL);
+  const bundleItems = [
+    { ...item1, tier: { common: {} } },
+    { ...item2, tier: { rare: {} } },
+  ];
+  const bundleConfig = {
+    ...defaultPoolConfig,
+    bundles: [{ size: 3, price: bundlePrice, guaranteedTier: { rare: {} } }],
+  };
+
+  const buyBundleInPool = async (
+    poolPda: PublicKey,
+    vaultPda: PublicKey,
+    buyer: web3.Keypair,
+    size: number
+  ): Promise<PublicKey[]> => {
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    const firstId = poolData.totalTicketsSold.toNumber();
+    const ticketPdas = Array.from({ length: size }, (_, i) => {
+      const ticketIdBuffer = Buffer.alloc(8);
+      ticketIdBuffer.writeBigUInt64LE(BigInt(firstId + i), 0);
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("user_ticket"), buyer.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+        program.programId
+      )[0];
+    });
+
+    await program.methods
+      .buyBundle(size)
+      .accounts({
+        companyPool: poolPda,
+        buyer: buyer.publicKey,
+        poolVault: vaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(ticketPdas.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false })))
+      .signers([buyer])
+      .rpc();
+
+    return ticketPdas;
+  };
+
+  it("Sells a bundle with sequential tickets and one guaranteed ticket", async () => {
+    const { poolPda, vaultPda } = await createPool("BundleTest", bundleItems, ticketPrice, bundleConfig);
+    const player = await createBuyerWithSol(5);
+    await buyTicketInPool(poolPda, vaultPda, player);
+
+    const poolBefore = await program.account.companyPool.fetch(poolPda);
+    const ticketPdas = await buyBundleInPool(poolPda, vaultPda, player, 3);
+    const poolAfter = await program.account.companyPool.fetch(poolPda);
+
+    assert.equal(poolAfter.totalTicketsSold.toNumber(), 4);
+    assert.equal(
+      poolAfter.totalTicketRevenue.sub(poolBefore.totalTicketRevenue).toNumber(),
+      bundlePrice.toNumber(),
+      "The bundle should be charged its own price, not 3 tickets"
+    );
+
+    const tickets = await Promise.all(ticketPdas.map((pda) => program.account.userTicket.fetch(pda)));
+    assert.deepEqual(tickets.map((t) => t.ticketId.toNumber()), [1, 2, 3]);
+    assert.equal(
+      tickets.reduce((sum, t) => sum + t.pricePaid.toNumber(), 0),
+      bundlePrice.toNumber()
+    );
+    assert.isNull(tickets[0].forcedMinTier);
+    assert.isNull(tickets[1].forcedMinTier);
+    assert.deepEqual(tickets[2].forcedMinTier, { rare: {} });
+
+    // The guaranteed ticket can only land on the Rare item
+    await waitForNextSlot();
+    await program.methods
+      .recordSpinResult()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPdas[2],
+        spinner: player.publicKey,
+        poolVault: vaultPda,
+        prizeVault: derivePrizeVaultPda(poolPda),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([player])
+      .rpc();
+    const spun = await program.account.userTicket.fetch(ticketPdas[2]);
+    assert.equal(spun.wonItem.itemId, 1, "Only the Rare item qualifies");
+    console.log("✅ Bundle sold with a guaranteed Rare ticket");
+  });
+
+  it("Rejects bundle sizes the pool doesn't sell", async () => {
+    const { poolPda, vaultPda } = await createPool("BundleSizeTest", bundleItems, ticketPrice, bundleConfig);
+    const player = await createBuyerWithSol(5);
+
+    try {
+      await buyBundleInPool(poolPda, vaultPda, player, 2);
+      assert.fail("Only the configured bundle size is for sale");
+    } catch (error) {
+      assert.include(error.toString(), "BundleNotFound");
+    }
+    console.log("✅ Unknown bundle size rejected");
+  });
+
+  // ================== SPIN DELEGATE TESTS ==================
+
+  const chainNow = async (): Promise<number> =>
+    (await connection.getBlockTime(await connection.getSlot())) ?? Math.floor(Date.now() / 1000);
+
+  const delegateSpinner = (poolPda: PublicKey, owner: web3.Keypair, delegate: PublicKey, expiresAt: number) =>
+    program.methods
+      .delegateSpinner(delegate, new anchor.BN(expiresAt))
+      .accounts({ companyPool: poolPda, owner: owner.publicKey })
+      .signers([owner])
+      .rpc();
+
+  const spinAsDelegate = (
+    poolPda: PublicKey,
+    vaultPda: PublicKey,
+    ticketPda: PublicKey,
+    owner: PublicKey,
+    delegate: web3.Keypair
+  ) =>
+    await waitForNextSlot();
+    program.methods
+      .recordSpinResult()
+      .accounts({
+        companyPool: poolPda,
+        userTicket: ticketPda,
+        spinner: delegate.publicKey,
+        userPoolState: deriveUserPoolStatePda(poolPda, owner),
+        ticketOwner: owner,
+        poolVault: vaultPda,
+        prizeVault: derivePrizeVaultPda(poolPda),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([delegate])
+      .rpc();
+
+  it("Lets a session key spin but not claim", async () => {
+    const { poolPda, vaultPda } = await createPool("DelegateSpinTest", [item1]);
+    const owner = await createBuyerWithSol(3);
+    const sessionKey = await createBuyerWithSol(1);
+    const ticketPda = await buyTicketInPool(poolPda, vaultPda, owner);
+
+    await delegateSpinner(poolPda, owner, sessionKey.publicKey, (await chainNow()) + 3600);
+    await spinAsDelegate(poolPda, vaultPda, ticketPda, owner.publicKey, sessionKey);
+
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isTrue(ticket.used);
+    assert.isTrue(ticket.owner.equals(owner.publicKey), "The ticket stays with its owner");
+
+    try {
+      await claimTicket(poolPda, ticketPda, sessionKey);
+      assert.fail("Only the owner can claim");
+    } catch (error) {
+      assert.include(error.toString(), "NotTicketOwner");
+    }
+    await claimTicket(poolPda, ticketPda, owner);
+    console.log("✅ Delegate spun, owner claimed");
+  });
+
+  it("Rejects spins from expired or revoked delegates", async () => {
+    const { poolPda, vaultPda } = await createPool("DelegateExpiryTest", [item1]);
+    const owner = await createBuyerWithSol(3);
+    const sessionKey = await createBuyerWithSol(1);
+    const ticketPda = await buyTicketInPool(poolPda, vaultPda, owner);
+
+    await delegateSpinner(poolPda, owner, sessionKey.publicKey, (await chainNow()) + 2);
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    try {
+      await spinAsDelegate(poolPda, vaultPda, ticketPda, owner.publicKey, sessionKey);
+      assert.fail("An expired delegate should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "NotTicketOwner");
+    }
+
+    await delegateSpinner(poolPda, owner, sessionKey.publicKey, (await chainNow()) + 3600);
+    await program.methods
+      .revokeDelegate()
+      .accounts({ companyPool: poolPda, owner: owner.publicKey })
+      .signers([owner])
+      .rpc();
+    try {
+      await spinAsDelegate(poolPda, vaultPda, ticketPda, owner.publicKey, sessionKey);
+      assert.fail("A revoked delegate should be rejected");
+    } catch (error) {
+      assert.include(error.toString(), "NotTicketOwner");
+    }
+
+    const state = await program.account.userPoolState.fetch(deriveUserPoolStatePda(poolPda, owner.publicKey));
+    assert.isNull(state.spinDelegate);
+    assert.isFalse((await program.account.userTicket.fetch(ticketPda)).used);
+    console.log("✅ Expired and revoked delegates rejected");
+  });
+
+  // ================== ITEM ACCOUNT TESTS ==================
+
+  const itemAccountConfig = { ...defaultPoolConfig, itemAccounts: true };
+
+  const poolItemPda = (poolPda: PublicKey, itemId: number): PublicKey => {
+    const itemIdBuffer = Buffer.alloc(4);
+    itemIdBuffer.writeUInt32LE(itemId, 0);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("item"), poolPda.toBuffer(), itemIdBuffer],
+      program.programId
+    )[0];
+  };
+
+  const catalogItem = (i: number) => ({
+    ...item1,
+    image: `https://test.com/catalog${i}.png`,
+    name: `Catalog${i}`,
+    description: `Catalog item ${i}`,
+    price: new anchor.BN(10 + i),
+  });
+
+  const addItemToPool = async (poolPda: PublicKey, item: any, itemId: number) => {
+    await program.methods
+      .addPoolItem(item)
+      .accounts({
+        companyPool: poolPda,
+        poolItem: poolItemPda(poolPda, itemId),
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+  };
+
+  it("Grows an item-account pool past the inline item limit", async () => {
+    const { poolPda, vaultPda } = await createPool("ItemAccountTest", [], ticketPrice, itemAccountConfig);
+    const itemCount = 12;
+    for (let i = 0; i < itemCount; i++) {
+      await addItemToPool(poolPda, catalogItem(i), i);
+    }
+
+    const poolData = await program.account.companyPool.fetch(poolPda);
+    assert.equal(poolData.poolVersion, 2);
+    assert.equal(poolData.items.length, itemCount);
+    assert.equal(poolData.items.reduce((sum, item) => sum + item.probability, 0), 10000);
+    assert.equal(poolData.items[11].itemId, 11);
+    assert.equal(poolData.items[11].name, "Catalog11");
+    assert.equal(poolData.items[11].image, "", "The text should live on the item account");
+
+    const itemAccount = await program.account.poolItemAccount.fetch(poolItemPda(poolPda, 11));
+    assert.equal(itemAccount.image, "https://test.com/catalog11.png");
+    assert.equal(itemAccount.description, "Catalog item 11");
+
+    // Spins draw from the pool's table without loading any item account
+    const player = await createBuyerWithSol(5);
+    const ticketPda = await buyAndSpinInPool(poolPda, vaultPda, player);
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    assert.isBelow(ticket.wonItem.itemId, itemCount);
+  });
+
+  it("Updates an item-account item's text on its account", async () => {
+    const { poolPda } = await createPool("ItemAccountEdit", [], ticketPrice, itemAccountConfig);
+    await addItemToPool(poolPda, catalogItem(0), 0);
+
+    try {
+      await program.methods
+        .updatePoolItem(0, "https://test.com/new.png", null, null)
+        .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+        .rpc();
+      assert.fail("Should require the item account");
+    } catch (error) {
+      assert.include(error.toString(), "ItemAccountMismatch");
+    }
+
+    await program.methods
+      .updatePoolItem(0, "https://test.com/new.png", null, null)
+      .accounts({
+        companyPool: poolPda,
+        poolItem: poolItemPda(poolPda, 0),
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const itemAccount = await program.account.poolItemAccount.fetch(poolItemPda(poolPda, 0));
+    assert.equal(itemAccount.image, "https://test.com/new.png");
+  });
+
+  it("Rejects add_pool_item on inline pools", async () => {
+    const { poolPda } = await createPool("InlineNoAdd", [item1, item2]);
+
+    try {
+      await addItemToPool(poolPda, catalogItem(0), 2);
+      assert.fail("Inline pools should not take item accounts");
+    } catch (error) {
+      assert.include(error.toString(), "ItemAccountsDisabled");
+    }
+  });
+
+  // ================== ZERO-COPY POOL TESTS ==================
+
+  it("Runs a zero-copy pool from purchase to withdrawal", async () => {
+    const name = "ZeroCopyPool";
+    const [poolPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("company_pool_v2"), poolNameSeed(name)],
+      program.programId
+    );
+    const [vaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_vault"), poolPda.toBuffer()],
+      program.programId
+    );
+    const prizeVaultPda = derivePrizeVaultPda(poolPda);
+
+    await program.methods
+      .initializeCompanyPoolV2(ticketPrice, name, companyImage, [item1, item2], {
+        prizeAllocationBps: 5000,
+        noWinBps: 0,
+        maxEvBps: 9000,
+        allowNegativeEv: false,
+        weighting: { ticketRatio: { exponentMilli: 1500 } },
+        allowCpiPurchases: false,
+      })
+      .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey })
+      .rpc();
+
+    const player = await createBuyerWithSol(3);
+    const ticketIdBuffer = Buffer.alloc(8);
+    const [ticketPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_ticket"), player.publicKey.toBuffer(), poolPda.toBuffer(), ticketIdBuffer],
+      program.programId
+    );
+    await program.methods
+      .buyTicketV2()
+      .accounts({ companyPool: poolPda, userTicket: ticketPda, buyer: player.publicKey })
+      .signers([player])
+      .rpc();
+    await waitForNextSlot();
+    await program.methods
+      .recordSpinResultV2()
+      .accounts({ companyPool: poolPda, userTicket: ticketPda, spinner: player.publicKey })
+      .signers([player])
+      .rpc();
+
+    const pool = await program.account.companyPoolV2.fetch(poolPda);
+    assert.equal(pool.itemCount, 2);
+    assert.equal(pool.totalTicketsSold.toNumber(), 1);
+    const ticket = await program.account.userTicket.fetch(ticketPda);
+    const won = pool.items[ticket.wonItem.itemId];
+    assert.equal(won.timesWon, 1);
+    assert.equal(ticket.wonItem.price.toNumber(), won.price.toNumber());
+    assert.equal(pool.outstandingLiabilities.toNumber(), won.price.toNumber());
+    const wonName = Buffer.from(won.name).toString("utf8").replace(/\0+$/, "");
+    assert.include([item1.name, item2.name], wonName);
+
+    await waitForNextSlot();
+    await program.methods
+      .claimRewardV2()
+      .accounts({ companyPool: poolPda, userTicket: ticketPda, spinner: player.publicKey, prizeVault: prizeVaultPda })
+      .signers([player])
+      .rpc();
+    assert.isTrue((await program.account.userTicket.fetch(ticketPda)).rewardClaimed);
+
+    await program.methods
+      .withdrawFundsFromVaultV2(pool.totalFunds)
+      .accounts({ companyPool: poolPda, authority: provider.wallet.publicKey, poolVault: vaultPda })
+      .rpc();
+    const after = await program.account.companyPoolV2.fetch(poolPda);
+    assert.equal(after.totalFunds.toNumber(), 0);
+    assert.equal(after.outstandingLiabilities.toNumber(), 0);
+  });
+
   // ================== FINAL COMPREHENSIVE TEST ==================
 
   it("Final comprehensive claim reward verification", async () => {